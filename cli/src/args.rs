@@ -21,32 +21,141 @@
 
 #![allow(clippy::needless_update)] // Required by From derive macro
 
-use std::fs;
-use std::io::ErrorKind;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::{env, fs};
+use std::io::{ErrorKind, Write};
 use std::ops::{Deref, DerefMut};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
 
-use bpstd::{Wpkh, XpubDerivable};
+use bpstd::{Network, Wpkh, XpubDerivable};
 use bpwallet::cli::{Args as BpArgs, Config, DescriptorOpts};
 use bpwallet::Wallet;
+use nonasync::persistence::CloneNoPersistence;
 use rgb::persistence::Stock;
-use rgb::resolvers::AnyResolver;
-use rgb::{RgbDescr, RgbWallet, TapretKey, WalletError};
-use rgbstd::persistence::fs::FsBinStore;
-use strict_types::encoding::{DecodeError, DeserializeError};
+use rgb::resolvers::{
+    indexer_url, AnyResolver, ConnectionOpts, ResolverConfig, DEFAULT_RESOLVER_BACKOFF,
+    DEFAULT_RESOLVER_RETRIES, DEFAULT_RESOLVER_TIMEOUT,
+};
+use rgb::{
+    is_encrypted, update_witnesses_with_progress, BackupStore, DeliveryError, EncryptionError,
+    HttpsFetcher, HttpsPayjoin, HttpsPoster, KeychainLayout, KitRegistry, KitRegistryError,
+    NetworkGuard, PayjoinError, RgbDescr, RgbWallet, StockLock, SyncError, SyncProgress, TapretKey,
+    WalletError, WalletFactory, DEFAULT_STOCK_BACKUPS,
+};
+use serde::Deserialize;
+use strict_types::encoding::Ident;
 
 use crate::Command;
 
+/// Lock on the stock directory, which is held until the process terminates.
+static STOCK_LOCK: OnceLock<StockLock> = OnceLock::new();
+
+/// Names of the stock files inside the network data directory.
+pub(crate) const STOCK_FILES: [&str; 3] = ["stash.dat", "state.dat", "index.dat"];
+
+thread_local! {
+    /// Whether the stock is kept loaded between the commands, which is the
+    /// case for the interactive shell.
+    static KEEP_STOCK: Cell<bool> = const { Cell::new(false) };
+
+    /// Stock kept loaded between the commands.
+    static LOADED_STOCK: RefCell<Option<LoadedStock>> = const { RefCell::new(None) };
+}
+
+/// Copy of the stock without persistence, together with the modification time
+/// of the stock files it corresponds to.
+struct LoadedStock {
+    stock: Stock,
+    modified: Option<SystemTime>,
+}
+
+/// Makes the commands executed later keep the stock loaded between them,
+/// reloading it only if the stock files were modified.
+pub(crate) fn keep_stock_loaded() { KEEP_STOCK.with(|keep| keep.set(true)) }
+
+/// Returns the latest modification time of the stock files in the directory.
+fn stock_modified(dir: &Path) -> Option<SystemTime> {
+    STOCK_FILES
+        .iter()
+        .filter_map(|name| fs::metadata(dir.join(name)).and_then(|meta| meta.modified()).ok())
+        .max()
+}
+
+/// Name of the file in the data directory caching resolved transactions.
+const RESOLVER_CACHE_FILE: &str = "resolver.cache";
+
+/// Names of the per-network profile sections of the configuration file.
+const PROFILES: [&str; 6] = ["mainnet", "bitcoin", "testnet3", "testnet4", "signet", "regtest"];
+
+/// Environment variable providing the wallet passphrase instead of prompting
+/// for it.
+const PASSPHRASE_ENV: &str = "RGB_WALLET_PASSPHRASE";
+
+/// Width of the progress bar rendered with `--progress`, in characters.
+const PROGRESS_BAR_WIDTH: usize = 30;
+
+/// Renders witness refresh progress as a single-line progress bar on stderr.
+fn render_progress(progress: &SyncProgress) {
+    let total = progress.witnesses_total.max(progress.witnesses_refreshed);
+    let filled = (progress.witnesses_refreshed * PROGRESS_BAR_WIDTH)
+        .checked_div(total)
+        .unwrap_or(PROGRESS_BAR_WIDTH);
+    eprint!(
+        "\r[{}{}] {}/{} witnesses, ~{} remaining",
+        "#".repeat(filled),
+        " ".repeat(PROGRESS_BAR_WIDTH - filled),
+        progress.witnesses_refreshed,
+        total,
+        progress.witnesses_remaining()
+    );
+    let _ = std::io::stderr().flush();
+}
+
+/// Reads passphrase of the encrypted wallet from the environment or prompts
+/// for it.
+#[allow(clippy::result_large_err)]
+pub fn read_passphrase(wallet: &str) -> Result<String, WalletError> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    Ok(rpassword::prompt_password(format!("Passphrase for wallet {wallet}: "))?)
+}
+
+/// Reads passphrase for the wallet encryption from the environment or prompts
+/// for it twice, such that a typo doesn't lock the wallet.
+#[allow(clippy::result_large_err)]
+pub fn read_new_passphrase() -> Result<String, WalletError> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    let passphrase = rpassword::prompt_password("New passphrase: ")?;
+    if rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+        return Err(WalletError::Format("passphrase", s!("passphrases don't match")));
+    }
+    Ok(passphrase)
+}
+
 #[derive(Args, Clone, PartialEq, Eq, Debug)]
-#[group()]
+#[group(multiple = true)]
 pub struct DescrRgbOpts {
     /// Use tapret(KEY) descriptor as wallet.
-    #[arg(long, global = true)]
+    #[arg(long, global = true, conflicts_with = "wpkh")]
     pub tapret_key_only: Option<XpubDerivable>,
 
     /// Use wpkh(KEY) descriptor as wallet.
     #[arg(long, global = true)]
     pub wpkh: Option<XpubDerivable>,
+
+    /// Keychain layout of the wallet descriptor, listing external, internal,
+    /// RGB and tapret keychains, like `<0;1;9;10>`.
+    ///
+    /// Used by wallets migrating from RGB implementations with other keychain
+    /// conventions.
+    #[arg(long, global = true)]
+    pub keychains: Option<KeychainLayout>,
 }
 
 impl DescriptorOpts for DescrRgbOpts {
@@ -60,9 +169,136 @@ impl DescriptorOpts for DescrRgbOpts {
             .map(TapretKey::from)
             .map(TapretKey::into)
             .or(self.wpkh.clone().map(Wpkh::from).map(Wpkh::into))
+            .map(|descr: RgbDescr| descr.with_keychain_layout(self.keychains.unwrap_or_default()))
+    }
+}
+
+/// RGB-specific settings read from the configuration file, which complement
+/// the settings of the bitcoin wallet kept in the same file.
+///
+/// The settings are read from the configuration file in the data directory,
+/// where they may be given either for all networks or in per-network profile
+/// sections, like `[regtest]`, and then from the configuration file in the
+/// network data directory, which takes precedence.
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(crate = "serde_crate", rename_all = "camelCase", default)]
+pub struct StockConfig {
+    /// Esplora server used if no indexer is given in the command line.
+    pub esplora: Option<String>,
+
+    /// Electrum server used if no indexer is given in the command line.
+    pub electrum: Option<String>,
+
+    /// Mempool server used if no indexer is given in the command line.
+    pub mempool: Option<String>,
+
+    /// Number of previous copies of the stock files kept on each save.
+    pub stock_backups: u8,
+
+    /// HTTP headers added to each request to esplora and mempool indexers,
+    /// like API keys of hosted indexer instances.
+    pub indexer_headers: BTreeMap<String, String>,
+
+    /// Token sent to esplora and mempool indexers as a bearer authorization.
+    pub indexer_token: Option<String>,
+
+    /// Timeout of a single request to the indexer, in seconds.
+    pub indexer_timeout: u64,
+
+    /// Number of retries of a failed request to the indexer.
+    pub indexer_retries: u8,
+
+    /// Delay before the first retry of a failed request to the indexer, in
+    /// milliseconds; it is doubled with each next retry.
+    pub indexer_backoff: u64,
+
+    /// Registries of issuer kits used by `registry update` and for fetching
+    /// kits of unknown schemata on `accept`.
+    pub kit_registries: Vec<KitRegistryConfig>,
+}
+
+/// Kit registry entry of the configuration file.
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(crate = "serde_crate", rename_all = "camelCase")]
+pub struct KitRegistryConfig {
+    /// HTTPS base URL of the registry.
+    pub url: String,
+
+    /// Hex-encoded SHA256 hash pinning the registry index.
+    pub sha256: Option<String>,
+}
+
+impl Default for StockConfig {
+    fn default() -> Self {
+        StockConfig {
+            esplora: None,
+            electrum: None,
+            mempool: None,
+            stock_backups: DEFAULT_STOCK_BACKUPS,
+            indexer_headers: empty!(),
+            indexer_token: None,
+            indexer_timeout: DEFAULT_RESOLVER_TIMEOUT,
+            indexer_retries: DEFAULT_RESOLVER_RETRIES,
+            indexer_backoff: DEFAULT_RESOLVER_BACKOFF,
+            kit_registries: empty!(),
+        }
+    }
+}
+
+impl StockConfig {
+    /// Loads the settings applying to the `network` from the configuration
+    /// file in the `data_dir` and then from the configuration file at
+    /// `conf_path`, if it is a different file.
+    pub fn load(data_dir: &Path, network: Network, conf_path: &Path) -> Self {
+        let name = network.to_string();
+        let profiles = match network {
+            // `bitcoin` is the name of the mainnet data directory, thus it is
+            // accepted as the profile name as well
+            Network::Mainnet => vec!["bitcoin", "mainnet"],
+            _ => vec![name.as_str()],
+        };
+        let global_path = data_dir.join(conf_path.file_name().unwrap_or_default());
+        let mut table = read_table(&global_path);
+        let profiles = profiles
+            .into_iter()
+            .filter_map(|name| match table.remove(name) {
+                Some(toml::Value::Table(profile)) => Some(profile),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        table.retain(|key, _| !PROFILES.contains(&key));
+        for profile in profiles {
+            table.extend(profile);
+        }
+        if global_path != conf_path {
+            let mut local = read_table(conf_path);
+            local.retain(|key, _| !PROFILES.contains(&key));
+            table.extend(local);
+        }
+        toml::Value::Table(table)
+            .try_into()
+            .map_err(|err| {
+                error!("Unable to parse config file: {err}");
+            })
+            .unwrap_or_default()
     }
 }
 
+/// Reads configuration file as a TOML table, returning an empty table if the
+/// file is absent or can't be parsed.
+fn read_table(path: &Path) -> toml::Table {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| {
+            toml::from_str(&s)
+                .map_err(|err| {
+                    error!("Unable to parse config file: {err}");
+                })
+                .ok()
+        })
+        .unwrap_or_default()
+}
+
 /// Command-line arguments
 #[derive(Parser)]
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -75,6 +311,49 @@ pub struct RgbArgs {
     /// should be checked for re-orgs
     #[clap(short = 'H', long, requires = "sync")]
     pub from_height: Option<u32>,
+
+    /// Report errors on stderr as JSON objects with the error class, exit
+    /// code and message
+    #[clap(long, global = true)]
+    pub json_errors: bool,
+
+    /// Render a progress bar while synchronizing the wallet
+    #[clap(long, global = true, requires = "sync")]
+    pub progress: bool,
+
+    /// Append tapret tweaks added to the wallet by transfers to the given file.
+    /// Not allowed for encrypted wallets, since the file is kept in plaintext
+    #[clap(long, global = true)]
+    pub tweaks_backup: Option<PathBuf>,
+
+    /// Wait for other processes to release the stock lock (default)
+    #[clap(long, global = true, overrides_with = "no_wait")]
+    pub wait: bool,
+
+    /// Fail immediately if the stock is locked by another process
+    #[clap(long, global = true, overrides_with = "wait")]
+    pub no_wait: bool,
+
+    /// Connect to the indexer via SOCKS5 proxy (like `127.0.0.1:9050` for
+    /// Tor)
+    #[clap(long, global = true, value_name = "HOST:PORT")]
+    pub proxy: Option<String>,
+
+    /// Accept TLS certificates of the indexer which can't be verified,
+    /// including self-signed ones
+    #[clap(long, global = true)]
+    pub accept_invalid_certs: bool,
+
+    /// Do not use the cache of resolved transactions kept in the data
+    /// directory
+    #[clap(long, global = true)]
+    pub no_resolver_cache: bool,
+
+    /// Esplora server indexing the Liquid chain, used to resolve witness
+    /// transactions of the contracts anchored to Liquid
+    #[cfg(feature = "liquid")]
+    #[clap(long, global = true, value_name = "URL")]
+    pub liquid_esplora: Option<String>,
 }
 
 impl Deref for RgbArgs {
@@ -93,45 +372,101 @@ impl Default for RgbArgs {
 }
 
 impl RgbArgs {
-    pub(crate) fn load_stock(
-        &self,
-        stock_path: impl ToOwned<Owned = PathBuf>,
-    ) -> Result<Stock, WalletError> {
-        let stock_path = stock_path.to_owned();
+    /// Processes the arguments, using the indexer from the configuration if
+    /// none is given in the command line and resolving the `{network}`
+    /// placeholder in the esplora and mempool URLs.
+    pub fn process(&mut self) {
+        self.inner.process();
+        let resolver = &mut self.inner.resolver;
+        if resolver.esplora.is_none() && resolver.electrum.is_none() && resolver.mempool.is_none() {
+            let config = self.stock_config();
+            let resolver = &mut self.inner.resolver;
+            resolver.esplora = config.esplora;
+            resolver.electrum = config.electrum;
+            resolver.mempool = config.mempool;
+        }
+        let network = self.general.network;
+        let resolver = &mut self.inner.resolver;
+        for url in [&mut resolver.esplora, &mut resolver.mempool]
+            .into_iter()
+            .flatten()
+        {
+            *url = indexer_url(url, network);
+        }
+    }
+
+    /// Loads the RGB-specific settings for the network selected with the
+    /// `--network` argument.
+    pub fn stock_config(&self) -> StockConfig {
+        StockConfig::load(&self.general.data_dir, self.general.network, &self.conf_path("rgb"))
+    }
+
+    /// Returns name of the wallet, which is either given explicitly, with the
+    /// `--wallet` argument or is the default wallet.
+    pub fn wallet_name(&self, config: &Config, wallet: Option<&String>) -> String {
+        wallet
+            .cloned()
+            .or_else(|| self.wallet.name.as_ref().map(Ident::to_string))
+            .unwrap_or_else(|| config.default_wallet.clone())
+    }
+
+    /// Constructs factory of the wallets kept in the data directory of the
+    /// network selected with the `--network` argument.
+    pub fn wallet_factory(&self) -> WalletFactory {
+        WalletFactory::in_dir(self.general.base_dir(), self.general.network)
+            .with_stock_backups(self.stock_config().stock_backups)
+            .with_wait(!self.no_wait)
+    }
+
+    pub(crate) fn load_stock(&self) -> Result<Stock, WalletError> {
+        let factory = self.wallet_factory();
+        self.lock_stock(factory.base_dir())?;
 
         if self.verbose > 1 {
-            eprint!("Loading stock from `{}` ... ", stock_path.display());
+            eprint!("Loading stock from `{}` ... ", factory.base_dir().display());
         }
 
-        let provider = FsBinStore::new(stock_path.clone())?;
-        let mut stock = Stock::load(provider, true).or_else(|err| {
-            if err
-                .0
-                .downcast_ref::<DeserializeError>()
-                .map(|e| matches!(e, DeserializeError::Decode(DecodeError::Io(ref e)) if e.kind() == ErrorKind::NotFound))
-                .unwrap_or_default()
-            {
-                if self.verbose > 1 {
-                    eprint!("stock file is absent, creating a new one ... ");
+        let mut stock = match self.loaded_stock(factory.base_dir())? {
+            Some(stock) => stock,
+            None => {
+                let stock = factory.stock().map_err(|err| {
+                    eprintln!("stock file is damaged, failing");
+                    error!("Unable to load stock data: {err:?}");
+                    err
+                })?;
+                if KEEP_STOCK.with(Cell::get) {
+                    LOADED_STOCK.with(|loaded| {
+                        *loaded.borrow_mut() = Some(LoadedStock {
+                            stock: stock.clone_no_persistence(),
+                            modified: stock_modified(factory.base_dir()),
+                        })
+                    });
                 }
-                fs::create_dir_all(&stock_path)?;
-                let provider = FsBinStore::new(stock_path)?;
-                let mut stock = Stock::in_memory();
                 stock
-                    .make_persistent(provider, true)
-                    .map_err(WalletError::StockPersist)?;
-                return Ok(stock);
             }
-            eprintln!("stock file is damaged, failing");
-            error!("Unable to load stock data: {err:?}");
-            Err(WalletError::StockPersist(err))
-        })?;
+        };
 
         if self.sync {
             let resolver = self.resolver()?;
             let from_height = self.from_height.unwrap_or(1);
             eprint!("Updating witness information starting from height {from_height} ... ");
-            let res = stock.update_witnesses(resolver, from_height)?;
+            let res = if self.progress {
+                eprintln!();
+                let mut progress = SyncProgress::default();
+                let res = update_witnesses_with_progress(
+                    &mut stock,
+                    resolver,
+                    from_height,
+                    &mut progress,
+                    &mut render_progress,
+                )?;
+                eprintln!();
+                res
+            } else {
+                stock
+                    .update_witnesses(resolver, from_height)
+                    .map_err(|err| SyncError::Stock(err.to_string()))?
+            };
             eprint!("{} transactions were checked and updated", res.succeeded);
             if res.failed.is_empty() {
                 eprintln!();
@@ -150,18 +485,81 @@ impl RgbArgs {
         Ok(stock)
     }
 
+    /// Returns copy of the stock kept loaded between the commands, unless the
+    /// stock files were modified after it was loaded. Changes made to the
+    /// returned stock are persisted to the stock files, which makes the next
+    /// command load the stock again.
+    #[allow(clippy::result_large_err)]
+    fn loaded_stock(&self, stock_path: &Path) -> Result<Option<Stock>, WalletError> {
+        let modified = stock_modified(stock_path);
+        let stock = LOADED_STOCK.with(|loaded| {
+            loaded
+                .borrow()
+                .as_ref()
+                .filter(|loaded| loaded.modified == modified)
+                .map(|loaded| loaded.stock.clone_no_persistence())
+        });
+        let Some(mut stock) = stock else {
+            return Ok(None);
+        };
+        let backups = self.stock_config().stock_backups;
+        stock
+            .make_persistent(BackupStore::new(stock_path.to_path_buf(), backups)?, true)
+            .map_err(WalletError::StockPersist)?;
+        // Attaching the persistence may re-write the stock files with the same
+        // data, which must not invalidate the loaded stock
+        LOADED_STOCK.with(|loaded| {
+            if let Some(loaded) = loaded.borrow_mut().as_mut() {
+                loaded.modified = stock_modified(stock_path);
+            }
+        });
+        Ok(Some(stock))
+    }
+
+    /// Locks the stock for the lifetime of the process, such that concurrently
+    /// running commands don't corrupt it.
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn lock_stock(&self, stock_path: &Path) -> Result<(), WalletError> {
+        if STOCK_LOCK.get().is_some() {
+            return Ok(());
+        }
+        let lock = match StockLock::acquire(stock_path, false) {
+            Err(WalletError::StockLocked(_)) if !self.no_wait => {
+                eprintln!("Stock is locked by another process, waiting for it to be released");
+                StockLock::acquire(stock_path, true)?
+            }
+            res => res?,
+        };
+        let _ = STOCK_LOCK.set(lock);
+        Ok(())
+    }
+
     pub fn rgb_stock(&self) -> Result<Stock, WalletError> {
-        let stock_path = self.general.base_dir();
-        let stock = self.load_stock(stock_path)?;
-        Ok(stock)
+        self.load_stock()
     }
 
+    /// Creates guard checking that all components used by a command are for
+    /// the network selected with the `--network` argument.
+    pub fn network_guard(&self) -> NetworkGuard { NetworkGuard::new(self.general.network) }
+
     pub fn rgb_wallet(
         &self,
         config: &Config,
+    ) -> Result<RgbWallet<Wallet<XpubDerivable, RgbDescr>>, WalletError> {
+        self.rgb_wallet_guarded(config, self.network_guard())
+    }
+
+    /// Loads the wallet, checking the network of its descriptor together with
+    /// the components already added to the `guard`, such that all network
+    /// mismatches are reported at once.
+    #[allow(clippy::result_large_err)]
+    pub fn rgb_wallet_guarded(
+        &self,
+        config: &Config,
+        guard: NetworkGuard,
     ) -> Result<RgbWallet<Wallet<XpubDerivable, RgbDescr>>, WalletError> {
         let stock = self.rgb_stock()?;
-        self.rgb_wallet_from_stock(config, stock)
+        self.load_rgb_wallet(config, stock, guard)
             .map_err(|(_, err)| err)
     }
 
@@ -170,26 +568,211 @@ impl RgbArgs {
         config: &Config,
         stock: Stock,
     ) -> Result<RgbWallet<Wallet<XpubDerivable, RgbDescr>>, (Stock, WalletError)> {
-        let wallet = match self.inner.bp_wallet::<RgbDescr>(config) {
+        self.load_rgb_wallet(config, stock, self.network_guard())
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn load_rgb_wallet(
+        &self,
+        config: &Config,
+        stock: Stock,
+        guard: NetworkGuard,
+    ) -> Result<RgbWallet<Wallet<XpubDerivable, RgbDescr>>, (Stock, WalletError)> {
+        let wallet = match self.bp_wallet(config) {
             Ok(wallet) => wallet,
+            Err(e) => return Err((stock, e)),
+        };
+        if let Err(err) = guard.check_descriptor(wallet.network()).finish() {
+            return Err((stock, err.into()));
+        }
+        if self.sync && self.progress {
+            eprintln!(
+                "Wallet UTXOs synchronized: {} addresses used, {} UTXOs found",
+                wallet.address_balance().count(),
+                wallet.utxos().count()
+            );
+        }
+        let factory = self.wallet_factory();
+        let frozen = match factory.frozen() {
+            Ok(frozen) => frozen,
+            Err(e) => return Err((stock, e.into())),
+        };
+        let imported = match factory.imported() {
+            Ok(imported) => imported,
             Err(e) => return Err((stock, e.into())),
         };
-        let wallet = RgbWallet::new(stock, wallet);
+        let velocity_prefs = match factory.velocity_prefs() {
+            Ok(prefs) => prefs,
+            Err(e) => return Err((stock, e.into())),
+        };
+        // Tapret tweaks of an encrypted wallet must not leak into a plaintext
+        // backup file
+        if let (Some(path), true) = (&self.tweaks_backup, self.is_wallet_encrypted(config)) {
+            return Err((stock, EncryptionError::PlaintextBackup(path.clone()).into()));
+        }
+        let mut wallet = RgbWallet::new(stock, wallet);
+        wallet.set_frozen(frozen);
+        wallet.set_imported(imported);
+        wallet.set_velocity_prefs(velocity_prefs);
+        if let Some(path) = self.tweaks_backup.clone() {
+            wallet.set_tweaks_backup(move |tweaks| {
+                let res = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .and_then(|mut file| file.write_all(tweaks.to_string().as_bytes()));
+                if let Err(err) = res {
+                    eprintln!("unable to backup tapret tweaks to `{}`: {err}", path.display());
+                }
+            });
+        }
 
         Ok(wallet)
     }
 
+    /// Loads the bitcoin wallet, prompting for the passphrase if the wallet
+    /// is encrypted.
+    #[allow(clippy::result_large_err)]
+    fn bp_wallet(&self, config: &Config) -> Result<Wallet<XpubDerivable, RgbDescr>, WalletError> {
+        if !self.is_wallet_encrypted(config) {
+            return Ok(self.inner.bp_wallet::<RgbDescr>(config)?);
+        }
+        self.open_wallet(&self.wallet_name(config, None))
+    }
+
+    /// Detects whether the wallet used by the command is an encrypted wallet
+    /// of the data directory.
+    fn is_wallet_encrypted(&self, config: &Config) -> bool {
+        !self.wallet.descriptor_opts.is_some()
+            && is_encrypted(self.general.base_dir().join(self.wallet_name(config, None)))
+    }
+
+    /// Opens the wallet of the data directory by its name, prompting for the
+    /// passphrase if the wallet is encrypted.
+    #[allow(clippy::result_large_err)]
+    pub fn open_wallet(&self, name: &str) -> Result<Wallet<XpubDerivable, RgbDescr>, WalletError> {
+        let factory = self.wallet_factory();
+        if !is_encrypted(self.general.base_dir().join(name)) {
+            return factory.open_wallet(name);
+        }
+        factory
+            .with_passphrase(read_passphrase(name)?)
+            .open_wallet(name)
+    }
+
+    /// Constructs kit registries from the configuration file together with
+    /// the fetcher which downloads kits from them.
+    #[allow(clippy::result_large_err)]
+    pub fn kit_registries(&self) -> Result<(Vec<KitRegistry>, HttpsFetcher), WalletError> {
+        let config = self.stock_config();
+        let registries = config
+            .kit_registries
+            .into_iter()
+            .map(|conf| {
+                let registry = KitRegistry::new(conf.url)?;
+                Ok(match conf.sha256 {
+                    Some(sha256) => registry.with_pin(sha256),
+                    None => registry,
+                })
+            })
+            .collect::<Result<_, KitRegistryError>>()?;
+        let fetcher =
+            HttpsFetcher::new(Duration::from_secs(config.indexer_timeout), self.proxy.as_deref())
+                .map_err(|e| KitRegistryError::Fetch(s!("registry"), e))?;
+        Ok((registries, fetcher))
+    }
+
+    /// Constructs the client exchanging payjoin proposals with the receiver
+    /// endpoints.
+    #[allow(clippy::result_large_err)]
+    pub fn payjoin_client(&self) -> Result<HttpsPayjoin, WalletError> {
+        let config = self.stock_config();
+        let client =
+            HttpsPayjoin::new(Duration::from_secs(config.indexer_timeout), self.proxy.as_deref())
+                .map_err(|e| PayjoinError::Exchange(s!("client"), e))?;
+        Ok(client)
+    }
+
+    /// Constructs the client delivering consignments to the transport
+    /// endpoints of invoices.
+    #[allow(clippy::result_large_err)]
+    pub fn consignment_poster(&self) -> Result<HttpsPoster, WalletError> {
+        let poster = HttpsPoster::new(self.proxy.as_deref())
+            .map_err(|e| DeliveryError::Failed(format!("client: {e}")))?;
+        Ok(poster)
+    }
+
+    /// Changes the default wallet in the configuration file, keeping the rest
+    /// of the configuration intact.
+    #[allow(clippy::result_large_err)]
+    pub fn set_default_wallet(&self, config: &mut Config, name: &str) -> Result<(), WalletError> {
+        let path = self.conf_path("rgb");
+        let mut table = match fs::read_to_string(&path) {
+            Ok(s) => toml::from_str::<toml::Table>(&s)
+                .map_err(|err| WalletError::Format("TOML", err.to_string()))?,
+            Err(err) if err.kind() == ErrorKind::NotFound => toml::Table::new(),
+            Err(err) => return Err(err.into()),
+        };
+        table.insert(s!("defaultWallet"), toml::Value::String(name.to_owned()));
+        fs::write(&path, table.to_string())?;
+        config.default_wallet = name.to_owned();
+        Ok(())
+    }
+
     pub fn resolver(&self) -> Result<AnyResolver, WalletError> {
+        self.resolver_guarded(self.network_guard())
+    }
+
+    /// Connects to the resolver, checking its network together with the
+    /// components already added to the `guard`, such that all network
+    /// mismatches are reported at once.
+    #[allow(clippy::result_large_err)]
+    pub fn resolver_guarded(&self, guard: NetworkGuard) -> Result<AnyResolver, WalletError> {
+        let config = self.stock_config();
+        let opts = ConnectionOpts {
+            socks5: self.proxy.clone(),
+            accept_invalid_certs: self.accept_invalid_certs,
+            headers: config.indexer_headers,
+            bearer_token: config.indexer_token,
+            resolver: ResolverConfig {
+                timeout: Duration::from_secs(config.indexer_timeout),
+                retries: config.indexer_retries,
+                backoff: Duration::from_millis(config.indexer_backoff),
+            },
+        };
         let resolver =
             match (&self.resolver.esplora, &self.resolver.electrum, &self.resolver.mempool) {
-                (None, Some(url), None) => AnyResolver::electrum_blocking(url, None),
-                (Some(url), None, None) => AnyResolver::esplora_blocking(url, None),
-                (None, None, Some(url)) => AnyResolver::mempool_blocking(url, None),
+                (None, Some(url), None) => AnyResolver::electrum_blocking_with(url, &opts),
+                (Some(url), None, None) => AnyResolver::esplora_blocking_with(url, &opts),
+                (None, None, Some(url)) => AnyResolver::mempool_blocking_with(url, &opts),
                 _ => Err(s!(" - error: no transaction resolver is specified; use either \
                              --esplora --mempool or --electrum argument")),
             }
             .map_err(WalletError::Resolver)?;
-        resolver.check(self.general.network)?;
+        let resolver = if self.no_resolver_cache {
+            resolver
+        } else {
+            resolver
+                .cached(self.general.base_dir().join(RESOLVER_CACHE_FILE))
+                .map_err(WalletError::Resolver)?
+        };
+        #[cfg(feature = "liquid")]
+        let resolver = match &self.liquid_esplora {
+            Some(url) => {
+                let liquid = AnyResolver::esplora_blocking_with(url, &opts)
+                    .map_err(WalletError::Resolver)?;
+                let resolver = resolver.with_liquid(liquid);
+                resolver
+                    .check_liquid(self.general.network)
+                    .map_err(WalletError::Resolver)?;
+                resolver
+            }
+            None => resolver,
+        };
+        guard
+            .check_resolver(&resolver)
+            .map_err(WalletError::Resolver)?
+            .finish()?;
         Ok(resolver)
     }
 }
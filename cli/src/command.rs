@@ -21,43 +21,105 @@
 
 use std::fs;
 use std::fs::File;
+use std::io::{self, BufReader};
 use std::ops::ControlFlow;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use amplify::confinement::{SmallOrdMap, TinyOrdMap, TinyOrdSet, U16 as MAX16};
+use amplify::confinement::{SmallOrdMap, TinyOrdMap, TinyOrdSet};
+use amplify::hex::{FromHex, ToHex};
 use baid64::DisplayBaid64;
-use bpstd::psbt::{Psbt, PsbtVer};
+use bpstd::psbt::{PsbtVer, TxParams};
+use bpstd::seals::txout::CloseMethod;
 use bpstd::seals::SecretSeal;
-use bpstd::{Sats, XpubDerivable};
+use bpstd::secp256k1::Keypair;
+use bpstd::{
+    LockTime, Outpoint, RedeemScript, Sats, ScriptPubkey, SeqNo, WitnessScript, XprivAccount,
+    XpubDerivable,
+};
 use bpwallet::cli::{BpCommand, Config, Exec};
 use bpwallet::Wallet;
+use clap::CommandFactory;
+use nonasync::persistence::PersistenceError;
+use psrgbt::{OutputOrdering, PsbtConstructor, RgbCosign, RgbInExt, RgbSignRequest};
 use rgb::containers::{
-    BuilderSeal, ConsignmentExt, ContainerVer, ContentId, ContentSigs, Contract, FileContent,
-    Supplement, Transfer, UniversalFile,
+    BuilderSeal, Consignment, ConsignmentExt, ConsignmentId, ContainerVer, ContentId, ContentSigs,
+    Contract, FileContent, Supplement, Transfer, UniversalFile, VelocityHint,
+};
+use rgb::interface::{AssignmentsFilter, ContractOp};
+use rgb::invoice::{
+    Amount, Beneficiary, Pay2Vout, RgbInvoice, RgbInvoiceBuilder, RgbTransport, XChainNet,
 };
-use rgb::interface::{AssignmentsFilter, ContractOp, IfaceId};
-use rgb::invoice::{Beneficiary, Pay2Vout, RgbInvoice, RgbInvoiceBuilder, XChainNet};
 use rgb::persistence::{MemContract, StashReadProvider, Stock};
-use rgb::resolvers::ContractIssueResolver;
+use rgb::resolvers::{checkpoint_path, ContractIssueResolver};
 use rgb::schema::SchemaId;
-use rgb::validation::Validity;
 use rgb::vm::{RgbIsa, WitnessOrd};
 use rgb::{
-    Allocation, BundleId, ContractId, DescriptorRgb, GenesisSeal, GraphSeal, Identity, OpId,
-    OutputSeal, OwnedFraction, RgbDescr, RgbKeychain, RgbWallet, StateType, TokenIndex,
-    TransferParams, WalletError, WalletProvider, XChain, XOutpoint, XWitnessId,
+    blank_contracts, chain_net, deliver_consignment, effective_feerate, fetch_schema_kit,
+    from_portable, has_flat_layout, migrate_flat_layout, parse_velocity, reveal_known_seals,
+    to_portable, update_kits, verify_ownership, AddressReservations, Allocation, AllocationProof,
+    AllocationsReader, Amendment, AmountFormatter, AmountRange, AssetCollision, AssetRegistryStock,
+    AssignmentType, BackupStore, BasketInvoice, Bip340Verifier, BundleId, CoinSelection,
+    CompactInvoice, CompositionError, ConsignmentDiff, ConsolidationScope, ContractCall,
+    ContractDefinition, ContractGraph, ContractId, ContractInfoExt, ContractStateQuery,
+    DeferredValidation, DescriptorRgb, FrozenOutpoints, Genesis, GenesisSeal, GraphSeal, Identity,
+    ImportError, ImportedUtxo, ImportedUtxos, InitialAllocation, InvoiceExpiry, IssuanceTemplate,
+    IssueError, IssueProblem, IssuerSigStock, IssuerStatus, LabelTarget, NetworkGuard, OpId, Opout,
+    OutputSeal, OwnedFraction, PayjoinEndpoint, PayjoinProposal, PolicyRule, Precision,
+    PrioritizedBuilder, Quarantine, RedeemInfo, ReportValidity, RequestedVelocity, Rgb20Issuance,
+    Rgb21Issuance, RgbDescr, RgbWallet, SaleProposal, SchemaDescription, SealExpiry, Signer,
+    SoftwareSigner, SplitSeals, SqliteStore, StateQuery, StateType, StockExport, StockExportError,
+    StockRecovery, SwapProposal, TapretTweaks, TokenIndex, TransferParams, TransportMeta,
+    TrustPolicy, ValidatedInvoiceBuilder, ValidationReport, WalletDir, WalletDirError, WalletError,
+    WalletLabels, WalletProvider, WitnessSats, WitnessStatus, XChain, XOutpoint, XWitnessId,
+    BALANCE_MIN_CONFIRMATIONS, FROZEN_FILE, IMPORTED_FILE, VELOCITY_FILE,
 };
-use rgbstd::interface::{AllocatedState, ContractIface, OwnedIface};
+use rgbstd::interface::{ContractIface, OwnedIface};
 use rgbstd::persistence::{MemContractState, StockError};
 use rgbstd::stl::rgb_contract_stl;
-use rgbstd::{KnownState, OutputAssignment};
+#[cfg(feature = "liquid")]
+use rgbstd::AltLayer1;
+use rgbstd::{KnownState, Layer1, OutputAssignment};
+use serde_crate::de::DeserializeOwned;
 use serde_crate::{Deserialize, Serialize};
-use strict_types::encoding::{FieldName, TypeName};
+use strict_types::encoding::{FieldName, StrictDeserialize, StrictSerialize, TypeName};
 use strict_types::StrictVal;
 
+use crate::stdio::{
+    check_stdout, is_stdio, load_content, load_psbt, load_transfer, load_universal, read_text,
+    save_content, save_psbt, STDIO,
+};
+use crate::args::{read_new_passphrase, read_passphrase, STOCK_FILES};
 use crate::RgbArgs;
 
+/// Name of the trust policy file inside the data directory.
+const POLICY_FILE: &str = "policy.yaml";
+/// Name of the directory inside the data directory keeping quarantined
+/// consignments.
+const QUARANTINE_DIR: &str = "quarantine";
+/// Name of the file inside the data directory keeping expiry times of the
+/// blinded seals created for the invoices.
+const SEAL_EXPIRY_FILE: &str = "seals.yaml";
+/// Name of the file inside the data directory keeping addresses reserved by
+/// the witness-out invoices until they expire.
+const RESERVATIONS_FILE: &str = "reservations.yaml";
+const DEFERRED_VALIDATION_FILE: &str = "deferred.yaml";
+/// Name of the file inside the data directory keeping user-assigned labels.
+const LABELS_FILE: &str = "labels.yaml";
+/// Files of the data directory carried by the stock export along with the
+/// stock.
+const STOCK_EXPORT_FILES: [&str; 8] = [
+    FROZEN_FILE,
+    IMPORTED_FILE,
+    VELOCITY_FILE,
+    POLICY_FILE,
+    SEAL_EXPIRY_FILE,
+    RESERVATIONS_FILE,
+    DEFERRED_VALIDATION_FILE,
+    LABELS_FILE,
+];
+
 #[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
 #[display(lowercase)]
 #[allow(clippy::large_enum_variant)]
@@ -75,20 +137,53 @@ pub enum Command {
     /// Prints out list of known RGB interfaces
     Interfaces,
 
+    /// Describes a schema: its global and owned state types, operations,
+    /// default assignments and strict type definitions of the state
+    #[display("schema")]
+    Schema {
+        /// Print the description in JSON format
+        #[clap(long)]
+        json: bool,
+
+        /// Schema id to describe
+        schema_id: SchemaId,
+    },
+
     /// Prints out list of known RGB contracts
     #[display("contracts")]
     Contracts,
 
+    /// Sign genesis of a contract with the issuer key, proving the issuer
+    /// identity
+    ///
+    /// The issuer identity must contain the BIP340 public key of the issuer
+    /// in form of `bip340:<hex>`. The signature is stored in the stash and
+    /// included into the consignments.
+    #[display("sign-issuer")]
+    SignIssuer {
+        /// File containing hex-encoded secret key of the issuer
+        #[arg(short, long)]
+        key: PathBuf,
+
+        /// Contract id to sign
+        contract_id: ContractId,
+    },
+
     /// Imports RGB data into the stash: contracts, schema, interfaces, etc
     #[display("import")]
     Import {
-        /// Use BASE64 ASCII armoring for binary data
-        #[arg(short)]
+        /// Kept for compatibility: binary and ASCII-armored data are detected
+        /// automatically
+        #[arg(short, hide = true)]
         armored: bool,
 
-        /// File with RGB data
-        ///
-        /// If not provided, assumes `-a` and prints out data to STDOUT
+        /// Import contract even if its ticker or name is already used by a
+        /// known contract
+        #[arg(long)]
+        allow_duplicate_ticker: bool,
+
+        /// File with RGB data, either binary or ASCII-armored. Use `-` to read
+        /// the data from STDIN
         file: PathBuf,
     },
 
@@ -102,18 +197,15 @@ pub enum Command {
         /// Contract to export
         contract: ContractId,
 
-        /// File with RGB data
-        ///
-        /// If not provided, assumes `-a` and reads the data from STDIN
+        /// File to save RGB data to. If not provided or `-`, prints out the
+        /// ASCII-armored data to STDOUT
         file: Option<PathBuf>,
     },
 
     /// Convert binary RGB file into a text armored version
     #[display("convert")]
     Armor {
-        /// File with RGB data
-        ///
-        /// If not provided, assumes `-a` and reads the data from STDIN
+        /// File with RGB data. Use `-` to read the data from STDIN
         file: PathBuf,
     },
 
@@ -124,6 +216,50 @@ pub enum Command {
         #[arg(short, long)]
         all: bool,
 
+        /// Print fungible amounts as integers in the smallest units, without
+        /// adjusting them for the contract precision
+        #[arg(long)]
+        raw: bool,
+
+        /// Show only the state assigned to the outpoint. May be repeated
+        #[arg(long = "outpoint")]
+        outpoints: Vec<Outpoint>,
+
+        /// Show only the fungible state with at least this amount (in the
+        /// smallest unit)
+        #[arg(long)]
+        min: Option<u64>,
+
+        /// Show only the fungible state with at most this amount (in the
+        /// smallest unit)
+        #[arg(long)]
+        max: Option<u64>,
+
+        /// Show only the state with the witness transaction of the given
+        /// status: `genesis`, `mined`, `tentative` or `archived`. May be
+        /// repeated
+        #[arg(long)]
+        witness: Vec<WitnessStatus>,
+
+        /// Show confirmation depth of the witness transactions, counted at the
+        /// blockchain tip reported by the indexer
+        #[arg(long)]
+        depth: bool,
+
+        /// Show only the state which witness transaction has at least this
+        /// number of confirmations. The genesis state is always shown. Implies
+        /// `--depth`
+        #[arg(long)]
+        min_confirmations: Option<u32>,
+
+        /// Number of the matching allocations of each state type to skip
+        #[arg(long, default_value = "0")]
+        offset: usize,
+
+        /// Maximal number of the allocations of each state type to show
+        #[arg(long)]
+        limit: Option<usize>,
+
         /// Contract identifier
         contract_id: ContractId,
 
@@ -131,6 +267,55 @@ pub enum Command {
         iface: Option<String>,
     },
 
+    /// Export graph of the contract history, including operations, witness
+    /// transactions with their confirmation heights and allocation flows.
+    ///
+    /// With `--sync` the status of the witness transactions is updated from
+    /// the indexer before the export.
+    #[display("explore")]
+    Explore {
+        /// Export the graph in Graphviz DOT language
+        #[arg(long, conflicts_with = "format")]
+        dot: bool,
+
+        /// Format of the exported graph
+        #[clap(long, value_enum, default_value_t)]
+        format: InspectFormat,
+
+        /// Contract identifier
+        contract_id: ContractId,
+    },
+
+    /// Export proof of the contract allocations at a transaction output,
+    /// which contains only the part of the contract history leading to them.
+    #[display("prove")]
+    Prove {
+        /// Contract identifier
+        contract_id: ContractId,
+
+        /// Transaction output holding the allocations
+        outpoint: Outpoint,
+
+        /// File to save the proof to
+        file: PathBuf,
+    },
+
+    /// Verify proof of the contract allocations at a transaction output and
+    /// print the proven allocations.
+    ///
+    /// The proof doesn't show whether the output is spent.
+    #[display("verify-proof")]
+    VerifyProof {
+        /// Contract identifier
+        contract_id: ContractId,
+
+        /// Transaction output holding the allocations
+        outpoint: Outpoint,
+
+        /// File with the proof
+        file: PathBuf,
+    },
+
     /// Print operation history for a default fungible token under a given
     /// interface
     #[display("history")]
@@ -139,6 +324,20 @@ pub enum Command {
         #[arg(long)]
         details: bool,
 
+        /// Export history in CSV format into a file instead of printing it
+        #[arg(long, value_name = "FILE", conflicts_with = "details")]
+        csv: Option<PathBuf>,
+
+        /// Print a single item per transfer, combining all operations
+        /// committed to by the same witness transaction
+        #[arg(long, conflicts_with_all = ["details", "csv"])]
+        grouped: bool,
+
+        /// Print fungible amounts as integers in the smallest units, without
+        /// adjusting them for the contract precision
+        #[arg(long)]
+        raw: bool,
+
         /// Contract identifier
         contract_id: ContractId,
 
@@ -146,6 +345,22 @@ pub enum Command {
         iface: Option<String>,
     },
 
+    /// Print balance of a fungible contract split by the confirmation status
+    #[display("balance")]
+    Balance {
+        /// Number of confirmations after which the state is counted as confirmed
+        #[arg(long, default_value_t = BALANCE_MIN_CONFIRMATIONS)]
+        confirmations: u32,
+
+        /// Print fungible amounts as integers in the smallest units, without
+        /// adjusting them for the contract precision
+        #[arg(long)]
+        raw: bool,
+
+        /// Contract identifier
+        contract_id: ContractId,
+    },
+
     /// Display all known UTXOs belonging to this wallet
     Utxos,
 
@@ -160,6 +375,120 @@ pub enum Command {
 
         /// File containing contract genesis description in YAML format
         contract: PathBuf,
+
+        /// CSV file with additional initial allocations of fungible state, one
+        /// per line in `assignment,seal,amount` format
+        #[arg(long)]
+        allocations: Option<PathBuf>,
+
+        /// Issue in the offline (air-gapped) mode, deferring verification that
+        /// the outpoints of the genesis seals exist on chain until the
+        /// `reconcile` command is run
+        #[arg(long)]
+        offline: bool,
+    },
+
+    /// Issues new fungible asset under RGB20 interface
+    #[display("issue-rgb20")]
+    IssueRgb20 {
+        /// Schema to use for the contract. Defaults to the first known schema
+        /// implementing RGB20 interface
+        #[arg(long)]
+        schema: Option<SchemaId>,
+
+        /// Number of decimal digits in the asset amounts
+        #[arg(short, long, default_value = "0")]
+        precision: u8,
+
+        /// Asset details
+        #[arg(long)]
+        details: Option<String>,
+
+        /// File containing text of the ricardian contract
+        #[arg(long)]
+        terms: Option<PathBuf>,
+
+        /// Issuer identity string
+        issuer: Identity,
+
+        /// Asset ticker
+        ticker: String,
+
+        /// Asset name
+        name: String,
+
+        /// Initial allocations in `SEAL=AMOUNT` format
+        #[arg(required = true, value_parser = parse_allocation)]
+        allocations: Vec<(OutputSeal, u64)>,
+
+        /// Issue in the offline (air-gapped) mode, deferring verification that
+        /// the outpoints of the genesis seals exist on chain until the
+        /// `reconcile` command is run
+        #[arg(long)]
+        offline: bool,
+    },
+
+    /// Issues new unique digital asset under RGB21 interface
+    #[display("issue-rgb21")]
+    IssueRgb21 {
+        /// Schema to use for the contract. Defaults to the first known schema
+        /// implementing RGB21 interface
+        #[arg(long)]
+        schema: Option<SchemaId>,
+
+        /// Asset details
+        #[arg(long)]
+        details: Option<String>,
+
+        /// File containing text of the ricardian contract
+        #[arg(long)]
+        terms: Option<PathBuf>,
+
+        /// Index of the token
+        #[arg(long, default_value = "0")]
+        index: TokenIndex,
+
+        /// Number of fractions the token is split into
+        #[arg(long, default_value = "1")]
+        fractions: OwnedFraction,
+
+        /// Issuer identity string
+        issuer: Identity,
+
+        /// Asset ticker
+        ticker: String,
+
+        /// Asset name
+        name: String,
+
+        /// Seal owning the token
+        owner: OutputSeal,
+
+        /// Issue in the offline (air-gapped) mode, deferring verification that
+        /// the outpoints of the genesis seals exist on chain until the
+        /// `reconcile` command is run
+        #[arg(long)]
+        offline: bool,
+    },
+
+    /// Amend contract global state (like asset name or terms) using a state
+    /// extension, producing contract consignment for the contract holders
+    #[display("amend")]
+    Amend {
+        /// State extension to use. Required if the contract provides several
+        /// state extensions
+        #[arg(short, long)]
+        extension: Option<String>,
+
+        /// Contract identifier
+        contract_id: ContractId,
+
+        /// File containing new values of the global state in YAML format, as a
+        /// mapping of the global state names to their values
+        globals: PathBuf,
+
+        /// File for the generated contract consignment
+        consignment: PathBuf,
     },
 
     /// Create new invoice
@@ -169,6 +498,12 @@ pub enum Command {
         #[arg(short, long)]
         address_based: bool,
 
+        /// Request the state on Liquid, to an address of the Liquid chain;
+        /// the contract must support Liquid as its layer 1
+        #[cfg(feature = "liquid")]
+        #[arg(long, requires = "address_based")]
+        liquid: bool,
+
         /// Interface to interpret the state data
         #[arg(short, long)]
         iface: Option<String>,
@@ -193,6 +528,57 @@ pub enum Command {
         #[arg(short, long)]
         amount: Option<u64>,
 
+        /// Minimal amount of tokens (in the smallest unit) accepted as a
+        /// payment, allowing the payer to pay less than the invoice amount
+        #[arg(long)]
+        min: Option<u64>,
+
+        /// Maximal amount of tokens (in the smallest unit) accepted as a
+        /// payment, allowing the payer to pay more than the invoice amount
+        #[arg(long)]
+        max: Option<u64>,
+
+        /// Number of additional blinded seals across which the amount has to
+        /// be split, each using a separate UTXO of the wallet
+        #[arg(long, requires = "amount", conflicts_with = "address_based")]
+        split: Option<usize>,
+
+        /// Amount of sats which the payer must send to the witness output
+        /// receiving the state, instead of its own default amount
+        #[arg(long, requires = "address_based")]
+        sats: Option<u64>,
+
+        /// Velocity class of the received state: `unspecified`, `seldom`,
+        /// `episodic`, `regular`, `frequent` or `high-frequency`. The payer
+        /// marks the witness output with it, and the wallet keeps the class
+        /// for the change of the state when spending it later
+        #[arg(long, value_parser = parse_velocity_class)]
+        velocity: Option<VelocityHint>,
+
+        /// HTTPS URL of the payjoin endpoint, at which the wallet contributes
+        /// its own input to the transfer transaction
+        #[arg(long)]
+        payjoin: Option<String>,
+
+        /// Transport endpoint to which the consignment has to be delivered,
+        /// in `TRANSPORT[=PRIORITY[:TIMEOUT][:strict]]` format. Endpoints are
+        /// tried in the order of priorities; by default in the order they are
+        /// given. Delivery fails without trying further endpoints once a
+        /// `strict` one fails
+        #[arg(long = "endpoint", value_parser = parse_endpoint)]
+        endpoints: Vec<(RgbTransport, Option<TransportMeta>)>,
+
+        /// Number of seconds after which the invoice expires. Blinded seals
+        /// of the expired invoices are removed by the `gc` command
+        #[arg(long)]
+        expiry: Option<u32>,
+
+        /// Number of blocks after which the invoice expires, counted from the
+        /// current chain tip. Addresses of the witness-out invoices which have
+        /// expired unpaid are released for new invoices by the `gc` command
+        #[arg(long)]
+        expiry_height: Option<u32>,
+
         /// Token index for NFT transfer
         #[arg(long)]
         token_index: Option<TokenIndex>,
@@ -200,6 +586,37 @@ pub enum Command {
         /// Fraction of an NFT token to transfer
         #[arg(long, requires = "token_index")]
         token_fraction: Option<OwnedFraction>,
+
+        /// Print the invoice in the compact binary form as a QR code
+        #[arg(long)]
+        qr: bool,
+
+        /// Add proof that the beneficiary belongs to the wallet, signed with
+        /// keys derived from a BIP39 mnemonic or an extended private key
+        ///
+        /// The proof for a blinded seal reveals the seal UTXO to the payer.
+        #[arg(long, requires = "prove_key")]
+        prove: bool,
+
+        /// File containing BIP39 mnemonic phrase for the ownership proof
+        #[arg(long, group = "prove_key", requires = "prove")]
+        mnemonic: Option<PathBuf>,
+
+        /// BIP39 passphrase used together with the mnemonic
+        #[arg(long, default_value = "", requires = "mnemonic")]
+        passphrase: String,
+
+        /// Extended private key for the ownership proof, optionally prefixed
+        /// with key origin information for account-level keys
+        #[arg(long, group = "prove_key", requires = "prove")]
+        xpriv: Option<String>,
+    },
+
+    /// Check whether an invoice issued by this wallet was paid
+    #[display("invoice-status")]
+    InvoiceStatus {
+        /// Invoice data
+        invoice: RgbInvoice,
     },
 
     /// Prepare PSBT file for transferring RGB assets
@@ -216,6 +633,21 @@ pub enum Command {
         #[arg(long, default_value = "2000")]
         sats: Sats,
 
+        /// Absolute lock time of the witness transaction, either as a block
+        /// height or as a UNIX timestamp
+        #[arg(long)]
+        locktime: Option<u32>,
+
+        /// Sequence number for a specific transaction input, in
+        /// `OUTPOINT=SEQ` format. May be repeated
+        #[arg(long = "sequence", value_parser = parse_sequence)]
+        sequences: Vec<(Outpoint, SeqNo)>,
+
+        /// Ordering of the transaction outputs: `host-first`, `bip69` or
+        /// `shuffle[:SEED]`
+        #[arg(long, default_value = "host-first")]
+        ordering: OutputOrdering,
+
         /// Invoice data
         invoice: RgbInvoice,
 
@@ -242,6 +674,83 @@ pub enum Command {
         consignment: PathBuf,
     },
 
+    /// Print assets and bitcoins moved by a PSBT, allowing to audit it before
+    /// signing
+    #[display("preview")]
+    Preview {
+        /// Name of PSBT file to preview
+        psbt: PathBuf,
+
+        /// Print fungible amounts as integers in the smallest units, without
+        /// adjusting them for the contract precision
+        #[arg(long)]
+        raw: bool,
+    },
+
+    /// Export minimal PSBT with a human-readable summary of the RGB data for
+    /// signing with an external (hardware or air-gapped) signer
+    ///
+    /// The PSBT must have RGB data already committed with `consign` command.
+    #[display("sign-request")]
+    SignRequest {
+        /// Name of PSBT file containing committed transfer data
+        psbt: PathBuf,
+
+        /// Name of PSBT file to save the sign request to
+        request: PathBuf,
+    },
+
+    /// Combine signatures returned by an external signer with the original
+    /// PSBT, checking that the RGB commitment was not tampered with
+    #[display("combine")]
+    Combine {
+        /// Name of PSBT file containing committed transfer data, which is
+        /// updated in place
+        psbt: PathBuf,
+
+        /// Name of PSBT file signed by the external signer
+        signed: PathBuf,
+    },
+
+    /// Merge signatures of multiple cosigners into a PSBT with RGB data and
+    /// report which cosigners have signed it
+    ///
+    /// Fails if RGB data in any of the PSBT copies were stripped or altered by
+    /// the software of a cosigner.
+    #[display("cosign")]
+    Cosign {
+        /// Name of PSBT file containing committed transfer data, which is
+        /// updated in place
+        psbt: PathBuf,
+
+        /// Names of PSBT files signed by the cosigners
+        copies: Vec<PathBuf>,
+    },
+
+    /// Sign PSBT with keys derived from a BIP39 mnemonic or an extended
+    /// private key
+    ///
+    /// Intended for tests and simple setups, where keys are kept in plain
+    /// files. The PSBT is updated in place.
+    #[display("sign")]
+    Sign {
+        /// File containing BIP39 mnemonic phrase
+        #[arg(short, long, conflicts_with = "xpriv", required_unless_present = "xpriv")]
+        mnemonic: Option<PathBuf>,
+
+        /// BIP39 passphrase used together with the mnemonic
+        #[arg(long, default_value = "", requires = "mnemonic")]
+        passphrase: String,
+
+        /// Extended private key, optionally prefixed with key origin
+        /// information for account-level keys
+        #[arg(short = 'k', long)]
+        xpriv: Option<String>,
+
+        /// Name of PSBT file to sign
+        psbt: PathBuf,
+    },
+
     /// Transfer RGB assets
     #[display("transfer")]
     Transfer {
@@ -254,6 +763,53 @@ pub enum Command {
         #[arg(long, default_value = "2000")]
         sats: Sats,
 
+        /// Amount of tokens (in the smallest unit) to pay, if it differs from
+        /// the invoice amount. Must fit the range of amounts accepted by the
+        /// invoice
+        #[arg(long)]
+        amount: Option<u64>,
+
+        /// Absolute lock time of the witness transaction, either as a block
+        /// height or as a UNIX timestamp
+        #[arg(long)]
+        locktime: Option<u32>,
+
+        /// Sequence number for a specific transaction input, in
+        /// `OUTPOINT=SEQ` format. May be repeated
+        #[arg(long = "sequence", value_parser = parse_sequence)]
+        sequences: Vec<(Outpoint, SeqNo)>,
+
+        /// Ordering of the transaction outputs: `host-first`, `bip69` or
+        /// `shuffle[:SEED]`
+        #[arg(long, default_value = "host-first")]
+        ordering: OutputOrdering,
+
+        /// Prefer outputs which don't hold the state of other contracts and pay
+        /// the fee from outputs without RGB state, such that blank transitions
+        /// are created only when they can't be avoided
+        #[arg(long)]
+        isolated: bool,
+
+        /// Velocity class for the change of an assignment type of the paid
+        /// contract, in `TYPE=CLASS` format, overriding the contract hint and
+        /// the wallet preferences. Classes are `unspecified`, `seldom`,
+        /// `episodic`, `regular`, `frequent` and `high-frequency`. May be
+        /// repeated
+        #[arg(long = "velocity", value_parser = parse_velocity_arg)]
+        velocities: Vec<(AssignmentType, VelocityHint)>,
+
+        /// Method closing the seals created by the transfer: `opret` or
+        /// `tapret`, overriding the method of the wallet descriptor. By
+        /// default, tapret wallets use opret for the change sent to a
+        /// non-taproot output
+        #[arg(long, value_parser = parse_close_method)]
+        close_method: Option<CloseMethod>,
+
+        /// Pay the invoice even if the wallet has already paid it. By default,
+        /// repeated payments to the same blinded seal are refused
+        #[arg(long)]
+        allow_duplicate: bool,
+
         /// Invoice data
         invoice: RgbInvoice,
 
@@ -261,67 +817,849 @@ pub enum Command {
         #[arg(short, long, default_value = "400")]
         fee: Sats,
 
+        /// Feerate of the witness transaction, in satoshis per virtual byte,
+        /// accounting for the commitment outputs. Overrides the fixed fee
+        #[arg(long, conflicts_with = "dry_run")]
+        feerate: Option<u64>,
+
+        /// Only plan the transfer, reporting the state which will be spent,
+        /// the change and the estimated consignment size, without creating
+        /// PSBT or consignment
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Print fungible amounts of the dry-run plan as integers in the
+        /// smallest units, without adjusting them for the contract precision
+        #[arg(long, requires = "dry_run")]
+        raw: bool,
+
+        /// Pay with a payjoin transfer, to which the receiver contributes its
+        /// own input via the endpoint specified in the invoice. The resulting
+        /// transaction has to be signed by both payer and receiver
+        #[arg(long, conflicts_with = "dry_run")]
+        payjoin: bool,
+
+        /// Deliver the consignment to the transport endpoints of the invoice
+        #[arg(long, conflicts_with = "dry_run")]
+        deliver: bool,
+
+        /// Require the invoice to contain a valid proof that the beneficiary
+        /// belongs to the wallet which has issued the invoice
+        #[arg(long)]
+        verify_ownership: bool,
+
         /// File for generated transfer consignment
-        consignment: PathBuf,
+        #[arg(required_unless_present = "dry_run")]
+        consignment: Option<PathBuf>,
 
         /// Name of PSBT file to save. If not given, prints PSBT to STDOUT
         psbt: Option<PathBuf>,
     },
 
-    /// Inspects any RGB data file
-    #[display("inspect")]
-    Inspect {
-        /// RGB file to inspect
-        file: PathBuf,
+    /// Issue additional amount of a contract asset, spending the issuance
+    /// rights owned by the wallet
+    #[display("issue-more")]
+    IssueMore {
+        /// Encode PSBT as V2
+        #[arg(short = '2')]
+        v2: bool,
 
-        /// Path to save the dumped data. If not given, prints PSBT to STDOUT.
-        path: Option<PathBuf>,
+        /// Outpoint which should receive the issued amount. If not given, the
+        /// amount is assigned to the change output of the transaction
+        #[arg(long)]
+        seal: Option<Outpoint>,
 
-        /// Export using directory format for the compound bundles
-        #[clap(long, requires("path"))]
-        dir: bool,
-    },
+        /// Fee for bitcoin transaction, in satoshis
+        #[arg(short, long, default_value = "400")]
+        fee: Sats,
 
-    /// Reconstructs consignment from a YAML file
-    #[display("reconstruct")]
-    #[clap(hide = true)]
-    Reconstruct {
-        #[clap(long)]
-        contract: bool,
+        /// Contract identifier
+        contract_id: ContractId,
 
-        /// RGB file with the consignment YAML data
-        src: PathBuf,
+        /// Amount of tokens (in the smallest unit) to issue
+        amount: u64,
 
-        /// Path for the resulting consignment file. If not given, prints the
-        /// consignment to STDOUT.
-        dst: Option<PathBuf>,
+        /// Name of PSBT file to save. If not given, prints PSBT to STDOUT
+        psbt: Option<PathBuf>,
     },
 
-    /// Debug-dump all stash and inventory data
-    #[display("dump")]
-    Dump {
-        /// Directory to put the dump into
-        #[arg(default_value = "./rgb-dump")]
-        root_dir: String,
-    },
+    /// Burn contract allocations owned by the wallet
+    #[display("burn")]
+    Burn {
+        /// Encode PSBT as V2
+        #[arg(short = '2')]
+        v2: bool,
 
-    /// Validate transfer consignment
-    #[display("validate")]
-    Validate {
-        /// File with the transfer consignment
-        file: PathBuf,
-    },
+        /// Fee for bitcoin transaction, in satoshis
+        #[arg(short, long, default_value = "400")]
+        fee: Sats,
 
-    /// Validate transfer consignment & accept to the stash
-    #[display("accept")]
-    Accept {
-        /// Force accepting consignments with non-mined terminal witness
-        #[arg(short, long)]
-        force: bool,
+        /// Name of PSBT file to save. If not given, prints PSBT to STDOUT
+        #[arg(long)]
+        psbt: Option<PathBuf>,
 
-        /// File with the transfer consignment
-        file: PathBuf,
-    },
+        /// Contract identifier
+        contract_id: ContractId,
+
+        /// Allocations to burn, in form of `<opid>/<type>/<no>`
+        #[arg(required = true)]
+        allocations: Vec<Opout>,
+    },
+
+    /// Call a contract state transition described in a YAML file
+    #[display("call")]
+    Call {
+        /// Encode PSBT as V2
+        #[arg(short = '2')]
+        v2: bool,
+
+        /// Fee for bitcoin transaction, in satoshis
+        #[arg(short, long, default_value = "400")]
+        fee: Sats,
+
+        /// Contract identifier
+        contract_id: ContractId,
+
+        /// File containing YAML definition of the transition, its inputs and
+        /// the new state
+        call: PathBuf,
+
+        /// Name of PSBT file to save. If not given, prints PSBT to STDOUT
+        psbt: Option<PathBuf>,
+    },
+
+    /// Bump fee of an incoming witness transaction with a child transaction
+    /// spending its wallet output (CPFP). The RGB state assigned to the output
+    /// is moved to the change of the child
+    #[display("bump")]
+    Bump {
+        /// Encode PSBT as V2
+        #[arg(short = '2')]
+        v2: bool,
+
+        /// Feerate which the package of the parent and the child transactions
+        /// must reach, in satoshis per virtual byte
+        #[arg(short, long, default_value = "2")]
+        feerate: u64,
+
+        /// Wallet output of the witness transaction to spend
+        outpoint: Outpoint,
+
+        /// Name of PSBT file to save. If not given, prints PSBT to STDOUT
+        psbt: Option<PathBuf>,
+    },
+
+    /// Consolidate RGB state fragmented across many wallet outputs into a
+    /// single new wallet output
+    #[display("consolidate")]
+    Consolidate {
+        /// Encode PSBT as V2
+        #[arg(short = '2')]
+        v2: bool,
+
+        /// Feerate of the consolidation transaction, in satoshis per virtual
+        /// byte
+        #[arg(short, long, default_value = "2")]
+        feerate: u64,
+
+        /// Consolidate all wallet outputs holding the state of the contract
+        #[arg(long, conflicts_with = "utxos", required_unless_present = "utxos")]
+        contract: Option<ContractId>,
+
+        /// Consolidate the state of all contracts assigned to the wallet
+        /// output. May be repeated
+        #[arg(long = "utxo")]
+        utxos: Vec<Outpoint>,
+
+        /// Only report the outputs which will be spent and the resulting
+        /// state layout, without creating PSBT
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Name of PSBT file to save. If not given, prints PSBT to STDOUT
+        psbt: Option<PathBuf>,
+    },
+
+    /// Combine invoices for multiple contracts into a single basket invoice
+    #[display("basket-invoice")]
+    BasketInvoice {
+        /// Invoices for each of the basket legs
+        #[arg(required = true)]
+        invoices: Vec<RgbInvoice>,
+    },
+
+    /// Pay all legs of a basket invoice within a single transaction
+    #[display("basket-transfer")]
+    BasketTransfer {
+        /// Encode PSBT as V2
+        #[arg(short = '2')]
+        v2: bool,
+
+        /// Amount of satoshis which should be paid to each of the address-based
+        /// beneficiaries
+        #[arg(long, default_value = "2000")]
+        sats: Sats,
+
+        /// Basket invoice data
+        invoice: BasketInvoice,
+
+        /// Fee for bitcoin transaction, in satoshis
+        #[arg(short, long, default_value = "400")]
+        fee: Sats,
+
+        /// Directory to save generated transfer consignments, one per contract
+        consignments: PathBuf,
+
+        /// Name of PSBT file to save. If not given, prints PSBT to STDOUT
+        psbt: Option<PathBuf>,
+    },
+
+    /// Atomic swaps of RGB assets under different contracts
+    #[display("swap")]
+    #[clap(subcommand)]
+    Swap(SwapCommand),
+
+    /// Payjoin transfers, to which the receiver contributes its own input
+    #[display("payjoin")]
+    #[clap(subcommand)]
+    Payjoin(PayjoinCommand),
+
+    /// Backup and recovery of tapret tweaks, required to spend the outputs
+    /// hosting tapret commitments
+    #[display("tweaks")]
+    #[clap(subcommand)]
+    Tweaks(TweaksCommand),
+
+    /// Export of the wallet descriptor for other bitcoin wallets
+    #[display("descriptor")]
+    #[clap(subcommand)]
+    Descriptor(DescriptorCommand),
+
+    /// Trust policy for automatic acceptance of consignments
+    #[display("policy")]
+    #[clap(subcommand)]
+    Policy(PolicyCommand),
+
+    /// Labels of contracts, witness transactions and allocations, which are
+    /// kept private to the wallet
+    #[display("label")]
+    #[clap(subcommand)]
+    Label(LabelCommand),
+
+    /// Freeze an outpoint, such that neither its bitcoins nor the RGB state it
+    /// holds are spent by the payments
+    #[display("freeze")]
+    Freeze {
+        /// Outpoint to freeze
+        outpoint: Outpoint,
+
+        /// Reason to freeze the outpoint, like a pending swap or a compliance
+        /// hold
+        #[arg(long)]
+        reason: Option<String>,
+    },
+
+    /// Unfreeze an outpoint, allowing payments to spend it
+    #[display("unfreeze")]
+    Unfreeze {
+        /// Outpoint to unfreeze
+        outpoint: Outpoint,
+    },
+
+    /// List frozen outpoints together with the reasons they were frozen for
+    #[display("list-frozen")]
+    ListFrozen,
+
+    /// Outputs not derived from the wallet descriptor, which the payments may
+    /// spend together with the RGB state assigned to them
+    #[display("utxo")]
+    #[clap(subcommand)]
+    Utxo(UtxoCommand),
+
+    /// Registries of issuer kits configured for the wallet
+    #[display("registry")]
+    #[clap(subcommand)]
+    Registry(RegistryCommand),
+
+    /// Management of the wallets kept in the data directory
+    #[display("wallet")]
+    #[clap(subcommand)]
+    Wallet(WalletCommand),
+
+    /// Inspects any RGB data file
+    #[display("inspect")]
+    Inspect {
+        /// RGB file to inspect
+        file: PathBuf,
+
+        /// Path to save the dumped data. If not given, prints PSBT to STDOUT.
+        path: Option<PathBuf>,
+
+        /// Export using directory format for the compound bundles
+        #[clap(long, requires("path"))]
+        dir: bool,
+
+        /// Format of the dumped data. JSON and TOML represent map keys, unit
+        /// values and enum tags which they can't express in a portable form
+        #[clap(long, value_enum, default_value_t)]
+        format: InspectFormat,
+
+        /// Compare with another consignment for the same contract, reporting
+        /// added and removed bundles, differing terminals and witnesses
+        #[clap(long, conflicts_with_all = ["path", "dir"])]
+        diff: Option<PathBuf>,
+    },
+
+    /// Reconstructs consignment from a file produced by `inspect` command,
+    /// verifying that the consignment is reproduced exactly
+    #[display("reconstruct")]
+    #[clap(hide = true)]
+    Reconstruct {
+        #[clap(long)]
+        contract: bool,
+
+        /// Format of the consignment data. If not given, it is detected from
+        /// the file extension, defaulting to YAML
+        #[clap(long, value_enum)]
+        format: Option<InspectFormat>,
+
+        /// Id of the original consignment, which must match the id of the
+        /// reconstructed one
+        #[clap(long)]
+        expect: Option<ConsignmentId>,
+
+        /// File with the consignment data
+        src: PathBuf,
+
+        /// Path for the resulting consignment file. If not given, prints the
+        /// consignment to STDOUT.
+        dst: Option<PathBuf>,
+    },
+
+    /// Move the stock and wallets kept directly in the data directory, as it
+    /// was done before the data got nested under the network name, into the
+    /// directory of the network given with `--network`
+    #[display("migrate")]
+    Migrate,
+
+    /// Print completions of the command-line arguments for the shell, to be
+    /// loaded by its configuration
+    #[display("completions")]
+    Completions {
+        /// Shell to generate the completions for
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+
+    /// Run interactive session executing commands one by one, keeping the
+    /// stock loaded between them.
+    ///
+    /// Global arguments given before `shell` apply to each command of the
+    /// session. Type `exit` or press Ctrl-D to end the session.
+    #[display("shell")]
+    Shell,
+
+    /// Debug-dump all stash and inventory data
+    #[display("dump")]
+    Dump {
+        /// Directory to put the dump into
+        #[arg(default_value = "./rgb-dump")]
+        root_dir: String,
+    },
+
+    /// Move the contract history which is not required for the state owned
+    /// by the wallet out of the stock into an archive file
+    #[display("compact")]
+    Compact {
+        /// Archive file, which is created or extended with the archived
+        /// history
+        archive: PathBuf,
+    },
+
+    /// Remove blinded seals which can't receive any state anymore: the seals
+    /// of the expired invoices and the seals on the spent wallet outputs.
+    /// Requires the wallet to be synced. Addresses of the witness-out invoices
+    /// which have expired unpaid are released for use by new invoices
+    #[display("gc")]
+    Gc,
+
+    /// Verify that the outpoints of the genesis seals of the contracts issued
+    /// in the offline mode exist on chain
+    #[display("reconcile")]
+    Reconcile,
+
+    /// Restore the contract history from an archive file created by the
+    /// `compact` command
+    #[display("restore-archive")]
+    RestoreArchive {
+        /// Archive file
+        archive: PathBuf,
+    },
+
+    /// Reconstruct the stock from a directory with kits, contracts and
+    /// transfer consignments, replacing the existing stock. Files of the
+    /// replaced stock are kept as the stock backups
+    #[display("recover")]
+    Recover {
+        /// Directory with the backups of kits, contracts and consignments
+        dir: PathBuf,
+    },
+
+    /// Export or import the stock together with the auxiliary data of the
+    /// wallets, to move it to another machine or another stock backend
+    #[display("stock")]
+    #[clap(subcommand)]
+    Stock(StockCommand),
+
+    /// Validate transfer consignment
+    #[display("validate")]
+    Validate {
+        /// Print the full validation report, with the results of each bundle
+        /// and operation, in the given format
+        #[clap(long, value_enum)]
+        report: Option<InspectFormat>,
+
+        /// File with the transfer consignment
+        file: PathBuf,
+    },
+
+    /// Validate transfer consignment & accept to the stash
+    #[display("accept")]
+    Accept {
+        /// Force accepting consignments with non-mined terminal witness
+        #[arg(short, long)]
+        force: bool,
+
+        /// Accept consignment not trusted by the policy, for instance after
+        /// a manual review of a quarantined consignment
+        #[arg(long)]
+        trust: bool,
+
+        /// Accept consignment even if its contract ticker or name is already
+        /// used by a known contract
+        #[arg(long)]
+        allow_duplicate_ticker: bool,
+
+        /// Resume interrupted acceptance of the consignment, reusing the
+        /// witness transactions resolved before the interruption. Without
+        /// this flag the acceptance starts from scratch
+        #[arg(long)]
+        resume: bool,
+
+        /// Minimal interval between requests to the indexer, in milliseconds
+        #[arg(long, value_name = "MS")]
+        rate_limit: Option<u64>,
+
+        /// File with the transfer consignment
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+#[display(lowercase)]
+#[allow(clippy::large_enum_variant)]
+pub enum SwapCommand {
+    /// Propose atomic swap, paying an invoice of the counterparty in exchange
+    /// for the counterparty paying our invoice in the same transaction
+    #[display("propose")]
+    Propose {
+        /// Amount of satoshis which should be paid to the address-based
+        /// beneficiary
+        #[arg(long, default_value = "2000")]
+        sats: Sats,
+
+        /// Fee for bitcoin transaction, in satoshis
+        #[arg(short, long, default_value = "400")]
+        fee: Sats,
+
+        /// Invoice issued by the counterparty, which is paid by us
+        offer: RgbInvoice,
+
+        /// Our invoice, which has to be paid by the counterparty
+        request: RgbInvoice,
+
+        /// Name of PSBT file to save the swap proposal to
+        proposal: PathBuf,
+    },
+
+    /// Accept atomic swap proposal, paying the invoice requested by the
+    /// counterparty
+    #[display("accept")]
+    Accept {
+        /// Amount of satoshis which should be paid to the address-based
+        /// beneficiary
+        #[arg(long, default_value = "2000")]
+        sats: Sats,
+
+        /// Fee for bitcoin transaction, in satoshis
+        #[arg(short, long, default_value = "400")]
+        fee: Sats,
+
+        /// Name of PSBT file with the swap proposal, which is updated in place
+        proposal: PathBuf,
+
+        /// File for transfer consignment to the counterparty
+        consignment: PathBuf,
+    },
+
+    /// Finalize atomic swap accepted by the counterparty
+    #[display("finalize")]
+    Finalize {
+        /// Name of PSBT file with the accepted swap proposal
+        proposal: PathBuf,
+
+        /// File for transfer consignment to the counterparty
+        consignment: PathBuf,
+    },
+
+    /// Propose selling RGB assets for bitcoins, paying an invoice of the buyer
+    #[display("sell")]
+    Sell {
+        /// Amount of satoshis which should be paid to the address-based
+        /// beneficiary
+        #[arg(long, default_value = "2000")]
+        sats: Sats,
+
+        /// Fee for bitcoin transaction, in satoshis
+        #[arg(short, long, default_value = "400")]
+        fee: Sats,
+
+        /// Invoice issued by the buyer
+        invoice: RgbInvoice,
+
+        /// Price in satoshis which has to be paid by the buyer
+        price: Sats,
+
+        /// Name of PSBT file to save the sale proposal to
+        proposal: PathBuf,
+    },
+
+    /// Fund sale proposal with bitcoins, buying RGB assets
+    ///
+    /// The transaction must be signed only after the consignment received from
+    /// the seller is validated.
+    #[display("buy")]
+    Buy {
+        /// Fee for bitcoin transaction, in satoshis
+        #[arg(short, long, default_value = "400")]
+        fee: Sats,
+
+        /// Name of PSBT file with the sale proposal, which is updated in place
+        proposal: PathBuf,
+    },
+
+    /// Finalize sale proposal funded by the buyer
+    #[display("settle")]
+    Settle {
+        /// Name of PSBT file with the funded sale proposal
+        proposal: PathBuf,
+
+        /// File for transfer consignment to the buyer
+        consignment: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+#[display(lowercase)]
+#[allow(clippy::large_enum_variant)]
+pub enum PayjoinCommand {
+    /// Propose payjoin transfer, paying an invoice with a transaction to
+    /// which the receiver may contribute its own input
+    #[display("propose")]
+    Propose {
+        /// Amount of satoshis which should be paid to the address-based
+        /// beneficiary
+        #[arg(long, default_value = "2000")]
+        sats: Sats,
+
+        /// Fee for bitcoin transaction, in satoshis
+        #[arg(short, long, default_value = "400")]
+        fee: Sats,
+
+        /// Invoice specifying the payjoin endpoint
+        invoice: RgbInvoice,
+
+        /// Name of PSBT file to save the payjoin proposal to
+        proposal: PathBuf,
+    },
+
+    /// Contribute an input to a payjoin proposal paying our invoice
+    ///
+    /// The transaction must be signed only after the consignment received from
+    /// the payer is validated.
+    #[display("contribute")]
+    Contribute {
+        /// Name of PSBT file with the payjoin proposal
+        proposal: PathBuf,
+
+        /// Name of PSBT file to save the extended proposal to. If not given,
+        /// the proposal is updated in place
+        output: Option<PathBuf>,
+    },
+
+    /// Finalize payjoin transfer extended by the receiver, committing to the
+    /// RGB data
+    #[display("finalize")]
+    Finalize {
+        /// Name of PSBT file with the original payjoin proposal
+        proposal: PathBuf,
+
+        /// Name of PSBT file with the proposal extended by the receiver, which
+        /// is updated in place
+        payjoin: PathBuf,
+
+        /// File for transfer consignment to the receiver
+        consignment: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+pub enum TweaksCommand {
+    /// Export all tapret tweaks known to the wallet
+    #[display("export")]
+    Export {
+        /// File to save the tweaks to. If not given, prints the tweaks to
+        /// STDOUT
+        file: Option<PathBuf>,
+    },
+
+    /// Import tapret tweaks from a backup file
+    #[display("import")]
+    Import {
+        /// File with the tweaks backup
+        file: PathBuf,
+    },
+
+    /// Recover tapret tweaks missing from the wallet using the tapret
+    /// commitments from the accepted consignments
+    #[display("recover")]
+    Recover,
+}
+
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+pub enum StockCommand {
+    /// Export the stock with the tapret tweaks of all wallets and the
+    /// auxiliary files of the data directory into a single portable file
+    #[display("export-all")]
+    ExportAll {
+        /// Export the stock kept in the SQLite database instead of the one in
+        /// the data directory
+        #[clap(long)]
+        sqlite: Option<PathBuf>,
+
+        /// File to save the export to
+        file: PathBuf,
+    },
+
+    /// Import the file produced by `export-all`. Refuses to replace an
+    /// existing stock and auxiliary files unless `--force` is given; files of
+    /// the replaced stock are kept as the stock backups
+    #[display("import-all")]
+    ImportAll {
+        /// Import the stock into the SQLite database instead of the data
+        /// directory
+        #[clap(long)]
+        sqlite: Option<PathBuf>,
+
+        /// Replace the existing stock and auxiliary files
+        #[arg(short, long)]
+        force: bool,
+
+        /// File with the stock export
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+pub enum DescriptorCommand {
+    /// Export the wallet descriptor as standard output descriptors, such that
+    /// a watch-only wallet can track the same addresses
+    #[display("export")]
+    Export {
+        /// Wallet software to export the descriptor for
+        #[clap(long, value_enum, default_value_t)]
+        format: DescriptorFormat,
+
+        /// Unix timestamp from which Bitcoin Core rescans the blockchain for
+        /// the wallet transactions; zero rescans the whole chain
+        #[clap(long, default_value = "0")]
+        timestamp: u64,
+    },
+}
+
+/// Format of the exported standard output descriptors.
+#[derive(ValueEnum, Copy, Clone, PartialEq, Eq, Debug, Default, Display)]
+#[display(lowercase)]
+pub enum DescriptorFormat {
+    /// Bitcoin Core `importdescriptors` request, covering all the keychains
+    /// and the tapret-tweaked outputs
+    #[default]
+    Core,
+
+    /// Multipath descriptor for Sparrow, covering only the external and the
+    /// internal keychains
+    Sparrow,
+}
+
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+pub enum PolicyCommand {
+    /// Whitelist contract, schema or issuer for automatic acceptance
+    #[display("add")]
+    Add {
+        /// Policy rule in form of `contract:<id>`, `schema:<id>` or
+        /// `issuer:<identity>`
+        rule: PolicyRule,
+    },
+
+    /// Remove rule from the policy
+    #[display("remove")]
+    Remove {
+        /// Policy rule in form of `contract:<id>`, `schema:<id>` or
+        /// `issuer:<identity>`
+        rule: PolicyRule,
+    },
+
+    /// List rules of the policy
+    #[display("list")]
+    List,
+
+    /// List consignments quarantined pending manual review
+    #[display("quarantine")]
+    Quarantine,
+}
+
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+pub enum LabelCommand {
+    /// Assign label to a contract, witness transaction or allocation,
+    /// replacing the existing one
+    #[display("set")]
+    Set {
+        /// Label target in form of `contract:<id>`, `witness:<txid>` or
+        /// `allocation:<opid>/<type>/<no>`
+        target: LabelTarget,
+
+        /// Label text
+        label: String,
+    },
+
+    /// Remove label from a target
+    #[display("remove")]
+    Remove {
+        /// Label target in form of `contract:<id>`, `witness:<txid>` or
+        /// `allocation:<opid>/<type>/<no>`
+        target: LabelTarget,
+    },
+
+    /// List all labels
+    #[display("list")]
+    List,
+}
+
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+pub enum UtxoCommand {
+    /// Import an output controlled outside of the wallet, replacing the
+    /// previously imported one. The inputs spending it are marked as external
+    /// and must be signed by the output owner
+    #[display("import")]
+    Import {
+        /// Outpoint to import
+        outpoint: Outpoint,
+
+        /// Amount of bitcoins held by the output, in sats
+        value: Sats,
+
+        /// Hex-encoded script pubkey of the output
+        script: String,
+
+        /// Hex-encoded redeem script, required for P2SH outputs
+        #[arg(long)]
+        redeem_script: Option<String>,
+
+        /// Hex-encoded witness script, required for P2WSH outputs
+        #[arg(long)]
+        witness_script: Option<String>,
+    },
+
+    /// Remove the imported output
+    #[display("remove")]
+    Remove {
+        /// Outpoint to remove
+        outpoint: Outpoint,
+    },
+
+    /// List all imported outputs
+    #[display("list")]
+    List,
+}
+
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+#[display(lowercase)]
+pub enum RegistryCommand {
+    /// Fetch kits from the configured registries and import the ones
+    /// providing schemata unknown to the stock
+    Update,
+
+    /// List configured registries
+    List,
+}
+
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+pub enum WalletCommand {
+    /// Print wallet descriptor, network, balance and other wallet details
+    #[display("info")]
+    Info {
+        /// Wallet name; defaults to the wallet given with `--wallet` or to the
+        /// default wallet
+        wallet: Option<String>,
+    },
+
+    /// Rename the wallet, updating the default wallet name if required
+    #[display("rename")]
+    Rename {
+        /// Current wallet name
+        from: String,
+
+        /// New wallet name
+        to: String,
+    },
+
+    /// Delete the wallet with all its data
+    #[display("delete")]
+    Delete {
+        /// Name of the wallet to delete
+        wallet: String,
+
+        /// Do not ask for a confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+
+        /// Directory to export wallet descriptor and tapret tweaks into before
+        /// deleting the wallet
+        #[arg(long)]
+        export: Option<PathBuf>,
+    },
+
+    /// Encrypt the wallet descriptor, tapret tweaks and UTXO set with a
+    /// passphrase
+    ///
+    /// The passphrase is read from the RGB_WALLET_PASSPHRASE environment
+    /// variable or prompted for. The stock and the files shared by all
+    /// wallets (labels, frozen and imported outpoints, velocity preferences
+    /// and the resolver cache) stay in plaintext.
+    #[display("encrypt")]
+    Encrypt {
+        /// Wallet name; defaults to the wallet given with `--wallet` or to the
+        /// default wallet
+        wallet: Option<String>,
+    },
+
+    /// Decrypt the wallet data, keeping them in plaintext afterwards
+    #[display("decrypt")]
+    Decrypt {
+        /// Wallet name; defaults to the wallet given with `--wallet` or to the
+        /// default wallet
+        wallet: Option<String>,
+    },
 }
 
 #[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
@@ -332,11 +1670,65 @@ pub enum DebugCommand {
     Taprets,
 }
 
+/// Text format used to present the consignment data.
+#[derive(ValueEnum, Copy, Clone, PartialEq, Eq, Debug, Default, Display)]
+#[display(lowercase)]
+pub enum InspectFormat {
+    /// YAML, which represents all the consignment data directly
+    #[default]
+    Yaml,
+
+    /// JSON, using the portable form for the data it can't represent
+    Json,
+
+    /// TOML, using the portable form for the data it can't represent
+    Toml,
+}
+
+impl InspectFormat {
+    /// Detects format from the file extension, defaulting to YAML.
+    pub fn with_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => InspectFormat::Json,
+            Some("toml") => InspectFormat::Toml,
+            _ => InspectFormat::Yaml,
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    pub fn serialize(self, value: &impl Serialize) -> Result<String, WalletError> {
+        let value = match self {
+            InspectFormat::Yaml => return Ok(serde_yaml::to_string(value)?),
+            _ => to_portable(serde_yaml::to_value(value)?),
+        };
+        match self {
+            InspectFormat::Yaml => unreachable!(),
+            InspectFormat::Json => serde_json::to_string_pretty(&value)
+                .map_err(|err| WalletError::Format("JSON", err.to_string())),
+            InspectFormat::Toml => toml::to_string_pretty(&value)
+                .map_err(|err| WalletError::Format("TOML", err.to_string())),
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    pub fn deserialize<T: DeserializeOwned>(self, data: &str) -> Result<T, WalletError> {
+        let value = match self {
+            InspectFormat::Yaml => return Ok(serde_yaml::from_str(data)?),
+            InspectFormat::Json => serde_json::from_str(data)
+                .map_err(|err| WalletError::Format("JSON", err.to_string()))?,
+            InspectFormat::Toml => {
+                toml::from_str(data).map_err(|err| WalletError::Format("TOML", err.to_string()))?
+            }
+        };
+        Ok(serde_yaml::from_value(from_portable(value)?)?)
+    }
+}
+
 impl Exec for RgbArgs {
     type Error = WalletError;
     const CONF_FILE_NAME: &'static str = "rgb.toml";
 
-    fn exec(self, config: Config, _name: &'static str) -> Result<(), WalletError> {
+    fn exec(self, mut config: Config, _name: &'static str) -> Result<(), WalletError> {
         match &self.command {
             Command::General(cmd) => {
                 self.inner.translate(cmd).exec(config, "rgb")?;
@@ -356,6 +1748,263 @@ impl Exec for RgbArgs {
                     println!("{witness_id}\t{tapret}");
                 }
             }
+            Command::Tweaks(TweaksCommand::Export { file }) => {
+                let wallet = self.rgb_wallet(&config)?;
+                let tweaks = wallet.tapret_tweaks();
+                match file {
+                    Some(file) => fs::write(file, tweaks.to_string())?,
+                    None => print!("{tweaks}"),
+                }
+            }
+            Command::Descriptor(DescriptorCommand::Export { format, timestamp }) => {
+                let wallet = self.rgb_wallet(&config)?;
+                let descriptor = wallet.wallet().descriptor();
+                match format {
+                    DescriptorFormat::Core => {
+                        let descriptors = descriptor.core_descriptors(*timestamp);
+                        let s = serde_json::to_string_pretty(&descriptors)
+                            .expect("descriptors are always serializable");
+                        println!("{s}");
+                    }
+                    DescriptorFormat::Sparrow => {
+                        println!("{}", descriptor.multipath_descriptor());
+                        let tapret = descriptor.tapret_descriptors();
+                        if !tapret.is_empty() {
+                            eprintln!(
+                                "Warning: {} outputs with tapret commitments can't be tracked by                                  Sparrow",
+                                tapret.len()
+                            );
+                        }
+                    }
+                }
+            }
+            Command::Tweaks(TweaksCommand::Import { file }) => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let tweaks = TapretTweaks::from_str(&fs::read_to_string(file)?)?;
+                let added = wallet.import_tapret_tweaks(&tweaks)?;
+                eprintln!("{} tapret tweaks were imported", added.len());
+            }
+            Command::Policy(cmd) => {
+                let path = self.general.base_dir().join(POLICY_FILE);
+                let mut policy = TrustPolicy::load_file(&path)?;
+                match cmd {
+                    PolicyCommand::Add { rule } => {
+                        if !policy.add(rule.clone()) {
+                            eprintln!("Rule {rule} is already present in the policy");
+                        }
+                        policy.save_file(&path)?;
+                    }
+                    PolicyCommand::Remove { rule } => {
+                        if !policy.remove(rule) {
+                            eprintln!("Rule {rule} is not present in the policy");
+                        }
+                        policy.save_file(&path)?;
+                    }
+                    PolicyCommand::List => {
+                        if policy.is_empty() {
+                            eprintln!("Policy has no rules; all valid consignments are accepted");
+                        }
+                        for rule in policy.rules() {
+                            println!("{rule}");
+                        }
+                    }
+                    PolicyCommand::Quarantine => {
+                        let quarantine =
+                            Quarantine::new(self.general.base_dir().join(QUARANTINE_DIR));
+                        for file in quarantine.list()? {
+                            let transfer = Transfer::load_file(&file)?;
+                            println!(
+                                "{}\t{}\t{}",
+                                file.display(),
+                                transfer.contract_id(),
+                                transfer.genesis().issuer
+                            );
+                        }
+                    }
+                }
+            }
+            Command::Registry(RegistryCommand::Update) => {
+                let (registries, fetcher) = self.kit_registries()?;
+                if registries.is_empty() {
+                    eprintln!("No kit registries are configured");
+                }
+                let mut stock = self.rgb_stock()?;
+                let imported = update_kits(&mut stock, &registries, &fetcher)?;
+                for kit_id in &imported {
+                    eprintln!("Kit {kit_id} is imported");
+                }
+                eprintln!("{} kits were imported", imported.len());
+            }
+            Command::Registry(RegistryCommand::List) => {
+                let (registries, _) = self.kit_registries()?;
+                for registry in registries {
+                    println!("{}\t{}", registry.url(), registry.pin().unwrap_or("-"));
+                }
+            }
+            Command::Wallet(cmd) => {
+                let wallets = WalletDir::new(self.general.base_dir());
+                match cmd {
+                    WalletCommand::Info { wallet } => {
+                        let name = self.wallet_name(&config, wallet.as_ref());
+                        let info = wallets.info(&name)?;
+                        println!("Name:           {}", info.name);
+                        println!("Path:           {}", info.path.display());
+                        println!("Descriptor:     {}", info.descriptor);
+                        println!("Network:        {}", info.network);
+                        println!("Close method:   {}", info.close_method);
+                        println!("Keychains:      {}", info.keychain_layout);
+                        println!("Balance:        {} sats", info.balance);
+                        println!("UTXOs:          {}", info.utxos);
+                        println!("Transactions:   {}", info.transactions);
+                        println!("Tapret tweaks:  {}", info.tapret_tweaks);
+                    }
+                    WalletCommand::Rename { from, to } => {
+                        wallets.rename(from, to)?;
+                        eprintln!("Wallet {from} is renamed into {to}");
+                        if &config.default_wallet == from {
+                            self.set_default_wallet(&mut config, to)?;
+                            eprintln!("Default wallet is now {to}");
+                        }
+                    }
+                    WalletCommand::Delete {
+                        wallet: name,
+                        yes,
+                        export,
+                    } => {
+                        if !wallets.exists(name) {
+                            return Err(WalletDirError::NotFound(name.clone()).into());
+                        }
+                        if !yes {
+                            eprint!("Type the wallet name to confirm deletion of {name}: ");
+                            let mut answer = String::new();
+                            io::stdin().read_line(&mut answer)?;
+                            if answer.trim() != name {
+                                eprintln!("Wallet name doesn't match; deletion is cancelled");
+                                return Ok(());
+                            }
+                        }
+                        if let Some(dir) = export {
+                            for file in wallets.export(name, dir)? {
+                                eprintln!("Exported {}", file.display());
+                            }
+                        }
+                        wallets.delete(name)?;
+                        eprintln!("Wallet {name} is deleted");
+                        if &config.default_wallet == name {
+                            eprintln!(
+                                "Warning: the deleted wallet was the default one; use `rgb \
+                                 default` to choose another default wallet"
+                            );
+                        }
+                    }
+                    WalletCommand::Encrypt { wallet } => {
+                        let name = self.wallet_name(&config, wallet.as_ref());
+                        let passphrase = read_new_passphrase()?;
+                        wallets.encrypt(&name, passphrase)?;
+                        eprintln!("Wallet {name} is encrypted");
+                    }
+                    WalletCommand::Decrypt { wallet } => {
+                        let name = self.wallet_name(&config, wallet.as_ref());
+                        let passphrase = read_passphrase(&name)?;
+                        wallets.decrypt(&name, passphrase)?;
+                        eprintln!("Wallet {name} is decrypted");
+                    }
+                }
+            }
+            Command::Freeze { outpoint, reason } => {
+                let path = self.general.base_dir().join(FROZEN_FILE);
+                let mut frozen = FrozenOutpoints::load_file(&path)?;
+                if let Some(old) = frozen.freeze(*outpoint, reason.clone().unwrap_or_default()) {
+                    eprintln!("Outpoint {outpoint} was already frozen (reason: '{old}')");
+                }
+                frozen.save_file(&path)?;
+            }
+            Command::Unfreeze { outpoint } => {
+                let path = self.general.base_dir().join(FROZEN_FILE);
+                let mut frozen = FrozenOutpoints::load_file(&path)?;
+                if frozen.unfreeze(*outpoint).is_none() {
+                    eprintln!("Outpoint {outpoint} is not frozen");
+                }
+                frozen.save_file(&path)?;
+            }
+            Command::ListFrozen => {
+                let frozen = FrozenOutpoints::load_file(self.general.base_dir().join(FROZEN_FILE))?;
+                for (outpoint, reason) in frozen.iter() {
+                    println!("{outpoint}\t{reason}");
+                }
+            }
+            Command::Utxo(cmd) => {
+                let path = self.general.base_dir().join(IMPORTED_FILE);
+                let mut imported = ImportedUtxos::load_file(&path)?;
+                match cmd {
+                    UtxoCommand::Import {
+                        outpoint,
+                        value,
+                        script,
+                        redeem_script,
+                        witness_script,
+                    } => {
+                        let redeem_info = RedeemInfo {
+                            redeem_script: redeem_script
+                                .as_deref()
+                                .map(parse_script)
+                                .transpose()?
+                                .map(RedeemScript::from_unsafe),
+                            witness_script: witness_script
+                                .as_deref()
+                                .map(parse_script)
+                                .transpose()?
+                                .map(WitnessScript::from_unsafe),
+                        };
+                        let script = ScriptPubkey::from_unsafe(parse_script(script)?);
+                        let utxo = ImportedUtxo::new(*outpoint, *value, script, redeem_info);
+                        if imported.import(utxo).is_some() {
+                            eprintln!("Output {outpoint} was already imported and is replaced");
+                        }
+                        imported.save_file(&path)?;
+                    }
+                    UtxoCommand::Remove { outpoint } => {
+                        if imported.remove(*outpoint).is_none() {
+                            eprintln!("Output {outpoint} is not imported");
+                        }
+                        imported.save_file(&path)?;
+                    }
+                    UtxoCommand::List => {
+                        for utxo in imported.iter() {
+                            println!("{}\t{}\t{}", utxo.outpoint, utxo.value, utxo.script.to_hex());
+                        }
+                    }
+                }
+            }
+            Command::Label(cmd) => {
+                let path = self.general.base_dir().join(LABELS_FILE);
+                let mut labels = WalletLabels::load_file(&path)?;
+                match cmd {
+                    LabelCommand::Set { target, label } => {
+                        if let Some(old) = labels.set(*target, label) {
+                            eprintln!("Label '{old}' of {target} is replaced");
+                        }
+                        labels.save_file(&path)?;
+                    }
+                    LabelCommand::Remove { target } => {
+                        if labels.remove(target).is_none() {
+                            eprintln!("{target} has no label");
+                        }
+                        labels.save_file(&path)?;
+                    }
+                    LabelCommand::List => {
+                        for (target, label) in labels.iter() {
+                            println!("{target}\t{label}");
+                        }
+                    }
+                }
+            }
+            Command::Tweaks(TweaksCommand::Recover) => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let recovered = wallet.recover_tapret_tweaks()?;
+                print!("{recovered}");
+                eprintln!("{} tapret tweaks were recovered", recovered.len());
+            }
             Command::Schemata => {
                 let stock = self.rgb_stock()?;
                 for info in stock.schemata()? {
@@ -368,17 +2017,47 @@ impl Exec for RgbArgs {
                     print!("{info}");
                 }
             }
+            Command::Schema { json, schema_id } => {
+                let stock = self.rgb_stock()?;
+                let description = SchemaDescription::with_stock(&stock, *schema_id)?;
+                if *json {
+                    let s = serde_json::to_string_pretty(&description)
+                        .expect("unable to present as JSON");
+                    println!("{s}");
+                } else {
+                    print!("{description}");
+                }
+            }
             Command::Contracts => {
                 let stock = self.rgb_stock()?;
                 for info in stock.contracts()? {
                     print!("{info}");
+                    let status = info.issuer_verified(&stock, &Bip340Verifier)?;
+                    println!("  Identity: {status}");
+                    if status == IssuerStatus::Invalid {
+                        eprintln!(
+                            "Warning: contract {} has invalid issuer signature and may spoof \
+                             identity of '{}'",
+                            info.id, info.issuer
+                        );
+                    }
                 }
             }
+            Command::SignIssuer { key, contract_id } => {
+                let mut stock = self.rgb_stock()?;
+                let keypair = Keypair::from_seckey_str_global(fs::read_to_string(key)?.trim())
+                    .map_err(|_| WalletError::IssuerKey)?;
+                stock.sign_issuer(*contract_id, &keypair)?;
+                eprintln!("Issuer signature for contract {contract_id} is stored in the stash");
+            }
 
             Command::History {
                 contract_id,
                 iface,
                 details,
+                csv,
+                grouped,
+                raw,
             } => {
                 let wallet = self.rgb_wallet(&config)?;
                 let iface = match contract_default_iface_name(*contract_id, wallet.stock(), iface)?
@@ -386,6 +2065,43 @@ impl Exec for RgbArgs {
                     ControlFlow::Continue(name) => name,
                     ControlFlow::Break(_) => return Ok(()),
                 };
+                if let Some(file) = csv {
+                    let mut exporter = wallet.history_exporter(*contract_id, iface)?;
+                    exporter.set_raw(*raw);
+                    let mut fd = File::create(file)?;
+                    exporter.write_csv(&mut fd)?;
+                    eprintln!(
+                        "{} operations were exported to {}",
+                        exporter.operations().len(),
+                        file.display()
+                    );
+                    return Ok(());
+                }
+                let mut amounts = wallet.amount_formatter(*contract_id)?;
+                amounts.set_raw(*raw);
+                let labels = WalletLabels::load_file(self.general.base_dir().join(LABELS_FILE))?;
+                if *grouped {
+                    println!("Role       \tSent     \tReceived \tWitness");
+                    for record in wallet.transfer_history(*contract_id, iface)? {
+                        println!(
+                            "{:11}\t{: >9}\t{: >9}\t{}{}",
+                            record.role.to_string(),
+                            amounts.format(record.sent.value()),
+                            amounts.format(record.received.value()),
+                            record
+                                .witness
+                                .zip(record.ord)
+                                .map(|(id, ord)| format!("{id} ({ord})"))
+                                .unwrap_or_else(|| s!("~")),
+                            record
+                                .witness
+                                .and_then(|id| labels.witness(*id.as_reduced_unsafe()))
+                                .map(|label| format!("\t{label}"))
+                                .unwrap_or_default()
+                        );
+                    }
+                    return Ok(());
+                }
                 let mut history = wallet.history(*contract_id, iface)?;
                 history.sort_by_key(|op| op.witness.map(|w| w.ord).unwrap_or(WitnessOrd::Archived));
                 if *details {
@@ -403,39 +2119,135 @@ impl Exec for RgbArgs {
                 } in history
                 {
                     print!("{:9}\t", direction.to_string());
-                    if let AllocatedState::Amount(amount) = state {
-                        print!("{: >9}", amount.value());
-                    } else {
-                        print!("{state:>9}");
-                    }
+                    print!("{: >9}", amounts.format_state(&state));
                     if *details {
                         print!("\t{ty}");
                     }
-                    println!(
-                        "\t{}\t{}",
-                        to.first().expect("at least one receiver is always present"),
-                        witness
-                            .map(|info| format!("{} ({})", info.id, info.ord))
-                            .unwrap_or_else(|| s!("~"))
-                    );
-                    if *details {
+                    println!(
+                        "\t{}\t{}{}",
+                        to.first().expect("at least one receiver is always present"),
+                        witness
+                            .map(|info| format!("{} ({})", info.id, info.ord))
+                            .unwrap_or_else(|| s!("~")),
+                        witness
+                            .and_then(|info| labels.witness(*info.id.as_reduced_unsafe()))
+                            .map(|label| format!("\t{label}"))
+                            .unwrap_or_default()
+                    );
+                    if *details {
+                        println!(
+                            "\topid={}",
+                            opids
+                                .iter()
+                                .map(OpId::to_string)
+                                .collect::<Vec<_>>()
+                                .join("\n\topid=")
+                        )
+                    }
+                }
+            }
+
+            Command::Explore {
+                dot,
+                format,
+                contract_id,
+            } => {
+                let wallet = self.rgb_wallet(&config)?;
+                let graph = ContractGraph::build(wallet.stock(), *contract_id)?;
+                if *dot {
+                    print!("{}", graph.to_dot());
+                } else {
+                    println!("{}", format.serialize(&graph)?);
+                }
+            }
+
+            Command::Prove {
+                contract_id,
+                outpoint,
+                file,
+            } => {
+                let wallet = self.rgb_wallet(&config)?;
+                let proof = AllocationProof::build(wallet.stock(), *contract_id, *outpoint)?;
+                save_content(proof.consignment(), file)?;
+            }
+
+            Command::VerifyProof {
+                contract_id,
+                outpoint,
+                file,
+            } => {
+                let proof = AllocationProof::from(load_content::<Transfer>(file)?);
+                let mut resolver = self.resolver_guarded(
+                    self.network_guard()
+                        .check_genesis(&proof.consignment().genesis),
+                )?;
+                let allocations = proof.verify(
+                    *contract_id,
+                    *outpoint,
+                    &mut resolver,
+                    self.general.network.is_testnet(),
+                )?;
+                println!("Allocations of {contract_id} at {outpoint}:");
+                for allocation in allocations {
+                    let witness = allocation
+                        .witness
+                        .map(|id| id.to_string())
+                        .unwrap_or_else(|| s!("genesis"));
+                    match allocation.amount {
+                        Some(amount) => {
+                            println!("\t{}\t{amount}\twitness={witness}", allocation.opout)
+                        }
+                        None => println!("\t{}\twitness={witness}", allocation.opout),
+                    }
+                }
+            }
+
+            Command::Balance {
+                contract_id,
+                confirmations,
+                raw,
+            } => {
+                let wallet = self.rgb_wallet(&config)?;
+                let resolver = self.resolver()?;
+                let tip_height = resolver
+                    .resolve_tip_height()
+                    .map_err(WalletError::Resolver)?;
+                let report = wallet.balance(*contract_id, tip_height, *confirmations)?;
+                let mut amounts = wallet.amount_formatter(*contract_id)?;
+                amounts.set_raw(*raw);
+                println!("Confirmed:           \t{}", amounts.format(report.confirmed.value()));
+                println!("Immature:            \t{}", amounts.format(report.immature.value()));
+                println!("Tentative:           \t{}", amounts.format(report.tentative.value()));
+                println!(
+                    "Unconfirmed incoming:\t{}",
+                    amounts.format(report.unconfirmed_incoming.value())
+                );
+                println!("Mined total:         \t{}", amounts.format(report.mined().value()));
+                println!("Pending total:       \t{}", amounts.format(report.pending().value()));
+
+                let stale = wallet.check_witnesses(*contract_id, &resolver)?;
+                if !stale.is_empty() {
+                    println!("\nStale allocations:");
+                    for allocation in stale {
+                        let state = allocation
+                            .amount
+                            .map(|amount| amounts.format(amount.value()))
+                            .unwrap_or_else(|| s!("~"));
                         println!(
-                            "\topid={}",
-                            opids
-                                .iter()
-                                .map(OpId::to_string)
-                                .collect::<Vec<_>>()
-                                .join("\n\topid=")
-                        )
+                            "  {: >9}\t{}\t{} ({})",
+                            state, allocation.seal, allocation.witness, allocation.reason
+                        );
                     }
                 }
             }
 
-            Command::Import { armored, file } => {
+            Command::Import {
+                armored: _,
+                allow_duplicate_ticker,
+                file,
+            } => {
                 let mut stock = self.rgb_stock()?;
-                assert!(!armored, "importing armored files is not yet supported");
-                // TODO: Support armored files
-                let content = UniversalFile::load_file(file)?;
+                let content = load_universal(file)?;
                 match content {
                     UniversalFile::Kit(kit) => {
                         let id = kit.kit_id();
@@ -467,7 +2279,7 @@ impl Exec for RgbArgs {
                             eprintln!("- script library {}", lib.id());
                         }
                         eprintln!("- strict types: {} definitions", kit.types.len());
-                        let kit = kit.validate().map_err(|(status, _)| status.to_string())?;
+                        let kit = kit.validate().map_err(|(status, _)| status)?;
                         stock.import_kit(kit)?;
                         eprintln!("Kit is imported");
                     }
@@ -480,39 +2292,53 @@ impl Exec for RgbArgs {
                             .validate(&resolver, self.general.network.is_testnet())
                             .map_err(|(status, _)| {
                                 eprintln!("failure");
-                                status.to_string()
+                                status
                             })?;
                         eprintln!("success");
-                        stock.import_contract(contract, &resolver)?;
+                        let (_, collisions) = stock.import_contract_checked(
+                            contract,
+                            &resolver,
+                            *allow_duplicate_ticker,
+                        )?;
+                        warn_collisions(&collisions);
                         eprintln!("Consignment is imported");
                     }
                     UniversalFile::Transfer(_) => {
-                        return Err(s!("use `validate` and `accept` commands to work with \
-                                       transfer consignments")
-                        .into());
+                        return Err(WalletError::UnexpectedTransfer);
                     }
                 }
             }
             Command::Export {
-                armored: _,
+                armored,
                 contract,
                 file,
             } => {
                 let stock = self.rgb_stock()?;
-                let contract = stock
-                    .export_contract(*contract)
-                    .map_err(|err| err.to_string())?;
-                if let Some(file) = file {
-                    // TODO: handle armored flag
-                    contract.save_file(file)?;
-                    eprintln!("Contract {contract} exported to '{}'", file.display());
-                } else {
-                    println!("{contract}");
+                let deferred = DeferredValidation::load_file(
+                    self.general.base_dir().join(DEFERRED_VALIDATION_FILE),
+                )?;
+                if deferred.contains(*contract) {
+                    eprintln!(
+                        "Warning: contract {contract} was issued offline and its genesis seals \
+                         are not verified yet; use `reconcile` command to verify them"
+                    );
+                }
+                let contract = stock.export_contract(*contract)?;
+                match file {
+                    Some(file) if !is_stdio(file) => {
+                        if *armored {
+                            fs::write(file, contract.to_string())?;
+                        } else {
+                            contract.save_file(file)?;
+                        }
+                        eprintln!("Contract {contract} exported to '{}'", file.display());
+                    }
+                    _ => println!("{contract}"),
                 }
             }
 
             Command::Armor { file } => {
-                let content = UniversalFile::load_file(file)?;
+                let content = load_universal(file)?;
                 println!("{content}");
             }
 
@@ -520,9 +2346,27 @@ impl Exec for RgbArgs {
                 contract_id,
                 iface,
                 all,
+                raw,
+                outpoints,
+                min,
+                max,
+                witness: statuses,
+                depth,
+                min_confirmations,
+                offset,
+                limit,
             } => {
-                let stock_path = self.general.base_dir();
-                let stock = self.load_stock(stock_path)?;
+                let stock = self.load_stock()?;
+                let tip_height = if *depth || min_confirmations.is_some() {
+                    let resolver = self.resolver()?;
+                    Some(
+                        resolver
+                            .resolve_tip_height()
+                            .map_err(WalletError::Resolver)?,
+                    )
+                } else {
+                    None
+                };
 
                 enum StockOrWallet {
                     Stock(Stock),
@@ -560,6 +2404,15 @@ impl Exec for RgbArgs {
                     .stock()
                     .contract_iface(*contract_id, tn!(iface.to_owned()))?;
 
+                let status = contract
+                    .info
+                    .issuer_verified(stock_wallet.stock(), &Bip340Verifier)?;
+                println!("Issuer: {} ({status})", contract.info.issuer);
+                let labels = WalletLabels::load_file(self.general.base_dir().join(LABELS_FILE))?;
+                if let Some(label) = labels.contract(*contract_id) {
+                    println!("Label: {label}");
+                }
+
                 println!("\nGlobal:");
                 for global in &contract.iface.global_state {
                     if let Ok(values) = contract.global(global.name.clone()) {
@@ -591,10 +2444,10 @@ impl Exec for RgbArgs {
                 }
                 impl<'w> Filter<'w> {
                     fn comment(&self, outpoint: XOutpoint) -> &'static str {
-                        let outpoint = outpoint
-                            .into_bp()
-                            .into_bitcoin()
-                            .expect("liquid is not yet supported");
+                        // The wallet holds only Bitcoin outputs
+                        let Some(outpoint) = outpoint.into_bp().into_bitcoin() else {
+                            return "-- liquid";
+                        };
                         match self {
                             Filter::Wallet(rgb) if rgb.wallet().is_unspent(outpoint) => "",
                             Filter::WalletAll(rgb) if rgb.wallet().is_unspent(outpoint) => {
@@ -608,233 +2461,355 @@ impl Exec for RgbArgs {
                     }
                 }
 
+                let mut amounts = AmountFormatter::with_stock(stock_wallet.stock(), *contract_id)?;
+                amounts.set_raw(*raw);
+
+                let query = StateQuery {
+                    outpoints: outpoints
+                        .iter()
+                        .map(|outpoint| XOutpoint::from(XChain::Bitcoin(*outpoint)))
+                        .collect(),
+                    amounts: AmountRange {
+                        min: min.map(Amount::from),
+                        max: max.map(Amount::from),
+                    },
+                    witness: statuses.iter().copied().collect(),
+                    min_confirmations: min_confirmations.unwrap_or_default(),
+                    tip_height: tip_height.unwrap_or_default(),
+                    offset: *offset,
+                    limit: *limit,
+                };
+
                 println!("\nOwned:");
                 fn witness<S: KnownState>(
                     allocation: &OutputAssignment<S>,
                     contract: &ContractIface<MemContract<&MemContractState>>,
+                    tip_height: Option<u32>,
                 ) -> String {
-                    allocation
-                        .witness
-                        .and_then(|w| contract.witness_info(w))
-                        .map(|info| format!("{} ({})", info.id, info.ord))
-                        .unwrap_or_else(|| s!("~"))
+                    let Some(info) = allocation.witness.and_then(|w| contract.witness_info(w))
+                    else {
+                        return s!("~");
+                    };
+                    match tip_height.and_then(|tip| contract.confirmations(Some(info.id), tip)) {
+                        Some(depth) => format!("{} ({}, {depth} conf.)", info.id, info.ord),
+                        None => format!("{} ({})", info.id, info.ord),
+                    }
+                }
+                fn label<S: KnownState>(
+                    allocation: &OutputAssignment<S>,
+                    labels: &WalletLabels,
+                ) -> String {
+                    labels
+                        .allocation(allocation.opout)
+                        .map(|label| format!("\t{label}"))
+                        .unwrap_or_default()
                 }
                 for owned in &contract.iface.assignments {
                     println!("  State      \t{:78}\tWitness", "Seal");
                     println!("  {}:", owned.name);
-                    if let Ok(allocations) = contract.fungible(owned.name.clone(), &filter) {
+                    if let Ok(allocations) =
+                        contract.query_fungible(owned.name.clone(), &filter, &query)
+                    {
                         for allocation in allocations {
                             println!(
-                                "    {: >9}\t{}\t{} {}",
-                                allocation.state.value(),
+                                "    {: >9}\t{}\t{} {}{}",
+                                amounts.format(allocation.state.value()),
                                 allocation.seal,
-                                witness(&allocation, &contract),
-                                filter.comment(allocation.seal.to_outpoint())
+                                witness(&allocation, &contract, tip_height),
+                                filter.comment(allocation.seal.to_outpoint()),
+                                label(&allocation, &labels)
                             );
                         }
                     }
-                    if let Ok(allocations) = contract.data(owned.name.clone(), &filter) {
+                    if let Ok(allocations) =
+                        contract.query_data(owned.name.clone(), &filter, &query)
+                    {
                         for allocation in allocations {
                             println!(
-                                "    {: >9}\t{}\t{} {}",
+                                "    {: >9}\t{}\t{} {}{}",
                                 allocation.state,
                                 allocation.seal,
-                                witness(&allocation, &contract),
-                                filter.comment(allocation.seal.to_outpoint())
+                                witness(&allocation, &contract, tip_height),
+                                filter.comment(allocation.seal.to_outpoint()),
+                                label(&allocation, &labels)
                             );
                         }
                     }
-                    if let Ok(allocations) = contract.attachments(owned.name.clone(), &filter) {
+                    if let Ok(allocations) =
+                        contract.query_attachments(owned.name.clone(), &filter, &query)
+                    {
                         for allocation in allocations {
                             println!(
-                                "    {: >9}\t{}\t{} {}",
+                                "    {: >9}\t{}\t{} {}{}",
                                 allocation.state,
                                 allocation.seal,
-                                witness(&allocation, &contract),
-                                filter.comment(allocation.seal.to_outpoint())
+                                witness(&allocation, &contract, tip_height),
+                                filter.comment(allocation.seal.to_outpoint()),
+                                label(&allocation, &labels)
                             );
                         }
                     }
-                    if let Ok(allocations) = contract.rights(owned.name.clone(), &filter) {
+                    if let Ok(allocations) =
+                        contract.query_rights(owned.name.clone(), &filter, &query)
+                    {
                         for allocation in allocations {
                             println!(
-                                "    {: >9}\t{}\t{} {}",
+                                "    {: >9}\t{}\t{} {}{}",
                                 "right",
                                 allocation.seal,
-                                witness(&allocation, &contract),
-                                filter.comment(allocation.seal.to_outpoint())
+                                witness(&allocation, &contract, tip_height),
+                                filter.comment(allocation.seal.to_outpoint()),
+                                label(&allocation, &labels)
                             );
                         }
                     }
                 }
             }
+            Command::Amend {
+                extension,
+                contract_id,
+                globals,
+                consignment,
+            } => {
+                let stock = self.rgb_stock()?;
+
+                let file = fs::File::open(globals)?;
+                let code = serde_yaml::from_reader::<_, serde_yaml::Value>(file)?;
+                let code = code
+                    .as_mapping()
+                    .expect("invalid YAML: globals must be an mapping");
+
+                let mut amendment = Amendment::new(*contract_id);
+                if let Some(extension) = extension {
+                    let name = FieldName::try_from(extension.clone()).map_err(|_| {
+                        WalletError::InvalidName("state extension", extension.clone())
+                    })?;
+                    amendment = amendment.with_extension(name);
+                }
+                for (name, val) in code {
+                    let name = name
+                        .as_str()
+                        .expect("invalid YAML: global name must be a string");
+                    let name = FieldName::try_from(name.to_owned())
+                        .map_err(|_| WalletError::InvalidName("global state", name.to_owned()))?;
+                    amendment = amendment.add_global(name, StrictVal::from(val.clone()));
+                }
+
+                let contract = amendment.consign(&stock)?;
+                save_content(&contract, consignment)?;
+                eprintln!(
+                    "Amendment of {contract_id} is saved to '{}'; distribute it to the contract \
+                     holders",
+                    consignment.display()
+                );
+            }
             Command::Issue {
                 schema: schema_id,
                 issuer,
                 contract,
+                allocations,
+                offline,
             } => {
                 let mut stock = self.rgb_stock()?;
 
                 let file = fs::File::open(contract)?;
+                let definition = ContractDefinition::from_reader(file)?;
+                let layer1 = definition.layer1()?;
+                let (schema_ifaces, iface_impl) = definition.iface_impl(&stock, *schema_id)?;
+                let mut builder = definition.builder(&stock, issuer.clone(), *schema_id)?;
 
-                let code = serde_yaml::from_reader::<_, serde_yaml::Value>(file)?;
-
-                let code = code
-                    .as_mapping()
-                    .expect("invalid YAML root-level structure");
-
-                let iface_name = code
-                    .get("interface")
-                    .expect("contract must specify interface under which it is constructed")
-                    .as_str()
-                    .expect("interface name must be a string");
-                let schema_ifaces = stock.schema(*schema_id)?;
-                let iface_name = tn!(iface_name.to_owned());
-                let iface = stock
-                    .iface(iface_name.clone())
-                    .or_else(|_| {
-                        let id = IfaceId::from_str(iface_name.as_str())?;
-                        stock.iface(id).map_err(WalletError::from)
-                    })?
-                    .clone();
-                let iface_id = iface.iface_id();
-                let iface_impl = schema_ifaces.get(iface_id).ok_or_else(|| {
-                    WalletError::Custom(format!(
-                        "no known interface implementation for {iface_name}"
-                    ))
-                })?;
-
-                let mut builder = stock.contract_builder(issuer.clone(), *schema_id, iface_id)?;
-                let types = builder.type_system().clone();
-
-                if let Some(globals) = code.get("globals") {
-                    for (name, val) in globals
-                        .as_mapping()
-                        .expect("invalid YAML: globals must be an mapping")
-                    {
-                        let name = name
-                            .as_str()
-                            .expect("invalid YAML: global name must be a string");
-                        let state_type = iface_impl
-                            .global_state
-                            .iter()
-                            .find(|info| info.name.as_str() == name)
-                            .unwrap_or_else(|| panic!("unknown type name '{name}'"))
-                            .id;
-                        let sem_id = schema_ifaces
-                            .schema
-                            .global_types
-                            .get(&state_type)
-                            .expect("invalid schema implementation")
-                            .sem_id;
-                        let val = StrictVal::from(val.clone());
-                        let typed_val = types
-                            .typify(val, sem_id)
-                            .expect("global type doesn't match type definition");
-
-                        let serialized = types
-                            .strict_serialize_type::<MAX16>(&typed_val)
-                            .expect("internal error");
-                        // Workaround for borrow checker:
-                        let field_name =
-                            FieldName::try_from(name.to_owned()).expect("invalid type name");
-                        builder = builder
-                            .add_global_state(field_name, serialized)
-                            .expect("invalid global state data");
-                    }
-                }
-
-                if let Some(assignments) = code.get("assignments") {
-                    for (name, val) in assignments
-                        .as_mapping()
-                        .expect("invalid YAML: assignments must be an mapping")
-                    {
-                        let name = name
-                            .as_str()
-                            .expect("invalid YAML: assignments name must be a string");
+                if let Some(allocations) = allocations {
+                    let file = BufReader::new(File::open(allocations)?);
+                    let mut count = 0usize;
+                    for allocation in AllocationsReader::new(file) {
+                        let InitialAllocation {
+                            assignment,
+                            seal,
+                            amount,
+                        } = allocation?;
                         let state_type = iface_impl
                             .assignments
                             .iter()
-                            .find(|info| info.name.as_str() == name)
-                            .expect("unknown type name")
+                            .find(|info| info.name == assignment)
+                            .ok_or_else(|| {
+                                let known = iface_impl
+                                    .assignments
+                                    .iter()
+                                    .map(|info| info.name.to_string())
+                                    .collect::<Vec<_>>();
+                                IssueError::from(IssueProblem::UnknownAssignment(
+                                    assignment.to_string(),
+                                    known.join(", "),
+                                ))
+                            })?
                             .id;
                         let state_schema = schema_ifaces
                             .schema
                             .owned_types
                             .get(&state_type)
                             .expect("invalid schema implementation");
-
-                        let assign = val.as_mapping().expect("an assignment must be a mapping");
-                        let seal = assign
-                            .get("seal")
-                            .expect("assignment doesn't provide seal information")
-                            .as_str()
-                            .expect("seal must be a string");
-                        let seal = OutputSeal::from_str(seal).expect("invalid seal definition");
+                        if state_schema.state_type() != StateType::Fungible {
+                            return Err(IssueError::from(IssueProblem::NonFungibleAllocation(
+                                assignment.to_string(),
+                            ))
+                            .into());
+                        }
                         let seal = GenesisSeal::new_random(seal.method, seal.txid, seal.vout);
-
-                        // Workaround for borrow checker:
-                        let field_name =
-                            FieldName::try_from(name.to_owned()).expect("invalid type name");
-                        match state_schema.state_type() {
-                            StateType::Void => todo!(),
-                            StateType::Fungible => {
-                                let amount = assign
-                                    .get("amount")
-                                    .expect("owned state must be a fungible amount")
-                                    .as_u64()
-                                    .expect("fungible state must be an integer");
-                                let seal = BuilderSeal::Revealed(XChain::Bitcoin(seal));
-                                builder = builder
-                                    .add_fungible_state(field_name, seal, amount)
-                                    .expect("invalid global state data");
-                            }
-                            StateType::Structured => todo!(),
-                            StateType::Attachment => todo!(),
+                        let seal = BuilderSeal::Revealed(XChain::with(layer1, seal));
+                        builder = builder.add_fungible_state(assignment, seal, amount)?;
+                        count += 1;
+                        if count % 100 == 0 {
+                            eprint!("\rAdded {count} allocations");
                         }
                     }
+                    eprintln!("\rAdded {count} allocations");
                 }
 
                 let contract = builder.issue_contract()?;
                 let id = contract.contract_id();
+                if *offline {
+                    defer_validation(&self.general.base_dir(), &contract.genesis)?;
+                }
                 stock.import_contract(contract, &ContractIssueResolver)?;
                 eprintln!(
                     "A new contract {id} is issued and added to the stash.\nUse `export` command \
                      to export the contract."
                 );
             }
+            Command::IssueRgb20 {
+                schema,
+                precision,
+                details,
+                terms,
+                issuer,
+                ticker,
+                name,
+                allocations,
+                offline,
+            } => {
+                let mut stock = self.rgb_stock()?;
+                let precision = Precision::try_from(*precision).map_err(|_| {
+                    IssueError::from(IssueProblem::InvalidField(
+                        s!("precision"),
+                        precision.to_string(),
+                    ))
+                })?;
+                let mut issuance = Rgb20Issuance::new(ticker, name, precision);
+                issuance.details = details.clone();
+                if let Some(terms) = terms {
+                    issuance.terms = fs::read_to_string(terms)?;
+                }
+                issuance.allocations = allocations.clone();
+                let contract = issuance.issue(&stock, issuer.clone(), *schema)?;
+                let id = contract.contract_id();
+                if *offline {
+                    defer_validation(&self.general.base_dir(), &contract.genesis)?;
+                }
+                stock.import_contract(contract, &ContractIssueResolver)?;
+                eprintln!(
+                    "A new RGB20 asset {id} is issued and added to the stash.\nUse `export` \
+                     command to export the contract."
+                );
+            }
+            Command::IssueRgb21 {
+                schema,
+                details,
+                terms,
+                index,
+                fractions,
+                issuer,
+                ticker,
+                name,
+                owner,
+                offline,
+            } => {
+                let mut stock = self.rgb_stock()?;
+                let mut issuance = Rgb21Issuance::new(ticker, name, *owner);
+                issuance.details = details.clone();
+                if let Some(terms) = terms {
+                    issuance.terms = fs::read_to_string(terms)?;
+                }
+                issuance.token_index = *index;
+                issuance.fractions = *fractions;
+                let contract = issuance.issue(&stock, issuer.clone(), *schema)?;
+                let id = contract.contract_id();
+                if *offline {
+                    defer_validation(&self.general.base_dir(), &contract.genesis)?;
+                }
+                stock.import_contract(contract, &ContractIssueResolver)?;
+                eprintln!(
+                    "A new RGB21 asset {id} is issued and added to the stash.\nUse `export` \
+                     command to export the contract."
+                );
+            }
             Command::Invoice {
                 address_based,
+                #[cfg(feature = "liquid")]
+                liquid,
                 operation,
                 state,
                 contract_id,
                 iface,
                 amount,
+                min,
+                max,
+                split,
+                sats,
+                velocity,
+                payjoin,
+                endpoints,
+                expiry,
+                expiry_height,
                 token_index,
                 token_fraction,
+                qr,
+                prove,
+                mnemonic,
+                passphrase,
+                xpriv,
             } => {
                 let mut wallet = self.rgb_wallet(&config)?;
 
-                let outpoint = wallet
+                let layout = wallet.wallet().keychain_layout();
+                let mut outpoints = wallet
                     .wallet()
-                    .coinselect(Sats::ZERO, |utxo| {
-                        RgbKeychain::contains_rgb(utxo.terminal.keychain)
-                    })
-                    .next();
+                    .coinselect(Sats::ZERO, |utxo| layout.contains_rgb(utxo.terminal.keychain))
+                    .collect::<Vec<_>>()
+                    .into_iter();
+                let outpoint = outpoints.next();
+                let split_outpoints = outpoints
+                    .take(split.unwrap_or_default())
+                    .collect::<Vec<_>>();
+                if split_outpoints.len() < split.unwrap_or_default() {
+                    return Err(WalletError::InsufficientOutpoints {
+                        required: split.unwrap_or_default(),
+                        available: split_outpoints.len(),
+                    });
+                }
+                let expiry = expiry.map(|secs| unix_now() + secs as i64);
+                let expiry_height = match expiry_height {
+                    Some(blocks) => {
+                        let tip_height = self
+                            .resolver()?
+                            .resolve_tip_height()
+                            .map_err(WalletError::Resolver)?;
+                        Some(tip_height + blocks)
+                    }
+                    None => None,
+                };
+                let invoice_expiry = InvoiceExpiry::new(expiry, expiry_height);
                 let network = wallet.wallet().network();
+                let mut secrets = Vec::with_capacity(split_outpoints.len() + 1);
                 let beneficiary = match (address_based, outpoint) {
                     (false, None) => {
-                        return Err(WalletError::Custom(s!(
-                            "blinded invoice requested but no suitable outpoint is available"
-                        )));
+                        return Err(WalletError::NoOutpoint);
                     }
                     (true, _) => {
-                        let addr = wallet
-                            .wallet()
-                            .addresses(RgbKeychain::Rgb)
-                            .next()
-                            .expect("no addresses left")
-                            .addr;
+                        let path = self.general.base_dir().join(RESERVATIONS_FILE);
+                        let mut reservations = AddressReservations::load_file(&path)?;
+                        let (_, addr) =
+                            wallet.reserve_rgb_address(&mut reservations, invoice_expiry);
+                        reservations.save_file(&path)?;
                         Beneficiary::WitnessVout(Pay2Vout {
                             address: addr.payload,
                             method: wallet.wallet().seal_close_method(),
@@ -847,7 +2822,9 @@ impl Exec for RgbArgs {
                             outpoint.vout,
                         ));
                         wallet.stock_mut().store_secret_seal(seal)?;
-                        Beneficiary::BlindedSeal(*seal.to_secret_seal().as_reduced_unsafe())
+                        let secret = *seal.to_secret_seal().as_reduced_unsafe();
+                        secrets.push(secret);
+                        Beneficiary::BlindedSeal(secret)
                     }
                 };
 
@@ -892,14 +2869,36 @@ impl Exec for RgbArgs {
                     )));
                 };
 
-                let mut builder = RgbInvoiceBuilder::new(XChainNet::bitcoin(network, beneficiary))
+                #[cfg(feature = "liquid")]
+                let layer1 = if *liquid {
+                    let genesis = wallet
+                        .stock()
+                        .as_stash_provider()
+                        .genesis(*contract_id)
+                        .map_err(|e| WalletError::Invoicing(e.to_string()))?;
+                    if !genesis.alt_layers1.contains(&AltLayer1::Liquid) {
+                        return Err(WalletError::Invoicing(format!(
+                            "contract {contract_id} doesn't support Liquid as its layer 1"
+                        )));
+                    }
+                    Layer1::Liquid
+                } else {
+                    Layer1::Bitcoin
+                };
+                #[cfg(not(feature = "liquid"))]
+                let layer1 = Layer1::Bitcoin;
+                let chain_net = chain_net(layer1, network);
+                let mut builder = RgbInvoiceBuilder::new(XChainNet::with(chain_net, beneficiary))
                     .set_contract(*contract_id)
                     .set_interface(iface_name.clone());
+                if let Some(expiry) = expiry {
+                    builder = builder.set_expiry_timestamp(expiry);
+                }
 
                 if operation.is_some() {
                     builder = builder.set_operation(op_name);
                     if let Some(state) = state {
-                        builder = builder.set_operation(fname!(state.clone()));
+                        builder = builder.set_assignment(fname!(state.clone()));
                     }
                 }
 
@@ -958,91 +2957,669 @@ impl Exec for RgbArgs {
                         ))
                     }
 
-                    (OwnedIface::Any, _, _) => {
-                        return Err(WalletError::Invoicing(format!(
-                            "state {state_name} in interface {iface_name} can be of any type; \
-                             adding it to the invoice is impossible"
-                        )));
-                    }
-                    (OwnedIface::AnyAttach, _, _) => {
-                        return Err(WalletError::Invoicing(s!(
-                            "invoicing with attachments is not yet supported"
-                        )));
-                    }
+                    (OwnedIface::Any, _, _) => {
+                        return Err(WalletError::Invoicing(format!(
+                            "state {state_name} in interface {iface_name} can be of any type; \
+                             adding it to the invoice is impossible"
+                        )));
+                    }
+                    (OwnedIface::AnyAttach, _, _) => {
+                        return Err(WalletError::Invoicing(s!(
+                            "invoicing with attachments is not yet supported"
+                        )));
+                    }
+                }
+
+                let mut invoice = if endpoints.is_empty() {
+                    builder.finish_validated(iface)?
+                } else {
+                    let builder = endpoints.iter().enumerate().fold(
+                        PrioritizedBuilder::from(builder),
+                        |builder, (no, (transport, meta))| {
+                            let meta = meta.unwrap_or_else(|| TransportMeta::with(no as u8));
+                            builder.add_transport_with(transport.clone(), meta)
+                        },
+                    );
+                    builder.finish_validated(iface)?
+                };
+                if min.is_some() || max.is_some() {
+                    if assign_iface.owned_state != OwnedIface::Amount {
+                        return Err(WalletError::Invoicing(format!(
+                            "state {state_name} in interface {iface_name} doesn't define a \
+                             fungible state, thus it can't have a range of accepted amounts"
+                        )));
+                    }
+                    let range = AmountRange {
+                        min: min.map(Amount::from),
+                        max: max.map(Amount::from),
+                    };
+                    if matches!(range, AmountRange { min: Some(min), max: Some(max) } if min > max)
+                    {
+                        return Err(WalletError::Invoicing(format!(
+                            "invalid range of accepted amounts {range}"
+                        )));
+                    }
+                    range.set_to_invoice(&mut invoice);
+                }
+                if !split_outpoints.is_empty() {
+                    if assign_iface.owned_state != OwnedIface::Amount {
+                        return Err(WalletError::Invoicing(format!(
+                            "state {state_name} in interface {iface_name} doesn't define a \
+                             fungible state, thus it can't be split"
+                        )));
+                    }
+                    let mut seals = Vec::with_capacity(split_outpoints.len());
+                    for outpoint in split_outpoints {
+                        let seal = XChain::Bitcoin(GraphSeal::new_random(
+                            wallet.wallet().seal_close_method(),
+                            outpoint.txid,
+                            outpoint.vout,
+                        ));
+                        wallet.stock_mut().store_secret_seal(seal)?;
+                        let secret = *seal.to_secret_seal().as_reduced_unsafe();
+                        seals.push(secret);
+                        secrets.push(secret);
+                    }
+                    SplitSeals::new(seals).set_to_invoice(&mut invoice);
+                }
+                if let Some(sats) = sats {
+                    if *sats == 0 {
+                        return Err(WalletError::Invoicing(s!(
+                            "the witness output must receive a non-zero amount of sats"
+                        )));
+                    }
+                    WitnessSats::new(Sats::from_sats(*sats)).set_to_invoice(&mut invoice);
+                }
+                if let Some(velocity) = velocity {
+                    RequestedVelocity::new(*velocity).set_to_invoice(&mut invoice);
+                    wallet.keep_requested_velocity(&invoice)?;
+                    wallet
+                        .velocity_prefs()
+                        .save_file(self.general.base_dir().join(VELOCITY_FILE))?;
+                }
+                if let Some(url) = payjoin {
+                    PayjoinEndpoint::new(url.clone())?.set_to_invoice(&mut invoice);
+                }
+                invoice_expiry.set_to_invoice(&mut invoice);
+                if *prove {
+                    let signer = software_signer(
+                        mnemonic.as_deref(),
+                        passphrase,
+                        xpriv.as_deref(),
+                        self.general.network.is_testnet(),
+                    )?
+                    .expect("clap requires a key for the ownership proof");
+                    let proof = wallet.prove_ownership(&invoice, &signer)?;
+                    proof.set_to_invoice(&mut invoice);
+                }
+                if let Some(expiry) = expiry.filter(|_| !secrets.is_empty()) {
+                    let path = self.general.base_dir().join(SEAL_EXPIRY_FILE);
+                    let mut seal_expiry = SealExpiry::load_file(&path)?;
+                    for secret in secrets {
+                        seal_expiry.insert(secret, expiry);
+                    }
+                    seal_expiry.save_file(&path)?;
+                }
+                println!("{invoice}");
+                if *qr {
+                    let code = invoice.to_qr_string()?;
+                    println!("{code}");
+                }
+            }
+            Command::InvoiceStatus { invoice } => {
+                let wallet =
+                    self.rgb_wallet_guarded(&config, self.network_guard().check_invoice(invoice))?;
+                let resolver = self.resolver()?;
+                let status = wallet.check_invoice_status(invoice, &resolver)?;
+                println!("{status}");
+            }
+            Command::Prepare {
+                v2,
+                invoice,
+                fee,
+                sats,
+                locktime,
+                sequences,
+                ordering,
+                psbt: psbt_file,
+            } => {
+                let mut wallet =
+                    self.rgb_wallet_guarded(&config, self.network_guard().check_invoice(invoice))?;
+                let mut params = TransferParams::with(*fee, *sats);
+                params.ordering = *ordering;
+                set_timelocks(&mut params, *locktime, sequences);
+
+                let (psbt, _) = wallet.construct_psbt(invoice, params)?;
+
+                let ver = if *v2 { PsbtVer::V2 } else { PsbtVer::V0 };
+                match psbt_file {
+                    Some(file_name) => save_psbt(&psbt, ver, file_name)?,
+                    None => match ver {
+                        PsbtVer::V0 => println!("{psbt}"),
+                        PsbtVer::V2 => println!("{psbt:#}"),
+                    },
+                }
+            }
+            Command::Consign {
+                invoice,
+                psbt: psbt_name,
+                consignment: out_file,
+            } => {
+                check_stdout([psbt_name.as_path(), out_file.as_path()])?;
+                let mut wallet = self.rgb_wallet(&config)?;
+                let mut psbt = load_psbt(psbt_name)?;
+                let transfer = wallet.transfer(invoice, &mut psbt)?;
+                save_psbt(&psbt, psbt.version, psbt_name)?;
+                save_content(&transfer, out_file)?;
+            }
+            Command::Preview { psbt, raw } => {
+                let wallet = self.rgb_wallet(&config)?;
+                let psbt = load_psbt(psbt)?;
+                let mut preview = wallet.describe_psbt(&psbt)?;
+                preview.set_raw(*raw);
+                print!("{preview}");
+            }
+            Command::SignRequest { psbt, request } => {
+                let psbt = load_psbt(psbt)?;
+                let request_psbt = psbt.rgb_sign_request()?;
+                save_psbt(&request_psbt, psbt.version, request)?;
+                if let Some(summary) = request_psbt.rgb_summary() {
+                    print!("{summary}");
+                }
+            }
+            Command::Combine {
+                psbt: psbt_name,
+                signed,
+            } => {
+                let mut psbt = load_psbt(psbt_name)?;
+                let signed = load_psbt(signed)?;
+                let count = psbt.rgb_combine(&signed)?;
+                save_psbt(&psbt, psbt.version, psbt_name)?;
+                eprintln!("{count} inputs got new signatures");
+            }
+            Command::Cosign {
+                psbt: psbt_name,
+                copies,
+            } => {
+                let mut psbt = load_psbt(psbt_name)?;
+                if !copies.is_empty() {
+                    let copies = copies
+                        .iter()
+                        .map(PathBuf::as_path)
+                        .map(load_psbt)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let count = psbt.rgb_cosign_combine(&copies)?;
+                    save_psbt(&psbt, psbt.version, psbt_name)?;
+                    eprintln!("{count} inputs got new signatures");
+                }
+                println!("Cosigner\tSigned inputs");
+                for (fp, status) in psbt.rgb_cosigners() {
+                    let state = if status.is_complete() { "complete" } else { "pending" };
+                    println!("{fp}\t{}/{}\t{state}", status.signed.len(), status.inputs.len());
+                }
+            }
+            Command::Sign {
+                mnemonic,
+                passphrase,
+                xpriv,
+                psbt: psbt_name,
+            } => {
+                let signer = software_signer(
+                    mnemonic.as_deref(),
+                    passphrase,
+                    xpriv.as_deref(),
+                    self.general.network.is_testnet(),
+                )?
+                .expect("clap requires either mnemonic or xpriv");
+                let mut psbt = load_psbt(psbt_name)?;
+                let count = signer.sign_psbt(&mut psbt)?;
+                save_psbt(&psbt, psbt.version, psbt_name)?;
+                eprintln!("{count} signatures created");
+                let external = psbt
+                    .inputs()
+                    .filter(|input| input.is_rgb_external())
+                    .count();
+                if external > 0 {
+                    eprintln!(
+                        "{external} inputs spend imported outputs and must be signed by their \
+                         owners"
+                    );
+                }
+            }
+            Command::Transfer {
+                v2,
+                amount,
+                invoice,
+                fee,
+                feerate,
+                sats,
+                locktime,
+                sequences,
+                ordering,
+                isolated,
+                velocities,
+                close_method,
+                allow_duplicate,
+                dry_run,
+                raw,
+                payjoin,
+                deliver,
+                verify_ownership: verify,
+                psbt: psbt_file,
+                consignment: out_file,
+            } => {
+                if *verify {
+                    let resolver = self.resolver()?;
+                    verify_ownership(invoice, &resolver)?;
+                }
+                let mut wallet =
+                    self.rgb_wallet_guarded(&config, self.network_guard().check_invoice(invoice))?;
+                let mut params = TransferParams::with(*fee, *sats);
+                params.amount = amount.map(Amount::from);
+                params.ordering = *ordering;
+                params.feerate = *feerate;
+                if *isolated {
+                    params.coin_selection = CoinSelection::Isolated;
+                }
+                if !velocities.is_empty() {
+                    let contract_id = invoice.contract.ok_or(CompositionError::NoContract)?;
+                    for (assignment_type, hint) in velocities {
+                        params.set_velocity_hint(contract_id, *assignment_type, *hint);
+                    }
+                }
+                params.close_method = *close_method;
+                params.allow_duplicate = *allow_duplicate;
+                params.now = Some(unix_now());
+                if InvoiceExpiry::from_invoice(invoice)?.height.is_some() {
+                    let tip_height = self
+                        .resolver()?
+                        .resolve_tip_height()
+                        .map_err(WalletError::Resolver)?;
+                    params.tip_height = Some(tip_height);
+                }
+                set_timelocks(&mut params, *locktime, sequences);
+
+                if *dry_run {
+                    let plan = wallet.plan_transfer(invoice, params)?;
+                    let formatter = |contract_id| {
+                        wallet.amount_formatter(contract_id).map(|mut amounts| {
+                            amounts.set_raw(*raw);
+                            amounts
+                        })
+                    };
+                    println!("Contract: {}", plan.contract_id);
+                    if let Some(amount) = plan.amount {
+                        println!("Amount: {}", formatter(plan.contract_id)?.format(amount.value()));
+                    }
+                    println!("Inputs:");
+                    for input in &plan.inputs {
+                        println!("\t{input}");
+                    }
+                    println!("Change:");
+                    for change in &plan.change {
+                        println!(
+                            "\t{}\t{}\t{}",
+                            change.contract_id,
+                            change.assignment_type,
+                            formatter(change.contract_id)?.format(change.amount.value())
+                        );
+                    }
+                    for contract_id in &plan.blank_contracts {
+                        println!("Blank transition for {contract_id}");
+                    }
+                    println!("Beneficiary sats: {}", plan.beneficiary_sats);
+                    println!("Fee: {}", plan.fee);
+                    println!("Consignment size: ~{} bytes", plan.consignment_size);
+                    return Ok(());
+                }
+
+                let imported = wallet.imported().len();
+                let (mut psbt, transfer) = if *payjoin {
+                    let client = self.payjoin_client()?;
+                    wallet.pay_payjoin(invoice.clone(), params, &client)?
+                } else {
+                    let (psbt, _, transfer) = wallet.pay(invoice, params)?;
+                    (psbt, transfer)
+                };
+                if wallet.imported().len() != imported {
+                    wallet
+                        .imported()
+                        .save_file(self.general.base_dir().join(IMPORTED_FILE))?;
+                }
+                if let (Some(fee), Some(feerate)) = (psbt.fee(), effective_feerate(&psbt)) {
+                    eprintln!("Fee: {fee} sats ({feerate:.2} sat/vB)");
+                }
+                for contract_id in blank_contracts(&psbt, invoice.contract) {
+                    eprintln!(
+                        "Warning: the spent outputs hold state of contract {contract_id}, which is \
+                         moved by a blank transition"
+                    );
+                }
+
+                let out_file = out_file.as_ref().expect("required by clap unless dry-run");
+                check_stdout([
+                    out_file.as_path(),
+                    psbt_file.as_deref().unwrap_or(Path::new(STDIO)),
+                ])?;
+                save_content(&transfer, out_file)?;
+                if *deliver {
+                    let poster = self.consignment_poster()?;
+                    let timeout = Duration::from_secs(self.stock_config().indexer_timeout);
+                    let transport = deliver_consignment(invoice, &transfer, &poster, timeout)?;
+                    eprintln!("Consignment delivered to {transport}");
+                }
+
+                psbt.version = if *v2 { PsbtVer::V2 } else { PsbtVer::V0 };
+                match psbt_file {
+                    Some(file_name) => save_psbt(&psbt, psbt.version, file_name)?,
+                    None => println!("{psbt}"),
+                }
+            }
+            Command::IssueMore {
+                v2,
+                seal,
+                fee,
+                contract_id,
+                amount,
+                psbt: psbt_file,
+            } => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let params = TxParams::with(*fee);
+                let (mut psbt, _) =
+                    wallet.issue_more(*contract_id, Amount::from(*amount), *seal, params)?;
+
+                psbt.version = if *v2 { PsbtVer::V2 } else { PsbtVer::V0 };
+                match psbt_file {
+                    Some(file_name) => save_psbt(&psbt, psbt.version, file_name)?,
+                    None => println!("{psbt}"),
                 }
+            }
+            Command::Burn {
+                v2,
+                fee,
+                psbt: psbt_file,
+                contract_id,
+                allocations,
+            } => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let params = TxParams::with(*fee);
+                let (mut psbt, _) =
+                    wallet.burn(*contract_id, allocations.iter().copied(), params)?;
 
-                let invoice = builder.finish();
-                println!("{invoice}");
+                psbt.version = if *v2 { PsbtVer::V2 } else { PsbtVer::V0 };
+                match psbt_file {
+                    Some(file_name) => save_psbt(&psbt, psbt.version, file_name)?,
+                    None => println!("{psbt}"),
+                }
             }
-            Command::Prepare {
+            Command::Call {
                 v2,
-                invoice,
                 fee,
-                sats,
+                contract_id,
+                call,
                 psbt: psbt_file,
             } => {
                 let mut wallet = self.rgb_wallet(&config)?;
-                // TODO: Support lock time and RBFs
-                let params = TransferParams::with(*fee, *sats);
+                let file = File::open(call)?;
+                let call = ContractCall::from_reader(*contract_id, file)?;
+                let params = TxParams::with(*fee);
+                let (mut psbt, _) = wallet.call(&call, params)?;
 
-                let (psbt, _) = wallet
-                    .construct_psbt(invoice, params)
-                    .map_err(|err| err.to_string())?;
+                psbt.version = if *v2 { PsbtVer::V2 } else { PsbtVer::V0 };
+                match psbt_file {
+                    Some(file_name) => save_psbt(&psbt, psbt.version, file_name)?,
+                    None => println!("{psbt}"),
+                }
+            }
+            Command::Bump {
+                v2,
+                feerate,
+                outpoint,
+                psbt: psbt_file,
+            } => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let resolver = self.resolver()?;
+                let params = TxParams::with(Sats::ZERO);
+                let (mut psbt, _, parent) =
+                    wallet.bump_incoming(*outpoint, *feerate, &resolver, params)?;
+                eprintln!(
+                    "Child transaction must be published together with the parent transaction {} \
+                     as a package",
+                    parent.txid()
+                );
 
-                let ver = if *v2 { PsbtVer::V2 } else { PsbtVer::V0 };
+                psbt.version = if *v2 { PsbtVer::V2 } else { PsbtVer::V0 };
                 match psbt_file {
-                    Some(file_name) => {
-                        let mut psbt_file = File::create(file_name)?;
-                        psbt.encode(ver, &mut psbt_file)?;
-                    }
-                    None => match ver {
-                        PsbtVer::V0 => println!("{psbt}"),
-                        PsbtVer::V2 => println!("{psbt:#}"),
-                    },
+                    Some(file_name) => save_psbt(&psbt, psbt.version, file_name)?,
+                    None => println!("{psbt}"),
                 }
             }
-            Command::Consign {
-                invoice,
-                psbt: psbt_name,
-                consignment: out_file,
+            Command::Consolidate {
+                v2,
+                feerate,
+                contract,
+                utxos,
+                dry_run,
+                psbt: psbt_file,
             } => {
                 let mut wallet = self.rgb_wallet(&config)?;
-                let mut psbt_file = File::open(psbt_name)?;
-                let mut psbt = Psbt::decode(&mut psbt_file)?;
-                let transfer = wallet
-                    .transfer(invoice, &mut psbt)
-                    .map_err(|err| err.to_string())?;
-                let mut psbt_file = File::create(psbt_name)?;
-                psbt.encode(psbt.version, &mut psbt_file)?;
-                transfer.save_file(out_file)?;
+                let scope = match contract {
+                    Some(contract_id) => ConsolidationScope::Contract(*contract_id),
+                    None => ConsolidationScope::Outpoints(utxos.iter().copied().collect()),
+                };
+                let params = TxParams::with(Sats::ZERO);
+
+                let (psbt, report) = if *dry_run {
+                    (None, wallet.plan_consolidation(&scope, *feerate, params)?)
+                } else {
+                    let (psbt, _, report) = wallet.consolidate(&scope, *feerate, params)?;
+                    (Some(psbt), report)
+                };
+
+                eprintln!("Inputs:");
+                for outpoint in &report.inputs {
+                    eprintln!("\t{outpoint}");
+                }
+                for outpoint in &report.fee_inputs {
+                    eprintln!("\t{outpoint}\t(fee)");
+                }
+                eprintln!("State:");
+                for state in &report.state {
+                    eprint!(
+                        "\t{}\t{}\t{} -> {}",
+                        state.contract_id,
+                        state.assignment_type,
+                        state.allocations_before,
+                        state.allocations_after
+                    );
+                    match state.amount {
+                        Some(amount) => eprintln!(
+                            "\t{}",
+                            wallet
+                                .amount_formatter(state.contract_id)?
+                                .format(amount.value())
+                        ),
+                        None => eprintln!(),
+                    }
+                }
+                eprintln!(
+                    "Allocations: {} -> {}",
+                    report.allocations_before(),
+                    report.allocations_after()
+                );
+                eprintln!("Change: {} sats at output #{}", report.change, report.change_vout);
+                eprintln!("Fee: {} sats (~{} vbytes)", report.fee, report.vsize);
+
+                let Some(mut psbt) = psbt else {
+                    return Ok(());
+                };
+                psbt.version = if *v2 { PsbtVer::V2 } else { PsbtVer::V0 };
+                match psbt_file {
+                    Some(file_name) => save_psbt(&psbt, psbt.version, file_name)?,
+                    None => println!("{psbt}"),
+                }
             }
-            Command::Transfer {
+            Command::BasketInvoice { invoices } => {
+                let basket = BasketInvoice::new(invoices.iter().cloned())?;
+                println!("{basket}");
+            }
+            Command::BasketTransfer {
                 v2,
                 invoice,
                 fee,
                 sats,
                 psbt: psbt_file,
-                consignment: out_file,
+                consignments,
             } => {
-                let mut wallet = self.rgb_wallet(&config)?;
-                // TODO: Support lock time and RBFs
+                let guard = invoice
+                    .legs()
+                    .iter()
+                    .fold(self.network_guard(), NetworkGuard::check_invoice);
+                let mut wallet = self.rgb_wallet_guarded(&config, guard)?;
                 let params = TransferParams::with(*fee, *sats);
 
-                let (mut psbt, _, transfer) =
-                    wallet.pay(invoice, params).map_err(|err| err.to_string())?;
+                let (mut psbt, _, transfers) = wallet.pay_basket(invoice, params)?;
 
-                transfer.save_file(out_file)?;
+                fs::create_dir_all(consignments)?;
+                for (contract_id, transfer) in invoice.contract_ids().zip(transfers) {
+                    let file = consignments.join(format!("{contract_id:-}.rgb"));
+                    transfer.save_file(&file)?;
+                    eprintln!("Consignment for {contract_id} is saved to '{}'", file.display());
+                }
 
                 psbt.version = if *v2 { PsbtVer::V2 } else { PsbtVer::V0 };
                 match psbt_file {
-                    Some(file_name) => {
-                        let mut psbt_file = File::create(file_name)?;
-                        psbt.encode(psbt.version, &mut psbt_file)?;
-                    }
+                    Some(file_name) => save_psbt(&psbt, psbt.version, file_name)?,
                     None => println!("{psbt}"),
                 }
             }
-            Command::Inspect { file, dir, path } => {
+            Command::Swap(SwapCommand::Propose {
+                sats,
+                fee,
+                offer,
+                request,
+                proposal: proposal_file,
+            }) => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let params = TransferParams::with(*fee, *sats);
+                let (proposal, _) = wallet.propose_swap(offer.clone(), request.clone(), params)?;
+                save_psbt(proposal.psbt(), PsbtVer::V2, proposal_file)?;
+            }
+            Command::Swap(SwapCommand::Accept {
+                sats,
+                fee,
+                proposal: proposal_file,
+                consignment: out_file,
+            }) => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let params = TransferParams::with(*fee, *sats);
+                let psbt = load_psbt(proposal_file)?;
+                let mut proposal = SwapProposal::from_psbt(psbt)?;
+                let (_, transfer) = wallet.accept_swap(&mut proposal, params)?;
+                save_content(&transfer, out_file)?;
+                save_psbt(proposal.psbt(), PsbtVer::V2, proposal_file)?;
+            }
+            Command::Swap(SwapCommand::Finalize {
+                proposal: proposal_file,
+                consignment: out_file,
+            }) => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let psbt = load_psbt(proposal_file)?;
+                let mut proposal = SwapProposal::from_psbt(psbt)?;
+                let transfer = wallet.finalize_swap(&mut proposal)?;
+                save_content(&transfer, out_file)?;
+                save_psbt(proposal.psbt(), PsbtVer::V2, proposal_file)?;
+            }
+            Command::Swap(SwapCommand::Sell {
+                sats,
+                fee,
+                invoice,
+                price,
+                proposal: proposal_file,
+            }) => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let params = TransferParams::with(*fee, *sats);
+                let (proposal, _) = wallet.propose_sale(invoice.clone(), *price, params)?;
+                save_psbt(proposal.psbt(), PsbtVer::V2, proposal_file)?;
+            }
+            Command::Swap(SwapCommand::Buy {
+                fee,
+                proposal: proposal_file,
+            }) => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let psbt = load_psbt(proposal_file)?;
+                let mut proposal = SaleProposal::from_psbt(psbt)?;
+                wallet.fund_purchase(&mut proposal, TxParams::with(*fee))?;
+                save_psbt(proposal.psbt(), PsbtVer::V2, proposal_file)?;
+                eprintln!(
+                    "Sale proposal funded; sign the transaction only after validating the \
+                     consignment from the seller"
+                );
+            }
+            Command::Swap(SwapCommand::Settle {
+                proposal: proposal_file,
+                consignment: out_file,
+            }) => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let psbt = load_psbt(proposal_file)?;
+                let mut proposal = SaleProposal::from_psbt(psbt)?;
+                let transfer = wallet.finalize_sale(&mut proposal)?;
+                save_content(&transfer, out_file)?;
+                save_psbt(proposal.psbt(), PsbtVer::V2, proposal_file)?;
+            }
+            Command::Payjoin(PayjoinCommand::Propose {
+                sats,
+                fee,
+                invoice,
+                proposal: proposal_file,
+            }) => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let params = TransferParams::with(*fee, *sats);
+                let (proposal, _) = wallet.propose_payjoin(invoice.clone(), params)?;
+                save_psbt(proposal.psbt(), PsbtVer::V2, proposal_file)?;
+            }
+            Command::Payjoin(PayjoinCommand::Contribute {
+                proposal: proposal_file,
+                output,
+            }) => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let psbt = load_psbt(proposal_file)?;
+                let mut proposal = PayjoinProposal::from_psbt(psbt)?;
+                let outpoint = wallet.contribute_payjoin(&mut proposal)?;
+                save_psbt(proposal.psbt(), PsbtVer::V2, output.as_ref().unwrap_or(proposal_file))?;
+                eprintln!(
+                    "Contributed {outpoint}; sign the transaction only after validating the \
+                     consignment from the payer"
+                );
+            }
+            Command::Payjoin(PayjoinCommand::Finalize {
+                proposal: proposal_file,
+                payjoin: payjoin_file,
+                consignment: out_file,
+            }) => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let proposal = PayjoinProposal::from_psbt(load_psbt(proposal_file)?)?;
+                let payjoin = load_psbt(payjoin_file)?;
+                let (psbt, transfer) = wallet.finalize_payjoin(&proposal, payjoin)?;
+                save_content(&transfer, out_file)?;
+                save_psbt(&psbt, PsbtVer::V2, payjoin_file)?;
+            }
+            Command::Inspect {
+                file,
+                diff: Some(other),
+                ..
+            } => {
+                let diff =
+                    ConsignmentDiff::new(&load_consignment(file)?, &load_consignment(other)?)?;
+                print!("{diff}");
+            }
+            Command::Inspect {
+                file,
+                dir,
+                path,
+                format,
+                diff: None,
+            } => {
                 #[derive(Clone, Debug)]
                 #[derive(Serialize, Deserialize)]
                 #[serde(crate = "serde_crate", rename_all = "camelCase")]
@@ -1054,25 +3631,25 @@ impl Exec for RgbArgs {
                     signatures: TinyOrdMap<ContentId, ContentSigs>,
                 }
 
-                let content = UniversalFile::load_file(file)?;
+                let content = load_universal(file)?;
                 let consignment = match content {
                     UniversalFile::Contract(contract) if *dir => Some(contract),
                     UniversalFile::Transfer(transfer) if *dir => Some(transfer.into_contract()),
                     content => {
-                        let s = serde_yaml::to_string(&content).expect("unable to present as YAML");
+                        let s = format.serialize(&content)?;
                         match path {
-                            None => println!("{s}"),
-                            Some(path) => fs::write(path, s)?,
+                            Some(path) if !is_stdio(path) => fs::write(path, s)?,
+                            _ => println!("{s}"),
                         }
                         None
                     }
                 };
                 if let Some(consignment) = consignment {
                     let mut map = map![
-                        s!("genesis.yaml") => serde_yaml::to_string(&consignment.genesis)?,
-                        s!("schema.yaml") => serde_yaml::to_string(&consignment.schema)?,
-                        s!("bundles.yaml") => serde_yaml::to_string(&consignment.bundles)?,
-                        s!("extensions.yaml") => serde_yaml::to_string(&consignment.extensions)?,
+                        format!("genesis.{format}") => format.serialize(&consignment.genesis)?,
+                        format!("schema.{format}") => format.serialize(&consignment.schema)?,
+                        format!("bundles.{format}") => format.serialize(&consignment.bundles)?,
+                        format!("extensions.{format}") => format.serialize(&consignment.extensions)?,
                         s!("types.sty") => consignment.types.to_string(),
                     ];
                     for lib in consignment.scripts {
@@ -1084,12 +3661,12 @@ impl Exec for RgbArgs {
                     }
                     for (iface, iimpl) in consignment.ifaces {
                         map.insert(
-                            format!("iface-{}.yaml", iface.name),
-                            serde_yaml::to_string(&iface)?,
+                            format!("iface-{}.{format}", iface.name),
+                            format.serialize(&iface)?,
                         );
                         map.insert(
-                            format!("impl-{}.yaml", iface.name),
-                            serde_yaml::to_string(&iimpl)?,
+                            format!("impl-{}.{format}", iface.name),
+                            format.serialize(&iimpl)?,
                         );
                     }
                     let contract = ConsignmentInspection {
@@ -1099,7 +3676,7 @@ impl Exec for RgbArgs {
                         supplements: consignment.supplements,
                         signatures: consignment.signatures,
                     };
-                    map.insert(s!("consignment-meta.yaml"), serde_yaml::to_string(&contract)?);
+                    map.insert(format!("consignment-meta.{format}"), format.serialize(&contract)?);
                     let path = path.as_ref().expect("required by clap");
                     fs::create_dir_all(path)?;
                     for (file, value) in map {
@@ -1109,31 +3686,248 @@ impl Exec for RgbArgs {
             }
             Command::Reconstruct {
                 contract: false,
+                format,
+                expect,
                 src,
                 dst,
             } => {
-                let file = File::open(src)?;
-                let transfer: Transfer = serde_yaml::from_reader(&file)?;
+                let format = format.unwrap_or_else(|| InspectFormat::with_path(src));
+                let transfer: Transfer = format.deserialize(&read_text(src)?)?;
+                let id = verify_reproduction(&transfer, format, *expect)?;
+                eprintln!("Consignment {id} is reproduced");
                 match dst {
                     None => println!("{transfer}"),
-                    Some(dst) => {
-                        transfer.save_file(dst)?;
-                    }
+                    Some(dst) => save_content(&transfer, dst)?,
                 }
             }
             Command::Reconstruct {
                 contract: true,
+                format,
+                expect,
                 src,
                 dst,
             } => {
-                let file = File::open(src)?;
-                let contract: Contract = serde_yaml::from_reader(&file)?;
+                let format = format.unwrap_or_else(|| InspectFormat::with_path(src));
+                let contract: Contract = format.deserialize(&read_text(src)?)?;
+                let id = verify_reproduction(&contract, format, *expect)?;
+                eprintln!("Consignment {id} is reproduced");
                 match dst {
                     None => println!("{contract}"),
-                    Some(dst) => {
-                        contract.save_file(dst)?;
+                    Some(dst) => save_content(&contract, dst)?,
+                }
+            }
+            Command::Compact { archive } => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let count = wallet.compact(archive)?;
+                eprintln!("{count} transition bundles were moved to the archive");
+            }
+            Command::Gc => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let path = self.general.base_dir().join(SEAL_EXPIRY_FILE);
+                let mut seal_expiry = SealExpiry::load_file(&path)?;
+                let pruned = wallet.gc(&mut seal_expiry, unix_now())?;
+                seal_expiry.save_file(&path)?;
+                for seal in &pruned {
+                    eprintln!("- {}", seal.to_secret_seal());
+                }
+                eprintln!("{} blinded seals were removed from the stash", pruned.len());
+
+                let path = self.general.base_dir().join(RESERVATIONS_FILE);
+                let mut reservations = AddressReservations::load_file(&path)?;
+                if reservations.reserved().next().is_some() {
+                    let resolver = self.resolver()?;
+                    let tip_height = resolver
+                        .resolve_tip_height()
+                        .map_err(WalletError::Resolver)?;
+                    let released = wallet.release_expired(
+                        &mut reservations,
+                        &resolver,
+                        unix_now(),
+                        tip_height,
+                    )?;
+                    reservations.save_file(&path)?;
+                    for terminal in &released {
+                        eprintln!("- {terminal}");
+                    }
+                    eprintln!("{} addresses of the expired invoices were released", released.len());
+                }
+            }
+            Command::Reconcile => {
+                let path = self.general.base_dir().join(DEFERRED_VALIDATION_FILE);
+                let mut deferred = DeferredValidation::load_file(&path)?;
+                if deferred.is_empty() {
+                    eprintln!("No contracts issued offline are pending verification");
+                    return Ok(());
+                }
+                let resolver = self.resolver()?;
+                let report = deferred
+                    .reconcile(&resolver)
+                    .map_err(|err| WalletError::Resolver(err.to_string()))?;
+                deferred.save_file(&path)?;
+                for id in &report.verified {
+                    println!("{id}\tverified");
+                }
+                for (id, outpoints) in &report.pending {
+                    for outpoint in outpoints {
+                        println!("{id}\tpending\t{outpoint}");
+                    }
+                }
+                for (id, outpoints) in &report.invalid {
+                    for outpoint in outpoints {
+                        println!("{id}\tinvalid\t{outpoint}");
+                    }
+                }
+                if report.has_invalid() {
+                    eprintln!(
+                        "Warning: some contracts assign state to non-existing outputs, which can \
+                         never be spent"
+                    );
+                }
+            }
+            Command::RestoreArchive { archive } => {
+                let mut wallet = self.rgb_wallet(&config)?;
+                let count = wallet.restore_archive(archive)?;
+                eprintln!("{count} transition bundles were restored from the archive");
+            }
+            Command::Recover { dir } => {
+                let stock_path = self.general.base_dir();
+                self.lock_stock(&stock_path)?;
+                let resolver = self.resolver()?;
+                let mut stock = Stock::in_memory();
+                let report =
+                    stock.recover_from_dir(dir, &resolver, self.general.network.is_testnet())?;
+                for (path, err) in &report.failed {
+                    eprintln!("- skipping `{}`: {err}", path.display());
+                }
+                let backups = self.stock_config().stock_backups;
+                stock
+                    .make_persistent(BackupStore::new(stock_path, backups)?, true)
+                    .map_err(WalletError::StockPersist)?;
+                eprintln!(
+                    "Stock is recovered from {} kits, {} contracts and {} transfers",
+                    report.kits, report.contracts, report.transfers
+                );
+            }
+            Command::Migrate => {
+                let data_dir = &self.general.data_dir;
+                if self.general.no_prefix || !has_flat_layout(data_dir) {
+                    eprintln!("Data directory `{}` has no data to migrate", data_dir.display());
+                    return Ok(());
+                }
+                let migrated =
+                    migrate_flat_layout(data_dir, self.general.network, &[Self::CONF_FILE_NAME])?;
+                for path in &migrated {
+                    eprintln!("- {}", path.display());
+                }
+                eprintln!(
+                    "{} entries were migrated into `{}`",
+                    migrated.len(),
+                    self.general.base_dir().display()
+                );
+            }
+            Command::Stock(StockCommand::ExportAll { sqlite, file }) => {
+                let stock = match sqlite {
+                    Some(path) => {
+                        let store = SqliteStore::open_read_only(path)
+                            .map_err(|e| WalletError::StockPersist(PersistenceError::with(e)))?;
+                        Stock::load(store, false).map_err(WalletError::StockPersist)?
+                    }
+                    None => self.rgb_stock()?,
+                };
+                let mut export = StockExport::with_stock(&stock)?;
+                let names = self.wallet_factory().wallets().list()?;
+                for name in &names {
+                    let wallet = self.open_wallet(name)?;
+                    export.set_tapret_tweaks(name, &wallet.descriptor().tapret_tweaks());
+                }
+                let base_dir = self.general.base_dir();
+                let mut files = 0usize;
+                for name in STOCK_EXPORT_FILES {
+                    match fs::read(base_dir.join(name)) {
+                        Ok(data) => export.add_file(name, data),
+                        Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                        Err(err) => return Err(err.into()),
+                    }
+                    files += 1;
+                }
+                export.save_file(file)?;
+                eprintln!(
+                    "Stock is exported with tapret tweaks of {} wallets and {files} auxiliary \
+                     files",
+                    names.len()
+                );
+            }
+            Command::Stock(StockCommand::ImportAll {
+                sqlite,
+                force,
+                file,
+            }) => {
+                let base_dir = self.general.base_dir();
+                let existing = match sqlite {
+                    Some(path) => fs::metadata(path)
+                        .is_ok_and(|meta| meta.len() > 0)
+                        .then(|| path.clone()),
+                    None => STOCK_FILES
+                        .iter()
+                        .chain(&STOCK_EXPORT_FILES)
+                        .map(|name| base_dir.join(name))
+                        .find(|path| path.exists()),
+                };
+                if let (Some(path), false) = (existing, *force) {
+                    return Err(StockExportError::StockExists(path.display().to_string()).into());
+                }
+
+                let export = StockExport::load_file(file)?;
+                let tweaks = export.tapret_tweaks()?;
+                let mut stock = export.stock()?;
+                match sqlite {
+                    Some(path) => {
+                        let store = SqliteStore::open(path)
+                            .map_err(|e| WalletError::StockPersist(PersistenceError::with(e)))?;
+                        stock
+                            .make_persistent(store, true)
+                            .map_err(WalletError::StockPersist)?;
+                    }
+                    None => {
+                        let stock_path = self.general.base_dir();
+                        self.lock_stock(&stock_path)?;
+                        let backups = self.stock_config().stock_backups;
+                        stock
+                            .make_persistent(BackupStore::new(stock_path, backups)?, true)
+                            .map_err(WalletError::StockPersist)?;
+                    }
+                }
+
+                for (name, data) in export.files() {
+                    if !STOCK_EXPORT_FILES.contains(&name) {
+                        eprintln!("- skipping unknown file `{name}`");
+                        continue;
+                    }
+                    fs::write(base_dir.join(name), data)?;
+                }
+
+                let wallets = self.wallet_factory().wallets();
+                for (name, tweaks) in &tweaks {
+                    if !wallets.exists(name) {
+                        eprintln!(
+                            "- wallet `{name}` doesn't exist; create it and repeat the import to \
+                             restore its {} tapret tweaks",
+                            tweaks.len()
+                        );
+                        continue;
                     }
+                    let wallet = self.open_wallet(name)?;
+                    let mut wallet = RgbWallet::new(Stock::in_memory(), wallet);
+                    let added = wallet.import_tapret_tweaks(tweaks)?;
+                    eprintln!("- {} tapret tweaks were imported into wallet `{name}`", added.len());
                 }
+                eprintln!("Stock is imported from `{}`", file.display());
+            }
+            Command::Completions { shell } => {
+                clap_complete::generate(*shell, &mut RgbArgs::command(), "rgb", &mut io::stdout());
+            }
+            Command::Shell => {
+                crate::shell::run()?;
             }
             Command::Dump { root_dir } => {
                 let stock = self.rgb_stock()?;
@@ -1247,31 +4041,92 @@ impl Exec for RgbArgs {
                 )?;
                 eprintln!("Dump is successfully generated and saved to '{root_dir}'");
             }
-            Command::Validate { file } => {
-                let mut resolver = self.resolver()?;
-                let consignment = Transfer::load_file(file)?;
+            Command::Validate {
+                report: format,
+                file,
+            } => {
+                let consignment = load_transfer(file)?;
+                let mut resolver = self
+                    .resolver_guarded(self.network_guard().check_genesis(&consignment.genesis))?;
                 resolver.add_terminals(&consignment);
-                let status =
-                    match consignment.validate(&resolver, self.general.network.is_testnet()) {
-                        Ok(consignment) => consignment.into_validation_status(),
-                        Err((status, _)) => status,
-                    };
-                if status.validity() == Validity::Valid {
+                let report = ValidationReport::validate(
+                    consignment,
+                    &resolver,
+                    self.general.network.is_testnet(),
+                );
+                if let Some(format) = format {
+                    println!("{}", format.serialize(&report)?);
+                } else if report.validity == ReportValidity::Valid {
                     eprintln!("The provided consignment is valid")
                 } else {
-                    eprintln!("{status}");
+                    eprint!("{report}");
                 }
             }
-            Command::Accept { force: _, file } => {
+            Command::Accept {
+                force: _,
+                trust,
+                allow_duplicate_ticker,
+                resume,
+                rate_limit,
+                file,
+            } => {
                 // TODO: Ensure we properly handle unmined terminal transactions
+                let transfer = load_transfer(file)?;
+                self.network_guard()
+                    .check_genesis(&transfer.genesis)
+                    .finish()?;
+                let quarantine = Quarantine::new(self.general.base_dir().join(QUARANTINE_DIR));
+                if !trust {
+                    let policy = TrustPolicy::load_file(self.general.base_dir().join(POLICY_FILE))?;
+                    if !policy.is_trusted(&transfer) {
+                        let path = quarantine.put(&transfer)?;
+                        eprintln!(
+                            "Consignment for contract {} is not trusted by the policy and was \
+                             quarantined to '{}' pending manual review",
+                            transfer.contract_id(),
+                            path.display()
+                        );
+                        return Ok(());
+                    }
+                }
+                let consignment_id = transfer.consignment_id();
                 let mut stock = self.rgb_stock()?;
-                let mut resolver = self.resolver()?;
-                let transfer = Transfer::load_file(file)?;
+                let (registries, fetcher) = self.kit_registries()?;
+                let schema_id = transfer.schema_id();
+                if let Some(kit_id) =
+                    fetch_schema_kit(&mut stock, &registries, &fetcher, schema_id)?
+                {
+                    eprintln!(
+                        "Kit {kit_id} providing schema {schema_id} was fetched from the registry"
+                    );
+                }
+                let checkpoint = checkpoint_path(self.general.base_dir(), consignment_id);
+                if checkpoint.exists() {
+                    if *resume {
+                        eprintln!(
+                            "Resuming acceptance of the consignment from '{}'",
+                            checkpoint.display()
+                        );
+                    } else {
+                        fs::remove_file(&checkpoint)?;
+                    }
+                }
+                let mut resolver = self
+                    .resolver()?
+                    .checkpointed(&checkpoint, rate_limit.map(Duration::from_millis))
+                    .map_err(WalletError::Resolver)?;
                 resolver.add_terminals(&transfer);
+                let transfer = reveal_known_seals(&stock, transfer)?;
                 let valid = transfer
                     .validate(&resolver, self.general.network.is_testnet())
                     .map_err(|(status, _)| status)?;
-                stock.accept_transfer(valid, &resolver)?;
+                let (_, collisions) =
+                    stock.accept_transfer_checked(valid, &resolver, *allow_duplicate_ticker)?;
+                warn_collisions(&collisions);
+                if checkpoint.exists() {
+                    fs::remove_file(&checkpoint)?;
+                }
+                quarantine.release(consignment_id)?;
                 eprintln!("Transfer accepted into the stash");
             }
         }
@@ -1279,6 +4134,12 @@ impl Exec for RgbArgs {
     }
 }
 
+fn warn_collisions(collisions: &[AssetCollision]) {
+    for collision in collisions {
+        eprintln!("Warning: {collision}");
+    }
+}
+
 fn contract_default_iface_name(
     contract_id: ContractId,
     stock: &Stock,
@@ -1314,3 +4175,167 @@ fn contract_default_iface_name(
         }
     })
 }
+
+fn parse_allocation(s: &str) -> Result<(OutputSeal, u64), String> {
+    let (seal, amount) = s
+        .split_once('=')
+        .ok_or_else(|| format!("allocation '{s}' must have `SEAL=AMOUNT` format"))?;
+    let seal = OutputSeal::from_str(seal).map_err(|e| format!("invalid seal '{seal}': {e}"))?;
+    let amount = amount
+        .parse()
+        .map_err(|e| format!("invalid amount '{amount}': {e}"))?;
+    Ok((seal, amount))
+}
+
+fn parse_script(hex: &str) -> Result<Vec<u8>, ImportError> {
+    Vec::<u8>::from_hex(hex).map_err(|_| ImportError::InvalidScript(hex.to_owned()))
+}
+
+fn parse_endpoint(s: &str) -> Result<(RgbTransport, Option<TransportMeta>), String> {
+    let (transport, meta) = match s.rsplit_once('=') {
+        Some((transport, meta)) => (transport, Some(meta)),
+        None => (s, None),
+    };
+    let transport = RgbTransport::from_str(transport)
+        .map_err(|e| format!("invalid transport '{transport}': {e}"))?;
+    let meta = meta
+        .map(TransportMeta::from_str)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    Ok((transport, meta))
+}
+
+fn parse_sequence(s: &str) -> Result<(Outpoint, SeqNo), String> {
+    let (outpoint, seq_no) = s
+        .split_once('=')
+        .ok_or_else(|| format!("sequence '{s}' must have `OUTPOINT=SEQ` format"))?;
+    let outpoint =
+        Outpoint::from_str(outpoint).map_err(|e| format!("invalid outpoint '{outpoint}': {e}"))?;
+    let seq_no = seq_no
+        .parse()
+        .map_err(|e| format!("invalid sequence number '{seq_no}': {e}"))?;
+    Ok((outpoint, SeqNo::from_consensus_u32(seq_no)))
+}
+
+fn parse_velocity_arg(s: &str) -> Result<(AssignmentType, VelocityHint), String> {
+    let (ty, hint) = s
+        .split_once('=')
+        .ok_or_else(|| format!("velocity '{s}' must have `TYPE=CLASS` format"))?;
+    let ty = ty
+        .parse()
+        .map_err(|e| format!("invalid assignment type '{ty}': {e}"))?;
+    Ok((AssignmentType::with(ty), parse_velocity_class(hint)?))
+}
+
+fn parse_close_method(s: &str) -> Result<CloseMethod, String> {
+    match s {
+        "opret" | "opret1st" => Ok(CloseMethod::OpretFirst),
+        "tapret" | "tapret1st" => Ok(CloseMethod::TapretFirst),
+        _ => Err(format!("unknown close method '{s}'; use `opret` or `tapret`")),
+    }
+}
+
+fn parse_velocity_class(s: &str) -> Result<VelocityHint, String> {
+    parse_velocity(s).ok_or_else(|| format!("unknown velocity class '{s}'"))
+}
+
+#[allow(clippy::result_large_err)]
+fn software_signer(
+    mnemonic: Option<&Path>,
+    passphrase: &str,
+    xpriv: Option<&str>,
+    testnet: bool,
+) -> Result<Option<SoftwareSigner>, WalletError> {
+    Ok(match (mnemonic, xpriv) {
+        (Some(path), _) => {
+            let phrase = fs::read_to_string(path)?;
+            Some(SoftwareSigner::from_mnemonic(phrase.trim(), passphrase, testnet)?)
+        }
+        (None, Some(xpriv)) => Some(SoftwareSigner::from_account(XprivAccount::from_str(xpriv)?)),
+        (None, None) => None,
+    })
+}
+
+/// Records the genesis outpoints of the contract issued offline for the later
+/// verification with `reconcile` command.
+#[allow(clippy::result_large_err)]
+fn defer_validation(base_dir: &Path, genesis: &Genesis) -> Result<(), WalletError> {
+    let path = base_dir.join(DEFERRED_VALIDATION_FILE);
+    let mut deferred = DeferredValidation::load_file(&path)?;
+    let count = deferred.defer(genesis);
+    deferred.save_file(&path)?;
+    eprintln!("Verification of {count} genesis outpoints is deferred");
+    Ok(())
+}
+
+/// Verifies that the reconstructed consignment is reproduced exactly by both
+/// the binary and the text serialization, and that it matches the expected
+/// consignment, if given. Returns the consignment id.
+#[allow(clippy::result_large_err)]
+fn verify_reproduction<const TRANSFER: bool>(
+    consignment: &Consignment<TRANSFER>,
+    format: InspectFormat,
+    expected: Option<ConsignmentId>,
+) -> Result<ConsignmentId, WalletError> {
+    let id = consignment.consignment_id();
+    let check = |found: ConsignmentId| {
+        if found != id {
+            return Err(WalletError::Reproduction {
+                expected: id,
+                found,
+            });
+        }
+        Ok(())
+    };
+
+    let data = consignment
+        .to_strict_serialized::<{ usize::MAX }>()
+        .map_err(|err| WalletError::Format("binary", err.to_string()))?;
+    let decoded = Consignment::<TRANSFER>::from_strict_serialized::<{ usize::MAX }>(data)
+        .map_err(|err| WalletError::Format("binary", err.to_string()))?;
+    check(decoded.consignment_id())?;
+
+    let text = format.serialize(consignment)?;
+    let parsed = format.deserialize::<Consignment<TRANSFER>>(&text)?;
+    check(parsed.consignment_id())?;
+
+    if let Some(expected) = expected {
+        if expected != id {
+            return Err(WalletError::Reproduction {
+                expected,
+                found: id,
+            });
+        }
+    }
+    Ok(id)
+}
+
+/// Loads contract or transfer consignment, presenting it as a contract.
+#[allow(clippy::result_large_err)]
+fn load_consignment(file: &Path) -> Result<Contract, WalletError> {
+    match load_universal(file)? {
+        UniversalFile::Contract(contract) => Ok(contract),
+        UniversalFile::Transfer(transfer) => Ok(transfer.into_contract()),
+        UniversalFile::Kit(_) => Err(WalletError::NotConsignment(file.display().to_string())),
+    }
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time before the UNIX epoch")
+        .as_secs() as i64
+}
+
+fn set_timelocks(
+    params: &mut TransferParams,
+    locktime: Option<u32>,
+    sequences: &[(Outpoint, SeqNo)],
+) {
+    if let Some(locktime) = locktime {
+        params.set_lock_time(LockTime::from_consensus_u32(locktime));
+    }
+    for (outpoint, seq_no) in sequences {
+        params.set_sequence(*outpoint, *seq_no);
+    }
+}
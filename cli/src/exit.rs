@@ -0,0 +1,247 @@
+// RGB smart contracts for Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2023 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2023 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Exit codes and structured error output of the command-line tool, which
+//! allow wrapping services to tell different classes of failures apart.
+
+use std::process::ExitCode;
+
+use bpwallet::cli::ExecError;
+use psrgbt::ConstructionError;
+use rgb::{
+    AcceptError, AllocationProofError, CompositionError, EncryptionError, ErrorCode, ExploreError,
+    KitRegistryError, PayError, ReservationError, StockExportError, SyncError, VelocityError,
+    WalletDirError, WalletError,
+};
+use serde::Serialize;
+
+/// Class of the error, which defines the process exit code.
+///
+/// Exit codes are stable: once assigned, they are never changed. Code 2 is
+/// used for command-line argument errors reported before the command runs.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Serialize)]
+#[serde(crate = "serde_crate", rename_all = "camelCase")]
+#[display(lowercase)]
+pub enum ErrorClass {
+    /// Error not falling into any other class.
+    Other = 1,
+
+    /// Consignment, contract or invoice didn't pass validation.
+    Validation = 3,
+
+    /// Indexer, resolver or registry can't be reached or reported an error.
+    Connectivity = 4,
+
+    /// Wallet doesn't have enough bitcoins, RGB state or outputs for the
+    /// operation.
+    InsufficientFunds = 5,
+
+    /// Wallet or stock data can't be read or written.
+    Storage = 6,
+
+    /// Data provided by the user can't be parsed or are inconsistent.
+    Input = 7,
+}
+
+impl From<ErrorClass> for ExitCode {
+    fn from(class: ErrorClass) -> Self { ExitCode::from(class as u8) }
+}
+
+impl From<&WalletError> for ErrorClass {
+    fn from(err: &WalletError) -> Self {
+        match err {
+            WalletError::InvalidConsignment(_)
+            | WalletError::IncompleteContract(_)
+            | WalletError::DeferredValidation(_)
+            | WalletError::Reproduction { .. }
+            | WalletError::ContractMismatch(_)
+            | WalletError::InvoiceApi(_)
+            | WalletError::Accept(AcceptError::Invalid(_))
+            | WalletError::AllocationProof(
+                AllocationProofError::Invalid(_) | AllocationProofError::WrongContract { .. },
+            ) => ErrorClass::Validation,
+
+            WalletError::Resolver(_)
+            | WalletError::Sync(SyncError::Indexer(_) | SyncError::WitnessResolver(..))
+            | WalletError::KitRegistry(KitRegistryError::Fetch(..))
+            | WalletError::WalletExec(ExecError::Indexer(_)) => ErrorClass::Connectivity,
+
+            WalletError::NoOutpoint | WalletError::InsufficientOutpoints { .. } => {
+                ErrorClass::InsufficientFunds
+            }
+            WalletError::Composition(err) | WalletError::Pay(PayError::Composition(err)) => {
+                composition_class(err)
+            }
+            WalletError::WalletExec(ExecError::ConstructPsbt(err)) => construction_class(err),
+
+            WalletError::File(_)
+            | WalletError::StockLoad(_)
+            | WalletError::WalletPersist(_)
+            | WalletError::StockPersist(_)
+            | WalletError::StockLocked(_)
+            | WalletError::Stock(_)
+            | WalletError::Explore(ExploreError::Stock(_))
+            | WalletError::AllocationProof(AllocationProofError::Stock(_))
+            | WalletError::Archive(_)
+            | WalletError::Recovery(_)
+            | WalletError::WalletDir(
+                WalletDirError::Io(_)
+                | WalletDirError::Load(..)
+                | WalletDirError::Store(..)
+                | WalletDirError::Encryption(EncryptionError::Io(_)),
+            )
+            | WalletError::Encryption(EncryptionError::Io(_))
+            | WalletError::Velocity(VelocityError::Io(_))
+            | WalletError::Reservation(ReservationError::Io(_))
+            | WalletError::StockExport(StockExportError::Io(_))
+            | WalletError::WalletExec(ExecError::Io(_) | ExecError::Store(_)) => {
+                ErrorClass::Storage
+            }
+
+            WalletError::InvalidId(_)
+            | WalletError::InvalidName(..)
+            | WalletError::PsbtDecode(_)
+            | WalletError::PsbtParse(_)
+            | WalletError::Armored(_)
+            | WalletError::Yaml(_)
+            | WalletError::Format(..)
+            | WalletError::Portable(_)
+            | WalletError::CompactInvoice(_)
+            | WalletError::Xpriv(_)
+            | WalletError::IssuerKey
+            | WalletError::Tweaks(_)
+            | WalletError::NotConsignment(_)
+            | WalletError::UnexpectedTransfer
+            | WalletError::WalletUnknown(_)
+            | WalletError::NetworkMismatch(_)
+            | WalletError::WalletDir(
+                WalletDirError::NotFound(_)
+                | WalletDirError::AlreadyExists(_)
+                | WalletDirError::InvalidName(_)
+                | WalletDirError::WrongNetwork { .. }
+                | WalletDirError::Collision(_)
+                | WalletDirError::Locked(_)
+                | WalletDirError::AlreadyEncrypted(_)
+                | WalletDirError::NotEncrypted(_)
+                | WalletDirError::Encryption(EncryptionError::WrongPassphrase(_)),
+            )
+            | WalletError::WalletLocked(_)
+            | WalletError::Encryption(
+                EncryptionError::WrongPassphrase(_) | EncryptionError::PlaintextBackup(_),
+            )
+            | WalletError::Accept(AcceptError::NetworkMismatch(_))
+            | WalletError::Explore(ExploreError::UnknownContract(_))
+            | WalletError::AllocationProof(
+                AllocationProofError::UnknownContract(_)
+                | AllocationProofError::NoAllocation { .. },
+            )
+            | WalletError::Velocity(VelocityError::InvalidKey(_) | VelocityError::InvalidHint(_))
+            | WalletError::Reservation(ReservationError::InvalidTerminal(_))
+            | WalletError::StockExport(
+                StockExportError::NotExport
+                | StockExportError::UnsupportedVersion(_)
+                | StockExportError::Truncated
+                | StockExportError::TrailingData
+                | StockExportError::InvalidName
+                | StockExportError::RepeatedEntry(_)
+                | StockExportError::Checksum(_)
+                | StockExportError::Missing(_)
+                | StockExportError::InvalidEntry(_)
+                | StockExportError::Decode(..)
+                | StockExportError::StockExists(_),
+            )
+            | WalletError::WalletExec(ExecError::DecodePsbt(_)) => ErrorClass::Input,
+
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+fn composition_class(err: &CompositionError) -> ErrorClass {
+    match err {
+        CompositionError::InsufficientState => ErrorClass::InsufficientFunds,
+        CompositionError::Construction(err) => construction_class(err),
+        CompositionError::Resolver(_) => ErrorClass::Connectivity,
+        CompositionError::InvoiceExpired
+        | CompositionError::FrozenOutput(_)
+        | CompositionError::NothingToConsolidate
+        | CompositionError::TemplateExhausted(_)
+        | CompositionError::InvalidAmountRange(_)
+        | CompositionError::AmountNotNegotiable(_)
+        | CompositionError::AmountOutOfRange(..)
+        | CompositionError::InvalidSplit(_)
+        | CompositionError::InvalidWitnessSats(_)
+        | CompositionError::InvalidVelocity(_)
+        | CompositionError::CloseMethodUnsupported(_)
+        | CompositionError::DuplicatePayment(..)
+        | CompositionError::InvalidExpiryHeight(_)
+        | CompositionError::UnsupportedLayer1(_)
+        | CompositionError::NetworkMismatch(_) => ErrorClass::Input,
+        _ => ErrorClass::Other,
+    }
+}
+
+fn construction_class(err: &ConstructionError) -> ErrorClass {
+    match err {
+        ConstructionError::NoInputs
+        | ConstructionError::OutputExceedsInputs { .. }
+        | ConstructionError::NoFundsForFee { .. } => ErrorClass::InsufficientFunds,
+        _ => ErrorClass::Other,
+    }
+}
+
+/// Error object printed to stderr with `--json-errors`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(crate = "serde_crate", rename_all = "camelCase")]
+pub struct ErrorReport {
+    /// Class of the error.
+    pub class: ErrorClass,
+    /// Process exit code.
+    pub exit_code: u8,
+    /// Code identifying the exact error kind, as defined by [`ErrorCode`].
+    pub code: u16,
+    pub message: String,
+}
+
+impl From<&WalletError> for ErrorReport {
+    fn from(err: &WalletError) -> Self {
+        let class = ErrorClass::from(err);
+        ErrorReport {
+            class,
+            exit_code: class as u8,
+            code: err.error_code(),
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Reports the error on stderr, either as text or as a JSON object, and
+/// returns the exit code of the process.
+pub fn report(err: &WalletError, json: bool) -> ExitCode {
+    let report = ErrorReport::from(err);
+    if json {
+        let json = serde_json::to_string(&report).expect("error report is always serializable");
+        eprintln!("{json}");
+    } else {
+        eprintln!("Error: {}", report.message);
+    }
+    report.class.into()
+}
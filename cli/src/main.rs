@@ -31,27 +31,30 @@ extern crate serde_crate as serde;
 
 mod command;
 mod args;
+mod exit;
+mod shell;
+mod stdio;
 
 use std::process::ExitCode;
 
 use bpwallet::cli::{Config, Exec, LogLevel};
 use clap::Parser;
-use rgb::WalletError;
+use rgb::{has_flat_layout, WalletError};
 
 pub use crate::args::RgbArgs;
 pub use crate::command::Command;
 
 fn main() -> ExitCode {
-    if let Err(err) = run() {
-        eprintln!("Error: {err}");
-        ExitCode::FAILURE
+    let args = RgbArgs::parse();
+    let json_errors = args.json_errors;
+    if let Err(err) = run(args) {
+        exit::report(&err, json_errors)
     } else {
         ExitCode::SUCCESS
     }
 }
 
-fn run() -> Result<(), WalletError> {
-    let mut args = RgbArgs::parse();
+fn run(mut args: RgbArgs) -> Result<(), WalletError> {
     args.process();
     LogLevel::from_verbosity_flag_count(args.verbose).apply();
     trace!("Command-line arguments: {:#?}", &args);
@@ -61,6 +64,19 @@ fn run() -> Result<(), WalletError> {
         eprintln!("     by LNP/BP Standards Association\n");
     }
 
+    if args.command != Command::Migrate
+        && !args.general.no_prefix
+        && !args.general.base_dir().exists()
+        && has_flat_layout(&args.general.data_dir)
+    {
+        eprintln!(
+            "Warning: data directory `{}` keeps the data outside of the network directories; use \
+             `migrate` command to move them into `{}`",
+            args.general.data_dir.display(),
+            args.general.base_dir().display()
+        );
+    }
+
     let conf = Config::load(&args.conf_path("rgb"));
     debug!("Executing command: {:?}", args.command);
     args.exec(conf, "rgb")?;
@@ -0,0 +1,86 @@
+// RGB smart contracts for Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2023 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2023 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Interactive shell executing the commands one by one within a single
+//! process.
+//!
+//! Loading a large stock dominates the latency of a single command, thus the
+//! shell keeps the stock loaded between the commands and loads it again only
+//! once a command has modified the stock files. The stock stays locked for
+//! other processes until the shell exits.
+
+use std::env;
+use std::io::{self, BufRead, Write};
+
+use clap::Parser;
+use rgb::WalletError;
+
+use crate::{exit, Command, RgbArgs};
+
+const PROMPT: &str = "rgb> ";
+
+/// Reads the commands from the standard input and executes them until the
+/// input ends or the `exit` command is given.
+#[allow(clippy::result_large_err)]
+pub fn run() -> Result<(), WalletError> {
+    // Global arguments preceding the `shell` command are applied to each
+    // command of the session
+    let prefix = env::args()
+        .take_while(|arg| arg != "shell")
+        .collect::<Vec<_>>();
+    crate::args::keep_stock_loaded();
+
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        print!("{PROMPT}");
+        io::stdout().flush()?;
+        let Some(line) = lines.next() else {
+            println!();
+            break;
+        };
+        let line = line?;
+        match line.split_whitespace().next() {
+            None => continue,
+            Some("exit" | "quit") => break,
+            Some(_) => {}
+        }
+        let Some(words) = shlex::split(&line) else {
+            eprintln!("Error: unbalanced quotes");
+            continue;
+        };
+        let args = match RgbArgs::try_parse_from(prefix.iter().cloned().chain(words)) {
+            Ok(args) => args,
+            Err(err) => {
+                let _ = err.print();
+                continue;
+            }
+        };
+        if args.command == Command::Shell {
+            eprintln!("Error: the shell is already running");
+            continue;
+        }
+        let json_errors = args.json_errors;
+        if let Err(err) = crate::run(args) {
+            exit::report(&err, json_errors);
+        }
+    }
+    Ok(())
+}
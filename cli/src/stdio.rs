@@ -0,0 +1,159 @@
+// RGB smart contracts for Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2023 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2023 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Support of `-` in place of the consignment and PSBT file names, standing
+//! for the standard input or output, such that the commands can be combined in
+//! shell pipelines.
+//!
+//! Data read from files or STDIN may be either binary or ASCII-armored (base64
+//! for PSBTs), which is detected automatically. Data written to STDOUT is
+//! always armored.
+
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+use bpstd::psbt::{Psbt, PsbtVer};
+use psrgbt::RgbPsbt;
+use rgb::containers::{Contract, FileContent, Kit, Transfer, UniversalFile};
+use rgb::{TransferReader, WalletError};
+
+/// File name standing for STDIN or STDOUT.
+pub const STDIO: &str = "-";
+
+const PSBT_MAGIC: [u8; 5] = *b"psbt\xff";
+
+/// Checks whether the path stands for STDIN or STDOUT.
+pub fn is_stdio(path: &Path) -> bool { path.as_os_str() == STDIO }
+
+/// Ensures that at most one of the command outputs is written to STDOUT.
+pub fn check_stdout<'p>(outputs: impl IntoIterator<Item = &'p Path>) -> Result<(), io::Error> {
+    if outputs.into_iter().filter(|path| is_stdio(path)).count() > 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "only one of the command outputs can be written to STDOUT",
+        ));
+    }
+    Ok(())
+}
+
+/// Reads all the data from the file or, for `-`, from STDIN.
+pub fn read_input(path: &Path) -> Result<Vec<u8>, io::Error> {
+    if !is_stdio(path) {
+        return fs::read(path);
+    }
+    let mut data = vec![];
+    io::stdin().lock().read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Reads text from the file or, for `-`, from STDIN.
+pub fn read_text(path: &Path) -> Result<String, io::Error> {
+    String::from_utf8(read_input(path)?)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn armored(data: &[u8]) -> Option<&str> {
+    let s = std::str::from_utf8(data).ok()?;
+    s.trim_start().starts_with("-----BEGIN ").then_some(s)
+}
+
+/// Loads RGB container, either binary or ASCII-armored, from the file or, for
+/// `-`, from STDIN.
+#[allow(clippy::result_large_err)]
+pub fn load_content<T: FileContent + FromStr>(path: &Path) -> Result<T, WalletError>
+where T::Err: Display {
+    let data = read_input(path)?;
+    match armored(&data) {
+        Some(s) => T::from_str(s).map_err(|err| WalletError::Armored(err.to_string())),
+        None => Ok(T::load(data.as_slice())?),
+    }
+}
+
+/// Loads transfer consignment, either binary or ASCII-armored, from the file
+/// or, for `-`, from STDIN. Binary consignment is decoded with
+/// [`TransferReader`], one witness bundle at a time.
+#[allow(clippy::result_large_err)]
+pub fn load_transfer(path: &Path) -> Result<Transfer, WalletError> {
+    let data = read_input(path)?;
+    match armored(&data) {
+        Some(s) => Transfer::from_str(s).map_err(|err| WalletError::Armored(err.to_string())),
+        None => Ok(TransferReader::new(data.as_slice())?.read_all()?),
+    }
+}
+
+/// Loads any RGB container, either binary or ASCII-armored, from the file or,
+/// for `-`, from STDIN.
+#[allow(clippy::result_large_err)]
+pub fn load_universal(path: &Path) -> Result<UniversalFile, WalletError> {
+    let data = read_input(path)?;
+    let Some(s) = armored(&data) else {
+        return Ok(UniversalFile::load(data.as_slice())?);
+    };
+    Transfer::from_str(s)
+        .map(UniversalFile::from)
+        .or_else(|_| Contract::from_str(s).map(UniversalFile::from))
+        .or_else(|_| Kit::from_str(s).map(UniversalFile::from))
+        .map_err(|err| WalletError::Armored(err.to_string()))
+}
+
+/// Saves RGB container in the binary form into the file or, for `-`, prints
+/// it ASCII-armored to STDOUT.
+pub fn save_content<T: FileContent + Display>(content: &T, path: &Path) -> Result<(), io::Error> {
+    if is_stdio(path) {
+        println!("{content}");
+        Ok(())
+    } else {
+        content.save_file(path)
+    }
+}
+
+/// Loads PSBT, either binary or base64-encoded, from the file or, for `-`,
+/// from STDIN. RGB data in PSBTs produced by the older versions are upgraded
+/// to the current layout version.
+#[allow(clippy::result_large_err)]
+pub fn load_psbt(path: &Path) -> Result<Psbt, WalletError> {
+    let data = read_input(path)?;
+    let mut psbt = if data.starts_with(&PSBT_MAGIC) {
+        Psbt::decode(&mut data.as_slice())?
+    } else {
+        let s = String::from_utf8(data)
+            .map_err(|_| WalletError::PsbtParse(s!("neither binary nor base64-encoded")))?;
+        Psbt::from_str(s.trim()).map_err(|err| WalletError::PsbtParse(err.to_string()))?
+    };
+    psbt.rgb_upgrade()
+        .map_err(|err| WalletError::PsbtParse(err.to_string()))?;
+    Ok(psbt)
+}
+
+/// Saves PSBT of the given version in the binary form into the file or, for
+/// `-`, prints it base64-encoded to STDOUT.
+pub fn save_psbt(psbt: &Psbt, ver: PsbtVer, path: &Path) -> Result<(), io::Error> {
+    if is_stdio(path) {
+        println!("{psbt:ver$}", ver = ver as usize);
+        return Ok(());
+    }
+    let mut file = File::create(path)?;
+    psbt.encode(ver, &mut file)?;
+    Ok(())
+}
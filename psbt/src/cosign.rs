@@ -0,0 +1,202 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2023 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2023 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bpstd::psbt::{Input, PropKey, Psbt, ValueData};
+use bpstd::{Txid, XpubFp};
+use rgbstd::XChain;
+
+use crate::sign::merge_sigs;
+use crate::{ExtractError, RgbPsbt, PSBT_RGB_PREFIX};
+
+/// Location of the PSBT key-value map.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+pub enum PsbtLocation {
+    #[display("global map")]
+    Global,
+
+    #[display("input #{0}")]
+    Input(usize),
+
+    #[display("output #{0}")]
+    Output(usize),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum CosignError {
+    /// PSBT copy #{0} has transaction {2} different from the original
+    /// transaction {1}; probably it was tampered with.
+    TxMismatch(usize, Txid, Txid),
+
+    /// PSBT copy #{0} lacks RGB data in the {1} which are present in the
+    /// original PSBT; probably the software of the cosigner has stripped them.
+    RgbStripped(usize, PsbtLocation),
+
+    /// PSBT copy #{0} has RGB data in the {1} different from the original
+    /// PSBT; probably the software of the cosigner has altered them.
+    RgbAltered(usize, PsbtLocation),
+
+    /// RGB data committed in the PSBT do not match its transaction.
+    CommitmentMismatch,
+
+    #[from]
+    #[display(inner)]
+    Extract(ExtractError),
+}
+
+/// Signing progress of a single cosigner, identified by the fingerprint of
+/// its master key.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct CosignerStatus {
+    /// Inputs which can be signed by the cosigner.
+    pub inputs: BTreeSet<usize>,
+    /// Inputs which are already signed by the cosigner (or are finalized).
+    pub signed: BTreeSet<usize>,
+}
+
+impl CosignerStatus {
+    /// Detects whether the cosigner has signed all inputs it is able to sign.
+    pub fn is_complete(&self) -> bool { self.signed == self.inputs }
+}
+
+/// Coordination of multi-signature RGB transfers, where a PSBT with RGB data
+/// is passed around between cosigners.
+///
+/// Each cosigner signs its own copy of the PSBT; the copies are then merged,
+/// checking that RGB proprietary keys of all copies are kept intact, since
+/// the RGB data are required to complete the transfer after all the
+/// signatures are collected.
+pub trait RgbCosign {
+    /// Reports signing progress of each cosigner, identified by the
+    /// fingerprints of the master keys found in the input key derivations.
+    fn rgb_cosigners(&self) -> BTreeMap<XpubFp, CosignerStatus>;
+
+    /// Merges signatures from the PSBT copies signed by the cosigners.
+    ///
+    /// Errors if any of the copies has a different transaction, or if its RGB
+    /// proprietary keys were stripped or altered; in this case the PSBT is
+    /// left untouched. Copies are numbered from 1 in the errors.
+    ///
+    /// Returns number of inputs which got new signatures.
+    fn rgb_cosign_combine<'a>(
+        &mut self,
+        copies: impl IntoIterator<Item = &'a Psbt>,
+    ) -> Result<usize, CosignError>;
+}
+
+impl RgbCosign for Psbt {
+    fn rgb_cosigners(&self) -> BTreeMap<XpubFp, CosignerStatus> {
+        let mut cosigners = BTreeMap::<XpubFp, CosignerStatus>::new();
+        for input in self.inputs() {
+            let no = input.index();
+            let finalized = input.final_script_sig.is_some() || input.final_witness.is_some();
+            for (fp, signed) in input_signers(input) {
+                let status = cosigners.entry(fp).or_default();
+                status.inputs.insert(no);
+                if signed || finalized {
+                    status.signed.insert(no);
+                }
+            }
+        }
+        cosigners
+    }
+
+    fn rgb_cosign_combine<'a>(
+        &mut self,
+        copies: impl IntoIterator<Item = &'a Psbt>,
+    ) -> Result<usize, CosignError> {
+        if self.rgb_extract()?.witness_id() != XChain::Bitcoin(self.txid()) {
+            return Err(CosignError::CommitmentMismatch);
+        }
+        let copies = copies.into_iter().collect::<Vec<_>>();
+        for (no, copy) in copies.iter().enumerate() {
+            check_copy(self, copy, no + 1)?;
+        }
+
+        let mut updated = BTreeSet::new();
+        for copy in copies {
+            for (input, signed) in self.inputs_mut().zip(copy.inputs()) {
+                if merge_sigs(input, signed) {
+                    updated.insert(input.index());
+                }
+            }
+        }
+        Ok(updated.len())
+    }
+}
+
+/// Returns fingerprints of the cosigners able to sign the input, together
+/// with the flag whether they have signed it already.
+fn input_signers(input: &Input) -> BTreeMap<XpubFp, bool> {
+    let mut signers = BTreeMap::<XpubFp, bool>::new();
+    for (pk, origin) in &input.bip32_derivation {
+        *signers.entry(origin.master_fp()).or_default() |= input.partial_sigs.contains_key(pk);
+    }
+    let internal_key = input.tap_internal_key.map(|pk| pk.to_xonly_pk());
+    for (pk, derivation) in &input.tap_bip32_derivation {
+        let signed = (Some(*pk) == internal_key && input.tap_key_sig.is_some())
+            || input.tap_script_sig.keys().any(|(key, _)| key == pk);
+        *signers.entry(derivation.origin.master_fp()).or_default() |= signed;
+    }
+    signers
+}
+
+fn check_copy(original: &Psbt, copy: &Psbt, no: usize) -> Result<(), CosignError> {
+    if original.txid() != copy.txid() {
+        return Err(CosignError::TxMismatch(no, original.txid(), copy.txid()));
+    }
+    check_rgb_keys(&original.proprietary, &copy.proprietary, no, PsbtLocation::Global)?;
+    for (input, other) in original.inputs().zip(copy.inputs()) {
+        let location = PsbtLocation::Input(input.index());
+        check_rgb_keys(&input.proprietary, &other.proprietary, no, location)?;
+    }
+    for (output, other) in original.outputs().zip(copy.outputs()) {
+        let location = PsbtLocation::Output(output.index());
+        check_rgb_keys(&output.proprietary, &other.proprietary, no, location)?;
+    }
+    Ok(())
+}
+
+fn check_rgb_keys<'a>(
+    original: impl IntoIterator<Item = (&'a PropKey, &'a ValueData)>,
+    copy: impl IntoIterator<Item = (&'a PropKey, &'a ValueData)>,
+    no: usize,
+    location: PsbtLocation,
+) -> Result<(), CosignError> {
+    fn rgb_keys<'a>(
+        map: impl IntoIterator<Item = (&'a PropKey, &'a ValueData)>,
+    ) -> BTreeMap<&'a PropKey, &'a ValueData> {
+        map.into_iter()
+            .filter(|(key, _)| key.identifier == PSBT_RGB_PREFIX)
+            .collect()
+    }
+    let original = rgb_keys(original);
+    let copy = rgb_keys(copy);
+    if original.keys().any(|key| !copy.contains_key(key)) {
+        return Err(CosignError::RgbStripped(no, location));
+    }
+    if original != copy {
+        return Err(CosignError::RgbAltered(no, location));
+    }
+    Ok(())
+}
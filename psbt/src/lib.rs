@@ -23,20 +23,37 @@
 extern crate amplify;
 
 mod rgb;
+mod sign;
+mod cosign;
+mod order;
+mod taptree;
 
+use amplify::confinement::{self, Confined, U24};
+use amplify::FromSliceError;
 use bp::dbc::opret::OpretProof;
-use bp::dbc::tapret::TapretProof;
+use bp::Vout;
 pub use bpstd::psbt::*;
+pub use cosign::{CosignError, CosignerStatus, PsbtLocation, RgbCosign};
+pub use order::{OutputOrdering, OutputOrderingParseError, OutputsUnmodifiable, RgbOutputOrdering};
 pub use rgb::*;
 use rgbstd::containers::{AnchorSet, Batch, CloseMethodSet, Fascia, PubWitness, XPubWitness};
-use rgbstd::XChain;
+use rgbstd::{OpId, TxoSeal, XChain};
+pub use sign::{RgbSignRequest, SignRequestError};
+use strict_encoding::{DeserializeError, StrictDeserialize, StrictSerialize};
+pub use taptree::{
+    check_tapret_tree, tap_tree_root, tapret_path_proof, tapret_tree, TapTreeError, TapretOutExt,
+};
 
 pub use self::rgb::{
-    ProprietaryKeyRgb, RgbExt, RgbInExt, RgbOutExt, RgbPsbtError, PSBT_GLOBAL_RGB_TRANSITION,
-    PSBT_IN_RGB_CONSUMED_BY, PSBT_OUT_RGB_VELOCITY_HINT, PSBT_RGB_PREFIX,
+    OutputRole, ProprietaryKeyRgb, RgbExt, RgbInExt, RgbOutExt, RgbPsbtError,
+    UnsupportedRgbPsbtVersion, PSBT_GLOBAL_RGB_FASCIA, PSBT_GLOBAL_RGB_OUTPUT_ORDERING,
+    PSBT_GLOBAL_RGB_SUMMARY, PSBT_GLOBAL_RGB_SWAP_OFFER, PSBT_GLOBAL_RGB_SWAP_PRICE,
+    PSBT_GLOBAL_RGB_SWAP_REQUEST, PSBT_GLOBAL_RGB_TRANSITION, PSBT_GLOBAL_RGB_VERSION,
+    PSBT_IN_RGB_CONSUMED_BY, PSBT_IN_RGB_EXTERNAL, PSBT_OUT_RGB_ROLE, PSBT_OUT_RGB_VELOCITY_HINT,
+    PSBT_RGB_PREFIX, RGB_PSBT_VERSION, RGB_PSBT_VERSION_LEGACY,
 };
 
-#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
 #[display(doc_comments)]
 pub enum EmbedError {
     /// provided transaction batch references inputs which are absent from the
@@ -46,6 +63,13 @@ pub enum EmbedError {
     /// the provided PSBT is invalid since it doublespends on some of its
     /// inputs.
     PsbtRepeatedInputs,
+
+    /// RGB data are already committed to the PSBT and can't be changed.
+    AlreadyCommitted,
+
+    #[from]
+    #[display(inner)]
+    UnsupportedVersion(UnsupportedRgbPsbtVersion),
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
@@ -56,11 +80,50 @@ pub enum CommitError {
 
     #[from]
     Dbc(DbcPsbtError),
+
+    #[from]
+    TapTree(TapTreeError),
+
+    #[from]
+    Layout(LayoutError),
 }
 
-#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
 #[display(doc_comments)]
-pub enum ExtractError {}
+pub enum LayoutError {
+    /// {0} output was moved from position {1} to {2} after the RGB data were
+    /// embedded into the PSBT, without updating the state transitions.
+    OutputMoved(OutputRole, Vout, Vout),
+
+    /// state transition {0} assigns state to output {1}, which is absent from
+    /// the PSBT.
+    AbsentOutput(OpId, Vout),
+
+    #[from]
+    #[from(FromSliceError)]
+    #[display(inner)]
+    Rgb(RgbPsbtError),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum ExtractError {
+    /// PSBT doesn't contain RGB fascia; probably RGB data were not committed
+    /// to it yet.
+    NoFascia,
+
+    /// the size of RGB fascia data in PSBT exceeds 16 MB.
+    #[from(confinement::Error)]
+    FasciaTooBig,
+
+    /// RGB fascia data in PSBT are invalid. Details: {0}
+    #[from]
+    InvalidFascia(DeserializeError),
+
+    #[from]
+    #[display(inner)]
+    UnsupportedVersion(UnsupportedRgbPsbtVersion),
+}
 
 // TODO: Batch must be homomorphic by the outpoint type (chain)
 
@@ -69,10 +132,41 @@ pub trait RgbPsbt {
     #[allow(clippy::result_large_err)]
     fn rgb_commit(&mut self) -> Result<Fascia, CommitError>;
     fn rgb_extract(&self) -> Result<Fascia, ExtractError>;
+
+    /// Upgrades RGB data in PSBT produced by versions 0.10 and 0.11, which
+    /// don't specify the version of their layout, by marking them with the
+    /// current [`RGB_PSBT_VERSION`]. The layout of the RGB data itself hasn't
+    /// changed since, so the data are kept as they are. PSBTs without RGB data
+    /// are not modified.
+    ///
+    /// Returns the version of the RGB data before the upgrade.
+    fn rgb_upgrade(&mut self) -> Result<u8, UnsupportedRgbPsbtVersion>;
+
+    /// Checks that the outputs which have a role in the RGB transfer were not
+    /// moved after the RGB data were embedded into the PSBT, and that all
+    /// outputs referenced by the state transitions are present.
+    ///
+    /// Run by [`RgbPsbt::rgb_commit`], such that a counterparty reordering the
+    /// outputs (for instance, during a payjoin) can't silently break the
+    /// seals.
+    #[allow(clippy::result_large_err)]
+    fn rgb_verify_layout(&self) -> Result<(), LayoutError>;
+
+    /// Removes the embedded state transitions together with the information
+    /// on the inputs they consume and the roles of the outputs, such that the
+    /// state transitions can be composed and embedded once again after the
+    /// outputs were moved by another party. Velocity hints and host markers
+    /// of the outputs are kept.
+    ///
+    /// Errors if the RGB data are already committed to the PSBT.
+    fn rgb_reset(&mut self) -> Result<(), EmbedError>;
 }
 
 impl RgbPsbt for Psbt {
     fn rgb_embed(&mut self, batch: Batch) -> Result<(), EmbedError> {
+        self.rgb_version()?;
+        self.proprietary
+            .insert(PropKey::rgb_version(), vec![RGB_PSBT_VERSION].into());
         for info in batch {
             let contract_id = info.transition.contract_id;
             let mut inputs = info.inputs.release();
@@ -89,10 +183,19 @@ impl RgbPsbt for Psbt {
             self.push_rgb_transition(info.transition, info.method)
                 .expect("transitions are unique since they are in BTreeMap indexed by opid");
         }
+        for output in self.outputs_mut() {
+            if output.is_tapret_host() {
+                output.set_rgb_role(OutputRole::TapretHost);
+            } else if output.is_opret_host() {
+                output.set_rgb_role(OutputRole::OpretHost);
+            }
+        }
         Ok(())
     }
 
     fn rgb_commit(&mut self) -> Result<Fascia, CommitError> {
+        self.rgb_version().map_err(RgbPsbtError::from)?;
+        self.rgb_verify_layout()?;
         // Convert RGB data to MPCs? Or should we do it at the moment we add them... No,
         // since we may require more DBC methods with each additional state transition
         let bundles = self.rgb_bundles_to_mpc()?;
@@ -105,7 +208,7 @@ impl RgbPsbt for Psbt {
             .ok_or(RgbPsbtError::NoContracts)?;
         let (mut tapret_anchor, mut opret_anchor) = (None, None);
         if methods.has_tapret_first() {
-            tapret_anchor = Some(self.dbc_commit::<TapretProof>()?);
+            tapret_anchor = Some(taptree::tapret_commit(self)?);
         }
         if methods.has_opret_first() {
             opret_anchor = Some(self.dbc_commit::<OpretProof>()?);
@@ -118,14 +221,88 @@ impl RgbPsbt for Psbt {
         };
         // TODO: Use signed transaction here!
         let witness = PubWitness::with(self.to_unsigned_tx().into());
-        Ok(Fascia {
+        let fascia = Fascia {
             witness: XPubWitness::Bitcoin(witness),
             anchor,
             bundles,
-        })
+        };
+        // We keep fascia inside PSBT such that other parties of multi-party
+        // protocols can extract it without repeating the commitment
+        let serialized_fascia = fascia
+            .to_strict_serialized::<U24>()
+            .map_err(|_| RgbPsbtError::FasciaTooBig)?;
+        let _ = self.push_proprietary(PropKey::rgb_fascia(), serialized_fascia.release());
+        Ok(fascia)
     }
 
     fn rgb_extract(&self) -> Result<Fascia, ExtractError> {
-        todo!("implement RGB PSBT fascia extraction for multi-party protocols")
+        self.rgb_version()?;
+        let data = self
+            .proprietary(&PropKey::rgb_fascia())
+            .ok_or(ExtractError::NoFascia)?;
+        let data = Confined::try_from_iter(data.iter().copied())?;
+        Ok(Fascia::from_strict_serialized::<U24>(data)?)
+    }
+
+    fn rgb_upgrade(&mut self) -> Result<u8, UnsupportedRgbPsbtVersion> {
+        let version = self.rgb_version()?;
+        if version != RGB_PSBT_VERSION && self.has_rgb_data() {
+            self.proprietary
+                .insert(PropKey::rgb_version(), vec![RGB_PSBT_VERSION].into());
+        }
+        Ok(version)
+    }
+
+    fn rgb_reset(&mut self) -> Result<(), EmbedError> {
+        self.rgb_version()?;
+        if self.proprietary(&PropKey::rgb_fascia()).is_some() {
+            return Err(EmbedError::AlreadyCommitted);
+        }
+        self.proprietary.retain(|key, _| {
+            key.identifier != PSBT_RGB_PREFIX
+                || (key.subtype != PSBT_GLOBAL_RGB_TRANSITION
+                    && key.subtype != PSBT_GLOBAL_RGB_CLOSE_METHODS)
+        });
+        for input in self.inputs_mut() {
+            input.proprietary.retain(|key, _| {
+                key.identifier != PSBT_RGB_PREFIX || key.subtype != PSBT_IN_RGB_CONSUMED_BY
+            });
+        }
+        for output in self.outputs_mut() {
+            output.proprietary.retain(|key, _| {
+                key.identifier != PSBT_RGB_PREFIX || key.subtype != PSBT_OUT_RGB_ROLE
+            });
+        }
+        Ok(())
+    }
+
+    fn rgb_verify_layout(&self) -> Result<(), LayoutError> {
+        for output in self.outputs() {
+            if let Some((role, vout)) = output.rgb_role() {
+                if vout != output.vout() {
+                    return Err(LayoutError::OutputMoved(role, vout, output.vout()));
+                }
+            }
+        }
+
+        let count = self.outputs().count();
+        for contract_id in self.rgb_contract_ids()? {
+            for opid in self.rgb_op_ids(contract_id)? {
+                let Some(transition) = self.rgb_transition(opid)? else {
+                    continue;
+                };
+                for assigns in transition.assignments.values() {
+                    for no in 0..assigns.len_u16() {
+                        let Ok(Some(XChain::Bitcoin(seal))) = assigns.revealed_seal_at(no) else {
+                            continue;
+                        };
+                        if seal.txid().is_none() && seal.vout().to_usize() >= count {
+                            return Err(LayoutError::AbsentOutput(opid, seal.vout()));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
     }
 }
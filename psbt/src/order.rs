@@ -0,0 +1,191 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2023 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2023 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::str::FromStr;
+
+use bpstd::psbt::{KeyMap, PropKey, Psbt};
+
+use crate::ProprietaryKeyRgb;
+
+/// Strategy for ordering outputs of the witness transaction before the RGB
+/// state is assigned to them.
+///
+/// Placing the DBC host output first, as it was done historically, reveals to
+/// the chain observers which of the outputs carries the tapret commitment.
+/// Other strategies make the position of the host output indistinguishable
+/// from the positions of the non-taproot outputs. Since the tapret commitment
+/// is valid only in the first taproot output of the transaction, the host
+/// always precedes other taproot outputs, whatever strategy is used.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Display)]
+pub enum OutputOrdering {
+    /// Tapret host output goes first, the rest of the outputs keep the order
+    /// of their construction.
+    #[default]
+    #[display("host-first")]
+    HostFirst,
+
+    /// Lexicographical ordering by the amount and the script pubkey, as
+    /// defined by BIP-69.
+    ///
+    /// Tapret host output is ordered by its script pubkey before the tweak,
+    /// which is applied only when the RGB data are committed.
+    #[display("bip69")]
+    Bip69,
+
+    /// Pseudo-random permutation of the outputs, derived from the seed. The
+    /// seed is recorded in the PSBT, such that the ordering can be reproduced
+    /// from the PSBT with the original order of the outputs.
+    #[display("shuffle:{0}")]
+    Shuffle(u64),
+}
+
+/// Errors parsing [`OutputOrdering`] from a string.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum OutputOrderingParseError {
+    /// unknown output ordering strategy '{0}'; valid strategies are
+    /// `host-first`, `bip69` and `shuffle[:SEED]`.
+    Unknown(String),
+
+    /// invalid seed '{0}' for the output shuffling.
+    InvalidSeed(String),
+}
+
+/// PSBT outputs can't be reordered since the PSBT is not modifiable anymore.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub struct OutputsUnmodifiable;
+
+impl FromStr for OutputOrdering {
+    type Err = OutputOrderingParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "host-first" => Ok(OutputOrdering::HostFirst),
+            None if s == "bip69" => Ok(OutputOrdering::Bip69),
+            None if s == "shuffle" => Ok(OutputOrdering::shuffle()),
+            Some(("shuffle", seed)) => seed
+                .parse()
+                .map(OutputOrdering::Shuffle)
+                .map_err(|_| OutputOrderingParseError::InvalidSeed(seed.to_owned())),
+            _ => Err(OutputOrderingParseError::Unknown(s.to_owned())),
+        }
+    }
+}
+
+impl OutputOrdering {
+    /// Constructs shuffling strategy with a random seed.
+    pub fn shuffle() -> Self { OutputOrdering::Shuffle(RandomState::new().build_hasher().finish()) }
+
+    fn with_data(data: &[u8]) -> Option<Self> {
+        Some(match data {
+            [0] => OutputOrdering::HostFirst,
+            [1] => OutputOrdering::Bip69,
+            [2, seed @ ..] => OutputOrdering::Shuffle(u64::from_le_bytes(seed.try_into().ok()?)),
+            _ => return None,
+        })
+    }
+
+    fn to_data(self) -> Vec<u8> {
+        match self {
+            OutputOrdering::HostFirst => vec![0],
+            OutputOrdering::Bip69 => vec![1],
+            OutputOrdering::Shuffle(seed) => {
+                let mut data = vec![2];
+                data.extend(seed.to_le_bytes());
+                data
+            }
+        }
+    }
+}
+
+/// Ordering of the PSBT outputs according to the [`OutputOrdering`] strategy.
+pub trait RgbOutputOrdering {
+    /// Returns the strategy which was used to order the PSBT outputs, if it
+    /// was recorded.
+    ///
+    /// Invalid data are not reported as an error and are treated as an absent
+    /// strategy.
+    fn rgb_output_ordering(&self) -> Option<OutputOrdering>;
+
+    /// Reorders the outputs according to the strategy and records the
+    /// strategy in the PSBT. If the strategy places the tapret host after
+    /// another taproot output, the two outputs are swapped.
+    ///
+    /// Outputs must be reordered before the RGB data are embedded, since the
+    /// state transitions reference the outputs by their number. The caller is
+    /// responsible for locating its outputs once again after the reordering.
+    fn rgb_order_outputs(&mut self, ordering: OutputOrdering) -> Result<(), OutputsUnmodifiable>;
+}
+
+impl RgbOutputOrdering for Psbt {
+    fn rgb_output_ordering(&self) -> Option<OutputOrdering> {
+        self.proprietary(&PropKey::rgb_output_ordering())
+            .and_then(|data| OutputOrdering::with_data(data))
+    }
+
+    fn rgb_order_outputs(&mut self, ordering: OutputOrdering) -> Result<(), OutputsUnmodifiable> {
+        let outputs = self.outputs().collect::<Vec<_>>();
+        let mut order = (0..outputs.len()).collect::<Vec<_>>();
+        match ordering {
+            OutputOrdering::HostFirst => order.sort_by_key(|no| !outputs[*no].is_tapret_host()),
+            OutputOrdering::Bip69 => {
+                order.sort_by_key(|no| (outputs[*no].amount, outputs[*no].script.as_slice()))
+            }
+            OutputOrdering::Shuffle(seed) => shuffle(seed, &mut order),
+        }
+        // Tapret commitment is valid only in the first taproot output of the
+        // transaction, thus the host can't be preceded by other taproot outputs
+        let host = order.iter().position(|no| outputs[*no].is_tapret_host());
+        let first = order.iter().position(|no| outputs[*no].script.is_p2tr());
+        if let (Some(host), Some(first)) = (host, first) {
+            order.swap(host, first);
+        }
+
+        let mut ranks = vec![0; order.len()];
+        for (rank, no) in order.into_iter().enumerate() {
+            ranks[no] = rank;
+        }
+        self.sort_outputs_by(|output| ranks[output.vout().to_usize()])
+            .map_err(|_| OutputsUnmodifiable)?;
+        self.proprietary
+            .insert(PropKey::rgb_output_ordering(), ordering.to_data().into());
+        Ok(())
+    }
+}
+
+/// Permutes the items using Fisher-Yates shuffle driven by SplitMix64
+/// generator initialized with the seed.
+fn shuffle(seed: u64, items: &mut [usize]) {
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+    for i in (1..items.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
@@ -25,8 +25,8 @@ use amplify::confinement::{Confined, SmallOrdMap, U24};
 use amplify::{confinement, FromSliceError};
 use bp::dbc::Method;
 use bp::seals::txout::CloseMethod;
-use bpstd::psbt;
 use bpstd::psbt::{KeyAlreadyPresent, KeyMap, MpcPsbtError, PropKey, Psbt};
+use bpstd::{psbt, Vout};
 use commit_verify::mpc;
 use rgbstd::containers::{BundleDichotomy, VelocityHint};
 use rgbstd::{
@@ -50,12 +50,86 @@ pub const PSBT_GLOBAL_RGB_TRANSITION: u64 = 0x01;
 /// Proprietary key subtype for storing information on which closed methods
 /// should be used for each of RGB state transitions.
 pub const PSBT_GLOBAL_RGB_CLOSE_METHODS: u64 = 0x02;
+/// Proprietary key subtype for storing RGB invoice which is paid by the party
+/// proposing an atomic swap.
+pub const PSBT_GLOBAL_RGB_SWAP_OFFER: u64 = 0x03;
+/// Proprietary key subtype for storing RGB invoice which has to be paid by the
+/// counterparty accepting an atomic swap.
+pub const PSBT_GLOBAL_RGB_SWAP_REQUEST: u64 = 0x04;
+/// Proprietary key subtype for storing strict-serialized RGB fascia, produced
+/// by the party which has committed to the RGB data in the PSBT.
+pub const PSBT_GLOBAL_RGB_FASCIA: u64 = 0x05;
+/// Proprietary key subtype for storing the number of output and the amount of
+/// sats which has to be paid to the seller in an asset-for-sats swap.
+pub const PSBT_GLOBAL_RGB_SWAP_PRICE: u64 = 0x06;
+/// Proprietary key subtype for storing human-readable summary of the RGB data
+/// committed in the transaction, provided to the external signers.
+pub const PSBT_GLOBAL_RGB_SUMMARY: u64 = 0x07;
+/// Proprietary key subtype for storing the version of the layout of the RGB
+/// data in the PSBT, such that the software can detect PSBTs it can't
+/// interpret.
+pub const PSBT_GLOBAL_RGB_VERSION: u64 = 0x08;
+/// Proprietary key subtype for storing the strategy which was used to order
+/// the outputs of the witness transaction.
+pub const PSBT_GLOBAL_RGB_OUTPUT_ORDERING: u64 = 0x09;
+/// Proprietary key subtype for storing RGB invoice paid by the payjoin
+/// proposal, to which the receiver contributes its inputs.
+pub const PSBT_GLOBAL_RGB_PAYJOIN: u64 = 0x0A;
 /// Proprietary key subtype for storing RGB state transition operation id which
 /// consumes this input.
 pub const PSBT_IN_RGB_CONSUMED_BY: u64 = 0x01;
+/// Proprietary key subtype marking inputs spending outputs which are not
+/// derived from the wallet descriptor, such that they must be signed by their
+/// external owner.
+pub const PSBT_IN_RGB_EXTERNAL: u64 = 0x02;
 /// Proprietary key subtype for storing hint for the velocity of the state
 /// which can be assigned to the provided output.
 pub const PSBT_OUT_RGB_VELOCITY_HINT: u64 = 0x01;
+/// Proprietary key subtype for storing the role of the output in the RGB
+/// transfer, together with the number of the output at the moment the role was
+/// assigned.
+pub const PSBT_OUT_RGB_ROLE: u64 = 0x02;
+
+/// Version of the layout of the RGB data in PSBTs produced by this library.
+pub const RGB_PSBT_VERSION: u8 = 1;
+/// Version of the layout of the RGB data in PSBTs produced by versions 0.10
+/// and 0.11, which don't contain [`PSBT_GLOBAL_RGB_VERSION`] key.
+pub const RGB_PSBT_VERSION_LEGACY: u8 = 0;
+
+/// Role of a PSBT output in the RGB transfer. Outputs with a role must not
+/// change their position once the RGB data are embedded into the PSBT, since
+/// the state transitions reference them by their number.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[repr(u8)]
+pub enum OutputRole {
+    /// Output receiving the state paid to the beneficiary.
+    #[display("beneficiary")]
+    Beneficiary = 1,
+
+    /// Output receiving the change, both in sats and in the RGB state.
+    #[display("change")]
+    Change = 2,
+
+    /// Output hosting tapret commitment.
+    #[display("tapret host")]
+    TapretHost = 3,
+
+    /// Output hosting opret commitment.
+    #[display("opret host")]
+    OpretHost = 4,
+}
+
+impl OutputRole {
+    pub fn with_value(value: u8) -> Option<Self> {
+        Some(match value {
+            1 => OutputRole::Beneficiary,
+            2 => OutputRole::Change,
+            3 => OutputRole::TapretHost,
+            4 => OutputRole::OpretHost,
+            _ => return None,
+        })
+    }
+}
 
 /// Extension trait for static functions returning RGB-related proprietary keys.
 pub trait ProprietaryKeyRgb {
@@ -76,6 +150,78 @@ pub trait ProprietaryKeyRgb {
         }
     }
 
+    /// Constructs [`PSBT_GLOBAL_RGB_SWAP_OFFER`] proprietary key.
+    fn rgb_swap_offer() -> PropKey {
+        PropKey {
+            identifier: PSBT_RGB_PREFIX.to_owned(),
+            subtype: PSBT_GLOBAL_RGB_SWAP_OFFER,
+            data: none!(),
+        }
+    }
+
+    /// Constructs [`PSBT_GLOBAL_RGB_SWAP_REQUEST`] proprietary key.
+    fn rgb_swap_request() -> PropKey {
+        PropKey {
+            identifier: PSBT_RGB_PREFIX.to_owned(),
+            subtype: PSBT_GLOBAL_RGB_SWAP_REQUEST,
+            data: none!(),
+        }
+    }
+
+    /// Constructs [`PSBT_GLOBAL_RGB_FASCIA`] proprietary key.
+    fn rgb_fascia() -> PropKey {
+        PropKey {
+            identifier: PSBT_RGB_PREFIX.to_owned(),
+            subtype: PSBT_GLOBAL_RGB_FASCIA,
+            data: none!(),
+        }
+    }
+
+    /// Constructs [`PSBT_GLOBAL_RGB_SWAP_PRICE`] proprietary key.
+    fn rgb_swap_price() -> PropKey {
+        PropKey {
+            identifier: PSBT_RGB_PREFIX.to_owned(),
+            subtype: PSBT_GLOBAL_RGB_SWAP_PRICE,
+            data: none!(),
+        }
+    }
+
+    /// Constructs [`PSBT_GLOBAL_RGB_SUMMARY`] proprietary key.
+    fn rgb_summary() -> PropKey {
+        PropKey {
+            identifier: PSBT_RGB_PREFIX.to_owned(),
+            subtype: PSBT_GLOBAL_RGB_SUMMARY,
+            data: none!(),
+        }
+    }
+
+    /// Constructs [`PSBT_GLOBAL_RGB_VERSION`] proprietary key.
+    fn rgb_version() -> PropKey {
+        PropKey {
+            identifier: PSBT_RGB_PREFIX.to_owned(),
+            subtype: PSBT_GLOBAL_RGB_VERSION,
+            data: none!(),
+        }
+    }
+
+    /// Constructs [`PSBT_GLOBAL_RGB_OUTPUT_ORDERING`] proprietary key.
+    fn rgb_output_ordering() -> PropKey {
+        PropKey {
+            identifier: PSBT_RGB_PREFIX.to_owned(),
+            subtype: PSBT_GLOBAL_RGB_OUTPUT_ORDERING,
+            data: none!(),
+        }
+    }
+
+    /// Constructs [`PSBT_GLOBAL_RGB_PAYJOIN`] proprietary key.
+    fn rgb_payjoin() -> PropKey {
+        PropKey {
+            identifier: PSBT_RGB_PREFIX.to_owned(),
+            subtype: PSBT_GLOBAL_RGB_PAYJOIN,
+            data: none!(),
+        }
+    }
+
     /// Constructs [`PSBT_IN_RGB_CONSUMED_BY`] proprietary key.
     fn rgb_in_consumed_by(contract_id: ContractId) -> PropKey {
         PropKey {
@@ -85,6 +231,15 @@ pub trait ProprietaryKeyRgb {
         }
     }
 
+    /// Constructs [`PSBT_IN_RGB_EXTERNAL`] proprietary key.
+    fn rgb_in_external() -> PropKey {
+        PropKey {
+            identifier: PSBT_RGB_PREFIX.to_owned(),
+            subtype: PSBT_IN_RGB_EXTERNAL,
+            data: none!(),
+        }
+    }
+
     /// Constructs [`PSBT_OUT_RGB_VELOCITY_HINT`] proprietary key.
     fn rgb_out_velocity_hint() -> PropKey {
         PropKey {
@@ -93,10 +248,32 @@ pub trait ProprietaryKeyRgb {
             data: none!(),
         }
     }
+
+    /// Constructs [`PSBT_OUT_RGB_ROLE`] proprietary key.
+    fn rgb_out_role() -> PropKey {
+        PropKey {
+            identifier: PSBT_RGB_PREFIX.to_owned(),
+            subtype: PSBT_OUT_RGB_ROLE,
+            data: none!(),
+        }
+    }
 }
 
 impl ProprietaryKeyRgb for PropKey {}
 
+/// RGB data in PSBT use a layout which is not supported by this library.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum UnsupportedRgbPsbtVersion {
+    /// RGB data in PSBT use layout version {0}, which is newer than the one
+    /// supported by this software; the PSBT was probably produced by a newer
+    /// version of the wallet.
+    Newer(u8),
+
+    /// RGB data layout version in PSBT is malformed.
+    Malformed,
+}
+
 /// Errors processing RGB-related proprietary PSBT keys and their values.
 #[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
 #[display(doc_comments)]
@@ -138,6 +315,9 @@ pub enum RgbPsbtError {
     /// the size of transition {0} exceeds 16 MB.
     TransitionTooBig(OpId),
 
+    /// the size of RGB fascia exceeds 16 MB.
+    FasciaTooBig,
+
     /// state transition data in PSBT are invalid. Details: {0}
     #[from]
     InvalidTransition(DeserializeError),
@@ -145,10 +325,26 @@ pub enum RgbPsbtError {
     #[from]
     #[display(inner)]
     Mpc(MpcPsbtError),
+
+    #[from]
+    #[display(inner)]
+    UnsupportedVersion(UnsupportedRgbPsbtVersion),
 }
 
 #[allow(clippy::result_large_err)]
 pub trait RgbExt {
+    /// Returns the version of the layout of the RGB data in the PSBT, which is
+    /// [`RGB_PSBT_VERSION_LEGACY`] for PSBTs without
+    /// [`PSBT_GLOBAL_RGB_VERSION`] key.
+    ///
+    /// # Errors
+    ///
+    /// If the version is not supported by this library or is malformed.
+    fn rgb_version(&self) -> Result<u8, UnsupportedRgbPsbtVersion>;
+
+    /// Detects whether the PSBT contains any RGB data.
+    fn has_rgb_data(&self) -> bool;
+
     fn rgb_contract_ids(&self) -> Result<BTreeSet<ContractId>, FromSliceError>;
 
     fn rgb_contract_consumers(
@@ -216,6 +412,28 @@ pub trait RgbExt {
 }
 
 impl RgbExt for Psbt {
+    fn rgb_version(&self) -> Result<u8, UnsupportedRgbPsbtVersion> {
+        let Some(data) = self.proprietary(&PropKey::rgb_version()) else {
+            return Ok(RGB_PSBT_VERSION_LEGACY);
+        };
+        match data.as_slice() {
+            [version] if *version <= RGB_PSBT_VERSION => Ok(*version),
+            [version] => Err(UnsupportedRgbPsbtVersion::Newer(*version)),
+            _ => Err(UnsupportedRgbPsbtVersion::Malformed),
+        }
+    }
+
+    fn has_rgb_data(&self) -> bool {
+        let is_rgb = |key: &PropKey| key.identifier == PSBT_RGB_PREFIX;
+        self.proprietary.keys().any(is_rgb)
+            || self
+                .inputs()
+                .any(|input| input.proprietary.keys().any(is_rgb))
+            || self
+                .outputs()
+                .any(|output| output.proprietary.keys().any(is_rgb))
+    }
+
     fn rgb_contract_ids(&self) -> Result<BTreeSet<ContractId>, FromSliceError> {
         self.inputs()
             .flat_map(|input| {
@@ -365,6 +583,17 @@ pub trait RgbInExt {
         contract_id: ContractId,
         opid: OpId,
     ) -> Result<bool, KeyAlreadyPresent>;
+
+    /// Detects whether the input spends an output which is not derived from
+    /// the wallet descriptor and must be signed externally.
+    fn is_rgb_external(&self) -> bool;
+
+    /// Marks the input as spending an output which must be signed externally.
+    ///
+    /// # Returns
+    ///
+    /// `false`, if the input was already marked and `true` otherwise.
+    fn set_rgb_external(&mut self) -> bool;
 }
 
 impl RgbInExt for psbt::Input {
@@ -393,6 +622,14 @@ impl RgbInExt for psbt::Input {
             Ok(Some(_)) => Err(KeyAlreadyPresent(key)),
         }
     }
+
+    fn is_rgb_external(&self) -> bool { self.proprietary.contains_key(&PropKey::rgb_in_external()) }
+
+    fn set_rgb_external(&mut self) -> bool {
+        self.proprietary
+            .insert(PropKey::rgb_in_external(), none!())
+            .is_none()
+    }
 }
 
 pub trait RgbOutExt {
@@ -413,6 +650,22 @@ pub trait RgbOutExt {
     /// `false`, if a velocity hint was already present in the input and
     /// `true` otherwise.
     fn set_rgb_velocity_hint(&mut self, hint: VelocityHint) -> bool;
+
+    /// Returns the role of the output in the RGB transfer together with the
+    /// number the output had when the role was assigned.
+    ///
+    /// Like with the velocity hint, invalid data are not reported as an error
+    /// and are treated as an absent role.
+    fn rgb_role(&self) -> Option<(OutputRole, Vout)>;
+
+    /// Assigns the role in the RGB transfer to the output, recording its
+    /// current number.
+    ///
+    /// # Returns
+    ///
+    /// `true`, if the output had no role or had a different role before, and
+    /// `false` otherwise.
+    fn set_rgb_role(&mut self, role: OutputRole) -> bool;
 }
 
 impl RgbOutExt for psbt::Output {
@@ -431,4 +684,22 @@ impl RgbOutExt for psbt::Output {
             .ok();
         Some(hint) == prev
     }
+
+    fn rgb_role(&self) -> Option<(OutputRole, Vout)> {
+        let data = self.proprietary.get(&PropKey::rgb_out_role())?;
+        let [role, vout @ ..] = data.as_slice() else {
+            return None;
+        };
+        let vout = <[u8; 4]>::try_from(vout).ok()?;
+        Some((OutputRole::with_value(*role)?, Vout::from_u32(u32::from_le_bytes(vout))))
+    }
+
+    fn set_rgb_role(&mut self, role: OutputRole) -> bool {
+        let vout = self.vout();
+        let prev = self.rgb_role();
+        let mut data = vec![role as u8];
+        data.extend(vout.to_u32().to_le_bytes());
+        self.push_proprietary(PropKey::rgb_out_role(), data).ok();
+        prev.map(|(prev, _)| prev) != Some(role)
+    }
 }
@@ -0,0 +1,169 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2023 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2023 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Write;
+
+use bpstd::psbt::{Input, KeyMap, PropKey, Psbt};
+use bpstd::Txid;
+use rgbstd::containers::{AnchorSet, Fascia};
+use rgbstd::{Operation, XChain};
+
+use crate::{ExtractError, ProprietaryKeyRgb, RgbPsbt, PSBT_RGB_PREFIX, RGB_PSBT_VERSION};
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum SignRequestError {
+    /// signed PSBT has transaction {1} different from the original
+    /// transaction {0}; probably it was tampered with.
+    TxMismatch(Txid, Txid),
+
+    /// RGB data committed in the PSBT do not match its transaction.
+    CommitmentMismatch,
+
+    #[from]
+    #[display(inner)]
+    Extract(ExtractError),
+}
+
+/// Workflow for signing PSBTs with RGB data by external (hardware or
+/// air-gapped) signers.
+pub trait RgbSignRequest {
+    /// Produces minimal PSBT for the external signer, which keeps all
+    /// information required for signing (key derivations, tapret host
+    /// information) but strips the RGB data, replacing them with a
+    /// human-readable summary.
+    ///
+    /// The PSBT must have RGB data already committed to it.
+    fn rgb_sign_request(&self) -> Result<Psbt, SignRequestError>;
+
+    /// Returns human-readable summary of the RGB data provided within a sign
+    /// request.
+    fn rgb_summary(&self) -> Option<String>;
+
+    /// Merges signatures from the PSBT returned by the external signer,
+    /// checking that the transaction and the RGB commitment in it were not
+    /// tampered with.
+    ///
+    /// Returns number of inputs which got new signatures.
+    fn rgb_combine(&mut self, signed: &Psbt) -> Result<usize, SignRequestError>;
+}
+
+impl RgbSignRequest for Psbt {
+    fn rgb_sign_request(&self) -> Result<Psbt, SignRequestError> {
+        let fascia = committed_fascia(self)?;
+        let mut psbt = self.clone();
+        psbt.proprietary
+            .retain(|key, _| key.identifier != PSBT_RGB_PREFIX);
+        for input in psbt.inputs_mut() {
+            input
+                .proprietary
+                .retain(|key, _| key.identifier != PSBT_RGB_PREFIX);
+        }
+        for output in psbt.outputs_mut() {
+            output
+                .proprietary
+                .retain(|key, _| key.identifier != PSBT_RGB_PREFIX);
+        }
+        let _ = psbt.push_proprietary(PropKey::rgb_version(), vec![RGB_PSBT_VERSION]);
+        let _ = psbt.push_proprietary(PropKey::rgb_summary(), summary(&fascia).into_bytes());
+        Ok(psbt)
+    }
+
+    fn rgb_summary(&self) -> Option<String> {
+        let data = self.proprietary(&PropKey::rgb_summary())?;
+        String::from_utf8(data.to_vec()).ok()
+    }
+
+    fn rgb_combine(&mut self, signed: &Psbt) -> Result<usize, SignRequestError> {
+        if self.txid() != signed.txid() {
+            return Err(SignRequestError::TxMismatch(self.txid(), signed.txid()));
+        }
+        committed_fascia(self)?;
+
+        let mut count = 0usize;
+        for (input, signed) in self.inputs_mut().zip(signed.inputs()) {
+            if merge_sigs(input, signed) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+/// Copies signatures absent from the `input` from its `signed` version,
+/// returning whether any new signatures were added.
+pub(crate) fn merge_sigs(input: &mut Input, signed: &Input) -> bool {
+    let mut updated = false;
+    for (pk, sig) in &signed.partial_sigs {
+        updated |= input.partial_sigs.insert(*pk, *sig).is_none();
+    }
+    for (key, sig) in &signed.tap_script_sig {
+        updated |= input.tap_script_sig.insert(*key, *sig).is_none();
+    }
+    if input.tap_key_sig.is_none() && signed.tap_key_sig.is_some() {
+        input.tap_key_sig = signed.tap_key_sig;
+        updated = true;
+    }
+    if input.final_script_sig.is_none() && signed.final_script_sig.is_some() {
+        input.final_script_sig = signed.final_script_sig.clone();
+        updated = true;
+    }
+    if input.final_witness.is_none() && signed.final_witness.is_some() {
+        input.final_witness = signed.final_witness.clone();
+        updated = true;
+    }
+    updated
+}
+
+fn committed_fascia(psbt: &Psbt) -> Result<Fascia, SignRequestError> {
+    let fascia = psbt.rgb_extract()?;
+    if fascia.witness_id() != XChain::Bitcoin(psbt.txid()) {
+        return Err(SignRequestError::CommitmentMismatch);
+    }
+    Ok(fascia)
+}
+
+fn summary(fascia: &Fascia) -> String {
+    let anchor = match fascia.anchor {
+        AnchorSet::Tapret(_) => "tapret",
+        AnchorSet::Opret(_) => "opret",
+        AnchorSet::Double { .. } => "tapret and opret",
+    };
+    let mut s =
+        format!("RGB data committed to transaction {} using {anchor}\n", fascia.witness_id());
+    for (contract_id, bundles) in &fascia.bundles {
+        let _ = writeln!(s, "contract {contract_id}");
+        for bundle in bundles.iter() {
+            for (opid, transition) in &bundle.known_transitions {
+                let _ = writeln!(
+                    s,
+                    "  transition {opid} of type {} spending {} input(s)",
+                    transition.transition_type,
+                    transition.inputs().len_u16()
+                );
+                for (ty, assigns) in transition.assignments.iter() {
+                    let _ = writeln!(s, "    {} assignment(s) of type {ty}", assigns.len_u16());
+                }
+            }
+        }
+    }
+    s
+}
@@ -0,0 +1,235 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Placement of tapret commitments into taproot outputs which already have
+//! script spending paths.
+//!
+//! The tapret commitment leaf is put at the depth 1 of the script tree, with
+//! the original tree becoming its sibling. The merkle path to the commitment
+//! is stored in the tapret proof as the partner node, which allows to spend
+//! the original script paths with the commitment in place.
+
+use amplify::num::u7;
+use bp::dbc::tapret::{TapretCommitment, TapretNodePartner, TapretPathProof, TapretProof};
+use bp::dbc::{Anchor, Method};
+use bpstd::psbt::{DbcPsbtError, KeyMap, Output, PropKey, Psbt, TapretKeyError};
+use bpstd::{
+    IntoTapHash, LeafInfo, ScriptPubkey, TapBranchHash, TapLeafHash, TapNodeHash, TapScript,
+    TapTree,
+};
+use commit_verify::{mpc, CommitVerify};
+
+use crate::CommitError;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum TapTreeError {
+    /// taproot script tree is too deep to host a tapret commitment.
+    TooDeep,
+
+    /// taproot script tree is invalid.
+    Invalid,
+
+    /// the original script tree already contains a tapret commitment.
+    AlternativeCommitment,
+
+    #[from]
+    #[display(inner)]
+    Tapret(TapretKeyError),
+}
+
+/// Computes merkle root of a taproot script tree with any number of leaves.
+pub fn tap_tree_root(tree: &TapTree) -> Result<TapNodeHash, TapTreeError> {
+    match tap_tree_nodes(tree, 0)?.as_slice() {
+        [(_, root)] => Ok(*root),
+        _ => Err(TapTreeError::Invalid),
+    }
+}
+
+/// Reduces leaves of the tree into the node hashes located at the provided
+/// depth, in the order of the depth-first traversal.
+fn tap_tree_nodes(tree: &TapTree, depth: u8) -> Result<Vec<(u8, TapNodeHash)>, TapTreeError> {
+    let mut stack = Vec::<(u8, TapNodeHash)>::with_capacity(tree.len());
+    for leaf in tree {
+        stack.push((leaf.depth.to_u8(), TapLeafHash::with_leaf_script(&leaf.script).into()));
+        while let [.., (d1, a), (d2, b)] = stack.as_slice() {
+            if d1 != d2 || *d2 <= depth {
+                break;
+            }
+            let node = (d2 - 1, TapBranchHash::with_nodes(*a, *b).into_tap_hash());
+            stack.truncate(stack.len() - 2);
+            stack.push(node);
+        }
+    }
+    if stack.is_empty() || stack.iter().any(|(d, _)| *d != depth) {
+        return Err(TapTreeError::Invalid);
+    }
+    Ok(stack)
+}
+
+/// Checks that the script tree can host tapret commitments: it leaves room
+/// for the commitment leaf and doesn't contain an alternative commitment
+/// which would be revealed by [`tapret_path_proof`].
+pub fn check_tapret_tree(original: &TapTree) -> Result<(), TapTreeError> {
+    if original
+        .iter()
+        .any(|leaf| leaf.depth.to_u8() >= u7::MAX.to_u8())
+    {
+        return Err(TapTreeError::TooDeep);
+    }
+    let partner = if let [leaf] = original.as_slice() {
+        TapretNodePartner::RightLeaf(leaf.script.clone())
+    } else {
+        match tap_tree_nodes(original, 1)?.as_slice() {
+            [(_, a), (_, b)] => TapretNodePartner::right_branch(*a, *b),
+            _ => return Err(TapTreeError::Invalid),
+        }
+    };
+    if !partner.check_no_commitment() {
+        return Err(TapTreeError::AlternativeCommitment);
+    }
+    Ok(())
+}
+
+/// Constructs script tree containing the tapret commitment leaf at the depth
+/// 1 and the original tree as its sibling. If there is no original tree, the
+/// commitment becomes the only leaf.
+pub fn tapret_tree(
+    original: Option<&TapTree>,
+    commitment: &TapretCommitment,
+) -> Result<TapTree, TapTreeError> {
+    let script_commitment = TapScript::commit(commitment);
+    let Some(original) = original else {
+        return Ok(TapTree::with_single_leaf(script_commitment));
+    };
+    let mut leaves = Vec::with_capacity(original.len() + 1);
+    for leaf in original {
+        let depth = u7::try_from(leaf.depth.to_u8() + 1).map_err(|_| TapTreeError::TooDeep)?;
+        leaves.push(LeafInfo {
+            depth,
+            script: leaf.script.clone(),
+        });
+    }
+    leaves.push(LeafInfo::tap_script(u7::ONE, script_commitment));
+    TapTree::from_leaves(leaves).map_err(|_| TapTreeError::Invalid)
+}
+
+/// Constructs merkle path proof for the tapret commitment placed into the tree
+/// by [`tapret_tree`].
+///
+/// If the original tree root hash precedes the commitment leaf hash, it is
+/// used as the left partner node, not revealing anything about the original
+/// scripts. Otherwise, the partner node must prove that it doesn't contain
+/// an alternative commitment, and the proof reveals either the original leaf
+/// script (for single-leaf trees) or the hashes of its two children.
+pub fn tapret_path_proof(
+    original: &TapTree,
+    commitment: &TapretCommitment,
+) -> Result<TapretPathProof, TapTreeError> {
+    let commitment_hash = TapScript::commit(commitment)
+        .tap_leaf_hash()
+        .into_tap_hash();
+    let root = tap_tree_root(original)?;
+    let partner = if root <= commitment_hash {
+        TapretNodePartner::LeftNode(root)
+    } else if let [leaf] = original.as_slice() {
+        TapretNodePartner::RightLeaf(leaf.script.clone())
+    } else {
+        match tap_tree_nodes(original, 1)?.as_slice() {
+            [(_, a), (_, b)] => TapretNodePartner::right_branch(*a, *b),
+            _ => return Err(TapTreeError::Invalid),
+        }
+    };
+    TapretPathProof::with(partner, commitment.nonce)
+        .map_err(|_| TapTreeError::AlternativeCommitment)
+}
+
+/// Extension trait for PSBT outputs hosting tapret commitments inside
+/// existing taproot script trees.
+pub trait TapretOutExt {
+    /// Assigns the tapret commitment to the output, preserving its existing
+    /// script tree, and updates the output script pubkey.
+    ///
+    /// For outputs without script tree this is equivalent to
+    /// [`Output::tapret_commit`].
+    fn tapret_commit_tree(
+        &mut self,
+        commitment: mpc::Commitment,
+    ) -> Result<TapretProof, TapTreeError>;
+}
+
+impl TapretOutExt for Output {
+    fn tapret_commit_tree(
+        &mut self,
+        commitment: mpc::Commitment,
+    ) -> Result<TapretProof, TapTreeError> {
+        let Some(original) = self.tap_tree.clone() else {
+            return Ok(self.tapret_commit(commitment)?);
+        };
+        if !self.script.is_p2tr() {
+            return Err(TapretKeyError::NotTaprootOutput.into());
+        }
+        if !self.is_tapret_host() {
+            return Err(TapretKeyError::TapretProhibited.into());
+        }
+        let internal_pk = self.tap_internal_key.ok_or(TapretKeyError::NoInternalKey)?;
+
+        let tapret_commitment = TapretCommitment::with(commitment, 0);
+        let tapret_proof = TapretProof {
+            path_proof: tapret_path_proof(&original, &tapret_commitment)?,
+            internal_pk,
+        };
+        let tap_tree = tapret_tree(Some(&original), &tapret_commitment)?;
+        let merkle_root = tap_tree_root(&tap_tree)?;
+
+        self.push_proprietary(PropKey::tapret_commitment(), &tapret_commitment)
+            .and_then(|_| self.push_proprietary(PropKey::tapret_proof(), &tapret_proof))
+            .map_err(|_| TapretKeyError::OutputAlreadyHasCommitment)?;
+
+        self.script = ScriptPubkey::p2tr(internal_pk, Some(merkle_root));
+        self.tap_tree = Some(tap_tree);
+
+        Ok(tapret_proof)
+    }
+}
+
+/// Creates tapret commitment in the first taproot output of the PSBT, which
+/// may already have a script tree.
+#[allow(clippy::result_large_err)]
+pub(crate) fn tapret_commit(
+    psbt: &mut Psbt,
+) -> Result<Anchor<mpc::MerkleBlock, TapretProof>, CommitError> {
+    if psbt.are_outputs_modifiable() {
+        return Err(DbcPsbtError::TxOutputsModifiable.into());
+    }
+    let output = psbt
+        .dbc_output_mut::<TapretProof>()
+        .ok_or(DbcPsbtError::NoProperOutput(Method::TapretFirst))?;
+    if output.tap_tree.is_none() {
+        return Ok(psbt.dbc_commit::<TapretProof>()?);
+    }
+    let (commitment, mpc_proof) = output.mpc_commit().map_err(DbcPsbtError::from)?;
+    if !output.is_tapret_host() {
+        return Err(DbcPsbtError::NoHostOutput.into());
+    }
+    let tapret_proof = output.tapret_commit_tree(commitment)?;
+    Ok(Anchor::new(mpc_proof, tapret_proof))
+}
@@ -0,0 +1,231 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Amendments of the contract global state, like renaming of an asset or
+//! change of its terms, performed via state extensions.
+//!
+//! The amendment redeems the valencies which the schema requires for the
+//! state extension and is distributed to the contract holders as a part of the
+//! contract consignment.
+
+use std::iter;
+
+use amplify::confinement::{TinyOrdMap, U16};
+use rgbstd::containers::{ConsignmentExt, Contract};
+use rgbstd::persistence::{IndexProvider, StashProvider, StateProvider, Stock};
+use rgbstd::{ContractId, DataState, Extension, GlobalState, Operation};
+use strict_types::{FieldName, StrictVal, TypeName};
+
+use crate::AmendError;
+
+/// Amendment of the contract global state.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Amendment {
+    contract_id: ContractId,
+    extension: Option<FieldName>,
+    globals: Vec<(FieldName, StrictVal)>,
+}
+
+impl Amendment {
+    pub fn new(contract_id: ContractId) -> Self {
+        Amendment {
+            contract_id,
+            extension: None,
+            globals: vec![],
+        }
+    }
+
+    /// Sets the name of the state extension performing the amendment. If not
+    /// set, the contract must provide a single state extension.
+    pub fn with_extension(mut self, name: FieldName) -> Self {
+        self.extension = Some(name);
+        self
+    }
+
+    /// Adds new value for the global state.
+    pub fn add_global(mut self, name: FieldName, value: StrictVal) -> Self {
+        self.globals.push((name, value));
+        self
+    }
+
+    pub fn contract_id(&self) -> ContractId { self.contract_id }
+
+    /// Finds the interface and the name of the state extension performing the
+    /// amendment.
+    fn resolve<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+    ) -> Result<(TypeName, FieldName), AmendError> {
+        let info = stock
+            .contract_info(self.contract_id)
+            .map_err(|e| e.to_string())?;
+        let schema = stock.schema(info.schema_id).map_err(|e| e.to_string())?;
+        let mut candidates = TinyOrdMap::<FieldName, TypeName>::new();
+        for (iface, iimpl) in &schema.iimpls {
+            for ext in &iimpl.extensions {
+                if matches!(&self.extension, Some(name) if *name != ext.name)
+                    || candidates.contains_key(&ext.name)
+                {
+                    continue;
+                }
+                candidates
+                    .insert(ext.name.clone(), iface.clone())
+                    .expect("schema extensions fit the map");
+            }
+        }
+        match (candidates.len(), &self.extension) {
+            (0, Some(name)) => Err(AmendError::UnknownExtension(self.contract_id, name.clone())),
+            (0, None) => Err(AmendError::NoExtension(self.contract_id)),
+            (1, _) => {
+                let (name, iface) = candidates.into_iter().next().expect("single item");
+                Ok((iface, name))
+            }
+            _ => Err(AmendError::AmbiguousExtension(self.contract_id)),
+        }
+    }
+
+    /// Composes state extension performing the amendment, validating the
+    /// provided global state against the contract schema.
+    pub fn compose<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+    ) -> Result<Extension, AmendError> {
+        let contract = stock
+            .export_contract(self.contract_id)
+            .map_err(|e| e.to_string())?;
+        self.compose_for(stock, &contract)
+    }
+
+    /// Produces contract consignment containing the amendment, which should be
+    /// distributed to the contract holders.
+    pub fn consign<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+    ) -> Result<Contract, AmendError> {
+        let mut contract = stock
+            .export_contract(self.contract_id)
+            .map_err(|e| e.to_string())?;
+        let extension = self.compose_for(stock, &contract)?;
+        contract
+            .extensions
+            .push(extension)
+            .map_err(|e| e.to_string())?;
+        Ok(contract)
+    }
+
+    fn compose_for<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+        contract: &Contract,
+    ) -> Result<Extension, AmendError> {
+        let (iface, name) = self.resolve(stock)?;
+        let contract_iface = stock
+            .contract_iface(self.contract_id, iface)
+            .map_err(|e| e.to_string())?;
+        let schema = &contract_iface.schema;
+        let iimpl = &contract_iface.iface;
+        let types = &contract_iface.types;
+
+        let extension_type = iimpl
+            .extension_type(&name)
+            .ok_or_else(|| AmendError::UnknownExtension(self.contract_id, name.clone()))?;
+        let extension_schema = schema
+            .extensions
+            .get(&extension_type)
+            .ok_or_else(|| AmendError::UnknownExtension(self.contract_id, name.clone()))?;
+
+        let mut globals = GlobalState::default();
+        for (global, value) in &self.globals {
+            let ty = iimpl
+                .global_type(global)
+                .ok_or_else(|| AmendError::UnknownGlobal(global.clone()))?;
+            if !extension_schema.globals.contains_key(&ty) {
+                return Err(AmendError::GlobalNotAllowed(global.clone(), name));
+            }
+            let sem_id = schema
+                .global_types
+                .get(&ty)
+                .expect("invalid schema implementation")
+                .sem_id;
+            let typed = types
+                .typify(value.clone(), sem_id)
+                .map_err(|e| AmendError::InvalidGlobal(global.clone(), e.to_string()))?;
+            let data = types
+                .strict_serialize_value::<U16>(&typed)
+                .map_err(|e| AmendError::InvalidGlobal(global.clone(), e.to_string()))?;
+            globals
+                .add_state(ty, DataState::from(data))
+                .map_err(|_| AmendError::GlobalOccurrences(global.to_string()))?;
+        }
+        for (ty, occurrences) in &extension_schema.globals {
+            let count = globals
+                .get(ty)
+                .map(|values| values.len())
+                .unwrap_or_default();
+            if occurrences.check(count as u16).is_err() {
+                let global = iimpl
+                    .global_name(*ty)
+                    .map(FieldName::to_string)
+                    .unwrap_or_else(|| ty.to_string());
+                return Err(AmendError::GlobalOccurrences(global));
+            }
+        }
+
+        let mut redeemed = TinyOrdMap::new();
+        for valency in &extension_schema.redeems {
+            let genesis = contract.genesis();
+            let opid = iter::once((genesis.id(), genesis.valencies()))
+                .chain(
+                    contract
+                        .bundled_witnesses()
+                        .flat_map(|bundle| bundle.known_transitions())
+                        .map(|transition| (transition.id(), transition.valencies())),
+                )
+                .find(|(_, valencies)| valencies.into_iter().any(|v| v == *valency))
+                .map(|(opid, _)| opid)
+                .ok_or_else(|| {
+                    AmendError::NoValency(
+                        iimpl
+                            .valency_name(*valency)
+                            .map(FieldName::to_string)
+                            .unwrap_or_else(|| valency.to_string()),
+                    )
+                })?;
+            redeemed
+                .insert(*valency, opid)
+                .expect("schema valencies fit the map");
+        }
+
+        Ok(Extension {
+            ffv: none!(),
+            contract_id: self.contract_id,
+            nonce: u64::MAX,
+            extension_type,
+            metadata: none!(),
+            globals,
+            assignments: none!(),
+            redeemed: redeemed.into(),
+            valencies: none!(),
+            validator: none!(),
+            witness: none!(),
+        })
+    }
+}
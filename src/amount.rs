@@ -0,0 +1,139 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use rgbstd::interface::AllocatedState;
+use rgbstd::persistence::{
+    IndexProvider, StashError, StashProvider, StateProvider, Stock, StockError,
+};
+use rgbstd::stl::AssetSpec;
+use rgbstd::{Amount, CoinAmount, ContractId, Precision};
+
+use crate::asset_spec;
+
+/// Formatter of the fungible amounts of a contract for presenting them to
+/// users, which adjusts the amounts for the contract precision and appends the
+/// asset ticker.
+///
+/// In the raw mode amounts are formatted as integers in the smallest units and
+/// without the ticker, which is useful for machine processing.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct AmountFormatter {
+    precision: Precision,
+    ticker: Option<String>,
+    raw: bool,
+}
+
+impl Default for AmountFormatter {
+    fn default() -> Self { Self::new(Precision::Indivisible, None) }
+}
+
+impl AmountFormatter {
+    pub fn new(precision: Precision, ticker: Option<String>) -> Self {
+        Self {
+            precision,
+            ticker: ticker.filter(|ticker| !ticker.is_empty()),
+            raw: false,
+        }
+    }
+
+    /// Constructs formatter which outputs amounts in the smallest units.
+    pub fn raw() -> Self {
+        let mut formatter = Self::default();
+        formatter.set_raw(true);
+        formatter
+    }
+
+    /// Constructs formatter using precision and ticker from the asset
+    /// specification.
+    pub fn with_spec(spec: &AssetSpec) -> Self {
+        Self::new(spec.precision, Some(spec.ticker.to_string()))
+    }
+
+    /// Constructs formatter for the contract known to the stock, taking the
+    /// precision and ticker from its asset specification. If none of the
+    /// interfaces implemented by the contract provides the specification, the
+    /// state is treated as indivisible.
+    pub fn with_stock<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        stock: &Stock<S, H, P>,
+        contract_id: ContractId,
+    ) -> Result<Self, StockError<S, H, P>> {
+        let info = stock.contract_info(contract_id)?;
+        let genesis = stock
+            .as_stash_provider()
+            .genesis(contract_id)
+            .map_err(StashError::<S>::from)?;
+        let schema = stock.schema(info.schema_id)?;
+        Ok(asset_spec(genesis, schema.iimpls.values())
+            .map(|spec| Self::with_spec(&spec))
+            .unwrap_or_default())
+    }
+
+    pub fn precision(&self) -> Precision { self.precision }
+
+    pub fn ticker(&self) -> Option<&str> { self.ticker.as_deref() }
+
+    pub fn is_raw(&self) -> bool { self.raw }
+
+    /// Switches the formatter into (or out of) the raw mode.
+    pub fn set_raw(&mut self, raw: bool) { self.raw = raw; }
+
+    /// Converts amount in the smallest units into the coin amount; in the raw
+    /// mode the amount is left in the smallest units.
+    pub fn coin_amount(&self, value: u64) -> CoinAmount {
+        let precision = if self.raw { Precision::Indivisible } else { self.precision };
+        CoinAmount::new(value, precision)
+    }
+
+    /// Formats amount provided in the smallest units as a decimal number,
+    /// without the asset ticker. Trailing zeros of the fractional part are
+    /// omitted.
+    pub fn format_amount(&self, value: u64) -> String {
+        if self.raw {
+            return value.to_string();
+        }
+        let (int, fract) = Amount::from(value).split(self.precision);
+        if fract == 0 {
+            return int.to_string();
+        }
+        let decimals = self.precision.decimals() as usize;
+        let fract = format!("{fract:0>decimals$}");
+        format!("{int}.{}", fract.trim_end_matches('0'))
+    }
+
+    /// Formats amount provided in the smallest units, appending the asset
+    /// ticker unless in the raw mode.
+    pub fn format(&self, value: u64) -> String {
+        let amount = self.format_amount(value);
+        match &self.ticker {
+            Some(ticker) if !self.raw => format!("{amount} {ticker}"),
+            _ => amount,
+        }
+    }
+
+    /// Formats allocated state, adjusting fungible amounts; other kinds of
+    /// state are formatted as is.
+    pub fn format_state(&self, state: &AllocatedState) -> String {
+        match state {
+            AllocatedState::Amount(amount) => self.format(amount.value()),
+            state => state.to_string(),
+        }
+    }
+}
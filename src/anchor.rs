@@ -0,0 +1,78 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of a single anchor against its witness transaction.
+//!
+//! Full consignment validation requires the whole contract history and a
+//! resolver of the witness transactions. Auditors and block explorers often
+//! need to check only whether a given transaction commits to a specific
+//! transition bundle, which is done with [`verify_anchor`].
+
+use bp::dbc::Proof;
+use bp::Tx;
+use commit_verify::mpc;
+use rgbstd::validation::EAnchor;
+use rgbstd::{BundleId, ContractId};
+
+use crate::AnchorError;
+
+/// Checks the two proofs of the `anchor`: convolves its multi-protocol
+/// commitment proof with the transition bundle `bundle_id` under the contract
+/// `contract_id`, and verifies the deterministic bitcoin commitment proof of
+/// the resulting commitment against the transaction `tx`.
+///
+/// Returns the multi-protocol commitment found in the transaction.
+///
+/// # Errors
+///
+/// With [`AnchorError::Mpc`] if the contract is not at the position of the
+/// multi-protocol commitment tree where the anchor proof puts it. Since the
+/// proof doesn't reveal the committed messages, a bundle other than the one
+/// committed by the anchor is detected only as a mismatch of the commitment in
+/// the transaction, reported with [`AnchorError::Dbc`].
+///
+/// This is not a verification of the witness: the function doesn't check
+/// that the transaction is mined, that its inputs close the seals spent by
+/// the bundle, that the bundle matches its transitions, or that the
+/// transitions are valid.
+pub fn verify_anchor(
+    tx: &Tx,
+    anchor: &EAnchor,
+    contract_id: ContractId,
+    bundle_id: BundleId,
+) -> Result<mpc::Commitment, AnchorError> {
+    let commitment =
+        anchor
+            .convolve(contract_id, bundle_id)
+            .map_err(|details| AnchorError::Mpc {
+                contract_id,
+                bundle_id,
+                details,
+            })?;
+    anchor
+        .dbc_proof
+        .verify(&commitment, tx)
+        .map_err(|details| AnchorError::Dbc {
+            txid: tx.txid(),
+            details,
+        })?;
+    Ok(commitment)
+}
@@ -0,0 +1,336 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+#[cfg(feature = "fs")]
+use amplify::confinement::U32 as ARCHIVE_MAX_LEN;
+use nonasync::persistence::Persisting;
+use rgbstd::containers::SealWitness;
+use rgbstd::persistence::{
+    IndexProvider, MemError, MemStash, StashWriteProvider, StateProvider, Stock,
+};
+use rgbstd::{
+    BundleId, ContractId, GraphSeal, SecretSeal, TransitionBundle, XChain, XOutpoint, XOutputSeal,
+    XWitnessId,
+};
+#[cfg(feature = "fs")]
+use strict_types::encoding::{StrictDeserialize, StrictSerialize};
+
+use crate::ArchiveError;
+
+/// Spent contract history moved out of the stock by [`StockCompaction::compact`].
+///
+/// The archive keeps transition bundles and witnesses which are not required
+/// for consigning the state retained by the stock. They have to be put back
+/// with [`StockCompaction::restore_archive`] before consigning the archived
+/// history again - for instance, when the state which was sent away returns
+/// to the wallet with a later transfer.
+#[derive(Debug)]
+pub struct StockArchive {
+    stash: MemStash,
+}
+
+impl Default for StockArchive {
+    fn default() -> Self { Self::new() }
+}
+
+impl StockArchive {
+    pub fn new() -> Self {
+        Self {
+            stash: MemStash::in_memory(),
+        }
+    }
+
+    /// Loads archive from a file previously saved with
+    /// [`StockArchive::save_file`].
+    #[cfg(feature = "fs")]
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self, ArchiveError> {
+        let stash = MemStash::strict_deserialize_from_file::<ARCHIVE_MAX_LEN>(path)?;
+        Ok(Self { stash })
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn save_file(&self, path: impl AsRef<Path>) -> Result<(), ArchiveError> {
+        self.stash
+            .strict_serialize_to_file::<ARCHIVE_MAX_LEN>(path)?;
+        Ok(())
+    }
+
+    pub fn bundle_count(&self) -> usize { self.stash.debug_bundles().len() }
+
+    pub fn witness_count(&self) -> usize { self.stash.debug_witnesses().len() }
+
+    pub fn is_empty(&self) -> bool { self.bundle_count() == 0 && self.witness_count() == 0 }
+
+    pub fn bundles(&self) -> impl Iterator<Item = &TransitionBundle> {
+        self.stash.debug_bundles().values()
+    }
+
+    pub fn witnesses(&self) -> impl Iterator<Item = &SealWitness> {
+        self.stash.debug_witnesses().values()
+    }
+
+    /// Adds data from other archive, allowing subsequent compactions to be
+    /// accumulated in a single archive file.
+    pub fn merge(&mut self, other: StockArchive) -> Result<(), ArchiveError> {
+        for bundle in other.bundles() {
+            self.stash.replace_bundle(bundle.clone())?;
+        }
+        for witness in other.witnesses() {
+            self.stash.replace_witness(witness.clone())?;
+        }
+        Ok(())
+    }
+}
+
+/// Compaction of the stock, moving the contract history which is no longer
+/// needed into a [`StockArchive`].
+pub trait StockCompaction {
+    /// Moves out of the stock all transition bundles and witnesses which are
+    /// not part of the history of the state assigned to the `retain` outputs
+    /// (and of the publicly-owned contract state), returning them as an
+    /// archive.
+    ///
+    /// The contract state and indexes are not affected, thus the stock keeps
+    /// reporting the same state; however, the archived history can't be
+    /// consigned until it is restored.
+    ///
+    /// The compacted stash is not persisted by this method, such that the
+    /// archive can be saved first: once it is done, the stash has to be
+    /// persisted by calling [`Persisting::mark_dirty`] on its provider.
+    #[allow(clippy::result_large_err)]
+    fn compact(
+        &mut self,
+        retain: impl IntoIterator<Item = impl Into<XOutpoint>>,
+    ) -> Result<StockArchive, ArchiveError>;
+
+    /// Puts the history from the archive back into the stock.
+    ///
+    /// Returns number of the restored transition bundles.
+    #[allow(clippy::result_large_err)]
+    fn restore_archive(&mut self, archive: &StockArchive) -> Result<usize, ArchiveError>;
+
+    /// Removes from the stash the secret seals matching the `prune`
+    /// predicate, returning the removed seals.
+    ///
+    /// Seals which are used by any contract operation known to the stash are
+    /// always retained, since they are required to reveal the state assigned
+    /// to them. Unlike [`StockCompaction::compact`], the stash is persisted
+    /// by this method.
+    #[allow(clippy::result_large_err)]
+    fn prune_secret_seals(
+        &mut self,
+        prune: impl Fn(&XChain<GraphSeal>) -> bool,
+    ) -> Result<BTreeSet<XChain<GraphSeal>>, ArchiveError>;
+}
+
+impl<H: StateProvider, P: IndexProvider> StockCompaction for Stock<MemStash, H, P> {
+    fn compact(
+        &mut self,
+        retain: impl IntoIterator<Item = impl Into<XOutpoint>>,
+    ) -> Result<StockArchive, ArchiveError> {
+        let retain = retain
+            .into_iter()
+            .map(Into::into)
+            .collect::<BTreeSet<XOutpoint>>();
+
+        // We use the consignments for the retained state to learn which
+        // history is still required, such that the stock remains able to
+        // produce exactly the same consignments after the compaction.
+        let mut bundle_ids = BTreeSet::<BundleId>::new();
+        let mut witness_ids = BTreeSet::<XWitnessId>::new();
+        let contract_ids = self
+            .as_stash_provider()
+            .debug_geneses()
+            .keys()
+            .copied()
+            .collect::<Vec<ContractId>>();
+        for contract_id in contract_ids {
+            let outputs = self
+                .contract_assignments_for(contract_id, retain.iter().copied())
+                .map_err(|e| e.to_string())?
+                .into_keys()
+                .collect::<Vec<XOutputSeal>>();
+            let consignment = self
+                .transfer(contract_id, outputs, None)
+                .map_err(|e| e.to_string())?;
+            for witness_bundle in consignment.bundles {
+                witness_ids.insert(witness_bundle.witness_id());
+                bundle_ids.extend(
+                    witness_bundle
+                        .anchored_bundles()
+                        .map(|(_, bundle)| bundle.bundle_id()),
+                );
+            }
+        }
+
+        let stash = self.as_stash_provider_mut();
+        let mut archive = StockArchive::new();
+        let mut compacted = MemStash::in_memory();
+        for (bundle_id, bundle) in stash.debug_bundles() {
+            if bundle_ids.contains(bundle_id) {
+                compacted.replace_bundle(bundle.clone())?;
+            } else {
+                archive.stash.replace_bundle(bundle.clone())?;
+            }
+        }
+        for (witness_id, witness) in stash.debug_witnesses() {
+            if witness_ids.contains(witness_id) {
+                compacted.replace_witness(witness.clone())?;
+            } else {
+                archive.stash.replace_witness(witness.clone())?;
+            }
+        }
+        if archive.is_empty() {
+            return Ok(archive);
+        }
+
+        copy_stash(stash, &mut compacted, |_| true)?;
+
+        *compacted.as_mut_persistence() = stash.as_mut_persistence().take();
+        *stash = compacted;
+
+        Ok(archive)
+    }
+
+    fn restore_archive(&mut self, archive: &StockArchive) -> Result<usize, ArchiveError> {
+        let stash = self.as_stash_provider_mut();
+        let mut count = 0usize;
+        for bundle in archive.bundles() {
+            if stash.replace_bundle(bundle.clone())? {
+                count += 1;
+            }
+        }
+        for witness in archive.witnesses() {
+            if !stash.debug_witnesses().contains_key(&witness.witness_id()) {
+                stash.replace_witness(witness.clone())?;
+            }
+        }
+        stash.mark_dirty();
+        Ok(count)
+    }
+
+    fn prune_secret_seals(
+        &mut self,
+        prune: impl Fn(&XChain<GraphSeal>) -> bool,
+    ) -> Result<BTreeSet<XChain<GraphSeal>>, ArchiveError> {
+        let stash = self.as_stash_provider_mut();
+
+        let mut used = BTreeSet::<XChain<SecretSeal>>::new();
+        for genesis in stash.debug_geneses().values() {
+            for assigns in genesis.assignments.values() {
+                used.extend(assigns.to_confidential_seals());
+            }
+        }
+        for extension in stash.debug_extensions().values() {
+            for assigns in extension.assignments.values() {
+                used.extend(assigns.to_confidential_seals());
+            }
+        }
+        for bundle in stash.debug_bundles().values() {
+            for transition in bundle.known_transitions.values() {
+                for assigns in transition.assignments.values() {
+                    used.extend(assigns.to_confidential_seals());
+                }
+            }
+        }
+
+        let pruned = stash
+            .debug_secret_seals()
+            .iter()
+            .filter(|seal| !used.contains(&seal.to_secret_seal()) && prune(seal))
+            .copied()
+            .collect::<BTreeSet<_>>();
+        if pruned.is_empty() {
+            return Ok(pruned);
+        }
+
+        let mut compacted = MemStash::in_memory();
+        for bundle in stash.debug_bundles().values() {
+            compacted.replace_bundle(bundle.clone())?;
+        }
+        for witness in stash.debug_witnesses().values() {
+            compacted.replace_witness(witness.clone())?;
+        }
+        copy_stash(stash, &mut compacted, |seal| !pruned.contains(seal))?;
+
+        *compacted.as_mut_persistence() = stash.as_mut_persistence().take();
+        *stash = compacted;
+        stash.mark_dirty();
+
+        Ok(pruned)
+    }
+}
+
+/// Copies all stash data except transition bundles and witnesses into the
+/// `compacted` stash, leaving out the secret seals not matching `retain_seal`.
+#[allow(clippy::result_large_err)]
+fn copy_stash(
+    stash: &MemStash,
+    compacted: &mut MemStash,
+    retain_seal: impl Fn(&XChain<GraphSeal>) -> bool,
+) -> Result<(), ArchiveError> {
+    // Interfaces must precede their implementations
+    for iface in stash.debug_ifaces().values() {
+        compacted.replace_iface(iface.clone())?;
+    }
+    for schema_ifaces in stash.debug_schemata().values() {
+        compacted.replace_schema(schema_ifaces.schema.clone())?;
+        for iimpl in schema_ifaces.iimpls.values() {
+            compacted.replace_iimpl(iimpl.clone())?;
+        }
+    }
+    for genesis in stash.debug_geneses().values() {
+        compacted.replace_genesis(genesis.clone())?;
+    }
+    for suppl in stash.debug_suppl().values().flatten() {
+        compacted.add_supplement(suppl.clone())?;
+    }
+    for extension in stash.debug_extensions().values() {
+        compacted.replace_extension(extension.clone())?;
+    }
+    for (id, attach) in stash.debug_attachments() {
+        compacted.replace_attachment(*id, attach.clone())?;
+    }
+    for seal in stash.debug_secret_seals() {
+        if retain_seal(seal) {
+            compacted.add_secret_seal(*seal)?;
+        }
+    }
+    compacted.consume_types(stash.debug_type_system().clone())?;
+    // Identities must precede the signatures, since otherwise the
+    // signatures are filtered by the default trust level
+    for (identity, trust) in stash.debug_identities() {
+        compacted
+            .set_trust(identity.clone(), *trust)
+            .map_err(MemError::from)?;
+    }
+    for lib in stash.debug_libs().values() {
+        compacted.replace_lib(lib.clone())?;
+    }
+    for (content_id, sigs) in stash.debug_sigs() {
+        compacted.import_sigs(*content_id, sigs.clone())?;
+    }
+    Ok(())
+}
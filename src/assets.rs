@@ -0,0 +1,210 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed metadata of the contracts following the RGB20 and RGB21 asset
+//! standards.
+//!
+//! The metadata are read from the contract global state through the standard
+//! interface implemented by the contract, such that the callers don't need to
+//! walk the strict value trees of the global state themselves.
+
+use rgbstd::info::ContractInfo;
+use rgbstd::interface::ContractIface;
+use rgbstd::invoice::{Amount, Precision, TokenIndex};
+use rgbstd::persistence::{ContractStateRead, IndexProvider, StashProvider, StateProvider, Stock};
+use rgbstd::stl::AssetSpec;
+use rgbstd::ContractId;
+use strict_types::{StrictVal, TypeName};
+
+use crate::{AssetInfoError, GLOBAL_ISSUED_SUPPLY, SPEC_GLOBAL};
+
+/// Prefix of the names of the interfaces of the RGB20 standard.
+const RGB20_PREFIX: &str = "RGB20";
+/// Prefix of the names of the interfaces of the RGB21 standard.
+const RGB21_PREFIX: &str = "RGB21";
+/// Name of the global state holding data of the RGB21 tokens.
+const TOKENS_GLOBAL: &str = "tokens";
+
+/// Metadata of a fungible asset following the RGB20 standard.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Rgb20Info {
+    pub contract_id: ContractId,
+    pub ticker: String,
+    pub name: String,
+    pub details: Option<String>,
+    pub precision: Precision,
+    /// Total amount issued by the genesis and the secondary issuances known
+    /// to the stock, in the smallest units.
+    pub supply: Amount,
+}
+
+impl Rgb20Info {
+    /// Reads metadata from the contract accessed through one of the RGB20
+    /// interfaces. Returns `None` if the interface doesn't provide the asset
+    /// specification.
+    pub fn with_contract<S: ContractStateRead>(contract: &ContractIface<S>) -> Option<Self> {
+        let spec = asset_spec(contract)?;
+        let supply = contract
+            .global(GLOBAL_ISSUED_SUPPLY)
+            .map(|values| {
+                values.fold(0u64, |sum, value| {
+                    sum.saturating_add(Amount::from_strict_val_unchecked(&value).value())
+                })
+            })
+            .unwrap_or_default();
+        Some(Self {
+            contract_id: contract.info.id,
+            ticker: spec.ticker.to_string(),
+            name: spec.name.to_string(),
+            details: spec.details.as_ref().map(ToString::to_string),
+            precision: spec.precision,
+            supply: Amount::from(supply),
+        })
+    }
+}
+
+/// Metadata of a single token of an RGB21 collection.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Rgb21Token {
+    pub index: TokenIndex,
+    pub ticker: Option<String>,
+    pub name: Option<String>,
+    pub details: Option<String>,
+}
+
+impl Rgb21Token {
+    fn from_strict_val(value: &StrictVal) -> Self {
+        let text = |field: &'static str| {
+            value
+                .unwrap_struct(field)
+                .unwrap_option()
+                .map(StrictVal::unwrap_string)
+        };
+        Self {
+            index: TokenIndex::from(value.unwrap_struct("index").unwrap_uint::<u32>()),
+            ticker: text("ticker"),
+            name: text("name"),
+            details: text("details"),
+        }
+    }
+}
+
+/// Metadata of a collectible asset following the RGB21 standard.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Rgb21Info {
+    pub contract_id: ContractId,
+    pub ticker: String,
+    pub name: String,
+    pub details: Option<String>,
+    /// Tokens of the collection, in the order they are defined by the
+    /// contract.
+    pub tokens: Vec<Rgb21Token>,
+}
+
+impl Rgb21Info {
+    /// Reads metadata from the contract accessed through one of the RGB21
+    /// interfaces. Returns `None` if the interface doesn't provide the asset
+    /// specification.
+    pub fn with_contract<S: ContractStateRead>(contract: &ContractIface<S>) -> Option<Self> {
+        let spec = asset_spec(contract)?;
+        let tokens = contract
+            .global(TOKENS_GLOBAL)
+            .map(|values| values.map(|value| Rgb21Token::from_strict_val(&value)).collect())
+            .unwrap_or_default();
+        Some(Self {
+            contract_id: contract.info.id,
+            ticker: spec.ticker.to_string(),
+            name: spec.name.to_string(),
+            details: spec.details.as_ref().map(ToString::to_string),
+            tokens,
+        })
+    }
+}
+
+/// Extension for [`ContractInfo`] providing typed metadata of the contracts
+/// following the common asset standards.
+pub trait AssetInfoExt {
+    /// Reads metadata of the RGB20 asset, returning `None` if the contract
+    /// doesn't implement any of the RGB20 interfaces.
+    fn as_rgb20<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+    ) -> Result<Option<Rgb20Info>, AssetInfoError>;
+
+    /// Reads metadata of the RGB21 collectible, returning `None` if the
+    /// contract doesn't implement any of the RGB21 interfaces.
+    fn as_rgb21<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+    ) -> Result<Option<Rgb21Info>, AssetInfoError>;
+}
+
+impl AssetInfoExt for ContractInfo {
+    fn as_rgb20<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+    ) -> Result<Option<Rgb20Info>, AssetInfoError> {
+        let Some(iface) = standard_iface(stock, self, RGB20_PREFIX)? else {
+            return Ok(None);
+        };
+        let contract = stock
+            .contract_iface(self.id, iface)
+            .map_err(|err| AssetInfoError::Stock(err.to_string()))?;
+        Ok(Rgb20Info::with_contract(&contract))
+    }
+
+    fn as_rgb21<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+    ) -> Result<Option<Rgb21Info>, AssetInfoError> {
+        let Some(iface) = standard_iface(stock, self, RGB21_PREFIX)? else {
+            return Ok(None);
+        };
+        let contract = stock
+            .contract_iface(self.id, iface)
+            .map_err(|err| AssetInfoError::Stock(err.to_string()))?;
+        Ok(Rgb21Info::with_contract(&contract))
+    }
+}
+
+/// Finds interface of the standard implemented by the contract schema.
+fn standard_iface<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &Stock<S, H, P>,
+    info: &ContractInfo,
+    prefix: &str,
+) -> Result<Option<TypeName>, AssetInfoError> {
+    let schema = stock
+        .schema(info.schema_id)
+        .map_err(|err| AssetInfoError::Stock(err.to_string()))?;
+    Ok(schema
+        .iimpls
+        .keys()
+        .find(|iface| iface.as_str().starts_with(prefix))
+        .cloned())
+}
+
+fn asset_spec<S: ContractStateRead>(contract: &ContractIface<S>) -> Option<AssetSpec> {
+    contract
+        .global(SPEC_GLOBAL)
+        .ok()
+        .and_then(|mut spec| spec.next())
+        .map(|spec| AssetSpec::from_strict_val_unchecked(&spec))
+}
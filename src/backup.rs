@@ -0,0 +1,207 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use amplify::confinement::{Confined, U32 as U32MAX};
+use commit_verify::{Digest, Sha256};
+use nonasync::persistence::{PersistenceError, PersistenceProvider};
+use rgbstd::persistence::{MemIndex, MemStash, MemState};
+use strict_types::encoding::{DecodeError, DeserializeError, StrictDeserialize, StrictSerialize};
+
+use crate::BackupStoreError;
+
+/// Number of previous copies of each stock file kept by [`BackupStore`] by
+/// default.
+pub const DEFAULT_STOCK_BACKUPS: u8 = 3;
+
+/// Extension added to the file name for the file keeping its checksum.
+const CHECKSUM_EXT: &str = "sha256";
+
+/// Extension added to the file name for the file which is being written.
+const TEMP_EXT: &str = "tmp";
+
+/// Stock component persisted as a separate file.
+trait StockFile: StrictSerialize + StrictDeserialize {
+    const FILE: &'static str;
+}
+
+impl StockFile for MemStash {
+    const FILE: &'static str = "stash.dat";
+}
+
+impl StockFile for MemState {
+    const FILE: &'static str = "state.dat";
+}
+
+impl StockFile for MemIndex {
+    const FILE: &'static str = "index.dat";
+}
+
+/// File-based persistence for the stash, state and index providers of the
+/// stock, keeping a number of previous versions of each file.
+///
+/// The files use the same names and format as the ones of
+/// [`rgbstd::persistence::fs::FsBinStore`], such that stocks created by it
+/// can be opened without any conversion. Each file is written next to the
+/// existing one and renamed into its place once completely written, while the
+/// previous versions are rotated into `<file>.1` ... `<file>.N` copies. The
+/// SHA256 checksum of each file is kept in `<file>.sha256`; if the checksum
+/// doesn't match or the file can't be decoded, the most recent valid backup
+/// is loaded instead.
+///
+/// Since the stash, state and index are saved independently, a backup of one
+/// of them may be older than the other two; in this case the stock may be
+/// reconstructed with [`crate::StockRecovery::recover_from_dir`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct BackupStore {
+    dir: PathBuf,
+    backups: u8,
+}
+
+impl BackupStore {
+    /// Creates store in the directory, creating the directory if it doesn't
+    /// exist, which keeps `backups` previous copies of each file.
+    pub fn new(dir: PathBuf, backups: u8) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir, backups })
+    }
+
+    /// Number of the previous copies kept for each file.
+    pub fn backups(&self) -> u8 { self.backups }
+
+    fn path(&self, file: &str, version: u8) -> PathBuf {
+        match version {
+            0 => self.dir.join(file),
+            no => self.dir.join(format!("{file}.{no}")),
+        }
+    }
+
+    fn load_component<T: StockFile>(&self) -> Result<T, BackupStoreError> {
+        let mut first_err = None;
+        for version in 0..=self.backups {
+            let path = self.path(T::FILE, version);
+            if version > 0 && !path.exists() {
+                break;
+            }
+            match load_verified(&path) {
+                Ok(object) => {
+                    if version > 0 {
+                        #[cfg(feature = "log")]
+                        log::warn!(
+                            "{} is damaged, using its backup from {}",
+                            T::FILE,
+                            path.display()
+                        );
+                    }
+                    return Ok(object);
+                }
+                Err(err) => {
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+        Err(first_err.expect("at least the primary file is tried"))
+    }
+
+    fn store_component<T: StockFile>(&self, object: &T) -> Result<(), BackupStoreError> {
+        let data = object.to_strict_serialized::<U32MAX>()?;
+        let path = self.path(T::FILE, 0);
+        let temp = with_ext(&path, TEMP_EXT);
+        write_synced(&temp, data.as_slice())?;
+        let checksum = checksum(data.as_slice());
+
+        for version in (0..self.backups).rev() {
+            let from = self.path(T::FILE, version);
+            if !from.exists() {
+                continue;
+            }
+            let to = self.path(T::FILE, version + 1);
+            let from_sum = with_ext(&from, CHECKSUM_EXT);
+            if from_sum.exists() {
+                fs::rename(from_sum, with_ext(&to, CHECKSUM_EXT))?;
+            } else {
+                // Remove stale checksum left by a more recent version
+                let _ = fs::remove_file(with_ext(&to, CHECKSUM_EXT));
+            }
+            fs::rename(from, to)?;
+        }
+
+        // The checksum is put in place before the data: if the process is
+        // interrupted in between, the primary file is absent and the most
+        // recent backup is used.
+        let temp_sum = with_ext(&temp, CHECKSUM_EXT);
+        write_synced(&temp_sum, checksum.as_bytes())?;
+        fs::rename(temp_sum, with_ext(&path, CHECKSUM_EXT))?;
+        fs::rename(temp, path)?;
+        Ok(())
+    }
+}
+
+impl<T: StockFile> PersistenceProvider<T> for BackupStore {
+    fn load(&self) -> Result<T, PersistenceError> {
+        self.load_component().map_err(PersistenceError::with)
+    }
+
+    fn store(&self, object: &T) -> Result<(), PersistenceError> {
+        self.store_component(object).map_err(PersistenceError::with)
+    }
+}
+
+fn with_ext(path: &Path, ext: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(ext);
+    PathBuf::from(path)
+}
+
+fn checksum(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn write_synced(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(data)?;
+    file.sync_all()
+}
+
+/// Loads the file, verifying its checksum if the checksum file is present.
+/// Files without checksum are the ones written by other stores and are
+/// accepted as long as they can be decoded.
+fn load_verified<T: StockFile>(path: &Path) -> Result<T, BackupStoreError> {
+    let data = fs::read(path)?;
+    match fs::read_to_string(with_ext(path, CHECKSUM_EXT)) {
+        Ok(expected) if expected.trim() != checksum(&data) => {
+            return Err(BackupStoreError::Checksum(path.to_owned()));
+        }
+        Ok(_) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+    let data = Confined::<Vec<u8>, 0, U32MAX>::try_from(data)
+        .map_err(|e| DeserializeError::Decode(DecodeError::from(e)))?;
+    Ok(T::from_strict_serialized(data)?)
+}
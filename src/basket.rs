@@ -0,0 +1,98 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use rgbstd::ContractId;
+
+use crate::invoice::RgbInvoice;
+use crate::BasketInvoiceError;
+
+/// Invoice requesting state under multiple contracts (for instance, some
+/// amount of fungible tokens together with an NFT), which are paid within a
+/// single bitcoin transaction.
+///
+/// Each leg of the basket is a normal invoice, which must specify a contract;
+/// all legs must use the same chain network, and each contract may be
+/// requested only once. In a string form the legs are separated by spaces.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct BasketInvoice(Vec<RgbInvoice>);
+
+impl BasketInvoice {
+    /// Constructs basket invoice out of the invoices for each of its legs.
+    pub fn new(legs: impl IntoIterator<Item = RgbInvoice>) -> Result<Self, BasketInvoiceError> {
+        let legs = legs.into_iter().collect::<Vec<_>>();
+        let first = legs.first().ok_or(BasketInvoiceError::Empty)?;
+        let chain_net = first.chain_network();
+        let mut contracts = Vec::<ContractId>::with_capacity(legs.len());
+        for invoice in &legs {
+            let contract_id = invoice.contract.ok_or(BasketInvoiceError::NoContract)?;
+            if contracts.contains(&contract_id) {
+                return Err(BasketInvoiceError::RepeatedContract(contract_id));
+            }
+            if invoice.chain_network() != chain_net {
+                return Err(BasketInvoiceError::ChainMismatch(contract_id));
+            }
+            contracts.push(contract_id);
+        }
+        Ok(Self(legs))
+    }
+
+    /// Returns invoices for each of the basket legs.
+    pub fn legs(&self) -> &[RgbInvoice] { &self.0 }
+
+    /// Returns contracts requested by the basket legs.
+    pub fn contract_ids(&self) -> impl Iterator<Item = ContractId> + '_ {
+        self.0
+            .iter()
+            .map(|invoice| invoice.contract.expect("checked on construction"))
+    }
+
+    /// Returns the earliest expiry time across the basket legs.
+    pub fn expiry(&self) -> Option<i64> { self.0.iter().filter_map(|invoice| invoice.expiry).min() }
+
+    pub fn into_legs(self) -> Vec<RgbInvoice> { self.0 }
+}
+
+impl Display for BasketInvoice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (no, invoice) in self.0.iter().enumerate() {
+            if no > 0 {
+                f.write_str(" ")?;
+            }
+            Display::fmt(invoice, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BasketInvoice {
+    type Err = BasketInvoiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let legs = s
+            .split_whitespace()
+            .map(RgbInvoice::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(legs)
+    }
+}
@@ -0,0 +1,211 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Child-pays-for-parent (CPFP) fee bumping of the witness transactions which
+//! pay to the wallet but can't get into the mempool or be mined due to a low
+//! feerate.
+//!
+//! The child transaction spends the wallet output of the parent witness
+//! transaction. Since spending the output closes the RGB seals defined over
+//! it, the state assigned to the output is moved to the change output of the
+//! child with blank transitions. Both transactions must be relayed together as
+//! a package, see [`crate::resolvers::AnyResolver::broadcast_package`].
+
+use std::collections::BTreeSet;
+
+use amplify::confinement::Confined;
+use bp::seals::txout::{CloseMethod, ExplicitSeal};
+use bp::{Outpoint, Sats, Tx, Txid, Vout};
+use bpstd::Weight;
+use psrgbt::Psbt;
+use rgbstd::containers::{Batch, BuilderSeal};
+use rgbstd::persistence::{IndexProvider, StashProvider, StateProvider, Stock};
+use rgbstd::validation::{ResolveWitness, WitnessResolverError};
+use rgbstd::{GraphSeal, XChain, XOutputSeal};
+
+use crate::supply::blank_transitions;
+use crate::CompositionError;
+
+/// Estimated virtual size of a signed transaction input spending P2WPKH
+/// output; inputs spending taproot outputs with a key path are smaller.
+pub const CPFP_INPUT_VSIZE: u32 = 68;
+/// Virtual size of a signed transaction input spending P2TR output with a key
+/// path (57.5 vbytes, rounded up).
+const TAPROOT_INPUT_VSIZE: u32 = 58;
+/// Virtual size of a segwit transaction fields other than inputs and outputs,
+/// assuming less than 253 inputs and outputs.
+const TX_OVERHEAD_VSIZE: u32 = 11;
+/// Virtual size of the witness of a signed input spending P2WPKH output,
+/// which is the largest one among the single-key inputs.
+const WITNESS_VSIZE: u32 = 27;
+/// Length of the opret commitment script, which is placed into the host
+/// output only once the PSBT is committed.
+const OPRET_SCRIPT_LEN: u32 = 34;
+
+/// Estimates virtual size of the transaction constructed from the PSBT once
+/// it is signed and committed to the RGB data.
+///
+/// The estimation accounts for the final size of the opret host output, which
+/// receives the commitment script only once the PSBT is committed. Tapret
+/// commitments tweak the output key without changing the script size.
+pub fn estimate_vsize(psbt: &Psbt) -> u32 {
+    let inputs = psbt
+        .inputs()
+        .map(|input| {
+            if input.prev_txout().script_pubkey.is_p2tr() {
+                TAPROOT_INPUT_VSIZE
+            } else {
+                CPFP_INPUT_VSIZE
+            }
+        })
+        .sum::<u32>();
+    let outputs = psbt
+        .outputs()
+        .map(|output| {
+            let script_len =
+                if output.is_opret_host() { OPRET_SCRIPT_LEN } else { output.script.len() as u32 };
+            // value and script length prefix
+            9 + script_len
+        })
+        .sum::<u32>();
+    TX_OVERHEAD_VSIZE + inputs + outputs
+}
+
+/// Estimates virtual size of a transaction with the given number of inputs and
+/// outputs before it is constructed, assuming the largest of the standard
+/// input and output types used by RGB wallets.
+pub(crate) fn estimate_vsize_for(inputs: usize, outputs: usize) -> u32 {
+    TX_OVERHEAD_VSIZE + inputs as u32 * CPFP_INPUT_VSIZE + outputs as u32 * (9 + OPRET_SCRIPT_LEN)
+}
+
+/// Computes feerate (in sats per virtual byte) which the transaction
+/// constructed from the PSBT pays once it is signed and committed, using the
+/// size from [`estimate_vsize`].
+///
+/// Returns `None` if the PSBT outputs spend more than its inputs provide.
+pub fn effective_feerate(psbt: &Psbt) -> Option<f64> {
+    let fee = psbt.fee()?;
+    Some(fee.sats() as f64 / estimate_vsize(psbt) as f64)
+}
+
+/// Computes virtual size of the transaction, estimating the witnesses of the
+/// inputs which were stripped of them, like in the witness transactions kept
+/// by the stock.
+pub(crate) fn tx_vsize(tx: &Tx) -> u32 {
+    let stripped = tx
+        .inputs()
+        .filter(|txin| txin.witness.is_empty() && txin.sig_script.is_empty())
+        .count() as u32;
+    // segwit marker and flag, which are absent when no input has a witness
+    let marker = if stripped as usize == tx.inputs.len() { 1 } else { 0 };
+    tx.vbytes().to_u32() + stripped * WITNESS_VSIZE + marker
+}
+
+/// Computes fee which must be paid by a child transaction of `child_vsize`,
+/// such that the package with its `parent` paying `parent_fee` reaches the
+/// `feerate` (in sats per virtual byte).
+///
+/// The child always pays at least the `feerate` for its own size, even if the
+/// parent fee is already sufficient. If the `parent` is stripped of its
+/// witnesses, their size is estimated.
+pub fn cpfp_fee(parent: &Tx, parent_fee: Sats, child_vsize: u32, feerate: u64) -> Sats {
+    let parent_vsize = tx_vsize(parent) as u64;
+    let child_vsize = child_vsize as u64;
+    let package_fee = feerate.saturating_mul(parent_vsize + child_vsize);
+    let fee = package_fee.saturating_sub(parent_fee.sats());
+    Sats::from_sats(fee.max(feerate.saturating_mul(child_vsize)))
+}
+
+/// Computes fee paid by the transaction, resolving the transactions which
+/// outputs it spends.
+pub fn witness_fee(tx: &Tx, resolver: &impl ResolveWitness) -> Result<Sats, WitnessResolverError> {
+    let mut input_value = Sats::ZERO;
+    for txin in tx.inputs() {
+        let prevout = txin.prev_output;
+        let prev_tx = resolve_bitcoin_tx(resolver, prevout.txid)?;
+        let value = prev_tx
+            .outputs
+            .get(prevout.vout.to_usize())
+            .map(|txout| txout.value)
+            .ok_or(WitnessResolverError::Unknown(XChain::Bitcoin(prevout.txid)))?;
+        input_value.saturating_add_assign(value);
+    }
+    let output_value = tx.outputs().map(|txout| txout.value).sum::<Sats>();
+    Ok(input_value.saturating_sub(output_value))
+}
+
+/// Resolves bitcoin transaction, failing if the resolver returns transaction
+/// of another layer 1.
+pub(crate) fn resolve_bitcoin_tx(
+    resolver: &impl ResolveWitness,
+    txid: Txid,
+) -> Result<Tx, WitnessResolverError> {
+    let expected = XChain::Bitcoin(txid);
+    match resolver.resolve_pub_witness(expected)? {
+        XChain::Bitcoin(tx) => Ok(tx),
+        other => Err(WitnessResolverError::IdMismatch {
+            actual: other.map_ref(Tx::txid),
+            expected,
+        }),
+    }
+}
+
+/// Returns outpoint of the seal held by the wallet, failing for the seals on
+/// layers 1 other than bitcoin, which can't be spent by the wallet.
+pub(crate) fn wallet_outpoint(seal: &XOutputSeal) -> Result<Outpoint, CompositionError> {
+    match seal {
+        XChain::Bitcoin(seal) => Ok(Outpoint::new(seal.txid, seal.vout)),
+        _ => Err(CompositionError::UnsupportedLayer1(seal.layer1())),
+    }
+}
+
+/// Returns seals which may be defined over the outpoint with any of the close
+/// methods.
+pub(crate) fn outpoint_seals(outpoint: Outpoint) -> BTreeSet<XOutputSeal> {
+    [CloseMethod::OpretFirst, CloseMethod::TapretFirst]
+        .into_iter()
+        .map(|method| XChain::Bitcoin(ExplicitSeal::new(method, outpoint)))
+        .collect()
+}
+
+/// Composes a batch of blank transitions moving the state of all contracts
+/// assigned to the spent `prev_outputs` to the `change` output of the child
+/// transaction.
+///
+/// Returns `None` if the spent outputs hold no RGB state.
+pub(crate) fn compose_bump<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &Stock<S, H, P>,
+    prev_outputs: &BTreeSet<XOutputSeal>,
+    method: CloseMethod,
+    change: Vout,
+) -> Result<Option<Batch>, CompositionError> {
+    let change_seal = || -> Result<BuilderSeal<GraphSeal>, CompositionError> {
+        Ok(BuilderSeal::Revealed(XChain::Bitcoin(GraphSeal::new_random_vout(method, change))))
+    };
+    let mut blanks = blank_transitions(stock, None, prev_outputs, change_seal)?.into_iter();
+    let Some(main) = blanks.next() else {
+        return Ok(None);
+    };
+    Ok(Some(Batch {
+        main,
+        blanks: Confined::try_from_iter(blanks).map_err(|e| e.to_string())?,
+    }))
+}
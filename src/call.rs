@@ -0,0 +1,257 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contract calls: state transitions of any type provided by the contract
+//! interfaces, composed from a YAML definition. This allows to operate
+//! contracts of custom schemata without writing code for each of their
+//! operations.
+//!
+//! The definition names the transition, lists allocations owned by the wallet
+//! which are spent by it and provides the new global state and assignments in
+//! the same form as [`crate::ContractDefinition`] does for the contract
+//! genesis:
+//!
+//! ```yaml
+//! interface: RGB20Fixed # optional
+//! transition: transfer
+//! inputs:
+//!   - <opid>/<type>/<no>
+//! globals:
+//!   name: value
+//! assignments:
+//!   assetOwner:
+//!     - seal: tapret1st:<txid>:<vout>
+//!       amount: 100
+//!     - amount: 50
+//! ```
+//!
+//! Assignments which don't specify a seal are assigned to the change output of
+//! the witness transaction. Other state assigned to the outputs spent by the
+//! call is moved to the change output as well. The definition is not checked
+//! against the schema validation rules (like conservation of the fungible
+//! amounts); making a valid call is up to the caller.
+
+use std::collections::BTreeSet;
+use std::io::Read;
+use std::str::FromStr;
+
+use amplify::confinement::Confined;
+use bp::seals::txout::CloseMethod;
+use bp::{Outpoint, Vout};
+use rgbstd::containers::{Batch, BuilderSeal, TransitionDichotomy, TransitionInfo};
+use rgbstd::interface::{IfaceImpl, TransitionIface};
+use rgbstd::persistence::{IndexProvider, StashProvider, StateProvider, Stock};
+use rgbstd::{ContractId, GraphSeal, Opout, XChain, XOutputSeal};
+use strict_types::{FieldName, TypeName};
+
+use crate::issue::DefinitionContext;
+use crate::supply::blank_transitions;
+use crate::{CallError, CompositionError, IssueProblem};
+
+/// Call of a contract state transition defined in YAML.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ContractCall {
+    contract_id: ContractId,
+    definition: serde_yaml::Mapping,
+}
+
+impl ContractCall {
+    pub fn new(contract_id: ContractId, definition: serde_yaml::Mapping) -> Self {
+        ContractCall {
+            contract_id,
+            definition,
+        }
+    }
+
+    /// Reads YAML definition of the call.
+    pub fn from_reader(contract_id: ContractId, reader: impl Read) -> Result<Self, CallError> {
+        let yaml = serde_yaml::from_reader::<_, serde_yaml::Value>(reader)
+            .map_err(|e| IssueProblem::Yaml(e.to_string()))?;
+        let serde_yaml::Value::Mapping(definition) = yaml else {
+            return Err(IssueProblem::InvalidStructure(s!("contract call"), "a mapping").into());
+        };
+        Ok(Self::new(contract_id, definition))
+    }
+
+    pub fn contract_id(&self) -> ContractId { self.contract_id }
+
+    /// Returns name of the called state transition.
+    pub fn transition(&self) -> Result<FieldName, CallError> {
+        let name = self
+            .definition
+            .get("transition")
+            .ok_or(IssueProblem::NoTransition)?
+            .as_str()
+            .ok_or(IssueProblem::InvalidStructure(s!("transition"), "a string"))?;
+        FieldName::try_from(name.to_owned())
+            .map_err(|e| IssueProblem::InvalidName(name.to_owned(), e.to_string()).into())
+    }
+
+    /// Returns name of the interface providing the transition, if specified.
+    pub fn interface(&self) -> Result<Option<TypeName>, CallError> {
+        let Some(name) = self.definition.get("interface") else {
+            return Ok(None);
+        };
+        let name = name
+            .as_str()
+            .ok_or(IssueProblem::InvalidStructure(s!("interface"), "a string"))?;
+        TypeName::try_from(name.to_owned())
+            .map(Some)
+            .map_err(|_| IssueProblem::UnknownInterface(name.to_owned()).into())
+    }
+
+    /// Returns allocations spent by the call.
+    pub fn inputs(&self) -> Result<BTreeSet<Opout>, CallError> {
+        let Some(inputs) = self.definition.get("inputs") else {
+            return Ok(none!());
+        };
+        let inputs = inputs
+            .as_sequence()
+            .ok_or(IssueProblem::InvalidStructure(s!("inputs"), "a list"))?;
+        let mut opouts = BTreeSet::new();
+        let mut problems = vec![];
+        for input in inputs {
+            match input.as_str().map(Opout::from_str) {
+                Some(Ok(opout)) => {
+                    opouts.insert(opout);
+                }
+                Some(Err(e)) => problems.push(IssueProblem::InvalidField(
+                    format!("input '{}'", input.as_str().unwrap_or_default()),
+                    e.to_string(),
+                )),
+                None => problems
+                    .push(IssueProblem::InvalidStructure(s!("input"), "an `<opid>/<type>/<no>`")),
+            }
+        }
+        if !problems.is_empty() {
+            return Err(problems.into());
+        }
+        Ok(opouts)
+    }
+
+    /// Finds the contract interface providing the called transition,
+    /// returning the interface name together with its implementation by the
+    /// contract schema and the transition definition.
+    fn resolve<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+    ) -> Result<(TypeName, IfaceImpl, TransitionIface), CompositionError> {
+        let name = self.transition()?;
+        let iface_name = self.interface()?;
+        let info = stock
+            .contract_info(self.contract_id)
+            .map_err(|e| e.to_string())?;
+        let schema = stock.schema(info.schema_id).map_err(|e| e.to_string())?;
+        for (iface, iimpl) in &schema.iimpls {
+            if matches!(&iface_name, Some(n) if n != iface)
+                || iimpl.transition_type(&name).is_none()
+            {
+                continue;
+            }
+            let iface_def = stock.iface(iface.clone()).map_err(|e| e.to_string())?;
+            if let Some(transition) = iface_def.transitions.get(&name) {
+                return Ok((iface.clone(), iimpl.clone(), transition.clone()));
+            }
+        }
+        Err(CompositionError::OperationUnsupported(self.contract_id, name.to_string()))
+    }
+
+    /// Selects outputs owned by the wallet which hold the allocations spent by
+    /// the call.
+    pub fn select_outputs<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+        owned: impl IntoIterator<Item = Outpoint>,
+    ) -> Result<BTreeSet<XOutputSeal>, CompositionError> {
+        let state = stock
+            .contract_assignments_for(self.contract_id, owned.into_iter().map(XChain::Bitcoin))
+            .map_err(|e| e.to_string())?;
+        self.inputs()?
+            .into_iter()
+            .map(|opout| {
+                state
+                    .iter()
+                    .find(|(_, assigns)| assigns.contains_key(&opout))
+                    .map(|(seal, _)| *seal)
+                    .ok_or(CompositionError::UnknownAllocation(opout))
+            })
+            .collect()
+    }
+
+    /// Composes a batch with the called state transition, which spends all
+    /// contract state assigned to `prev_outputs`.
+    ///
+    /// The state which is not listed in the call inputs, as well as the state
+    /// of other contracts assigned to the same outputs, is re-assigned to the
+    /// `change` output of the witness transaction.
+    pub fn compose<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+        prev_outputs: &BTreeSet<XOutputSeal>,
+        method: CloseMethod,
+        change: Option<Vout>,
+    ) -> Result<Batch, CompositionError> {
+        let contract_id = self.contract_id;
+        let name = self.transition()?;
+        let inputs = self.inputs()?;
+        let (iface, iimpl, transition) = self.resolve(stock)?;
+        let change_seal = || -> Result<BuilderSeal<GraphSeal>, CompositionError> {
+            let vout = change.ok_or(CompositionError::NoChange)?;
+            Ok(BuilderSeal::Revealed(XChain::Bitcoin(GraphSeal::new_random_vout(method, vout))))
+        };
+
+        let mut builder = stock
+            .transition_builder(contract_id, iface, Some(name.clone()))
+            .map_err(|e| e.to_string())?;
+        let state = stock
+            .contract_assignments_for(contract_id, prev_outputs.iter().copied())
+            .map_err(|e| e.to_string())?;
+        for (opout, state) in state.into_values().flatten() {
+            builder = builder.add_input(opout, state.clone())?;
+            if inputs.contains(&opout) {
+                continue;
+            }
+            match iimpl.assignment_name(opout.ty) {
+                Some(assignment) if transition.assignments.contains_key(assignment) => {
+                    builder = builder.add_owned_state_raw(opout.ty, change_seal()?, state)?;
+                }
+                _ => return Err(CompositionError::UnpreservedState(opout, name.to_string())),
+            }
+        }
+
+        let info = stock
+            .contract_info(contract_id)
+            .map_err(|e| e.to_string())?;
+        let schema_ifaces = stock.schema(info.schema_id).map_err(|e| e.to_string())?;
+        let mut ctx = DefinitionContext::new(schema_ifaces, &iimpl, builder, change_seal().ok());
+        ctx.add_state(&self.definition);
+        let builder = ctx.complete().map_err(CallError::from)?;
+
+        let outputs = prev_outputs.iter().copied().collect::<Vec<_>>();
+        let main = TransitionInfo::new(builder.complete_transition()?, outputs)
+            .map_err(|e| e.to_string())?;
+        let blanks = blank_transitions(stock, Some(contract_id), prev_outputs, change_seal)?;
+        Ok(Batch {
+            main: TransitionDichotomy::single(main),
+            blanks: Confined::try_from(blanks).map_err(|e| e.to_string())?,
+        })
+    }
+}
@@ -0,0 +1,286 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use amplify::confinement::{TinyString, U24 as COMPACT_MAX_LEN};
+use baid64::DisplayBaid64;
+use indexmap::IndexMap;
+use rgbstd::{AttachId, ContractId, SecretSeal};
+use strict_types::encoding::{
+    FieldName, StrictDecode, StrictEncode, StrictReader, StrictWriter, TypeName,
+};
+
+use crate::invoice::{
+    Allocation, Amount, Beneficiary, ChainNet, InvoiceState, NonFungible, Pay2Vout, RgbInvoice,
+    RgbInvoiceBuilder, RgbTransport, XChainNet,
+};
+use crate::CompactInvoiceError;
+
+/// Version of the compact binary invoice encoding, put in the first byte of
+/// the encoded data.
+pub const COMPACT_INVOICE_VERSION: u8 = 1;
+
+const CHAIN_NETS: [ChainNet; 6] = [
+    ChainNet::BitcoinMainnet,
+    ChainNet::BitcoinTestnet,
+    ChainNet::BitcoinSignet,
+    ChainNet::BitcoinRegtest,
+    ChainNet::LiquidMainnet,
+    ChainNet::LiquidTestnet,
+];
+
+const BENEFICIARY_BLINDED: u8 = 0;
+const BENEFICIARY_WITNESS: u8 = 1;
+
+const STATE_VOID: u8 = 0;
+const STATE_AMOUNT: u8 = 1;
+const STATE_ALLOCATION: u8 = 2;
+const STATE_ATTACH: u8 = 3;
+
+const TRANSPORT_JSON_RPC: u8 = 0;
+const TRANSPORT_REST_HTTP: u8 = 1;
+const TRANSPORT_WEB_SOCKETS: u8 = 2;
+const TRANSPORT_STORM: u8 = 3;
+const TRANSPORT_UNSPECIFIED: u8 = 4;
+
+/// Compact binary representation of an invoice.
+///
+/// The encoding uses strict encoding for the invoice components, keeping
+/// identifiers in binary form, which makes the data noticeably shorter than
+/// the baid64-based invoice string. This is mostly useful for QR codes, where
+/// the binary data are encoded in byte mode.
+pub trait CompactInvoice: Sized {
+    /// Encodes invoice into the compact binary form.
+    fn to_compact_bytes(&self) -> Result<Vec<u8>, CompactInvoiceError>;
+
+    /// Decodes invoice from the compact binary form.
+    fn from_compact_bytes(data: impl AsRef<[u8]>) -> Result<Self, CompactInvoiceError>;
+
+    /// Renders invoice in the compact binary form as a QR code made of
+    /// unicode block characters, suitable for printing to a terminal.
+    #[cfg(feature = "qr")]
+    fn to_qr_string(&self) -> Result<String, CompactInvoiceError> {
+        use qrcode::render::unicode::Dense1x2;
+        use qrcode::QrCode;
+
+        let code = QrCode::new(self.to_compact_bytes()?)?;
+        Ok(code
+            .render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .build())
+    }
+}
+
+impl CompactInvoice for RgbInvoice {
+    fn to_compact_bytes(&self) -> Result<Vec<u8>, CompactInvoiceError> {
+        let mut writer = Writer::new();
+
+        writer.write(&COMPACT_INVOICE_VERSION);
+        let chain_net = self.chain_network();
+        let chain_net = CHAIN_NETS
+            .iter()
+            .position(|cn| *cn == chain_net)
+            .ok_or(CompactInvoiceError::UnsupportedNetwork)?;
+        writer.write(&(chain_net as u8));
+        match self.beneficiary.into_inner() {
+            Beneficiary::BlindedSeal(seal) => {
+                writer.write(&BENEFICIARY_BLINDED);
+                writer.write(&seal);
+            }
+            Beneficiary::WitnessVout(pay2vout) => {
+                writer.write(&BENEFICIARY_WITNESS);
+                writer.write(&pay2vout.to_baid64_payload());
+            }
+        }
+
+        writer.write(&self.contract);
+        writer.write(&self.iface);
+        writer.write(&self.operation);
+        writer.write(&self.assignment);
+        match &self.owned_state {
+            InvoiceState::Void => writer.write(&STATE_VOID),
+            InvoiceState::Amount(amount) => {
+                writer.write(&STATE_AMOUNT);
+                writer.write(amount);
+            }
+            InvoiceState::Data(NonFungible::RGB21(allocation)) => {
+                writer.write(&STATE_ALLOCATION);
+                writer.write(allocation);
+            }
+            InvoiceState::Attach(attach_id) => {
+                writer.write(&STATE_ATTACH);
+                writer.write(attach_id);
+            }
+        }
+        writer.write(&self.expiry);
+
+        writer.write(&count(self.transports.len(), "transports")?);
+        for transport in &self.transports {
+            let (tag, tls, host) = match transport {
+                RgbTransport::JsonRpc { tls, host } => (TRANSPORT_JSON_RPC, *tls, host.as_str()),
+                RgbTransport::RestHttp { tls, host } => (TRANSPORT_REST_HTTP, *tls, host.as_str()),
+                RgbTransport::WebSockets { tls, host } => {
+                    (TRANSPORT_WEB_SOCKETS, *tls, host.as_str())
+                }
+                RgbTransport::Storm {} => {
+                    writer.write(&TRANSPORT_STORM);
+                    continue;
+                }
+                RgbTransport::UnspecifiedMeans => {
+                    writer.write(&TRANSPORT_UNSPECIFIED);
+                    continue;
+                }
+                _ => return Err(CompactInvoiceError::UnsupportedTransport),
+            };
+            writer.write(&tag);
+            writer.write(&tls);
+            writer.write(&tiny_string(host, "transport host")?);
+        }
+
+        writer.write(&count(self.unknown_query.len(), "query parameters")?);
+        for (key, value) in &self.unknown_query {
+            writer.write(&tiny_string(key, "query parameter")?);
+            writer.write(&tiny_string(value, "query parameter")?);
+        }
+
+        Ok(writer.finish())
+    }
+
+    fn from_compact_bytes(data: impl AsRef<[u8]>) -> Result<Self, CompactInvoiceError> {
+        let data = data.as_ref();
+        let mut reader = StrictReader::in_memory::<COMPACT_MAX_LEN>(data);
+
+        let version = u8::strict_decode(&mut reader)?;
+        if version != COMPACT_INVOICE_VERSION {
+            return Err(CompactInvoiceError::UnsupportedVersion(version));
+        }
+        let chain_net = u8::strict_decode(&mut reader)?;
+        let chain_net = *CHAIN_NETS
+            .get(chain_net as usize)
+            .ok_or(CompactInvoiceError::InvalidTag("network", chain_net))?;
+        let beneficiary = match u8::strict_decode(&mut reader)? {
+            BENEFICIARY_BLINDED => {
+                Beneficiary::BlindedSeal(SecretSeal::strict_decode(&mut reader)?)
+            }
+            BENEFICIARY_WITNESS => {
+                let payload = <[u8; 34]>::strict_decode(&mut reader)?;
+                Beneficiary::WitnessVout(Pay2Vout::try_from(payload)?)
+            }
+            wrong => return Err(CompactInvoiceError::InvalidTag("beneficiary", wrong)),
+        };
+        let mut builder = RgbInvoiceBuilder::new(XChainNet::with(chain_net, beneficiary));
+
+        if let Some(contract_id) = Option::<ContractId>::strict_decode(&mut reader)? {
+            builder = builder.set_contract(contract_id);
+        }
+        if let Some(iface) = Option::<TypeName>::strict_decode(&mut reader)? {
+            builder = builder.set_interface(iface);
+        }
+        if let Some(operation) = Option::<FieldName>::strict_decode(&mut reader)? {
+            builder = builder.set_operation(operation);
+        }
+        if let Some(assignment) = Option::<FieldName>::strict_decode(&mut reader)? {
+            builder = builder.set_assignment(assignment);
+        }
+        let owned_state = match u8::strict_decode(&mut reader)? {
+            STATE_VOID => InvoiceState::Void,
+            STATE_AMOUNT => InvoiceState::Amount(Amount::strict_decode(&mut reader)?),
+            STATE_ALLOCATION => {
+                InvoiceState::Data(NonFungible::RGB21(Allocation::strict_decode(&mut reader)?))
+            }
+            STATE_ATTACH => InvoiceState::Attach(AttachId::strict_decode(&mut reader)?),
+            wrong => return Err(CompactInvoiceError::InvalidTag("state", wrong)),
+        };
+        if let Some(expiry) = Option::<i64>::strict_decode(&mut reader)? {
+            builder = builder.set_expiry_timestamp(expiry);
+        }
+
+        let count = u8::strict_decode(&mut reader)?;
+        let mut transports = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let transport = match u8::strict_decode(&mut reader)? {
+                TRANSPORT_STORM => RgbTransport::Storm {},
+                TRANSPORT_UNSPECIFIED => RgbTransport::UnspecifiedMeans,
+                tag @ (TRANSPORT_JSON_RPC | TRANSPORT_REST_HTTP | TRANSPORT_WEB_SOCKETS) => {
+                    let tls = bool::strict_decode(&mut reader)?;
+                    let host = TinyString::strict_decode(&mut reader)?.release();
+                    match tag {
+                        TRANSPORT_JSON_RPC => RgbTransport::JsonRpc { tls, host },
+                        TRANSPORT_REST_HTTP => RgbTransport::RestHttp { tls, host },
+                        _ => RgbTransport::WebSockets { tls, host },
+                    }
+                }
+                wrong => return Err(CompactInvoiceError::InvalidTag("transport", wrong)),
+            };
+            transports.push(transport);
+        }
+
+        let params = u8::strict_decode(&mut reader)?;
+        let mut unknown_query = IndexMap::with_capacity(params as usize);
+        for _ in 0..params {
+            let key = TinyString::strict_decode(&mut reader)?.release();
+            let value = TinyString::strict_decode(&mut reader)?.release();
+            unknown_query.insert(key, value);
+        }
+
+        let read = reader.into_cursor().position() as usize;
+        if read < data.len() {
+            return Err(CompactInvoiceError::ExtraData(data.len() - read));
+        }
+
+        // Transports are set directly, since the builder always keeps at least
+        // one of them
+        let mut invoice = builder.finish();
+        invoice.transports = transports;
+        invoice.owned_state = owned_state;
+        invoice.unknown_query = unknown_query;
+        Ok(invoice)
+    }
+}
+
+struct Writer(Option<StrictWriter<strict_types::encoding::StreamWriter<Vec<u8>>>>);
+
+impl Writer {
+    fn new() -> Self { Self(Some(StrictWriter::in_memory::<COMPACT_MAX_LEN>())) }
+
+    fn write(&mut self, value: &impl StrictEncode) {
+        let writer = self.0.take().expect("writer is always present");
+        let writer = value
+            .strict_encode(writer)
+            .expect("invoice data are always less than the in-memory writer limit");
+        self.0 = Some(writer);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.0
+            .expect("writer is always present")
+            .unbox()
+            .unconfine()
+    }
+}
+
+fn count(len: usize, what: &'static str) -> Result<u8, CompactInvoiceError> {
+    u8::try_from(len).map_err(|_| CompactInvoiceError::Oversized(what))
+}
+
+fn tiny_string(s: &str, what: &'static str) -> Result<TinyString, CompactInvoiceError> {
+    TinyString::try_from(s.to_owned()).map_err(|_| CompactInvoiceError::Oversized(what))
+}
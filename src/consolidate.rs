@@ -0,0 +1,204 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Consolidation of the RGB allocations fragmented across many wallet outputs.
+//!
+//! Repeated transfers leave the wallet with many small change outputs, each
+//! holding a part of the contract state. Consolidation spends such outputs in
+//! a single transaction, moving all the state assigned to them to a single
+//! change output with blank transitions. Fungible state of each assignment
+//! type is merged into a single allocation, while non-fungible allocations
+//! are moved as they are.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use amplify::confinement::Confined;
+use bp::seals::txout::CloseMethod;
+use bp::{Outpoint, Sats, Vout};
+use rgbstd::containers::{Batch, BuilderSeal, TransitionDichotomy, TransitionInfo};
+use rgbstd::invoice::Amount;
+use rgbstd::persistence::{IndexProvider, PersistedState, StashProvider, StateProvider, Stock};
+use rgbstd::{AssignmentType, BlindingFactor, ContractId, GraphSeal, XChain, XOutputSeal};
+
+use crate::CompositionError;
+
+/// Wallet outputs which state has to be consolidated.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ConsolidationScope {
+    /// All wallet outputs holding the state of the contract. State of other
+    /// contracts assigned to the same outputs is consolidated as well.
+    Contract(ContractId),
+
+    /// Specific wallet outputs, with the state of all contracts assigned to
+    /// them.
+    Outpoints(BTreeSet<Outpoint>),
+}
+
+/// State of a single assignment type of a contract after the consolidation.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ConsolidatedState {
+    pub contract_id: ContractId,
+    pub assignment_type: AssignmentType,
+    /// Number of the allocations spent by the consolidation.
+    pub allocations_before: usize,
+    /// Number of the allocations assigned to the change output.
+    pub allocations_after: usize,
+    /// Total fungible amount, if the state is fungible.
+    pub amount: Option<Amount>,
+}
+
+/// Report on the consolidation, describing the resulting allocation layout.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ConsolidationReport {
+    /// Wallet outputs holding RGB state which are spent.
+    pub inputs: BTreeSet<Outpoint>,
+
+    /// Wallet outputs without RGB state which are spent to cover the fee.
+    pub fee_inputs: BTreeSet<Outpoint>,
+
+    /// State assigned to the change output.
+    pub state: Vec<ConsolidatedState>,
+
+    /// Output of the transaction receiving all the state.
+    pub change_vout: Vout,
+
+    /// Amount of sats left in the change output.
+    pub change: Sats,
+
+    /// Fee for the transaction.
+    pub fee: Sats,
+
+    /// Estimated virtual size of the transaction.
+    pub vsize: u32,
+}
+
+impl ConsolidationReport {
+    /// Returns the number of allocations spent by the consolidation.
+    pub fn allocations_before(&self) -> usize {
+        self.state
+            .iter()
+            .map(|state| state.allocations_before)
+            .sum()
+    }
+
+    /// Returns the number of allocations assigned to the change output.
+    pub fn allocations_after(&self) -> usize {
+        self.state.iter().map(|state| state.allocations_after).sum()
+    }
+}
+
+/// Composes a batch of blank transitions moving the state of all contracts
+/// assigned to the spent `prev_outputs` to the `change` output, merging
+/// fungible allocations of the same assignment type.
+///
+/// Returns `None` if the spent outputs hold no RGB state.
+#[allow(clippy::type_complexity)]
+pub(crate) fn compose_consolidation<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &Stock<S, H, P>,
+    prev_outputs: &BTreeSet<XOutputSeal>,
+    method: CloseMethod,
+    change: Vout,
+) -> Result<Option<(Batch, Vec<ConsolidatedState>)>, CompositionError> {
+    let change_seal =
+        || BuilderSeal::Revealed(XChain::Bitcoin(GraphSeal::new_random_vout(method, change)));
+    let contracts = stock
+        .contracts_assigning(prev_outputs.iter().copied())
+        .map_err(|e| e.to_string())?
+        .collect::<BTreeSet<_>>();
+    let mut transitions = Vec::with_capacity(contracts.len());
+    let mut report = vec![];
+    for id in contracts {
+        let info = stock.contract_info(id).map_err(|e| e.to_string())?;
+        let schema = stock.schema(info.schema_id).map_err(|e| e.to_string())?;
+        let Some(iface) = schema.iimpls.keys().next() else {
+            continue;
+        };
+        let mut builder = stock
+            .blank_builder(id, iface.clone())
+            .map_err(|e| e.to_string())?;
+        let mut fungible = BTreeMap::<AssignmentType, (usize, Amount)>::new();
+        let mut other = BTreeMap::<AssignmentType, usize>::new();
+        let mut outputs = Vec::new();
+        for (output, assigns) in stock
+            .contract_assignments_for(id, prev_outputs.iter().copied())
+            .map_err(|e| e.to_string())?
+        {
+            outputs.push(output);
+            for (opout, state) in assigns {
+                builder = builder.add_input(opout, state.clone())?;
+                if let PersistedState::Amount(amount, _, _) = state {
+                    let (count, sum) = fungible.entry(opout.ty).or_default();
+                    *count += 1;
+                    *sum += amount;
+                } else {
+                    builder = builder.add_owned_state_raw(opout.ty, change_seal(), state)?;
+                    *other.entry(opout.ty).or_default() += 1;
+                }
+            }
+        }
+        if !builder.has_inputs() {
+            continue;
+        }
+        for (ty, (count, sum)) in fungible {
+            // Blinding factor of the output is balanced by the builder
+            builder =
+                builder.add_fungible_state_raw(ty, change_seal(), sum, BlindingFactor::random())?;
+            report.push(ConsolidatedState {
+                contract_id: id,
+                assignment_type: ty,
+                allocations_before: count,
+                allocations_after: 1,
+                amount: Some(sum),
+            });
+        }
+        for (ty, count) in other {
+            report.push(ConsolidatedState {
+                contract_id: id,
+                assignment_type: ty,
+                allocations_before: count,
+                allocations_after: count,
+                amount: None,
+            });
+        }
+        let info = TransitionInfo::new(builder.complete_transition()?, outputs)
+            .map_err(|e| e.to_string())?;
+        transitions.push(TransitionDichotomy::single(info));
+    }
+    let mut transitions = transitions.into_iter();
+    let Some(main) = transitions.next() else {
+        return Ok(None);
+    };
+    let batch = Batch {
+        main,
+        blanks: Confined::try_from_iter(transitions).map_err(|e| e.to_string())?,
+    };
+    Ok(Some((batch, report)))
+}
@@ -0,0 +1,443 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Human-readable description of a contract schema and the interfaces it
+//! implements, allowing integrators to understand how to construct invoices
+//! and contract calls.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+use std::hash::Hash;
+
+use amplify::confinement::TinyOrdMap;
+use amplify::Wrapper;
+use rgbstd::interface::{Iface, IfaceImpl};
+use rgbstd::persistence::{
+    IndexProvider, SchemaIfaces, StashProvider, StateProvider, Stock, StockError,
+};
+use rgbstd::schema::{Occurrences, OwnedStateSchema, SchemaId};
+use rgbstd::stl::{bp_tx_stl, rgb_contract_stl};
+use strict_types::stl::std_stl;
+use strict_types::typesys::SystemBuilder;
+use strict_types::{SemId, SymbolicSys, TypeSystem};
+
+/// Interface implemented by a schema.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct InterfaceDescription {
+    pub name: String,
+    pub iface_id: String,
+    /// Operation used by the invoices which don't specify one explicitly.
+    pub default_operation: Option<String>,
+}
+
+/// Global state type defined by a schema.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct GlobalTypeDescription {
+    pub id: u16,
+    /// Name of the state under the implemented interfaces, if any.
+    pub name: Option<String>,
+    /// Semantic id of the strict type of the state.
+    pub sem_id: String,
+    /// Name of the strict type of the state, if it is a standard type.
+    pub type_name: Option<String>,
+    /// Maximal number of state items kept by the contract.
+    pub max_items: u32,
+}
+
+/// Owned state type defined by a schema.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct OwnedTypeDescription {
+    pub id: u16,
+    /// Name of the state under the implemented interfaces, if any.
+    pub name: Option<String>,
+    /// Kind of the state: `declarative`, `fungible`, `structured` or
+    /// `attachment`.
+    pub kind: String,
+    /// Semantic id of the strict type of the structured state.
+    pub sem_id: Option<String>,
+    /// Name of the strict type of the structured state, if it is a standard
+    /// type.
+    pub type_name: Option<String>,
+}
+
+/// State type used by an operation, with the number of its occurrences.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct OpStateDescription {
+    pub id: u16,
+    pub name: Option<String>,
+    pub min: u16,
+    /// Maximal number of occurrences; `None` for unlimited.
+    pub max: Option<u16>,
+}
+
+/// Genesis or state transition defined by a schema.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct OperationDescription {
+    /// Transition type; `None` for the genesis.
+    pub id: Option<u16>,
+    pub name: Option<String>,
+    pub globals: Vec<OpStateDescription>,
+    pub inputs: Vec<OpStateDescription>,
+    pub assignments: Vec<OpStateDescription>,
+    /// Assignment receiving the invoiced state, as defined by the interfaces.
+    pub default_assignment: Option<String>,
+}
+
+/// Strict type definition used by the schema state.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct TypeDefinition {
+    pub sem_id: String,
+    /// Fully qualified type name, if it is a standard type.
+    pub name: Option<String>,
+    pub definition: String,
+}
+
+/// Description of a schema, including its state types, operations and the
+/// strict type definitions of its state.
+///
+/// State and operation names are taken from the interface implementations;
+/// state types which are not exposed by any of the interfaces are left
+/// unnamed.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct SchemaDescription {
+    pub schema_id: SchemaId,
+    pub name: String,
+    pub developer: String,
+    pub interfaces: Vec<InterfaceDescription>,
+    pub global_types: Vec<GlobalTypeDescription>,
+    pub owned_types: Vec<OwnedTypeDescription>,
+    pub genesis: OperationDescription,
+    pub transitions: Vec<OperationDescription>,
+    pub types: Vec<TypeDefinition>,
+}
+
+impl SchemaDescription {
+    /// Describes schema with its interface implementations, using the
+    /// interface definitions to find the default assignments and the type
+    /// system to render the types of the state. Type names are resolved with
+    /// the provided symbols.
+    pub fn new<'a>(
+        schema_ifaces: &SchemaIfaces,
+        ifaces: impl IntoIterator<Item = &'a Iface>,
+        types: &TypeSystem,
+        symbols: &SymbolicSys,
+    ) -> Self {
+        let schema = &schema_ifaces.schema;
+        let iimpls = schema_ifaces.iimpls.values().collect::<Vec<_>>();
+        let ifaces = ifaces.into_iter().collect::<Vec<_>>();
+
+        let global_name = |id| {
+            iimpls
+                .iter()
+                .find_map(|iimpl| iimpl.global_name(id))
+                .map(|name| name.to_string())
+        };
+        let owned_name = |id| {
+            iimpls
+                .iter()
+                .flat_map(|iimpl| &iimpl.assignments)
+                .find(|field| field.id == id)
+                .map(|field| field.name.to_string())
+        };
+        let transition_name = |id| {
+            iimpls
+                .iter()
+                .flat_map(|iimpl| &iimpl.transitions)
+                .find(|field| field.id == id)
+                .map(|field| field.name.to_string())
+        };
+        let type_name = |sem_id: SemId| symbols.lookup(sem_id).map(|fqn| fqn.to_string());
+
+        let interfaces = ifaces
+            .iter()
+            .map(|iface| InterfaceDescription {
+                name: iface.name.to_string(),
+                iface_id: iface.iface_id().to_string(),
+                default_operation: iface.default_operation.as_ref().map(|op| op.to_string()),
+            })
+            .collect();
+
+        let global_types = schema
+            .global_types
+            .iter()
+            .map(|(id, global)| GlobalTypeDescription {
+                id: id.to_inner(),
+                name: global_name(*id),
+                sem_id: global.sem_id.to_string(),
+                type_name: type_name(global.sem_id),
+                max_items: global.max_items.to_u32(),
+            })
+            .collect();
+
+        let owned_types = schema
+            .owned_types
+            .iter()
+            .map(|(id, owned)| {
+                let (kind, sem_id) = match owned {
+                    OwnedStateSchema::Declarative => ("declarative", None),
+                    OwnedStateSchema::Fungible(_) => ("fungible", None),
+                    OwnedStateSchema::Structured(sem_id) => ("structured", Some(*sem_id)),
+                    OwnedStateSchema::Attachment(_) => ("attachment", None),
+                };
+                OwnedTypeDescription {
+                    id: id.to_inner(),
+                    name: owned_name(*id),
+                    kind: kind.to_owned(),
+                    sem_id: sem_id.map(|id| id.to_string()),
+                    type_name: sem_id.and_then(type_name),
+                }
+            })
+            .collect();
+
+        let genesis = OperationDescription {
+            id: None,
+            name: Some(s!("genesis")),
+            globals: describe_states(&schema.genesis.globals, global_name),
+            inputs: vec![],
+            assignments: describe_states(&schema.genesis.assignments, owned_name),
+            default_assignment: None,
+        };
+
+        let transitions = schema
+            .transitions
+            .iter()
+            .map(|(id, transition)| {
+                let name = transition_name(*id);
+                let default_assignment = name.as_ref().and_then(|name| {
+                    ifaces.iter().find_map(|iface| {
+                        iface
+                            .transitions
+                            .iter()
+                            .find(|(op, _)| op.as_str() == name)
+                            .and_then(|(_, op)| op.default_assignment.as_ref())
+                            .map(|name| name.to_string())
+                    })
+                });
+                OperationDescription {
+                    id: Some(id.to_inner()),
+                    name,
+                    globals: describe_states(&transition.globals, global_name),
+                    inputs: describe_states(&transition.inputs, owned_name),
+                    assignments: describe_states(&transition.assignments, owned_name),
+                    default_assignment,
+                }
+            })
+            .collect();
+
+        let roots = schema
+            .global_types
+            .values()
+            .map(|global| global.sem_id)
+            .chain(schema.owned_types.values().filter_map(|owned| match owned {
+                OwnedStateSchema::Structured(sem_id) => Some(*sem_id),
+                _ => None,
+            }))
+            .collect::<BTreeSet<_>>();
+        let mut types_used = TypeSystem::new();
+        for sem_id in roots {
+            // Types missing from the type system are skipped, such that the
+            // description is available even for incomplete stocks
+            if let Ok(sys) = types.extract([sem_id]) {
+                let _ = types_used.extend(sys);
+            }
+        }
+        let types = types_used
+            .iter()
+            .map(|(sem_id, ty)| TypeDefinition {
+                sem_id: sem_id.to_string(),
+                name: type_name(*sem_id),
+                definition: ty.to_string(),
+            })
+            .collect();
+
+        SchemaDescription {
+            schema_id: schema.schema_id(),
+            name: schema.name.to_string(),
+            developer: schema.developer.to_string(),
+            interfaces,
+            global_types,
+            owned_types,
+            genesis,
+            transitions,
+            types,
+        }
+    }
+
+    /// Describes schema known to the stock.
+    pub fn with_stock<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        stock: &Stock<S, H, P>,
+        schema_id: SchemaId,
+    ) -> Result<Self, StockError<S, H, P>> {
+        let schema_ifaces = stock.schema(schema_id)?;
+        let ifaces = schema_ifaces
+            .iimpls
+            .values()
+            .map(|iimpl: &IfaceImpl| stock.iface(iimpl.iface_id))
+            .collect::<Result<Vec<_>, _>>()?;
+        let types = stock
+            .as_stash_provider()
+            .type_system()
+            .map_err(StockError::StashRead)?;
+        Ok(Self::new(schema_ifaces, ifaces, types, &standard_symbols()))
+    }
+}
+
+/// Symbols of the standard libraries used by the contract state, allowing to
+/// name the types of the schemata built from them.
+pub fn standard_symbols() -> SymbolicSys {
+    let mut builder = SystemBuilder::new();
+    for lib in [std_stl(), bp_tx_stl(), rgb_contract_stl()] {
+        builder = builder.import(lib).expect("invalid standard type library");
+    }
+    builder.finalize().expect("incomplete standard type system")
+}
+
+fn describe_states<T: Copy + Ord + Hash + Wrapper<Inner = u16>>(
+    states: &TinyOrdMap<T, Occurrences>,
+    name: impl Fn(T) -> Option<String>,
+) -> Vec<OpStateDescription> {
+    states
+        .iter()
+        .map(|(id, occ)| OpStateDescription {
+            id: id.to_inner(),
+            name: name(*id),
+            min: occ.min_value(),
+            max: Some(occ.max_value()).filter(|max| *max != u16::MAX),
+        })
+        .collect()
+}
+
+fn fmt_states(f: &mut Formatter, title: &str, states: &[OpStateDescription]) -> fmt::Result {
+    if states.is_empty() {
+        return Ok(());
+    }
+    write!(f, "    {title}:")?;
+    for state in states {
+        let name = state
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("#{}", state.id));
+        match state.max {
+            Some(max) if max == state.min => write!(f, " {name}[{max}]")?,
+            Some(max) => write!(f, " {name}[{}..{max}]", state.min)?,
+            None => write!(f, " {name}[{}..]", state.min)?,
+        }
+    }
+    writeln!(f)
+}
+
+fn fmt_operation(f: &mut Formatter, op: &OperationDescription) -> fmt::Result {
+    match (op.id, &op.name) {
+        (None, _) => writeln!(f, "  genesis")?,
+        (Some(id), Some(name)) => writeln!(f, "  #{id} {name}")?,
+        (Some(id), None) => writeln!(f, "  #{id}")?,
+    }
+    fmt_states(f, "globals", &op.globals)?;
+    fmt_states(f, "inputs", &op.inputs)?;
+    fmt_states(f, "assignments", &op.assignments)?;
+    if let Some(default) = &op.default_assignment {
+        writeln!(f, "    default assignment: {default}")?;
+    }
+    Ok(())
+}
+
+impl Display for SchemaDescription {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Schema {} {}", self.name, self.schema_id)?;
+        writeln!(f, "Developer: {}", self.developer)?;
+
+        writeln!(f, "Interfaces:")?;
+        for iface in &self.interfaces {
+            write!(f, "  {} {}", iface.name, iface.iface_id)?;
+            if let Some(op) = &iface.default_operation {
+                write!(f, " (default operation: {op})")?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "Global state:")?;
+        for global in &self.global_types {
+            let name = global.name.as_deref().unwrap_or("~");
+            let ty = global.type_name.as_ref().unwrap_or(&global.sem_id);
+            writeln!(f, "  #{} {name}: {ty} (up to {} items)", global.id, global.max_items)?;
+        }
+
+        writeln!(f, "Owned state:")?;
+        for owned in &self.owned_types {
+            let name = owned.name.as_deref().unwrap_or("~");
+            write!(f, "  #{} {name}: {}", owned.id, owned.kind)?;
+            if let Some(ty) = owned.type_name.as_ref().or(owned.sem_id.as_ref()) {
+                write!(f, " {ty}")?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "Operations:")?;
+        fmt_operation(f, &self.genesis)?;
+        for transition in &self.transitions {
+            fmt_operation(f, transition)?;
+        }
+
+        writeln!(f, "Types:")?;
+        for ty in &self.types {
+            match &ty.name {
+                Some(name) => writeln!(f, "  {name} {} := {}", ty.sem_id, ty.definition)?,
+                None => writeln!(f, "  {} := {}", ty.sem_id, ty.definition)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -19,36 +19,300 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt::{self, Display, Formatter};
 use std::iter;
 use std::str::FromStr;
 
-use amplify::Wrapper;
-use bp::dbc::tapret::TapretCommitment;
+use amplify::{Wrapper, WrapperMut};
+use bp::dbc::tapret::{TapretCommitment, TapretNodePartner};
 use bp::dbc::Method;
 use bp::seals::txout::CloseMethod;
 use bp::{LegacyPk, SigScript, Witness};
+use bpstd::psbt::Psbt;
 use bpstd::{
     Derive, DeriveCompr, DeriveSet, DeriveXOnly, DerivedScript, Descriptor, Idx, IdxBase,
-    IndexError, IndexParseError, KeyOrigin, Keychain, LegacyKeySig, NormalIndex, SpkClass,
-    StdDescr, TapDerivation, TapScript, TapTree, TaprootKeySig, Terminal, TrKey, Wpkh, XOnlyPk,
+    IndexError, IndexParseError, InternalPk, IntoTapHash, KeyOrigin, Keychain, LegacyKeySig,
+    NormalIndex, ScriptPubkey, SpkClass, StdDescr, TapBranchHash, TapDerivation, TapNodeHash,
+    TapScript, TapTree, TaprootKeySig, Terminal, TerminalParseError, TrKey, Wpkh, XOnlyPk,
     XpubAccount, XpubDerivable,
 };
 use commit_verify::CommitVerify;
 use indexmap::IndexMap;
+use psrgbt::{check_tapret_tree, tap_tree_root, tapret_path_proof, tapret_tree, TapTreeError};
+use strict_types::encoding::DeserializeError;
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
 #[display("terminal derivation {0} already has a taptweak assigned")]
 pub struct TapTweakAlreadyAssigned(pub Terminal);
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum DescriptorError {
+    /// descriptor supports only the standard keychain layout, while {0} is
+    /// requested.
+    FixedKeychainLayout(KeychainLayout),
+}
+
 pub trait DescriptorRgb<K = XpubDerivable, V = ()>: Descriptor<K, V> {
     fn seal_close_method(&self) -> CloseMethod;
+    /// Keychains used by the wallet for the bitcoin and RGB seal outputs.
+    fn keychain_layout(&self) -> KeychainLayout { KeychainLayout::STANDARD }
+    fn set_keychain_layout(&mut self, layout: KeychainLayout) -> Result<(), DescriptorError>;
+    fn tapret_tweaks(&self) -> TapretTweaks;
     fn add_tapret_tweak(
         &mut self,
         terminal: Terminal,
         tweak: TapretCommitment,
     ) -> Result<(), TapTweakAlreadyAssigned>;
+    /// Taproot data of the output derived at the terminal, if the descriptor
+    /// derives it as a bare script (see [`TapOutput`]).
+    fn tap_output(&self, _terminal: Terminal) -> Option<TapOutput> { None }
+    /// Script pubkey which the output derived at the terminal would have with
+    /// the tapret tweak, if the descriptor supports tapret commitments.
+    fn tapret_script(
+        &self,
+        _terminal: Terminal,
+        _tweak: &TapretCommitment,
+    ) -> Option<ScriptPubkey> {
+        None
+    }
+}
+
+/// Taproot data of a wallet output having script tree with multiple leaves.
+///
+/// [`TapTree::merkle_root`] supports only single-leaf trees, thus such
+/// outputs are derived as bare scripts and the taproot data of PSBT inputs
+/// and outputs are filled by [`fill_tap_outputs`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TapOutput {
+    pub internal_pk: InternalPk,
+    pub merkle_root: TapNodeHash,
+    /// Script tree of the output, known only for the outputs without tapret
+    /// commitment.
+    pub tap_tree: Option<TapTree>,
+}
+
+impl TapOutput {
+    pub fn script_pubkey(&self) -> ScriptPubkey {
+        ScriptPubkey::p2tr(self.internal_pk, Some(self.merkle_root))
+    }
+}
+
+/// Fills taproot data of the PSBT inputs and outputs, which the descriptor
+/// derives as bare scripts (see [`TapOutput`]).
+///
+/// Must be called before committing to the RGB data, such that the tapret
+/// commitment is put into the script tree of the output, and before signing.
+pub(crate) fn fill_tap_outputs<K, D: DescriptorRgb<K>>(psbt: &mut Psbt, descriptor: &D) {
+    fn tap_terminal(keyset: &IndexMap<XOnlyPk, TapDerivation>) -> Option<Terminal> {
+        let mut terminals = keyset
+            .values()
+            .filter_map(|derivation| derivation.origin.derivation().terminal());
+        let terminal = terminals.next()?;
+        terminals.all(|t| t == terminal).then_some(terminal)
+    }
+
+    for input in psbt.inputs_mut() {
+        let Some(output) =
+            tap_terminal(&input.tap_bip32_derivation).and_then(|t| descriptor.tap_output(t))
+        else {
+            continue;
+        };
+        if input
+            .witness_utxo
+            .as_ref()
+            .map(|txout| &txout.script_pubkey)
+            == Some(&output.script_pubkey())
+        {
+            input.tap_internal_key = Some(output.internal_pk);
+            input.tap_merkle_root = Some(output.merkle_root);
+        }
+    }
+    for txout in psbt.outputs_mut() {
+        let Some(output) =
+            tap_terminal(&txout.tap_bip32_derivation).and_then(|t| descriptor.tap_output(t))
+        else {
+            continue;
+        };
+        if txout.script == output.script_pubkey() {
+            txout.tap_internal_key = Some(output.internal_pk);
+            txout.tap_tree = output.tap_tree;
+        }
+    }
+}
+
+/// Tapret tweaks known to a wallet descriptor, indexed by the terminal
+/// derivation of the tweaked outputs.
+///
+/// Without the tweaks the outputs hosting tapret commitments can't be spent,
+/// thus they must be backed up. The string representation, used for the
+/// backups, lists each tweak on a separate line as a terminal derivation
+/// followed by a tab and the commitment.
+#[derive(Wrapper, WrapperMut, Clone, Eq, PartialEq, Debug, Default, From)]
+#[wrapper(Deref)]
+#[wrapper_mut(DerefMut)]
+pub struct TapretTweaks(BTreeMap<Terminal, TapretCommitment>);
+
+impl TapretTweaks {
+    /// Returns tweaks which are not present in the `other` set.
+    pub fn difference(&self, other: &TapretTweaks) -> TapretTweaks {
+        self.iter()
+            .filter(|(terminal, _)| !other.contains_key(terminal))
+            .map(|(terminal, tweak)| (*terminal, tweak.clone()))
+            .collect()
+    }
+}
+
+impl FromIterator<(Terminal, TapretCommitment)> for TapretTweaks {
+    fn from_iter<T: IntoIterator<Item = (Terminal, TapretCommitment)>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl Display for TapretTweaks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (terminal, tweak) in &self.0 {
+            writeln!(f, "{terminal}\t{tweak}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TapretTweaks {
+    type Err = TapretTweaksParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tweaks = TapretTweaks::default();
+        for line in s.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (terminal, tweak) = line
+                .split_once('\t')
+                .ok_or_else(|| TapretTweaksParseError::InvalidLine(line.to_owned()))?;
+            let terminal = Terminal::from_str(terminal.trim())?;
+            let tweak = TapretCommitment::from_str(tweak.trim())?;
+            match tweaks.get(&terminal) {
+                Some(known) if *known != tweak => {
+                    return Err(TapretTweaksParseError::Conflict(terminal));
+                }
+                _ => {
+                    tweaks.insert(terminal, tweak);
+                }
+            }
+        }
+        Ok(tweaks)
+    }
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum TapretTweaksParseError {
+    /// tapret tweak record '{0}' must contain terminal derivation and
+    /// commitment separated by a tab.
+    InvalidLine(String),
+
+    /// invalid terminal derivation in tapret tweak record. Details: {0}
+    #[from]
+    Terminal(TerminalParseError),
+
+    /// invalid tapret commitment in tapret tweak record. Details: {0}
+    #[from]
+    Commitment(DeserializeError),
+
+    /// terminal derivation {0} has multiple conflicting tapret tweaks.
+    Conflict(Terminal),
+}
+
+/// Tapret tweak of a wallet output.
+///
+/// If the output has script spending paths next to the commitment, the
+/// partner node of the commitment leaf is kept, such that the output is
+/// derived the same way even if the descriptor script tree changes later.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TapretTweak {
+    pub commitment: TapretCommitment,
+    pub partner: Option<TapretNodePartner>,
+}
+
+impl TapretTweak {
+    /// Computes merkle root of the output script tree from the commitment leaf
+    /// and its partner node.
+    pub fn merkle_root(&self) -> TapNodeHash {
+        let leaf = TapScript::commit(&self.commitment)
+            .tap_leaf_hash()
+            .into_tap_hash();
+        match &self.partner {
+            None => leaf,
+            Some(partner) => {
+                TapBranchHash::with_nodes(leaf, partner.tap_node_hash()).into_tap_hash()
+            }
+        }
+    }
+}
+
+/// Tweaks without partner node are serialized as just a commitment, like
+/// they were serialized before the script trees were supported.
+#[cfg(feature = "serde")]
+mod tapret_tweak_serde {
+    use std::fmt::{self, Formatter};
+
+    use bp::dbc::tapret::{TapretCommitment, TapretNodePartner};
+    use serde_crate::de::value::MapAccessDeserializer;
+    use serde_crate::de::{Error, MapAccess, Visitor};
+    use serde_crate::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::TapretTweak;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(crate = "serde_crate", rename_all = "camelCase")]
+    struct TapretTweakData {
+        commitment: TapretCommitment,
+        partner: TapretNodePartner,
+    }
+
+    impl Serialize for TapretTweak {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match &self.partner {
+                None => self.commitment.serialize(serializer),
+                Some(partner) => TapretTweakData {
+                    commitment: self.commitment.clone(),
+                    partner: partner.clone(),
+                }
+                .serialize(serializer),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for TapretTweak {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct TweakVisitor;
+
+            impl<'de> Visitor<'de> for TweakVisitor {
+                type Value = TapretTweak;
+
+                fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+                    f.write_str("tapret commitment, optionally with the partner node")
+                }
+
+                fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+                    let commitment = v.parse().map_err(E::custom)?;
+                    Ok(TapretTweak {
+                        commitment,
+                        partner: None,
+                    })
+                }
+
+                fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                    let data = TapretTweakData::deserialize(MapAccessDeserializer::new(map))?;
+                    Ok(TapretTweak {
+                        commitment: data.commitment,
+                        partner: Some(data.partner),
+                    })
+                }
+            }
+
+            deserializer.deserialize_any(TweakVisitor)
+        }
+    }
 }
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
@@ -111,6 +375,104 @@ impl From<RgbKeychain> for Keychain {
     fn from(keychain: RgbKeychain) -> Self { Keychain::from(keychain as u8) }
 }
 
+/// Keychain indexes used by a wallet for each of [`RgbKeychain`] roles.
+///
+/// Wallets created by this library use the [`KeychainLayout::STANDARD`]
+/// layout; wallets migrating from other RGB implementations may use different
+/// keychain conventions. The string representation lists external, internal,
+/// RGB (opret) and tapret keychains in the descriptor multipath notation, i.e.
+/// `<0;1;9;10>` for the standard layout.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[display("<{external};{internal};{rgb};{tapret}>")]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct KeychainLayout {
+    pub external: u8,
+    pub internal: u8,
+    pub rgb: u8,
+    pub tapret: u8,
+}
+
+impl Default for KeychainLayout {
+    fn default() -> Self { Self::STANDARD }
+}
+
+impl KeychainLayout {
+    pub const STANDARD: KeychainLayout = KeychainLayout {
+        external: RgbKeychain::External as u8,
+        internal: RgbKeychain::Internal as u8,
+        rgb: RgbKeychain::Rgb as u8,
+        tapret: RgbKeychain::Tapret as u8,
+    };
+
+    pub fn is_standard(&self) -> bool { *self == Self::STANDARD }
+
+    /// Returns keychain index used by the wallet for the given role.
+    pub fn keychain(&self, role: RgbKeychain) -> Keychain {
+        Keychain::from(match role {
+            RgbKeychain::External => self.external,
+            RgbKeychain::Internal => self.internal,
+            RgbKeychain::Rgb => self.rgb,
+            RgbKeychain::Tapret => self.tapret,
+        })
+    }
+
+    /// Returns keychain used for the seals closed with the given method.
+    pub fn for_method(&self, method: Method) -> Keychain {
+        self.keychain(RgbKeychain::for_method(method))
+    }
+
+    /// Checks whether the keychain is used for RGB seals.
+    pub fn contains_rgb(&self, keychain: impl Into<Keychain>) -> bool {
+        let k = keychain.into().into_inner();
+        k == self.rgb || k == self.tapret
+    }
+
+    /// Returns all distinct keychains of the layout.
+    pub fn keychains(&self) -> BTreeSet<Keychain> {
+        [self.external, self.internal, self.rgb, self.tapret]
+            .into_iter()
+            .map(Keychain::from)
+            .collect()
+    }
+}
+
+impl FromStr for KeychainLayout {
+    type Err = KeychainLayoutParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let inner = s.trim();
+        let inner = inner
+            .strip_prefix('<')
+            .and_then(|inner| inner.strip_suffix('>'))
+            .unwrap_or(inner);
+        let keychains = inner
+            .split([';', ','])
+            .map(|k| k.trim().parse::<u8>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| KeychainLayoutParseError(s.to_owned()))?;
+        let [external, internal, rgb, tapret] = keychains[..] else {
+            return Err(KeychainLayoutParseError(s.to_owned()));
+        };
+        Ok(KeychainLayout {
+            external,
+            internal,
+            rgb,
+            tapret,
+        })
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(
+    "invalid keychain layout '{0}'; the layout must list external, internal, RGB and tapret \
+     keychain indexes as `<0;1;9;10>`."
+)]
+pub struct KeychainLayoutParseError(pub String);
+
 #[derive(Clone, Eq, PartialEq, Debug)]
 #[cfg_attr(
     feature = "serde",
@@ -119,8 +481,71 @@ impl From<RgbKeychain> for Keychain {
 )]
 pub struct TapretKey<K: DeriveXOnly = XpubDerivable> {
     pub tr: TrKey<K>,
+    /// Script tree present in all wallet outputs next to the tapret
+    /// commitments.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none", with = "tap_tree_serde")
+    )]
+    tap_tree: Option<TapTree>,
     // TODO: Allow multiple tweaks per index by introducing derivation using new Terminal trait
-    pub tweaks: HashMap<Terminal, TapretCommitment>,
+    pub tweaks: HashMap<Terminal, TapretTweak>,
+}
+
+/// Serialization of the descriptor script tree as a list of leaves, checking
+/// that the tree can host tapret commitments on deserialization.
+#[cfg(feature = "serde")]
+mod tap_tree_serde {
+    use amplify::num::u7;
+    use bpstd::{LeafInfo, LeafScript, TapTree};
+    use serde_crate::de::Error;
+    use serde_crate::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(crate = "serde_crate", rename_all = "camelCase")]
+    struct LeafData {
+        depth: u8,
+        script: LeafScript,
+    }
+
+    pub fn serialize<S: Serializer>(
+        tap_tree: &Option<TapTree>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        tap_tree
+            .as_ref()
+            .map(|tree| {
+                tree.iter()
+                    .map(|leaf| LeafData {
+                        depth: leaf.depth.to_u8(),
+                        script: leaf.script.clone(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<TapTree>, D::Error> {
+        let Some(leaves) = Option::<Vec<LeafData>>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        let leaves = leaves
+            .into_iter()
+            .map(|leaf| {
+                let depth = u7::try_from(leaf.depth)
+                    .map_err(|_| D::Error::custom("script tree is too deep"))?;
+                Ok(LeafInfo {
+                    depth,
+                    script: leaf.script,
+                })
+            })
+            .collect::<Result<Vec<_>, D::Error>>()?;
+        let tree = TapTree::from_leaves(leaves).map_err(D::Error::custom)?;
+        psrgbt::check_tapret_tree(&tree).map_err(D::Error::custom)?;
+        Ok(Some(tree))
+    }
 }
 
 impl<K: DeriveXOnly + Display> Display for TapretKey<K> {
@@ -131,7 +556,7 @@ impl<K: DeriveXOnly + Display> Display for TapretKey<K> {
             if term.keychain != RgbKeychain::Tapret.into() {
                 write!(f, "{}/", term.keychain)?;
             }
-            write!(f, "{}={tweak}", term.index)?;
+            write!(f, "{}={}", term.index, tweak.commitment)?;
             if iter.peek().is_some() {
                 f.write_str(";")?;
             }
@@ -144,9 +569,41 @@ impl<K: DeriveXOnly> TapretKey<K> {
     pub fn new_unfunded(internal_key: K) -> Self {
         TapretKey {
             tr: TrKey::from(internal_key),
+            tap_tree: None,
             tweaks: empty!(),
         }
     }
+
+    /// Adds the script tree to the wallet outputs, which can be spent with
+    /// its scripts next to the key. Outputs which already have tapret tweaks
+    /// keep the script tree they were tweaked with.
+    pub fn with_tap_tree(mut self, tap_tree: TapTree) -> Result<Self, TapTreeError> {
+        check_tapret_tree(&tap_tree)?;
+        self.tap_tree = Some(tap_tree);
+        Ok(self)
+    }
+
+    pub fn tap_tree(&self) -> Option<&TapTree> { self.tap_tree.as_ref() }
+
+    fn tweak(&self, commitment: TapretCommitment) -> TapretTweak {
+        let partner = self.tap_tree.as_ref().and_then(|tap_tree| {
+            tapret_path_proof(tap_tree, &commitment)
+                .expect("script tree is checked to host commitments")
+                .partner_node()
+                .clone()
+        });
+        TapretTweak {
+            commitment,
+            partner,
+        }
+    }
+
+    fn internal_pk(&self, terminal: Terminal) -> InternalPk {
+        self.tr
+            .as_internal_key()
+            .derive(terminal.keychain, terminal.index)
+            .into()
+    }
 }
 
 impl<K: DeriveXOnly> Derive<DerivedScript> for TapretKey<K> {
@@ -160,18 +617,23 @@ impl<K: DeriveXOnly> Derive<DerivedScript> for TapretKey<K> {
         keychain: impl Into<Keychain>,
         index: impl Into<NormalIndex>,
     ) -> DerivedScript {
-        let keychain = keychain.into();
-        let index = index.into();
-        let terminal = Terminal::new(keychain, index);
-        let internal_key = self.tr.as_internal_key().derive(keychain, index);
-        if keychain.into_inner() == RgbKeychain::Tapret as u8 {
-            if let Some(tweak) = self.tweaks.get(&terminal) {
-                let script_commitment = TapScript::commit(tweak);
-                let tap_tree = TapTree::with_single_leaf(script_commitment);
-                return DerivedScript::TaprootScript(internal_key.into(), tap_tree);
-            }
+        let terminal = Terminal::new(keychain, index.into());
+        let internal_pk = self.internal_pk(terminal);
+        // Outputs with multi-leaf trees are derived as bare scripts, see `TapOutput`
+        if let Some(output) = self.tap_output(terminal) {
+            return DerivedScript::Bare(output.script_pubkey());
+        }
+        // Tweaks are known only for the tapret keychain, whichever index it has in the wallet
+        // keychain layout
+        if let Some(tweak) = self.tweaks.get(&terminal) {
+            let tap_tree =
+                tapret_tree(None, &tweak.commitment).expect("tree without scripts is always valid");
+            return DerivedScript::TaprootScript(internal_pk, tap_tree);
+        }
+        match &self.tap_tree {
+            Some(tap_tree) => DerivedScript::TaprootScript(internal_pk, tap_tree.clone()),
+            None => DerivedScript::TaprootKeyOnly(internal_pk),
         }
-        DerivedScript::TaprootKeyOnly(internal_key.into())
     }
 }
 
@@ -179,6 +641,7 @@ impl<K: DeriveXOnly> From<K> for TapretKey<K> {
     fn from(internal_key: K) -> Self {
         TapretKey {
             tr: TrKey::from(internal_key),
+            tap_tree: None,
             tweaks: none!(),
         }
     }
@@ -188,6 +651,7 @@ impl<K: DeriveXOnly> From<TrKey<K>> for TapretKey<K> {
     fn from(tr: TrKey<K>) -> Self {
         TapretKey {
             tr,
+            tap_tree: None,
             tweaks: none!(),
         }
     }
@@ -229,6 +693,20 @@ impl<K: DeriveXOnly> Descriptor<K> for TapretKey<K> {
 impl<K: DeriveXOnly> DescriptorRgb<K> for TapretKey<K> {
     fn seal_close_method(&self) -> CloseMethod { CloseMethod::TapretFirst }
 
+    fn set_keychain_layout(&mut self, layout: KeychainLayout) -> Result<(), DescriptorError> {
+        if layout != KeychainLayout::STANDARD {
+            return Err(DescriptorError::FixedKeychainLayout(layout));
+        }
+        Ok(())
+    }
+
+    fn tapret_tweaks(&self) -> TapretTweaks {
+        self.tweaks
+            .iter()
+            .map(|(terminal, tweak)| (*terminal, tweak.commitment.clone()))
+            .collect()
+    }
+
     fn add_tapret_tweak(
         &mut self,
         terminal: Terminal,
@@ -237,32 +715,140 @@ impl<K: DeriveXOnly> DescriptorRgb<K> for TapretKey<K> {
         if self.tweaks.contains_key(&terminal) {
             return Err(TapTweakAlreadyAssigned(terminal));
         }
+        let tweak = self.tweak(tweak);
         self.tweaks.insert(terminal, tweak);
         Ok(())
     }
+
+    fn tapret_script(&self, terminal: Terminal, tweak: &TapretCommitment) -> Option<ScriptPubkey> {
+        let merkle_root = self.tweak(tweak.clone()).merkle_root();
+        Some(ScriptPubkey::p2tr(self.internal_pk(terminal), Some(merkle_root)))
+    }
+
+    fn tap_output(&self, terminal: Terminal) -> Option<TapOutput> {
+        let internal_pk = self.internal_pk(terminal);
+        if let Some(tweak) = self.tweaks.get(&terminal) {
+            return tweak.partner.is_some().then(|| TapOutput {
+                internal_pk,
+                merkle_root: tweak.merkle_root(),
+                tap_tree: None,
+            });
+        }
+        let tap_tree = self
+            .tap_tree
+            .as_ref()
+            .filter(|tap_tree| tap_tree.len() > 1)?;
+        Some(TapOutput {
+            internal_pk,
+            merkle_root: tap_tree_root(tap_tree).expect("script tree is always valid"),
+            tap_tree: Some(tap_tree.clone()),
+        })
+    }
 }
 
-#[derive(Clone, Eq, PartialEq, Debug, From)]
+/// RGB wallet descriptor, which also keeps the [`KeychainLayout`] of the
+/// wallet.
+///
+/// The layout is serialized next to the descriptor only if it is not the
+/// standard one, keeping the serialization of the existing wallets intact.
+#[derive(Clone, Eq, PartialEq, Debug)]
 #[cfg_attr(
     feature = "serde",
     derive(Serialize, Deserialize),
     serde(
         crate = "serde_crate",
-        rename_all = "camelCase",
+        from = "RgbDescrData<S>",
+        into = "RgbDescrData<S>",
         bound(
-            serialize = "S::Compr: serde::Serialize, S::XOnly: serde::Serialize",
+            serialize = "S: Clone, S::Compr: serde::Serialize, S::XOnly: serde::Serialize",
             deserialize = "S::Compr: serde::Deserialize<'de>, S::XOnly: serde::Deserialize<'de>"
         )
     )
 )]
 #[non_exhaustive]
 pub enum RgbDescr<S: DeriveSet = XpubDerivable> {
-    #[from]
+    Wpkh(Wpkh<S::Compr>, KeychainLayout),
+    TapretKey(TapretKey<S::XOnly>, KeychainLayout),
+}
+
+impl<S: DeriveSet> From<Wpkh<S::Compr>> for RgbDescr<S> {
+    fn from(wpkh: Wpkh<S::Compr>) -> Self { RgbDescr::Wpkh(wpkh, KeychainLayout::STANDARD) }
+}
+
+impl<S: DeriveSet> From<TapretKey<S::XOnly>> for RgbDescr<S> {
+    fn from(tapret: TapretKey<S::XOnly>) -> Self {
+        RgbDescr::TapretKey(tapret, KeychainLayout::STANDARD)
+    }
+}
+
+impl<S: DeriveSet> RgbDescr<S> {
+    /// Replaces the keychain layout of the descriptor.
+    pub fn with_keychain_layout(mut self, layout: KeychainLayout) -> Self {
+        match &mut self {
+            RgbDescr::Wpkh(_, l) | RgbDescr::TapretKey(_, l) => *l = layout,
+        }
+        self
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(
+    crate = "serde_crate",
+    rename_all = "camelCase",
+    bound(
+        serialize = "S::Compr: serde::Serialize, S::XOnly: serde::Serialize",
+        deserialize = "S::Compr: serde::Deserialize<'de>, S::XOnly: serde::Deserialize<'de>"
+    )
+)]
+enum RgbDescrKind<S: DeriveSet> {
     Wpkh(Wpkh<S::Compr>),
-    #[from]
     TapretKey(TapretKey<S::XOnly>),
 }
 
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(
+    crate = "serde_crate",
+    rename_all = "camelCase",
+    bound(
+        serialize = "S::Compr: serde::Serialize, S::XOnly: serde::Serialize",
+        deserialize = "S::Compr: serde::Deserialize<'de>, S::XOnly: serde::Deserialize<'de>"
+    )
+)]
+struct RgbDescrData<S: DeriveSet> {
+    #[serde(flatten)]
+    descr: RgbDescrKind<S>,
+    #[serde(default, skip_serializing_if = "KeychainLayout::is_standard")]
+    keychains: KeychainLayout,
+}
+
+#[cfg(feature = "serde")]
+impl<S: DeriveSet> From<RgbDescrData<S>> for RgbDescr<S> {
+    fn from(data: RgbDescrData<S>) -> Self {
+        match data.descr {
+            RgbDescrKind::Wpkh(d) => RgbDescr::Wpkh(d, data.keychains),
+            RgbDescrKind::TapretKey(d) => RgbDescr::TapretKey(d, data.keychains),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<S: DeriveSet> From<RgbDescr<S>> for RgbDescrData<S> {
+    fn from(descr: RgbDescr<S>) -> Self {
+        match descr {
+            RgbDescr::Wpkh(d, keychains) => RgbDescrData {
+                descr: RgbDescrKind::Wpkh(d),
+                keychains,
+            },
+            RgbDescr::TapretKey(d, keychains) => RgbDescrData {
+                descr: RgbDescrKind::TapretKey(d),
+                keychains,
+            },
+        }
+    }
+}
+
 impl<S: DeriveSet> Display for RgbDescr<S>
 where
     S::Legacy: Display,
@@ -271,8 +857,8 @@ where
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            RgbDescr::Wpkh(d) => Display::fmt(d, f),
-            RgbDescr::TapretKey(d) => Display::fmt(d, f),
+            RgbDescr::Wpkh(d, _) => Display::fmt(d, f),
+            RgbDescr::TapretKey(d, _) => Display::fmt(d, f),
         }
     }
 }
@@ -280,22 +866,26 @@ where
 impl<S: DeriveSet> Derive<DerivedScript> for RgbDescr<S> {
     fn default_keychain(&self) -> Keychain {
         match self {
-            RgbDescr::Wpkh(d) => d.default_keychain(),
-            RgbDescr::TapretKey(d) => d.default_keychain(),
+            RgbDescr::Wpkh(d, _) => d.default_keychain(),
+            RgbDescr::TapretKey(d, _) => d.default_keychain(),
         }
     }
 
     fn keychains(&self) -> BTreeSet<Keychain> {
-        match self {
-            RgbDescr::Wpkh(d) => d.keychains(),
-            RgbDescr::TapretKey(d) => d.keychains(),
+        let (mut keychains, layout) = match self {
+            RgbDescr::Wpkh(d, layout) => (d.keychains(), layout),
+            RgbDescr::TapretKey(d, layout) => (d.keychains(), layout),
+        };
+        if !layout.is_standard() {
+            keychains.extend(layout.keychains());
         }
+        keychains
     }
 
     fn derive(&self, change: impl Into<Keychain>, index: impl Into<NormalIndex>) -> DerivedScript {
         match self {
-            RgbDescr::Wpkh(d) => d.derive(change, index),
-            RgbDescr::TapretKey(d) => d.derive(change, index),
+            RgbDescr::Wpkh(d, _) => d.derive(change, index),
+            RgbDescr::TapretKey(d, _) => d.derive(change, index),
         }
     }
 }
@@ -305,16 +895,16 @@ where Self: Derive<DerivedScript>
 {
     fn class(&self) -> SpkClass {
         match self {
-            RgbDescr::Wpkh(d) => d.class(),
-            RgbDescr::TapretKey(d) => d.class(),
+            RgbDescr::Wpkh(d, _) => d.class(),
+            RgbDescr::TapretKey(d, _) => d.class(),
         }
     }
 
     fn keys<'a>(&'a self) -> impl Iterator<Item = &'a K>
     where K: 'a {
         match self {
-            RgbDescr::Wpkh(d) => d.keys().collect::<Vec<_>>(),
-            RgbDescr::TapretKey(d) => d.keys().collect::<Vec<_>>(),
+            RgbDescr::Wpkh(d, _) => d.keys().collect::<Vec<_>>(),
+            RgbDescr::TapretKey(d, _) => d.keys().collect::<Vec<_>>(),
         }
         .into_iter()
     }
@@ -326,23 +916,23 @@ where Self: Derive<DerivedScript>
 
     fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
         match self {
-            RgbDescr::Wpkh(d) => d.xpubs().collect::<Vec<_>>(),
-            RgbDescr::TapretKey(d) => d.xpubs().collect::<Vec<_>>(),
+            RgbDescr::Wpkh(d, _) => d.xpubs().collect::<Vec<_>>(),
+            RgbDescr::TapretKey(d, _) => d.xpubs().collect::<Vec<_>>(),
         }
         .into_iter()
     }
 
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         match self {
-            RgbDescr::Wpkh(d) => d.legacy_keyset(terminal),
-            RgbDescr::TapretKey(d) => d.legacy_keyset(terminal),
+            RgbDescr::Wpkh(d, _) => d.legacy_keyset(terminal),
+            RgbDescr::TapretKey(d, _) => d.legacy_keyset(terminal),
         }
     }
 
     fn xonly_keyset(&self, terminal: Terminal) -> IndexMap<XOnlyPk, TapDerivation> {
         match self {
-            RgbDescr::Wpkh(d) => d.xonly_keyset(terminal),
-            RgbDescr::TapretKey(d) => d.xonly_keyset(terminal),
+            RgbDescr::Wpkh(d, _) => d.xonly_keyset(terminal),
+            RgbDescr::TapretKey(d, _) => d.xonly_keyset(terminal),
         }
     }
 
@@ -351,15 +941,15 @@ where Self: Derive<DerivedScript>
         keysigs: HashMap<&KeyOrigin, LegacyKeySig>,
     ) -> Option<(SigScript, Witness)> {
         match self {
-            RgbDescr::Wpkh(d) => d.legacy_witness(keysigs),
-            RgbDescr::TapretKey(d) => d.legacy_witness(keysigs),
+            RgbDescr::Wpkh(d, _) => d.legacy_witness(keysigs),
+            RgbDescr::TapretKey(d, _) => d.legacy_witness(keysigs),
         }
     }
 
     fn taproot_witness(&self, keysigs: HashMap<&KeyOrigin, TaprootKeySig>) -> Option<Witness> {
         match self {
-            RgbDescr::Wpkh(d) => d.taproot_witness(keysigs),
-            RgbDescr::TapretKey(d) => d.taproot_witness(keysigs),
+            RgbDescr::Wpkh(d, _) => d.taproot_witness(keysigs),
+            RgbDescr::TapretKey(d, _) => d.taproot_witness(keysigs),
         }
     }
 }
@@ -370,8 +960,28 @@ where Self: Derive<DerivedScript>
 {
     fn seal_close_method(&self) -> CloseMethod {
         match self {
-            RgbDescr::Wpkh(_) => CloseMethod::OpretFirst,
-            RgbDescr::TapretKey(d) => d.seal_close_method(),
+            RgbDescr::Wpkh(..) => CloseMethod::OpretFirst,
+            RgbDescr::TapretKey(d, _) => d.seal_close_method(),
+        }
+    }
+
+    fn keychain_layout(&self) -> KeychainLayout {
+        match self {
+            RgbDescr::Wpkh(_, layout) | RgbDescr::TapretKey(_, layout) => *layout,
+        }
+    }
+
+    fn set_keychain_layout(&mut self, layout: KeychainLayout) -> Result<(), DescriptorError> {
+        match self {
+            RgbDescr::Wpkh(_, l) | RgbDescr::TapretKey(_, l) => *l = layout,
+        }
+        Ok(())
+    }
+
+    fn tapret_tweaks(&self) -> TapretTweaks {
+        match self {
+            RgbDescr::Wpkh(..) => none!(),
+            RgbDescr::TapretKey(d, _) => d.tapret_tweaks(),
         }
     }
 
@@ -381,8 +991,22 @@ where Self: Derive<DerivedScript>
         tweak: TapretCommitment,
     ) -> Result<(), TapTweakAlreadyAssigned> {
         match self {
-            RgbDescr::Wpkh(_) => panic!("adding tapret tweak to non-taproot descriptor"),
-            RgbDescr::TapretKey(d) => d.add_tapret_tweak(terminal, tweak),
+            RgbDescr::Wpkh(..) => panic!("adding tapret tweak to non-taproot descriptor"),
+            RgbDescr::TapretKey(d, _) => d.add_tapret_tweak(terminal, tweak),
+        }
+    }
+
+    fn tap_output(&self, terminal: Terminal) -> Option<TapOutput> {
+        match self {
+            RgbDescr::Wpkh(..) => None,
+            RgbDescr::TapretKey(d, _) => d.tap_output(terminal),
+        }
+    }
+
+    fn tapret_script(&self, terminal: Terminal, tweak: &TapretCommitment) -> Option<ScriptPubkey> {
+        match self {
+            RgbDescr::Wpkh(..) => None,
+            RgbDescr::TapretKey(d, _) => d.tapret_script(terminal, tweak),
         }
     }
 }
@@ -390,8 +1014,8 @@ where Self: Derive<DerivedScript>
 impl From<StdDescr> for RgbDescr {
     fn from(descr: StdDescr) -> Self {
         match descr {
-            StdDescr::Wpkh(wpkh) => RgbDescr::Wpkh(wpkh),
-            StdDescr::TrKey(tr) => RgbDescr::TapretKey(tr.into()),
+            StdDescr::Wpkh(wpkh) => wpkh.into(),
+            StdDescr::TrKey(tr) => TapretKey::from(tr).into(),
             _ => todo!(),
         }
     }
@@ -0,0 +1,190 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Comparison of consignments for the same contract, used to find out why a
+//! consignment accepted by one party is rejected by the other.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+
+use rgbstd::containers::{Consignment, ConsignmentExt};
+use rgbstd::{BundleId, ContractId, SecretSeal, XChain, XWitnessId};
+
+use crate::ContractMismatch;
+
+/// Terminal seal of a bundle which differs between two consignments.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TerminalDiff {
+    /// Terminal in the first consignment, if any.
+    pub first: Option<XChain<SecretSeal>>,
+    /// Terminal in the second consignment, if any.
+    pub second: Option<XChain<SecretSeal>>,
+}
+
+/// Differences between two consignments for the same contract.
+///
+/// Bundles and witnesses are reported as added when they are present only in
+/// the second consignment and as removed when they are present only in the
+/// first one.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ConsignmentDiff {
+    pub contract_id: ContractId,
+    /// Bundles present only in the second consignment, with their witnesses.
+    pub added_bundles: BTreeMap<BundleId, XWitnessId>,
+    /// Bundles present only in the first consignment, with their witnesses.
+    pub removed_bundles: BTreeMap<BundleId, XWitnessId>,
+    /// Bundles present in both consignments but anchored to different
+    /// witnesses, with the witnesses from the first and the second
+    /// consignments.
+    pub rewitnessed_bundles: BTreeMap<BundleId, (XWitnessId, XWitnessId)>,
+    /// Witnesses present only in the second consignment.
+    pub added_witnesses: BTreeSet<XWitnessId>,
+    /// Witnesses present only in the first consignment.
+    pub removed_witnesses: BTreeSet<XWitnessId>,
+    /// Bundles with differing terminal seals.
+    pub terminals: BTreeMap<BundleId, TerminalDiff>,
+}
+
+impl ConsignmentDiff {
+    /// Compares two consignments, failing if they are for different
+    /// contracts.
+    pub fn new<const FIRST: bool, const SECOND: bool>(
+        first: &Consignment<FIRST>,
+        second: &Consignment<SECOND>,
+    ) -> Result<Self, ContractMismatch> {
+        let contract_id = first.contract_id();
+        if second.contract_id() != contract_id {
+            return Err(ContractMismatch(contract_id, second.contract_id()));
+        }
+
+        let first_bundles = bundle_witnesses(first);
+        let second_bundles = bundle_witnesses(second);
+        let first_witnesses = first_bundles.values().copied().collect::<BTreeSet<_>>();
+        let second_witnesses = second_bundles.values().copied().collect::<BTreeSet<_>>();
+
+        let mut diff = ConsignmentDiff {
+            contract_id,
+            added_bundles: bmap![],
+            removed_bundles: bmap![],
+            rewitnessed_bundles: bmap![],
+            added_witnesses: &second_witnesses - &first_witnesses,
+            removed_witnesses: &first_witnesses - &second_witnesses,
+            terminals: bmap![],
+        };
+        for (bundle_id, witness_id) in &first_bundles {
+            match second_bundles.get(bundle_id) {
+                None => {
+                    diff.removed_bundles.insert(*bundle_id, *witness_id);
+                }
+                Some(other) if other != witness_id => {
+                    diff.rewitnessed_bundles
+                        .insert(*bundle_id, (*witness_id, *other));
+                }
+                Some(_) => {}
+            }
+        }
+        for (bundle_id, witness_id) in &second_bundles {
+            if !first_bundles.contains_key(bundle_id) {
+                diff.added_bundles.insert(*bundle_id, *witness_id);
+            }
+        }
+
+        let bundle_ids = first
+            .terminals
+            .keys()
+            .chain(second.terminals.keys())
+            .collect::<BTreeSet<_>>();
+        for bundle_id in bundle_ids {
+            let terminal = TerminalDiff {
+                first: first.terminals.get(bundle_id).copied(),
+                second: second.terminals.get(bundle_id).copied(),
+            };
+            if terminal.first != terminal.second {
+                diff.terminals.insert(*bundle_id, terminal);
+            }
+        }
+
+        Ok(diff)
+    }
+
+    /// Detects whether consignments have the same bundles, witnesses and
+    /// terminals.
+    pub fn is_empty(&self) -> bool {
+        self.added_bundles.is_empty()
+            && self.removed_bundles.is_empty()
+            && self.rewitnessed_bundles.is_empty()
+            && self.added_witnesses.is_empty()
+            && self.removed_witnesses.is_empty()
+            && self.terminals.is_empty()
+    }
+}
+
+fn bundle_witnesses<const TRANSFER: bool>(
+    consignment: &Consignment<TRANSFER>,
+) -> BTreeMap<BundleId, XWitnessId> {
+    consignment
+        .bundles
+        .iter()
+        .flat_map(|bundle| {
+            let witness_id = bundle.witness_id();
+            bundle
+                .anchored_bundles
+                .bundles()
+                .map(move |bundle| (bundle.bundle_id(), witness_id))
+        })
+        .collect()
+}
+
+impl Display for ConsignmentDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Contract {}", self.contract_id)?;
+        if self.is_empty() {
+            return writeln!(f, "No differences in bundles, witnesses and terminals");
+        }
+        for (bundle_id, witness_id) in &self.removed_bundles {
+            writeln!(f, "- bundle {bundle_id} (witness {witness_id})")?;
+        }
+        for (bundle_id, witness_id) in &self.added_bundles {
+            writeln!(f, "+ bundle {bundle_id} (witness {witness_id})")?;
+        }
+        for (bundle_id, (first, second)) in &self.rewitnessed_bundles {
+            writeln!(f, "~ bundle {bundle_id} witness {first} -> {second}")?;
+        }
+        for witness_id in &self.removed_witnesses {
+            writeln!(f, "- witness {witness_id}")?;
+        }
+        for witness_id in &self.added_witnesses {
+            writeln!(f, "+ witness {witness_id}")?;
+        }
+        for (bundle_id, terminal) in &self.terminals {
+            write!(f, "~ terminal {bundle_id}: ")?;
+            match terminal.first {
+                Some(seal) => write!(f, "{seal}")?,
+                None => write!(f, "none")?,
+            }
+            match terminal.second {
+                Some(seal) => writeln!(f, " -> {seal}")?,
+                None => writeln!(f, " -> none")?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,237 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Encryption of the wallet data at rest.
+//!
+//! Wallet descriptor, tapret tweaks and the UTXO set are kept in files
+//! encrypted with ChaCha20-Poly1305, using a key derived from the user
+//! passphrase with Argon2id. Each file has its own random salt and nonce, such
+//! that files can be re-written independently.
+//!
+//! Encryption covers the files of the wallet directory only. The stock and
+//! the files of the data directory shared by all wallets (labels, frozen and
+//! imported outpoints, velocity preferences and the resolver cache) are kept
+//! in plaintext. Tapret tweaks backups written on each transfer are plaintext
+//! too, thus they are refused for the encrypted wallets.
+
+use std::fmt::{self, Debug, Formatter};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use argon2::Argon2;
+use bpstd::XpubDerivable;
+use bpwallet::{Layer2, NoLayer2, WalletCache, WalletData, WalletDescr};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use nonasync::persistence::{PersistenceError, PersistenceProvider};
+
+use crate::{EncryptionError, RgbDescr};
+
+/// Name of the encrypted file inside the wallet directory holding the
+/// descriptor, which also marks the wallet directory as an encrypted one.
+pub const ENCRYPTED_DESCRIPTOR_FILE: &str = "descriptor.enc";
+
+const ENCRYPTED_DATA_FILE: &str = "data.enc";
+const ENCRYPTED_CACHE_FILE: &str = "cache.enc";
+
+/// Magic bytes starting each encrypted file, including the format version.
+const MAGIC: &[u8; 8] = b"RGBWENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Passphrase protecting the wallet data, which is never printed in debug
+/// output.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Passphrase(String);
+
+impl Debug for Passphrase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { f.write_str("Passphrase(***)") }
+}
+
+impl From<String> for Passphrase {
+    fn from(passphrase: String) -> Self { Self(passphrase) }
+}
+
+impl From<&str> for Passphrase {
+    fn from(passphrase: &str) -> Self { Self(passphrase.to_owned()) }
+}
+
+impl Passphrase {
+    fn derive_key(&self, salt: &[u8]) -> Result<Key, EncryptionError> {
+        let mut key = Key::default();
+        Argon2::default()
+            .hash_password_into(self.0.as_bytes(), salt, &mut key)
+            .map_err(|e| EncryptionError::KeyDerivation(e.to_string()))?;
+        Ok(key)
+    }
+}
+
+/// Checks whether the wallet directory keeps encrypted wallet data.
+pub fn is_encrypted(wallet_dir: impl AsRef<Path>) -> bool {
+    wallet_dir
+        .as_ref()
+        .join(ENCRYPTED_DESCRIPTOR_FILE)
+        .is_file()
+}
+
+/// Persistence of the wallet in a directory, encrypting the wallet files with
+/// the passphrase.
+///
+/// Used in place of [`bpwallet::fs::FsTextStore`] for the wallets which data
+/// must not be kept in plaintext.
+#[derive(Clone, Debug)]
+pub struct EncryptedStore {
+    dir: PathBuf,
+    passphrase: Passphrase,
+}
+
+impl EncryptedStore {
+    /// Creates store in the directory, creating the directory if it doesn't
+    /// exist.
+    pub fn new(dir: PathBuf, passphrase: impl Into<Passphrase>) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            passphrase: passphrase.into(),
+        })
+    }
+
+    /// Opens store of the existing encrypted wallet, checking that the
+    /// passphrase decrypts the wallet descriptor.
+    pub fn unlock(
+        dir: PathBuf,
+        passphrase: impl Into<Passphrase>,
+    ) -> Result<Self, EncryptionError> {
+        let store = Self {
+            dir,
+            passphrase: passphrase.into(),
+        };
+        store.decrypt_file(ENCRYPTED_DESCRIPTOR_FILE)?;
+        Ok(store)
+    }
+
+    pub fn dir(&self) -> &Path { &self.dir }
+
+    fn load_file<T: for<'de> serde::Deserialize<'de>>(
+        &self,
+        file: &str,
+    ) -> Result<T, EncryptionError> {
+        let data = self.decrypt_file(file)?;
+        Ok(serde_yaml::from_slice(&data)?)
+    }
+
+    fn store_file<T: serde::Serialize>(
+        &self,
+        file: &str,
+        object: &T,
+    ) -> Result<(), EncryptionError> {
+        let data = serde_yaml::to_string(object)?;
+        self.encrypt_file(file, data.as_bytes())
+    }
+
+    fn decrypt_file(&self, file: &str) -> Result<Vec<u8>, EncryptionError> {
+        let path = self.dir.join(file);
+        let data = fs::read(&path)?;
+        let header = MAGIC.len() + SALT_LEN + NONCE_LEN;
+        if data.len() < header || &data[..MAGIC.len()] != MAGIC {
+            return Err(EncryptionError::Format(path));
+        }
+        let (salt, rest) = data[MAGIC.len()..].split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let key = self.passphrase.derive_key(salt)?;
+        ChaCha20Poly1305::new(&key)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| EncryptionError::WrongPassphrase(path))
+    }
+
+    fn encrypt_file(&self, file: &str, data: &[u8]) -> Result<(), EncryptionError> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let key = self.passphrase.derive_key(&salt)?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(&key)
+            .encrypt(&nonce, data)
+            .map_err(|_| EncryptionError::Encrypt)?;
+
+        // The file is written next to the existing one and renamed into its
+        // place, such that an interrupted write doesn't destroy the data.
+        let path = self.dir.join(file);
+        let temp = self.dir.join(format!("{file}.tmp"));
+        let mut f = File::create(&temp)?;
+        f.write_all(MAGIC)?;
+        f.write_all(&salt)?;
+        f.write_all(&nonce)?;
+        f.write_all(&ciphertext)?;
+        f.sync_all()?;
+        fs::rename(temp, path)?;
+        Ok(())
+    }
+}
+
+impl PersistenceProvider<WalletDescr<XpubDerivable, RgbDescr>> for EncryptedStore {
+    fn load(&self) -> Result<WalletDescr<XpubDerivable, RgbDescr>, PersistenceError> {
+        self.load_file(ENCRYPTED_DESCRIPTOR_FILE)
+            .map_err(PersistenceError::with)
+    }
+
+    fn store(&self, object: &WalletDescr<XpubDerivable, RgbDescr>) -> Result<(), PersistenceError> {
+        self.store_file(ENCRYPTED_DESCRIPTOR_FILE, object)
+            .map_err(PersistenceError::with)
+    }
+}
+
+impl PersistenceProvider<WalletData<<NoLayer2 as Layer2>::Data>> for EncryptedStore {
+    fn load(&self) -> Result<WalletData<<NoLayer2 as Layer2>::Data>, PersistenceError> {
+        self.load_file(ENCRYPTED_DATA_FILE)
+            .map_err(PersistenceError::with)
+    }
+
+    fn store(
+        &self,
+        object: &WalletData<<NoLayer2 as Layer2>::Data>,
+    ) -> Result<(), PersistenceError> {
+        self.store_file(ENCRYPTED_DATA_FILE, object)
+            .map_err(PersistenceError::with)
+    }
+}
+
+impl PersistenceProvider<WalletCache<<NoLayer2 as Layer2>::Cache>> for EncryptedStore {
+    fn load(&self) -> Result<WalletCache<<NoLayer2 as Layer2>::Cache>, PersistenceError> {
+        self.load_file(ENCRYPTED_CACHE_FILE)
+            .map_err(PersistenceError::with)
+    }
+
+    fn store(
+        &self,
+        object: &WalletCache<<NoLayer2 as Layer2>::Cache>,
+    ) -> Result<(), PersistenceError> {
+        self.store_file(ENCRYPTED_CACHE_FILE, object)
+            .map_err(PersistenceError::with)
+    }
+}
+
+impl PersistenceProvider<NoLayer2> for EncryptedStore {
+    fn load(&self) -> Result<NoLayer2, PersistenceError> { Ok(none!()) }
+
+    fn store(&self, _: &NoLayer2) -> Result<(), PersistenceError> { Ok(()) }
+}
@@ -22,22 +22,37 @@
 #![allow(clippy::result_large_err)]
 
 use std::convert::Infallible;
+use std::fmt::{self, Display, Formatter};
 use std::io;
+use std::path::PathBuf;
 
 use amplify::IoError;
-use bpstd::Psbt;
+use bp::seals::txout::CloseMethod;
+use bpstd::{Network, Outpoint, Psbt, Sats, Txid, XkeyParseError};
+use commit_verify::mpc;
 use nonasync::persistence::PersistenceError;
-use psrgbt::{CommitError, ConstructionError, EmbedError, TapretKeyError};
-use rgbstd::containers::LoadError;
+use psrgbt::{
+    CommitError, ConstructionError, CosignError, EmbedError, ExtractError, RgbPsbtError,
+    SignRequestError, TapretKeyError,
+};
+use rgbstd::containers::{ConsignmentId, LoadError};
 use rgbstd::interface::{BuilderError, ContractError};
+use rgbstd::invoice::{Amount, InvoiceParseError, Pay2VoutError};
 use rgbstd::persistence::{
-    ComposeError, ConsignError, ContractIfaceError, FasciaError, Stock, StockError, StockErrorAll,
-    StockErrorMem,
+    ComposeError, ConsignError, ContractIfaceError, FasciaError, MemError, Stock, StockError,
+    StockErrorAll, StockErrorMem,
 };
-use strict_types::encoding::Ident;
+use rgbstd::schema::SchemaId;
+use rgbstd::validation::DbcError;
+use rgbstd::{BundleId, ContractId, Layer1, Opout, SecretSeal, XWitnessId};
+use strict_types::encoding::{FieldName, Ident};
 
-use crate::{validation, TapTweakAlreadyAssigned};
+use crate::{
+    validation, AmountRange, AssetCollisions, NetworkComponent, TapTweakAlreadyAssigned,
+    TapretTweaksParseError,
+};
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Display, Error, From)]
 #[display(inner)]
 pub enum WalletError {
@@ -67,10 +82,22 @@ pub enum WalletError {
     #[from]
     PsbtDecode(psrgbt::DecodeError),
 
+    /// invalid PSBT data: {0}.
+    #[display(doc_comments)]
+    PsbtParse(String),
+
+    /// invalid ASCII-armored data: {0}.
+    #[display(doc_comments)]
+    Armored(String),
+
     /// wallet with id '{0}' is not known to the system.
     #[display(doc_comments)]
     WalletUnknown(Ident),
 
+    /// stock at {0:?} is locked by another process.
+    #[display(doc_comments)]
+    StockLocked(PathBuf),
+
     #[from]
     InvalidConsignment(validation::Status),
 
@@ -91,6 +118,7 @@ pub enum WalletError {
 
     #[from(StockError)]
     #[from(StockErrorAll)]
+    #[from(StockErrorMem<ConsignError>)]
     #[from(StockErrorMem<ContractIfaceError>)]
     #[display(inner)]
     Stock(String),
@@ -100,7 +128,191 @@ pub enum WalletError {
     Yaml(serde_yaml::Error),
 
     #[from]
-    Custom(String),
+    Issue(IssueError),
+
+    #[from]
+    Allocations(AllocationsError),
+
+    /// invalid {0} name '{1}'.
+    #[display(doc_comments)]
+    InvalidName(&'static str, String),
+
+    /// invalid issuer private key.
+    #[display(doc_comments)]
+    IssuerKey,
+
+    /// invalid extended private key. Details: {0}
+    #[display(doc_comments)]
+    #[from]
+    Xpriv(XkeyParseError),
+
+    /// consignment is a transfer and must be accepted instead of being
+    /// imported.
+    #[display(doc_comments)]
+    UnexpectedTransfer,
+
+    /// blinded invoice requested but no suitable outpoint is available.
+    #[display(doc_comments)]
+    NoOutpoint,
+
+    /// split invoice requires {required} more outpoints, but only {available}
+    /// are available.
+    #[display(doc_comments)]
+    InsufficientOutpoints {
+        required: usize,
+        available: usize,
+    },
+
+    #[from]
+    Tweaks(TapretTweaksParseError),
+
+    #[from]
+    TapTweak(TapTweakAlreadyAssigned),
+
+    #[from]
+    Policy(PolicyError),
+
+    #[from]
+    Label(LabelError),
+
+    #[from]
+    Identity(IdentityError),
+
+    #[from]
+    Registry(RegistryError),
+
+    #[from]
+    KitRegistry(KitRegistryError),
+
+    #[from]
+    InvoiceApi(InvoiceApiError),
+
+    #[from]
+    WalletDir(WalletDirError),
+
+    #[from]
+    Freeze(FreezeError),
+
+    #[from]
+    Velocity(VelocityError),
+
+    #[from]
+    Reservation(ReservationError),
+
+    #[from]
+    StockExport(StockExportError),
+
+    #[from]
+    Amend(AmendError),
+
+    #[from]
+    CompactInvoice(CompactInvoiceError),
+
+    #[from]
+    InvoiceStatus(InvoiceStatusError),
+
+    #[from]
+    Basket(BasketInvoiceError),
+
+    #[from]
+    Composition(CompositionError),
+
+    #[from]
+    Completion(CompletionError),
+
+    #[from]
+    Pay(PayError),
+
+    #[from]
+    Call(CallError),
+
+    #[from]
+    Preview(PreviewError),
+
+    #[from]
+    SignRequest(SignRequestError),
+
+    #[from]
+    Cosign(CosignError),
+
+    #[from]
+    Signer(SignerError),
+
+    #[from]
+    Swap(SwapError),
+
+    #[from]
+    Payjoin(PayjoinError),
+
+    #[from]
+    Delivery(DeliveryError),
+
+    #[from]
+    Import(ImportError),
+
+    #[from]
+    WitnessCheck(WitnessCheckError),
+
+    /// wallet '{0}' is encrypted; a passphrase is required to unlock it.
+    #[display(doc_comments)]
+    WalletLocked(String),
+
+    #[cfg(feature = "encryption")]
+    #[from]
+    Encryption(EncryptionError),
+
+    #[from]
+    Archive(ArchiveError),
+
+    #[cfg(feature = "fs")]
+    #[from]
+    Recovery(RecoveryError),
+
+    #[from]
+    Ownership(OwnershipError),
+
+    #[from]
+    SealExpiry(SealExpiryError),
+
+    #[from]
+    DeferredValidation(DeferredValidationError),
+
+    /// file '{0}' doesn't contain a consignment.
+    #[display(doc_comments)]
+    NotConsignment(String),
+
+    #[from]
+    ContractMismatch(ContractMismatch),
+
+    #[from]
+    Portable(PortableValueError),
+
+    /// invalid {0} data. Details: {1}
+    #[display(doc_comments)]
+    Format(&'static str, String),
+
+    /// reconstructed consignment {found} doesn't match the original
+    /// consignment {expected}.
+    #[display(doc_comments)]
+    Reproduction {
+        expected: ConsignmentId,
+        found: ConsignmentId,
+    },
+
+    #[from]
+    NetworkMismatch(NetworkMismatch),
+
+    #[from]
+    Accept(AcceptError),
+
+    #[from]
+    Sync(SyncError),
+
+    #[from]
+    Explore(ExploreError),
+
+    #[from]
+    AllocationProof(AllocationProofError),
 }
 
 impl From<Infallible> for WalletError {
@@ -146,7 +358,7 @@ pub enum CompositionError {
     /// the invoice has expired.
     InvoiceExpired,
 
-    /// one of the RGB assignments spent require presence of tapret output -
+    /// one of the RGB assignments spent requires presence of tapret output -
     /// even this is not a taproot wallet. Unable to create a valid PSBT, manual
     /// work is needed.
     TapretRequired,
@@ -154,6 +366,115 @@ pub enum CompositionError {
     /// non-fungible state is not yet supported by the invoices.
     Unsupported,
 
+    /// the PSBT can't be extended since its construction was already completed.
+    Unmodifiable,
+
+    /// invoice specifies invalid range of accepted amounts {0}.
+    InvalidAmountRange(String),
+
+    /// invoice requests fixed amount {0}, which can't be changed by the payer.
+    AmountNotNegotiable(Amount),
+
+    /// amount {0} doesn't fit the range {1} of amounts accepted by the invoice.
+    AmountOutOfRange(Amount, AmountRange),
+
+    /// invoice specifies invalid list of split seals {0}.
+    InvalidSplit(String),
+
+    /// split payments are supported only for fungible state assigned to
+    /// blinded seals.
+    SplitUnsupported,
+
+    /// contract {0} doesn't implement any interface providing '{1}' operation.
+    OperationUnsupported(ContractId, String),
+
+    /// allocation {0} is not owned by the wallet.
+    UnknownAllocation(Opout),
+
+    /// allocation {0} can't be preserved by '{1}' operation and must be moved
+    /// to a different output first.
+    UnpreservedState(Opout, String),
+
+    /// the transaction doesn't have a change output to hold the remaining
+    /// contract state.
+    NoChange,
+
+    /// sequence number is provided for {0}, which is not spent by the
+    /// transaction.
+    UnknownInput(Outpoint),
+
+    /// lock time of the transaction is not enforced since all its inputs have
+    /// final sequence numbers.
+    LockTimeDisabled,
+
+    /// lock time {0} of the transaction prevents it from being mined before
+    /// the invoice expiry at {1}.
+    LockTimeAfterExpiry(u32, i64),
+
+    /// only PSBT v2 can be updated with RGB data.
+    PsbtVersion,
+
+    /// the PSBT doesn't contain output paying to the invoice beneficiary.
+    NoBeneficiaryOutput,
+
+    /// one of the RGB assignments spent requires presence of OP_RETURN output,
+    /// which is absent from the PSBT and can't be added since the PSBT
+    /// outputs are not modifiable.
+    OpretRequired,
+
+    /// invoice specifies invalid amount of sats for the witness output {0}.
+    InvalidWitnessSats(String),
+
+    /// amount of sats for the witness output can be specified only by the
+    /// invoices paying to a witness output.
+    WitnessSatsUnsupported,
+
+    /// invoice requires {0} sats to be paid to the witness output, while the
+    /// transaction pays {1} sats.
+    WitnessSatsMismatch(Sats, Sats),
+
+    /// output {0} doesn't belong to the wallet.
+    ForeignOutput(Outpoint),
+
+    /// output {0} is frozen and can't be spent.
+    FrozenOutput(Outpoint),
+
+    /// the selected wallet outputs don't hold any RGB state to consolidate.
+    NothingToConsolidate,
+
+    /// payment template can't derive beneficiary address for payment #{0}
+    /// since the derivation index is out of range.
+    TemplateExhausted(u32),
+
+    /// invoice requests unknown velocity class '{0}' for the received state.
+    InvalidVelocity(String),
+
+    /// the wallet descriptor can't close seals with {0} method.
+    CloseMethodUnsupported(CloseMethod),
+
+    /// invoice beneficiary {0} was already paid by transaction {1}; if the
+    /// transaction was never published, sync the wallet to release the invoice.
+    DuplicatePayment(SecretSeal, Txid),
+
+    /// invalid invoice expiry height '{0}'.
+    InvalidExpiryHeight(String),
+
+    /// RGB state on {0} can't be spent since the wallet holds bitcoin outputs
+    /// only.
+    UnsupportedLayer1(Layer1),
+
+    #[from]
+    #[display(inner)]
+    Resolver(validation::WitnessResolverError),
+
+    #[from]
+    #[display(inner)]
+    NetworkMismatch(NetworkMismatch),
+
+    #[from]
+    #[display(inner)]
+    Builder(BuilderError),
+
     #[from]
     #[display(inner)]
     Construction(ConstructionError),
@@ -166,6 +487,10 @@ pub enum CompositionError {
     #[display(inner)]
     Embed(EmbedError),
 
+    #[from]
+    #[display(inner)]
+    Call(CallError),
+
     #[from(String)]
     #[from(StockError)]
     #[from(StockErrorMem<ComposeError>)]
@@ -204,3 +529,1391 @@ pub enum CompletionError {
     #[display(inner)]
     Stock(String),
 }
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum AcceptError {
+    /// consignment is invalid.
+    ///
+    /// {0}
+    #[from]
+    Invalid(validation::Status),
+
+    #[from]
+    #[display(inner)]
+    NetworkMismatch(NetworkMismatch),
+
+    #[from]
+    #[display(inner)]
+    Registry(RegistryError),
+
+    #[from(String)]
+    #[from(StockError)]
+    #[from(StockErrorMem<ContractIfaceError>)]
+    #[display(inner)]
+    Stock(String),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum SyncError {
+    /// unable to get wallet transactions from the indexer. Details: {0}
+    Indexer(String),
+
+    /// unable to resolve witness transaction {0}. Details: {1}
+    WitnessResolver(XWitnessId, String),
+
+    #[from]
+    #[display(inner)]
+    Reorg(ReorgError),
+
+    #[from(String)]
+    #[from(StockError)]
+    #[display(inner)]
+    Stock(String),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum PreviewError {
+    /// PSBT doesn't contain RGB data.
+    NoRgbData,
+
+    #[from]
+    #[display(inner)]
+    Psbt(RgbPsbtError),
+
+    #[from]
+    #[display(inner)]
+    Stock(String),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum ReorgError {
+    /// unable to resolve block at height {0}. Details: {1}
+    BlockResolver(u32, String),
+
+    /// unable to resolve witness transaction {0}. Details: {1}
+    WitnessResolver(Txid, String),
+
+    #[from]
+    #[display(inner)]
+    Stock(String),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum InvoiceStatusError {
+    /// invoice beneficiary seal is unknown to the wallet; probably the invoice
+    /// was issued by some other wallet.
+    UnknownSeal,
+
+    /// unable to resolve witness transaction {0}. Details: {1}
+    WitnessResolver(XWitnessId, String),
+
+    /// unable to resolve transactions paying to the invoice address. Details:
+    /// {0}
+    ScriptResolver(String),
+
+    #[from]
+    #[display(inner)]
+    Stock(String),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum WitnessCheckError {
+    /// unable to resolve witness transaction {0}. Details: {1}
+    WitnessResolver(XWitnessId, String),
+
+    #[from]
+    #[display(inner)]
+    Stock(String),
+}
+
+#[cfg(feature = "fs")]
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum BackupStoreError {
+    #[from]
+    #[from(io::Error)]
+    #[display(inner)]
+    Io(IoError),
+
+    /// checksum of {0:?} doesn't match its content.
+    Checksum(PathBuf),
+
+    /// unable to encode stock data. Details: {0}
+    #[from]
+    Encode(strict_types::encoding::SerializeError),
+
+    /// stock data are corrupted. Details: {0}
+    #[from]
+    Decode(strict_types::encoding::DeserializeError),
+}
+
+#[cfg(feature = "encryption")]
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum EncryptionError {
+    #[from]
+    #[from(io::Error)]
+    #[display(inner)]
+    Io(IoError),
+
+    /// {0:?} is not an encrypted wallet file.
+    Format(PathBuf),
+
+    /// wrong passphrase or damaged encrypted file {0:?}.
+    WrongPassphrase(PathBuf),
+
+    /// unable to derive encryption key from the passphrase. Details: {0}
+    KeyDerivation(String),
+
+    /// unable to encrypt wallet data.
+    Encrypt,
+
+    /// wallet is encrypted, while tapret tweaks backup {0:?} would be kept in
+    /// plaintext.
+    PlaintextBackup(PathBuf),
+
+    /// invalid wallet data. Details: {0}
+    #[from]
+    Yaml(serde_yaml::Error),
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum SqliteStoreError {
+    /// stock database is opened in read-only mode.
+    ReadOnly,
+
+    /// stock database doesn't contain {0} data.
+    Missing(&'static str),
+
+    /// stock database uses schema version {0}, which is newer than the most
+    /// recent version {1} supported by this software.
+    UnsupportedSchema(u32, u32),
+
+    /// stock database uses schema version {0} and must be opened for writing
+    /// to be upgraded to version {1}.
+    OutdatedSchema(u32, u32),
+
+    /// unable to encode stock {0} data. Details: {1}
+    Encode(&'static str, strict_types::encoding::SerializeError),
+
+    /// stock {0} data in the database are corrupted. Details: {1}
+    Decode(&'static str, strict_types::encoding::DeserializeError),
+
+    #[from]
+    #[display(inner)]
+    Sqlite(rusqlite::Error),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum BasketInvoiceError {
+    /// basket invoice must contain at least one leg.
+    Empty,
+
+    /// basket invoice leg doesn't specify a contract.
+    NoContract,
+
+    /// contract {0} is requested by multiple legs of the basket invoice.
+    RepeatedContract(ContractId),
+
+    /// basket invoice leg for contract {0} uses a different chain network.
+    ChainMismatch(ContractId),
+
+    #[from]
+    #[display(inner)]
+    Invoice(InvoiceParseError),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum AllocationsError {
+    #[from]
+    #[from(io::Error)]
+    #[display(inner)]
+    Io(IoError),
+
+    /// line {0} of the allocation list must have `assignment,seal,amount`
+    /// format.
+    Format(usize),
+
+    /// invalid assignment name '{1}' at line {0}.
+    InvalidAssignment(usize, String),
+
+    /// invalid seal '{1}' at line {0}.
+    InvalidSeal(usize, String),
+
+    /// invalid amount '{1}' at line {0}.
+    InvalidAmount(usize, String),
+}
+
+/// Problem found in a contract definition used for the issuance.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum IssueProblem {
+    /// invalid YAML in the contract definition: {0}
+    Yaml(String),
+
+    /// {0} must be {1}.
+    InvalidStructure(String, &'static str),
+
+    /// contract definition must specify interface under which it is
+    /// constructed.
+    NoInterface,
+
+    /// interface '{0}' is not known to the stock.
+    UnknownInterface(String),
+
+    /// schema {0} is not known to the stock.
+    UnknownSchema(SchemaId),
+
+    /// interface '{0}' is not implemented for schema {1}.
+    NoImplementation(String, SchemaId),
+
+    /// invalid state name '{0}': {1}.
+    InvalidName(String, String),
+
+    /// unknown global state '{0}'; the interface defines: {1}.
+    UnknownGlobal(String, String),
+
+    /// unknown assignment '{0}'; the interface defines: {1}.
+    UnknownAssignment(String, String),
+
+    /// global state '{name}' doesn't match expected type {expected}: {details}.
+    GlobalTypeMismatch {
+        name: String,
+        expected: String,
+        details: String,
+    },
+
+    /// assignment '{name}' data doesn't match expected type {expected}:
+    /// {details}.
+    AssignmentTypeMismatch {
+        name: String,
+        expected: String,
+        details: String,
+    },
+
+    /// assignment '{0}' doesn't provide seal information.
+    NoSeal(String),
+
+    /// invalid seal '{seal}' in assignment '{name}': {details}.
+    InvalidSeal {
+        name: String,
+        seal: String,
+        details: String,
+    },
+
+    /// assignment '{0}' must provide fungible amount as an integer.
+    InvalidAmount(String),
+
+    /// assignment '{0}' must provide structured state under `data` key.
+    NoData(String),
+
+    /// {1} state of assignment '{0}' is not supported in contract
+    /// definitions.
+    UnsupportedState(String, &'static str),
+
+    /// no schema implementing interface '{0}' is known to the stock.
+    NoSchema(String),
+
+    /// invalid {0}: {1}.
+    InvalidField(String, String),
+
+    /// total supply of the allocations exceeds the maximal amount.
+    SupplyOverflow,
+
+    /// unable to add '{0}' to the contract: {1}.
+    Builder(String, String),
+
+    /// contract call must specify name of the state transition.
+    NoTransition,
+
+    /// assignment '{0}' is not fungible and can't be used in the allocation
+    /// list.
+    NonFungibleAllocation(String),
+
+    /// layer 1 '{0}' is not supported.
+    UnsupportedLayer1(String),
+}
+
+/// Contract definition can't be used for the issuance, listing all the
+/// problems found in it.
+#[derive(Clone, PartialEq, Eq, Debug, From, Error)]
+pub struct IssueError(#[from] Vec<IssueProblem>);
+
+impl IssueError {
+    pub fn problems(&self) -> &[IssueProblem] { &self.0 }
+}
+
+impl From<IssueProblem> for IssueError {
+    fn from(problem: IssueProblem) -> Self { Self(vec![problem]) }
+}
+
+impl Display for IssueError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid contract definition:")?;
+        for problem in &self.0 {
+            write!(f, "\n- {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Components of a wallet operation are for a network different from the
+/// expected one, listing all mismatching components.
+#[derive(Clone, PartialEq, Eq, Debug, Error)]
+pub struct NetworkMismatch {
+    pub expected: Network,
+    pub components: Vec<NetworkComponent>,
+}
+
+impl Display for NetworkMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "operation components don't match {} network:", self.expected)?;
+        for component in &self.components {
+            write!(f, "\n- {component}")?;
+        }
+        Ok(())
+    }
+}
+
+/// consignments are for different contracts {0} and {1}.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub struct ContractMismatch(pub ContractId, pub ContractId);
+
+/// Contract call definition can't be used to compose the state transition,
+/// listing all the problems found in it.
+#[derive(Clone, PartialEq, Eq, Debug, From, Error)]
+pub struct CallError(#[from] Vec<IssueProblem>);
+
+impl CallError {
+    pub fn problems(&self) -> &[IssueProblem] { &self.0 }
+}
+
+impl From<IssueProblem> for CallError {
+    fn from(problem: IssueProblem) -> Self { Self(vec![problem]) }
+}
+
+impl Display for CallError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid contract call:")?;
+        for problem in &self.0 {
+            write!(f, "\n- {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum AmendError {
+    /// contract {0} doesn't provide state extensions which can update its
+    /// global state.
+    NoExtension(ContractId),
+
+    /// contract {0} doesn't provide '{1}' state extension.
+    UnknownExtension(ContractId, FieldName),
+
+    /// contract {0} provides multiple state extensions; the one to use must be
+    /// specified explicitly.
+    AmbiguousExtension(ContractId),
+
+    /// global state '{0}' is not known to the contract interface.
+    UnknownGlobal(FieldName),
+
+    /// global state '{0}' can't be updated by '{1}' state extension.
+    GlobalNotAllowed(FieldName, FieldName),
+
+    /// invalid value for global state '{0}': {1}.
+    InvalidGlobal(FieldName, String),
+
+    /// number of values for global state '{0}' doesn't match the schema
+    /// requirements.
+    GlobalOccurrences(String),
+
+    /// contract has no operation providing valency '{0}' redeemed by the state
+    /// extension.
+    NoValency(String),
+
+    #[from(String)]
+    #[from(StockError)]
+    #[from(StockErrorMem<ConsignError>)]
+    #[from(StockErrorMem<ContractIfaceError>)]
+    #[display(inner)]
+    Stock(String),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum CompactInvoiceError {
+    /// unsupported version {0} of the compact invoice encoding.
+    UnsupportedVersion(u8),
+
+    /// invoice network is not supported by the compact invoice encoding.
+    UnsupportedNetwork,
+
+    /// invoice transport is not supported by the compact invoice encoding.
+    UnsupportedTransport,
+
+    /// invoice {0} are too long for the compact invoice encoding.
+    Oversized(&'static str),
+
+    /// invalid {0} tag {1:#04x} in the compact invoice.
+    InvalidTag(&'static str, u8),
+
+    /// invalid beneficiary in the compact invoice: {0}
+    #[from]
+    InvalidBeneficiary(Pay2VoutError),
+
+    /// compact invoice has {0} extra bytes following the invoice data.
+    ExtraData(usize),
+
+    /// invalid compact invoice data. Details: {0}
+    #[from]
+    Decode(strict_types::encoding::DecodeError),
+
+    /// invoice can't be represented as a QR code. Details: {0}
+    #[cfg(feature = "qr")]
+    #[from]
+    Qr(qrcode::types::QrError),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum ArchiveError {
+    /// unable to write the stock archive. Details: {0}
+    #[from]
+    Encode(strict_types::encoding::SerializeError),
+
+    /// unable to read the stock archive. Details: {0}
+    #[from]
+    Decode(strict_types::encoding::DeserializeError),
+
+    #[from]
+    #[display(inner)]
+    Stash(MemError),
+
+    #[from(String)]
+    #[display(inner)]
+    Stock(String),
+}
+
+#[cfg(feature = "fs")]
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum RecoveryError {
+    /// unable to read the recovery directory. Details: {0}
+    #[from]
+    #[from(io::Error)]
+    Io(IoError),
+
+    /// unable to load the file. Details: {0}
+    Load(LoadError),
+
+    #[from]
+    #[display(inner)]
+    Accept(AcceptError),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum Layer2Error {
+    /// channel with funding outpoint {0} is already known.
+    ChannelExists(Outpoint),
+
+    /// channel with funding outpoint {0} is not known.
+    UnknownChannel(Outpoint),
+
+    /// channel with funding outpoint {0} is already closed.
+    ChannelClosed(Outpoint),
+
+    /// channel with funding outpoint {0} is not closed yet.
+    ChannelOpen(Outpoint),
+
+    /// channel state must be anchored to bitcoin transactions.
+    UnsupportedLayer1,
+
+    /// commitment transaction {0} is not known; channel state requires full
+    /// commitment transaction.
+    NoWitnessTx(Txid),
+
+    /// transaction {0} doesn't spend channel funding outpoint {1}.
+    NotCommitment(Txid, Outpoint),
+
+    /// transaction {0} doesn't keep inputs and commitment outputs of the
+    /// replaced commitment transaction.
+    CommitmentMismatch(Txid),
+
+    #[from]
+    #[display(inner)]
+    Stock(String),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum RegistryError {
+    /// the contract imitates already known assets:
+    /// {0}
+    Collision(AssetCollisions),
+
+    /// unable to read contracts from the stock. Details: {0}
+    Stock(String),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum AssetInfoError {
+    /// unable to read the contract from the stock. Details: {0}
+    Stock(String),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum InvoiceApiError {
+    /// invoice doesn't specify the contract.
+    NoContract,
+
+    /// contract {0} is not known to the wallet.
+    UnknownContract(ContractId),
+
+    /// invoice doesn't specify the interface, and the contract is known under
+    /// multiple interfaces or none at all.
+    NoIface,
+
+    /// interface '{0}' is not known to the wallet.
+    UnknownIface(String),
+
+    /// interface '{iface}' is not implemented by contract {contract}.
+    NotImplemented { iface: String, contract: ContractId },
+
+    /// invoice uses interface '{invoice}', while it is validated against
+    /// '{iface}'.
+    IfaceMismatch { invoice: String, iface: String },
+
+    /// invoice doesn't specify the operation, and interface '{0}' doesn't
+    /// define the default one.
+    NoOperation(String),
+
+    /// unknown operation '{0}'; the interface defines: {1}.
+    UnknownOperation(String, String),
+
+    /// invoice doesn't specify the state, and operation '{0}' doesn't define
+    /// the default one.
+    NoAssignment(String),
+
+    /// unknown state '{assignment}' in operation '{operation}'; the operation
+    /// defines: {defined}.
+    UnknownAssignment {
+        operation: String,
+        assignment: String,
+        defined: String,
+    },
+
+    /// invoice provides {found} for state '{assignment}', which requires
+    /// {expected}.
+    StateMismatch {
+        assignment: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    /// invoice doesn't specify value of state '{0}', which is required by the
+    /// payer to construct the transfer.
+    MissingValue(String),
+
+    /// unable to read contract API from the stock. Details: {0}
+    Stock(String),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum KitRegistryError {
+    /// kit registry URL '{0}' doesn't use HTTPS.
+    InsecureUrl(String),
+
+    /// unable to fetch '{0}' from the kit registry. Details: {1}
+    Fetch(String, String),
+
+    /// content of '{url}' has SHA256 hash {found}, while {expected} was
+    /// expected.
+    HashMismatch {
+        url: String,
+        expected: String,
+        found: String,
+    },
+
+    /// invalid kit registry index '{0}'. Details: {1}
+    InvalidIndex(String, String),
+
+    /// invalid kit '{0}'. Details: {1}
+    InvalidKit(String, String),
+
+    /// kit '{0}' doesn't provide schema {1} listed in the registry index.
+    SchemaMismatch(String, SchemaId),
+
+    /// unable to import kit into the stock. Details: {0}
+    Stock(String),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum IdentityError {
+    /// issuer identity doesn't contain a BIP340 public key.
+    NoKey,
+
+    /// issuer identity contains invalid BIP340 public key.
+    InvalidKey,
+
+    /// the signing key doesn't match the key from the issuer identity.
+    KeyMismatch,
+
+    /// unable to access signatures in the stash. Details: {0}
+    Stash(String),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum OwnershipError {
+    /// invoice doesn't contain a proof of the beneficiary ownership.
+    NoProof,
+
+    /// invalid encoding of the beneficiary ownership proof '{0}'.
+    InvalidEncoding(String),
+
+    /// invoice beneficiary doesn't belong to the wallet.
+    UnknownBeneficiary,
+
+    /// ownership of the beneficiary on {0} can't be proven since the wallet
+    /// holds bitcoin outputs only.
+    UnsupportedLayer1(Layer1),
+
+    /// seal revealed by the ownership proof doesn't match the invoice
+    /// beneficiary.
+    SealMismatch,
+
+    /// output {0} of the revealed beneficiary seal is not known.
+    UnknownOutput(Outpoint),
+
+    /// unable to resolve transaction of the beneficiary seal. Details: {0}
+    Resolver(String),
+
+    /// ownership can be proven only for P2WPKH and P2TR outputs.
+    UnsupportedScript,
+
+    /// the signer didn't produce a signature for the ownership proof.
+    Unsigned,
+
+    /// the ownership proof signature is invalid.
+    InvalidSignature,
+
+    #[from]
+    #[display(inner)]
+    Signer(SignerError),
+
+    /// unable to access the stash. Details: {0}
+    Stash(String),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum PolicyError {
+    #[from]
+    #[from(io::Error)]
+    #[display(inner)]
+    Io(IoError),
+
+    /// invalid policy rule '{0}'; rules must have `contract:<id>`,
+    /// `schema:<id>` or `issuer:<identity>` format.
+    InvalidRule(String),
+
+    /// invalid trust policy file. Details: {0}
+    #[cfg(feature = "serde_yaml")]
+    #[from]
+    Yaml(serde_yaml::Error),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum LabelError {
+    #[from]
+    #[from(io::Error)]
+    #[display(inner)]
+    Io(IoError),
+
+    /// invalid label target '{0}'; targets must have `contract:<id>`,
+    /// `witness:<txid>` or `allocation:<opid>/<type>/<no>` format.
+    InvalidTarget(String),
+
+    /// invalid labels file. Details: {0}
+    #[cfg(feature = "serde_yaml")]
+    #[from]
+    Yaml(serde_yaml::Error),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum FreezeError {
+    #[from]
+    #[from(io::Error)]
+    #[display(inner)]
+    Io(IoError),
+
+    /// invalid frozen outpoint '{0}'.
+    InvalidOutpoint(String),
+
+    /// invalid frozen outpoints file. Details: {0}
+    #[cfg(feature = "serde_yaml")]
+    #[from]
+    Yaml(serde_yaml::Error),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum VelocityError {
+    #[from]
+    #[from(io::Error)]
+    #[display(inner)]
+    Io(IoError),
+
+    /// invalid velocity preference key '{0}', which must have
+    /// `CONTRACT_ID/ASSIGNMENT_TYPE` or `CONTRACT_ID/ASSIGNMENT_NAME` format.
+    InvalidKey(String),
+
+    /// unknown velocity class '{0}'.
+    InvalidHint(String),
+
+    /// invalid velocity preferences file. Details: {0}
+    #[cfg(feature = "serde_yaml")]
+    #[from]
+    Yaml(serde_yaml::Error),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum ImportError {
+    #[from]
+    #[from(io::Error)]
+    #[display(inner)]
+    Io(IoError),
+
+    /// invalid imported outpoint '{0}'.
+    InvalidOutpoint(String),
+
+    /// invalid script '{0}' of an imported output.
+    InvalidScript(String),
+
+    /// invalid imported outputs file. Details: {0}
+    #[cfg(feature = "serde_yaml")]
+    #[from]
+    Yaml(serde_yaml::Error),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum WalletDirError {
+    #[from]
+    #[from(io::Error)]
+    #[display(inner)]
+    Io(IoError),
+
+    /// wallet '{0}' doesn't exist.
+    NotFound(String),
+
+    /// wallet '{0}' already exists.
+    AlreadyExists(String),
+
+    /// invalid wallet name '{0}'; the name must be non-empty, must not start
+    /// with a dot and must not contain path separators.
+    InvalidName(String),
+
+    /// unable to load wallet '{0}'. Details: {1}
+    Load(String, String),
+
+    /// wallet '{name}' uses {found} network and can't be moved into the data
+    /// directory of {expected} network.
+    WrongNetwork {
+        name: String,
+        expected: Network,
+        found: Network,
+    },
+
+    /// '{0}' already exists in the network data directory.
+    Collision(String),
+
+    /// wallet '{0}' is encrypted and can't be read without a passphrase.
+    Locked(String),
+
+    /// wallet '{0}' is already encrypted.
+    AlreadyEncrypted(String),
+
+    /// wallet '{0}' is not encrypted.
+    NotEncrypted(String),
+
+    /// unable to save wallet '{0}'. Details: {1}
+    Store(String, String),
+
+    #[cfg(feature = "encryption")]
+    #[from]
+    #[display(inner)]
+    Encryption(EncryptionError),
+}
+
+/// Errors constructing RGB descriptor from the standard output descriptors.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum DescriptorImportError {
+    /// no descriptors are provided.
+    Empty,
+
+    /// descriptor '{0}' contains invalid characters.
+    InvalidCharacters(String),
+
+    /// invalid descriptor checksum '{found}'; expected '{expected}'.
+    Checksum { expected: String, found: String },
+
+    /// descriptor '{0}' is not supported; only single-key `wpkh` and `tr`
+    /// descriptors can be used for RGB wallets.
+    Unsupported(String),
+
+    /// descriptors use different extended keys or script types.
+    Mismatch,
+
+    /// descriptors use too many keychains.
+    TooManyKeychains,
+
+    /// invalid extended key in the descriptor. Details: {0}
+    #[from]
+    Key(XkeyParseError),
+}
+
+/// Errors exporting contract history graph.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum ExploreError {
+    /// contract {0} is unknown to the stock.
+    UnknownContract(ContractId),
+
+    /// unable to read the contract history from the stock. Details: {0}
+    Stock(String),
+}
+
+/// Errors building or verifying proof of a contract allocation.
+#[derive(Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum AllocationProofError {
+    /// contract {0} is unknown to the stock.
+    UnknownContract(ContractId),
+
+    /// unable to read the contract history from the stock. Details: {0}
+    Stock(String),
+
+    /// contract {contract_id} has no known allocations at {outpoint}.
+    NoAllocation {
+        contract_id: ContractId,
+        outpoint: Outpoint,
+    },
+
+    /// proof is made for the contract {found} instead of {expected}.
+    WrongContract {
+        expected: ContractId,
+        found: ContractId,
+    },
+
+    /// proof is invalid.
+    ///
+    /// {0}
+    Invalid(validation::Status),
+
+    /// unable to process the proof history. Details: {0}
+    Accept(String),
+}
+
+/// Errors verifying anchor of a transition bundle against its witness
+/// transaction.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum AnchorError {
+    /// anchor doesn't commit to the bundle {bundle_id} under the contract
+    /// {contract_id}. Details: {details}
+    Mpc {
+        contract_id: ContractId,
+        bundle_id: BundleId,
+        details: mpc::InvalidProof,
+    },
+
+    /// transaction {txid} doesn't contain the commitment proven by the anchor.
+    /// Details: {details}
+    Dbc { txid: Txid, details: DbcError },
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum PortableValueError {
+    /// invalid portable value marker `{0}`.
+    InvalidMarker(&'static str),
+
+    /// map key {0} is not a string; maps with non-string keys must use `$map`
+    /// marker.
+    NonStringKey(String),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum SealExpiryError {
+    #[from]
+    #[from(io::Error)]
+    #[display(inner)]
+    Io(IoError),
+
+    /// invalid seal expiry file. Details: {0}
+    #[cfg(feature = "serde_yaml")]
+    #[from]
+    Yaml(serde_yaml::Error),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum ReservationError {
+    #[from]
+    #[from(io::Error)]
+    #[display(inner)]
+    Io(IoError),
+
+    /// invalid derivation terminal '{0}' of a reserved address.
+    InvalidTerminal(String),
+
+    /// invalid address reservations file. Details: {0}
+    #[cfg(feature = "serde_yaml")]
+    #[from]
+    Yaml(serde_yaml::Error),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum StockExportError {
+    #[from]
+    #[from(io::Error)]
+    #[display(inner)]
+    Io(IoError),
+
+    /// the file is not an RGB stock export.
+    NotExport,
+
+    /// stock export uses format version {0}, which is not supported by this
+    /// software.
+    UnsupportedVersion(u16),
+
+    /// stock export is truncated.
+    Truncated,
+
+    /// stock export contains data past its last entry.
+    TrailingData,
+
+    /// stock export contains an entry with a non-UTF8 name.
+    InvalidName,
+
+    /// stock export contains entry '{0}' more than once.
+    RepeatedEntry(String),
+
+    /// checksum of the stock export entry '{0}' doesn't match its data.
+    Checksum(String),
+
+    /// stock export doesn't contain {0} data.
+    Missing(&'static str),
+
+    /// stock export entry '{0}' has invalid content.
+    InvalidEntry(String),
+
+    /// unable to encode stock {0} data. Details: {1}
+    Encode(&'static str, strict_types::encoding::SerializeError),
+
+    /// stock {0} data in the export are corrupted. Details: {1}
+    Decode(&'static str, strict_types::encoding::DeserializeError),
+
+    /// stock export can't be modified; the imported stock has to be made
+    /// persistent with the provider of the target backend.
+    ReadOnly,
+
+    /// unable to construct the stock from the export. Details: {0}
+    Stock(PersistenceError),
+
+    /// '{0}' already exists; importing the stock would replace it.
+    StockExists(String),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum DeferredValidationError {
+    #[from]
+    #[from(io::Error)]
+    #[display(inner)]
+    Io(IoError),
+
+    /// invalid deferred validation file. Details: {0}
+    #[cfg(feature = "serde_yaml")]
+    #[from]
+    Yaml(serde_yaml::Error),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum SignerError {
+    /// invalid mnemonic phrase. Details: {0}
+    #[cfg(feature = "hot")]
+    #[from]
+    Mnemonic(bip39::Error),
+
+    #[from]
+    #[display(inner)]
+    Sign(psrgbt::SignError),
+
+    #[display(inner)]
+    Custom(String),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum SwapError {
+    /// PSBT is not an RGB swap proposal since it doesn't contain valid {0}
+    /// invoice.
+    NoInvoice(&'static str),
+
+    /// PSBT is not an RGB sale proposal since it doesn't contain valid price
+    /// information.
+    NoPrice,
+
+    /// swap invoice doesn't specify the contract.
+    NoContract,
+
+    /// invalid swap invoice. Details: {0}
+    #[from]
+    InvalidInvoice(InvoiceParseError),
+
+    /// swap proposal contains invalid RGB data.
+    InvalidRgbData,
+
+    /// swap proposal was already accepted by the counterparty.
+    AlreadyAccepted,
+
+    /// swap transaction doesn't contain state transitions for contract {0},
+    /// meaning one of the swap legs is missing.
+    MissingLeg(ContractId),
+
+    /// RGB data committed in the swap proposal do not match the swap
+    /// transaction.
+    WitnessMismatch,
+
+    /// swap transaction doesn't spend any RGB state owned by the wallet.
+    NoOwnLeg,
+
+    /// swap transaction doesn't pay the agreed price to the seller.
+    PriceMismatch,
+
+    /// state transitions of the counterparty don't pay the requested amount
+    /// to the beneficiary of the swap request invoice.
+    RequestMismatch,
+
+    #[from]
+    #[display(inner)]
+    Extract(ExtractError),
+
+    #[from]
+    #[display(inner)]
+    Composition(CompositionError),
+
+    #[from]
+    #[from(CommitError)]
+    #[display(inner)]
+    Completion(CompletionError),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum PayjoinError {
+    /// PSBT is not an RGB payjoin proposal since it doesn't contain valid
+    /// invoice.
+    NoInvoice,
+
+    /// invoice doesn't specify payjoin endpoint.
+    NoEndpoint,
+
+    /// invalid payjoin endpoint '{0}'; only HTTPS endpoints are supported.
+    InvalidEndpoint(String),
+
+    /// invalid payjoin invoice. Details: {0}
+    #[from]
+    InvalidInvoice(InvoiceParseError),
+
+    /// payjoin proposal pays invoice different from the original one.
+    InvoiceMismatch,
+
+    /// payjoin proposal doesn't contain state transitions for contract {0}.
+    NoPayment(ContractId),
+
+    /// RGB data are already committed to the payjoin proposal.
+    AlreadyCommitted,
+
+    /// wallet doesn't have coins without RGB state which can be contributed
+    /// to the payjoin transaction.
+    NoCoins,
+
+    /// payjoin exchange with '{0}' has failed. Details: {1}
+    Exchange(String, String),
+
+    /// payjoin transaction doesn't spend input {0} of the original
+    /// transaction.
+    InputRemoved(Outpoint),
+
+    /// receiver has added input {0}, which belongs to the payer wallet, to the
+    /// payjoin transaction.
+    OwnInputAdded(Outpoint),
+
+    /// payjoin transaction doesn't contain output #{0} of the original
+    /// transaction.
+    OutputRemoved(u32),
+
+    /// receiver has changed the amount of output #{0} of the original
+    /// transaction which belongs to the payer or reduced the amount paid to
+    /// the beneficiary.
+    OutputChanged(u32),
+
+    /// payjoin transaction pays fee {1}, which is lower than the fee {0} of
+    /// the original transaction; probably the receiver has taken a part of it.
+    FeeReduced(Sats, Sats),
+
+    #[from]
+    #[display(inner)]
+    Composition(CompositionError),
+
+    #[from]
+    #[from(CommitError)]
+    #[display(inner)]
+    Completion(CompletionError),
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum DeliveryError {
+    /// invalid transport delivery metadata '{0}'.
+    InvalidMeta(String),
+
+    /// invoice provides delivery metadata for {0} transports, while it lists
+    /// {1} transports.
+    MetaMismatch(usize, usize),
+
+    /// invoice doesn't specify transports for the consignment delivery.
+    NoTransports,
+
+    /// unable to serialize the consignment. Details: {0}
+    #[from]
+    Serialize(io::Error),
+
+    /// consignment delivery has failed. Details: {0}
+    Failed(String),
+}
+
+/// Error providing a stable numeric code of its kind, allowing consumers of
+/// the library (and foreign language bindings in particular) to branch on the
+/// failure kind without parsing error messages.
+///
+/// Codes are grouped by the error type: 1xxx for the generic wallet errors,
+/// 2xxx for [`CompositionError`], 3xxx for [`CompletionError`], 4xxx for
+/// [`AcceptError`] and 5xxx for [`SyncError`]. Once assigned, a code is never
+/// changed or reused.
+pub trait ErrorCode {
+    fn error_code(&self) -> u16;
+}
+
+impl ErrorCode for WalletError {
+    fn error_code(&self) -> u16 {
+        match self {
+            WalletError::File(_) => 1001,
+            WalletError::StockLoad(_) => 1002,
+            WalletError::WalletPersist(_) => 1003,
+            WalletError::StockPersist(_) => 1004,
+            #[cfg(feature = "cli")]
+            WalletError::WalletExec(_) => 1005,
+            WalletError::Builder(_) => 1006,
+            WalletError::Contract(_) => 1007,
+            WalletError::Invoicing(_) => 1008,
+            WalletError::PsbtDecode(_) => 1009,
+            WalletError::WalletUnknown(_) => 1010,
+            WalletError::StockLocked(_) => 1011,
+            WalletError::InvalidConsignment(_) => AcceptError::INVALID,
+            WalletError::InvalidId(_) => 1013,
+            WalletError::IncompleteContract(_) => 1014,
+            WalletError::Resolver(_) => 1015,
+            WalletError::Stock(_) => 1016,
+            #[cfg(feature = "serde_yaml")]
+            WalletError::Yaml(_) => 1017,
+            WalletError::Issue(_) => 1018,
+            WalletError::Allocations(_) => 1019,
+            WalletError::InvalidName(_, _) => 1020,
+            WalletError::IssuerKey => 1021,
+            WalletError::Xpriv(_) => 1022,
+            WalletError::UnexpectedTransfer => 1023,
+            WalletError::NoOutpoint => 1024,
+            WalletError::InsufficientOutpoints { .. } => 1025,
+            WalletError::Tweaks(_) => 1026,
+            WalletError::TapTweak(_) => 1027,
+            WalletError::Policy(_) => 1028,
+            WalletError::Identity(_) => 1029,
+            WalletError::Registry(_) => 1030,
+            WalletError::Amend(_) => 1031,
+            WalletError::CompactInvoice(_) => 1032,
+            WalletError::InvoiceStatus(_) => 1033,
+            WalletError::Basket(_) => 1034,
+            WalletError::Call(_) => 1035,
+            WalletError::Preview(_) => 1036,
+            WalletError::SignRequest(_) => 1037,
+            WalletError::Signer(_) => 1038,
+            WalletError::Swap(_) => 1039,
+            WalletError::Archive(_) => 1040,
+            #[cfg(feature = "fs")]
+            WalletError::Recovery(_) => 1041,
+            WalletError::Ownership(_) => 1042,
+            WalletError::SealExpiry(_) => 1043,
+            WalletError::NetworkMismatch(_) => 1044,
+            WalletError::Cosign(_) => 1045,
+            WalletError::DeferredValidation(_) => 1046,
+            WalletError::NotConsignment(_) => 1047,
+            WalletError::ContractMismatch(_) => 1048,
+            WalletError::Portable(_) => 1049,
+            WalletError::Reproduction { .. } => 1050,
+            WalletError::Format(..) => 1051,
+            WalletError::Label(_) => 1052,
+            WalletError::KitRegistry(_) => 1053,
+            WalletError::InvoiceApi(_) => 1054,
+            WalletError::WalletDir(_) => 1055,
+            WalletError::Freeze(_) => 1056,
+            WalletError::PsbtParse(_) => 1057,
+            WalletError::Armored(_) => 1058,
+            WalletError::Explore(_) => 1059,
+            WalletError::Payjoin(_) => 1060,
+            WalletError::Delivery(_) => 1061,
+            WalletError::Import(_) => 1062,
+            WalletError::WitnessCheck(_) => 1063,
+            WalletError::WalletLocked(_) => 1064,
+            #[cfg(feature = "encryption")]
+            WalletError::Encryption(_) => 1065,
+            WalletError::AllocationProof(_) => 1066,
+            WalletError::Velocity(_) => 1067,
+            WalletError::Reservation(_) => 1069,
+            WalletError::StockExport(_) => 1070,
+            WalletError::Composition(err) => err.error_code(),
+            WalletError::Completion(err) => err.error_code(),
+            WalletError::Pay(err) => err.error_code(),
+            WalletError::Accept(err) => err.error_code(),
+            WalletError::Sync(err) => err.error_code(),
+        }
+    }
+}
+
+impl ErrorCode for PayError {
+    fn error_code(&self) -> u16 {
+        match self {
+            PayError::Composition(err) => err.error_code(),
+            PayError::Completion(err, _) => err.error_code(),
+        }
+    }
+}
+
+impl ErrorCode for CompositionError {
+    fn error_code(&self) -> u16 {
+        match self {
+            CompositionError::NoContract => 2001,
+            CompositionError::NoIface => 2002,
+            CompositionError::NoOperation => 2003,
+            CompositionError::NoAssignment => 2004,
+            CompositionError::InsufficientState => 2005,
+            CompositionError::InvoiceExpired => 2006,
+            CompositionError::TapretRequired => 2007,
+            CompositionError::Unsupported => 2008,
+            CompositionError::Unmodifiable => 2009,
+            CompositionError::InvalidAmountRange(_) => 2010,
+            CompositionError::AmountNotNegotiable(_) => 2011,
+            CompositionError::AmountOutOfRange(_, _) => 2012,
+            CompositionError::InvalidSplit(_) => 2013,
+            CompositionError::SplitUnsupported => 2014,
+            CompositionError::OperationUnsupported(_, _) => 2015,
+            CompositionError::UnknownAllocation(_) => 2016,
+            CompositionError::UnpreservedState(_, _) => 2017,
+            CompositionError::NoChange => 2018,
+            CompositionError::UnknownInput(_) => 2019,
+            CompositionError::LockTimeDisabled => 2020,
+            CompositionError::LockTimeAfterExpiry(_, _) => 2021,
+            CompositionError::Builder(_) => 2022,
+            CompositionError::Construction(_) => 2023,
+            CompositionError::Interface(_) => 2024,
+            CompositionError::Embed(_) => 2025,
+            CompositionError::Call(_) => 2026,
+            CompositionError::Stock(_) => 2027,
+            CompositionError::PsbtVersion => 2028,
+            CompositionError::NoBeneficiaryOutput => 2029,
+            CompositionError::OpretRequired => 2030,
+            CompositionError::InvalidWitnessSats(_) => 2031,
+            CompositionError::WitnessSatsUnsupported => 2032,
+            CompositionError::WitnessSatsMismatch(_, _) => 2033,
+            CompositionError::NetworkMismatch(_) => 2034,
+            CompositionError::ForeignOutput(_) => 2035,
+            CompositionError::Resolver(_) => 2036,
+            CompositionError::FrozenOutput(_) => 2037,
+            CompositionError::NothingToConsolidate => 2038,
+            CompositionError::TemplateExhausted(_) => 2039,
+            CompositionError::InvalidVelocity(_) => 2040,
+            CompositionError::CloseMethodUnsupported(_) => 2041,
+            CompositionError::DuplicatePayment(..) => 2042,
+            CompositionError::InvalidExpiryHeight(_) => 2043,
+            CompositionError::UnsupportedLayer1(_) => 2044,
+        }
+    }
+}
+
+impl ErrorCode for CompletionError {
+    fn error_code(&self) -> u16 {
+        match self {
+            CompletionError::NoContract => 3001,
+            CompletionError::NoBeneficiaryOutput => 3002,
+            CompletionError::InconclusiveDerivation => 3003,
+            CompletionError::MultipleTweaks(_) => 3004,
+            CompletionError::TapretKey(_) => 3005,
+            CompletionError::Commit(_) => 3006,
+            CompletionError::Stock(_) => 3007,
+        }
+    }
+}
+
+impl AcceptError {
+    const INVALID: u16 = 4001;
+}
+
+impl ErrorCode for AcceptError {
+    fn error_code(&self) -> u16 {
+        match self {
+            AcceptError::Invalid(_) => Self::INVALID,
+            AcceptError::Registry(_) => 4002,
+            AcceptError::Stock(_) => 4003,
+            AcceptError::NetworkMismatch(_) => 4004,
+        }
+    }
+}
+
+impl ErrorCode for SyncError {
+    fn error_code(&self) -> u16 {
+        match self {
+            SyncError::Indexer(_) => 5001,
+            SyncError::WitnessResolver(_, _) => 5002,
+            SyncError::Reorg(_) => 5003,
+            SyncError::Stock(_) => 5004,
+        }
+    }
+}
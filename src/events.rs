@@ -0,0 +1,121 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+use std::sync::mpsc;
+
+use bpstd::{Outpoint, Txid};
+use rgbstd::{ContractId, Opout};
+
+use crate::KeychainLayout;
+
+/// Change of the wallet or contract state reported to the subscribers of
+/// [`crate::RgbWallet`].
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(doc_comments)]
+pub enum WalletEvent {
+    /// new unspent output {0} is available to the wallet.
+    UtxoAdded(Outpoint),
+
+    /// transfer of contract {0} is accepted into the stock.
+    TransferAccepted(ContractId),
+
+    /// witness transaction {0} is mined at height {1}.
+    WitnessMined(Txid, u32),
+
+    /// allocation {0} owned by the wallet is spent.
+    AllocationSpent(Opout),
+
+    /// wallet keychain layout is changed to {0} after detecting RGB seals
+    /// on a keychain not known to the previous layout.
+    KeychainLayoutDetected(KeychainLayout),
+}
+
+/// Callback receiving wallet events.
+pub type EventHook = Box<dyn FnMut(&WalletEvent) + Send>;
+
+/// Subscribers to the wallet events.
+#[derive(Default)]
+pub(crate) struct Observers {
+    hooks: Vec<EventHook>,
+    snapshot: Option<StateSnapshot>,
+}
+
+impl Observers {
+    pub fn is_empty(&self) -> bool { self.hooks.is_empty() }
+
+    /// Forgets the last known state, such that the next update produces no
+    /// events.
+    pub fn reset(&mut self) { self.snapshot = None; }
+
+    pub fn subscribe(&mut self, hook: EventHook) { self.hooks.push(hook); }
+
+    pub fn subscribe_channel(&mut self) -> mpsc::Receiver<WalletEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribe(Box::new(move |event| {
+            // Disconnected receivers are ignored
+            sender.send(event.clone()).ok();
+        }));
+        receiver
+    }
+
+    pub fn notify(&mut self, event: WalletEvent) {
+        for hook in &mut self.hooks {
+            hook(&event);
+        }
+    }
+
+    /// Replaces the last known state, notifying subscribers about the
+    /// changes; the very first snapshot produces no events.
+    pub fn update(&mut self, snapshot: StateSnapshot) {
+        let Some(prev) = self.snapshot.replace(snapshot) else {
+            return;
+        };
+        let snapshot = self.snapshot.as_ref().expect("just set");
+        let events = snapshot
+            .utxos
+            .difference(&prev.utxos)
+            .copied()
+            .map(WalletEvent::UtxoAdded)
+            .chain(
+                prev.allocations
+                    .difference(&snapshot.allocations)
+                    .copied()
+                    .map(WalletEvent::AllocationSpent),
+            )
+            .collect::<Vec<_>>();
+        for event in events {
+            self.notify(event);
+        }
+    }
+}
+
+/// Part of the wallet state tracked for producing the events.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub(crate) struct StateSnapshot {
+    pub utxos: BTreeSet<Outpoint>,
+    pub allocations: BTreeSet<Opout>,
+}
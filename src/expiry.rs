@@ -0,0 +1,252 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Expiry of invoices by wall-clock time and by block height.
+//!
+//! Invoices may expire at a timestamp, as defined by the invoice standard, and
+//! at a block height, which is given in the invoice query parameters. Unlike
+//! timestamps, heights can't be shifted by a wrong clock of the payer. Payers
+//! refuse to pay expired invoices, such that the receiver may reuse the
+//! address of an expired witness-out invoice, which was not paid: the address
+//! is kept in [`AddressReservations`] until the invoice expires and is
+//! released afterwards.
+
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::path::Path;
+#[cfg(feature = "serde")]
+use std::str::FromStr;
+
+use bpstd::Terminal;
+use rgbstd::invoice::RgbInvoice;
+
+use crate::CompositionError;
+#[cfg(feature = "serde")]
+use crate::ReservationError;
+
+/// Invoice query parameter specifying the block height at which the invoice
+/// expires.
+pub const INVOICE_QUERY_EXPIRY_HEIGHT: &str = "expiry_height";
+
+/// Expiry of an invoice: a timestamp and a block height, each of which is
+/// optional. The invoice expires once any of them is reached.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct InvoiceExpiry {
+    /// UNIX timestamp at which the invoice expires.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub timestamp: Option<i64>,
+    /// Block height at which the invoice expires.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub height: Option<u32>,
+}
+
+impl InvoiceExpiry {
+    pub fn new(timestamp: Option<i64>, height: Option<u32>) -> Self { Self { timestamp, height } }
+
+    /// Reads expiry from the invoice and its query parameters.
+    pub fn from_invoice(invoice: &RgbInvoice) -> Result<Self, CompositionError> {
+        let height = invoice
+            .unknown_query
+            .get(INVOICE_QUERY_EXPIRY_HEIGHT)
+            .map(|value| {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| CompositionError::InvalidExpiryHeight(value.clone()))
+            })
+            .transpose()?;
+        Ok(Self::new(invoice.expiry, height))
+    }
+
+    /// Stores the expiry in the invoice and its query parameters.
+    pub fn set_to_invoice(&self, invoice: &mut RgbInvoice) {
+        invoice.expiry = self.timestamp;
+        match self.height {
+            Some(height) => {
+                invoice
+                    .unknown_query
+                    .insert(INVOICE_QUERY_EXPIRY_HEIGHT.to_owned(), height.to_string());
+            }
+            None => {
+                invoice.unknown_query.shift_remove(INVOICE_QUERY_EXPIRY_HEIGHT);
+            }
+        }
+    }
+
+    /// Detects whether the invoice never expires.
+    pub fn is_never(&self) -> bool { self.timestamp.is_none() && self.height.is_none() }
+
+    /// Checks whether the invoice has expired at the UNIX timestamp `now` or
+    /// once the chain tip reached `tip_height`. Parts of the expiry which
+    /// can't be checked since the current time or height is not known are
+    /// ignored.
+    pub fn is_expired(&self, now: Option<i64>, tip_height: Option<u32>) -> bool {
+        let by_time = matches!((self.timestamp, now), (Some(expiry), Some(now)) if expiry <= now);
+        let by_height =
+            matches!((self.height, tip_height), (Some(expiry), Some(tip)) if expiry <= tip);
+        by_time || by_height
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(crate = "serde_crate", rename_all = "camelCase")]
+struct ReservationsData {
+    #[serde(default)]
+    reserved: BTreeMap<String, InvoiceExpiry>,
+    #[serde(default)]
+    released: BTreeSet<String>,
+}
+
+/// Addresses of the witness-out invoices issued by the wallet, which are
+/// reserved until the invoices expire, and the addresses of the invoices which
+/// have expired unpaid and may be used by new invoices.
+///
+/// Addresses are identified by the terminal derivation of their keys.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", try_from = "ReservationsData", into = "ReservationsData")
+)]
+pub struct AddressReservations {
+    reserved: BTreeMap<Terminal, InvoiceExpiry>,
+    released: BTreeSet<Terminal>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ReservationsData> for AddressReservations {
+    type Error = ReservationError;
+
+    fn try_from(data: ReservationsData) -> Result<Self, Self::Error> {
+        let parse = |terminal: String| {
+            Terminal::from_str(&terminal).map_err(|_| ReservationError::InvalidTerminal(terminal))
+        };
+        let reserved = data
+            .reserved
+            .into_iter()
+            .map(|(terminal, expiry)| Ok((parse(terminal)?, expiry)))
+            .collect::<Result<_, ReservationError>>()?;
+        let released = data
+            .released
+            .into_iter()
+            .map(parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { reserved, released })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<AddressReservations> for ReservationsData {
+    fn from(reservations: AddressReservations) -> Self {
+        ReservationsData {
+            reserved: reservations
+                .reserved
+                .into_iter()
+                .map(|(terminal, expiry)| (terminal.to_string(), expiry))
+                .collect(),
+            released: reservations
+                .released
+                .into_iter()
+                .map(|terminal| terminal.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl AddressReservations {
+    pub fn new() -> Self { Self::default() }
+
+    /// Loads reservations from a YAML file, returning no reservations if the
+    /// file doesn't exist.
+    #[cfg(feature = "fs")]
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self, ReservationError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = fs::File::open(path)?;
+        Ok(serde_yaml::from_reader(file)?)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn save_file(&self, path: impl AsRef<Path>) -> Result<(), ReservationError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = fs::File::create(path)?;
+        serde_yaml::to_writer(file, self)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool { self.reserved.is_empty() && self.released.is_empty() }
+
+    /// Reserves the address until the invoice using it expires. Returns the
+    /// previous expiry of the reservation, if any.
+    pub fn reserve(&mut self, terminal: Terminal, expiry: InvoiceExpiry) -> Option<InvoiceExpiry> {
+        self.released.remove(&terminal);
+        self.reserved.insert(terminal, expiry)
+    }
+
+    /// Removes the reservation of the address, which then stays used by the
+    /// wallet. Returns the expiry of the reservation.
+    pub fn remove(&mut self, terminal: Terminal) -> Option<InvoiceExpiry> {
+        self.reserved.remove(&terminal)
+    }
+
+    /// Releases the address, such that it can be used by a new invoice.
+    /// Returns the expiry of the reservation.
+    pub fn release(&mut self, terminal: Terminal) -> Option<InvoiceExpiry> {
+        self.released.insert(terminal);
+        self.reserved.remove(&terminal)
+    }
+
+    /// Takes one of the released addresses for use by a new invoice.
+    pub fn take_released(&mut self) -> Option<Terminal> { self.released.pop_first() }
+
+    pub fn expiry(&self, terminal: Terminal) -> Option<InvoiceExpiry> {
+        self.reserved.get(&terminal).copied()
+    }
+
+    pub fn reserved(&self) -> impl Iterator<Item = (Terminal, InvoiceExpiry)> + '_ {
+        self.reserved
+            .iter()
+            .map(|(terminal, expiry)| (*terminal, *expiry))
+    }
+
+    pub fn released(&self) -> impl Iterator<Item = Terminal> + '_ { self.released.iter().copied() }
+
+    /// Returns the reserved addresses which invoices have expired at the UNIX
+    /// timestamp `now` and the chain tip height `tip_height`.
+    pub fn expired(&self, now: i64, tip_height: u32) -> impl Iterator<Item = Terminal> + '_ {
+        self.reserved
+            .iter()
+            .filter(move |(_, expiry)| expiry.is_expired(Some(now), Some(tip_height)))
+            .map(|(terminal, _)| *terminal)
+    }
+}
@@ -0,0 +1,303 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export of the contract history known to the stock as a graph, suitable for
+//! block explorers and visualisation tools.
+//!
+//! The graph consists of the contract operations, the witness transactions
+//! anchoring their bundles and the allocations which flow from the
+//! operations assigning the state to the operations spending it. The
+//! confirmation status of the witness transactions is the one known to the
+//! stock, i.e. it is as recent as the last wallet sync with the indexer.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use bp::Outpoint;
+use rgbstd::containers::ConsignmentExt;
+use rgbstd::invoice::Amount;
+use rgbstd::persistence::{ContractStateRead, IndexProvider, StashProvider, StateProvider, Stock};
+use rgbstd::vm::WitnessOrd;
+use rgbstd::{
+    BundleId, ContractId, OpFullType, OpId, Operation, Opout, OutputSeal, XChain, XOutputSeal,
+    XWitnessId,
+};
+
+use crate::ExploreError;
+
+/// Contract operation in the [`ContractGraph`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct GraphOperation {
+    pub opid: OpId,
+    pub op_type: OpFullType,
+    /// Transition bundle containing the operation; absent for the genesis and
+    /// the state extensions.
+    pub bundle_id: Option<BundleId>,
+    /// Witness transaction anchoring the operation bundle.
+    pub witness_id: Option<XWitnessId>,
+}
+
+/// Witness transaction in the [`ContractGraph`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct GraphWitness {
+    pub witness_id: XWitnessId,
+    /// Bundles of the contract anchored to the transaction.
+    pub bundles: Vec<BundleId>,
+    /// Status of the transaction known to the stock, if any.
+    pub status: Option<WitnessOrd>,
+    /// Height of the block mining the transaction.
+    pub height: Option<u32>,
+}
+
+/// Allocation of the contract state in the [`ContractGraph`], assigned by an
+/// operation and, possibly, spent by another one.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct GraphAllocation {
+    pub opout: Opout,
+    /// Transaction output holding the state, if the seal is known to the
+    /// stock.
+    pub seal: Option<XChain<Outpoint>>,
+    /// Fungible amount, if the state is fungible and known to the stock.
+    pub amount: Option<Amount>,
+    /// Operation spending the state, if it is known to the stock.
+    pub spent_by: Option<OpId>,
+}
+
+/// Graph of the contract history known to the stock.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ContractGraph {
+    pub contract_id: ContractId,
+    pub operations: Vec<GraphOperation>,
+    pub witnesses: Vec<GraphWitness>,
+    pub allocations: Vec<GraphAllocation>,
+}
+
+impl ContractGraph {
+    /// Walks the contract history known to the stock, starting from the
+    /// genesis up to all the allocations the stock is aware of.
+    pub fn build<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        stock: &Stock<S, H, P>,
+        contract_id: ContractId,
+    ) -> Result<Self, ExploreError> {
+        let state = stock
+            .contract_state(contract_id)
+            .map_err(|_| ExploreError::UnknownContract(contract_id))?;
+
+        let mut allocations = BTreeMap::<Opout, GraphAllocation>::new();
+        let mut seals = Vec::new();
+        let mut add_known = |opout: Opout, seal: XOutputSeal, amount: Option<Amount>| {
+            seals.push(seal);
+            allocations.insert(opout, GraphAllocation {
+                opout,
+                seal: Some(seal.map_ref(OutputSeal::to_outpoint)),
+                amount,
+                spent_by: None,
+            });
+        };
+        for a in state.rights_all() {
+            add_known(a.opout, a.seal, None);
+        }
+        for a in state.fungible_all() {
+            add_known(a.opout, a.seal, Some(Amount::from(a.state.value.as_u64())));
+        }
+        for a in state.data_all() {
+            add_known(a.opout, a.seal, None);
+        }
+        for a in state.attach_all() {
+            add_known(a.opout, a.seal, None);
+        }
+
+        let consignment = stock
+            .transfer(contract_id, seals, None)
+            .map_err(|e| ExploreError::Stock(e.to_string()))?;
+
+        let mut operations = vec![GraphOperation {
+            opid: consignment.genesis.id(),
+            op_type: consignment.genesis.full_type(),
+            bundle_id: None,
+            witness_id: None,
+        }];
+        let mut ops = vec![(consignment.genesis.id(), consignment.genesis.assignments().flat())];
+        for extension in consignment.extensions() {
+            operations.push(GraphOperation {
+                opid: extension.id(),
+                op_type: extension.full_type(),
+                bundle_id: None,
+                witness_id: None,
+            });
+            ops.push((extension.id(), extension.assignments().flat()));
+        }
+
+        let mut witnesses = Vec::with_capacity(consignment.bundles.len());
+        for witness_bundle in &consignment.bundles {
+            let witness_id = witness_bundle.witness_id();
+            let mut bundles = vec![];
+            for (_, bundle) in witness_bundle.anchored_bundles() {
+                let bundle_id = bundle.bundle_id();
+                bundles.push(bundle_id);
+                for (opid, transition) in &bundle.known_transitions {
+                    operations.push(GraphOperation {
+                        opid: *opid,
+                        op_type: transition.full_type(),
+                        bundle_id: Some(bundle_id),
+                        witness_id: Some(witness_id),
+                    });
+                    ops.push((*opid, transition.assignments().flat()));
+                    for input in &transition.inputs() {
+                        allocations
+                            .entry(input.prev_out)
+                            .or_insert_with(|| GraphAllocation::unknown(input.prev_out))
+                            .spent_by = Some(*opid);
+                    }
+                }
+            }
+            let status = state.witness_ord(witness_id);
+            let height = match status {
+                Some(WitnessOrd::Mined(pos)) => Some(pos.height().get()),
+                _ => None,
+            };
+            witnesses.push(GraphWitness {
+                witness_id,
+                bundles,
+                status,
+                height,
+            });
+        }
+
+        // Allocations which seals or state are not known to the stock
+        for (opid, assignments) in ops {
+            for (ty, typed) in assignments.iter() {
+                for no in 0..typed.len_u16() {
+                    let opout = Opout::new(opid, *ty, no);
+                    allocations
+                        .entry(opout)
+                        .or_insert_with(|| GraphAllocation::unknown(opout));
+                }
+            }
+        }
+
+        Ok(ContractGraph {
+            contract_id,
+            operations,
+            witnesses,
+            allocations: allocations.into_values().collect(),
+        })
+    }
+
+    /// Returns operation with the given id, if it is part of the graph.
+    pub fn operation(&self, opid: OpId) -> Option<&GraphOperation> {
+        self.operations.iter().find(|op| op.opid == opid)
+    }
+
+    /// Returns allocations assigned by the operation.
+    pub fn allocations_by(&self, opid: OpId) -> impl Iterator<Item = &GraphAllocation> {
+        self.allocations
+            .iter()
+            .filter(move |allocation| allocation.opout.op == opid)
+    }
+
+    /// Renders the graph in the Graphviz DOT language. Operations anchored to
+    /// the same witness transaction are grouped into a cluster labelled with
+    /// the transaction id and its status.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        let _ = self.write_dot(&mut dot);
+        dot
+    }
+
+    fn write_dot(&self, f: &mut impl Write) -> std::fmt::Result {
+        writeln!(f, "digraph \"{}\" {{", self.contract_id)?;
+        writeln!(f, "  rankdir=LR;")?;
+        writeln!(f, "  node [shape=box];")?;
+        let op_node = |f: &mut dyn Write, op: &GraphOperation| {
+            writeln!(f, "    \"{}\" [label=\"{}\\n{}\"];", op.opid, op.op_type, op.opid)
+        };
+        for op in self.operations.iter().filter(|op| op.witness_id.is_none()) {
+            op_node(f, op)?;
+        }
+        for witness in &self.witnesses {
+            writeln!(f, "  subgraph \"cluster_{}\" {{", witness.witness_id)?;
+            let status = match (witness.height, witness.status) {
+                (Some(height), _) => format!("mined at {height}"),
+                (None, Some(ord)) => ord.to_string(),
+                (None, None) => s!("unknown"),
+            };
+            writeln!(f, "    label=\"{}\\n{status}\";", witness.witness_id)?;
+            for op in self
+                .operations
+                .iter()
+                .filter(|op| op.witness_id == Some(witness.witness_id))
+            {
+                op_node(f, op)?;
+            }
+            writeln!(f, "  }}")?;
+        }
+        for allocation in &self.allocations {
+            let opout = allocation.opout;
+            let mut label = format!("{}/{}", opout.ty, opout.no);
+            if let Some(amount) = allocation.amount {
+                write!(label, "\\n{amount}")?;
+            }
+            if let Some(seal) = allocation.seal {
+                write!(label, "\\n{seal}")?;
+            }
+            writeln!(f, "  \"{opout}\" [shape=ellipse, label=\"{label}\"];")?;
+            if self.operation(opout.op).is_some() {
+                writeln!(f, "  \"{}\" -> \"{opout}\";", opout.op)?;
+            }
+            if let Some(opid) = allocation.spent_by {
+                writeln!(f, "  \"{opout}\" -> \"{opid}\";")?;
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl GraphAllocation {
+    fn unknown(opout: Opout) -> Self {
+        GraphAllocation {
+            opout,
+            seal: None,
+            amount: None,
+            spent_by: None,
+        }
+    }
+}
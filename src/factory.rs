@@ -0,0 +1,315 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Creation and opening of the wallets kept in the data directory, using the
+//! same layout as the command-line tool, such that the wallets can be used by
+//! both of them.
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use bpstd::{Network, Wpkh, XpubDerivable};
+use bpwallet::fs::FsTextStore;
+use bpwallet::{Indexer, Wallet};
+use nonasync::persistence::PersistenceError;
+use rgbstd::persistence::Stock;
+
+use crate::resolvers::AnyResolver;
+use crate::wallets::check_name;
+#[cfg(feature = "encryption")]
+use crate::{is_encrypted, EncryptedStore, Passphrase};
+use crate::{
+    BackupStore, BackupStoreError, FreezeError, FrozenOutpoints, ImportError, ImportedUtxos,
+    KeychainLayout, NetworkGuard, RgbDescr, RgbWallet, StockLock, SyncProgress, TapretKey,
+    VelocityError, VelocityPrefs, WalletDir, WalletDirError, WalletError, DEFAULT_STOCK_BACKUPS,
+};
+
+/// Name of the file inside the network data directory keeping frozen
+/// outpoints.
+pub const FROZEN_FILE: &str = "frozen.yaml";
+
+/// Name of the file inside the network data directory keeping outputs imported
+/// into the wallet.
+pub const IMPORTED_FILE: &str = "imported.yaml";
+
+/// Name of the file inside the network data directory keeping velocity
+/// preferences of the wallets.
+pub const VELOCITY_FILE: &str = "velocity.yaml";
+
+/// Descriptor of a newly created wallet.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum DescriptorSpec {
+    /// `tapret(KEY)` descriptor.
+    TapretKeyOnly(XpubDerivable),
+    /// `wpkh(KEY)` descriptor.
+    Wpkh(XpubDerivable),
+}
+
+impl DescriptorSpec {
+    /// Constructs the wallet descriptor using the keychain layout.
+    pub fn descriptor(&self, layout: KeychainLayout) -> RgbDescr {
+        let descr = match self {
+            DescriptorSpec::TapretKeyOnly(key) => RgbDescr::from(TapretKey::from(key.clone())),
+            DescriptorSpec::Wpkh(key) => RgbDescr::from(Wpkh::from(key.clone())),
+        };
+        descr.with_keychain_layout(layout)
+    }
+}
+
+/// Creates and opens wallets kept in the data directory.
+///
+/// The data directory of the network holds the stock shared by all wallets,
+/// the frozen and imported outputs, the velocity preferences and a
+/// sub-directory for each of the wallets.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct WalletFactory {
+    base_dir: PathBuf,
+    network: Network,
+    stock_backups: u8,
+    wait: bool,
+    #[cfg(feature = "encryption")]
+    passphrase: Option<Passphrase>,
+}
+
+impl WalletFactory {
+    /// Constructs factory keeping the data of the `network` in the directory
+    /// named after the network inside the `data_dir`.
+    pub fn new(data_dir: impl Into<PathBuf>, network: Network) -> Self {
+        let base_dir = data_dir.into().join(network.to_string());
+        Self::in_dir(base_dir, network)
+    }
+
+    /// Constructs factory keeping the data of the `network` directly in the
+    /// `base_dir`, without nesting it into the network directory.
+    pub fn in_dir(base_dir: impl Into<PathBuf>, network: Network) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+            network,
+            stock_backups: DEFAULT_STOCK_BACKUPS,
+            wait: true,
+            #[cfg(feature = "encryption")]
+            passphrase: None,
+        }
+    }
+
+    /// Sets number of previous copies of the stock files kept on each save.
+    pub fn with_stock_backups(mut self, backups: u8) -> Self {
+        self.stock_backups = backups;
+        self
+    }
+
+    /// Sets whether opening a wallet waits for other processes to release the
+    /// stock lock (default) or fails with [`WalletError::StockLocked`].
+    pub fn with_wait(mut self, wait: bool) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    /// Sets passphrase encrypting the data of the created wallets and
+    /// unlocking the encrypted wallets on opening.
+    #[cfg(feature = "encryption")]
+    pub fn with_passphrase(mut self, passphrase: impl Into<Passphrase>) -> Self {
+        self.passphrase = Some(passphrase.into());
+        self
+    }
+
+    pub fn network(&self) -> Network { self.network }
+
+    /// Returns the data directory of the network.
+    pub fn base_dir(&self) -> &Path { &self.base_dir }
+
+    /// Returns the directory of the network data holding the wallets.
+    pub fn wallets(&self) -> WalletDir { WalletDir::new(&self.base_dir) }
+
+    /// Creates a new wallet in the network data directory and opens it
+    /// together with the stock, creating an empty stock if there is none.
+    ///
+    /// If the factory has a passphrase, the wallet data are encrypted with it.
+    #[allow(clippy::result_large_err)]
+    pub fn create(
+        &self,
+        name: &str,
+        spec: &DescriptorSpec,
+        layout: KeychainLayout,
+    ) -> Result<RgbWallet<Wallet<XpubDerivable, RgbDescr>>, WalletError> {
+        check_name(name)?;
+        let wallets = self.wallets();
+        if wallets.path(name).exists() {
+            return Err(WalletDirError::AlreadyExists(name.to_owned()).into());
+        }
+        let lock = StockLock::acquire(&self.base_dir, self.wait)?;
+
+        let mut wallet = Wallet::new_layer1(spec.descriptor(layout), self.network);
+        self.persist(wallets.path(name), &mut wallet)?;
+
+        self.wrap(name, lock, wallet)
+    }
+
+    /// Creates a new wallet like [`Self::create`] and synchronizes its UTXOs
+    /// and the witness transactions of the stock, reporting the progress to
+    /// the callback.
+    #[allow(clippy::result_large_err)]
+    pub fn create_synced<I: Indexer>(
+        &self,
+        name: &str,
+        spec: &DescriptorSpec,
+        layout: KeychainLayout,
+        indexer: &I,
+        resolver: &AnyResolver,
+        callback: impl FnMut(&SyncProgress),
+    ) -> Result<RgbWallet<Wallet<XpubDerivable, RgbDescr>>, WalletError>
+    where
+        I::Error: std::fmt::Display,
+    {
+        let mut wallet = self.create(name, spec, layout)?;
+        wallet.sync(indexer, resolver, 1, callback)?;
+        Ok(wallet)
+    }
+
+    /// Opens the existing wallet together with the stock, checking that the
+    /// wallet uses the network of the factory.
+    ///
+    /// Encrypted wallets are unlocked with the passphrase of the factory; if
+    /// there is none, fails with [`WalletError::WalletLocked`].
+    #[allow(clippy::result_large_err)]
+    pub fn open(
+        &self,
+        name: &str,
+    ) -> Result<RgbWallet<Wallet<XpubDerivable, RgbDescr>>, WalletError> {
+        let wallet = self.open_wallet(name)?;
+        NetworkGuard::new(self.network)
+            .check_descriptor(wallet.network())
+            .finish()?;
+        let lock = StockLock::acquire(&self.base_dir, self.wait)?;
+
+        self.wrap(name, lock, wallet)
+    }
+
+    /// Opens the existing bitcoin wallet without the stock, unlocking it with
+    /// the passphrase of the factory if the wallet is encrypted.
+    #[allow(clippy::result_large_err)]
+    pub fn open_wallet(&self, name: &str) -> Result<Wallet<XpubDerivable, RgbDescr>, WalletError> {
+        check_name(name)?;
+        let wallets = self.wallets();
+        if !wallets.exists(name) {
+            return Err(WalletDirError::NotFound(name.to_owned()).into());
+        }
+        self.load_wallet(name, wallets.path(name))
+    }
+
+    /// Loads the stock from the network data directory, creating an empty one
+    /// if the stock files are absent.
+    ///
+    /// Unlike [`Self::create`] and [`Self::open`], doesn't lock the stock.
+    #[allow(clippy::result_large_err)]
+    pub fn stock(&self) -> Result<Stock, WalletError> {
+        let provider = BackupStore::new(self.base_dir.clone(), self.stock_backups)
+            .map_err(|e| WalletError::StockPersist(PersistenceError::with(e)))?;
+        Stock::load(provider.clone(), true).or_else(|err| {
+            let absent = err.0.downcast_ref::<BackupStoreError>().is_some_and(
+                |e| matches!(e, BackupStoreError::Io(e) if e.kind() == ErrorKind::NotFound),
+            );
+            if !absent {
+                return Err(WalletError::StockPersist(err));
+            }
+            let mut stock = Stock::in_memory();
+            stock
+                .make_persistent(provider, true)
+                .map_err(WalletError::StockPersist)?;
+            Ok(stock)
+        })
+    }
+
+    /// Loads outpoints frozen in the network data directory.
+    pub fn frozen(&self) -> Result<FrozenOutpoints, FreezeError> {
+        FrozenOutpoints::load_file(self.base_dir.join(FROZEN_FILE))
+    }
+
+    /// Loads outputs imported into the wallets of the network data directory.
+    pub fn imported(&self) -> Result<ImportedUtxos, ImportError> {
+        ImportedUtxos::load_file(self.base_dir.join(IMPORTED_FILE))
+    }
+
+    /// Loads velocity preferences kept in the network data directory.
+    pub fn velocity_prefs(&self) -> Result<VelocityPrefs, VelocityError> {
+        VelocityPrefs::load_file(self.base_dir.join(VELOCITY_FILE))
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn persist(
+        &self,
+        path: PathBuf,
+        wallet: &mut Wallet<XpubDerivable, RgbDescr>,
+    ) -> Result<(), WalletError> {
+        #[cfg(feature = "encryption")]
+        if let Some(passphrase) = &self.passphrase {
+            let store = EncryptedStore::new(path, passphrase.clone())?;
+            wallet
+                .make_persistent(store, true)
+                .map_err(WalletError::WalletPersist)?;
+            return wallet.store().map_err(WalletError::WalletPersist);
+        }
+        let provider = FsTextStore::new(path)
+            .map_err(|e| WalletError::WalletPersist(PersistenceError::with(e)))?;
+        wallet
+            .make_persistent(provider, true)
+            .map_err(WalletError::WalletPersist)?;
+        wallet.store().map_err(WalletError::WalletPersist)
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn load_wallet(
+        &self,
+        name: &str,
+        path: PathBuf,
+    ) -> Result<Wallet<XpubDerivable, RgbDescr>, WalletError> {
+        #[cfg(feature = "encryption")]
+        if is_encrypted(&path) {
+            let Some(passphrase) = self.passphrase.clone() else {
+                return Err(WalletError::WalletLocked(name.to_owned()));
+            };
+            let store = EncryptedStore::unlock(path, passphrase)?;
+            return Wallet::load(store, true).map_err(WalletError::WalletPersist);
+        }
+        #[cfg(not(feature = "encryption"))]
+        let _ = name;
+        let provider = FsTextStore::new(path)
+            .map_err(|e| WalletError::WalletPersist(PersistenceError::with(e)))?;
+        Wallet::load(provider, true).map_err(WalletError::WalletPersist)
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn wrap(
+        &self,
+        name: &str,
+        lock: StockLock,
+        wallet: Wallet<XpubDerivable, RgbDescr>,
+    ) -> Result<RgbWallet<Wallet<XpubDerivable, RgbDescr>>, WalletError> {
+        let stock = self.stock()?;
+        let mut wallet = RgbWallet::new(stock, wallet);
+        wallet.set_frozen(self.frozen()?);
+        wallet.set_imported(self.imported()?);
+        wallet.set_velocity_prefs(self.velocity_prefs()?);
+        wallet.set_wallet_name(name);
+        wallet.set_stock_lock(lock);
+        Ok(wallet)
+    }
+}
@@ -0,0 +1,517 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Foreign function interface layer for mobile and other non-Rust wallets.
+//!
+//! All the types exposed by this module are plain data (strings, integers,
+//! vectors and records of them) and can be directly mapped by UniFFI or other
+//! bindings generators. Objects are provided as thread-safe handles using
+//! interior mutability, such that they can be shared as `Arc<T>` by the
+//! generated bindings.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use amplify::confinement::U16 as MAX16;
+use bpstd::{Network, Sats, Wpkh, XpubDerivable};
+use bpwallet::fs::FsTextStore;
+use bpwallet::indexers::esplora;
+use bpwallet::Wallet;
+use nonasync::persistence::PersistenceError;
+use rgbstd::containers::{BuilderSeal, ConsignmentExt, FileContent};
+use rgbstd::interface::IfaceRef;
+use rgbstd::invoice::{Beneficiary, Pay2Vout, RgbInvoice, RgbInvoiceBuilder, XChainNet};
+use rgbstd::persistence::fs::FsBinStore;
+use rgbstd::persistence::Stock;
+use rgbstd::schema::SchemaId;
+use rgbstd::validation::Validity;
+use rgbstd::{ContractId, GenesisSeal, GraphSeal, Identity, OutputSeal, XChain};
+use strict_types::encoding::{FieldName, TypeName};
+use strict_types::StrictVal;
+
+use crate::resolvers::{AnyResolver, ContractIssueResolver};
+use crate::{
+    reveal_known_seals, AcceptError, DescriptorRgb, ErrorCode, PayError, RgbDescr, RgbWallet,
+    SyncError, TapretKey, TransferParams, TransferReader, WalletError,
+};
+
+type FfiRgbWallet = RgbWallet<Wallet<XpubDerivable, RgbDescr>>;
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum FfiError {
+    /// invalid argument - {details}
+    InvalidArgument { details: String },
+
+    /// wallet error - {details}
+    Wallet { code: u16, details: String },
+
+    /// resolver error - {details}
+    Resolver { code: u16, details: String },
+
+    /// unable to create invoice - {details}
+    Invoice { code: u16, details: String },
+
+    /// unable to pay - {details}
+    Payment { code: u16, details: String },
+
+    /// consignment is invalid - {details}
+    Validation { code: u16, details: String },
+
+    /// wallet object is poisoned due to a panic in another thread.
+    Poisoned,
+}
+
+impl FfiError {
+    /// Stable numeric code of the error kind, as defined by [`ErrorCode`].
+    ///
+    /// Errors originating from the FFI layer itself have code `0`.
+    pub fn code(&self) -> u16 {
+        match self {
+            FfiError::InvalidArgument { .. } | FfiError::Poisoned => 0,
+            FfiError::Wallet { code, .. }
+            | FfiError::Resolver { code, .. }
+            | FfiError::Invoice { code, .. }
+            | FfiError::Payment { code, .. }
+            | FfiError::Validation { code, .. } => *code,
+        }
+    }
+}
+
+impl From<WalletError> for FfiError {
+    fn from(err: WalletError) -> Self {
+        let code = err.error_code();
+        let details = err.to_string();
+        match err {
+            WalletError::Resolver(_) => FfiError::Resolver { code, details },
+            WalletError::Invoicing(_) | WalletError::NoOutpoint => {
+                FfiError::Invoice { code, details }
+            }
+            WalletError::InvalidConsignment(_) | WalletError::Accept(_) => {
+                FfiError::Validation { code, details }
+            }
+            WalletError::Composition(_) | WalletError::Completion(_) | WalletError::Pay(_) => {
+                FfiError::Payment { code, details }
+            }
+            _ => FfiError::Wallet { code, details },
+        }
+    }
+}
+
+impl From<PayError> for FfiError {
+    fn from(err: PayError) -> Self { WalletError::from(err).into() }
+}
+
+impl From<AcceptError> for FfiError {
+    fn from(err: AcceptError) -> Self { WalletError::from(err).into() }
+}
+
+impl From<SyncError> for FfiError {
+    fn from(err: SyncError) -> Self { WalletError::from(err).into() }
+}
+
+impl From<PersistenceError> for FfiError {
+    fn from(err: PersistenceError) -> Self { WalletError::WalletPersist(err).into() }
+}
+
+impl From<std::io::Error> for FfiError {
+    fn from(err: std::io::Error) -> Self { WalletError::from(err).into() }
+}
+
+fn invalid(details: impl ToString) -> FfiError {
+    FfiError::InvalidArgument {
+        details: details.to_string(),
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum FfiNetwork {
+    Mainnet,
+    Testnet3,
+    Testnet4,
+    Signet,
+    Regtest,
+}
+
+impl From<FfiNetwork> for Network {
+    fn from(network: FfiNetwork) -> Self {
+        match network {
+            FfiNetwork::Mainnet => Network::Mainnet,
+            FfiNetwork::Testnet3 => Network::Testnet3,
+            FfiNetwork::Testnet4 => Network::Testnet4,
+            FfiNetwork::Signet => Network::Signet,
+            FfiNetwork::Regtest => Network::Regtest,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum FfiDescriptorType {
+    /// `tapret(KEY)` descriptor.
+    TapretKeyOnly,
+    /// `wpkh(KEY)` descriptor.
+    Wpkh,
+}
+
+/// Global state value for the contract issue.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct FfiGlobalState {
+    /// Global state name, as defined by the contract interface.
+    pub name: String,
+    /// State value in YAML format matching the state strict type.
+    pub value: String,
+}
+
+/// Fungible state allocation for the contract issue.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct FfiAllocation {
+    /// Assignment name, as defined by the contract interface.
+    pub name: String,
+    /// Seal definition in form of `method:txid:vout`.
+    pub seal: String,
+    pub amount: u64,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct FfiPayment {
+    /// Base64-encoded PSBT which has to be signed and published.
+    pub psbt: String,
+    /// Path to the saved transfer consignment.
+    pub consignment: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct FfiValidation {
+    pub valid: bool,
+    /// Human-readable validation report.
+    pub report: String,
+}
+
+/// Thread-safe RGB wallet handle for use from foreign languages.
+pub struct FfiWallet {
+    inner: Mutex<FfiRgbWallet>,
+    network: Network,
+}
+
+impl FfiWallet {
+    /// Creates a new wallet with an empty stock, persisting its data in the
+    /// provided directories.
+    pub fn create(
+        stock_dir: String,
+        wallet_dir: String,
+        network: FfiNetwork,
+        descriptor_type: FfiDescriptorType,
+        xpub: String,
+    ) -> Result<Arc<Self>, FfiError> {
+        let network = Network::from(network);
+        let key = XpubDerivable::from_str(&xpub).map_err(invalid)?;
+        let descr = match descriptor_type {
+            FfiDescriptorType::TapretKeyOnly => RgbDescr::from(TapretKey::from(key)),
+            FfiDescriptorType::Wpkh => RgbDescr::from(Wpkh::from(key)),
+        };
+
+        let provider = FsBinStore::new(PathBuf::from(stock_dir))?;
+        let mut stock = Stock::in_memory();
+        stock.make_persistent(provider, true)?;
+
+        let provider = FsTextStore::new(PathBuf::from(wallet_dir))?;
+        let mut wallet = Wallet::new_layer1(descr, network);
+        wallet.make_persistent(provider, true)?;
+
+        Ok(Arc::new(FfiWallet {
+            inner: Mutex::new(RgbWallet::new(stock, wallet)),
+            network,
+        }))
+    }
+
+    /// Loads previously created wallet.
+    pub fn load(stock_dir: String, wallet_dir: String) -> Result<Arc<Self>, FfiError> {
+        let wallet = FfiRgbWallet::load(PathBuf::from(stock_dir), PathBuf::from(wallet_dir), true)?;
+        let network = wallet.wallet().network();
+        Ok(Arc::new(FfiWallet {
+            inner: Mutex::new(wallet),
+            network,
+        }))
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, FfiRgbWallet>, FfiError> {
+        self.inner.lock().map_err(|_| FfiError::Poisoned)
+    }
+
+    fn resolver(&self, esplora_url: &str) -> Result<AnyResolver, FfiError> {
+        let resolver =
+            AnyResolver::esplora_blocking(esplora_url, None).map_err(WalletError::Resolver)?;
+        resolver
+            .check(self.network)
+            .map_err(WalletError::Resolver)?;
+        Ok(resolver)
+    }
+
+    /// Synchronizes wallet UTXO set and RGB witness information with an
+    /// Esplora indexer.
+    ///
+    /// Returns list of non-fatal errors happened during the synchronization.
+    pub fn sync(&self, esplora_url: String) -> Result<Vec<String>, FfiError> {
+        let indexer = esplora::Client::new_esplora(&esplora_url)
+            .map_err(|err| SyncError::Indexer(err.to_string()))?;
+        let resolver = self.resolver(&esplora_url)?;
+        let mut wallet = self.lock()?;
+
+        let mut failures = vec![];
+        if let Some(errors) = wallet.wallet_mut().update(&indexer).err {
+            failures.extend(errors.into_iter().map(|err| err.to_string()));
+        }
+        let res = wallet.update_witnesses(&resolver, 1)?;
+        failures.extend(
+            res.failed
+                .into_iter()
+                .map(|(witness_id, failure)| format!("{witness_id}: {failure}")),
+        );
+        Ok(failures)
+    }
+
+    /// Imports kit, contract or other RGB data from a file.
+    pub fn import(&self, file: String, esplora_url: String) -> Result<(), FfiError> {
+        use rgbstd::containers::UniversalFile;
+
+        let mut wallet = self.lock()?;
+        match UniversalFile::load_file(file).map_err(WalletError::from)? {
+            UniversalFile::Kit(kit) => {
+                let kit = kit
+                    .validate()
+                    .map_err(|(status, _)| AcceptError::from(status))?;
+                wallet
+                    .stock_mut()
+                    .import_kit(kit)
+                    .map_err(WalletError::from)?;
+            }
+            UniversalFile::Contract(contract) => {
+                let resolver = self.resolver(&esplora_url)?;
+                let contract = contract
+                    .validate(&resolver, self.network.is_testnet())
+                    .map_err(|(status, _)| AcceptError::from(status))?;
+                wallet
+                    .stock_mut()
+                    .import_contract(contract, &resolver)
+                    .map_err(WalletError::from)?;
+            }
+            UniversalFile::Transfer(_) => {
+                return Err(invalid("transfer consignments must be processed with `accept`"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Issues a new contract and adds it to the stock.
+    ///
+    /// Returns contract id of the issued contract.
+    pub fn issue(
+        &self,
+        schema_id: String,
+        iface: String,
+        issuer: String,
+        globals: Vec<FfiGlobalState>,
+        allocations: Vec<FfiAllocation>,
+    ) -> Result<String, FfiError> {
+        let schema_id = SchemaId::from_str(&schema_id).map_err(invalid)?;
+        let issuer = Identity::from_str(&issuer).map_err(invalid)?;
+        let iface_name = TypeName::try_from(iface).map_err(invalid)?;
+
+        let mut wallet = self.lock()?;
+        let stock = wallet.stock_mut();
+        let iface_id = stock
+            .iface(IfaceRef::from(iface_name))
+            .map_err(WalletError::from)?
+            .iface_id();
+        let mut builder = stock
+            .contract_builder(issuer, schema_id, iface_id)
+            .map_err(WalletError::from)?;
+        let types = builder.type_system().clone();
+
+        for FfiGlobalState { name, value } in globals {
+            let field_name = FieldName::try_from(name).map_err(invalid)?;
+            let sem_id = builder
+                .global_type(&field_name)
+                .and_then(|ty| {
+                    stock
+                        .schema(schema_id)
+                        .ok()?
+                        .schema
+                        .global_types
+                        .get(&ty)
+                        .map(|details| details.sem_id)
+                })
+                .ok_or_else(|| invalid(format!("unknown global state '{field_name}'")))?;
+            let value = serde_yaml::from_str::<serde_yaml::Value>(&value).map_err(invalid)?;
+            let typed_val = types
+                .typify(StrictVal::from(value), sem_id)
+                .map_err(|err| invalid(format!("invalid value for '{field_name}' - {err}")))?;
+            #[allow(deprecated)]
+            let serialized = types
+                .strict_serialize_type::<MAX16>(&typed_val)
+                .map_err(|err| invalid(format!("invalid value for '{field_name}' - {err}")))?;
+            builder = builder
+                .add_global_state(field_name, serialized)
+                .map_err(WalletError::from)?;
+        }
+
+        for FfiAllocation { name, seal, amount } in allocations {
+            let field_name = FieldName::try_from(name).map_err(invalid)?;
+            let seal = OutputSeal::from_str(&seal).map_err(invalid)?;
+            let seal = GenesisSeal::new_random(seal.method, seal.txid, seal.vout);
+            builder = builder
+                .add_fungible_state(
+                    field_name,
+                    BuilderSeal::Revealed(XChain::Bitcoin(seal)),
+                    amount,
+                )
+                .map_err(WalletError::from)?;
+        }
+
+        let contract = builder.issue_contract().map_err(WalletError::from)?;
+        let contract_id = contract.contract_id();
+        stock
+            .import_contract(contract, &ContractIssueResolver)
+            .map_err(WalletError::from)?;
+        Ok(contract_id.to_string())
+    }
+
+    /// Creates a new invoice for receiving a fungible amount of the contract
+    /// state.
+    pub fn invoice(
+        &self,
+        contract_id: String,
+        iface: String,
+        amount: u64,
+        address_based: bool,
+    ) -> Result<String, FfiError> {
+        let contract_id = ContractId::from_str(&contract_id).map_err(invalid)?;
+        let iface_name = TypeName::try_from(iface).map_err(invalid)?;
+
+        let mut wallet = self.lock()?;
+        let method = wallet.wallet().seal_close_method();
+        let layout = wallet.wallet().keychain_layout();
+        let beneficiary = if address_based {
+            let (_, addr) = wallet.next_rgb_address(true);
+            Beneficiary::WitnessVout(Pay2Vout {
+                address: addr.payload,
+                method,
+            })
+        } else {
+            let outpoint = wallet
+                .wallet()
+                .coinselect(Sats::ZERO, |utxo| layout.contains_rgb(utxo.terminal.keychain))
+                .next()
+                .ok_or(WalletError::NoOutpoint)?;
+            let seal = XChain::Bitcoin(GraphSeal::new_random(method, outpoint.txid, outpoint.vout));
+            wallet
+                .stock_mut()
+                .store_secret_seal(seal)
+                .map_err(WalletError::from)?;
+            Beneficiary::BlindedSeal(*seal.to_secret_seal().as_reduced_unsafe())
+        };
+
+        let invoice = RgbInvoiceBuilder::new(XChainNet::bitcoin(self.network, beneficiary))
+            .set_contract(contract_id)
+            .set_interface(iface_name)
+            .set_amount_raw(amount)
+            .finish();
+        Ok(invoice.to_string())
+    }
+
+    /// Pays the invoice, saving transfer consignment to the provided path.
+    pub fn pay(
+        &self,
+        invoice: String,
+        fee: u64,
+        sats: u64,
+        consignment: String,
+    ) -> Result<FfiPayment, FfiError> {
+        let invoice = RgbInvoice::from_str(&invoice).map_err(invalid)?;
+        let params = TransferParams::with(Sats::from(fee), Sats::from(sats));
+
+        let mut wallet = self.lock()?;
+        let (psbt, _, transfer) = wallet.pay(&invoice, params)?;
+        transfer
+            .save_file(&consignment)
+            .map_err(WalletError::from)?;
+        Ok(FfiPayment {
+            psbt: psbt.to_string(),
+            consignment,
+        })
+    }
+
+    /// Validates transfer consignment without accepting it.
+    pub fn validate(
+        &self,
+        consignment: String,
+        esplora_url: String,
+    ) -> Result<FfiValidation, FfiError> {
+        let mut resolver = self.resolver(&esplora_url)?;
+        let transfer = TransferReader::open(consignment)
+            .and_then(TransferReader::read_all)
+            .map_err(WalletError::from)?;
+        resolver.add_terminals(&transfer);
+        let status = match transfer.validate(&resolver, self.network.is_testnet()) {
+            Ok(valid) => valid.into_validation_status(),
+            Err((status, _)) => status,
+        };
+        Ok(FfiValidation {
+            valid: status.validity() == Validity::Valid,
+            report: status.to_string(),
+        })
+    }
+
+    /// Validates transfer consignment and, if it is valid, accepts it into
+    /// the stock.
+    pub fn accept(
+        &self,
+        consignment: String,
+        esplora_url: String,
+    ) -> Result<FfiValidation, FfiError> {
+        let mut resolver = self.resolver(&esplora_url)?;
+        let transfer = TransferReader::open(consignment)
+            .and_then(TransferReader::read_all)
+            .map_err(WalletError::from)?;
+        resolver.add_terminals(&transfer);
+        let mut wallet = self.lock()?;
+        let transfer = reveal_known_seals(wallet.stock(), transfer)?;
+        let valid = match transfer.validate(&resolver, self.network.is_testnet()) {
+            Ok(valid) => valid,
+            Err((status, _)) => {
+                return Ok(FfiValidation {
+                    valid: false,
+                    report: status.to_string(),
+                });
+            }
+        };
+        let report = valid.validation_status().to_string();
+        wallet
+            .stock_mut()
+            .accept_transfer(valid, &resolver)
+            .map_err(|err| AcceptError::Stock(err.to_string()))?;
+        Ok(FfiValidation {
+            valid: true,
+            report,
+        })
+    }
+}
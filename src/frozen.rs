@@ -0,0 +1,135 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Outpoints frozen by the user, which are excluded from payments.
+//!
+//! Outpoints may be frozen to keep them as a swap collateral, to put a
+//! compliance hold on the state they carry or for any other reason. Freezing
+//! an outpoint freezes all the RGB allocations it holds: neither bitcoin coin
+//! selection nor RGB state selection spends it until it is unfrozen. Like
+//! labels, frozen outpoints are private to the wallet.
+
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::str::FromStr;
+
+use bpstd::Outpoint;
+
+use crate::FreezeError;
+
+/// Frozen outpoints of the wallet, together with the reasons they were
+/// frozen for.
+///
+/// Serialized as a map from the outpoint to the reason text, which may be
+/// empty.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(
+        crate = "serde_crate",
+        try_from = "BTreeMap<String, String>",
+        into = "BTreeMap<String, String>"
+    )
+)]
+pub struct FrozenOutpoints(BTreeMap<Outpoint, String>);
+
+impl TryFrom<BTreeMap<String, String>> for FrozenOutpoints {
+    type Error = FreezeError;
+
+    fn try_from(map: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        map.into_iter()
+            .map(|(outpoint, reason)| {
+                Outpoint::from_str(&outpoint)
+                    .map(|outpoint| (outpoint, reason))
+                    .map_err(|_| FreezeError::InvalidOutpoint(outpoint))
+            })
+            .collect::<Result<_, _>>()
+            .map(FrozenOutpoints)
+    }
+}
+
+impl From<FrozenOutpoints> for BTreeMap<String, String> {
+    fn from(frozen: FrozenOutpoints) -> Self {
+        frozen
+            .0
+            .into_iter()
+            .map(|(outpoint, reason)| (outpoint.to_string(), reason))
+            .collect()
+    }
+}
+
+impl FrozenOutpoints {
+    pub fn new() -> Self { Self::default() }
+
+    /// Loads frozen outpoints from a YAML file, returning no outpoints if the
+    /// file doesn't exist.
+    #[cfg(feature = "fs")]
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self, FreezeError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = fs::File::open(path)?;
+        Ok(serde_yaml::from_reader(file)?)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn save_file(&self, path: impl AsRef<Path>) -> Result<(), FreezeError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = fs::File::create(path)?;
+        serde_yaml::to_writer(file, self)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Freezes the outpoint, returning the reason it was frozen for before,
+    /// if it was already frozen.
+    pub fn freeze(&mut self, outpoint: Outpoint, reason: impl Into<String>) -> Option<String> {
+        self.0.insert(outpoint, reason.into())
+    }
+
+    /// Unfreezes the outpoint, returning the reason it was frozen for.
+    pub fn unfreeze(&mut self, outpoint: Outpoint) -> Option<String> { self.0.remove(&outpoint) }
+
+    pub fn is_frozen(&self, outpoint: Outpoint) -> bool { self.0.contains_key(&outpoint) }
+
+    pub fn reason(&self, outpoint: Outpoint) -> Option<&str> {
+        self.0.get(&outpoint).map(String::as_str)
+    }
+
+    pub fn outpoints(&self) -> BTreeSet<Outpoint> { self.0.keys().copied().collect() }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Outpoint, &str)> {
+        self.0
+            .iter()
+            .map(|(outpoint, reason)| (*outpoint, reason.as_str()))
+    }
+}
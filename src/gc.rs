@@ -0,0 +1,91 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use rgbstd::SecretSeal;
+
+#[cfg(feature = "fs")]
+use crate::SealExpiryError;
+
+/// Expiry metadata of the secret seals stored for the wallet invoices.
+///
+/// Seals created for an invoice are kept in the stash until the payment is
+/// received; once the invoice expires without being paid, the seal is no
+/// longer needed and may be pruned with [`crate::RgbWallet::gc`].
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", transparent)
+)]
+pub struct SealExpiry(BTreeMap<SecretSeal, i64>);
+
+impl SealExpiry {
+    pub fn new() -> Self { Self::default() }
+
+    /// Loads expiry metadata from a YAML file, returning empty metadata if
+    /// the file doesn't exist.
+    #[cfg(feature = "fs")]
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self, SealExpiryError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = fs::File::open(path)?;
+        Ok(serde_yaml::from_reader(file)?)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn save_file(&self, path: impl AsRef<Path>) -> Result<(), SealExpiryError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = fs::File::create(path)?;
+        serde_yaml::to_writer(file, self)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Registers the expiry time (as a UNIX timestamp) of the invoice using
+    /// the seal.
+    pub fn insert(&mut self, seal: SecretSeal, expiry: i64) -> Option<i64> {
+        self.0.insert(seal, expiry)
+    }
+
+    pub fn remove(&mut self, seal: &SecretSeal) -> Option<i64> { self.0.remove(seal) }
+
+    pub fn expiry(&self, seal: &SecretSeal) -> Option<i64> { self.0.get(seal).copied() }
+
+    /// Checks whether the invoice using the seal has expired at `now`. Seals
+    /// without the expiry metadata never expire.
+    pub fn is_expired(&self, seal: &SecretSeal, now: i64) -> bool {
+        self.expiry(seal).is_some_and(|expiry| expiry <= now)
+    }
+}
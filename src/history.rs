@@ -0,0 +1,311 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+
+use chrono::DateTime;
+use rgbstd::interface::{AllocatedState, ContractIface, ContractOp, OpDirection};
+use rgbstd::invoice::Amount;
+use rgbstd::persistence::ContractStateRead;
+use rgbstd::stl::AssetSpec;
+use rgbstd::vm::WitnessOrd;
+use rgbstd::{CoinAmount, Precision, XOutputSeal, XWitnessId};
+
+use crate::AmountFormatter;
+
+/// Name of the global state field keeping asset specification, from which the
+/// precision of the fungible state is taken.
+const SPEC_GLOBAL: &str = "spec";
+
+/// Header of the CSV history export.
+pub const HISTORY_CSV_HEADER: &str = "timestamp,txid,direction,seal,amount,balance";
+
+/// Single row of the contract operation history export.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct HistoryRow {
+    /// Timestamp of the block mining the witness transaction; `None` for
+    /// operations without a witness or with a witness which is not mined yet.
+    pub timestamp: Option<i64>,
+    /// Witness transaction id, if any.
+    pub txid: Option<XWitnessId>,
+    pub direction: OpDirection,
+    /// Seals receiving the state; for the sent operations these are seals of
+    /// the counterparty.
+    pub seals: Vec<XOutputSeal>,
+    /// Operation state; fungible amounts are adjusted for the contract
+    /// precision, unless the exporter is in the raw mode.
+    pub state: String,
+    /// Fungible balance after the operation, adjusted for the contract
+    /// precision.
+    pub balance: CoinAmount,
+}
+
+/// Exporter of the contract operation history into an accounting-friendly
+/// (CSV) format.
+///
+/// Operations are ordered by their witness position (the ones which are not
+/// mined yet go last), and the running balance of the fungible state is
+/// computed for each of them: issued and received amounts increase it, while
+/// sent amounts decrease.
+#[derive(Clone, Debug)]
+pub struct HistoryExporter {
+    ops: Vec<ContractOp>,
+    amounts: AmountFormatter,
+}
+
+impl HistoryExporter {
+    /// Constructs exporter from the contract operation history and the
+    /// precision of its fungible state.
+    pub fn new(ops: Vec<ContractOp>, precision: Precision) -> Self {
+        Self::with_formatter(ops, AmountFormatter::new(precision, None))
+    }
+
+    /// Constructs exporter from the contract operation history and the
+    /// formatter of its fungible amounts. Asset ticker is not included into
+    /// the exported amounts.
+    pub fn with_formatter(mut ops: Vec<ContractOp>, amounts: AmountFormatter) -> Self {
+        ops.sort_by_key(|op| op.witness.map(|w| w.ord).unwrap_or(WitnessOrd::Archived));
+        Self { ops, amounts }
+    }
+
+    /// Constructs exporter from the contract operation history, taking the
+    /// precision from the asset specification of the contract. If the contract
+    /// interface doesn't provide the specification, the state is treated as
+    /// indivisible.
+    pub fn with_contract<S: ContractStateRead>(
+        ops: Vec<ContractOp>,
+        contract: &ContractIface<S>,
+    ) -> Self {
+        let precision = contract
+            .global(SPEC_GLOBAL)
+            .ok()
+            .and_then(|mut spec| spec.next())
+            .map(|spec| AssetSpec::from_strict_val_unchecked(&spec).precision)
+            .unwrap_or(Precision::Indivisible);
+        Self::new(ops, precision)
+    }
+
+    /// Returns precision used for fungible amounts.
+    pub fn precision(&self) -> Precision { self.amounts.precision() }
+
+    /// Switches export of the fungible amounts in the smallest units instead
+    /// of adjusting them for the contract precision.
+    pub fn set_raw(&mut self, raw: bool) { self.amounts.set_raw(raw) }
+
+    /// Returns operations in the order they are exported.
+    pub fn operations(&self) -> &[ContractOp] { &self.ops }
+
+    /// Computes export rows, one per operation.
+    pub fn rows(&self) -> Vec<HistoryRow> {
+        let mut balance = 0u64;
+        self.ops
+            .iter()
+            .map(|op| {
+                let state = match op.state {
+                    AllocatedState::Amount(amount) => {
+                        let value = amount.value();
+                        balance = match op.direction {
+                            OpDirection::Issued | OpDirection::Received => {
+                                balance.saturating_add(value)
+                            }
+                            OpDirection::Sent => balance.saturating_sub(value),
+                        };
+                        self.amounts.format_amount(value)
+                    }
+                    ref state => state.to_string(),
+                };
+                let timestamp = op.witness.and_then(|info| match info.ord {
+                    WitnessOrd::Mined(pos) => Some(pos.timestamp()),
+                    WitnessOrd::Tentative | WitnessOrd::Archived => None,
+                });
+                HistoryRow {
+                    timestamp,
+                    txid: op.witness.map(|info| info.id),
+                    direction: op.direction,
+                    seals: op.to.iter().copied().collect(),
+                    state,
+                    balance: self.amounts.coin_amount(balance),
+                }
+            })
+            .collect()
+    }
+
+    /// Writes history in CSV format, including the header line. Timestamps
+    /// are written in RFC 3339 format (UTC).
+    pub fn write_csv(&self, mut writer: impl io::Write) -> io::Result<()> {
+        writeln!(writer, "{HISTORY_CSV_HEADER}")?;
+        for row in self.rows() {
+            let timestamp = row
+                .timestamp
+                .and_then(|ts| DateTime::from_timestamp(ts, 0))
+                .map(|dt| dt.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+                .unwrap_or_default();
+            let txid = row.txid.map(|id| id.to_string()).unwrap_or_default();
+            let seals = row
+                .seals
+                .iter()
+                .map(XOutputSeal::to_string)
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(
+                writer,
+                "{timestamp},{txid},{},{},{},{}",
+                row.direction,
+                csv_escape(&seals),
+                csv_escape(&row.state),
+                self.amounts.format_amount(coin_value(row.balance))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Role of a transfer in the contract history, from the wallet perspective.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(lowercase)]
+pub enum TransferRole {
+    /// State issued to the wallet by the genesis or a state extension.
+    Issuance,
+    /// State sent to a counterparty; the change returned to the wallet is part
+    /// of the same transfer.
+    Payment,
+    /// State received from a counterparty.
+    Receipt,
+    /// State moved between the outputs of the wallet only, which happens when
+    /// the spent outputs also hold state of another contract which is paid by
+    /// the witness transaction.
+    Passthrough,
+}
+
+/// Transfer in the contract history, combining all operations of the contract
+/// committed to by the same witness transaction into a single history item.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TransferRecord {
+    /// Witness transaction; `None` for the issued state.
+    pub witness: Option<XWitnessId>,
+    /// Status of the witness transaction known to the stock.
+    pub ord: Option<WitnessOrd>,
+    pub role: TransferRole,
+    /// Fungible amount leaving the wallet.
+    pub sent: Amount,
+    /// Fungible amount coming to the wallet, including the change and the
+    /// issued state.
+    pub received: Amount,
+    /// Seals of the counterparty receiving the sent state. Seals of the
+    /// counterparty sending the state to the wallet are not known to it.
+    pub counterparty: BTreeSet<XOutputSeal>,
+    /// Operations of the contract making the transfer.
+    pub operations: Vec<ContractOp>,
+}
+
+impl TransferRecord {
+    /// Groups operations of the contract history by their witness transaction
+    /// and classifies each of the groups. The issuance goes first and the rest
+    /// of the records are ordered by the witness position, with the ones which
+    /// are not mined, or which status is not known, going last.
+    pub fn group(ops: impl IntoIterator<Item = ContractOp>) -> Vec<TransferRecord> {
+        let mut groups = BTreeMap::<Option<XWitnessId>, Vec<ContractOp>>::new();
+        for op in ops {
+            groups
+                .entry(op.witness.map(|info| info.id))
+                .or_default()
+                .push(op);
+        }
+        let mut records = groups
+            .into_iter()
+            .map(|(witness, operations)| TransferRecord::with(witness, operations))
+            .collect::<Vec<_>>();
+        records.sort_by_key(|record| {
+            (record.witness.is_some(), record.ord.unwrap_or(WitnessOrd::Tentative))
+        });
+        records
+    }
+
+    fn with(witness: Option<XWitnessId>, operations: Vec<ContractOp>) -> Self {
+        let mut sent = 0u64;
+        let mut received = 0u64;
+        let mut has_sent = false;
+        let mut has_received = false;
+        let mut issued = false;
+        let mut counterparty = BTreeSet::new();
+        for op in &operations {
+            let value = match op.state {
+                AllocatedState::Amount(amount) => amount.value(),
+                _ => 0,
+            };
+            match op.direction {
+                OpDirection::Issued => {
+                    issued = true;
+                    received = received.saturating_add(value);
+                }
+                OpDirection::Received => {
+                    has_received = true;
+                    received = received.saturating_add(value);
+                }
+                OpDirection::Sent => {
+                    has_sent = true;
+                    sent = sent.saturating_add(value);
+                    counterparty.extend(op.to.iter().copied());
+                }
+            }
+        }
+        let role = match (issued, has_sent, has_received) {
+            (true, ..) => TransferRole::Issuance,
+            (false, true, true) if sent == received => TransferRole::Passthrough,
+            (false, true, _) => TransferRole::Payment,
+            (false, false, _) => TransferRole::Receipt,
+        };
+        TransferRecord {
+            witness,
+            ord: operations
+                .iter()
+                .find_map(|op| op.witness)
+                .map(|info| info.ord),
+            role,
+            sent: Amount::from(sent),
+            received: Amount::from(received),
+            counterparty,
+            operations,
+        }
+    }
+
+    /// Fungible amount by which the transfer changed the wallet balance, which
+    /// is negative for the payments.
+    pub fn net(&self) -> i128 { self.received.value() as i128 - self.sent.value() as i128 }
+}
+
+fn coin_value(amount: CoinAmount) -> u64 {
+    amount.int() * 10u64.pow(amount.precision().decimals() as u32) + amount.fract()
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
@@ -0,0 +1,228 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use amplify::confinement::NonEmptyBlob;
+use bpstd::secp256k1::{schnorr, Keypair, XOnlyPublicKey, SECP256K1};
+use commit_verify::{DigestExt, Sha256};
+use rgbstd::containers::{ContentId, ContentSigs, SigBlob};
+use rgbstd::info::ContractInfo;
+use rgbstd::persistence::{IndexProvider, StashProvider, StateProvider, Stock};
+use rgbstd::{ContractId, Identity};
+
+use crate::IdentityError;
+
+/// Tag of the hash committing to the contract genesis, which is signed by the
+/// issuer.
+pub const ISSUER_SIG_TAG: &str = "urn:lnp-bp:rgb:issuer-sig#2024-10-15";
+
+/// Marker of a BIP340 public key inside an issuer identity string. The marker
+/// must be followed by the hex-encoded x-only public key, for instance
+/// `ssi:acme bip340:<64 hex chars>`.
+pub const BIP340_IDENTITY_MARKER: &str = "bip340:";
+
+/// Result of the issuer identity verification.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display(lowercase)]
+pub enum IssuerStatus {
+    /// The contract genesis doesn't have a signature of its issuer.
+    Unsigned,
+
+    /// The genesis signature of the issuer is present, but none of the
+    /// verifiers knows how to check it.
+    Unverifiable,
+
+    /// The genesis signature of the issuer is valid.
+    Verified,
+
+    /// The genesis signature of the issuer is invalid; the contract may be
+    /// spoofing identity of some other issuer.
+    Invalid,
+}
+
+impl IssuerStatus {
+    pub fn is_verified(self) -> bool { self == IssuerStatus::Verified }
+}
+
+/// Verifier of issuer identity proofs.
+///
+/// Implementations may check signatures with keys embedded into the identity,
+/// published keys from DNS or onion services, or any other registry.
+pub trait IdentityVerifier {
+    /// Verifies signature of the issuer over the genesis of the contract.
+    ///
+    /// Returns `None` if the verifier doesn't know how to check the identity.
+    fn verify_issuer(
+        &self,
+        identity: &Identity,
+        contract_id: ContractId,
+        sig: &SigBlob,
+    ) -> Option<bool>;
+}
+
+impl<A: IdentityVerifier, B: IdentityVerifier> IdentityVerifier for (A, B) {
+    fn verify_issuer(
+        &self,
+        identity: &Identity,
+        contract_id: ContractId,
+        sig: &SigBlob,
+    ) -> Option<bool> {
+        self.0
+            .verify_issuer(identity, contract_id, sig)
+            .or_else(|| self.1.verify_issuer(identity, contract_id, sig))
+    }
+}
+
+/// Verifier of BIP340 signatures made with a key embedded into the issuer
+/// identity after the [`BIP340_IDENTITY_MARKER`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct Bip340Verifier;
+
+impl IdentityVerifier for Bip340Verifier {
+    fn verify_issuer(
+        &self,
+        identity: &Identity,
+        contract_id: ContractId,
+        sig: &SigBlob,
+    ) -> Option<bool> {
+        let pk = identity_key(identity).ok()?;
+        let Ok(sig) = schnorr::Signature::from_slice(sig.as_slice()) else {
+            return Some(false);
+        };
+        Some(
+            SECP256K1
+                .verify_schnorr(&sig, &issuer_message(contract_id), &pk)
+                .is_ok(),
+        )
+    }
+}
+
+/// Extracts BIP340 public key embedded into the issuer identity.
+pub fn identity_key(identity: &Identity) -> Result<XOnlyPublicKey, IdentityError> {
+    let identity = identity.to_string();
+    let (_, rest) = identity
+        .split_once(BIP340_IDENTITY_MARKER)
+        .ok_or(IdentityError::NoKey)?;
+    let hex = rest.get(..64).ok_or(IdentityError::InvalidKey)?;
+    let mut key = [0u8; 32];
+    for (byte, chunk) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        let chunk = std::str::from_utf8(chunk).map_err(|_| IdentityError::InvalidKey)?;
+        *byte = u8::from_str_radix(chunk, 16).map_err(|_| IdentityError::InvalidKey)?;
+    }
+    XOnlyPublicKey::from_slice(&key).map_err(|_| IdentityError::InvalidKey)
+}
+
+/// Computes message which is signed by the issuer.
+pub fn issuer_message(contract_id: ContractId) -> [u8; 32] {
+    let mut engine = Sha256::from_tag(ISSUER_SIG_TAG);
+    engine.input_raw(contract_id.as_slice());
+    engine.finish()
+}
+
+/// Signs contract genesis with the issuer key, checking that the key matches
+/// the one embedded into the issuer identity.
+pub fn sign_issuer(
+    identity: &Identity,
+    contract_id: ContractId,
+    keypair: &Keypair,
+) -> Result<SigBlob, IdentityError> {
+    if identity_key(identity)? != keypair.x_only_public_key().0 {
+        return Err(IdentityError::KeyMismatch);
+    }
+    let sig = keypair.sign_schnorr(&issuer_message(contract_id));
+    Ok(SigBlob::from(NonEmptyBlob::from_slice_checked(&sig.to_byte_array())))
+}
+
+/// Extension for [`ContractInfo`] reporting issuer identity verification
+/// status.
+pub trait ContractInfoExt {
+    /// Checks signature of the issuer over the contract genesis known to the
+    /// stock.
+    fn issuer_verified<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+        verifier: &impl IdentityVerifier,
+    ) -> Result<IssuerStatus, IdentityError>;
+}
+
+impl ContractInfoExt for ContractInfo {
+    fn issuer_verified<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+        verifier: &impl IdentityVerifier,
+    ) -> Result<IssuerStatus, IdentityError> {
+        let sigs = stock
+            .as_stash_provider()
+            .sigs_for(&ContentId::Genesis(self.id))
+            .map_err(|err| IdentityError::Stash(err.to_string()))?;
+        Ok(issuer_status(&self.issuer, self.id, sigs, verifier))
+    }
+}
+
+/// Checks signature of the issuer among the signatures over the contract
+/// genesis.
+pub fn issuer_status(
+    issuer: &Identity,
+    contract_id: ContractId,
+    sigs: Option<&ContentSigs>,
+    verifier: &impl IdentityVerifier,
+) -> IssuerStatus {
+    let Some(sig) = sigs.and_then(|sigs| sigs.get(issuer)) else {
+        return IssuerStatus::Unsigned;
+    };
+    match verifier.verify_issuer(issuer, contract_id, sig) {
+        None => IssuerStatus::Unverifiable,
+        Some(true) => IssuerStatus::Verified,
+        Some(false) => IssuerStatus::Invalid,
+    }
+}
+
+/// Stock extension storing issuer signatures.
+pub trait IssuerSigStock {
+    /// Signs the genesis of a known contract with the issuer key and stores
+    /// the signature, such that it is included into the consignments.
+    fn sign_issuer(
+        &mut self,
+        contract_id: ContractId,
+        keypair: &Keypair,
+    ) -> Result<(), IdentityError>;
+}
+
+impl<S: StashProvider, H: StateProvider, P: IndexProvider> IssuerSigStock for Stock<S, H, P> {
+    fn sign_issuer(
+        &mut self,
+        contract_id: ContractId,
+        keypair: &Keypair,
+    ) -> Result<(), IdentityError> {
+        let issuer = self
+            .as_stash_provider()
+            .genesis(contract_id)
+            .map_err(|err| IdentityError::Stash(err.to_string()))?
+            .issuer
+            .clone();
+        let sig = sign_issuer(&issuer, contract_id, keypair)?;
+        self.as_stash_provider_mut()
+            .import_sigs(ContentId::Genesis(contract_id), [(issuer, sig)])
+            .map_err(|err| IdentityError::Stash(err.to_string()))?;
+        self.as_stash_provider_mut()
+            .store()
+            .map_err(|err| IdentityError::Stash(err.to_string()))
+    }
+}
@@ -0,0 +1,228 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Outputs which are not derived from the wallet descriptor, but hold RGB
+//! state or bitcoins the wallet may spend.
+//!
+//! RGB state may be assigned to seals defined over outputs which the wallet
+//! descriptor doesn't track, for instance after the wallet has migrated to a
+//! new descriptor. Once such outputs are imported, the transfers select them
+//! like the wallet own outputs. Since the wallet can't derive keys for them,
+//! the PSBT inputs spending them are marked as externally controlled and have
+//! to be signed by their owner.
+
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::str::FromStr;
+
+use amplify::hex::{FromHex, ToHex};
+use bpstd::{Outpoint, RedeemScript, Sats, ScriptPubkey, TxOut, WitnessScript};
+use psrgbt::{Input, RgbInExt};
+
+use crate::ImportError;
+
+/// Output imported into the wallet.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct ImportedUtxo {
+    pub outpoint: Outpoint,
+    pub value: Sats,
+    pub script: ScriptPubkey,
+    /// Scripts which are required to spend the output.
+    pub redeem_info: RedeemInfo,
+}
+
+/// Scripts which are required to spend P2SH and P2WSH outputs, which must be
+/// provided to the signers of the transaction inputs spending them.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct RedeemInfo {
+    pub redeem_script: Option<RedeemScript>,
+    pub witness_script: Option<WitnessScript>,
+}
+
+impl ImportedUtxo {
+    pub fn new(
+        outpoint: Outpoint,
+        value: Sats,
+        script: ScriptPubkey,
+        redeem_info: RedeemInfo,
+    ) -> Self {
+        ImportedUtxo {
+            outpoint,
+            value,
+            script,
+            redeem_info,
+        }
+    }
+
+    /// Makes the PSBT input, which was constructed from the wallet descriptor,
+    /// to spend this output instead, removing the key derivation information
+    /// of the wallet and marking the input as externally controlled.
+    pub(crate) fn apply_to(&self, input: &mut Input) {
+        debug_assert_eq!(input.previous_outpoint, self.outpoint);
+        input.witness_utxo = Some(TxOut::new(self.script.clone(), self.value));
+        input.redeem_script = self.redeem_info.redeem_script.clone();
+        input.witness_script = self.redeem_info.witness_script.clone();
+        input.bip32_derivation.clear();
+        input.tap_bip32_derivation.clear();
+        input.tap_leaf_script.clear();
+        input.tap_internal_key = None;
+        input.tap_merkle_root = None;
+        input.set_rgb_external();
+    }
+}
+
+/// Serialized form of [`ImportedUtxo`], with the scripts encoded as
+/// hexadecimal strings.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+struct ImportedData {
+    value: u64,
+    script: String,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    redeem_script: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    witness_script: Option<String>,
+}
+
+/// Outputs imported into the wallet.
+///
+/// Serialized as a map from the outpoint to the output value and its scripts.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(
+        crate = "serde_crate",
+        try_from = "BTreeMap<String, ImportedData>",
+        into = "BTreeMap<String, ImportedData>"
+    )
+)]
+pub struct ImportedUtxos(BTreeMap<Outpoint, ImportedUtxo>);
+
+impl TryFrom<BTreeMap<String, ImportedData>> for ImportedUtxos {
+    type Error = ImportError;
+
+    fn try_from(map: BTreeMap<String, ImportedData>) -> Result<Self, Self::Error> {
+        let script = |hex: &str| {
+            Vec::<u8>::from_hex(hex).map_err(|_| ImportError::InvalidScript(hex.to_owned()))
+        };
+        map.into_iter()
+            .map(|(outpoint, data)| {
+                let outpoint = Outpoint::from_str(&outpoint)
+                    .map_err(|_| ImportError::InvalidOutpoint(outpoint))?;
+                let redeem_info = RedeemInfo {
+                    redeem_script: data
+                        .redeem_script
+                        .as_deref()
+                        .map(script)
+                        .transpose()?
+                        .map(RedeemScript::from_unsafe),
+                    witness_script: data
+                        .witness_script
+                        .as_deref()
+                        .map(script)
+                        .transpose()?
+                        .map(WitnessScript::from_unsafe),
+                };
+                let script = ScriptPubkey::from_unsafe(script(&data.script)?);
+                let utxo =
+                    ImportedUtxo::new(outpoint, Sats::from_sats(data.value), script, redeem_info);
+                Ok((outpoint, utxo))
+            })
+            .collect::<Result<_, _>>()
+            .map(ImportedUtxos)
+    }
+}
+
+impl From<ImportedUtxos> for BTreeMap<String, ImportedData> {
+    fn from(imported: ImportedUtxos) -> Self {
+        imported
+            .0
+            .into_iter()
+            .map(|(outpoint, utxo)| {
+                let data = ImportedData {
+                    value: utxo.value.sats(),
+                    script: utxo.script.to_hex(),
+                    redeem_script: utxo.redeem_info.redeem_script.map(|s| s.to_hex()),
+                    witness_script: utxo.redeem_info.witness_script.map(|s| s.to_hex()),
+                };
+                (outpoint.to_string(), data)
+            })
+            .collect()
+    }
+}
+
+impl ImportedUtxos {
+    pub fn new() -> Self { Self::default() }
+
+    /// Loads imported outputs from a YAML file, returning no outputs if the
+    /// file doesn't exist.
+    #[cfg(feature = "fs")]
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self, ImportError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = fs::File::open(path)?;
+        Ok(serde_yaml::from_reader(file)?)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn save_file(&self, path: impl AsRef<Path>) -> Result<(), ImportError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = fs::File::create(path)?;
+        serde_yaml::to_writer(file, self)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Imports the output, returning the previously imported output with the
+    /// same outpoint, if any.
+    pub fn import(&mut self, utxo: ImportedUtxo) -> Option<ImportedUtxo> {
+        self.0.insert(utxo.outpoint, utxo)
+    }
+
+    /// Removes the imported output, for instance once it is spent.
+    pub fn remove(&mut self, outpoint: Outpoint) -> Option<ImportedUtxo> {
+        self.0.remove(&outpoint)
+    }
+
+    pub fn is_imported(&self, outpoint: Outpoint) -> bool { self.0.contains_key(&outpoint) }
+
+    pub fn get(&self, outpoint: Outpoint) -> Option<&ImportedUtxo> { self.0.get(&outpoint) }
+
+    pub fn outpoints(&self) -> BTreeSet<Outpoint> { self.0.keys().copied().collect() }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ImportedUtxo> { self.0.values() }
+}
@@ -23,13 +23,19 @@
 
 use std::collections::HashMap;
 
-use bp::Tx;
-use bpstd::Network;
+use bp::{BlockHash, Tx};
+use bpstd::{Network, ScriptPubkey};
 use rgbstd::containers::Consignment;
 use rgbstd::validation::{ResolveWitness, WitnessResolverError};
 use rgbstd::XWitnessId;
 
-use crate::vm::{WitnessOrd, XWitnessTx};
+#[cfg(any(
+    feature = "electrum_blocking",
+    feature = "esplora_blocking",
+    feature = "mempool_blocking"
+))]
+use super::ConnectionOpts;
+use crate::vm::{WitnessOrd, WitnessPos, XWitnessTx};
 use crate::{Txid, XChain};
 
 // We need to repeat methods of `WitnessResolve` trait here to avoid making
@@ -38,6 +44,80 @@ pub trait RgbResolver: Send {
     fn check(&self, network: Network, expected_block_hash: String) -> Result<(), String>;
     fn resolve_pub_witness(&self, txid: Txid) -> Result<Option<Tx>, String>;
     fn resolve_pub_witness_ord(&self, txid: Txid) -> Result<WitnessOrd, String>;
+    fn resolve_block_hash(&self, height: u32) -> Result<BlockHash, String>;
+    fn resolve_tip_height(&self) -> Result<u32, String>;
+
+    /// Subscribes to notifications about transactions paying to the script
+    /// pubkey, including the ones which are still in mempool. Resolvers which
+    /// can't push notifications query the indexer on each
+    /// [`RgbResolver::resolve_script_txids`] call and do nothing here.
+    fn subscribe_script(&self, _script: &ScriptPubkey) -> Result<(), String> { Ok(()) }
+
+    /// Returns ids of the transactions known to the indexer which pay to or
+    /// spend from the script pubkey, including unconfirmed ones. Resolvers
+    /// which don't support script history return an empty list.
+    fn resolve_script_txids(&self, _script: &ScriptPubkey) -> Result<Vec<Txid>, String> {
+        Ok(vec![])
+    }
+
+    /// Relays package of transactions (like a parent with its CPFP child),
+    /// which are evaluated by the mempool together such that a child may pay
+    /// for a parent which feerate is too low for the parent to be accepted
+    /// alone. Resolvers which can't relay packages return an error.
+    fn broadcast_package(&self, _txs: &[Tx]) -> Result<(), String> {
+        Err(s!("the indexer doesn't support relaying of transaction packages"))
+    }
+}
+
+impl<T: RgbResolver + ?Sized> RgbResolver for Box<T> {
+    fn check(&self, network: Network, expected_block_hash: String) -> Result<(), String> {
+        (**self).check(network, expected_block_hash)
+    }
+    fn resolve_pub_witness(&self, txid: Txid) -> Result<Option<Tx>, String> {
+        (**self).resolve_pub_witness(txid)
+    }
+    fn resolve_pub_witness_ord(&self, txid: Txid) -> Result<WitnessOrd, String> {
+        (**self).resolve_pub_witness_ord(txid)
+    }
+    fn resolve_block_hash(&self, height: u32) -> Result<BlockHash, String> {
+        (**self).resolve_block_hash(height)
+    }
+    fn resolve_tip_height(&self) -> Result<u32, String> { (**self).resolve_tip_height() }
+    fn subscribe_script(&self, script: &ScriptPubkey) -> Result<(), String> {
+        (**self).subscribe_script(script)
+    }
+    fn resolve_script_txids(&self, script: &ScriptPubkey) -> Result<Vec<Txid>, String> {
+        (**self).resolve_script_txids(script)
+    }
+    fn broadcast_package(&self, txs: &[Tx]) -> Result<(), String> {
+        (**self).broadcast_package(txs)
+    }
+}
+
+/// Returns hash of the genesis block of the network.
+pub(crate) fn genesis_block_hash(network: Network) -> &'static str {
+    match network {
+        Network::Mainnet => "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f",
+        Network::Testnet3 => "000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943",
+        Network::Testnet4 => "00000000da84f2bafbbc53dee25a72ae507ff4914b867c565be350b0da8bf043",
+        Network::Signet => "00000008819873e925422c1ff0f99f7cc9bbb232af63a077a480a3633bee1ef6",
+        Network::Regtest => "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206",
+    }
+}
+
+/// Returns hash of the genesis block of the Liquid network paired with the
+/// Bitcoin one, if the Liquid network has a well-known genesis.
+#[cfg(feature = "liquid")]
+pub(crate) fn liquid_genesis_block_hash(network: Network) -> Option<&'static str> {
+    match network {
+        Network::Mainnet => {
+            Some("1466275836220db2944ca059a3a10ef6fd2ea684b0688d2c379296888a206003")
+        }
+        Network::Testnet3 | Network::Testnet4 | Network::Signet => {
+            Some("a771da8e52ee6ad581ed1e9a99825e5b3b7992225534eaa2ae23244fe26ab1c1")
+        }
+        Network::Regtest => None,
+    }
 }
 
 /// Type that contains any of the [`Resolver`] types defined by the library
@@ -45,52 +125,212 @@ pub trait RgbResolver: Send {
 #[non_exhaustive]
 pub struct AnyResolver {
     inner: Box<dyn RgbResolver>,
-    terminal_txes: HashMap<Txid, Tx>,
+    #[cfg(feature = "liquid")]
+    liquid: Option<Box<dyn RgbResolver>>,
+    terminal_txes: HashMap<XWitnessId, XWitnessTx>,
 }
 
 impl AnyResolver {
+    fn with_inner(inner: Box<dyn RgbResolver>) -> Self {
+        AnyResolver {
+            inner,
+            #[cfg(feature = "liquid")]
+            liquid: None,
+            terminal_txes: Default::default(),
+        }
+    }
+
     #[cfg(feature = "electrum_blocking")]
     pub fn electrum_blocking(url: &str, config: Option<electrum::Config>) -> Result<Self, String> {
-        Ok(AnyResolver {
-            inner: Box::new(
-                electrum::Client::from_config(url, config.unwrap_or_default())
-                    .map_err(|e| e.to_string())?,
-            ),
-            terminal_txes: Default::default(),
-        })
+        Ok(AnyResolver::with_inner(Box::new(
+            electrum::Client::from_config(url, config.unwrap_or_default())
+                .map_err(|e| e.to_string())?,
+        )))
+    }
+
+    /// Constructs electrum resolver using the provided connection options.
+    #[cfg(feature = "electrum_blocking")]
+    pub fn electrum_blocking_with(url: &str, opts: &ConnectionOpts) -> Result<Self, String> {
+        Self::electrum_blocking(url, Some(opts.electrum_config()))
     }
 
     #[cfg(feature = "esplora_blocking")]
     pub fn esplora_blocking(url: &str, config: Option<esplora::Config>) -> Result<Self, String> {
-        Ok(AnyResolver {
-            inner: Box::new(
-                esplora::BlockingClient::from_config(url, config.unwrap_or_default())
-                    .map_err(|e| e.to_string())?,
-            ),
-            terminal_txes: Default::default(),
-        })
+        Ok(AnyResolver::with_inner(Box::new(
+            esplora::BlockingClient::from_config(url, config.unwrap_or_default())
+                .map_err(|e| e.to_string())?,
+        )))
+    }
+
+    /// Constructs esplora resolver using the provided connection options.
+    #[cfg(feature = "esplora_blocking")]
+    pub fn esplora_blocking_with(url: &str, opts: &ConnectionOpts) -> Result<Self, String> {
+        let client =
+            super::esplora_blocking::blocking_client(url, opts).map_err(|e| e.to_string())?;
+        Ok(AnyResolver::with_inner(Box::new(client)).with_retries(opts.resolver))
     }
 
     #[cfg(feature = "mempool_blocking")]
     pub fn mempool_blocking(url: &str, config: Option<esplora::Config>) -> Result<Self, String> {
+        Ok(AnyResolver::with_inner(Box::new(super::mempool_blocking::MemPoolClient::new(
+            url,
+            config.unwrap_or_default(),
+        )?)))
+    }
+
+    /// Constructs mempool resolver using the provided connection options.
+    #[cfg(feature = "mempool_blocking")]
+    pub fn mempool_blocking_with(url: &str, opts: &ConnectionOpts) -> Result<Self, String> {
+        let client =
+            super::esplora_blocking::blocking_client(url, opts).map_err(|e| e.to_string())?;
+        Ok(AnyResolver::with_inner(Box::new(super::mempool_blocking::MemPoolClient::with_client(
+            client,
+        )))
+        .with_retries(opts.resolver))
+    }
+
+    #[cfg(feature = "esplora_async")]
+    pub fn esplora_async(resolver: super::esplora_async::EsploraAsyncResolver) -> Self {
+        AnyResolver::with_inner(Box::new(resolver))
+    }
+
+    /// Wraps the resolver into [`super::CachingResolver`] persisting
+    /// resolved transactions and their final mining positions in the file at
+    /// `path`.
+    #[cfg(feature = "fs")]
+    pub fn cached(self, path: impl AsRef<std::path::Path>) -> Result<Self, String> {
         Ok(AnyResolver {
-            inner: Box::new(super::mempool_blocking::MemPoolClient::new(
-                url,
-                config.unwrap_or_default(),
-            )?),
-            terminal_txes: Default::default(),
+            inner: Box::new(super::CachingResolver::load(self.inner, path)?),
+            ..self
         })
     }
+
+    /// Wraps the resolver into [`super::CheckpointResolver`] persisting the
+    /// witnesses resolved during acceptance of a consignment in the
+    /// checkpoint file at `path`, and restoring the witnesses already present
+    /// in the file. If `rate_limit` is given, requests to the wrapped resolver
+    /// are made no more often than once per the interval.
+    #[cfg(feature = "fs")]
+    pub fn checkpointed(
+        self,
+        path: impl AsRef<std::path::Path>,
+        rate_limit: Option<std::time::Duration>,
+    ) -> Result<Self, String> {
+        let mut resolver = super::CheckpointResolver::load(self.inner, path)?;
+        if let Some(interval) = rate_limit {
+            resolver = resolver.with_rate_limit(interval);
+        }
+        Ok(AnyResolver {
+            inner: Box::new(resolver),
+            ..self
+        })
+    }
+
+    /// Wraps the resolver into [`super::RetryingResolver`] retrying failed
+    /// requests according to the provided policy. Does nothing if the policy
+    /// disables retries.
+    #[cfg(feature = "esplora_blocking")]
+    pub fn with_retries(self, config: super::ResolverConfig) -> Self {
+        if config.retries == 0 {
+            return self;
+        }
+        AnyResolver {
+            inner: Box::new(super::RetryingResolver::new(self.inner, config)),
+            ..self
+        }
+    }
+
+    /// Constructs resolver answering from the in-memory [`super::MockChain`],
+    /// which is shared with the provided chain handle.
+    #[cfg(feature = "testing")]
+    pub fn mock(chain: &super::MockChain) -> Self {
+        AnyResolver::with_inner(Box::new(chain.clone()))
+    }
+
     pub fn check(&self, network: Network) -> Result<(), String> {
-        let expected_block_hash = match network {
-            Network::Mainnet => "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f",
-            Network::Testnet3 => "000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943",
-            Network::Testnet4 => "00000000da84f2bafbbc53dee25a72ae507ff4914b867c565be350b0da8bf043",
-            Network::Signet => "00000008819873e925422c1ff0f99f7cc9bbb232af63a077a480a3633bee1ef6",
-            Network::Regtest => "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206",
+        self.inner
+            .check(network, genesis_block_hash(network).to_string())
+    }
+
+    /// Adds resolver of the witness transactions on Liquid, like an esplora
+    /// instance indexing an Elements chain. Bitcoin witnesses are still
+    /// resolved by this resolver.
+    #[cfg(feature = "liquid")]
+    pub fn with_liquid(self, liquid: AnyResolver) -> Self {
+        AnyResolver {
+            liquid: Some(liquid.inner),
+            ..self
+        }
+    }
+
+    /// Checks that the Liquid resolver is for the Liquid network matching
+    /// the Bitcoin one. Liquid regtest chains have custom genesis, so only
+    /// the presence of the resolver is checked for them.
+    #[cfg(feature = "liquid")]
+    pub fn check_liquid(&self, network: Network) -> Result<(), String> {
+        let liquid = self
+            .liquid
+            .as_ref()
+            .ok_or_else(|| s!("no resolver for Liquid witnesses is configured"))?;
+        let Some(expected) = liquid_genesis_block_hash(network) else {
+            return Ok(());
+        };
+        if liquid.resolve_block_hash(0)?.to_string() != expected {
+            return Err(s!("Liquid resolver is for a network different from the wallet's one"));
+        }
+        Ok(())
+    }
+
+    /// Returns the resolver for the layer 1 of the witness together with the
+    /// witness transaction id.
+    fn layer1_resolver(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<(&dyn RgbResolver, Txid), WitnessResolverError> {
+        match witness_id {
+            XChain::Bitcoin(txid) => Ok((self.inner.as_ref(), txid)),
+            #[cfg(feature = "liquid")]
+            XChain::Liquid(txid) => match &self.liquid {
+                Some(liquid) => Ok((liquid.as_ref(), txid)),
+                None => Err(WitnessResolverError::Other(
+                    witness_id,
+                    s!("no resolver for Liquid witnesses is configured"),
+                )),
+            },
+            _ => Err(WitnessResolverError::Other(
+                witness_id,
+                format!("{} is not supported as layer 1 network", witness_id.layer1()),
+            )),
         }
-        .to_string();
-        self.inner.check(network, expected_block_hash)
+    }
+
+    /// Returns hash of the block at the given height in the current main
+    /// chain, as known to the indexer.
+    pub fn resolve_block_hash(&self, height: u32) -> Result<BlockHash, String> {
+        self.inner.resolve_block_hash(height)
+    }
+
+    /// Returns height of the current main chain tip, as known to the indexer.
+    pub fn resolve_tip_height(&self) -> Result<u32, String> { self.inner.resolve_tip_height() }
+
+    /// Subscribes to the mempool and blockchain events for transactions
+    /// paying to the script pubkey.
+    pub fn subscribe_script(&self, script: &ScriptPubkey) -> Result<(), String> {
+        self.inner.subscribe_script(script)
+    }
+
+    /// Returns ids of the transactions known to the indexer (including
+    /// unconfirmed ones) which pay to or spend from the script pubkey.
+    pub fn resolve_script_txids(&self, script: &ScriptPubkey) -> Result<Vec<Txid>, String> {
+        self.inner.resolve_script_txids(script)
+    }
+
+    /// Relays package of transactions, where each transaction may spend
+    /// outputs of the preceding ones, such that the mempool evaluates their
+    /// feerate together. Used to broadcast a witness transaction with its
+    /// CPFP child.
+    pub fn broadcast_package(&self, txs: &[Tx]) -> Result<(), String> {
+        self.inner.broadcast_package(txs)
     }
 
     pub fn add_terminals<const TYPE: bool>(&mut self, consignment: &Consignment<TYPE>) {
@@ -99,11 +339,7 @@ impl AnyResolver {
                 .bundles
                 .iter()
                 .filter_map(|bw| bw.pub_witness.maybe_map_ref(|w| w.tx().cloned()))
-                .filter_map(|tx| match tx {
-                    XChain::Bitcoin(tx) => Some(tx),
-                    XChain::Liquid(_) | XChain::Other(_) => None,
-                })
-                .map(|tx| (tx.txid(), tx)),
+                .map(|tx| (tx.witness_id(), tx)),
         );
     }
 }
@@ -113,41 +349,46 @@ impl ResolveWitness for AnyResolver {
         &self,
         witness_id: XWitnessId,
     ) -> Result<XWitnessTx, WitnessResolverError> {
-        let XWitnessId::Bitcoin(txid) = witness_id else {
-            return Err(WitnessResolverError::Other(
-                witness_id,
-                format!("{} is not supported as layer 1 network", witness_id.layer1()),
-            ));
-        };
+        let (resolver, txid) = self.layer1_resolver(witness_id)?;
 
-        if let Some(tx) = self.terminal_txes.get(&txid) {
-            return Ok(XWitnessTx::Bitcoin(tx.clone()));
+        if let Some(tx) = self.terminal_txes.get(&witness_id) {
+            return Ok(tx.clone());
         }
 
-        self.inner
+        resolver
             .resolve_pub_witness(txid)
             .map_err(|e| WitnessResolverError::Other(witness_id, e))
             .and_then(|r| r.ok_or(WitnessResolverError::Unknown(witness_id)))
-            .map(XChain::Bitcoin)
+            .map(|tx| XChain::with(witness_id.layer1(), tx))
     }
 
     fn resolve_pub_witness_ord(
         &self,
         witness_id: XWitnessId,
     ) -> Result<WitnessOrd, WitnessResolverError> {
-        let XWitnessId::Bitcoin(txid) = witness_id else {
-            return Err(WitnessResolverError::Other(
-                witness_id,
-                format!("{} is not supported as layer 1 network", witness_id.layer1()),
-            ));
-        };
+        let (resolver, txid) = self.layer1_resolver(witness_id)?;
 
-        if self.terminal_txes.contains_key(&txid) {
+        if self.terminal_txes.contains_key(&witness_id) {
             return Ok(WitnessOrd::Tentative);
         }
 
-        self.inner
+        let ord = resolver
             .resolve_pub_witness_ord(txid)
-            .map_err(|e| WitnessResolverError::Other(witness_id, e))
+            .map_err(|e| WitnessResolverError::Other(witness_id, e))?;
+        // Indexers report Bitcoin positions, which have to be assigned to the
+        // layer 1 of the witness
+        match ord {
+            WitnessOrd::Mined(pos) if pos.layer1() != witness_id.layer1() => {
+                WitnessPos::liquid(pos.height(), pos.timestamp())
+                    .map(WitnessOrd::Mined)
+                    .ok_or_else(|| {
+                        WitnessResolverError::Other(
+                            witness_id,
+                            s!("invalid timestamp of the Liquid block"),
+                        )
+                    })
+            }
+            ord => Ok(ord),
+        }
     }
 }
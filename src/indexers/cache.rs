@@ -0,0 +1,243 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Resolver wrapper persisting answers of the indexer which can't change
+//! anymore.
+//!
+//! Transactions are identified by their txid and are cached forever. Mining
+//! positions are cached only once the transaction is buried under at least
+//! [`CachingResolver::reorg_depth`] blocks; positions of transactions mined
+//! within the re-org distance from the tip (as well as tentative and archived
+//! witnesses) are always requested from the underlying resolver.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use bp::{BlockHash, Tx};
+use bpstd::{Network, ScriptPubkey};
+
+use super::RgbResolver;
+use crate::vm::{WitnessOrd, WitnessPos};
+use crate::Txid;
+
+/// Default number of confirmations after which the mining position of a
+/// transaction is considered final.
+pub const DEFAULT_REORG_DEPTH: u32 = 6;
+
+const TAG_TX: &str = "tx";
+const TAG_MINED: &str = "mined";
+
+/// Resolver wrapper caching resolved transactions and their final mining
+/// positions in a file.
+pub struct CachingResolver<R: RgbResolver> {
+    inner: R,
+    path: PathBuf,
+    reorg_depth: u32,
+    tip: Cell<Option<u32>>,
+    txes: RefCell<HashMap<Txid, Tx>>,
+    positions: RefCell<HashMap<Txid, WitnessPos>>,
+}
+
+impl<R: RgbResolver> CachingResolver<R> {
+    /// Wraps the resolver, loading cached data from the file at `path` (if
+    /// exists) with the [`DEFAULT_REORG_DEPTH`].
+    pub fn load(inner: R, path: impl AsRef<Path>) -> Result<Self, String> {
+        Self::with_reorg_depth(inner, path, DEFAULT_REORG_DEPTH)
+    }
+
+    /// Wraps the resolver, loading cached data from the file at `path` (if
+    /// exists). Mining positions with less than `reorg_depth` confirmations
+    /// are never cached.
+    pub fn with_reorg_depth(
+        inner: R,
+        path: impl AsRef<Path>,
+        reorg_depth: u32,
+    ) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        let mut txes = HashMap::new();
+        let mut positions = HashMap::new();
+        if path.exists() {
+            let data = fs::read_to_string(&path)
+                .map_err(|e| format!("unable to read resolver cache: {e}"))?;
+            for (no, line) in data
+                .lines()
+                .enumerate()
+                .filter(|(_, l)| !l.trim().is_empty())
+            {
+                let err = || format!("invalid resolver cache entry at line {}", no + 1);
+                let mut fields = line.split('\t');
+                let tag = fields.next().ok_or_else(err)?;
+                let txid = fields
+                    .next()
+                    .and_then(|s| Txid::from_str(s).ok())
+                    .ok_or_else(err)?;
+                match tag {
+                    TAG_TX => {
+                        let tx = fields
+                            .next()
+                            .and_then(|s| Tx::from_str(s).ok())
+                            .filter(|tx| tx.txid() == txid)
+                            .ok_or_else(err)?;
+                        txes.insert(txid, tx);
+                    }
+                    TAG_MINED => {
+                        let height = fields
+                            .next()
+                            .and_then(|s| NonZeroU32::from_str(s).ok())
+                            .ok_or_else(err)?;
+                        let timestamp = fields
+                            .next()
+                            .and_then(|s| i64::from_str(s).ok())
+                            .ok_or_else(err)?;
+                        let pos = WitnessPos::bitcoin(height, timestamp).ok_or_else(err)?;
+                        positions.insert(txid, pos);
+                    }
+                    _ => return Err(err()),
+                }
+            }
+        }
+        Ok(Self {
+            inner,
+            path,
+            reorg_depth,
+            tip: Cell::new(None),
+            txes: RefCell::new(txes),
+            positions: RefCell::new(positions),
+        })
+    }
+
+    /// Path to the cache file.
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Number of confirmations after which the mining position is cached.
+    pub fn reorg_depth(&self) -> u32 { self.reorg_depth }
+
+    /// Returns the wrapped resolver.
+    pub fn into_inner(self) -> R { self.inner }
+
+    /// Removes all cached data, including the cache file.
+    pub fn clear(&self) -> Result<(), String> {
+        self.txes.borrow_mut().clear();
+        self.positions.borrow_mut().clear();
+        if self.path.exists() {
+            fs::remove_file(&self.path)
+                .map_err(|e| format!("unable to remove resolver cache: {e}"))?;
+        }
+        Ok(())
+    }
+
+    fn append(&self, line: String) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("unable to write resolver cache: {e}"))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("unable to write resolver cache: {e}"))?;
+        writeln!(file, "{line}").map_err(|e| format!("unable to write resolver cache: {e}"))
+    }
+
+    // The tip is requested once per resolver lifetime: an outdated tip only
+    // makes positions look more recent than they are, which is safe.
+    fn tip_height(&self) -> Result<u32, String> {
+        if let Some(tip) = self.tip.get() {
+            return Ok(tip);
+        }
+        let tip = self.inner.resolve_tip_height()?;
+        self.tip.set(Some(tip));
+        Ok(tip)
+    }
+
+    fn is_final(&self, pos: &WitnessPos) -> Result<bool, String> {
+        let tip = self.tip_height()?;
+        Ok(tip.saturating_sub(pos.height().get()) >= self.reorg_depth)
+    }
+}
+
+impl<R: RgbResolver> RgbResolver for CachingResolver<R> {
+    fn check(&self, network: Network, expected_block_hash: String) -> Result<(), String> {
+        self.inner.check(network, expected_block_hash)
+    }
+
+    fn resolve_pub_witness(&self, txid: Txid) -> Result<Option<Tx>, String> {
+        if let Some(tx) = self.txes.borrow().get(&txid) {
+            return Ok(Some(tx.clone()));
+        }
+        let Some(tx) = self.inner.resolve_pub_witness(txid)? else {
+            return Ok(None);
+        };
+        self.append(format!("{TAG_TX}\t{txid}\t{tx:x}"))?;
+        self.txes.borrow_mut().insert(txid, tx.clone());
+        Ok(Some(tx))
+    }
+
+    fn resolve_pub_witness_ord(&self, txid: Txid) -> Result<WitnessOrd, String> {
+        if let Some(pos) = self.positions.borrow().get(&txid) {
+            if self.is_final(pos)? {
+                return Ok(WitnessOrd::Mined(*pos));
+            }
+        }
+        let ord = self.inner.resolve_pub_witness_ord(txid)?;
+        match ord {
+            WitnessOrd::Mined(pos) if self.is_final(&pos)? => {
+                if self.positions.borrow_mut().insert(txid, pos) != Some(pos) {
+                    self.append(format!(
+                        "{TAG_MINED}\t{txid}\t{}\t{}",
+                        pos.height(),
+                        pos.timestamp()
+                    ))?;
+                }
+            }
+            _ => {
+                self.positions.borrow_mut().remove(&txid);
+            }
+        }
+        Ok(ord)
+    }
+
+    fn resolve_block_hash(&self, height: u32) -> Result<BlockHash, String> {
+        self.inner.resolve_block_hash(height)
+    }
+
+    fn resolve_tip_height(&self) -> Result<u32, String> {
+        let tip = self.inner.resolve_tip_height()?;
+        self.tip.set(Some(tip));
+        Ok(tip)
+    }
+
+    fn subscribe_script(&self, script: &ScriptPubkey) -> Result<(), String> {
+        self.inner.subscribe_script(script)
+    }
+
+    fn resolve_script_txids(&self, script: &ScriptPubkey) -> Result<Vec<Txid>, String> {
+        self.inner.resolve_script_txids(script)
+    }
+
+    fn broadcast_package(&self, txs: &[Tx]) -> Result<(), String> {
+        self.inner.broadcast_package(txs)
+    }
+}
@@ -0,0 +1,245 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Resolver wrapper checkpointing the witnesses resolved while accepting a
+//! consignment, such that an interrupted acceptance of a large consignment
+//! can be resumed without requesting the same witnesses from the indexer
+//! again.
+//!
+//! Validation of a consignment resolves the witness transaction of each of
+//! its bundles, which is what makes acceptance of large consignments slow on
+//! poor connections. Each resolved witness transaction and its mining
+//! position are appended to the checkpoint file as soon as they are received,
+//! i.e. the progress is persisted per bundle. Unlike [`super::CachingResolver`],
+//! the checkpoint is specific to a single consignment and is removed once the
+//! consignment is accepted. Witnesses which are not mined are always requested
+//! again, since they may get mined in the meantime.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bp::{BlockHash, Tx};
+use bpstd::{Network, ScriptPubkey};
+use rgbstd::containers::ConsignmentId;
+
+use super::RgbResolver;
+use crate::vm::{WitnessOrd, WitnessPos};
+use crate::Txid;
+
+/// Name of the directory inside the data directory keeping checkpoints of the
+/// consignments which acceptance was interrupted.
+pub const CHECKPOINT_DIR: &str = "checkpoints";
+
+const TAG_TX: &str = "tx";
+const TAG_MINED: &str = "mined";
+
+/// Returns path to the checkpoint file of the consignment inside the
+/// [`CHECKPOINT_DIR`] of the data directory.
+pub fn checkpoint_path(data_dir: impl AsRef<Path>, consignment_id: ConsignmentId) -> PathBuf {
+    data_dir
+        .as_ref()
+        .join(CHECKPOINT_DIR)
+        .join(format!("{consignment_id:-}.checkpoint"))
+}
+
+enum Entry {
+    Tx(Txid, Tx),
+    Mined(Txid, WitnessPos),
+}
+
+fn parse_entry(line: &str) -> Option<Entry> {
+    let mut fields = line.split('\t');
+    let tag = fields.next()?;
+    let txid = Txid::from_str(fields.next()?).ok()?;
+    match tag {
+        TAG_TX => {
+            let tx = Tx::from_str(fields.next()?).ok()?;
+            (tx.txid() == txid).then_some(Entry::Tx(txid, tx))
+        }
+        TAG_MINED => {
+            let height = NonZeroU32::from_str(fields.next()?).ok()?;
+            let timestamp = i64::from_str(fields.next()?).ok()?;
+            WitnessPos::bitcoin(height, timestamp).map(|pos| Entry::Mined(txid, pos))
+        }
+        _ => None,
+    }
+}
+
+/// Resolver wrapper persisting resolved witnesses in the checkpoint file and,
+/// optionally, limiting the rate of the requests to the underlying resolver.
+pub struct CheckpointResolver<R: RgbResolver> {
+    inner: R,
+    path: PathBuf,
+    rate_limit: Option<Duration>,
+    last_request: Cell<Option<Instant>>,
+    restored: usize,
+    txes: RefCell<HashMap<Txid, Tx>>,
+    positions: RefCell<HashMap<Txid, WitnessPos>>,
+}
+
+impl<R: RgbResolver> CheckpointResolver<R> {
+    /// Wraps the resolver, restoring witnesses from the checkpoint file at
+    /// `path`, if it exists.
+    pub fn load(inner: R, path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        let mut txes = HashMap::new();
+        let mut positions = HashMap::new();
+        if path.exists() {
+            let data =
+                fs::read_to_string(&path).map_err(|e| format!("unable to read checkpoint: {e}"))?;
+            let lines = data.lines().collect::<Vec<_>>();
+            for (no, line) in lines.iter().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match parse_entry(line) {
+                    Some(Entry::Tx(txid, tx)) => {
+                        txes.insert(txid, tx);
+                    }
+                    Some(Entry::Mined(txid, pos)) => {
+                        positions.insert(txid, pos);
+                    }
+                    // The last line is cut if the process was interrupted
+                    // while writing it
+                    None if no + 1 == lines.len() && !data.ends_with('\n') => {}
+                    None => return Err(format!("invalid checkpoint entry at line {}", no + 1)),
+                }
+            }
+        }
+        Ok(Self {
+            inner,
+            path,
+            rate_limit: None,
+            last_request: Cell::new(None),
+            restored: txes.len(),
+            txes: RefCell::new(txes),
+            positions: RefCell::new(positions),
+        })
+    }
+
+    /// Sets minimal interval between two requests to the underlying resolver.
+    pub fn with_rate_limit(mut self, interval: Duration) -> Self {
+        self.rate_limit = Some(interval);
+        self
+    }
+
+    /// Path to the checkpoint file.
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Number of witness transactions restored from the checkpoint file.
+    pub fn restored(&self) -> usize { self.restored }
+
+    /// Number of witness transactions resolved so far, including the restored
+    /// ones.
+    pub fn resolved(&self) -> usize { self.txes.borrow().len() }
+
+    /// Returns the wrapped resolver.
+    pub fn into_inner(self) -> R { self.inner }
+
+    fn append(&self, line: String) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("unable to write checkpoint: {e}"))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("unable to write checkpoint: {e}"))?;
+        writeln!(file, "{line}").map_err(|e| format!("unable to write checkpoint: {e}"))?;
+        file.sync_data()
+            .map_err(|e| format!("unable to write checkpoint: {e}"))
+    }
+
+    fn throttle(&self) {
+        let Some(interval) = self.rate_limit else {
+            return;
+        };
+        if let Some(last) = self.last_request.get() {
+            let elapsed = last.elapsed();
+            if elapsed < interval {
+                thread::sleep(interval - elapsed);
+            }
+        }
+        self.last_request.set(Some(Instant::now()));
+    }
+}
+
+impl<R: RgbResolver> RgbResolver for CheckpointResolver<R> {
+    fn check(&self, network: Network, expected_block_hash: String) -> Result<(), String> {
+        self.inner.check(network, expected_block_hash)
+    }
+
+    fn resolve_pub_witness(&self, txid: Txid) -> Result<Option<Tx>, String> {
+        if let Some(tx) = self.txes.borrow().get(&txid) {
+            return Ok(Some(tx.clone()));
+        }
+        self.throttle();
+        let Some(tx) = self.inner.resolve_pub_witness(txid)? else {
+            return Ok(None);
+        };
+        self.append(format!("{TAG_TX}\t{txid}\t{tx:x}"))?;
+        self.txes.borrow_mut().insert(txid, tx.clone());
+        Ok(Some(tx))
+    }
+
+    fn resolve_pub_witness_ord(&self, txid: Txid) -> Result<WitnessOrd, String> {
+        if let Some(pos) = self.positions.borrow().get(&txid) {
+            return Ok(WitnessOrd::Mined(*pos));
+        }
+        self.throttle();
+        let ord = self.inner.resolve_pub_witness_ord(txid)?;
+        if let WitnessOrd::Mined(pos) = ord {
+            self.append(format!("{TAG_MINED}\t{txid}\t{}\t{}", pos.height(), pos.timestamp()))?;
+            self.positions.borrow_mut().insert(txid, pos);
+        }
+        Ok(ord)
+    }
+
+    fn resolve_block_hash(&self, height: u32) -> Result<BlockHash, String> {
+        self.throttle();
+        self.inner.resolve_block_hash(height)
+    }
+
+    fn resolve_tip_height(&self) -> Result<u32, String> {
+        self.throttle();
+        self.inner.resolve_tip_height()
+    }
+
+    fn subscribe_script(&self, script: &ScriptPubkey) -> Result<(), String> {
+        self.inner.subscribe_script(script)
+    }
+
+    fn resolve_script_txids(&self, script: &ScriptPubkey) -> Result<Vec<Txid>, String> {
+        self.throttle();
+        self.inner.resolve_script_txids(script)
+    }
+
+    fn broadcast_package(&self, txs: &[Tx]) -> Result<(), String> {
+        self.inner.broadcast_package(txs)
+    }
+}
@@ -24,8 +24,8 @@
 use std::iter;
 use std::num::NonZeroU32;
 
-use bp::ConsensusDecode;
-use bpstd::{Network, Tx, Txid};
+use bp::{BlockHash, ConsensusDecode};
+use bpstd::{Network, ScriptPubkey, Tx, Txid};
 use electrum::{Client, ElectrumApi, Param};
 pub use electrum::{Config, ConfigBuilder, Error, Socks5Config};
 use rgbstd::vm::WitnessPos;
@@ -47,12 +47,13 @@ impl RgbResolver for Client {
             return Err(s!("resolver is for a network different from the wallet's one"));
         }
         // check the electrum server has the required functionality (verbose
-        // transactions)
+        // transactions); custom signets share the genesis block with the default
+        // one, so only its coinbase is guaranteed to be known to the server
         let txid = match network {
             Network::Mainnet => "33e794d097969002ee05d336686fc03c9e15a597c1b9827669460fac98799036",
             Network::Testnet3 => "5e6560fd518aadbed67ee4a55bdc09f19e619544f5511e9343ebba66d2f62653",
             Network::Testnet4 => "7aa0a7ae1e223414cb807e40cd57e667b718e42aaf9306db9102fe28912b7b4e",
-            Network::Signet => "8153034f45e695453250a8fb7225a5e545144071d8ed7b0d3211efa1f3c92ad8",
+            Network::Signet => "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
             Network::Regtest => "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
         };
         if let Err(e) = self.raw_call("blockchain.transaction.get", vec![
@@ -115,7 +116,10 @@ impl RgbResolver for Client {
             // we need this under assumption that electrum was lying due to "DB desynchronization"
             // since this have a very low probability we do that after everything else
             .chain((1..=SAFETY_MARGIN).flat_map(|i| [i + forward + 1, 1 - i]))
-            .find_map(|offset| self.transaction_get_merkle(&txid, (height + offset) as usize).ok())
+            .find_map(|offset| {
+                self.transaction_get_merkle(&txid, (height + offset) as usize)
+                    .ok()
+            })
             .ok_or_else(|| s!("transaction can't be located in the blockchain"))?;
 
         let tx_height = u32::try_from(get_merkle_res.block_height)
@@ -145,4 +149,36 @@ impl RgbResolver for Client {
                 }
             })
     }
+
+    fn resolve_block_hash(&self, height: u32) -> Result<BlockHash, String> {
+        Ok(check!(self.block_header(height as usize)).block_hash())
+    }
+
+    fn resolve_tip_height(&self) -> Result<u32, String> {
+        let header = check!(self.block_headers_subscribe());
+        u32::try_from(header.height).map_err(|_| s!("impossible height value"))
+    }
+
+    fn subscribe_script(&self, script: &ScriptPubkey) -> Result<(), String> {
+        match self.script_subscribe(script) {
+            Ok(_) | Err(Error::AlreadySubscribed(_)) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn resolve_script_txids(&self, script: &ScriptPubkey) -> Result<Vec<Txid>, String> {
+        // Drain status notifications received since the subscription: the
+        // history returned below already accounts for them
+        loop {
+            match self.script_pop(script) {
+                Ok(Some(_)) => continue,
+                Ok(None) | Err(Error::NotSubscribed(_)) => break,
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+        Ok(check!(self.script_get_history(script))
+            .into_iter()
+            .map(|item| item.tx_hash)
+            .collect())
+    }
 }
@@ -0,0 +1,208 @@
+// RGB smart contracts for Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use bp::{BlockHash, Tx};
+use bpstd::{Network, Txid};
+pub use esplora::{AsyncClient, Builder, Config, Error};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use rgbstd::containers::Consignment;
+use rgbstd::vm::WitnessPos;
+
+use super::{ConnectionOpts, RgbResolver};
+use crate::vm::WitnessOrd;
+use crate::XChain;
+
+/// Resolver for environments where only asynchronous I/O is available, like
+/// browsers (where the HTTP requests are performed via `fetch` API).
+///
+/// Since RGB validation is synchronous, the resolver can't query the indexer
+/// during the validation. Instead, all witness transactions which will be
+/// required for the validation must be fetched in advance using
+/// [`EsploraAsyncResolver::prefetch`] or
+/// [`EsploraAsyncResolver::prefetch_consignment`] methods. Requests for the
+/// transactions which were not prefetched result in an error.
+pub struct EsploraAsyncResolver {
+    client: AsyncClient,
+    genesis_hash: Option<String>,
+    witnesses: HashMap<Txid, (Option<Tx>, WitnessOrd)>,
+    blocks: HashMap<u32, BlockHash>,
+    tip_height: Option<u32>,
+}
+
+impl EsploraAsyncResolver {
+    #[allow(clippy::result_large_err)]
+    pub fn new(url: &str, config: Config) -> Result<Self, Error> {
+        Ok(EsploraAsyncResolver {
+            client: AsyncClient::from_config(url, config)?,
+            genesis_hash: None,
+            witnesses: empty!(),
+            blocks: empty!(),
+            tip_height: None,
+        })
+    }
+
+    /// Constructs resolver using the provided connection options.
+    ///
+    /// The SOCKS5 proxy is ignored when targeting `wasm32`, where the
+    /// requests are performed by the browser.
+    pub fn with_opts(url: &str, opts: &ConnectionOpts) -> Result<Self, String> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in opts.http_headers() {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| format!("invalid HTTP header name '{name}': {e}"))?;
+            let value = HeaderValue::from_str(&value)
+                .map_err(|e| format!("invalid value of HTTP header '{name}': {e}"))?;
+            headers.insert(name, value);
+        }
+        #[allow(unused_mut)]
+        let mut builder = reqwest::Client::builder().default_headers(headers);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            builder = builder.timeout(opts.resolver.timeout);
+            if let Some(proxy) = opts.esplora_config().proxy {
+                builder = builder.proxy(reqwest::Proxy::all(proxy).map_err(|e| e.to_string())?);
+            }
+        }
+        let client = builder.build().map_err(|e| e.to_string())?;
+        Ok(EsploraAsyncResolver {
+            client: AsyncClient::from_client(url.to_owned(), client),
+            genesis_hash: None,
+            witnesses: empty!(),
+            blocks: empty!(),
+            tip_height: None,
+        })
+    }
+
+    /// Fetches genesis block hash from the indexer, which is later used by
+    /// [`RgbResolver::check`].
+    pub async fn connect(&mut self) -> Result<(), String> {
+        let block_hash = self.client.block_hash(0).await?;
+        self.genesis_hash = Some(block_hash.to_string());
+        Ok(())
+    }
+
+    /// Fetches witness transactions and their mining status from the indexer
+    /// and caches them for use in the synchronous validation.
+    pub async fn prefetch(&mut self, txids: impl IntoIterator<Item = Txid>) -> Result<(), String> {
+        for txid in txids {
+            if self.witnesses.contains_key(&txid) {
+                continue;
+            }
+            let Some(tx) = self.client.tx(&txid).await? else {
+                self.witnesses.insert(txid, (None, WitnessOrd::Archived));
+                continue;
+            };
+            let status = self.client.tx_status(&txid).await?;
+            let ord = match status
+                .block_height
+                .and_then(|h| status.block_time.map(|t| (h, t)))
+            {
+                Some((h, t)) => {
+                    let height = NonZeroU32::new(h).ok_or(Error::InvalidServerData)?;
+                    WitnessOrd::Mined(
+                        WitnessPos::bitcoin(height, t as i64).ok_or(Error::InvalidServerData)?,
+                    )
+                }
+                None => WitnessOrd::Tentative,
+            };
+            self.witnesses.insert(txid, (Some(tx), ord));
+        }
+        Ok(())
+    }
+
+    /// Fetches hashes of the main chain blocks at the given heights, which are
+    /// required for the re-org detection.
+    pub async fn prefetch_blocks(
+        &mut self,
+        heights: impl IntoIterator<Item = u32>,
+    ) -> Result<(), String> {
+        for height in heights {
+            let block_hash = self.client.block_hash(height).await?;
+            self.blocks.insert(height, block_hash);
+        }
+        Ok(())
+    }
+
+    /// Fetches height of the current main chain tip, which is required for
+    /// computing the number of witness confirmations.
+    pub async fn prefetch_tip(&mut self) -> Result<(), String> {
+        self.tip_height = Some(self.client.height().await?);
+        Ok(())
+    }
+
+    /// Prefetches all bitcoin witness transactions referenced by the
+    /// consignment.
+    pub async fn prefetch_consignment<const TYPE: bool>(
+        &mut self,
+        consignment: &Consignment<TYPE>,
+    ) -> Result<(), String> {
+        let txids = consignment
+            .bundles
+            .iter()
+            .filter_map(|bw| match bw.witness_id() {
+                XChain::Bitcoin(txid) => Some(txid),
+                XChain::Liquid(_) | XChain::Other(_) => None,
+            })
+            .collect::<Vec<_>>();
+        self.prefetch(txids).await
+    }
+}
+
+impl RgbResolver for EsploraAsyncResolver {
+    fn check(&self, _network: Network, expected_block_hash: String) -> Result<(), String> {
+        let Some(block_hash) = &self.genesis_hash else {
+            return Err(s!("resolver is not connected; call `connect` before using it"));
+        };
+        if &expected_block_hash != block_hash {
+            return Err(s!("resolver is for a network different from the wallet's one"));
+        }
+        Ok(())
+    }
+
+    fn resolve_pub_witness(&self, txid: Txid) -> Result<Option<Tx>, String> {
+        self.witnesses
+            .get(&txid)
+            .map(|(tx, _)| tx.clone())
+            .ok_or_else(|| format!("witness transaction {txid} was not prefetched"))
+    }
+
+    fn resolve_pub_witness_ord(&self, txid: Txid) -> Result<WitnessOrd, String> {
+        self.witnesses
+            .get(&txid)
+            .map(|(_, ord)| *ord)
+            .ok_or_else(|| format!("witness transaction {txid} was not prefetched"))
+    }
+
+    fn resolve_block_hash(&self, height: u32) -> Result<BlockHash, String> {
+        self.blocks
+            .get(&height)
+            .copied()
+            .ok_or_else(|| format!("block hash at height {height} was not prefetched"))
+    }
+
+    fn resolve_tip_height(&self) -> Result<u32, String> {
+        self.tip_height
+            .ok_or_else(|| s!("chain tip height was not prefetched"))
+    }
+}
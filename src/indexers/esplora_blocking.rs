@@ -20,16 +20,90 @@
 // limitations under the License.
 
 use std::num::NonZeroU32;
+use std::sync::Arc;
 
-use bp::Tx;
-use bpstd::{Network, Txid};
+use bp::{BlockHash, Tx};
+use bpstd::{Network, ScriptPubkey, Txid};
 use esplora::BlockingClient;
 pub use esplora::{Builder, Config, Error};
 use rgbstd::vm::WitnessPos;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
 
-use super::RgbResolver;
+use super::{ConnectionOpts, RgbResolver};
 use crate::vm::WitnessOrd;
 
+/// Constructs blocking esplora client using the provided connection options.
+#[allow(clippy::result_large_err)]
+pub fn blocking_client(url: &str, opts: &ConnectionOpts) -> Result<BlockingClient, Error> {
+    let mut agent = ureq::AgentBuilder::new().timeout(opts.resolver.timeout);
+    if let Some(proxy) = opts.esplora_config().proxy {
+        agent = agent.proxy(ureq::Proxy::new(proxy)?);
+    }
+    if opts.accept_invalid_certs {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let tls_config = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .expect("default protocol versions are always supported")
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCert(provider)))
+            .with_no_client_auth();
+        agent = agent.tls_config(Arc::new(tls_config));
+    }
+    let headers = opts.http_headers();
+    if !headers.is_empty() {
+        agent = agent.middleware(move |mut request: ureq::Request, next: ureq::MiddlewareNext| {
+            for (name, value) in &headers {
+                request = request.set(name, value);
+            }
+            next.handle(request)
+        });
+    }
+    Ok(BlockingClient::from_agent(url.to_owned(), agent.build()))
+}
+
+/// Certificate verifier accepting any server certificate, while still
+/// checking the handshake signatures.
+#[derive(Debug)]
+struct AcceptAnyCert(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
 impl RgbResolver for BlockingClient {
     fn check(&self, _network: Network, expected_block_hash: String) -> Result<(), String> {
         // check the esplora server is for the correct network
@@ -66,4 +140,41 @@ impl RgbResolver for BlockingClient {
             e => Err(e.to_string()),
         })
     }
+
+    fn resolve_block_hash(&self, height: u32) -> Result<BlockHash, String> {
+        Ok(self.block_hash(height)?)
+    }
+
+    fn resolve_tip_height(&self) -> Result<u32, String> { Ok(self.height()?) }
+
+    fn resolve_script_txids(&self, script: &ScriptPubkey) -> Result<Vec<Txid>, String> {
+        // The first page contains all mempool transactions followed by the
+        // most recent confirmed ones
+        Ok(self
+            .scripthash_txs(script, None)?
+            .into_iter()
+            .map(|tx| tx.txid)
+            .collect())
+    }
+
+    fn broadcast_package(&self, txs: &[Tx]) -> Result<(), String> {
+        // Esplora relays packages with `submitpackage` RPC of the bitcoin node
+        let package = txs
+            .iter()
+            .map(|tx| format!("\"{tx:x}\""))
+            .collect::<Vec<_>>()
+            .join(",");
+        match self
+            .agent()
+            .post(&format!("{}/txs/package", self.url()))
+            .send_string(&format!("[{package}]"))
+        {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(code, resp)) => Err(format!(
+                "package is rejected by the indexer with HTTP status {code}: {}",
+                resp.into_string().unwrap_or_default()
+            )),
+            Err(e) => Err(e.to_string()),
+        }
+    }
 }
@@ -19,8 +19,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use bp::Tx;
-use bpstd::{Network, Txid};
+use bp::{BlockHash, Tx};
+use bpstd::{Network, ScriptPubkey, Txid};
 use esplora::{BlockingClient, Config, Error};
 use rgbstd::vm::WitnessOrd;
 
@@ -53,6 +53,10 @@ impl MemPoolClient {
         let inner = BlockingClient::from_config(url, config)?;
         Ok(MemPoolClient { inner })
     }
+
+    /// Creates a new `MemPoolClient` instance wrapping already configured
+    /// esplora client.
+    pub fn with_client(inner: BlockingClient) -> Self { MemPoolClient { inner } }
 }
 
 impl RgbResolver for MemPoolClient {
@@ -67,6 +71,20 @@ impl RgbResolver for MemPoolClient {
     fn resolve_pub_witness(&self, txid: Txid) -> Result<Option<Tx>, String> {
         self.inner.resolve_pub_witness(txid)
     }
+
+    fn resolve_block_hash(&self, height: u32) -> Result<BlockHash, String> {
+        self.inner.resolve_block_hash(height)
+    }
+
+    fn resolve_tip_height(&self) -> Result<u32, String> { self.inner.resolve_tip_height() }
+
+    fn resolve_script_txids(&self, script: &ScriptPubkey) -> Result<Vec<Txid>, String> {
+        self.inner.resolve_script_txids(script)
+    }
+
+    fn broadcast_package(&self, txs: &[Tx]) -> Result<(), String> {
+        self.inner.broadcast_package(txs)
+    }
 }
 
 #[cfg(test)]
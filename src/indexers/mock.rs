@@ -0,0 +1,592 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deterministic in-memory blockchain for tests and documentation examples.
+//!
+//! [`MockChain`] is a cheaply clonable handle to a simulated chain, which
+//! acts both as an RGB resolver (see [`super::AnyResolver::mock`]) and as a
+//! bitcoin wallet indexer. Blocks are produced only on request, such that the
+//! number of confirmations and re-orgs are fully controlled by the test.
+
+use std::collections::{BTreeSet, HashMap};
+use std::num::NonZeroU32;
+use std::slice;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bp::{BlockHash, ConsensusEncode, Tx};
+use bpstd::{
+    Address, Descriptor, LockTime, Network, Outpoint, Sats, ScriptPubkey, SeqNo, SigScript, TxIn,
+    TxOut, TxVer, Txid, VarIntArray, Weight, Witness,
+};
+use bpwallet::{
+    Indexer, Layer2, MayError, MiningInfo, Party, TxCredit, TxDebit, TxStatus, WalletAddr,
+    WalletCache, WalletDescr, WalletTx,
+};
+
+use super::any::genesis_block_hash;
+use super::RgbResolver;
+use crate::vm::{WitnessOrd, WitnessPos};
+
+/// Timestamp of the bitcoin genesis block, used as the time of the mock
+/// chain genesis.
+const GENESIS_TIME: u64 = 1231006505;
+/// Timestamp of the Liquid genesis block, used as the time of the mock Liquid
+/// chain genesis.
+#[cfg(feature = "liquid")]
+const LIQUID_GENESIS_TIME: u64 = 1296692202;
+/// Interval between the timestamps of the mock blocks, in seconds.
+const BLOCK_INTERVAL: u64 = 600;
+/// Number of consecutive unused addresses after which the wallet scan of a
+/// keychain stops.
+const GAP_LIMIT: usize = 20;
+
+#[derive(Clone, Debug)]
+struct MockBlock {
+    hash: BlockHash,
+    time: u64,
+    txids: Vec<Txid>,
+}
+
+#[derive(Debug)]
+struct MockState {
+    network: Network,
+    /// Blocks of the main chain, starting with the genesis at height zero.
+    blocks: Vec<MockBlock>,
+    /// Transactions which are not mined yet, in the order of their arrival.
+    mempool: Vec<Txid>,
+    /// All transactions ever seen by the chain, including the dropped ones.
+    txes: HashMap<Txid, Tx>,
+    /// Number of the re-orgs happened, which makes hashes of the re-mined
+    /// blocks different from the original ones.
+    forks: u32,
+    /// Number of the funding transactions created so far.
+    funds: u32,
+    /// Minimal feerate (in sats per virtual byte) of the transactions and
+    /// packages accepted to the mempool.
+    min_feerate: u64,
+}
+
+impl MockState {
+    fn tip(&self) -> u32 { self.blocks.len() as u32 - 1 }
+
+    fn block_hash(&self, height: u32) -> BlockHash {
+        let mut hash = [0u8; 32];
+        hash[..4].copy_from_slice(&height.to_le_bytes());
+        hash[4..8].copy_from_slice(&self.forks.to_le_bytes());
+        hash[31] = 0xAC;
+        BlockHash::from(hash)
+    }
+
+    fn height(&self, txid: Txid) -> Option<u32> {
+        self.blocks
+            .iter()
+            .position(|block| block.txids.contains(&txid))
+            .map(|height| height as u32)
+    }
+
+    fn status(&self, txid: Txid) -> Option<TxStatus> {
+        if let Some(height) = self.height(txid) {
+            let block = &self.blocks[height as usize];
+            return Some(TxStatus::Mined(MiningInfo {
+                height: NonZeroU32::new(height).expect("genesis has no transactions"),
+                time: block.time,
+                block_hash: block.hash,
+            }));
+        }
+        self.mempool.contains(&txid).then_some(TxStatus::Mempool)
+    }
+
+    /// Iterates over transactions which are either mined in the main chain
+    /// or are present in the mempool, in the order of their inclusion.
+    fn active(&self) -> impl Iterator<Item = &Tx> {
+        self.blocks
+            .iter()
+            .flat_map(|block| &block.txids)
+            .chain(&self.mempool)
+            .map(|txid| &self.txes[txid])
+    }
+
+    /// Returns transaction input spending the output, in form of an outpoint
+    /// with input number in place of the output number.
+    fn spender(&self, outpoint: Outpoint) -> Option<Outpoint> {
+        self.active().find_map(|tx| {
+            tx.inputs()
+                .position(|txin| txin.prev_output == outpoint)
+                .map(|vin| Outpoint::new(tx.txid(), vin as u32))
+        })
+    }
+
+    fn prevout(&self, outpoint: Outpoint) -> Option<&TxOut> {
+        self.txes
+            .get(&outpoint.txid)?
+            .outputs
+            .get(outpoint.vout.to_usize())
+    }
+
+    fn party(&self, script: ScriptPubkey, owned: &HashMap<ScriptPubkey, WalletAddr>) -> Party {
+        if let Some(wallet_addr) = owned.get(&script) {
+            return Party::from_wallet_addr(wallet_addr);
+        }
+        match Address::with(&script, self.network) {
+            Ok(addr) => Party::Counterparty(addr),
+            Err(_) => Party::Unknown(script),
+        }
+    }
+}
+
+/// Handle to a deterministic in-memory blockchain.
+///
+/// All clones of the handle share the same chain, such that a test may keep
+/// one of them for mining blocks while the others are used as a resolver and
+/// a wallet indexer.
+///
+/// Since bitcoin wallet cache can't be constructed outside of the wallet
+/// library, wallets must be created with `Wallet::new_layer1` and then synced
+/// with `Wallet::update`; [`Indexer::create`] is not supported.
+#[derive(Clone, Debug)]
+pub struct MockChain(Arc<Mutex<MockState>>);
+
+impl MockChain {
+    /// Constructs chain for the network, consisting of the genesis block
+    /// only.
+    pub fn new(network: Network) -> Self {
+        Self::with_genesis(network, genesis_block_hash(network), GENESIS_TIME)
+    }
+
+    /// Constructs Liquid chain paired with the Bitcoin network, consisting of
+    /// the genesis block only. Liquid regtest chain has the genesis of the
+    /// Bitcoin regtest.
+    #[cfg(feature = "liquid")]
+    pub fn liquid(network: Network) -> Self {
+        let hash = super::any::liquid_genesis_block_hash(network)
+            .unwrap_or_else(|| genesis_block_hash(network));
+        Self::with_genesis(network, hash, LIQUID_GENESIS_TIME)
+    }
+
+    fn with_genesis(network: Network, hash: &str, time: u64) -> Self {
+        let genesis = MockBlock {
+            hash: BlockHash::from_str(hash).expect("hardcoded genesis block hash"),
+            time,
+            txids: vec![],
+        };
+        MockChain(Arc::new(Mutex::new(MockState {
+            network,
+            blocks: vec![genesis],
+            mempool: vec![],
+            txes: empty!(),
+            forks: 0,
+            funds: 0,
+            min_feerate: 0,
+        })))
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns network of the chain.
+    pub fn network(&self) -> Network { self.state().network }
+
+    /// Returns height of the chain tip.
+    pub fn tip_height(&self) -> u32 { self.state().tip() }
+
+    /// Returns number of confirmations of the transaction, which is zero for
+    /// transactions in the mempool, or `None` if the transaction is neither
+    /// mined nor present in the mempool.
+    pub fn confirmations(&self, txid: Txid) -> Option<u32> {
+        let state = self.state();
+        match state.height(txid) {
+            Some(height) => Some(state.tip() - height + 1),
+            None => state.mempool.contains(&txid).then_some(0),
+        }
+    }
+
+    /// Adds to the mempool a transaction paying `value` to the script, which
+    /// doesn't spend any existing outputs, returning the funded outpoint.
+    pub fn fund(&self, script: &ScriptPubkey, value: Sats) -> Outpoint {
+        let mut state = self.state();
+        state.funds += 1;
+        let mut nonce = state.funds.to_le_bytes().to_vec();
+        nonce.insert(0, 4);
+        let tx = Tx {
+            version: TxVer::V2,
+            inputs: VarIntArray::from_checked(vec![TxIn {
+                prev_output: Outpoint::coinbase(),
+                sig_script: SigScript::from_unsafe(nonce),
+                sequence: SeqNo::from_consensus_u32(u32::MAX),
+                witness: Witness::new(),
+            }]),
+            outputs: VarIntArray::from_checked(vec![TxOut::new(script.clone(), value)]),
+            lock_time: LockTime::ZERO,
+        };
+        let txid = tx.txid();
+        state.txes.insert(txid, tx);
+        state.mempool.push(txid);
+        Outpoint::new(txid, 0)
+    }
+
+    /// Sets minimal feerate (in sats per virtual byte) of the transactions
+    /// accepted to the mempool; transactions paying less are accepted only
+    /// as a part of a package which reaches the feerate.
+    pub fn set_min_feerate(&self, feerate: u64) { self.state().min_feerate = feerate; }
+
+    /// Adds transaction to the mempool.
+    ///
+    /// Errors if the transaction spends outputs unknown to the chain, or
+    /// outputs which are already spent by other mined or mempool
+    /// transactions, or if it pays a fee below the minimal feerate.
+    pub fn broadcast(&self, tx: &Tx) -> Result<(), String> {
+        self.broadcast_package(slice::from_ref(tx))
+    }
+
+    /// Adds package of transactions to the mempool, where a transaction may
+    /// spend outputs of the preceding ones.
+    ///
+    /// The package is accepted atomically if each of its transactions spends
+    /// existing unspent outputs and the total fee of the transactions not
+    /// yet known to the chain reaches the minimal feerate for their total
+    /// size.
+    pub fn broadcast_package(&self, txs: &[Tx]) -> Result<(), String> {
+        let mut state = self.state();
+        let mut added = vec![];
+        let res = txs.iter().try_for_each(|tx| {
+            let txid = tx.txid();
+            if state.status(txid).is_some() {
+                return Ok(());
+            }
+            for txin in tx.inputs() {
+                let outpoint = txin.prev_output;
+                if state.prevout(outpoint).is_none() || state.status(outpoint.txid).is_none() {
+                    return Err(format!("transaction {txid} spends unknown output {outpoint}"));
+                }
+                if let Some(inpoint) = state.spender(outpoint) {
+                    return Err(format!(
+                        "transaction {txid} spends output {outpoint} already spent by {}",
+                        inpoint.txid
+                    ));
+                }
+            }
+            state.txes.insert(txid, tx.clone());
+            state.mempool.push(txid);
+            added.push(txid);
+            Ok(())
+        });
+
+        let res = res.and_then(|_| {
+            let mut fee = Sats::ZERO;
+            let mut vsize = 0u64;
+            for txid in &added {
+                let tx = &state.txes[txid];
+                let input_value = tx
+                    .inputs()
+                    .filter_map(|txin| state.prevout(txin.prev_output))
+                    .map(|txout| txout.value)
+                    .sum::<Sats>();
+                let output_value = tx.outputs().map(|txout| txout.value).sum::<Sats>();
+                fee.saturating_add_assign(input_value.saturating_sub(output_value));
+                vsize += tx.vbytes().to_u32() as u64;
+            }
+            if fee.sats() < state.min_feerate * vsize {
+                return Err(format!(
+                    "transactions pay {fee} sats for {vsize} vbytes, which is below the minimal \
+                     feerate of {} sats per vbyte",
+                    state.min_feerate
+                ));
+            }
+            Ok(())
+        });
+
+        if res.is_err() {
+            state.mempool.retain(|txid| !added.contains(txid));
+            for txid in &added {
+                state.txes.remove(txid);
+            }
+        }
+        res
+    }
+
+    /// Mines `count` blocks, the first of which includes all mempool
+    /// transactions. Returns the new tip height.
+    pub fn mine(&self, count: u32) -> u32 {
+        let mut state = self.state();
+        let genesis_time = state.blocks[0].time;
+        for _ in 0..count {
+            let height = state.tip() + 1;
+            let block = MockBlock {
+                hash: state.block_hash(height),
+                time: genesis_time + height as u64 * BLOCK_INTERVAL,
+                txids: std::mem::take(&mut state.mempool),
+            };
+            state.blocks.push(block);
+        }
+        state.tip()
+    }
+
+    /// Disconnects `depth` blocks from the chain tip, returning transactions
+    /// mined in them back to the mempool.
+    ///
+    /// Blocks mined afterwards get hashes different from the disconnected
+    /// ones. Returns ids of the transactions returned to the mempool.
+    pub fn reorg(&self, depth: u32) -> Vec<Txid> {
+        let mut state = self.state();
+        assert!(depth <= state.tip(), "re-org can't disconnect the genesis block");
+        let fork = state.blocks.len() - depth as usize;
+        let txids = state
+            .blocks
+            .drain(fork..)
+            .flat_map(|block| block.txids)
+            .collect::<Vec<_>>();
+        state.forks += 1;
+        let mempool = std::mem::replace(&mut state.mempool, txids.clone());
+        state.mempool.extend(mempool);
+        txids
+    }
+
+    /// Removes transaction from the mempool, like if it was evicted or
+    /// replaced, together with all mempool transactions spending its outputs.
+    ///
+    /// Returns ids of the removed transactions; mined transactions are not
+    /// affected.
+    pub fn drop_tx(&self, txid: Txid) -> Vec<Txid> {
+        let mut state = self.state();
+        let mut dropped = vec![];
+        let mut queue = vec![txid];
+        while let Some(txid) = queue.pop() {
+            let Some(pos) = state.mempool.iter().position(|id| *id == txid) else {
+                continue;
+            };
+            state.mempool.remove(pos);
+            dropped.push(txid);
+            queue.extend(
+                state
+                    .mempool
+                    .iter()
+                    .filter(|id| {
+                        state.txes[*id]
+                            .inputs()
+                            .any(|txin| txin.prev_output.txid == txid)
+                    })
+                    .copied(),
+            );
+        }
+        dropped
+    }
+}
+
+impl RgbResolver for MockChain {
+    fn check(&self, network: Network, expected_block_hash: String) -> Result<(), String> {
+        let state = self.state();
+        if state.network != network || state.blocks[0].hash.to_string() != expected_block_hash {
+            return Err(s!("resolver is for a network different from the wallet's one"));
+        }
+        Ok(())
+    }
+
+    fn resolve_pub_witness(&self, txid: Txid) -> Result<Option<Tx>, String> {
+        let state = self.state();
+        Ok(state
+            .status(txid)
+            .and_then(|_| state.txes.get(&txid))
+            .cloned())
+    }
+
+    fn resolve_pub_witness_ord(&self, txid: Txid) -> Result<WitnessOrd, String> {
+        let state = self.state();
+        Ok(match state.status(txid) {
+            Some(TxStatus::Mined(info)) => WitnessOrd::Mined(
+                WitnessPos::bitcoin(info.height, info.time as i64)
+                    .expect("mock block timestamps are after the genesis"),
+            ),
+            Some(_) => WitnessOrd::Tentative,
+            None => WitnessOrd::Archived,
+        })
+    }
+
+    fn resolve_block_hash(&self, height: u32) -> Result<BlockHash, String> {
+        self.state()
+            .blocks
+            .get(height as usize)
+            .map(|block| block.hash)
+            .ok_or_else(|| format!("block at height {height} is not known"))
+    }
+
+    fn resolve_tip_height(&self) -> Result<u32, String> { Ok(self.tip_height()) }
+
+    fn broadcast_package(&self, txs: &[Tx]) -> Result<(), String> {
+        MockChain::broadcast_package(self, txs)
+    }
+
+    fn resolve_script_txids(&self, script: &ScriptPubkey) -> Result<Vec<Txid>, String> {
+        let state = self.state();
+        Ok(state
+            .active()
+            .filter(|tx| {
+                tx.outputs().any(|txout| &txout.script_pubkey == script)
+                    || tx.inputs().any(|txin| {
+                        state
+                            .prevout(txin.prev_output)
+                            .is_some_and(|prevout| &prevout.script_pubkey == script)
+                    })
+            })
+            .map(Tx::txid)
+            .collect())
+    }
+}
+
+impl Indexer for MockChain {
+    type Error = String;
+
+    fn create<K, D: Descriptor<K>, L2: Layer2>(
+        &self,
+        _descr: &WalletDescr<K, D, L2::Descr>,
+    ) -> MayError<WalletCache<L2::Cache>, Vec<Self::Error>> {
+        unimplemented!("wallet cache must be created with `Wallet::new_layer1`")
+    }
+
+    /// Re-builds the wallet cache from scratch, such that transactions
+    /// disconnected by re-orgs or dropped from the mempool are removed from
+    /// the wallet. Returns number of the wallet transactions.
+    fn update<K, D: Descriptor<K>, L2: Layer2>(
+        &self,
+        descriptor: &WalletDescr<K, D, L2::Descr>,
+        cache: &mut WalletCache<L2::Cache>,
+    ) -> MayError<usize, Vec<Self::Error>> {
+        let state = self.state();
+
+        let mut owned = HashMap::new();
+        let mut keychains = vec![];
+        for keychain in descriptor.keychains() {
+            let mut empty_count = 0usize;
+            let mut scripts = vec![];
+            for derive in descriptor.addresses(keychain) {
+                let script = derive.addr.script_pubkey();
+                let used = state
+                    .active()
+                    .any(|tx| tx.outputs().any(|txout| txout.script_pubkey == script));
+                if used {
+                    empty_count = 0;
+                } else {
+                    empty_count += 1;
+                    if empty_count >= GAP_LIMIT {
+                        break;
+                    }
+                }
+                owned.insert(script.clone(), WalletAddr::<Sats>::from(derive));
+                scripts.push(script);
+            }
+            keychains.push((keychain, scripts));
+        }
+
+        cache.tx.clear();
+        cache.utxo.clear();
+        for tx in state.active() {
+            let txid = tx.txid();
+            let inputs = tx
+                .inputs()
+                .map(|txin| {
+                    let prevout = state.prevout(txin.prev_output);
+                    TxCredit {
+                        outpoint: txin.prev_output,
+                        payer: prevout.map_or(Party::Subsidy, |prevout| {
+                            state.party(prevout.script_pubkey.clone(), &owned)
+                        }),
+                        sequence: txin.sequence,
+                        coinbase: txin.prev_output.is_coinbase(),
+                        script_sig: txin.sig_script.clone(),
+                        witness: txin.witness.clone(),
+                        value: prevout.map(|prevout| prevout.value).unwrap_or_default(),
+                    }
+                })
+                .collect::<Vec<_>>();
+            let outputs = tx
+                .outputs()
+                .enumerate()
+                .map(|(vout, txout)| {
+                    let outpoint = Outpoint::new(txid, vout as u32);
+                    TxDebit {
+                        outpoint,
+                        beneficiary: state.party(txout.script_pubkey.clone(), &owned),
+                        value: txout.value,
+                        spent: state.spender(outpoint).map(Into::into),
+                    }
+                })
+                .collect::<Vec<_>>();
+            if !inputs.iter().any(TxCredit::is_ourself) && !outputs.iter().any(TxDebit::is_ourself)
+            {
+                continue;
+            }
+
+            for debit in outputs.iter().filter(|debit| debit.is_ourself()) {
+                let script = tx.outputs[debit.outpoint.vout.to_usize()]
+                    .script_pubkey
+                    .clone();
+                let wallet_addr = owned.get_mut(&script).expect("wallet output");
+                wallet_addr.used = wallet_addr.used.saturating_add(1);
+                wallet_addr.volume.saturating_add_assign(debit.value);
+                if debit.spent.is_none() {
+                    wallet_addr.balance.saturating_add_assign(debit.value);
+                    cache.utxo.insert(debit.outpoint);
+                }
+            }
+
+            let input_value = inputs.iter().map(|credit| credit.value).sum::<Sats>();
+            let output_value = outputs.iter().map(|debit| debit.value).sum::<Sats>();
+            cache.tx.insert(txid, WalletTx {
+                txid,
+                status: state.status(txid).expect("active transaction"),
+                inputs,
+                outputs,
+                fee: input_value.saturating_sub(output_value),
+                size: tx.consensus_serialize().len() as u32,
+                weight: tx.weight_units().to_u32(),
+                version: tx.version,
+                locktime: tx.lock_time,
+            });
+        }
+
+        cache.addr.clear();
+        for (keychain, scripts) in keychains {
+            cache.addr.insert(
+                keychain,
+                scripts
+                    .iter()
+                    .map(|script| owned[script])
+                    .collect::<BTreeSet<_>>(),
+            );
+        }
+        if let Some(block) = state.blocks.last() {
+            if let Some(height) = NonZeroU32::new(state.tip()) {
+                cache.last_block = MiningInfo {
+                    height,
+                    time: block.time,
+                    block_hash: block.hash,
+                };
+            }
+        }
+
+        MayError::ok(cache.tx.len())
+    }
+
+    fn publish(&self, tx: &Tx) -> Result<(), Self::Error> { self.broadcast(tx) }
+}
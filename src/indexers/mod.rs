@@ -19,13 +19,153 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use bpstd::Network;
+
 mod any;
+#[cfg(feature = "fs")]
+mod cache;
+#[cfg(feature = "fs")]
+mod checkpoint;
+#[cfg(feature = "testing")]
+mod mock;
+#[cfg(feature = "esplora_blocking")]
+mod retry;
 #[cfg(feature = "esplora_blocking")]
 pub mod esplora_blocking;
+#[cfg(feature = "esplora_async")]
+pub mod esplora_async;
 #[cfg(feature = "electrum_blocking")]
 pub mod electrum_blocking;
 
 #[cfg(feature = "mempool_blocking")]
 pub mod mempool_blocking;
 
+pub(crate) use any::genesis_block_hash;
 pub use any::{AnyResolver, RgbResolver};
+#[cfg(feature = "fs")]
+pub use cache::{CachingResolver, DEFAULT_REORG_DEPTH};
+#[cfg(feature = "fs")]
+pub use checkpoint::{checkpoint_path, CheckpointResolver, CHECKPOINT_DIR};
+#[cfg(feature = "testing")]
+pub use mock::MockChain;
+#[cfg(feature = "esplora_blocking")]
+pub use retry::{RetryingResolver, MAX_RETRY_BACKOFF};
+
+/// Default timeout for the requests to the indexer servers, in seconds.
+pub const DEFAULT_RESOLVER_TIMEOUT: u64 = 30;
+/// Default number of retries of the failed requests to the indexer servers.
+pub const DEFAULT_RESOLVER_RETRIES: u8 = 3;
+/// Default delay before the first retry of a failed request, in milliseconds.
+pub const DEFAULT_RESOLVER_BACKOFF: u64 = 500;
+
+/// Substitutes the `{network}` placeholder in an esplora or mempool server URL
+/// template, like `https://mempool.space/{network}/api`, with the path
+/// segment used by the public instances for the given network.
+///
+/// Mainnet instances are served from the root path, so the placeholder is
+/// removed together with its leading slash; testnet3 is served under
+/// `testnet`, while testnet4, signet and regtest use their own names.
+pub fn indexer_url(template: &str, network: Network) -> String {
+    let segment = match network {
+        Network::Mainnet => "",
+        Network::Testnet3 => "testnet",
+        Network::Testnet4 => "testnet4",
+        Network::Signet => "signet",
+        Network::Regtest => "regtest",
+    };
+    let url =
+        if segment.is_empty() { template.replace("/{network}", "") } else { template.to_owned() };
+    url.replace("{network}", segment)
+}
+
+/// Timeout and retry policy of the requests to the indexer servers.
+///
+/// Failed requests are retried with an exponential backoff: the delay before
+/// the first retry is [`ResolverConfig::backoff`], and it is doubled with
+/// each next one. Electrum resolvers also re-establish the connection before
+/// each retry, while esplora resolvers reuse pooled HTTP connections.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct ResolverConfig {
+    /// Timeout of a single request.
+    pub timeout: Duration,
+
+    /// Number of retries of a failed request; zero disables retrying.
+    pub retries: u8,
+
+    /// Delay before the first retry of a failed request.
+    pub backoff: Duration,
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        ResolverConfig {
+            timeout: Duration::from_secs(DEFAULT_RESOLVER_TIMEOUT),
+            retries: DEFAULT_RESOLVER_RETRIES,
+            backoff: Duration::from_millis(DEFAULT_RESOLVER_BACKOFF),
+        }
+    }
+}
+
+/// Options for connecting to the indexer servers, allowing to sync over Tor
+/// (or another SOCKS5 proxy) and to use servers with self-signed TLS
+/// certificates.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct ConnectionOpts {
+    /// Address of SOCKS5 proxy in `host:port` form, like `127.0.0.1:9050` for
+    /// a local Tor daemon.
+    pub socks5: Option<String>,
+
+    /// Accept TLS certificates which can't be verified, including
+    /// self-signed ones.
+    pub accept_invalid_certs: bool,
+
+    /// HTTP headers added to each request to esplora and mempool servers,
+    /// like API keys required by hosted indexer instances.
+    pub headers: BTreeMap<String, String>,
+
+    /// Token sent to esplora and mempool servers in the `Authorization:
+    /// Bearer` HTTP header.
+    pub bearer_token: Option<String>,
+
+    /// Timeout and retry policy of the requests.
+    pub resolver: ResolverConfig,
+}
+
+impl ConnectionOpts {
+    #[cfg(feature = "electrum_blocking")]
+    pub fn electrum_config(&self) -> electrum::Config {
+        let timeout = self.resolver.timeout.as_secs().clamp(1, u8::MAX as u64) as u8;
+        electrum::ConfigBuilder::new()
+            .socks5(self.socks5.as_ref().map(electrum::Socks5Config::new))
+            // Electrum client doesn't support timeouts for proxied connections
+            .timeout(if self.socks5.is_none() { Some(timeout) } else { None })
+            .retry(self.resolver.retries)
+            .validate_domain(!self.accept_invalid_certs)
+            .build()
+    }
+
+    /// Returns HTTP headers for the requests to esplora and mempool servers,
+    /// including the authorization header for the bearer token.
+    pub fn http_headers(&self) -> Vec<(String, String)> {
+        let mut headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        if let Some(token) = &self.bearer_token {
+            headers.push((s!("Authorization"), format!("Bearer {token}")));
+        }
+        headers
+    }
+
+    #[cfg(any(feature = "esplora_blocking", feature = "esplora_async"))]
+    pub fn esplora_config(&self) -> esplora::Config {
+        esplora::Config {
+            proxy: self.socks5.as_ref().map(|addr| format!("socks5://{addr}")),
+            timeout: Some(self.resolver.timeout.as_secs()),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Resolver wrapper retrying failed requests to the indexer with an
+//! exponential backoff, such that long-running services survive transient
+//! indexer connectivity problems.
+
+use std::thread;
+use std::time::Duration;
+
+use bp::{BlockHash, Tx};
+use bpstd::{Network, ScriptPubkey};
+
+use super::{ResolverConfig, RgbResolver};
+use crate::vm::WitnessOrd;
+use crate::Txid;
+
+/// Maximal delay between two attempts of the same request.
+pub const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Resolver wrapper retrying failed requests according to the
+/// [`ResolverConfig`].
+///
+/// The network check is never retried, since its failure usually indicates
+/// a misconfiguration rather than a transient problem.
+pub struct RetryingResolver<R: RgbResolver> {
+    inner: R,
+    config: ResolverConfig,
+}
+
+impl<R: RgbResolver> RetryingResolver<R> {
+    pub fn new(inner: R, config: ResolverConfig) -> Self { Self { inner, config } }
+
+    pub fn config(&self) -> &ResolverConfig { &self.config }
+
+    fn retry<T>(&self, f: impl Fn(&R) -> Result<T, String>) -> Result<T, String> {
+        let mut backoff = self.config.backoff;
+        let mut attempt = 0u8;
+        loop {
+            match f(&self.inner) {
+                Ok(val) => return Ok(val),
+                Err(err) if attempt >= self.config.retries => {
+                    return Err(if attempt > 0 {
+                        format!("{err} (failed after {} attempts)", attempt + 1)
+                    } else {
+                        err
+                    });
+                }
+                Err(_) => {
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+impl<R: RgbResolver> RgbResolver for RetryingResolver<R> {
+    fn check(&self, network: Network, expected_block_hash: String) -> Result<(), String> {
+        self.inner.check(network, expected_block_hash)
+    }
+
+    fn resolve_pub_witness(&self, txid: Txid) -> Result<Option<Tx>, String> {
+        self.retry(|inner| inner.resolve_pub_witness(txid))
+    }
+
+    fn resolve_pub_witness_ord(&self, txid: Txid) -> Result<WitnessOrd, String> {
+        self.retry(|inner| inner.resolve_pub_witness_ord(txid))
+    }
+
+    fn resolve_block_hash(&self, height: u32) -> Result<BlockHash, String> {
+        self.retry(|inner| inner.resolve_block_hash(height))
+    }
+
+    fn resolve_tip_height(&self) -> Result<u32, String> {
+        self.retry(|inner| inner.resolve_tip_height())
+    }
+
+    fn subscribe_script(&self, script: &ScriptPubkey) -> Result<(), String> {
+        self.retry(|inner| inner.subscribe_script(script))
+    }
+
+    fn resolve_script_txids(&self, script: &ScriptPubkey) -> Result<Vec<Txid>, String> {
+        self.retry(|inner| inner.resolve_script_txids(script))
+    }
+
+    // Rejections of the package by the mempool are not transient, thus the
+    // broadcast is not retried
+    fn broadcast_package(&self, txs: &[Tx]) -> Result<(), String> {
+        self.inner.broadcast_package(txs)
+    }
+}
@@ -0,0 +1,273 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Portable interchange format of the stock, used to move the stock with all
+//! the auxiliary wallet data between machines and persistence backends.
+//!
+//! The export is a single file with named entries: the stash, state and index
+//! of the stock - which hold the contracts, the kits and the secret seals -
+//! the tapret tweaks of each wallet and the auxiliary files of the data
+//! directory, like the registry of the paid invoices or the address
+//! reservations. The stock components are kept in the same strict encoding
+//! as used by both file and SQLite persistence, thus an export made from one
+//! backend can be imported into the other.
+//!
+//! The file starts with [`STOCK_EXPORT_MAGIC`] and the format version, which
+//! are followed by the entries. Each entry carries the SHA256 checksum of its
+//! data, which is verified when the export is read.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::str::FromStr;
+
+use amplify::confinement::{Confined, U32 as U32MAX};
+use commit_verify::{Digest, Sha256};
+use nonasync::persistence::{PersistenceError, PersistenceProvider};
+use rgbstd::persistence::{MemIndex, MemStash, MemState, Stock};
+use strict_types::encoding::{StrictDeserialize, StrictSerialize};
+
+use crate::{StockExportError, TapretTweaks};
+
+/// Magic bytes starting each stock export file.
+pub const STOCK_EXPORT_MAGIC: &[u8; 8] = b"RGBSTOCK";
+
+/// Version of the stock export format produced by this software.
+pub const STOCK_EXPORT_VERSION: u16 = 1;
+
+const TWEAKS_PREFIX: &str = "tweaks/";
+const FILE_PREFIX: &str = "files/";
+const CHECKSUM_LEN: usize = 32;
+
+/// Stock component kept as a separate entry of the export.
+trait ExportComponent: StrictSerialize + StrictDeserialize {
+    const NAME: &'static str;
+}
+
+impl ExportComponent for MemStash {
+    const NAME: &'static str = "stash";
+}
+
+impl ExportComponent for MemState {
+    const NAME: &'static str = "state";
+}
+
+impl ExportComponent for MemIndex {
+    const NAME: &'static str = "index";
+}
+
+/// Stock together with the tapret tweaks of the wallets and the auxiliary
+/// files of the data directory, in a form which can be saved into a single
+/// portable file.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct StockExport {
+    entries: BTreeMap<String, Vec<u8>>,
+}
+
+impl StockExport {
+    pub fn new() -> Self { Self::default() }
+
+    /// Exports stash, state and index of the stock.
+    pub fn with_stock(stock: &Stock) -> Result<Self, StockExportError> {
+        let mut export = Self::new();
+        export.put_component(stock.as_stash_provider())?;
+        export.put_component(stock.as_state_provider())?;
+        export.put_component(stock.as_index_provider())?;
+        Ok(export)
+    }
+
+    /// Loads export from a file previously saved with
+    /// [`StockExport::save_file`], verifying the checksums of all entries.
+    #[cfg(feature = "fs")]
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self, StockExportError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn save_file(&self, path: impl AsRef<Path>) -> Result<(), StockExportError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Parses export, verifying the checksums of all entries.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, StockExportError> {
+        let mut reader = Reader(data);
+        if reader.take(STOCK_EXPORT_MAGIC.len()).ok() != Some(STOCK_EXPORT_MAGIC.as_slice()) {
+            return Err(StockExportError::NotExport);
+        }
+        let version = u16::from_le_bytes(reader.array()?);
+        if version != STOCK_EXPORT_VERSION {
+            return Err(StockExportError::UnsupportedVersion(version));
+        }
+        let count = u64::from_le_bytes(reader.array()?);
+        let mut entries = BTreeMap::new();
+        for _ in 0..count {
+            let name = String::from_utf8(reader.bytes()?.to_vec())
+                .map_err(|_| StockExportError::InvalidName)?;
+            let data = reader.bytes()?;
+            if Sha256::digest(data).as_slice() != reader.take(CHECKSUM_LEN)? {
+                return Err(StockExportError::Checksum(name));
+            }
+            if entries.contains_key(&name) {
+                return Err(StockExportError::RepeatedEntry(name));
+            }
+            entries.insert(name, data.to_vec());
+        }
+        if !reader.0.is_empty() {
+            return Err(StockExportError::TrailingData);
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(STOCK_EXPORT_MAGIC);
+        data.extend_from_slice(&STOCK_EXPORT_VERSION.to_le_bytes());
+        data.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+        for (name, entry) in &self.entries {
+            write_bytes(&mut data, name.as_bytes());
+            write_bytes(&mut data, entry);
+            data.extend_from_slice(&Sha256::digest(entry));
+        }
+        data
+    }
+
+    /// Constructs the stock from the exported stash, state and index.
+    ///
+    /// The returned stock is not persistent: before it can be saved, it has
+    /// to be given the provider of the target backend with
+    /// [`Stock::make_persistent`].
+    pub fn stock(&self) -> Result<Stock, StockExportError> {
+        let mut components = StockExport::new();
+        for name in [MemStash::NAME, MemState::NAME, MemIndex::NAME] {
+            let data = self
+                .entries
+                .get(name)
+                .ok_or(StockExportError::Missing(name))?;
+            components.entries.insert(name.to_owned(), data.clone());
+        }
+        Stock::load(ExportedStock(components), false).map_err(StockExportError::Stock)
+    }
+
+    /// Adds tapret tweaks of the wallet, replacing the ones added before.
+    pub fn set_tapret_tweaks(&mut self, wallet: &str, tweaks: &TapretTweaks) {
+        self.entries
+            .insert(format!("{TWEAKS_PREFIX}{wallet}"), tweaks.to_string().into_bytes());
+    }
+
+    /// Returns tapret tweaks of each wallet contained in the export.
+    pub fn tapret_tweaks(&self) -> Result<BTreeMap<String, TapretTweaks>, StockExportError> {
+        self.entries
+            .iter()
+            .filter_map(|(name, data)| Some((name, name.strip_prefix(TWEAKS_PREFIX)?, data)))
+            .map(|(name, wallet, data)| {
+                let tweaks = std::str::from_utf8(data)
+                    .ok()
+                    .and_then(|s| TapretTweaks::from_str(s).ok())
+                    .ok_or_else(|| StockExportError::InvalidEntry(name.clone()))?;
+                Ok((wallet.to_owned(), tweaks))
+            })
+            .collect()
+    }
+
+    /// Adds auxiliary file of the data directory, replacing the file with the
+    /// same name added before.
+    pub fn add_file(&mut self, name: &str, data: Vec<u8>) {
+        self.entries.insert(format!("{FILE_PREFIX}{name}"), data);
+    }
+
+    /// Returns names and content of the auxiliary files contained in the
+    /// export.
+    pub fn files(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.entries
+            .iter()
+            .filter_map(|(name, data)| Some((name.strip_prefix(FILE_PREFIX)?, data.as_slice())))
+    }
+
+    fn put_component<T: ExportComponent>(&mut self, component: &T) -> Result<(), StockExportError> {
+        let data = component
+            .to_strict_serialized::<U32MAX>()
+            .map_err(|e| StockExportError::Encode(T::NAME, e))?;
+        self.entries.insert(T::NAME.to_owned(), data.release());
+        Ok(())
+    }
+
+    fn component<T: ExportComponent>(&self) -> Result<T, StockExportError> {
+        let data = self
+            .entries
+            .get(T::NAME)
+            .ok_or(StockExportError::Missing(T::NAME))?;
+        let data = Confined::<Vec<u8>, 0, U32MAX>::try_from(data.clone())
+            .map_err(|_| StockExportError::InvalidEntry(T::NAME.to_owned()))?;
+        T::from_strict_serialized(data).map_err(|e| StockExportError::Decode(T::NAME, e))
+    }
+}
+
+/// Read-only provider of the stock components contained in the export, used
+/// to construct the stock from them.
+#[derive(Clone, Debug)]
+struct ExportedStock(StockExport);
+
+impl<T: ExportComponent> PersistenceProvider<T> for ExportedStock {
+    fn load(&self) -> Result<T, PersistenceError> {
+        self.0.component().map_err(PersistenceError::with)
+    }
+
+    fn store(&self, _: &T) -> Result<(), PersistenceError> {
+        Err(PersistenceError::with(StockExportError::ReadOnly))
+    }
+}
+
+fn write_bytes(data: &mut Vec<u8>, bytes: &[u8]) {
+    data.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    data.extend_from_slice(bytes);
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], StockExportError> {
+        if self.0.len() < len {
+            return Err(StockExportError::Truncated);
+        }
+        let (data, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(data)
+    }
+
+    fn array<const LEN: usize>(&mut self) -> Result<[u8; LEN], StockExportError> {
+        let mut array = [0u8; LEN];
+        array.copy_from_slice(self.take(LEN)?);
+        Ok(array)
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], StockExportError> {
+        let len = u64::from_le_bytes(self.array()?);
+        let len = usize::try_from(len).map_err(|_| StockExportError::Truncated)?;
+        self.take(len)
+    }
+}
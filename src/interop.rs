@@ -0,0 +1,283 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conversion of the RGB wallet descriptors from and into the standard output
+//! descriptors understood by other bitcoin wallets, like Bitcoin Core and
+//! Sparrow.
+//!
+//! RGB descriptors carry information which can't be expressed with the
+//! standard descriptors: the keychain layout and the tapret tweaks. Thus, the
+//! export produces a descriptor per keychain and, for taproot wallets, a
+//! `rawtr` descriptor per each tapret-tweaked output, such that watch-only
+//! wallets track the same addresses as the RGB wallet does.
+
+use amplify::hex::ToHex;
+use bpstd::{Derive, Keychain, Terminal, Wpkh, XpubDerivable};
+
+use crate::descriptor::{KeychainLayout, RgbKeychain, TapretKey};
+use crate::{DescriptorImportError, DescriptorRgb, RgbDescr};
+
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!\
+                             ^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+fn polymod(c: u64, val: u64) -> u64 {
+    let c0 = c >> 35;
+    let mut c = ((c & 0x7ffffffff) << 5) ^ val;
+    for (bit, gen) in [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd]
+        .into_iter()
+        .enumerate()
+    {
+        if c0 & (1 << bit) != 0 {
+            c ^= gen;
+        }
+    }
+    c
+}
+
+/// Computes BIP-380 checksum of the output descriptor, given without the
+/// checksum part. Returns `None` if the descriptor contains characters which
+/// are not allowed in descriptors.
+pub fn descriptor_checksum(descriptor: &str) -> Option<String> {
+    let mut c = 1u64;
+    let mut cls = 0u64;
+    let mut cls_count = 0u8;
+    for ch in descriptor.chars() {
+        let pos = INPUT_CHARSET.find(ch)? as u64;
+        c = polymod(c, pos & 31);
+        cls = cls * 3 + (pos >> 5);
+        cls_count += 1;
+        if cls_count == 3 {
+            c = polymod(c, cls);
+            cls = 0;
+            cls_count = 0;
+        }
+    }
+    if cls_count > 0 {
+        c = polymod(c, cls);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+    Some(
+        (0..8)
+            .map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char)
+            .collect(),
+    )
+}
+
+/// Appends BIP-380 checksum to the output descriptor.
+///
+/// # Panics
+///
+/// If the descriptor contains characters which are not allowed in
+/// descriptors.
+fn with_checksum(descriptor: String) -> String {
+    let checksum = descriptor_checksum(&descriptor).expect("invalid descriptor characters");
+    format!("{descriptor}#{checksum}")
+}
+
+/// Descriptor in the format of the Bitcoin Core `importdescriptors` RPC
+/// request.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct CoreDescriptor {
+    /// Output descriptor with the checksum.
+    pub desc: String,
+    /// Time of the creation of the oldest key, from which the blockchain is
+    /// rescanned; zero rescans the whole chain.
+    pub timestamp: u64,
+    /// Whether the descriptor is used to generate new addresses.
+    pub active: bool,
+    /// Whether the descriptor is used for the change addresses.
+    pub internal: bool,
+}
+
+impl RgbDescr {
+    fn std_key(&self) -> &XpubDerivable {
+        match self {
+            RgbDescr::Wpkh(d, _) => d.as_key(),
+            RgbDescr::TapretKey(d, _) => d.tr.as_internal_key(),
+        }
+    }
+
+    fn std_descriptor(&self, keychains: &[Keychain]) -> String {
+        let key = self.std_key();
+        let variant = key.variant().map(|v| format!("{v}/")).unwrap_or_default();
+        let keychains = match keychains {
+            [keychain] => keychain.to_string(),
+            keychains => format!(
+                "<{}>",
+                keychains
+                    .iter()
+                    .map(Keychain::to_string)
+                    .collect::<Vec<_>>()
+                    .join(";")
+            ),
+        };
+        let key = format!("{}/{variant}{keychains}/*", key.spec());
+        with_checksum(match self {
+            RgbDescr::Wpkh(..) => format!("wpkh({key})"),
+            RgbDescr::TapretKey(..) => format!("tr({key})"),
+        })
+    }
+
+    /// Returns standard output descriptor with the checksum for each of the
+    /// wallet keychains.
+    pub fn keychain_descriptors(&self) -> Vec<(Keychain, String)> {
+        self.keychains()
+            .into_iter()
+            .map(|keychain| (keychain, self.std_descriptor(&[keychain])))
+            .collect()
+    }
+
+    /// Returns multipath descriptor with the checksum for the external and
+    /// internal keychains of the wallet, in the form used by Sparrow and
+    /// other BIP-389 compatible wallets.
+    ///
+    /// The descriptor doesn't cover keychains holding RGB seals, preventing
+    /// such wallets from spending outputs with RGB state.
+    pub fn multipath_descriptor(&self) -> String {
+        let layout = self.keychain_layout();
+        self.std_descriptor(&[
+            layout.keychain(RgbKeychain::External),
+            layout.keychain(RgbKeychain::Internal),
+        ])
+    }
+
+    /// Returns `rawtr` descriptor with the checksum for each of the outputs
+    /// with tapret commitment, which can't be derived by other wallets.
+    pub fn tapret_descriptors(&self) -> Vec<(Terminal, String)> {
+        self.tapret_tweaks()
+            .keys()
+            .copied()
+            .map(|terminal| {
+                let script = self
+                    .derive(terminal.keychain, terminal.index)
+                    .to_script_pubkey();
+                let output_key = &script.as_slice()[2..];
+                (terminal, with_checksum(format!("rawtr({})", output_key.to_hex())))
+            })
+            .collect()
+    }
+
+    /// Returns descriptors for the Bitcoin Core `importdescriptors` request,
+    /// creating watch-only wallet tracking all the wallet addresses, including
+    /// the tapret-tweaked ones.
+    ///
+    /// Only the external and internal keychains are made active, such that
+    /// Bitcoin Core never generates addresses on the keychains used for RGB
+    /// seals.
+    pub fn core_descriptors(&self, timestamp: u64) -> Vec<CoreDescriptor> {
+        let layout = self.keychain_layout();
+        let external = layout.keychain(RgbKeychain::External);
+        let internal = layout.keychain(RgbKeychain::Internal);
+        let mut descriptors = self
+            .keychain_descriptors()
+            .into_iter()
+            .map(|(keychain, desc)| CoreDescriptor {
+                desc,
+                timestamp,
+                active: keychain == external || keychain == internal,
+                internal: keychain == internal,
+            })
+            .collect::<Vec<_>>();
+        descriptors.extend(
+            self.tapret_descriptors()
+                .into_iter()
+                .map(|(_, desc)| CoreDescriptor {
+                    desc,
+                    timestamp,
+                    active: false,
+                    internal: false,
+                }),
+        );
+        descriptors
+    }
+
+    /// Constructs RGB descriptor from the standard output descriptors, like
+    /// the ones exported by Bitcoin Core `listdescriptors` RPC or by Sparrow.
+    ///
+    /// All descriptors must be either `wpkh` or `tr` descriptors of the same
+    /// extended key; the checksums, if present, are verified. The keychains
+    /// of the descriptors are extended with the keychains of the `layout`, and
+    /// taproot descriptors are converted into the tapret descriptor with no
+    /// tweaks.
+    pub fn from_std_descriptors<'s>(
+        descriptors: impl IntoIterator<Item = &'s str>,
+        layout: KeychainLayout,
+    ) -> Result<Self, DescriptorImportError> {
+        let mut account = None::<(bool, XpubDerivable)>;
+        let mut keychains = layout.keychains();
+        for descriptor in descriptors {
+            let descriptor = descriptor.trim();
+            let descriptor = match descriptor.rsplit_once('#') {
+                Some((descriptor, checksum)) => {
+                    let expected = descriptor_checksum(descriptor).ok_or_else(|| {
+                        DescriptorImportError::InvalidCharacters(descriptor.to_owned())
+                    })?;
+                    if checksum != expected {
+                        return Err(DescriptorImportError::Checksum {
+                            expected,
+                            found: checksum.to_owned(),
+                        });
+                    }
+                    descriptor
+                }
+                None => descriptor,
+            };
+            let (taproot, key) = if let Some(key) = descriptor.strip_prefix("tr(") {
+                (true, key)
+            } else if let Some(key) = descriptor.strip_prefix("wpkh(") {
+                (false, key)
+            } else {
+                return Err(DescriptorImportError::Unsupported(descriptor.to_owned()));
+            };
+            let key = key
+                .strip_suffix(')')
+                .filter(|key| !key.contains([',', '(', ')']))
+                .ok_or_else(|| DescriptorImportError::Unsupported(descriptor.to_owned()))?
+                .replace('\'', "h");
+            let key = key.parse::<XpubDerivable>()?;
+            keychains.extend(key.keychains().to_set());
+            match &account {
+                None => account = Some((taproot, key)),
+                Some((t, k))
+                    if *t == taproot && k.xpub() == key.xpub() && k.origin() == key.origin() => {}
+                Some(_) => return Err(DescriptorImportError::Mismatch),
+            }
+        }
+        let (taproot, key) = account.ok_or(DescriptorImportError::Empty)?;
+        let key = XpubDerivable::try_custom(key.xpub(), key.origin().clone(), keychains)
+            .map_err(|_| DescriptorImportError::TooManyKeychains)?;
+        let descr = if taproot {
+            RgbDescr::from(TapretKey::from(key))
+        } else {
+            RgbDescr::from(Wpkh::from(key))
+        };
+        Ok(descr.with_keychain_layout(layout))
+    }
+}
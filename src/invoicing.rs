@@ -0,0 +1,193 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validation of invoices against the contract API known to the wallet, which
+//! prevents creating invoices the payer can't fulfill.
+
+use rgbstd::interface::{Iface, OwnedIface};
+use rgbstd::persistence::{IndexProvider, StashProvider, StateProvider, Stock};
+use rgbstd::stl::rgb_contract_stl;
+use strict_types::encoding::{FieldName, TypeName};
+
+use crate::invoice::{InvoiceState, NonFungible, RgbInvoice, RgbInvoiceBuilder};
+use crate::InvoiceApiError;
+
+/// Validation of an invoice against the interface of the contract.
+pub trait InvoiceValidation {
+    /// Checks that the operation and the assignment of the invoice, or the
+    /// interface defaults used in their absence, are defined by the interface
+    /// and that the invoiced state matches the assignment state type.
+    fn validate_against(&self, iface: &Iface) -> Result<(), InvoiceApiError>;
+
+    /// Validates the invoice against the interface under which the invoiced
+    /// contract is known to the stock.
+    fn validate_in<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+    ) -> Result<(), InvoiceApiError>;
+}
+
+impl InvoiceValidation for RgbInvoice {
+    fn validate_against(&self, iface: &Iface) -> Result<(), InvoiceApiError> {
+        if let Some(name) = &self.iface {
+            if *name != iface.name {
+                return Err(InvoiceApiError::IfaceMismatch {
+                    invoice: name.to_string(),
+                    iface: iface.name.to_string(),
+                });
+            }
+        }
+
+        let operation = self
+            .operation
+            .as_ref()
+            .or(iface.default_operation.as_ref())
+            .ok_or_else(|| InvoiceApiError::NoOperation(iface.name.to_string()))?;
+        let transition = iface.transitions.get(operation).ok_or_else(|| {
+            InvoiceApiError::UnknownOperation(
+                operation.to_string(),
+                names(iface.transitions.keys()),
+            )
+        })?;
+
+        let assignment = self
+            .assignment
+            .as_ref()
+            .or(transition.default_assignment.as_ref())
+            .ok_or_else(|| InvoiceApiError::NoAssignment(operation.to_string()))?;
+        if !transition.assignments.contains_key(assignment) {
+            return Err(InvoiceApiError::UnknownAssignment {
+                operation: operation.to_string(),
+                assignment: assignment.to_string(),
+                defined: names(transition.assignments.keys()),
+            });
+        }
+        let owned_state = iface
+            .assignments
+            .get(assignment)
+            .map(|assign| assign.owned_state)
+            .ok_or_else(|| InvoiceApiError::UnknownAssignment {
+                operation: operation.to_string(),
+                assignment: assignment.to_string(),
+                defined: names(iface.assignments.keys()),
+            })?;
+
+        let matches = match (&self.owned_state, owned_state) {
+            // Invoices without amount let the payer choose it
+            (InvoiceState::Void, OwnedIface::Rights | OwnedIface::Amount) => true,
+            (InvoiceState::Void, _) => {
+                return Err(InvoiceApiError::MissingValue(assignment.to_string()));
+            }
+            (_, OwnedIface::Any) => true,
+            (InvoiceState::Amount(_), OwnedIface::Amount) => true,
+            (InvoiceState::Data(NonFungible::RGB21(_)), OwnedIface::AnyData) => true,
+            (InvoiceState::Data(NonFungible::RGB21(_)), OwnedIface::Data(sem_id)) => {
+                sem_id
+                    == rgb_contract_stl()
+                        .types
+                        .get(&TypeName::from("Allocation"))
+                        .expect("STL is broken")
+                        .sem_id_named(&TypeName::from("Allocation"))
+            }
+            (InvoiceState::Attach(_), OwnedIface::AnyAttach) => true,
+            _ => false,
+        };
+        if !matches {
+            return Err(InvoiceApiError::StateMismatch {
+                assignment: assignment.to_string(),
+                expected: state_kind(owned_state),
+                found: match self.owned_state {
+                    InvoiceState::Void => "no value",
+                    InvoiceState::Amount(_) => "an amount",
+                    InvoiceState::Data(_) => "a token allocation",
+                    InvoiceState::Attach(_) => "an attachment",
+                },
+            });
+        }
+        Ok(())
+    }
+
+    fn validate_in<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+    ) -> Result<(), InvoiceApiError> {
+        let contract_id = self.contract.ok_or(InvoiceApiError::NoContract)?;
+        let info = stock
+            .contract_info(contract_id)
+            .map_err(|_| InvoiceApiError::UnknownContract(contract_id))?;
+        let schema = stock
+            .schema(info.schema_id)
+            .map_err(|e| InvoiceApiError::Stock(e.to_string()))?;
+        let iface = match &self.iface {
+            Some(name) => stock
+                .iface(name.clone())
+                .map_err(|_| InvoiceApiError::UnknownIface(name.to_string()))?,
+            None => {
+                let mut ifaces = schema.iimpls.keys();
+                match (ifaces.next(), ifaces.next()) {
+                    (Some(name), None) => stock
+                        .iface(name.clone())
+                        .map_err(|e| InvoiceApiError::Stock(e.to_string()))?,
+                    _ => return Err(InvoiceApiError::NoIface),
+                }
+            }
+        };
+        if !schema.iimpls.contains_key(&iface.name) {
+            return Err(InvoiceApiError::NotImplemented {
+                iface: iface.name.to_string(),
+                contract: contract_id,
+            });
+        }
+        self.validate_against(iface)
+    }
+}
+
+/// Construction of invoices validated against the contract interface.
+pub trait ValidatedInvoiceBuilder {
+    /// Completes the invoice, checking it with
+    /// [`InvoiceValidation::validate_against`].
+    fn finish_validated(self, iface: &Iface) -> Result<RgbInvoice, InvoiceApiError>;
+}
+
+impl ValidatedInvoiceBuilder for RgbInvoiceBuilder {
+    fn finish_validated(self, iface: &Iface) -> Result<RgbInvoice, InvoiceApiError> {
+        let invoice = self.finish();
+        invoice.validate_against(iface)?;
+        Ok(invoice)
+    }
+}
+
+fn names<'a>(names: impl Iterator<Item = &'a FieldName>) -> String {
+    names
+        .map(FieldName::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn state_kind(owned_state: OwnedIface) -> &'static str {
+    match owned_state {
+        OwnedIface::Any => "any state",
+        OwnedIface::Rights => "a right without value",
+        OwnedIface::Amount => "an amount",
+        OwnedIface::AnyData | OwnedIface::Data(_) => "a token allocation",
+        OwnedIface::AnyAttach => "an attachment",
+    }
+}
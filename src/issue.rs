@@ -0,0 +1,633 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::BufRead;
+#[cfg(feature = "serde")]
+use std::io::Read;
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use amplify::confinement::U16 as MAX16;
+#[cfg(feature = "serde")]
+use rgbstd::containers::BuilderSeal;
+#[cfg(feature = "serde")]
+use rgbstd::interface::{
+    BuilderError, ContractBuilder, IfaceId, IfaceImpl, IfaceRef, TransitionBuilder,
+};
+use rgbstd::invoice::Amount;
+#[cfg(feature = "serde")]
+use rgbstd::persistence::{IndexProvider, SchemaIfaces, StashProvider, StateProvider, Stock};
+#[cfg(feature = "serde")]
+use rgbstd::schema::{OwnedStateSchema, SchemaId};
+use rgbstd::OutputSeal;
+#[cfg(feature = "serde")]
+use rgbstd::{AltLayer1, ExposedSeal, GenesisSeal, GraphSeal, Identity, Layer1, XChain};
+#[cfg(feature = "serde")]
+use strict_types::encoding::StrictSerialize;
+use strict_types::FieldName;
+#[cfg(feature = "serde")]
+use strict_types::{SemId, StrictVal, TypeName, TypeSystem};
+
+use crate::AllocationsError;
+#[cfg(feature = "serde")]
+use crate::{IssueError, IssueProblem};
+
+/// Header line of CSV allocation lists, which is skipped if present.
+pub const ALLOCATIONS_CSV_HEADER: &str = "assignment,seal,amount";
+
+/// Initial allocation of a fungible state, assigned to a seal in the contract
+/// genesis.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct InitialAllocation {
+    /// Name of the assignment in the interface.
+    pub assignment: FieldName,
+    pub seal: OutputSeal,
+    pub amount: Amount,
+}
+
+/// Streaming reader of initial allocations from a CSV list, allowing to issue
+/// contracts with large number of allocations (like for airdrops at genesis)
+/// without loading the whole list into memory.
+///
+/// Each line of the list must have `assignment,seal,amount` form. Empty lines,
+/// lines starting with `#` and the header line are skipped.
+pub struct AllocationsReader<R: BufRead> {
+    reader: R,
+    line_no: usize,
+    line: String,
+}
+
+impl<R: BufRead> AllocationsReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line_no: 0,
+            line: String::new(),
+        }
+    }
+
+    /// Returns number of the last line read from the list.
+    pub fn line_no(&self) -> usize { self.line_no }
+
+    fn parse_line(&self) -> Result<InitialAllocation, AllocationsError> {
+        let no = self.line_no;
+        let mut fields = self.line.trim().split(',').map(str::trim);
+        let (Some(assignment), Some(seal), Some(amount), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(AllocationsError::Format(no));
+        };
+        let assignment = FieldName::try_from(assignment.to_owned())
+            .map_err(|_| AllocationsError::InvalidAssignment(no, assignment.to_owned()))?;
+        let seal = OutputSeal::from_str(seal)
+            .map_err(|_| AllocationsError::InvalidSeal(no, seal.to_owned()))?;
+        let amount = amount
+            .parse::<u64>()
+            .map(Amount::from)
+            .map_err(|_| AllocationsError::InvalidAmount(no, amount.to_owned()))?;
+        Ok(InitialAllocation {
+            assignment,
+            seal,
+            amount,
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for AllocationsReader<R> {
+    type Item = Result<InitialAllocation, AllocationsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(err) => return Some(Err(err.into())),
+            }
+            self.line_no += 1;
+            let line = self.line.trim();
+            if line.is_empty() || line.starts_with('#') || line == ALLOCATIONS_CSV_HEADER {
+                continue;
+            }
+            return Some(self.parse_line());
+        }
+    }
+}
+
+/// Contract definition in YAML form, providing the interface name together
+/// with the global state and assignments of the contract genesis.
+///
+/// Unlike the contract builder, which stops on the first error, the
+/// definition is checked as a whole, such that [`IssueError`] reports all
+/// problems found in the definition at once.
+#[cfg(feature = "serde")]
+#[derive(Clone, PartialEq, Debug)]
+pub struct ContractDefinition(serde_yaml::Value);
+
+#[cfg(feature = "serde")]
+impl ContractDefinition {
+    pub fn new(yaml: serde_yaml::Value) -> Self { Self(yaml) }
+
+    /// Reads YAML contract definition.
+    pub fn from_reader(reader: impl Read) -> Result<Self, IssueError> {
+        serde_yaml::from_reader(reader)
+            .map(Self)
+            .map_err(|e| IssueProblem::Yaml(e.to_string()).into())
+    }
+
+    /// Returns name of the interface under which the contract is defined.
+    pub fn interface(&self) -> Result<&str, IssueError> {
+        let code = self
+            .0
+            .as_mapping()
+            .ok_or(IssueProblem::InvalidStructure(s!("contract definition"), "a mapping"))?;
+        let iface = code.get("interface").ok_or(IssueProblem::NoInterface)?;
+        Ok(iface
+            .as_str()
+            .ok_or(IssueProblem::InvalidStructure(s!("interface"), "a string"))?)
+    }
+
+    /// Returns layer 1 of the genesis seals, specified under the optional
+    /// `layer1` key of the definition. Defaults to Bitcoin; Liquid is
+    /// supported only if the library is compiled with the `liquid` feature.
+    pub fn layer1(&self) -> Result<Layer1, IssueError> {
+        let code = self
+            .0
+            .as_mapping()
+            .ok_or(IssueProblem::InvalidStructure(s!("contract definition"), "a mapping"))?;
+        match code.get("layer1").map(serde_yaml::Value::as_str) {
+            None | Some(Some("bitcoin")) => Ok(Layer1::Bitcoin),
+            #[cfg(feature = "liquid")]
+            Some(Some("liquid")) => Ok(Layer1::Liquid),
+            Some(Some(layer1)) => Err(IssueProblem::UnsupportedLayer1(layer1.to_owned()).into()),
+            Some(None) => Err(IssueProblem::InvalidStructure(s!("layer1"), "a string").into()),
+        }
+    }
+
+    /// Returns the schema and its implementation of the contract interface.
+    pub fn iface_impl<'stock, S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &'stock Stock<S, H, P>,
+        schema_id: SchemaId,
+    ) -> Result<(&'stock SchemaIfaces, &'stock IfaceImpl), IssueError> {
+        let iface_name = self.interface()?;
+        let schema_ifaces = stock
+            .schema(schema_id)
+            .map_err(|_| IssueProblem::UnknownSchema(schema_id))?;
+        let iface = TypeName::try_from(iface_name.to_owned())
+            .ok()
+            .and_then(|name| stock.iface(name).ok())
+            .or_else(|| {
+                let id = IfaceId::from_str(iface_name).ok()?;
+                stock.iface(id).ok()
+            })
+            .ok_or_else(|| IssueProblem::UnknownInterface(iface_name.to_owned()))?;
+        let iface_impl = schema_ifaces
+            .get(iface.iface_id())
+            .ok_or_else(|| IssueProblem::NoImplementation(iface_name.to_owned(), schema_id))?;
+        Ok((schema_ifaces, iface_impl))
+    }
+
+    /// Constructs contract builder with the state from the definition.
+    pub fn builder<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+        issuer: Identity,
+        schema_id: SchemaId,
+    ) -> Result<ContractBuilder, IssueError> {
+        let (schema_ifaces, iface_impl) = self.iface_impl(stock, schema_id)?;
+        let layer1 = self.layer1()?;
+        let mut builder = stock
+            .contract_builder(issuer, schema_id, IfaceRef::Id(iface_impl.iface_id))
+            .map_err(|e| IssueProblem::Builder(s!("genesis"), e.to_string()))?;
+        if layer1 == Layer1::Liquid {
+            builder = builder
+                .add_layer1(AltLayer1::Liquid)
+                .map_err(|e| IssueProblem::Builder(s!("layer1"), e.to_string()))?;
+        }
+        let code = self.0.as_mapping().expect("checked by interface method");
+
+        let mut ctx =
+            DefinitionContext::new(schema_ifaces, iface_impl, builder, None).with_layer1(layer1);
+        ctx.add_state(code);
+        ctx.complete().map_err(IssueError::from)
+    }
+}
+
+/// Builder of the contract operation which can be constructed from a YAML
+/// definition.
+#[cfg(feature = "serde")]
+pub(crate) trait StateBuilder: Clone {
+    type Seal: ExposedSeal;
+
+    fn type_system(&self) -> &TypeSystem;
+
+    fn explicit_seal(seal: OutputSeal, layer1: Layer1) -> BuilderSeal<Self::Seal>;
+
+    fn add_global_state(
+        self,
+        name: FieldName,
+        value: impl StrictSerialize,
+    ) -> Result<Self, BuilderError>;
+
+    fn add_rights(
+        self,
+        name: FieldName,
+        seal: BuilderSeal<Self::Seal>,
+    ) -> Result<Self, BuilderError>;
+
+    fn add_fungible_state(
+        self,
+        name: FieldName,
+        seal: BuilderSeal<Self::Seal>,
+        value: u64,
+    ) -> Result<Self, BuilderError>;
+
+    fn add_data(
+        self,
+        name: FieldName,
+        seal: BuilderSeal<Self::Seal>,
+        value: impl StrictSerialize,
+    ) -> Result<Self, BuilderError>;
+}
+
+#[cfg(feature = "serde")]
+impl StateBuilder for ContractBuilder {
+    type Seal = GenesisSeal;
+
+    fn type_system(&self) -> &TypeSystem { ContractBuilder::type_system(self) }
+
+    fn explicit_seal(seal: OutputSeal, layer1: Layer1) -> BuilderSeal<GenesisSeal> {
+        BuilderSeal::Revealed(XChain::with(
+            layer1,
+            GenesisSeal::new_random(seal.method, seal.txid, seal.vout),
+        ))
+    }
+
+    fn add_global_state(
+        self,
+        name: FieldName,
+        value: impl StrictSerialize,
+    ) -> Result<Self, BuilderError> {
+        ContractBuilder::add_global_state(self, name, value)
+    }
+
+    fn add_rights(
+        self,
+        name: FieldName,
+        seal: BuilderSeal<GenesisSeal>,
+    ) -> Result<Self, BuilderError> {
+        ContractBuilder::add_rights(self, name, seal)
+    }
+
+    fn add_fungible_state(
+        self,
+        name: FieldName,
+        seal: BuilderSeal<GenesisSeal>,
+        value: u64,
+    ) -> Result<Self, BuilderError> {
+        ContractBuilder::add_fungible_state(self, name, seal, value)
+    }
+
+    fn add_data(
+        self,
+        name: FieldName,
+        seal: BuilderSeal<GenesisSeal>,
+        value: impl StrictSerialize,
+    ) -> Result<Self, BuilderError> {
+        ContractBuilder::add_data(self, name, seal, value)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl StateBuilder for TransitionBuilder {
+    type Seal = GraphSeal;
+
+    fn type_system(&self) -> &TypeSystem { TransitionBuilder::type_system(self) }
+
+    fn explicit_seal(seal: OutputSeal, layer1: Layer1) -> BuilderSeal<GraphSeal> {
+        BuilderSeal::Revealed(XChain::with(
+            layer1,
+            GraphSeal::new_random(seal.method, seal.txid, seal.vout),
+        ))
+    }
+
+    fn add_global_state(
+        self,
+        name: FieldName,
+        value: impl StrictSerialize,
+    ) -> Result<Self, BuilderError> {
+        TransitionBuilder::add_global_state(self, name, value)
+    }
+
+    fn add_rights(
+        self,
+        name: FieldName,
+        seal: BuilderSeal<GraphSeal>,
+    ) -> Result<Self, BuilderError> {
+        TransitionBuilder::add_rights(self, name, seal)
+    }
+
+    fn add_fungible_state(
+        self,
+        name: FieldName,
+        seal: BuilderSeal<GraphSeal>,
+        value: u64,
+    ) -> Result<Self, BuilderError> {
+        TransitionBuilder::add_fungible_state(self, name, seal, value)
+    }
+
+    fn add_data(
+        self,
+        name: FieldName,
+        seal: BuilderSeal<GraphSeal>,
+        value: impl StrictSerialize,
+    ) -> Result<Self, BuilderError> {
+        TransitionBuilder::add_data(self, name, seal, value)
+    }
+}
+
+/// Context adding global state and assignments from a YAML definition to the
+/// operation builder, collecting all problems found in the definition.
+#[cfg(feature = "serde")]
+pub(crate) struct DefinitionContext<'stock, B: StateBuilder> {
+    schema_ifaces: &'stock SchemaIfaces,
+    iface_impl: &'stock IfaceImpl,
+    types: TypeSystem,
+    builder: B,
+    // Seal used for the assignments which don't specify one
+    default_seal: Option<BuilderSeal<B::Seal>>,
+    // Layer 1 of the explicit seals
+    layer1: Layer1,
+    problems: Vec<IssueProblem>,
+}
+
+#[cfg(feature = "serde")]
+impl<'stock, B: StateBuilder> DefinitionContext<'stock, B> {
+    pub fn new(
+        schema_ifaces: &'stock SchemaIfaces,
+        iface_impl: &'stock IfaceImpl,
+        builder: B,
+        default_seal: Option<BuilderSeal<B::Seal>>,
+    ) -> Self {
+        DefinitionContext {
+            schema_ifaces,
+            iface_impl,
+            types: builder.type_system().clone(),
+            builder,
+            default_seal,
+            layer1: Layer1::Bitcoin,
+            problems: vec![],
+        }
+    }
+
+    /// Sets layer 1 of the explicit seals given in the definition.
+    pub fn with_layer1(mut self, layer1: Layer1) -> Self {
+        self.layer1 = layer1;
+        self
+    }
+
+    /// Adds `globals` and `assignments` sections of the definition.
+    pub fn add_state(&mut self, code: &serde_yaml::Mapping) {
+        if let Some(globals) = code.get("globals") {
+            match globals.as_mapping() {
+                Some(globals) => {
+                    for (name, value) in globals {
+                        self.add_global(name, value);
+                    }
+                }
+                None => self.problem(IssueProblem::InvalidStructure(s!("globals"), "a mapping")),
+            }
+        }
+        if let Some(assignments) = code.get("assignments") {
+            match assignments.as_mapping() {
+                Some(assignments) => {
+                    for (name, value) in assignments {
+                        self.add_assignments(name, value);
+                    }
+                }
+                None => {
+                    self.problem(IssueProblem::InvalidStructure(s!("assignments"), "a mapping"))
+                }
+            }
+        }
+    }
+
+    /// Returns the builder, or all the problems found in the definition.
+    pub fn complete(self) -> Result<B, Vec<IssueProblem>> {
+        if !self.problems.is_empty() {
+            return Err(self.problems);
+        }
+        Ok(self.builder)
+    }
+
+    pub fn problem(&mut self, problem: IssueProblem) { self.problems.push(problem); }
+
+    fn name(&mut self, name: &serde_yaml::Value, what: &str) -> Option<FieldName> {
+        let Some(name) = name.as_str() else {
+            self.problem(IssueProblem::InvalidStructure(format!("{what} name"), "a string"));
+            return None;
+        };
+        match FieldName::try_from(name.to_owned()) {
+            Ok(name) => Some(name),
+            Err(e) => {
+                self.problem(IssueProblem::InvalidName(name.to_owned(), e.to_string()));
+                None
+            }
+        }
+    }
+
+    #[allow(deprecated)]
+    fn typify(
+        &self,
+        value: &serde_yaml::Value,
+        sem_id: SemId,
+    ) -> Result<impl StrictSerialize, String> {
+        let value = StrictVal::from(value.clone());
+        let typed = self
+            .types
+            .typify(value, sem_id)
+            .map_err(|e| e.to_string())?;
+        self.types
+            .strict_serialize_type::<MAX16>(&typed)
+            .map_err(|e| e.to_string())
+    }
+
+    fn expected_type(&self, sem_id: SemId) -> String {
+        match self.types.get(sem_id) {
+            Some(ty) => format!("{sem_id} ({ty})"),
+            None => sem_id.to_string(),
+        }
+    }
+
+    fn add_global(&mut self, name: &serde_yaml::Value, value: &serde_yaml::Value) {
+        let Some(name) = self.name(name, "global state") else {
+            return;
+        };
+        let Some(info) = self
+            .iface_impl
+            .global_state
+            .iter()
+            .find(|info| info.name == name)
+        else {
+            let known = self.iface_impl.global_state.iter().map(|info| &info.name);
+            self.problem(IssueProblem::UnknownGlobal(name.to_string(), list(known)));
+            return;
+        };
+        let Some(global_schema) = self.schema_ifaces.schema.global_types.get(&info.id) else {
+            self.problem(IssueProblem::Builder(name.to_string(), s!("absent in the schema")));
+            return;
+        };
+        let sem_id = global_schema.sem_id;
+        match self.typify(value, sem_id) {
+            Ok(data) => self.add(&name, |builder| builder.add_global_state(name.clone(), data)),
+            Err(details) => self.problem(IssueProblem::GlobalTypeMismatch {
+                name: name.to_string(),
+                expected: self.expected_type(sem_id),
+                details,
+            }),
+        }
+    }
+
+    fn add_assignments(&mut self, name: &serde_yaml::Value, value: &serde_yaml::Value) {
+        let Some(name) = self.name(name, "assignment") else {
+            return;
+        };
+        let Some(info) = self
+            .iface_impl
+            .assignments
+            .iter()
+            .find(|info| info.name == name)
+        else {
+            let known = self.iface_impl.assignments.iter().map(|info| &info.name);
+            self.problem(IssueProblem::UnknownAssignment(name.to_string(), list(known)));
+            return;
+        };
+        let Some(state_schema) = self.schema_ifaces.schema.owned_types.get(&info.id) else {
+            self.problem(IssueProblem::Builder(name.to_string(), s!("absent in the schema")));
+            return;
+        };
+        let state_schema = *state_schema;
+
+        // An assignment may be either a single allocation or a list of them
+        let assigns = match value.as_sequence() {
+            Some(list) => list.iter().collect::<Vec<_>>(),
+            None => vec![value],
+        };
+        for assign in assigns {
+            self.add_assignment(&name, &state_schema, assign);
+        }
+    }
+
+    fn add_assignment(
+        &mut self,
+        name: &FieldName,
+        state_schema: &OwnedStateSchema,
+        assign: &serde_yaml::Value,
+    ) {
+        let Some(assign) = assign.as_mapping() else {
+            self.problem(IssueProblem::InvalidStructure(format!("assignment {name}"), "a mapping"));
+            return;
+        };
+        let seal = match assign.get("seal").map(|seal| seal.as_str()) {
+            None if self.default_seal.is_some() => self.default_seal,
+            None => {
+                self.problem(IssueProblem::NoSeal(name.to_string()));
+                None
+            }
+            Some(None) => {
+                self.problem(IssueProblem::InvalidStructure(
+                    format!("seal of assignment {name}"),
+                    "a string",
+                ));
+                None
+            }
+            Some(Some(seal)) => match OutputSeal::from_str(seal) {
+                Ok(seal) => Some(B::explicit_seal(seal, self.layer1)),
+                Err(e) => {
+                    self.problem(IssueProblem::InvalidSeal {
+                        name: name.to_string(),
+                        seal: seal.to_owned(),
+                        details: e.to_string(),
+                    });
+                    None
+                }
+            },
+        };
+
+        match state_schema {
+            OwnedStateSchema::Declarative => {
+                if let Some(seal) = seal {
+                    self.add(name, |builder| builder.add_rights(name.clone(), seal));
+                }
+            }
+            OwnedStateSchema::Fungible(_) => {
+                let Some(amount) = assign.get("amount").and_then(serde_yaml::Value::as_u64) else {
+                    self.problem(IssueProblem::InvalidAmount(name.to_string()));
+                    return;
+                };
+                if let Some(seal) = seal {
+                    self.add(name, |builder| {
+                        builder.add_fungible_state(name.clone(), seal, amount)
+                    });
+                }
+            }
+            OwnedStateSchema::Structured(sem_id) => {
+                let Some(data) = assign.get("data") else {
+                    self.problem(IssueProblem::NoData(name.to_string()));
+                    return;
+                };
+                match self.typify(data, *sem_id) {
+                    Ok(data) => {
+                        if let Some(seal) = seal {
+                            self.add(name, |builder| builder.add_data(name.clone(), seal, data));
+                        }
+                    }
+                    Err(details) => self.problem(IssueProblem::AssignmentTypeMismatch {
+                        name: name.to_string(),
+                        expected: self.expected_type(*sem_id),
+                        details,
+                    }),
+                }
+            }
+            OwnedStateSchema::Attachment(_) => {
+                self.problem(IssueProblem::UnsupportedState(name.to_string(), "attachment"))
+            }
+        }
+    }
+
+    fn add(&mut self, name: &FieldName, f: impl FnOnce(B) -> Result<B, BuilderError>) {
+        match f(self.builder.clone()) {
+            Ok(builder) => self.builder = builder,
+            Err(e) => self.problem(IssueProblem::Builder(name.to_string(), e.to_string())),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn list<'a>(names: impl Iterator<Item = &'a FieldName>) -> String {
+    let names = names.map(FieldName::to_string).collect::<Vec<_>>();
+    if names.is_empty() {
+        s!("none")
+    } else {
+        names.join(", ")
+    }
+}
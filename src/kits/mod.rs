@@ -0,0 +1,238 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Registries of issuer kits, which allow fetching schemata, interfaces and
+//! their implementations over HTTPS instead of distributing kit files
+//! manually.
+//!
+//! Registry publishes [`KIT_INDEX_FILE`] under its base URL, listing kit
+//! files together with the schema they provide and the SHA256 hash of the
+//! file. The hash of the index itself may be pinned by the wallet
+//! configuration, such that the registry can't substitute kits without the
+//! user noticing it.
+
+use commit_verify::{Digest, Sha256};
+use rgbstd::containers::{FileContent, Kit, KitId, ValidKit};
+use rgbstd::persistence::{IndexProvider, StashProvider, StateProvider, Stock};
+use rgbstd::schema::SchemaId;
+
+#[cfg(feature = "kit_registry")]
+mod registry;
+
+#[cfg(feature = "kit_registry")]
+pub use registry::HttpsFetcher;
+
+use crate::KitRegistryError;
+
+/// Name of the index file published by kit registries under their base URL.
+pub const KIT_INDEX_FILE: &str = "index.yaml";
+
+/// Kit file listed in the registry index.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "serde_crate", rename_all = "camelCase")]
+pub struct KitEntry {
+    /// Schema provided by the kit.
+    pub schema_id: SchemaId,
+    /// Path to the kit file relative to the registry base URL.
+    pub file: String,
+    /// Hex-encoded SHA256 hash of the kit file.
+    pub sha256: String,
+}
+
+/// Index of the kits published by a registry.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "serde_crate", rename_all = "camelCase")]
+pub struct KitIndex {
+    pub kits: Vec<KitEntry>,
+}
+
+impl KitIndex {
+    /// Finds the kit providing the schema.
+    pub fn find(&self, schema_id: SchemaId) -> Option<&KitEntry> {
+        self.kits.iter().find(|entry| entry.schema_id == schema_id)
+    }
+}
+
+/// Transport used to download registry files.
+pub trait KitFetcher {
+    /// Downloads the file under the URL, returning its content.
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, String>;
+}
+
+impl<F: Fn(&str) -> Result<Vec<u8>, String>> KitFetcher for F {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, String> { self(url) }
+}
+
+/// Registry of issuer kits available under an HTTPS URL.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct KitRegistry {
+    url: String,
+    pin: Option<String>,
+}
+
+impl KitRegistry {
+    /// Constructs registry with the base URL, which must use HTTPS.
+    pub fn new(url: impl Into<String>) -> Result<Self, KitRegistryError> {
+        let url = url.into();
+        if !url.starts_with("https://") {
+            return Err(KitRegistryError::InsecureUrl(url));
+        }
+        Ok(KitRegistry {
+            url: url.trim_end_matches('/').to_owned(),
+            pin: None,
+        })
+    }
+
+    /// Pins the hex-encoded SHA256 hash of the registry index.
+    pub fn with_pin(mut self, sha256: impl Into<String>) -> Self {
+        self.pin = Some(sha256.into().to_lowercase());
+        self
+    }
+
+    pub fn url(&self) -> &str { &self.url }
+
+    pub fn pin(&self) -> Option<&str> { self.pin.as_deref() }
+
+    /// Downloads the registry index, checking it against the pinned hash.
+    pub fn index(&self, fetcher: &impl KitFetcher) -> Result<KitIndex, KitRegistryError> {
+        let url = format!("{}/{KIT_INDEX_FILE}", self.url);
+        let data = fetch_verified(fetcher, &url, self.pin.as_deref())?;
+        serde_yaml::from_slice(&data)
+            .map_err(|e| KitRegistryError::InvalidIndex(url, e.to_string()))
+    }
+
+    /// Downloads the kit listed in the registry index and validates it.
+    pub fn fetch_kit(
+        &self,
+        fetcher: &impl KitFetcher,
+        entry: &KitEntry,
+    ) -> Result<ValidKit, KitRegistryError> {
+        let url = format!("{}/{}", self.url, entry.file.trim_start_matches('/'));
+        let data = fetch_verified(fetcher, &url, Some(&entry.sha256))?;
+        let kit = Kit::load(data.as_slice())
+            .map_err(|e| KitRegistryError::InvalidKit(url.clone(), e.to_string()))?;
+        let kit = kit
+            .validate()
+            .map_err(|(status, _)| KitRegistryError::InvalidKit(url.clone(), status.to_string()))?;
+        if !kit
+            .schemata
+            .iter()
+            .any(|schema| schema.schema_id() == entry.schema_id)
+        {
+            return Err(KitRegistryError::SchemaMismatch(url, entry.schema_id));
+        }
+        Ok(kit)
+    }
+
+    /// Downloads the kit providing the schema, if the registry has one.
+    pub fn fetch_schema(
+        &self,
+        fetcher: &impl KitFetcher,
+        schema_id: SchemaId,
+    ) -> Result<Option<ValidKit>, KitRegistryError> {
+        let index = self.index(fetcher)?;
+        index
+            .find(schema_id)
+            .map(|entry| self.fetch_kit(fetcher, entry))
+            .transpose()
+    }
+}
+
+/// Downloads all kits from the registries and imports them into the stock,
+/// returning ids of the imported kits.
+///
+/// Kits providing schemata already known to the stock are skipped.
+pub fn update_kits<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &mut Stock<S, H, P>,
+    registries: &[KitRegistry],
+    fetcher: &impl KitFetcher,
+) -> Result<Vec<KitId>, KitRegistryError> {
+    let mut imported = vec![];
+    for registry in registries {
+        for entry in registry.index(fetcher)?.kits {
+            if stock.schema(entry.schema_id).is_ok() {
+                continue;
+            }
+            let kit = registry.fetch_kit(fetcher, &entry)?;
+            imported.push(import_kit(stock, kit)?);
+        }
+    }
+    Ok(imported)
+}
+
+/// Fetches the kit providing the schema from the first registry which has it
+/// and imports it into the stock.
+///
+/// Returns `None` if the schema is already known to the stock or none of the
+/// registries provide it.
+pub fn fetch_schema_kit<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &mut Stock<S, H, P>,
+    registries: &[KitRegistry],
+    fetcher: &impl KitFetcher,
+    schema_id: SchemaId,
+) -> Result<Option<KitId>, KitRegistryError> {
+    if stock.schema(schema_id).is_ok() {
+        return Ok(None);
+    }
+    for registry in registries {
+        if let Some(kit) = registry.fetch_schema(fetcher, schema_id)? {
+            return import_kit(stock, kit).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+fn import_kit<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &mut Stock<S, H, P>,
+    kit: ValidKit,
+) -> Result<KitId, KitRegistryError> {
+    let id = kit.kit_id();
+    stock
+        .import_kit(kit)
+        .map_err(|e| KitRegistryError::Stock(e.to_string()))?;
+    Ok(id)
+}
+
+fn fetch_verified(
+    fetcher: &impl KitFetcher,
+    url: &str,
+    sha256: Option<&str>,
+) -> Result<Vec<u8>, KitRegistryError> {
+    let data = fetcher
+        .fetch(url)
+        .map_err(|e| KitRegistryError::Fetch(url.to_owned(), e))?;
+    let found = Sha256::digest(&data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    match sha256 {
+        Some(expected) if !expected.eq_ignore_ascii_case(&found) => {
+            Err(KitRegistryError::HashMismatch {
+                url: url.to_owned(),
+                expected: expected.to_owned(),
+                found,
+            })
+        }
+        _ => Ok(data),
+    }
+}
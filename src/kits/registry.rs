@@ -0,0 +1,65 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! HTTPS transport of the kit registries.
+
+use std::io::Read;
+use std::time::Duration;
+
+use super::KitFetcher;
+
+/// Fetcher downloading registry files over HTTPS.
+///
+/// Redirects are not followed, since the registry could otherwise send the
+/// wallet to a plain HTTP host; a redirecting registry results in an error.
+pub struct HttpsFetcher(ureq::Agent);
+
+impl HttpsFetcher {
+    /// Constructs the fetcher, which connects via an optional SOCKS5 proxy.
+    pub fn new(timeout: Duration, socks5: Option<&str>) -> Result<Self, String> {
+        let mut agent = ureq::AgentBuilder::new().timeout(timeout).redirects(0);
+        if let Some(proxy) = socks5 {
+            let proxy = ureq::Proxy::new(format!("socks5://{proxy}")).map_err(|e| e.to_string())?;
+            agent = agent.proxy(proxy);
+        }
+        Ok(Self(agent.build()))
+    }
+}
+
+impl KitFetcher for HttpsFetcher {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, String> {
+        if !url.starts_with("https://") {
+            return Err(format!("'{url}' doesn't use HTTPS"));
+        }
+        let resp = self.0.get(url).call().map_err(|e| e.to_string())?;
+        if (300..400).contains(&resp.status()) {
+            return Err(format!(
+                "registry redirects to '{}', which is not followed",
+                resp.header("Location").unwrap_or_default()
+            ));
+        }
+        let mut data = Vec::new();
+        resp.into_reader()
+            .read_to_end(&mut data)
+            .map_err(|e| e.to_string())?;
+        Ok(data)
+    }
+}
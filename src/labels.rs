@@ -0,0 +1,184 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! User-assigned labels of the wallet entities, like contract nicknames,
+//! notes on the witness transactions and tags on allocations.
+//!
+//! Labels are private to the wallet: they are persisted next to the stock
+//! and are never included into consignments.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::str::FromStr;
+
+use bpstd::Txid;
+use rgbstd::{ContractId, Opout};
+
+use crate::LabelError;
+
+/// Entity of the wallet which may be labelled.
+///
+/// The string representation is the target kind followed by a colon and the
+/// entity id, e.g. `contract:<contract id>`, `witness:<txid>` or
+/// `allocation:<opid>/<type>/<no>`.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+pub enum LabelTarget {
+    Contract(ContractId),
+    Witness(Txid),
+    Allocation(Opout),
+}
+
+impl Display for LabelTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LabelTarget::Contract(id) => write!(f, "contract:{id}"),
+            LabelTarget::Witness(txid) => write!(f, "witness:{txid}"),
+            // `Opout` displays assignment type in hex, which its `FromStr` doesn't parse
+            LabelTarget::Allocation(opout) => {
+                write!(f, "allocation:{}/{}/{}", opout.op, u16::from(opout.ty), opout.no)
+            }
+        }
+    }
+}
+
+impl FromStr for LabelTarget {
+    type Err = LabelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = s
+            .split_once(':')
+            .ok_or_else(|| LabelError::InvalidTarget(s.to_owned()))?;
+        let value = value.trim();
+        match kind.trim() {
+            "contract" => ContractId::from_str(value)
+                .map(LabelTarget::Contract)
+                .map_err(|_| LabelError::InvalidTarget(s.to_owned())),
+            "witness" => Txid::from_str(value)
+                .map(LabelTarget::Witness)
+                .map_err(|_| LabelError::InvalidTarget(s.to_owned())),
+            "allocation" => Opout::from_str(value)
+                .map(LabelTarget::Allocation)
+                .map_err(|_| LabelError::InvalidTarget(s.to_owned())),
+            _ => Err(LabelError::InvalidTarget(s.to_owned())),
+        }
+    }
+}
+
+/// Labels assigned by the user to the contracts, witness transactions and
+/// allocations of the wallet.
+///
+/// Serialized as a map from the string representation of [`LabelTarget`] to
+/// the label text.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(
+        crate = "serde_crate",
+        try_from = "BTreeMap<String, String>",
+        into = "BTreeMap<String, String>"
+    )
+)]
+pub struct WalletLabels(BTreeMap<LabelTarget, String>);
+
+impl TryFrom<BTreeMap<String, String>> for WalletLabels {
+    type Error = LabelError;
+
+    fn try_from(map: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        map.into_iter()
+            .map(|(target, label)| Ok((target.parse()?, label)))
+            .collect::<Result<_, _>>()
+            .map(WalletLabels)
+    }
+}
+
+impl From<WalletLabels> for BTreeMap<String, String> {
+    fn from(labels: WalletLabels) -> Self {
+        labels
+            .0
+            .into_iter()
+            .map(|(target, label)| (target.to_string(), label))
+            .collect()
+    }
+}
+
+impl WalletLabels {
+    pub fn new() -> Self { Self::default() }
+
+    /// Loads labels from a YAML file, returning no labels if the file doesn't
+    /// exist.
+    #[cfg(feature = "fs")]
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self, LabelError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = fs::File::open(path)?;
+        Ok(serde_yaml::from_reader(file)?)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn save_file(&self, path: impl AsRef<Path>) -> Result<(), LabelError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = fs::File::create(path)?;
+        serde_yaml::to_writer(file, self)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Assigns label to the target, returning the label it had before.
+    pub fn set(&mut self, target: LabelTarget, label: impl Into<String>) -> Option<String> {
+        self.0.insert(target, label.into())
+    }
+
+    /// Removes label of the target, returning it.
+    pub fn remove(&mut self, target: &LabelTarget) -> Option<String> { self.0.remove(target) }
+
+    pub fn get(&self, target: &LabelTarget) -> Option<&str> {
+        self.0.get(target).map(String::as_str)
+    }
+
+    pub fn contract(&self, contract_id: ContractId) -> Option<&str> {
+        self.get(&LabelTarget::Contract(contract_id))
+    }
+
+    pub fn witness(&self, txid: Txid) -> Option<&str> { self.get(&LabelTarget::Witness(txid)) }
+
+    pub fn allocation(&self, opout: Opout) -> Option<&str> {
+        self.get(&LabelTarget::Allocation(opout))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (LabelTarget, &str)> {
+        self.0
+            .iter()
+            .map(|(target, label)| (*target, label.as_str()))
+    }
+}
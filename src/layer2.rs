@@ -0,0 +1,365 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Support for keeping RGB state of layer 2 channels in the stock.
+//!
+//! Lightning implementations assign RGB state to channel commitment
+//! transactions, which are not published and get replaced with each channel
+//! update. The stock orders state by the status of witness transactions,
+//! thus [`OffchainRegistry`] tracks commitment transactions of each channel,
+//! reporting the latest one as tentative and all the revoked ones as
+//! archived, such that only the latest channel state is taken into account.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bpstd::{Outpoint, Tx, Txid};
+use rgbstd::containers::{Fascia, PubWitness};
+use rgbstd::persistence::{IndexProvider, StashProvider, StateProvider, Stock};
+use rgbstd::validation::{ResolveWitness, WitnessResolverError};
+use rgbstd::vm::{WitnessOrd, XWitnessTx};
+use rgbstd::{XChain, XWitnessId};
+
+use crate::Layer2Error;
+
+/// Off-chain state of a channel.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ChannelState {
+    commitment: Option<Tx>,
+    revoked: BTreeSet<Txid>,
+    closed: bool,
+}
+
+impl ChannelState {
+    /// Returns the latest commitment transaction of the channel.
+    pub fn commitment(&self) -> Option<&Tx> { self.commitment.as_ref() }
+
+    /// Returns commitment transactions replaced by the channel updates.
+    pub fn revoked(&self) -> impl Iterator<Item = Txid> + '_ { self.revoked.iter().copied() }
+
+    pub fn is_closed(&self) -> bool { self.closed }
+}
+
+/// Registry of channels carrying RGB state, indexed by their funding
+/// outpoints.
+///
+/// The registry is not persisted by the stock and must be saved by the
+/// layer 2 implementation together with the rest of its channel data.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct OffchainRegistry {
+    channels: BTreeMap<Outpoint, ChannelState>,
+}
+
+impl OffchainRegistry {
+    pub fn new() -> Self { Self::default() }
+
+    /// Returns funding outpoints of all known channels.
+    pub fn channels(&self) -> impl Iterator<Item = Outpoint> + '_ { self.channels.keys().copied() }
+
+    pub fn channel(&self, funding: Outpoint) -> Option<&ChannelState> {
+        self.channels.get(&funding)
+    }
+
+    /// Starts tracking the channel with the given funding outpoint.
+    pub fn open(&mut self, funding: Outpoint) -> Result<(), Layer2Error> {
+        if self.channels.contains_key(&funding) {
+            return Err(Layer2Error::ChannelExists(funding));
+        }
+        self.channels.insert(funding, none!());
+        Ok(())
+    }
+
+    /// Stops tracking a closed channel, returning its state.
+    ///
+    /// Once the channel is forgotten, its commitment transactions are resolved
+    /// from the blockchain as any other witness transaction.
+    pub fn forget(&mut self, funding: Outpoint) -> Result<ChannelState, Layer2Error> {
+        match self.channels.get(&funding) {
+            None => Err(Layer2Error::UnknownChannel(funding)),
+            Some(channel) if !channel.closed => Err(Layer2Error::ChannelOpen(funding)),
+            Some(_) => Ok(self.channels.remove(&funding).expect("checked above")),
+        }
+    }
+
+    /// Checks whether the transaction is a commitment transaction of a known
+    /// channel.
+    pub fn is_offchain(&self, txid: Txid) -> bool {
+        self.channels.values().any(|channel| {
+            channel.revoked.contains(&txid)
+                || channel.commitment.as_ref().map(Tx::txid) == Some(txid)
+        })
+    }
+
+    /// Returns ordering of the commitment transaction, if it has to be
+    /// determined by the registry and not by the blockchain.
+    ///
+    /// The latest commitment of an open channel is tentative and revoked
+    /// commitments are archived. The latest commitment of a closed channel is
+    /// left to the blockchain, since it may be used to close the channel.
+    pub fn witness_ord(&self, txid: Txid) -> Option<WitnessOrd> {
+        self.channels.values().find_map(|channel| {
+            if channel.revoked.contains(&txid) {
+                Some(WitnessOrd::Archived)
+            } else if !channel.closed && channel.commitment.as_ref().map(Tx::txid) == Some(txid) {
+                Some(WitnessOrd::Tentative)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Records new commitment transaction of the channel, returning id of the
+    /// commitment transaction it replaces.
+    pub fn record_commitment(
+        &mut self,
+        funding: Outpoint,
+        tx: Tx,
+    ) -> Result<Option<Txid>, Layer2Error> {
+        let channel = self.open_channel_mut(funding)?;
+        let txid = tx.txid();
+        if !tx.inputs().any(|input| input.prev_output == funding) {
+            return Err(Layer2Error::NotCommitment(txid, funding));
+        }
+        let replaced = channel.commitment.replace(tx).map(|tx| tx.txid());
+        if let Some(prev) = replaced.filter(|prev| *prev != txid) {
+            channel.revoked.insert(prev);
+        }
+        channel.revoked.remove(&txid);
+        Ok(replaced.filter(|prev| *prev != txid))
+    }
+
+    /// Marks the channel as closed.
+    ///
+    /// If the channel is closed with a transaction other than its latest
+    /// commitment (for instance, a cooperative closing transaction), the
+    /// latest commitment gets revoked.
+    pub fn close(&mut self, funding: Outpoint, closing: Option<Txid>) -> Result<(), Layer2Error> {
+        let channel = self.open_channel_mut(funding)?;
+        channel.closed = true;
+        let latest = channel.commitment.as_ref().map(Tx::txid);
+        if let Some(latest) = latest.filter(|txid| Some(*txid) != closing) {
+            channel.revoked.insert(latest);
+        }
+        Ok(())
+    }
+
+    /// Constructs resolver answering for the commitment transactions from
+    /// the registry and using the provided resolver for all other witnesses.
+    pub fn resolver<R: ResolveWitness>(&self, inner: R) -> OffchainResolver<'_, R> {
+        OffchainResolver {
+            registry: self,
+            inner,
+        }
+    }
+
+    fn open_channel_mut(&mut self, funding: Outpoint) -> Result<&mut ChannelState, Layer2Error> {
+        let channel = self
+            .channels
+            .get_mut(&funding)
+            .ok_or(Layer2Error::UnknownChannel(funding))?;
+        if channel.closed {
+            return Err(Layer2Error::ChannelClosed(funding));
+        }
+        Ok(channel)
+    }
+}
+
+/// Witness resolver taking into account channel commitment transactions
+/// from [`OffchainRegistry`].
+///
+/// Must be used instead of the plain blockchain resolver for updating
+/// witnesses of a stock holding channel state, since otherwise unpublished
+/// commitment transactions are reported as archived.
+pub struct OffchainResolver<'registry, R: ResolveWitness> {
+    registry: &'registry OffchainRegistry,
+    inner: R,
+}
+
+impl<R: ResolveWitness> ResolveWitness for OffchainResolver<'_, R> {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        if let XWitnessId::Bitcoin(txid) = witness_id {
+            let tx = self
+                .registry
+                .channels
+                .values()
+                .filter_map(|channel| channel.commitment.as_ref())
+                .find(|tx| tx.txid() == txid);
+            if let Some(tx) = tx {
+                return Ok(XWitnessTx::Bitcoin(tx.clone()));
+            }
+        }
+        self.inner.resolve_pub_witness(witness_id)
+    }
+
+    fn resolve_pub_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        if let XWitnessId::Bitcoin(txid) = witness_id {
+            if let Some(ord) = self.registry.witness_ord(txid) {
+                return Ok(ord);
+            }
+        }
+        self.inner.resolve_pub_witness_ord(witness_id)
+    }
+}
+
+/// Resolver which doesn't know any witnesses, used to update ordering of
+/// commitment transactions without accessing the blockchain.
+struct NoResolver;
+
+impl ResolveWitness for NoResolver {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        Err(WitnessResolverError::Unknown(witness_id))
+    }
+
+    fn resolve_pub_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        Err(WitnessResolverError::Unknown(witness_id))
+    }
+}
+
+/// Re-anchors RGB state to a commitment transaction which replaces the
+/// commitment transaction of the fascia, without creating new state
+/// transitions.
+///
+/// This is possible when the new transaction spends the same funding outpoint
+/// and keeps the outputs holding the deterministic bitcoin commitments
+/// (the first OP_RETURN and the first taproot output) unchanged.
+pub fn reanchor_fascia(fascia: &Fascia, tx: Tx) -> Result<Fascia, Layer2Error> {
+    let XChain::Bitcoin(witness) = &fascia.witness else {
+        return Err(Layer2Error::UnsupportedLayer1);
+    };
+    let prev = witness
+        .tx()
+        .ok_or(Layer2Error::NoWitnessTx(witness.txid()))?;
+    let txid = tx.txid();
+    let inputs = |tx: &Tx| {
+        tx.inputs()
+            .map(|input| input.prev_output)
+            .collect::<BTreeSet<_>>()
+    };
+    if inputs(prev) != inputs(&tx) {
+        return Err(Layer2Error::CommitmentMismatch(txid));
+    }
+    let commitments = |tx: &Tx| {
+        let scripts = tx
+            .outputs()
+            .map(|output| &output.script_pubkey)
+            .collect::<Vec<_>>();
+        let opret = scripts.iter().find(|s| s.is_op_return()).copied().cloned();
+        let tapret = scripts.iter().find(|s| s.is_p2tr()).copied().cloned();
+        (opret, tapret)
+    };
+    if commitments(prev) != commitments(&tx) {
+        return Err(Layer2Error::CommitmentMismatch(txid));
+    }
+    let mut fascia = fascia.clone();
+    fascia.witness = XChain::Bitcoin(PubWitness::with(tx));
+    Ok(fascia)
+}
+
+/// Stock operations with RGB state of layer 2 channels.
+pub trait OffchainStock {
+    /// Adds state transitions committed in the channel commitment transaction
+    /// to the stock, revoking the state from the previous commitment.
+    ///
+    /// The fascia must contain the full commitment transaction. Returns id of
+    /// the revoked commitment transaction, if any.
+    fn register_offchain_witness(
+        &mut self,
+        registry: &mut OffchainRegistry,
+        funding: Outpoint,
+        fascia: Fascia,
+    ) -> Result<Option<Txid>, Layer2Error>;
+
+    /// Closes the channel, evicting the state of all commitment transactions
+    /// except the one used for closing the channel (if any).
+    fn evict_channel(
+        &mut self,
+        registry: &mut OffchainRegistry,
+        funding: Outpoint,
+        closing: Option<Txid>,
+    ) -> Result<(), Layer2Error>;
+
+    /// Updates ordering of all commitment transactions in the stock according
+    /// to the registry.
+    fn sync_offchain(&mut self, registry: &OffchainRegistry) -> Result<(), Layer2Error>;
+}
+
+impl<S: StashProvider, H: StateProvider, P: IndexProvider> OffchainStock for Stock<S, H, P> {
+    fn register_offchain_witness(
+        &mut self,
+        registry: &mut OffchainRegistry,
+        funding: Outpoint,
+        fascia: Fascia,
+    ) -> Result<Option<Txid>, Layer2Error> {
+        let XChain::Bitcoin(witness) = &fascia.witness else {
+            return Err(Layer2Error::UnsupportedLayer1);
+        };
+        let tx = witness
+            .tx()
+            .ok_or(Layer2Error::NoWitnessTx(witness.txid()))?
+            .clone();
+        let revoked = registry.record_commitment(funding, tx)?;
+        self.consume_fascia(fascia, registry.resolver(NoResolver))
+            .map_err(|e| e.to_string())?;
+        if revoked.is_some() {
+            self.sync_offchain(registry)?;
+        }
+        Ok(revoked)
+    }
+
+    fn evict_channel(
+        &mut self,
+        registry: &mut OffchainRegistry,
+        funding: Outpoint,
+        closing: Option<Txid>,
+    ) -> Result<(), Layer2Error> {
+        registry.close(funding, closing)?;
+        self.sync_offchain(registry)
+    }
+
+    fn sync_offchain(&mut self, registry: &OffchainRegistry) -> Result<(), Layer2Error> {
+        // Witnesses mined in blocks are skipped, and all non-commitment
+        // witnesses fail to resolve and thus stay unchanged
+        self.update_witnesses(registry.resolver(NoResolver), u32::MAX)
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
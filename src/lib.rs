@@ -19,6 +19,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(all(target_arch = "wasm32", target_os = "unknown", feature = "fs"))]
+compile_error!("`fs` feature is not supported on wasm32-unknown-unknown target");
+
 #[macro_use]
 extern crate amplify;
 #[cfg(feature = "serde")]
@@ -28,18 +31,200 @@ extern crate serde_crate as serde;
 mod descriptor;
 mod indexers;
 mod filters;
+mod query;
 pub mod pay;
 mod errors;
 mod wallet;
+#[cfg(feature = "fs")]
+mod wallets;
+#[cfg(feature = "fs")]
+mod factory;
+mod swap;
+mod payjoin;
+mod transport;
+mod reorg;
+mod preview;
+mod basket;
+mod history;
+#[cfg(feature = "serde")]
+mod call;
+mod issue;
+mod amend;
+mod anchor;
+mod supply;
+mod plan;
+mod archive;
+mod interchange;
+#[cfg(feature = "fs")]
+mod backup;
+mod events;
+mod compact;
+mod gc;
+mod templates;
+mod layer2;
+mod signer;
+mod policy;
+mod labels;
+mod frozen;
+mod imported;
+mod velocity;
+mod paid;
+mod expiry;
+mod identity;
+mod invoicing;
+#[cfg(feature = "serde")]
+mod kits;
+mod network;
+mod ownership;
+mod amount;
+mod assets;
+mod offline;
+mod describe;
+mod diff;
+mod explore;
+mod proof;
+mod interop;
+mod bump;
+mod consolidate;
+mod recurring;
+mod progress;
+#[cfg(feature = "serde")]
+mod portable;
+mod registry;
+mod report;
+mod stream;
+#[cfg(feature = "fs")]
+mod lock;
+#[cfg(feature = "fs")]
+mod recover;
+#[cfg(feature = "encryption")]
+mod encryption;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 
-pub use descriptor::{DescriptorRgb, RgbDescr, RgbKeychain, TapTweakAlreadyAssigned, TapretKey};
-pub use errors::{CompletionError, CompositionError, PayError, WalletError};
-pub use pay::{TransferParams, WalletProvider};
+pub use amend::Amendment;
+pub use amount::AmountFormatter;
+pub use anchor::verify_anchor;
+pub use archive::{StockArchive, StockCompaction};
+pub use assets::{AssetInfoExt, Rgb20Info, Rgb21Info, Rgb21Token};
+#[cfg(feature = "fs")]
+pub use backup::{BackupStore, DEFAULT_STOCK_BACKUPS};
+pub use basket::BasketInvoice;
+pub use bump::{cpfp_fee, effective_feerate, estimate_vsize, witness_fee, CPFP_INPUT_VSIZE};
+pub use compact::{CompactInvoice, COMPACT_INVOICE_VERSION};
+pub use consolidate::{ConsolidatedState, ConsolidationReport, ConsolidationScope};
+pub use describe::{
+    standard_symbols, GlobalTypeDescription, InterfaceDescription, OpStateDescription,
+    OperationDescription, OwnedTypeDescription, SchemaDescription, TypeDefinition,
+};
+pub use descriptor::{
+    DescriptorError, DescriptorRgb, KeychainLayout, KeychainLayoutParseError, RgbDescr, RgbKeychain,
+    TapOutput, TapTweakAlreadyAssigned, TapretKey, TapretTweak, TapretTweaks,
+    TapretTweaksParseError,
+};
+pub use diff::{ConsignmentDiff, TerminalDiff};
+#[cfg(feature = "encryption")]
+pub use encryption::{is_encrypted, EncryptedStore, Passphrase, ENCRYPTED_DESCRIPTOR_FILE};
+#[cfg(feature = "encryption")]
+pub use errors::EncryptionError;
+#[cfg(feature = "sqlite")]
+pub use errors::SqliteStoreError;
+pub use errors::{
+    AcceptError, AllocationProofError, AllocationsError, AmendError, AnchorError, ArchiveError,
+    AssetInfoError, BasketInvoiceError, CallError, CompactInvoiceError, CompletionError,
+    CompositionError, ContractMismatch, DeferredValidationError, DeliveryError,
+    DescriptorImportError, ErrorCode, ExploreError, FreezeError, IdentityError, ImportError,
+    InvoiceApiError, InvoiceStatusError, IssueError, IssueProblem, KitRegistryError, LabelError,
+    Layer2Error, NetworkMismatch, OwnershipError, PayError, PayjoinError, PolicyError,
+    PortableValueError, PreviewError, RegistryError, ReorgError, ReservationError, SealExpiryError,
+    SignerError, StockExportError, SwapError, SyncError, VelocityError, WalletDirError,
+    WalletError, WitnessCheckError,
+};
+#[cfg(feature = "fs")]
+pub use errors::{BackupStoreError, RecoveryError};
+pub use expiry::{AddressReservations, InvoiceExpiry, INVOICE_QUERY_EXPIRY_HEIGHT};
+pub use explore::{ContractGraph, GraphAllocation, GraphOperation, GraphWitness};
+#[cfg(feature = "fs")]
+pub use factory::{DescriptorSpec, WalletFactory, FROZEN_FILE, IMPORTED_FILE, VELOCITY_FILE};
+pub use frozen::FrozenOutpoints;
+pub use gc::SealExpiry;
+pub use identity::{
+    identity_key, issuer_message, issuer_status, sign_issuer, Bip340Verifier, ContractInfoExt,
+    IdentityVerifier, IssuerSigStock, IssuerStatus, BIP340_IDENTITY_MARKER, ISSUER_SIG_TAG,
+};
+pub use imported::{ImportedUtxo, ImportedUtxos, RedeemInfo};
+pub use interchange::{StockExport, STOCK_EXPORT_MAGIC, STOCK_EXPORT_VERSION};
+pub use interop::{descriptor_checksum, CoreDescriptor};
+pub use invoicing::{InvoiceValidation, ValidatedInvoiceBuilder};
+#[cfg(feature = "kit_registry")]
+pub use kits::HttpsFetcher;
+#[cfg(feature = "serde")]
+pub use kits::{
+    fetch_schema_kit, update_kits, KitEntry, KitFetcher, KitIndex, KitRegistry, KIT_INDEX_FILE,
+};
+pub use labels::{LabelTarget, WalletLabels};
+pub use layer2::{
+    reanchor_fascia, ChannelState, OffchainRegistry, OffchainResolver, OffchainStock,
+};
+pub use network::{chain_net, NetworkComponent, NetworkGuard};
+pub use offline::{genesis_outpoints, DeferredValidation, ReconcileReport};
+pub use ownership::{
+    invoice_id, verify_ownership, OwnershipProof, BIP322_TAG, INVOICE_ID_TAG, INVOICE_QUERY_PROOF,
+};
+pub use paid::invoice_payment;
+pub use pay::{
+    blank_contracts, reveal_known_seals, AmountRange, CoinSelection, SplitSeals, TransferParams,
+    WalletProvider, WitnessSats, INVOICE_QUERY_MAX, INVOICE_QUERY_MIN, INVOICE_QUERY_SATS,
+    INVOICE_QUERY_SPLIT,
+};
+#[cfg(feature = "fs")]
+pub use policy::Quarantine;
+pub use policy::{PolicyRule, TrustPolicy};
+pub use progress::{update_witnesses_with_progress, SyncProgress, SyncStage};
+pub use proof::{AllocationProof, ProvenAllocation};
+pub use recurring::{PaymentTemplate, TemplateBeneficiary};
+pub use registry::{
+    asset_spec, AssetCollision, AssetCollisions, AssetRegistry, AssetRegistryStock, SPEC_GLOBAL,
+};
+pub use report::{
+    BundleReport, IssueKind, IssueSeverity, OperationReport, ReportValidity, ScriptFailure,
+    ValidationIssue, ValidationReport,
+};
 pub use rgbstd::*;
+pub use signer::Signer;
+#[cfg(feature = "hot")]
+pub use signer::SoftwareSigner;
+pub use templates::{
+    iface_schema, IssuanceTemplate, Rgb20Issuance, Rgb21Issuance, Rgb25Issuance, TemplateBuilder,
+    RGB20_IFACE, RGB21_IFACE, RGB25_IFACE,
+};
+#[cfg(feature = "esplora_blocking")]
+pub use transport::HttpsPoster;
+pub use transport::{
+    deliver_consignment, delivery_order, ConsignmentPoster, PrioritizedBuilder,
+    TransportInvoiceBuilder, TransportMeta, TransportPriorities, INVOICE_QUERY_PRIORITIES,
+};
+pub use velocity::{
+    parse_velocity, velocity_name, RequestedVelocity, VelocityPrefs, INVOICE_QUERY_VELOCITY,
+};
 pub mod resolvers {
-    #[cfg(any(feature = "electrum_blocking", feature = "esplora_blocking"))]
+    #[cfg(feature = "testing")]
+    pub use super::indexers::MockChain;
+    #[cfg(any(
+        feature = "electrum_blocking",
+        feature = "esplora_blocking",
+        feature = "esplora_async"
+    ))]
     pub use super::indexers::*;
-    pub use super::indexers::{AnyResolver, RgbResolver};
+    pub use super::indexers::{
+        indexer_url, AnyResolver, ConnectionOpts, ResolverConfig, RgbResolver,
+    };
+    #[cfg(feature = "fs")]
+    pub use super::indexers::{
+        checkpoint_path, CachingResolver, CheckpointResolver, CHECKPOINT_DIR, DEFAULT_REORG_DEPTH,
+    };
     use super::validation::{ResolveWitness, WitnessResolverError};
     use super::vm::{WitnessOrd, XWitnessTx};
     use super::XWitnessId;
@@ -57,5 +242,50 @@ pub mod resolvers {
         }
     }
 }
+#[cfg(feature = "serde")]
+pub use call::ContractCall;
+pub use events::{EventHook, WalletEvent};
 pub use filters::{WalletOutpointsFilter, WalletUnspentFilter, WalletWitnessFilter};
-pub use wallet::RgbWallet;
+pub use history::{
+    HistoryExporter, HistoryRow, TransferRecord, TransferRole, HISTORY_CSV_HEADER,
+};
+#[cfg(feature = "serde")]
+pub use issue::ContractDefinition;
+pub use issue::{AllocationsReader, InitialAllocation, ALLOCATIONS_CSV_HEADER};
+#[cfg(feature = "fs")]
+pub use lock::{StockLock, STOCK_LOCK_FILE};
+#[cfg(feature = "esplora_blocking")]
+pub use payjoin::HttpsPayjoin;
+pub use payjoin::{PayjoinClient, PayjoinEndpoint, PayjoinProposal, INVOICE_QUERY_PAYJOIN};
+pub use plan::{PlannedChange, TransferPlan, PLAN_WITNESS_SIZE_ESTIMATE};
+#[cfg(feature = "serde")]
+pub use portable::{from_portable, to_portable};
+pub use preview::{
+    AssignmentPreview, ContractPreview, StateDestination, TransferPreview, TxOutPreview,
+};
+pub use query::{
+    confirmations, ContractStateQuery, QueryFilter, StaleAllocation, StaleReason, StateQuery,
+    WitnessStatus, WitnessStatusParseError,
+};
+#[cfg(feature = "fs")]
+pub use recover::{RecoveryReport, StockRecovery};
+pub use reorg::ReorgTracker;
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteStore, SQLITE_SCHEMA_VERSION};
+pub use stream::{
+    ConsignmentReader, ConsignmentWriter, ContractReader, ContractWriter, TransferReader,
+    TransferWriter,
+};
+pub use supply::{
+    SupplyOperation, GLOBAL_BURNED_SUPPLY, GLOBAL_ISSUED_SUPPLY, OPERATION_BURN, OPERATION_ISSUE,
+};
+pub use swap::{SaleProposal, SwapLeg, SwapMeta, SwapParty, SwapProposal};
+pub use wallet::{
+    BalanceReport, InvoiceStatus, RgbWallet, TweaksBackupHook, BALANCE_MIN_CONFIRMATIONS,
+    DEFAULT_WALLET_NAME, TAPRET_RECOVERY_GAP,
+};
+#[cfg(feature = "fs")]
+pub use wallets::{
+    has_flat_layout, migrate_flat_layout, WalletDir, WalletInfo, WALLET_DESCRIPTOR_FILE,
+    WALLET_TWEAKS_FILE,
+};
@@ -0,0 +1,83 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use fs4::fs_std::FileExt;
+
+use crate::WalletError;
+
+/// Name of the lock file created in the stock directory.
+pub const STOCK_LOCK_FILE: &str = "stock.lock";
+
+/// Advisory exclusive lock on a stock directory, preventing other processes
+/// from loading and storing the same stock concurrently.
+///
+/// The lock is held until the object is dropped; it is also released by the
+/// operating system if the process terminates.
+#[derive(Debug)]
+pub struct StockLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl StockLock {
+    /// Acquires the lock on the stock directory, creating the directory if
+    /// it doesn't exist.
+    ///
+    /// If the stock is locked by another process, either blocks until the
+    /// lock is released (if `wait` is set) or fails with
+    /// [`WalletError::StockLocked`].
+    #[allow(clippy::result_large_err)]
+    pub fn acquire(stock_dir: impl AsRef<Path>, wait: bool) -> Result<Self, WalletError> {
+        let stock_dir = stock_dir.as_ref();
+        fs::create_dir_all(stock_dir)?;
+        let path = stock_dir.join(STOCK_LOCK_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+        if wait {
+            file.lock_exclusive()?;
+        } else if let Err(err) = file.try_lock_exclusive() {
+            if err.kind() == io::ErrorKind::WouldBlock
+                || err.raw_os_error() == fs4::lock_contended_error().raw_os_error()
+            {
+                return Err(WalletError::StockLocked(stock_dir.to_path_buf()));
+            }
+            return Err(err.into());
+        }
+        Ok(Self { file, path })
+    }
+
+    /// Returns path to the lock file.
+    pub fn path(&self) -> &Path { &self.path }
+}
+
+impl Drop for StockLock {
+    fn drop(&mut self) {
+        // Errors are ignored since the lock is released with the file handle anyway.
+        let _ = FileExt::unlock(&self.file);
+    }
+}
@@ -0,0 +1,158 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::{self, Display, Formatter};
+
+use bpstd::{BlockHash, Network};
+use rgbstd::invoice::{ChainNet, RgbInvoice, XChainNet};
+use rgbstd::{ContractId, Genesis, Layer1, Operation};
+
+use crate::resolvers::AnyResolver;
+use crate::NetworkMismatch;
+
+const NETWORKS: [Network; 5] =
+    [Network::Mainnet, Network::Testnet3, Network::Testnet4, Network::Signet, Network::Regtest];
+
+/// Component of a wallet operation which is bound to a specific network.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum NetworkComponent {
+    Descriptor(Network),
+
+    Invoice(ChainNet),
+
+    /// Contract with the testnet flag of its genesis.
+    Contract(ContractId, bool),
+
+    /// Resolver with the hash of its genesis block.
+    Resolver(BlockHash),
+}
+
+impl Display for NetworkComponent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkComponent::Descriptor(network) => write!(f, "wallet descriptor for {network}"),
+            NetworkComponent::Invoice(chain_net) => write!(f, "invoice for `{chain_net}` chain"),
+            NetworkComponent::Contract(id, true) => write!(f, "contract {id} issued on testnet"),
+            NetworkComponent::Contract(id, false) => write!(f, "contract {id} issued on mainnet"),
+            NetworkComponent::Resolver(genesis) => {
+                match NETWORKS
+                    .into_iter()
+                    .find(|network| genesis_hash(*network) == *genesis)
+                {
+                    Some(network) => write!(f, "resolver for {network}"),
+                    None => write!(f, "resolver for unknown chain with genesis {genesis}"),
+                }
+            }
+        }
+    }
+}
+
+/// Returns chain network of the given layer 1 which is paired with the Bitcoin
+/// network. Liquid has a single test network, used with all Bitcoin test
+/// networks.
+pub fn chain_net(layer1: Layer1, network: Network) -> ChainNet {
+    match (layer1, network) {
+        (Layer1::Bitcoin, network) => XChainNet::bitcoin(network, ()).chain_network(),
+        (Layer1::Liquid, Network::Mainnet) => ChainNet::LiquidMainnet,
+        (Layer1::Liquid, _) => ChainNet::LiquidTestnet,
+    }
+}
+
+fn genesis_hash(network: Network) -> BlockHash {
+    crate::indexers::genesis_block_hash(network)
+        .parse()
+        .expect("hardcoded genesis hash")
+}
+
+/// Checks that all components of a wallet operation - the wallet descriptor,
+/// invoices, contracts and the resolver - are for the same network.
+///
+/// Unlike failing on the first problem, the guard collects all mismatching
+/// components, which are reported together with [`NetworkGuard::finish`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct NetworkGuard {
+    network: Network,
+    mismatches: Vec<NetworkComponent>,
+}
+
+impl NetworkGuard {
+    pub fn new(network: Network) -> Self {
+        Self {
+            network,
+            mismatches: vec![],
+        }
+    }
+
+    pub fn network(&self) -> Network { self.network }
+
+    pub fn check_descriptor(mut self, network: Network) -> Self {
+        if network != self.network {
+            self.mismatches.push(NetworkComponent::Descriptor(network));
+        }
+        self
+    }
+
+    /// Checks the chain network of the invoice. Invoices for Liquid are
+    /// accepted only if the library is compiled with the `liquid` feature.
+    pub fn check_invoice(mut self, invoice: &RgbInvoice) -> Self {
+        let chain_net = invoice.chain_network();
+        let layer1 = chain_net.layer1();
+        let supported = layer1 == Layer1::Bitcoin || cfg!(feature = "liquid");
+        if !supported || chain_net != self::chain_net(layer1, self.network) {
+            self.mismatches.push(NetworkComponent::Invoice(chain_net));
+        }
+        self
+    }
+
+    pub fn check_genesis(mut self, genesis: &Genesis) -> Self {
+        if genesis.testnet != self.network.is_testnet() {
+            self.mismatches
+                .push(NetworkComponent::Contract(genesis.contract_id(), genesis.testnet));
+        }
+        self
+    }
+
+    /// Checks the network of the resolver by comparing its genesis block
+    /// with the one of the guarded network.
+    ///
+    /// Errors if the resolver is not able to provide the genesis block.
+    pub fn check_resolver(mut self, resolver: &AnyResolver) -> Result<Self, String> {
+        let genesis = resolver.resolve_block_hash(0)?;
+        if genesis != genesis_hash(self.network) {
+            self.mismatches.push(NetworkComponent::Resolver(genesis));
+        }
+        Ok(self)
+    }
+
+    pub fn mismatches(&self) -> &[NetworkComponent] { &self.mismatches }
+
+    /// Completes the checks, erroring if any of the components is for a
+    /// different network.
+    pub fn finish(self) -> Result<(), NetworkMismatch> {
+        if self.mismatches.is_empty() {
+            return Ok(());
+        }
+        Err(NetworkMismatch {
+            expected: self.network,
+            components: self.mismatches,
+        })
+    }
+}
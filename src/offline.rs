@@ -0,0 +1,180 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Offline (air-gapped) issuance, where the genesis seals reference outpoints
+//! which can't be checked without chain access at the time of the issue.
+
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use bpstd::Outpoint;
+use rgbstd::validation::{ResolveWitness, WitnessResolverError};
+use rgbstd::{ContractId, Genesis, Operation, TxoSeal, XChain};
+
+#[cfg(feature = "fs")]
+use crate::DeferredValidationError;
+
+/// Outpoints of the genesis seals which were not verified against the
+/// blockchain when the contracts were issued offline.
+///
+/// Contracts stay in the list until all their genesis outpoints are found on
+/// chain with [`DeferredValidation::reconcile`].
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", transparent)
+)]
+pub struct DeferredValidation(BTreeMap<ContractId, BTreeSet<Outpoint>>);
+
+/// Result of reconciling contracts issued offline with the blockchain.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ReconcileReport {
+    /// Contracts with all genesis outpoints found on chain; they are removed
+    /// from the deferred validation list.
+    pub verified: BTreeSet<ContractId>,
+    /// Genesis outpoints which transactions are not known to the resolver
+    /// yet; the contracts stay in the deferred validation list.
+    pub pending: BTreeMap<ContractId, BTreeSet<Outpoint>>,
+    /// Genesis outpoints which transactions don't have the referenced output;
+    /// the state assigned to them can never be spent.
+    pub invalid: BTreeMap<ContractId, BTreeSet<Outpoint>>,
+}
+
+impl ReconcileReport {
+    /// Detects whether some of the contracts reference outputs which don't
+    /// exist.
+    pub fn has_invalid(&self) -> bool { !self.invalid.is_empty() }
+}
+
+impl DeferredValidation {
+    pub fn new() -> Self { Self::default() }
+
+    /// Loads deferred validation list from a YAML file, returning empty list
+    /// if the file doesn't exist.
+    #[cfg(feature = "fs")]
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self, DeferredValidationError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = fs::File::open(path)?;
+        Ok(serde_yaml::from_reader(file)?)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn save_file(&self, path: impl AsRef<Path>) -> Result<(), DeferredValidationError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = fs::File::create(path)?;
+        serde_yaml::to_writer(file, self)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    pub fn len(&self) -> usize { self.0.len() }
+
+    pub fn contains(&self, contract_id: ContractId) -> bool { self.0.contains_key(&contract_id) }
+
+    /// Returns unverified genesis outpoints of the contract.
+    pub fn outpoints(&self, contract_id: ContractId) -> Option<&BTreeSet<Outpoint>> {
+        self.0.get(&contract_id)
+    }
+
+    pub fn contracts(&self) -> impl Iterator<Item = ContractId> + '_ { self.0.keys().copied() }
+
+    /// Defers validation of the outpoints referenced by the genesis seals,
+    /// returning their number. Genesis without bitcoin seals is not added.
+    pub fn defer(&mut self, genesis: &Genesis) -> usize {
+        let outpoints = genesis_outpoints(genesis);
+        let count = outpoints.len();
+        if count > 0 {
+            self.0.insert(genesis.contract_id(), outpoints);
+        }
+        count
+    }
+
+    pub fn remove(&mut self, contract_id: ContractId) -> Option<BTreeSet<Outpoint>> {
+        self.0.remove(&contract_id)
+    }
+
+    /// Checks genesis outpoints of all deferred contracts against the
+    /// resolver, removing the contracts which outpoints are all found on
+    /// chain.
+    ///
+    /// Fails if the resolver can't be reached; in this case the list is left
+    /// untouched.
+    pub fn reconcile(
+        &mut self,
+        resolver: &impl ResolveWitness,
+    ) -> Result<ReconcileReport, WitnessResolverError> {
+        let mut report = ReconcileReport::default();
+        for (contract_id, outpoints) in &self.0 {
+            for outpoint in outpoints {
+                match resolver.resolve_pub_witness(XChain::Bitcoin(outpoint.txid)) {
+                    Ok(XChain::Bitcoin(tx)) if outpoint.vout.to_usize() < tx.outputs.len() => {}
+                    Ok(_) => {
+                        report
+                            .invalid
+                            .entry(*contract_id)
+                            .or_default()
+                            .insert(*outpoint);
+                    }
+                    Err(WitnessResolverError::Unknown(_)) => {
+                        report
+                            .pending
+                            .entry(*contract_id)
+                            .or_default()
+                            .insert(*outpoint);
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            if !report.pending.contains_key(contract_id)
+                && !report.invalid.contains_key(contract_id)
+            {
+                report.verified.insert(*contract_id);
+            }
+        }
+        self.0
+            .retain(|contract_id, _| !report.verified.contains(contract_id));
+        Ok(report)
+    }
+}
+
+/// Collects outpoints referenced by the revealed bitcoin seals of the genesis.
+pub fn genesis_outpoints(genesis: &Genesis) -> BTreeSet<Outpoint> {
+    let mut outpoints = BTreeSet::new();
+    for assigns in genesis.assignments.values() {
+        for no in 0..assigns.len_u16() {
+            if let Ok(Some(XChain::Bitcoin(seal))) = assigns.revealed_seal_at(no) {
+                outpoints.extend(seal.outpoint());
+            }
+        }
+    }
+    outpoints
+}
@@ -0,0 +1,330 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proofs that the beneficiary of an invoice belongs to the wallet which has
+//! issued the invoice.
+//!
+//! The proof is a BIP-322 "simple" signature over the invoice id, made by the
+//! key controlling the beneficiary output. For the invoices paying to a
+//! witness output the output script is known from the invoice address; for
+//! the blinded seals the proof reveals the seal, such that the payer can look
+//! up the output script with a resolver. Thus, proving ownership of a blinded
+//! seal discloses the seal UTXO to the payer.
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use amplify::hex::{FromHex, ToHex};
+use amplify::ByteArray;
+use bp::seals::txout::{BlindSeal, TxPtr};
+use bpstd::secp256k1::{ecdsa, XOnlyPublicKey, SECP256K1};
+use bpstd::{
+    Bip340Sig, CompressedPk, ConsensusDecode, ConsensusEncode, Descriptor, LegacySig, LockTime,
+    OpCode, Outpoint, Prevout, Psbt, PsbtVer, Sats, ScriptCode, ScriptPubkey, SeqNo, SigScript,
+    SighashCache, Terminal, Tx, TxIn, TxOut, TxVer, Txid, VarIntArray, Vout, WPubkeyHash, Witness,
+};
+use commit_verify::{Conceal, DigestExt, Sha256};
+use rgbstd::GraphSeal;
+
+use crate::bump::resolve_bitcoin_tx;
+use crate::invoice::{Beneficiary, RgbInvoice};
+use crate::validation::ResolveWitness;
+use crate::OwnershipError;
+
+/// Tag of the hash identifying an invoice, which is signed by the ownership
+/// proofs.
+pub const INVOICE_ID_TAG: &str = "urn:lnp-bp:rgb:invoice-id#2024-12-05";
+
+/// Tag of the BIP-322 message hash.
+pub const BIP322_TAG: &str = "BIP0322-signed-message";
+
+/// Invoice query parameter holding the proof of the beneficiary ownership.
+pub const INVOICE_QUERY_PROOF: &str = "proof";
+
+const PROOF_WITNESS: u8 = 0;
+const PROOF_SEAL: u8 = 1;
+
+const METHOD_OPRET: u8 = 0;
+const METHOD_TAPRET: u8 = 1;
+
+/// Computes invoice id, which commits to the invoice string with all its
+/// parameters except the ownership proof itself.
+pub fn invoice_id(invoice: &RgbInvoice) -> [u8; 32] {
+    let mut invoice = invoice.clone();
+    invoice.unknown_query.shift_remove(INVOICE_QUERY_PROOF);
+    let mut engine = Sha256::from_tag(INVOICE_ID_TAG);
+    engine.input_raw(invoice.to_string().as_bytes());
+    engine.finish()
+}
+
+/// Verifies the proof of the beneficiary ownership contained in the invoice,
+/// failing if the invoice doesn't have it. The resolver is used to retrieve
+/// the output script of a blinded seal.
+pub fn verify_ownership(
+    invoice: &RgbInvoice,
+    resolver: &impl ResolveWitness,
+) -> Result<(), OwnershipError> {
+    OwnershipProof::from_invoice(invoice)?
+        .ok_or(OwnershipError::NoProof)?
+        .verify(invoice, resolver)
+}
+
+/// Constructs BIP-322 virtual transaction committing to the message and
+/// paying to the script whose ownership is proven.
+fn bip322_to_spend(script: &ScriptPubkey, message: &[u8]) -> Tx {
+    let mut engine = Sha256::from_tag(BIP322_TAG);
+    engine.input_raw(message);
+    let mut sig_script = vec![OpCode::PushBytes0 as u8, OpCode::PushBytes32 as u8];
+    sig_script.extend(engine.finish());
+    Tx {
+        version: TxVer::from_consensus_i32(0),
+        inputs: VarIntArray::from_checked(vec![TxIn {
+            prev_output: Outpoint::new(Txid::from([0u8; 32]), Vout::from_u32(0xFFFFFFFF)),
+            sig_script: SigScript::from_unsafe(sig_script),
+            sequence: SeqNo::ZERO,
+            witness: Witness::new(),
+        }]),
+        outputs: VarIntArray::from_checked(vec![TxOut::new(script.clone(), Sats::ZERO)]),
+        lock_time: LockTime::ZERO,
+    }
+}
+
+/// Constructs BIP-322 virtual transaction spending the output of
+/// [`bip322_to_spend`] transaction with the provided witness.
+fn bip322_to_sign(to_spend: Txid, witness: Witness) -> Tx {
+    Tx {
+        version: TxVer::from_consensus_i32(0),
+        inputs: VarIntArray::from_checked(vec![TxIn {
+            prev_output: Outpoint::new(to_spend, Vout::from_u32(0)),
+            sig_script: SigScript::empty(),
+            sequence: SeqNo::ZERO,
+            witness,
+        }]),
+        outputs: VarIntArray::from_checked(vec![TxOut::new(
+            ScriptPubkey::from_unsafe(vec![OpCode::Return as u8]),
+            Sats::ZERO,
+        )]),
+        lock_time: LockTime::ZERO,
+    }
+}
+
+/// Constructs PSBT of the BIP-322 `to_sign` transaction, which has to be
+/// signed with the key of the descriptor terminal.
+pub(crate) fn bip322_psbt<K, D: Descriptor<K>>(
+    descriptor: &D,
+    terminal: Terminal,
+    message: &[u8],
+) -> Psbt {
+    let script = descriptor
+        .derive(terminal.keychain, terminal.index)
+        .to_script_pubkey();
+    let to_spend = bip322_to_spend(&script, message);
+    let mut psbt = Psbt::create(PsbtVer::V0);
+    psbt.tx_version = TxVer::from_consensus_i32(0);
+    psbt.construct_input_expect(
+        Prevout::new(Outpoint::new(to_spend.txid(), Vout::from_u32(0)), Sats::ZERO),
+        descriptor,
+        terminal,
+        SeqNo::ZERO,
+    );
+    psbt.construct_output_expect(ScriptPubkey::from_unsafe(vec![OpCode::Return as u8]), Sats::ZERO);
+    psbt
+}
+
+/// Proof that the invoice beneficiary belongs to the wallet which has issued
+/// the invoice.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct OwnershipProof {
+    seal: Option<GraphSeal>,
+    witness: Witness,
+}
+
+impl OwnershipProof {
+    /// Constructs proof from the signed BIP-322 PSBT, produced with
+    /// [`bip322_psbt`] and finalized by the wallet.
+    pub(crate) fn with_psbt(seal: Option<GraphSeal>, psbt: &Psbt) -> Result<Self, OwnershipError> {
+        let tx = psbt.extract().map_err(|_| OwnershipError::Unsigned)?;
+        let witness = tx.inputs[0].witness.clone();
+        Ok(Self { seal, witness })
+    }
+
+    /// Seal revealed by the proof, if the invoice beneficiary is a blinded
+    /// seal.
+    pub fn seal(&self) -> Option<GraphSeal> { self.seal }
+
+    /// BIP-322 witness signing the invoice id.
+    pub fn witness(&self) -> &Witness { &self.witness }
+
+    /// Reads the proof from the invoice query parameters. Returns `None` if
+    /// the invoice doesn't contain the proof.
+    pub fn from_invoice(invoice: &RgbInvoice) -> Result<Option<Self>, OwnershipError> {
+        invoice
+            .unknown_query
+            .get(INVOICE_QUERY_PROOF)
+            .map(|value| Self::from_str(value))
+            .transpose()
+    }
+
+    /// Stores the proof in the invoice query parameters.
+    pub fn set_to_invoice(&self, invoice: &mut RgbInvoice) {
+        invoice
+            .unknown_query
+            .insert(INVOICE_QUERY_PROOF.to_owned(), self.to_string());
+    }
+
+    /// Verifies the proof against the invoice beneficiary. The resolver is
+    /// used to retrieve the output script of a blinded seal.
+    pub fn verify(
+        &self,
+        invoice: &RgbInvoice,
+        resolver: &impl ResolveWitness,
+    ) -> Result<(), OwnershipError> {
+        let script = match (invoice.beneficiary.into_inner(), self.seal) {
+            (Beneficiary::WitnessVout(pay2vout), None) => pay2vout.address.script_pubkey(),
+            (Beneficiary::BlindedSeal(secret), Some(seal)) => {
+                if seal.conceal() != secret {
+                    return Err(OwnershipError::SealMismatch);
+                }
+                let TxPtr::Txid(txid) = seal.txid else {
+                    return Err(OwnershipError::SealMismatch);
+                };
+                let outpoint = Outpoint::new(txid, seal.vout);
+                let tx = resolve_bitcoin_tx(resolver, txid)
+                    .map_err(|e| OwnershipError::Resolver(e.to_string()))?;
+                tx.outputs
+                    .get(seal.vout.to_usize())
+                    .ok_or(OwnershipError::UnknownOutput(outpoint))?
+                    .script_pubkey
+                    .clone()
+            }
+            _ => return Err(OwnershipError::SealMismatch),
+        };
+        self.verify_script(&script, &invoice_id(invoice))
+    }
+
+    /// Verifies BIP-322 signature of the message by the key controlling the
+    /// script.
+    fn verify_script(&self, script: &ScriptPubkey, message: &[u8]) -> Result<(), OwnershipError> {
+        let to_spend = bip322_to_spend(script, message);
+        let to_sign = bip322_to_sign(to_spend.txid(), self.witness.clone());
+        let prevout = TxOut::new(script.clone(), Sats::ZERO);
+        let mut sighasher =
+            SighashCache::new(&to_sign, vec![prevout]).expect("single input and prevout");
+        let elements = self.witness.elements().collect::<Vec<_>>();
+        if script.is_p2tr() {
+            let [sig] = elements[..] else {
+                return Err(OwnershipError::InvalidSignature);
+            };
+            let sig = Bip340Sig::from_bytes(sig).map_err(|_| OwnershipError::InvalidSignature)?;
+            let sighash = sighasher
+                .tap_sighash_key(0, sig.sighash_type)
+                .map_err(|_| OwnershipError::InvalidSignature)?;
+            let output_key = XOnlyPublicKey::from_slice(&script[2..])
+                .map_err(|_| OwnershipError::UnsupportedScript)?;
+            SECP256K1
+                .verify_schnorr(&sig.sig, &<[u8; 32]>::from(sighash), &output_key)
+                .map_err(|_| OwnershipError::InvalidSignature)
+        } else if script.is_p2wpkh() {
+            let [sig, pk] = elements[..] else {
+                return Err(OwnershipError::InvalidSignature);
+            };
+            let sig = LegacySig::from_bytes(sig).map_err(|_| OwnershipError::InvalidSignature)?;
+            let pk = CompressedPk::from_bytes(pk).map_err(|_| OwnershipError::InvalidSignature)?;
+            if ScriptPubkey::p2wpkh(WPubkeyHash::from(pk)) != *script {
+                return Err(OwnershipError::InvalidSignature);
+            }
+            let sighash = sighasher
+                .segwit_sighash(0, &ScriptCode::with_p2wpkh(script), Sats::ZERO, sig.sighash_type)
+                .map_err(|_| OwnershipError::InvalidSignature)?;
+            let sig: ecdsa::Signature = sig.sig;
+            SECP256K1
+                .verify_ecdsa(&sighash.into(), &sig, &pk)
+                .map_err(|_| OwnershipError::InvalidSignature)
+        } else {
+            Err(OwnershipError::UnsupportedScript)
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![];
+        match self.seal {
+            None => data.push(PROOF_WITNESS),
+            Some(seal) => {
+                let TxPtr::Txid(txid) = seal.txid else {
+                    unreachable!("ownership proofs are constructed for the seals with known txid")
+                };
+                data.push(PROOF_SEAL);
+                data.push(match seal.method {
+                    bp::dbc::Method::OpretFirst => METHOD_OPRET,
+                    bp::dbc::Method::TapretFirst => METHOD_TAPRET,
+                });
+                data.extend(txid.to_byte_array());
+                data.extend(seal.vout.to_u32().to_le_bytes());
+                data.extend(seal.blinding.to_le_bytes());
+            }
+        }
+        data.extend(self.witness.consensus_serialize());
+        data
+    }
+
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        let (tag, data) = data.split_first()?;
+        let (seal, data) = match *tag {
+            PROOF_WITNESS => (None, data),
+            PROOF_SEAL => {
+                let (method, data) = data.split_first()?;
+                let method = match *method {
+                    METHOD_OPRET => bp::dbc::Method::OpretFirst,
+                    METHOD_TAPRET => bp::dbc::Method::TapretFirst,
+                    _ => return None,
+                };
+                let (txid, data) = data.split_first_chunk::<32>()?;
+                let (vout, data) = data.split_first_chunk::<4>()?;
+                let (blinding, data) = data.split_first_chunk::<8>()?;
+                let seal = BlindSeal::with_blinding(
+                    method,
+                    TxPtr::Txid(Txid::from(*txid)),
+                    Vout::from_u32(u32::from_le_bytes(*vout)),
+                    u64::from_le_bytes(*blinding),
+                );
+                (Some(seal), data)
+            }
+            _ => return None,
+        };
+        let witness = Witness::consensus_deserialize(data).ok()?;
+        Some(Self { seal, witness })
+    }
+}
+
+impl Display for OwnershipProof {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { f.write_str(&self.to_bytes().to_hex()) }
+}
+
+impl FromStr for OwnershipProof {
+    type Err = OwnershipError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Vec::<u8>::from_hex(s)
+            .ok()
+            .and_then(|data| Self::from_bytes(&data))
+            .ok_or_else(|| OwnershipError::InvalidEncoding(s.to_owned()))
+    }
+}
@@ -0,0 +1,84 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Invoices paid by the wallet, protecting the user from paying the same
+//! invoice twice.
+//!
+//! An invoice with a blinded seal is meant to be paid once; repeating the
+//! transfer to the same seal sends the state to the beneficiary again. The
+//! payments are not registered apart from the stock: the stock keeps the
+//! transitions assigning state to the blinded seals together with the status
+//! of their witness transactions, and the invoice counts as paid while any of
+//! these witness transactions is mined or is not known to be dropped. Witness
+//! transactions which were never published, or were replaced, are archived by
+//! the next update of the witnesses, after which the invoice can be paid again.
+//! Invoices paying to witness outputs are not tracked.
+
+use std::collections::BTreeSet;
+
+use bpstd::Txid;
+use rgbstd::invoice::{Beneficiary, RgbInvoice};
+use rgbstd::persistence::{ContractStateRead, IndexProvider, StashProvider, StateProvider, Stock};
+use rgbstd::vm::WitnessOrd;
+use rgbstd::XChain;
+
+/// Returns the witness transaction by which the stock has paid the invoice,
+/// if the transaction is mined or is not known to be dropped. If the invoice
+/// was paid more than once, mined payments are preferred. Always returns `None`
+/// for the invoices paying to witness outputs.
+pub fn invoice_payment<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &Stock<S, H, P>,
+    invoice: &RgbInvoice,
+) -> Option<Txid> {
+    let Beneficiary::BlindedSeal(seal) = invoice.beneficiary.into_inner() else {
+        return None;
+    };
+    let index = stock.as_index_provider();
+    let opids = index
+        .opouts_by_terminals([XChain::with(invoice.chain_network().layer1(), seal)])
+        .ok()?
+        .into_iter()
+        .map(|opout| opout.op)
+        .collect::<BTreeSet<_>>();
+    let mut payments = Vec::new();
+    for opid in opids {
+        let Ok(bundle_id) = index.bundle_id_for_op(opid) else {
+            continue;
+        };
+        let Ok((witness_ids, contract_id)) = index.bundle_info(bundle_id) else {
+            continue;
+        };
+        let witness_ids = witness_ids.collect::<Vec<_>>();
+        let Ok(state) = stock.contract_state(contract_id) else {
+            continue;
+        };
+        for witness_id in witness_ids {
+            if let (
+                XChain::Bitcoin(txid) | XChain::Liquid(txid),
+                Some(ord @ (WitnessOrd::Mined(_) | WitnessOrd::Tentative)),
+            ) = (witness_id, state.witness_ord(witness_id))
+            {
+                payments.push((ord, txid));
+            }
+        }
+    }
+    payments.into_iter().min().map(|(_, txid)| txid)
+}
@@ -19,37 +19,642 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Reverse;
 use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
 use std::marker::PhantomData;
+use std::str::FromStr;
+use std::{iter, slice};
 
+use amplify::confinement::{Confined, LargeOrdSet, SmallVec, U32};
 use bp::dbc::tapret::TapretProof;
-use bp::seals::txout::ExplicitSeal;
-use bp::{Outpoint, Sats, ScriptPubkey, Vout};
-use bpstd::{psbt, Address};
+use bp::seals::txout::{CloseMethod, ExplicitSeal, TxPtr};
+use bp::{LockTime, Outpoint, Sats, ScriptPubkey, SeqNo, Tx, Vout};
+use bpstd::seals::SecretSeal;
+use bpstd::{psbt, Address, Derive, Descriptor, Idx, IdxBase, Network, NormalIndex, Terminal};
 use bpwallet::{Layer2, Layer2Tx, NoLayer2, TxRow, Wallet, WalletDescr};
 use psrgbt::{
-    Beneficiary as BpBeneficiary, Psbt, PsbtConstructor, PsbtMeta, RgbPsbt, TapretKeyError,
-    TxParams,
+    Beneficiary as BpBeneficiary, ConstructionError, OutputOrdering, OutputRole, Prevout, Psbt,
+    PsbtConstructor, PsbtMeta, PsbtVer, RgbExt, RgbOutExt, RgbOutputOrdering, RgbPsbt,
+    TapretKeyError, TxParams, Utxo,
 };
-use rgbstd::containers::Transfer;
+use rgbstd::containers::{Batch, Consignment, Fascia, Transfer, VelocityHint};
 use rgbstd::interface::AssignmentsFilter;
 use rgbstd::invoice::{Amount, Beneficiary, InvoiceState, RgbInvoice};
 use rgbstd::persistence::{IndexProvider, StashProvider, StateProvider, Stock};
 use rgbstd::validation::ResolveWitness;
-use rgbstd::{ContractId, DataState, XChain, XOutpoint};
+use rgbstd::{
+    Assign, AssignmentType, BlindingFactor, ContractId, DataState, ExposedState, GraphSeal,
+    Operation, RevealedValue, TypedAssigns, XChain, XOutpoint, XOutputSeal,
+};
+use strict_types::encoding::StrictSerialize;
+use strict_types::FieldName;
 
+use crate::bump::{
+    compose_bump, cpfp_fee, estimate_vsize, estimate_vsize_for, outpoint_seals, wallet_outpoint,
+};
+use crate::consolidate::compose_consolidation;
+use crate::descriptor::fill_tap_outputs;
 use crate::invoice::NonFungible;
+use crate::plan::{PLAN_BENEFICIARY_VOUT, PLAN_CHANGE_VOUT, PLAN_WITNESS_SIZE_ESTIMATE};
 use crate::validation::WitnessResolverError;
 use crate::vm::{WitnessOrd, XWitnessTx};
+#[cfg(feature = "serde")]
+use crate::ContractCall;
 use crate::{
-    CompletionError, CompositionError, DescriptorRgb, PayError, RgbKeychain, Txid,
+    invoice_payment, AcceptError, BasketInvoice, CompletionError, CompositionError,
+    ConsolidationReport, ConsolidationScope, DescriptorRgb, ImportedUtxos, InvoiceExpiry,
+    NetworkGuard, PayError, RequestedVelocity, RgbKeychain, SupplyOperation, TransferPlan, Txid,
     WalletOutpointsFilter, WalletUnspentFilter, WalletWitnessFilter, XWitnessId,
 };
 
+/// Invoice query parameter specifying the minimal amount accepted by the
+/// beneficiary.
+pub const INVOICE_QUERY_MIN: &str = "min";
+/// Invoice query parameter specifying the maximal amount accepted by the
+/// beneficiary.
+pub const INVOICE_QUERY_MAX: &str = "max";
+
+/// Invoice query parameter listing additional blinded seals of the
+/// beneficiary, across which the invoiced amount must be split.
+pub const INVOICE_QUERY_SPLIT: &str = "split";
+
+/// Invoice query parameter specifying the amount of sats which must be paid to
+/// the witness output of the beneficiary.
+pub const INVOICE_QUERY_SATS: &str = "sats";
+
+/// Range of fungible amounts accepted by an invoice beneficiary, allowing the
+/// payer to pay an amount different from the one stated in the invoice.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct AmountRange {
+    pub min: Option<Amount>,
+    pub max: Option<Amount>,
+}
+
+impl Display for AmountRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(min) = self.min {
+            write!(f, "{min}")?;
+        }
+        f.write_str("..")?;
+        if let Some(max) = self.max {
+            write!(f, "{max}")?;
+        }
+        Ok(())
+    }
+}
+
+impl AmountRange {
+    /// Reads amount range from the invoice query parameters. Returns `None` if
+    /// the invoice doesn't specify a range.
+    pub fn from_invoice(invoice: &RgbInvoice) -> Result<Option<Self>, CompositionError> {
+        let parse = |name: &str| {
+            invoice
+                .unknown_query
+                .get(name)
+                .map(|value| {
+                    value.parse::<u64>().map(Amount::from).map_err(|_| {
+                        CompositionError::InvalidAmountRange(format!("{name}={value}"))
+                    })
+                })
+                .transpose()
+        };
+        let range = AmountRange {
+            min: parse(INVOICE_QUERY_MIN)?,
+            max: parse(INVOICE_QUERY_MAX)?,
+        };
+        match (range.min, range.max) {
+            (None, None) => Ok(None),
+            (Some(min), Some(max)) if min > max => {
+                Err(CompositionError::InvalidAmountRange(range.to_string()))
+            }
+            _ => Ok(Some(range)),
+        }
+    }
+
+    /// Stores the range in the invoice query parameters.
+    pub fn set_to_invoice(&self, invoice: &mut RgbInvoice) {
+        for (name, value) in [(INVOICE_QUERY_MIN, self.min), (INVOICE_QUERY_MAX, self.max)] {
+            match value {
+                Some(value) => {
+                    invoice
+                        .unknown_query
+                        .insert(name.to_owned(), value.value().to_string());
+                }
+                None => {
+                    invoice.unknown_query.shift_remove(name);
+                }
+            }
+        }
+    }
+
+    pub fn contains(&self, amount: Amount) -> bool {
+        self.min.map_or(true, |min| amount >= min) && self.max.map_or(true, |max| amount <= max)
+    }
+}
+
+/// Additional blinded seals of an invoice beneficiary. The invoiced amount is
+/// split evenly between the invoice beneficiary seal and these seals, such
+/// that the receiver gets several allocations which may be spent in parallel
+/// later, without extra on-chain transactions.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct SplitSeals(Vec<SecretSeal>);
+
+impl Display for SplitSeals {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut iter = self.0.iter();
+        if let Some(first) = iter.next() {
+            write!(f, "{first}")?;
+        }
+        for seal in iter {
+            write!(f, ",{seal}")?;
+        }
+        Ok(())
+    }
+}
+
+impl SplitSeals {
+    pub fn new(seals: impl IntoIterator<Item = SecretSeal>) -> Self {
+        Self(seals.into_iter().collect())
+    }
+
+    pub fn seals(&self) -> &[SecretSeal] { &self.0 }
+
+    /// Reads split seals from the invoice query parameters. Returns `None` if
+    /// the invoice doesn't request a split payment.
+    pub fn from_invoice(invoice: &RgbInvoice) -> Result<Option<Self>, CompositionError> {
+        let Some(value) = invoice.unknown_query.get(INVOICE_QUERY_SPLIT) else {
+            return Ok(None);
+        };
+        let seals = value
+            .split(',')
+            .map(SecretSeal::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| CompositionError::InvalidSplit(value.clone()))?;
+        let beneficiary = match invoice.beneficiary.into_inner() {
+            Beneficiary::BlindedSeal(seal) => seal,
+            Beneficiary::WitnessVout(_) => return Err(CompositionError::SplitUnsupported),
+        };
+        let unique = seals.iter().collect::<BTreeSet<_>>();
+        if unique.len() != seals.len() || unique.contains(&beneficiary) {
+            return Err(CompositionError::InvalidSplit(value.clone()));
+        }
+        Ok(Some(Self(seals)))
+    }
+
+    /// Stores the seals in the invoice query parameters.
+    pub fn set_to_invoice(&self, invoice: &mut RgbInvoice) {
+        if self.0.is_empty() {
+            invoice.unknown_query.shift_remove(INVOICE_QUERY_SPLIT);
+        } else {
+            invoice
+                .unknown_query
+                .insert(INVOICE_QUERY_SPLIT.to_owned(), self.to_string());
+        }
+    }
+
+    /// Splits the amount between the invoice beneficiary and the additional
+    /// seals; the remainder of the division goes to the beneficiary. Parts
+    /// which are zero are omitted.
+    pub fn split(
+        &self,
+        beneficiary: SecretSeal,
+        amount: Amount,
+    ) -> impl Iterator<Item = (SecretSeal, Amount)> + '_ {
+        let count = self.0.len() as u64 + 1;
+        let part = amount.value() / count;
+        let remainder = amount.value() % count;
+        [(beneficiary, part + remainder)]
+            .into_iter()
+            .chain(self.0.iter().map(move |seal| (*seal, part)))
+            .filter(|(_, amount)| *amount > 0)
+            .map(|(seal, amount)| (seal, Amount::from(amount)))
+    }
+
+    /// Replaces the fungible assignment to the invoice beneficiary in the
+    /// composed batch with the assignments to each of the split seals. The
+    /// blinding factors are chosen such that the sum of the Pedersen
+    /// commitments is not changed.
+    fn apply(&self, batch: &mut Batch, invoice: &RgbInvoice) -> Result<(), CompositionError> {
+        let Beneficiary::BlindedSeal(beneficiary) = invoice.beneficiary.into_inner() else {
+            return Err(CompositionError::SplitUnsupported);
+        };
+        if !matches!(invoice.owned_state, InvoiceState::Amount(_)) {
+            return Err(CompositionError::SplitUnsupported);
+        }
+        let layer1 = invoice.layer1();
+        let target = XChain::with(layer1, beneficiary);
+        let main = &mut batch.main;
+        for info in iter::once(&mut main.first).chain(main.second.as_mut()) {
+            let mut modified = false;
+            for assigns in info.transition.assignments.values_mut() {
+                let Some(assigns) = assigns.as_fungible_mut() else {
+                    continue;
+                };
+                let mut list = Vec::with_capacity(assigns.len() + self.0.len());
+                for assign in assigns.iter() {
+                    match assign {
+                        Assign::ConfidentialSeal { seal, state, .. } if *seal == target => {
+                            let parts = self
+                                .split(beneficiary, Amount::from(state.value))
+                                .collect::<Vec<_>>();
+                            let mut blindings = Vec::with_capacity(parts.len());
+                            for (no, (seal, amount)) in parts.iter().enumerate() {
+                                let blinding = if no + 1 == parts.len() {
+                                    BlindingFactor::zero_balanced(
+                                        [state.blinding],
+                                        blindings.iter().copied(),
+                                    )
+                                    .expect("blinding factor overflow is not possible")
+                                } else {
+                                    BlindingFactor::random()
+                                };
+                                blindings.push(blinding);
+                                list.push(Assign::ConfidentialSeal {
+                                    seal: XChain::with(layer1, *seal),
+                                    state: RevealedValue::with_blinding(
+                                        *amount, blinding, state.tag,
+                                    ),
+                                    lock: default!(),
+                                });
+                            }
+                            modified = true;
+                        }
+                        assign => list.push(assign.clone()),
+                    }
+                }
+                list.sort();
+                *assigns = Confined::try_from(list).map_err(|_| {
+                    CompositionError::InvalidSplit(s!("too many assignments in a transition"))
+                })?;
+            }
+            if modified {
+                info.id = info.transition.id();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Amount of sats which must be paid to the witness output of an invoice
+/// beneficiary, overriding the default amount chosen by the payer. Receivers
+/// may require it to have a budget for spending the received allocations.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct WitnessSats(Sats);
+
+impl Display for WitnessSats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { Display::fmt(&self.0, f) }
+}
+
+impl WitnessSats {
+    pub fn new(sats: Sats) -> Self { Self(sats) }
+
+    pub fn sats(&self) -> Sats { self.0 }
+
+    /// Reads the required amount of sats from the invoice query parameters.
+    /// Returns `None` if the invoice doesn't require a specific amount.
+    pub fn from_invoice(invoice: &RgbInvoice) -> Result<Option<Self>, CompositionError> {
+        let Some(value) = invoice.unknown_query.get(INVOICE_QUERY_SATS) else {
+            return Ok(None);
+        };
+        let sats = value
+            .parse::<u64>()
+            .ok()
+            .filter(|sats| *sats > 0)
+            .ok_or_else(|| CompositionError::InvalidWitnessSats(value.clone()))?;
+        if matches!(invoice.beneficiary.into_inner(), Beneficiary::BlindedSeal(_)) {
+            return Err(CompositionError::WitnessSatsUnsupported);
+        }
+        Ok(Some(Self(Sats::from_sats(sats))))
+    }
+
+    /// Stores the amount in the invoice query parameters.
+    pub fn set_to_invoice(&self, invoice: &mut RgbInvoice) {
+        invoice
+            .unknown_query
+            .insert(INVOICE_QUERY_SATS.to_owned(), self.0.sats().to_string());
+    }
+
+    /// Checks that the output paying to the invoice beneficiary has the amount
+    /// of sats required by the invoice.
+    pub fn check(&self, sats: Sats) -> Result<(), CompositionError> {
+        if sats != self.0 {
+            return Err(CompositionError::WitnessSatsMismatch(self.0, sats));
+        }
+        Ok(())
+    }
+}
+
+/// Determines the amount of sats paid to the beneficiary witness output: the
+/// one required by the invoice, if any, or the default chosen by the payer.
+fn beneficiary_sats(invoice: &RgbInvoice, default: Sats) -> Result<Sats, CompositionError> {
+    Ok(WitnessSats::from_invoice(invoice)?.map_or(default, |sats| sats.sats()))
+}
+
+/// Determines method closing the seals created by a transfer, checking that
+/// the method requested with the transfer parameters can be used by the wallet
+/// descriptor. Tapret commitments require a taproot output of the wallet,
+/// while opret commitments can be made by any wallet.
+fn transfer_close_method<K, D: DescriptorRgb<K>>(
+    descriptor: &D,
+    requested: Option<CloseMethod>,
+) -> Result<CloseMethod, CompositionError> {
+    let method = descriptor.seal_close_method();
+    match requested {
+        None => Ok(method),
+        Some(CloseMethod::OpretFirst) => Ok(CloseMethod::OpretFirst),
+        Some(CloseMethod::TapretFirst) if method == CloseMethod::TapretFirst => {
+            Ok(CloseMethod::TapretFirst)
+        }
+        Some(requested) => Err(CompositionError::CloseMethodUnsupported(requested)),
+    }
+}
+
+/// Chooses the method given to the stock composing a transfer which creates
+/// seals closed by `method`. The stock puts the spent seals closed by other
+/// methods into a separate state transition, but it can't compose a transfer
+/// none of which spent seals is closed by the given method. In this case the
+/// method of the spent seals is used, and the seals created by the transfer are
+/// switched to `method` with [`retarget_seals`].
+fn compose_close_method(prev_outputs: &BTreeSet<XOutputSeal>, method: CloseMethod) -> CloseMethod {
+    let mut methods = prev_outputs
+        .iter()
+        .map(|output| output.as_reduced_unsafe().method);
+    if methods.clone().any(|m| m == method) {
+        return method;
+    }
+    methods.next().unwrap_or(method)
+}
+
+/// Composes the transfer paying the invoice from `prev_outputs` with the
+/// stock, such that the seals it creates are closed by `method`.
+fn compose_transfer<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &Stock<S, H, P>,
+    invoice: &RgbInvoice,
+    prev_outputs: &BTreeSet<XOutputSeal>,
+    method: CloseMethod,
+    beneficiary_vout: Option<Vout>,
+    allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+) -> Result<Batch, CompositionError> {
+    let compose_method = compose_close_method(prev_outputs, method);
+    let mut batch = stock
+        .compose(invoice, prev_outputs.iter().copied(), compose_method, beneficiary_vout, allocator)
+        .map_err(|e| e.to_string())?;
+    if compose_method != method {
+        retarget_seals(&mut batch, method, beneficiary_vout);
+    }
+    Ok(batch)
+}
+
+/// Switches the seals defined by the composed batch over the outputs of the
+/// witness transaction to the close `method`. The seal of the invoice
+/// beneficiary at `beneficiary_vout` keeps the method requested by the
+/// receiver.
+fn retarget_seals(batch: &mut Batch, method: CloseMethod, beneficiary_vout: Option<Vout>) {
+    for dichotomy in iter::once(&mut batch.main).chain(batch.blanks.iter_mut()) {
+        for info in iter::once(&mut dichotomy.first).chain(dichotomy.second.as_mut()) {
+            let mut modified = false;
+            for assigns in info.transition.assignments.values_mut() {
+                modified |= match assigns {
+                    TypedAssigns::Declarative(list) => {
+                        retarget_assigns(list, method, beneficiary_vout)
+                    }
+                    TypedAssigns::Fungible(list) => {
+                        retarget_assigns(list, method, beneficiary_vout)
+                    }
+                    TypedAssigns::Structured(list) => {
+                        retarget_assigns(list, method, beneficiary_vout)
+                    }
+                    TypedAssigns::Attachment(list) => {
+                        retarget_assigns(list, method, beneficiary_vout)
+                    }
+                };
+            }
+            if modified {
+                info.id = info.transition.id();
+            }
+        }
+    }
+}
+
+fn retarget_assigns<State: ExposedState>(
+    assigns: &mut SmallVec<Assign<State, GraphSeal>>,
+    method: CloseMethod,
+    beneficiary_vout: Option<Vout>,
+) -> bool {
+    let mut modified = false;
+    let mut list = assigns.iter().cloned().collect::<Vec<_>>();
+    for assign in &mut list {
+        let (Assign::Revealed { seal, .. } | Assign::ConfidentialState { seal, .. }) = assign
+        else {
+            continue;
+        };
+        let (XChain::Bitcoin(seal) | XChain::Liquid(seal)) = seal else {
+            continue;
+        };
+        if seal.txid == TxPtr::WitnessTx && Some(seal.vout) != beneficiary_vout {
+            modified |= seal.method != method;
+            seal.method = method;
+        }
+    }
+    if modified {
+        // Assignments are ordered by their concealed seals, which depend on
+        // the close method
+        list.sort();
+        *assigns = Confined::try_from(list).expect("same number of assignments");
+    }
+    modified
+}
+
+/// Reveals all seals of the consignment which are known to the stash, not
+/// only the ones listed as the consignment terminals. This is required to
+/// discover all allocations of a split payment (see [`SplitSeals`]), since the
+/// consignment may list just a single terminal seal per bundle.
+pub fn reveal_known_seals<
+    const TRANSFER: bool,
+    S: StashProvider,
+    H: StateProvider,
+    P: IndexProvider,
+>(
+    stock: &Stock<S, H, P>,
+    mut consignment: Consignment<TRANSFER>,
+) -> Result<Consignment<TRANSFER>, AcceptError> {
+    let stash = stock.as_stash_provider();
+    let mut bundles = LargeOrdSet::with_capacity(consignment.bundles.len());
+    for mut witness_bundle in consignment.bundles {
+        let secrets = witness_bundle
+            .anchored_bundles
+            .bundles()
+            .flat_map(|bundle| {
+                bundle
+                    .known_transitions
+                    .values()
+                    .flat_map(|t| t.assignments.values())
+                    .flat_map(|a| a.to_confidential_seals())
+                    .map(move |secret| (bundle.bundle_id(), secret))
+            })
+            .collect::<BTreeSet<_>>();
+        for (bundle_id, secret) in secrets {
+            if let Some(seal) = stash.seal_secret(secret).map_err(|e| e.to_string())? {
+                witness_bundle.reveal_seal(bundle_id, seal);
+            }
+        }
+        bundles.push(witness_bundle).ok();
+    }
+    consignment.bundles = bundles;
+    Ok(consignment)
+}
+
+/// Checks that the invoice and the paid contract are for the wallet network.
+fn check_networks<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    network: Network,
+    stock: &Stock<S, H, P>,
+    invoice: &RgbInvoice,
+    contract_id: ContractId,
+) -> Result<(), CompositionError> {
+    let genesis = stock
+        .as_stash_provider()
+        .genesis(contract_id)
+        .map_err(|e| e.to_string())?;
+    NetworkGuard::new(network)
+        .check_invoice(invoice)
+        .check_genesis(genesis)
+        .finish()?;
+    Ok(())
+}
+
+/// Checks that the invoice wasn't paid before, unless duplicate payments are
+/// allowed by the transfer parameters.
+fn check_duplicate<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &Stock<S, H, P>,
+    invoice: &RgbInvoice,
+    params: &TransferParams,
+) -> Result<(), CompositionError> {
+    if params.allow_duplicate {
+        return Ok(());
+    }
+    match (invoice.beneficiary.into_inner(), invoice_payment(stock, invoice)) {
+        (Beneficiary::BlindedSeal(seal), Some(txid)) => {
+            Err(CompositionError::DuplicatePayment(seal, txid))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks that the invoice hasn't expired at the time and the chain tip height
+/// provided by the transfer parameters.
+fn check_expiry(invoice: &RgbInvoice, params: &TransferParams) -> Result<(), CompositionError> {
+    if InvoiceExpiry::from_invoice(invoice)?.is_expired(params.now, params.tip_height) {
+        return Err(CompositionError::InvoiceExpired);
+    }
+    Ok(())
+}
+
+/// Determines the state which should be paid for the invoice, taking into
+/// account the amount chosen by the payer and the invoice amount range.
+///
+/// Invoices without a stated amount ("any amount" invoices) are paid with the
+/// amount chosen by the payer or, if none, with the minimal amount of the
+/// range. Invoices with a stated amount may be paid with a different amount
+/// only if they specify a range.
+#[allow(clippy::result_large_err)]
+fn negotiate_amount<'invoice>(
+    invoice: &'invoice RgbInvoice,
+    amount: Option<Amount>,
+) -> Result<Cow<'invoice, RgbInvoice>, CompositionError> {
+    let range = AmountRange::from_invoice(invoice)?;
+    let stated = match invoice.owned_state {
+        InvoiceState::Amount(stated) => Some(stated),
+        InvoiceState::Void => None,
+        InvoiceState::Data(_) | InvoiceState::Attach(_) => {
+            if amount.is_some() || range.is_some() {
+                return Err(CompositionError::Unsupported);
+            }
+            return Ok(Cow::Borrowed(invoice));
+        }
+    };
+    let paid = match (amount, stated, range) {
+        (Some(amount), Some(stated), None) if amount != stated => {
+            return Err(CompositionError::AmountNotNegotiable(stated));
+        }
+        (Some(amount), _, _) => amount,
+        (None, Some(stated), _) => stated,
+        (None, None, Some(AmountRange { min: Some(min), .. })) => min,
+        // Keeping invoice without amount as is, such that the state selection
+        // reports it as unsupported
+        (None, None, _) => return Ok(Cow::Borrowed(invoice)),
+    };
+    if let Some(range) = range {
+        if !range.contains(paid) {
+            return Err(CompositionError::AmountOutOfRange(paid, range));
+        }
+    }
+    if stated == Some(paid) {
+        return Ok(Cow::Borrowed(invoice));
+    }
+    let mut invoice = invoice.clone();
+    invoice.owned_state = InvoiceState::Amount(paid);
+    Ok(Cow::Owned(invoice))
+}
+
+/// Strategy for selecting the outputs spent by a transfer.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Display)]
+#[display(lowercase)]
+pub enum CoinSelection {
+    /// Spends the largest allocations of the paid contract first, regardless
+    /// of the state of other contracts assigned to the same outputs.
+    #[default]
+    Largest,
+
+    /// Prefers the outputs which don't hold the state of other contracts, and
+    /// pays the fee from the outputs without any RGB state, such that blank
+    /// transitions are created only when they can't be avoided.
+    Isolated,
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct TransferParams {
     pub tx: TxParams,
     pub min_amount: Sats,
+    /// Amount of fungible state to pay, if it differs from the one stated in
+    /// the invoice. Must fit the range of amounts accepted by the invoice.
+    pub amount: Option<Amount>,
+    /// Velocity preferences overriding the velocity hints provided by the
+    /// contract supplements for specific assignment types.
+    pub velocity_hints: BTreeMap<(ContractId, AssignmentType), VelocityHint>,
+    /// Sequence numbers of specific transaction inputs, overriding the one
+    /// provided by the transaction parameters.
+    pub sequences: BTreeMap<Outpoint, SeqNo>,
+    /// Outpoints which must not be spent, neither as a source of RGB state
+    /// nor as bitcoin coins paying the fee.
+    pub frozen: BTreeSet<Outpoint>,
+    /// Strategy for ordering the outputs of the witness transaction.
+    pub ordering: OutputOrdering,
+    /// Feerate of the witness transaction, in sats per virtual byte. If set,
+    /// the fee is computed from the estimated size of the signed witness
+    /// transaction, including the commitment outputs, and the fee from the
+    /// transaction parameters is ignored.
+    pub feerate: Option<u64>,
+    /// Outputs not derived from the wallet descriptor, which the transfer may
+    /// spend together with the wallet outputs.
+    pub imported: ImportedUtxos,
+    /// Strategy for selecting the outputs spent by the transfer.
+    pub coin_selection: CoinSelection,
+    /// Method closing the seals created by the transfer, overriding the one of
+    /// the wallet descriptor. If not set, the descriptor method is used,
+    /// unless it is tapret and the change output is not a taproot one, in
+    /// which case opret is used.
+    pub close_method: Option<CloseMethod>,
+    /// Whether invoices which were already paid may be paid again.
+    pub allow_duplicate: bool,
+    /// Current UNIX timestamp, at which the invoice must not be expired. If
+    /// not set, the invoice expiry time is not checked.
+    pub now: Option<i64>,
+    /// Current height of the chain tip, which must be below the invoice expiry
+    /// height. If not set, the invoice expiry height is not checked.
+    pub tip_height: Option<u32>,
 }
 
 impl TransferParams {
@@ -57,13 +662,113 @@ impl TransferParams {
         TransferParams {
             tx: TxParams::with(fee),
             min_amount,
+            amount: None,
+            velocity_hints: none!(),
+            sequences: none!(),
+            frozen: none!(),
+            ordering: default!(),
+            feerate: None,
+            imported: none!(),
+            coin_selection: default!(),
+            close_method: None,
+            allow_duplicate: false,
+            now: None,
+            tip_height: None,
         }
     }
+
+    /// Constructs parameters paying fee at the `feerate` (in sats per virtual
+    /// byte) instead of a fixed fee.
+    pub fn with_feerate(feerate: u64, min_amount: Sats) -> Self {
+        let mut params = TransferParams::with(Sats::ZERO, min_amount);
+        params.feerate = Some(feerate);
+        params
+    }
+
+    /// Sets absolute lock time of the witness transaction.
+    pub fn set_lock_time(&mut self, lock_time: LockTime) { self.tx.lock_time = Some(lock_time); }
+
+    /// Sets sequence number of the transaction input spending the outpoint.
+    /// Returns previously set sequence number, if any.
+    pub fn set_sequence(&mut self, outpoint: Outpoint, seq_no: SeqNo) -> Option<SeqNo> {
+        self.sequences.insert(outpoint, seq_no)
+    }
+
+    /// Sets velocity preference for an assignment type of a contract, which
+    /// is used instead of the velocity hint from the contract supplement when
+    /// the change is allocated. Returns previously set preference, if any.
+    pub fn set_velocity_hint(
+        &mut self,
+        contract_id: ContractId,
+        assignment_type: AssignmentType,
+        hint: VelocityHint,
+    ) -> Option<VelocityHint> {
+        self.velocity_hints
+            .insert((contract_id, assignment_type), hint)
+    }
+
+    /// Returns velocity for an assignment type of a contract, taking into
+    /// account the wallet preferences.
+    pub fn velocity_hint(
+        &self,
+        contract_id: ContractId,
+        assignment_type: AssignmentType,
+        default: VelocityHint,
+    ) -> VelocityHint {
+        self.velocity_hints
+            .get(&(contract_id, assignment_type))
+            .copied()
+            .unwrap_or(default)
+    }
+}
+
+/// Applies per-input sequence numbers to the PSBT and checks that the
+/// absolute lock time of the transaction is enforceable and doesn't prevent
+/// the witness transaction from being mined before the invoice expires.
+fn apply_timelocks(
+    psbt: &mut Psbt,
+    params: &TransferParams,
+    expiry: Option<i64>,
+) -> Result<(), CompositionError> {
+    for (outpoint, seq_no) in &params.sequences {
+        let input = psbt
+            .inputs_mut()
+            .find(|input| input.previous_outpoint == *outpoint)
+            .ok_or(CompositionError::UnknownInput(*outpoint))?;
+        input.sequence_number = Some(*seq_no);
+    }
+
+    let Some(lock_time) = params
+        .tx
+        .lock_time
+        .filter(|lock_time| *lock_time != LockTime::ZERO)
+    else {
+        return Ok(());
+    };
+    if psbt.inputs().all(|input| {
+        input
+            .sequence_number
+            .unwrap_or(SeqNo::from_consensus_u32(u32::MAX))
+            .to_consensus_u32()
+            == u32::MAX
+    }) {
+        return Err(CompositionError::LockTimeDisabled);
+    }
+    if let Some(expiry) = expiry {
+        if !lock_time.is_height_based() && lock_time.to_consensus_u32() as i64 > expiry {
+            return Err(CompositionError::LockTimeAfterExpiry(
+                lock_time.to_consensus_u32(),
+                expiry,
+            ));
+        }
+    }
+    Ok(())
 }
 
 struct ContractOutpointsFilter<
     'stock,
     'wallet,
+    'params,
     W: WalletProvider<K, L2> + ?Sized,
     K,
     S: StashProvider,
@@ -75,6 +780,8 @@ struct ContractOutpointsFilter<
     contract_id: ContractId,
     stock: &'stock Stock<S, H, P>,
     wallet: &'wallet W,
+    frozen: &'params BTreeSet<Outpoint>,
+    imported: &'params ImportedUtxos,
     _key_phantom: PhantomData<K>,
     _layer2_phantom: PhantomData<L2>,
 }
@@ -82,24 +789,651 @@ struct ContractOutpointsFilter<
 impl<
         'stock,
         'wallet,
+        'params,
         W: WalletProvider<K, L2> + ?Sized,
         K,
         S: StashProvider,
         H: StateProvider,
         P: IndexProvider,
         L2: Layer2,
-    > AssignmentsFilter for ContractOutpointsFilter<'stock, 'wallet, W, K, S, H, P, L2>
+    > AssignmentsFilter for ContractOutpointsFilter<'stock, 'wallet, 'params, W, K, S, H, P, L2>
 where W::Descr: DescriptorRgb<K>
 {
     fn should_include(&self, output: impl Into<XOutpoint>, id: Option<XWitnessId>) -> bool {
         let output = output.into();
-        if !self.wallet.filter_unspent().should_include(output, id) {
+        if self.frozen.contains(output.as_reduced_unsafe()) {
+            return false;
+        }
+        if !self.imported.is_imported(*output.as_reduced_unsafe())
+            && !self.wallet.filter_unspent().should_include(output, id)
+        {
             return false;
         }
         matches!(self.stock.contract_assignments_for(self.contract_id, [output]), Ok(list) if !list.is_empty())
     }
 }
 
+fn select_rgb_state<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &Stock<S, H, P>,
+    invoice: &RgbInvoice,
+    filter: impl AssignmentsFilter,
+    selection: CoinSelection,
+) -> Result<BTreeSet<XOutputSeal>, CompositionError> {
+    let contract_id = invoice.contract.ok_or(CompositionError::NoContract)?;
+    let iface_name = invoice.iface.clone().ok_or(CompositionError::NoIface)?;
+    let iface = stock.iface(iface_name.clone()).map_err(|e| e.to_string())?;
+    let operation = invoice
+        .operation
+        .as_ref()
+        .or(iface.default_operation.as_ref())
+        .ok_or(CompositionError::NoOperation)?;
+
+    let assignment_name = invoice
+        .assignment
+        .as_ref()
+        .or_else(|| {
+            iface
+                .transitions
+                .get(operation)
+                .and_then(|t| t.default_assignment.as_ref())
+        })
+        .cloned()
+        .ok_or(CompositionError::NoAssignment)?;
+
+    let contract = stock
+        .contract_iface(contract_id, iface_name)
+        .map_err(|e| e.to_string())?;
+    let prev_outputs = match invoice.owned_state {
+        InvoiceState::Amount(amount) => {
+            let state: BTreeMap<_, Vec<Amount>> = contract
+                .fungible(assignment_name, &filter)?
+                .fold(bmap![], |mut set, a| {
+                    set.entry(a.seal).or_default().push(a.state);
+                    set
+                });
+            // Outputs without the state of other contracts go first, such that
+            // the contaminated ones are spent only if the rest is insufficient
+            let mut state: Vec<_> = state
+                .into_iter()
+                .map(|(seal, vals)| {
+                    let isolated = selection == CoinSelection::Isolated
+                        && !assigns_other_contracts(stock, contract_id, &seal);
+                    (isolated, vals.iter().copied().sum::<Amount>(), seal, vals)
+                })
+                .collect();
+            state.sort_by_key(|(isolated, sum, _, _)| (*isolated, *sum));
+            let mut sum = Amount::ZERO;
+            state
+                .iter()
+                .rev()
+                .take_while(|(_, val, _, _)| {
+                    if sum >= amount {
+                        false
+                    } else {
+                        sum += *val;
+                        true
+                    }
+                })
+                .map(|(_, _, seal, _)| *seal)
+                .collect::<BTreeSet<_>>()
+        }
+        InvoiceState::Data(NonFungible::RGB21(allocation)) => {
+            let data_state = DataState::from(allocation);
+            contract
+                .data(assignment_name, &filter)?
+                .filter(|x| x.state == data_state)
+                .map(|x| x.seal)
+                .collect::<BTreeSet<_>>()
+        }
+        _ => return Err(CompositionError::Unsupported),
+    };
+    Ok(prev_outputs)
+}
+
+/// Resolves name of the assignment requested by the invoice, using the default
+/// assignment of the interface operation if the invoice doesn't name one.
+pub(crate) fn invoice_assignment_name<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &Stock<S, H, P>,
+    invoice: &RgbInvoice,
+) -> Result<FieldName, CompositionError> {
+    if let Some(name) = &invoice.assignment {
+        return Ok(name.clone());
+    }
+    let iface_name = invoice.iface.clone().ok_or(CompositionError::NoIface)?;
+    let iface = stock.iface(iface_name).map_err(|e| e.to_string())?;
+    let operation = invoice
+        .operation
+        .as_ref()
+        .or(iface.default_operation.as_ref())
+        .ok_or(CompositionError::NoOperation)?;
+    iface
+        .transitions
+        .get(operation)
+        .and_then(|t| t.default_assignment.clone())
+        .ok_or(CompositionError::NoAssignment)
+}
+
+/// Resolves type of the assignment requested by the invoice, using the
+/// interface implementation of the contract schema.
+pub(crate) fn invoice_assignment_type<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &Stock<S, H, P>,
+    invoice: &RgbInvoice,
+) -> Result<AssignmentType, CompositionError> {
+    let contract_id = invoice.contract.ok_or(CompositionError::NoContract)?;
+    let iface_name = invoice.iface.clone().ok_or(CompositionError::NoIface)?;
+    let assignment_name = invoice_assignment_name(stock, invoice)?;
+
+    let info = stock
+        .contract_info(contract_id)
+        .map_err(|e| e.to_string())?;
+    let schema = stock.schema(info.schema_id).map_err(|e| e.to_string())?;
+    schema
+        .iimpls
+        .get(&iface_name)
+        .and_then(|iimpl| iimpl.assignments_type(&assignment_name))
+        .ok_or(CompositionError::NoAssignment)
+}
+
+/// Resolves type of the named assignment of the contract with any of the
+/// interface implementations of the contract schema. Returns `None` if the
+/// contract is not known to the stock or doesn't have such an assignment.
+pub(crate) fn contract_assignment_type<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &Stock<S, H, P>,
+    contract_id: ContractId,
+    name: &FieldName,
+) -> Option<AssignmentType> {
+    let info = stock.contract_info(contract_id).ok()?;
+    let schema = stock.schema(info.schema_id).ok()?;
+    schema
+        .iimpls
+        .values()
+        .find_map(|iimpl| iimpl.assignments_type(name))
+}
+
+/// Checks whether the output of the seal holds the state of contracts other
+/// than `contract_id`, which has to be moved by blank transitions once the
+/// output is spent.
+fn assigns_other_contracts<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &Stock<S, H, P>,
+    contract_id: ContractId,
+    seal: &XOutputSeal,
+) -> bool {
+    let seals = [CloseMethod::OpretFirst, CloseMethod::TapretFirst]
+        .into_iter()
+        .map(|method| seal.map_ref(|seal| ExplicitSeal::new(method, seal.to_outpoint())))
+        .collect::<BTreeSet<_>>();
+    stock
+        .contracts_assigning(seals)
+        .map(|mut list| list.any(|id| id != contract_id))
+        .unwrap_or_default()
+}
+
+/// Returns contracts other than the `paid` ones, which state is assigned to
+/// the outputs spent by the PSBT and thus is moved by blank transitions.
+///
+/// Non-empty set means that spending the state of unrelated contracts was
+/// unavoidable, which grows the consignments of the transfer.
+pub fn blank_contracts(
+    psbt: &Psbt,
+    paid: impl IntoIterator<Item = ContractId>,
+) -> BTreeSet<ContractId> {
+    let mut contracts = psbt.rgb_contract_ids().unwrap_or_default();
+    for contract_id in paid {
+        contracts.remove(&contract_id);
+    }
+    contracts
+}
+
+/// Splits the change between outputs having different velocity hints, such
+/// that the state which is spent often does not share an output with the state
+/// which is rarely spent.
+///
+/// The main change output, keeping most of the sats, gets the highest
+/// velocity; other velocity classes receive new outputs with a dust-limit
+/// amount, as long as the change is sufficient to cover them. Returns the map
+/// of velocity classes to the outputs which should receive the change.
+#[allow(clippy::too_many_arguments, clippy::result_large_err)]
+fn split_change_by_velocity<W: PsbtConstructor + ?Sized, S, H, P>(
+    wallet: &mut W,
+    stock: &Stock<S, H, P>,
+    invoice: &RgbInvoice,
+    prev_outputs: &BTreeSet<XOutputSeal>,
+    method: CloseMethod,
+    params: &TransferParams,
+    psbt: &mut Psbt,
+    meta: &PsbtMeta,
+) -> Result<BTreeMap<VelocityHint, Vout>, CompositionError>
+where
+    S: StashProvider,
+    H: StateProvider,
+    P: IndexProvider,
+{
+    let (Some(change_vout), Some(change_terminal)) = (meta.change_vout, meta.change_terminal)
+    else {
+        return Ok(none!());
+    };
+
+    // We do a dry run of the composition to learn which velocity classes the
+    // change has to be allocated for. The composer asks for the change seal
+    // even if no change is left, so the change goes to a placeholder output
+    // and only the classes of the state actually assigned to it are taken.
+    let placeholder = Vout::from_u32(u32::MAX);
+    let requested = RefCell::new(BTreeMap::new());
+    let beneficiary_vout = match invoice.beneficiary.into_inner() {
+        Beneficiary::WitnessVout(_) => Some(change_vout),
+        Beneficiary::BlindedSeal(_) => None,
+    };
+    let batch = compose_transfer(
+        stock,
+        invoice,
+        prev_outputs,
+        method,
+        beneficiary_vout,
+        |id, ty, hint| {
+            requested
+                .borrow_mut()
+                .insert((id, ty), params.velocity_hint(id, ty, hint));
+            Some(placeholder)
+        },
+    )?;
+    let requested = requested.into_inner();
+    let mut hints = BTreeSet::new();
+    for dichotomy in iter::once(&batch.main).chain(&batch.blanks) {
+        for info in iter::once(&dichotomy.first).chain(&dichotomy.second) {
+            let transition = &info.transition;
+            for (ty, assigns) in transition.assignments.iter() {
+                let is_change = (0..assigns.len_u16()).any(|no| {
+                    matches!(
+                        assigns.revealed_seal_at(no),
+                        Ok(Some(XChain::Bitcoin(seal))) if seal.txid == TxPtr::WitnessTx
+                            && seal.vout == placeholder
+                    )
+                });
+                if !is_change {
+                    continue;
+                }
+                if let Some(hint) = requested.get(&(transition.contract_id, *ty)) {
+                    hints.insert(*hint);
+                }
+            }
+        }
+    }
+
+    let Some(main_hint) = hints.pop_last() else {
+        return Ok(none!());
+    };
+    let mut vouts = bmap! { main_hint => change_vout };
+    psbt.output_mut(change_vout.to_usize())
+        .expect("change output must be present")
+        .set_rgb_velocity_hint(main_hint);
+
+    let dust = wallet.descriptor().class().dust_limit();
+    for hint in hints.into_iter().rev() {
+        let change = psbt
+            .output_mut(change_vout.to_usize())
+            .expect("change output must be present");
+        match change.amount.checked_sub(dust) {
+            Some(remaining) if remaining >= dust => change.amount = remaining,
+            _ => break,
+        }
+        let index = wallet.next_derivation_index(change_terminal.keychain, true);
+        let terminal = Terminal::new(change_terminal.keychain, index);
+        let output = psbt
+            .construct_change(wallet.descriptor(), terminal, dust)
+            .map_err(|_| CompositionError::Unmodifiable)?;
+        output.set_rgb_velocity_hint(hint);
+        vouts.insert(hint, output.vout());
+    }
+    Ok(vouts)
+}
+
+/// Records roles of the beneficiary and change outputs in the PSBT, such that
+/// their reordering is detected before the RGB data are committed.
+fn mark_output_roles(
+    psbt: &mut Psbt,
+    beneficiary_vout: Option<Vout>,
+    change_vouts: impl IntoIterator<Item = Vout>,
+) {
+    let change_vouts = change_vouts.into_iter().collect::<BTreeSet<_>>();
+    for output in psbt.outputs_mut() {
+        let vout = output.vout();
+        if Some(vout) == beneficiary_vout {
+            output.set_rgb_role(OutputRole::Beneficiary);
+        } else if change_vouts.contains(&vout) {
+            output.set_rgb_role(OutputRole::Change);
+        }
+    }
+}
+
+/// Returns wallet UTXOs which don't hold any RGB state and are not frozen,
+/// starting from the largest one.
+fn bitcoin_coins<K, L2, W, S, H, P>(
+    wallet: &W,
+    stock: &Stock<S, H, P>,
+    frozen: &BTreeSet<Outpoint>,
+) -> Vec<Utxo>
+where
+    L2: Layer2,
+    W: WalletProvider<K, L2> + ?Sized,
+    W::Descr: DescriptorRgb<K>,
+    S: StashProvider,
+    H: StateProvider,
+    P: IndexProvider,
+{
+    let layout = wallet.descriptor().keychain_layout();
+    let mut coins = wallet
+        .utxos()
+        .filter_map(|outpoint| wallet.utxo(outpoint))
+        .filter(|utxo| !layout.contains_rgb(utxo.terminal.keychain))
+        .filter(|utxo| !frozen.contains(&utxo.outpoint))
+        .filter(|utxo| {
+            stock
+                .contracts_assigning(outpoint_seals(utxo.outpoint))
+                .map(|mut list| list.next().is_none())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    coins.sort_by_key(|utxo| Reverse(utxo.value));
+    coins
+}
+
+/// Returns wallet UTXOs which may pay the fee of a transfer without spending
+/// the state of other contracts: the [`bitcoin_coins`] go first, followed by
+/// the outputs of the RGB keychains which don't hold any RGB state yet and are
+/// not the seals of the pending blinded invoices.
+fn isolated_coins<K, L2, W, S, H, P>(
+    wallet: &W,
+    stock: &Stock<S, H, P>,
+    frozen: &BTreeSet<Outpoint>,
+) -> Vec<Utxo>
+where
+    L2: Layer2,
+    W: WalletProvider<K, L2> + ?Sized,
+    W::Descr: DescriptorRgb<K>,
+    S: StashProvider,
+    H: StateProvider,
+    P: IndexProvider,
+{
+    let layout = wallet.descriptor().keychain_layout();
+    let invoiced = stock
+        .as_stash_provider()
+        .secret_seals()
+        .map(|seals| {
+            // Seals on other layers 1 can't be defined over the wallet outputs
+            seals
+                .filter_map(|seal| match seal {
+                    XChain::Bitcoin(seal) => Some(seal),
+                    _ => None,
+                })
+                .filter_map(|seal| match seal.txid {
+                    TxPtr::Txid(txid) => Some(Outpoint::new(txid, seal.vout)),
+                    TxPtr::WitnessTx => None,
+                })
+                .collect::<BTreeSet<_>>()
+        })
+        .unwrap_or_default();
+    let mut rgb_coins = wallet
+        .utxos()
+        .filter_map(|outpoint| wallet.utxo(outpoint))
+        .filter(|utxo| layout.contains_rgb(utxo.terminal.keychain))
+        .filter(|utxo| !frozen.contains(&utxo.outpoint) && !invoiced.contains(&utxo.outpoint))
+        .filter(|utxo| {
+            stock
+                .contracts_assigning(outpoint_seals(utxo.outpoint))
+                .map(|mut list| list.next().is_none())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    rgb_coins.sort_by_key(|utxo| Reverse(utxo.value));
+    let mut coins = bitcoin_coins(wallet, stock, frozen);
+    coins.extend(rgb_coins);
+    coins
+}
+
+/// Adds wallet `coins` to the PSBT until its inputs cover the `fee` computed
+/// for the PSBT, leaving more than the dust limit for the change. Returns the
+/// fee together with the added coins.
+#[allow(clippy::result_large_err)]
+fn cover_fee<K, W: PsbtConstructor + ?Sized>(
+    wallet: &W,
+    psbt: &mut Psbt,
+    coins: impl IntoIterator<Item = Utxo>,
+    seq_no: SeqNo,
+    fee: impl Fn(&Psbt) -> Sats,
+) -> Result<(Sats, BTreeSet<Outpoint>), CompositionError>
+where
+    W::Descr: DescriptorRgb<K>,
+{
+    let dust = wallet.descriptor().class().dust_limit();
+    let mut coins = coins.into_iter();
+    let mut added = bset![];
+    loop {
+        let fee = fee(psbt);
+        let input_value = psbt.input_sum();
+        if input_value
+            .checked_sub(fee)
+            .is_some_and(|change| change > dust)
+        {
+            return Ok((fee, added));
+        }
+        let Some(utxo) = coins.next() else {
+            return Err(ConstructionError::NoFundsForFee {
+                input_value,
+                output_value: Sats::ZERO,
+                fee,
+            }
+            .into());
+        };
+        psbt.construct_input_expect(utxo.to_prevout(), wallet.descriptor(), utxo.terminal, seq_no);
+        added.insert(utxo.outpoint);
+    }
+}
+
+/// Adds PSBT input spending either the wallet output or the output imported
+/// into the wallet. Returns the value of the spent output.
+fn construct_coin_input<K, W: PsbtConstructor + ?Sized>(
+    wallet: &W,
+    psbt: &mut Psbt,
+    outpoint: Outpoint,
+    imported: &ImportedUtxos,
+    seq_no: SeqNo,
+) -> Sats
+where
+    W::Descr: DescriptorRgb<K>,
+{
+    match imported.get(outpoint) {
+        Some(utxo) => {
+            // The input is constructed for an arbitrary descriptor terminal
+            // and then replaced with the imported output data
+            let terminal = Terminal::new(RgbKeychain::Rgb, NormalIndex::ZERO);
+            let prevout = Prevout::new(outpoint, utxo.value);
+            let input = psbt.construct_input_expect(prevout, wallet.descriptor(), terminal, seq_no);
+            utxo.apply_to(input);
+            utxo.value
+        }
+        None => {
+            let utxo = wallet.utxo(outpoint).expect("wallet data inconsistency");
+            psbt.construct_input_expect(
+                utxo.to_prevout(),
+                wallet.descriptor(),
+                utxo.terminal,
+                seq_no,
+            );
+            utxo.value
+        }
+    }
+}
+
+/// Constructs PSBT like [`PsbtConstructor::construct_psbt`], where some of the
+/// `coins` are outputs imported into the wallet.
+///
+/// If the `coins` don't cover the beneficiaries and the fee, imported outputs
+/// which don't hold any RGB state are spent as well.
+#[allow(clippy::result_large_err)]
+fn construct_psbt_imported<K, L2, W, S, H, P>(
+    wallet: &mut W,
+    stock: &Stock<S, H, P>,
+    coins: &[Outpoint],
+    beneficiaries: &[BpBeneficiary],
+    params: &TransferParams,
+) -> Result<(Psbt, PsbtMeta), CompositionError>
+where
+    L2: Layer2,
+    W: WalletProvider<K, L2> + ?Sized,
+    W::Descr: DescriptorRgb<K>,
+    S: StashProvider,
+    H: StateProvider,
+    P: IndexProvider,
+{
+    let tx = &params.tx;
+    let mut psbt = Psbt::create(PsbtVer::V2);
+    psbt.fallback_locktime = tx.lock_time;
+    for spec in wallet.descriptor().xpubs() {
+        psbt.xpubs.insert(*spec.xpub(), spec.origin().clone());
+    }
+    for outpoint in coins {
+        construct_coin_input(wallet, &mut psbt, *outpoint, &params.imported, tx.seq_no);
+    }
+
+    let mut output_value = Sats::ZERO;
+    for beneficiary in beneficiaries {
+        let amount = beneficiary.amount.unwrap_or(Sats::ZERO);
+        output_value
+            .checked_add_assign(amount)
+            .ok_or(ConstructionError::Overflow(output_value))?;
+        psbt.construct_output_expect(beneficiary.script_pubkey(), amount);
+    }
+    let required_value = output_value
+        .checked_add(tx.fee)
+        .ok_or(ConstructionError::Overflow(output_value))?;
+    let bitcoin_coins = params
+        .imported
+        .iter()
+        .filter(|utxo| !coins.contains(&utxo.outpoint) && !params.frozen.contains(&utxo.outpoint))
+        .filter(|utxo| {
+            stock
+                .contracts_assigning(outpoint_seals(utxo.outpoint))
+                .map(|mut list| list.next().is_none())
+                .unwrap_or_default()
+        });
+    for utxo in bitcoin_coins {
+        if psbt.input_sum() >= required_value {
+            break;
+        }
+        construct_coin_input(wallet, &mut psbt, utxo.outpoint, &params.imported, tx.seq_no);
+    }
+
+    let input_value = psbt.input_sum();
+    let remaining_value =
+        input_value
+            .checked_sub(required_value)
+            .ok_or(ConstructionError::NoFundsForFee {
+                input_value,
+                output_value,
+                fee: tx.fee,
+            })?;
+    let (change_vout, change_terminal) =
+        if remaining_value > wallet.descriptor().class().dust_limit() {
+            let index = wallet.next_derivation_index(tx.change_keychain, tx.change_shift);
+            let terminal = Terminal::new(tx.change_keychain, index);
+            let vout = psbt
+                .construct_change_expect(wallet.descriptor(), terminal, remaining_value)
+                .vout();
+            (Some(vout), Some(terminal))
+        } else {
+            (None, None)
+        };
+    Ok((psbt, PsbtMeta {
+        change_vout,
+        change_terminal,
+    }))
+}
+
+/// Sets the amount of the change output such that the transaction constructed
+/// from the PSBT pays fee at the `feerate` (in sats per virtual byte), with
+/// its size estimated by [`estimate_vsize`]. Returns the fee.
+///
+/// PSBT without a change output pays all the remaining value as a fee, which
+/// must be sufficient for the `feerate`.
+fn settle_fee(
+    psbt: &mut Psbt,
+    change_vout: Option<Vout>,
+    feerate: u64,
+    dust: Sats,
+) -> Result<Sats, CompositionError> {
+    let fee = Sats::from_sats(feerate.saturating_mul(estimate_vsize(psbt) as u64));
+    let input_value = psbt.input_sum();
+    let change = change_vout.and_then(|vout| psbt.output(vout.to_usize()));
+    let output_value = psbt.output_sum() - change.map(psbt::Output::value).unwrap_or_default();
+    let remaining_value = input_value
+        .checked_sub(output_value)
+        .and_then(|value| value.checked_sub(fee));
+    let no_funds = ConstructionError::NoFundsForFee {
+        input_value,
+        output_value,
+        fee,
+    };
+    match (change_vout, remaining_value) {
+        (Some(vout), Some(change)) if change > dust => {
+            psbt.output_mut(vout.to_usize())
+                .expect("change output is present")
+                .amount = change;
+            Ok(fee)
+        }
+        (None, Some(remaining)) => Ok(fee + remaining),
+        _ => Err(no_funds.into()),
+    }
+}
+
+/// Constructs PSBT anchoring the contract operation other than transfer (like
+/// supply change or a contract call) which spends `prev_outputs` and may
+/// assign state to the change output.
+#[allow(clippy::result_large_err)]
+fn construct_psbt_operation<K, W: PsbtConstructor + ?Sized>(
+    wallet: &mut W,
+    prev_outputs: &BTreeSet<XOutputSeal>,
+    mut params: TxParams,
+    compose: impl FnOnce(CloseMethod, Option<Vout>) -> Result<Batch, CompositionError>,
+) -> Result<(Psbt, PsbtMeta), CompositionError>
+where
+    W::Descr: DescriptorRgb<K>,
+{
+    let method = wallet.descriptor().seal_close_method();
+    let prev_outpoints = prev_outputs
+        .iter()
+        .map(wallet_outpoint)
+        .collect::<Result<Vec<_>, _>>()?;
+    params.change_keychain = wallet.descriptor().keychain_layout().for_method(method);
+    let (mut psbt, mut meta) = wallet.construct_psbt(prev_outpoints, &[], params)?;
+
+    psbt.outputs_mut()
+        .find(|o| o.script.is_p2tr())
+        .map(|o| o.set_tapret_host().expect("just created"));
+    let change_script = meta
+        .change_vout
+        .and_then(|vout| psbt.output(vout.to_usize()))
+        .map(|output| output.script.clone());
+    psbt.rgb_order_outputs(OutputOrdering::HostFirst)
+        .expect("PSBT must be modifiable at this stage");
+    if let Some(change_script) = change_script {
+        meta.change_vout = psbt
+            .outputs()
+            .find(|output| output.script == change_script)
+            .map(psbt::Output::vout);
+    }
+
+    let batch = compose(method, meta.change_vout)?;
+    if batch.close_method_set().has_opret_first() {
+        let output = psbt.construct_output_expect(ScriptPubkey::op_return(&[]), Sats::ZERO);
+        output.set_opret_host().expect("just created");
+    }
+
+    mark_output_roles(&mut psbt, None, meta.change_vout);
+    psbt.complete_construction();
+    psbt.rgb_embed(batch)?;
+    Ok((psbt, meta))
+}
+
 pub trait WalletProvider<K, L2: Layer2>: PsbtConstructor
 where Self::Descr: DescriptorRgb<K>
 {
@@ -115,7 +1449,22 @@ where Self::Descr: DescriptorRgb<K>
     fn txids(&self) -> impl Iterator<Item = Txid>;
     fn history(&self) -> impl Iterator<Item = TxRow<impl Layer2Tx>> + '_;
 
-    // TODO: Add method `color` to add RGB information to an already existing PSBT
+    /// Finds derivation terminal of the wallet producing the script pubkey,
+    /// scanning all keychains up to their next derivation index.
+    fn script_terminal(&mut self, script: &ScriptPubkey) -> Option<Terminal> {
+        let keychains = self.descriptor().keychains();
+        for keychain in keychains {
+            let end = self.next_derivation_index(keychain, false);
+            for index in 0..=end.index() {
+                let index = NormalIndex::try_from_index(index).expect("index below normal one");
+                let derived = self.descriptor().derive(keychain, index);
+                if &derived.to_script_pubkey() == script {
+                    return Some(Terminal::new(keychain, index));
+                }
+            }
+        }
+        None
+    }
 
     #[allow(clippy::result_large_err)]
     fn pay<S: StashProvider, H: StateProvider, P: IndexProvider>(
@@ -133,101 +1482,216 @@ where Self::Descr: DescriptorRgb<K>
         Ok((psbt, meta, transfer))
     }
 
+    /// Pays all legs of the basket invoice within a single transaction,
+    /// producing a transfer consignment for each of the basket contracts.
     #[allow(clippy::result_large_err)]
-    fn construct_psbt_rgb<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    fn pay_basket<S: StashProvider, H: StateProvider, P: IndexProvider>(
         &mut self,
-        stock: &Stock<S, H, P>,
-        invoice: &RgbInvoice,
-        mut params: TransferParams,
-    ) -> Result<(Psbt, PsbtMeta), CompositionError> {
-        let contract_id = invoice.contract.ok_or(CompositionError::NoContract)?;
-        let method = self.descriptor().seal_close_method();
-
-        let iface_name = invoice.iface.clone().ok_or(CompositionError::NoIface)?;
-        let iface = stock.iface(iface_name.clone()).map_err(|e| e.to_string())?;
-        let operation = invoice
-            .operation
-            .as_ref()
-            .or(iface.default_operation.as_ref())
-            .ok_or(CompositionError::NoOperation)?;
-
-        let assignment_name = invoice
-            .assignment
-            .as_ref()
-            .or_else(|| {
-                iface
-                    .transitions
-                    .get(operation)
-                    .and_then(|t| t.default_assignment.as_ref())
-            })
-            .cloned()
-            .ok_or(CompositionError::NoAssignment)?;
+        stock: &mut Stock<S, H, P>,
+        basket: &BasketInvoice,
+        params: TransferParams,
+    ) -> Result<(Psbt, Vec<PsbtMeta>, Vec<Transfer>), PayError> {
+        let (mut psbt, meta) = self.construct_psbt_basket(stock, basket, params)?;
+        let transfers = match self.transfer_basket(stock, basket, &mut psbt) {
+            Ok(transfers) => transfers,
+            Err(e) => return Err(PayError::Completion(e, psbt)),
+        };
+        Ok((psbt, meta, transfers))
+    }
+
+    /// Pays each of the invoices with a separate transaction, producing a PSBT
+    /// and a transfer consignment for each of them, in the order of the
+    /// invoices.
+    ///
+    /// All PSBTs are constructed with [`WalletProvider::construct_psbt_batch`]
+    /// before any of the transfers is made, such that if the wallet can't pay
+    /// some of the invoices the stock is left intact.
+    #[allow(clippy::result_large_err)]
+    fn pay_batch<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &mut Stock<S, H, P>,
+        invoices: &[RgbInvoice],
+        params: TransferParams,
+    ) -> Result<Vec<(Psbt, PsbtMeta, Transfer)>, PayError> {
+        let batch = self.construct_psbt_batch(stock, invoices, params)?;
+        let mut payments = Vec::with_capacity(batch.len());
+        for (invoice, (mut psbt, meta)) in invoices.iter().zip(batch) {
+            let transfer = match self.transfer(stock, invoice, &mut psbt) {
+                Ok(transfer) => transfer,
+                Err(e) => return Err(PayError::Completion(e, psbt)),
+            };
+            payments.push((psbt, meta, transfer));
+        }
+        Ok(payments)
+    }
+
+    /// Plans the transfer paying the invoice without constructing the
+    /// transaction: selects the state to spend and builds the state
+    /// transitions, reporting the inputs, the change, the fee and the
+    /// estimated size of the transfer consignment.
+    ///
+    /// Useful for showing a confirmation screen before the transfer is made.
+    #[allow(clippy::result_large_err)]
+    fn plan_transfer<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+        invoice: &RgbInvoice,
+        params: TransferParams,
+    ) -> Result<TransferPlan, CompositionError> {
+        let invoice = &*negotiate_amount(invoice, params.amount)?;
+        let contract_id = invoice.contract.ok_or(CompositionError::NoContract)?;
+        check_networks(self.network(), stock, invoice, contract_id)?;
+        check_expiry(invoice, &params)?;
+        let method = transfer_close_method(self.descriptor(), params.close_method)?;
 
         let filter = ContractOutpointsFilter {
             contract_id,
             stock,
             wallet: self,
+            frozen: &params.frozen,
+            imported: &params.imported,
             _key_phantom: PhantomData,
             _layer2_phantom: PhantomData,
         };
-        let contract = stock
-            .contract_iface(contract_id, iface_name)
+        let prev_outputs = select_rgb_state(stock, invoice, filter, params.coin_selection)?;
+        let witness_sats = beneficiary_sats(invoice, params.min_amount)?;
+        let (beneficiary_vout, beneficiary_sats) = match invoice.beneficiary.into_inner() {
+            Beneficiary::WitnessVout(_) => (Some(PLAN_BENEFICIARY_VOUT), witness_sats),
+            Beneficiary::BlindedSeal(_) => (None, Sats::ZERO),
+        };
+        let batch = compose_transfer(
+            stock,
+            invoice,
+            &prev_outputs,
+            method,
+            beneficiary_vout,
+            |_, _, _| Some(PLAN_CHANGE_VOUT),
+        )?;
+
+        // The consignment contains the history of the spent state, the new
+        // state transitions and the witness transaction with its anchor
+        let history = stock
+            .transfer(contract_id, prev_outputs.iter().copied().collect::<Vec<_>>(), None)
             .map_err(|e| e.to_string())?;
-        let prev_outputs = match invoice.owned_state {
-            InvoiceState::Amount(amount) => {
-                let state: BTreeMap<_, Vec<Amount>> = contract
-                    .fungible(assignment_name, &filter)?
-                    .fold(bmap![], |mut set, a| {
-                        set.entry(a.seal).or_default().push(a.state);
-                        set
-                    });
-                let mut state: Vec<_> = state
-                    .into_iter()
-                    .map(|(seal, vals)| (vals.iter().copied().sum::<Amount>(), seal, vals))
-                    .collect();
-                state.sort_by_key(|(sum, _, _)| *sum);
-                let mut sum = Amount::ZERO;
-                state
-                    .iter()
-                    .rev()
-                    .take_while(|(val, _, _)| {
-                        if sum >= amount {
-                            false
-                        } else {
-                            sum += *val;
-                            true
-                        }
-                    })
-                    .map(|(_, seal, _)| *seal)
-                    .collect::<BTreeSet<_>>()
-            }
-            InvoiceState::Data(NonFungible::RGB21(allocation)) => {
-                let data_state = DataState::from(allocation);
-                contract
-                    .data(assignment_name, &filter)?
-                    .filter(|x| x.state == data_state)
-                    .map(|x| x.seal)
-                    .collect::<BTreeSet<_>>()
-            }
-            _ => return Err(CompositionError::Unsupported),
+        let mut consignment_size = history
+            .strict_serialized_len::<U32>()
+            .map_err(|e| e.to_string())?
+            + PLAN_WITNESS_SIZE_ESTIMATE;
+        for info in batch.main.iter() {
+            consignment_size += info
+                .transition
+                .strict_serialized_len::<U32>()
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(TransferPlan {
+            contract_id,
+            amount: match invoice.owned_state {
+                InvoiceState::Amount(amount) => Some(amount),
+                _ => None,
+            },
+            change: TransferPlan::batch_change(&batch),
+            blank_contracts: batch
+                .blanks
+                .iter()
+                .map(|blank| blank.first.transition.contract_id)
+                .collect(),
+            inputs: prev_outputs,
+            beneficiary_sats,
+            fee: params.tx.fee,
+            consignment_size,
+        })
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn construct_psbt_rgb<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &Stock<S, H, P>,
+        invoice: &RgbInvoice,
+        mut params: TransferParams,
+    ) -> Result<(Psbt, PsbtMeta), CompositionError> {
+        let invoice = &*negotiate_amount(invoice, params.amount)?;
+        let contract_id = invoice.contract.ok_or(CompositionError::NoContract)?;
+        check_networks(self.network(), stock, invoice, contract_id)?;
+        check_duplicate(stock, invoice, &params)?;
+        check_expiry(invoice, &params)?;
+        let mut method = transfer_close_method(self.descriptor(), params.close_method)?;
+
+        let filter = ContractOutpointsFilter {
+            contract_id,
+            stock,
+            wallet: self,
+            frozen: &params.frozen,
+            imported: &params.imported,
+            _key_phantom: PhantomData,
+            _layer2_phantom: PhantomData,
         };
+        let prev_outputs = select_rgb_state(stock, invoice, filter, params.coin_selection)?;
+        let witness_sats = beneficiary_sats(invoice, params.min_amount)?;
         let beneficiaries = match invoice.beneficiary.into_inner() {
             Beneficiary::BlindedSeal(_) => vec![],
             Beneficiary::WitnessVout(pay2vout) => {
                 vec![BpBeneficiary::new(
                     Address::new(pay2vout.address, invoice.address_network()),
-                    params.min_amount,
+                    witness_sats,
                 )]
             }
         };
-        let prev_outpoints = prev_outputs
+        let mut prev_outpoints = prev_outputs
+            .iter()
+            .map(wallet_outpoint)
+            .collect::<Result<Vec<_>, _>>()?;
+        params.tx.change_keychain = self.descriptor().keychain_layout().for_method(method);
+        if let Some(feerate) = params.feerate {
+            // Preliminary fee for the coin selection, assuming an additional
+            // input paying it, the change and the commitment outputs. The
+            // change is adjusted to the final size of the transaction below.
+            let vsize = estimate_vsize_for(prev_outputs.len() + 1, beneficiaries.len() + 2);
+            params.tx.fee = Sats::from_sats(feerate.saturating_mul(vsize as u64));
+        }
+        if params.coin_selection == CoinSelection::Isolated {
+            // Outputs without RGB state pay the fee, such that the PSBT
+            // constructor doesn't spend outputs of unrelated contracts
+            let required = beneficiaries
+                .iter()
+                .filter_map(|b| b.amount.sats())
+                .fold(params.tx.fee + self.descriptor().class().dust_limit(), |sum, sats| {
+                    sum + sats
+                });
+            let mut input_value = prev_outpoints
+                .iter()
+                .filter_map(|outpoint| match params.imported.get(*outpoint) {
+                    Some(utxo) => Some(utxo.value),
+                    None => self.utxo(*outpoint).map(|utxo| utxo.value),
+                })
+                .fold(Sats::ZERO, |sum, sats| sum + sats);
+            for utxo in isolated_coins(self, stock, &params.frozen) {
+                if input_value > required {
+                    break;
+                }
+                input_value += utxo.value;
+                prev_outpoints.push(utxo.outpoint);
+            }
+        }
+        let (mut psbt, mut meta) = if prev_outpoints
             .iter()
-            // TODO: Support liquid
-            .map(|o| o.as_reduced_unsafe())
-            .map(|o| Outpoint::new(o.txid, o.vout));
-        params.tx.change_keychain = RgbKeychain::for_method(method).into();
-        let (mut psbt, mut meta) =
-            self.construct_psbt(prev_outpoints, &beneficiaries, params.tx)?;
+            .any(|outpoint| params.imported.is_imported(*outpoint))
+        {
+            construct_psbt_imported(self, stock, &prev_outpoints, &beneficiaries, &params)?
+        } else {
+            self.construct_psbt(prev_outpoints, &beneficiaries, params.tx)?
+        };
+        split_change_by_velocity(
+            self,
+            stock,
+            invoice,
+            &prev_outputs,
+            method,
+            &params,
+            &mut psbt,
+            &meta,
+        )?;
+        apply_timelocks(&mut psbt, &params, invoice.expiry)?;
 
         let beneficiary_script =
             if let Beneficiary::WitnessVout(pay2vout) = invoice.beneficiary.into_inner() {
@@ -244,7 +1708,7 @@ where Self::Descr: DescriptorRgb<K>
             .change_vout
             .and_then(|vout| psbt.output(vout.to_usize()))
             .map(|output| output.script.clone());
-        psbt.sort_outputs_by(|output| !output.is_tapret_host())
+        psbt.rgb_order_outputs(params.ordering)
             .expect("PSBT must be modifiable at this stage");
         if let Some(change_script) = change_script {
             for output in psbt.outputs() {
@@ -254,6 +1718,17 @@ where Self::Descr: DescriptorRgb<K>
                 }
             }
         }
+        if params.close_method.is_none()
+            && method == CloseMethod::TapretFirst
+            && meta
+                .change_vout
+                .and_then(|vout| psbt.output(vout.to_usize()))
+                .is_some_and(|output| !output.script.is_p2tr())
+        {
+            // Seals defined on a non-taproot change are closed with opret,
+            // even if the wallet descriptor prefers tapret
+            method = CloseMethod::OpretFirst;
+        }
 
         let beneficiary_vout = match invoice.beneficiary.into_inner() {
             Beneficiary::WitnessVout(pay2vout) => {
@@ -268,21 +1743,788 @@ where Self::Descr: DescriptorRgb<K>
             }
             Beneficiary::BlindedSeal(_) => None,
         };
-        let batch = stock
-            .compose(invoice, prev_outputs, method, beneficiary_vout, |_, _, _| meta.change_vout)
-            .map_err(|e| e.to_string())?;
+        // Output indexes have changed after sorting, so we re-read them
+        let velocity_vouts = psbt
+            .outputs()
+            .filter_map(|output| output.rgb_velocity_hint().map(|hint| (hint, output.vout())))
+            .collect::<BTreeMap<_, _>>();
+        // The beneficiary output is marked only after the change outputs are
+        // known, such that it never receives the change of the payer
+        if let (Some(velocity), Some(vout)) =
+            (RequestedVelocity::from_invoice(invoice)?, beneficiary_vout)
+        {
+            if let Some(output) = psbt.outputs_mut().find(|output| output.vout() == vout) {
+                output.set_rgb_velocity_hint(velocity.hint());
+            }
+        }
+        let mut batch = compose_transfer(
+            stock,
+            invoice,
+            &prev_outputs,
+            method,
+            beneficiary_vout,
+            |id, ty, hint| {
+                let hint = params.velocity_hint(id, ty, hint);
+                velocity_vouts.get(&hint).copied().or(meta.change_vout)
+            },
+        )?;
+        if let Some(split) = SplitSeals::from_invoice(invoice)? {
+            split.apply(&mut batch, invoice)?;
+        }
 
         let methods = batch.close_method_set();
         if methods.has_opret_first() {
             let output = psbt.construct_output_expect(ScriptPubkey::op_return(&[]), Sats::ZERO);
             output.set_opret_host().expect("just created");
         }
+        if let Some(feerate) = params.feerate {
+            let dust = self.descriptor().class().dust_limit();
+            settle_fee(&mut psbt, meta.change_vout, feerate, dust)?;
+        }
 
+        mark_output_roles(
+            &mut psbt,
+            beneficiary_vout,
+            meta.change_vout
+                .into_iter()
+                .chain(velocity_vouts.into_values()),
+        );
         psbt.complete_construction();
         psbt.rgb_embed(batch)?;
         Ok((psbt, meta))
     }
 
+    /// Constructs a separate PSBT paying each of the invoices, in the order of
+    /// the invoices. The PSBTs never spend the same outputs, such that all of
+    /// them may be published at once; the outputs spent by each of the PSBTs
+    /// are excluded from the selection of the RGB state and the bitcoin coins
+    /// for the following ones.
+    #[allow(clippy::result_large_err)]
+    fn construct_psbt_batch<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &Stock<S, H, P>,
+        invoices: &[RgbInvoice],
+        mut params: TransferParams,
+    ) -> Result<Vec<(Psbt, PsbtMeta)>, CompositionError> {
+        let mut batch = Vec::with_capacity(invoices.len());
+        for invoice in invoices {
+            let (psbt, meta) = self.construct_psbt_rgb(stock, invoice, params.clone())?;
+            params
+                .frozen
+                .extend(psbt.inputs().map(|input| input.previous_outpoint));
+            batch.push((psbt, meta));
+        }
+        Ok(batch)
+    }
+
+    /// Constructs PSBT paying all legs of the basket invoice.
+    ///
+    /// The legs are added to the PSBT one by one, each spending its own RGB
+    /// inputs and receiving its own change; the transaction fee is paid from
+    /// the wallet coins which do not hold any RGB state. Returns PSBT
+    /// metadata for each of the legs, followed by the metadata of the fee
+    /// payment.
+    #[allow(clippy::result_large_err)]
+    fn construct_psbt_basket<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &Stock<S, H, P>,
+        basket: &BasketInvoice,
+        params: TransferParams,
+    ) -> Result<(Psbt, Vec<PsbtMeta>), CompositionError> {
+        let mut psbt = Psbt::create(PsbtVer::V2);
+        let mut meta = Vec::with_capacity(basket.legs().len() + 1);
+        for invoice in basket.legs() {
+            check_duplicate(stock, invoice, &params)?;
+            check_expiry(invoice, &params)?;
+            let mut leg_params = params.clone();
+            leg_params.tx.fee = Sats::ZERO;
+            // Each leg is paid with the amount requested by its invoice
+            leg_params.amount = None;
+            meta.push(self.extend_psbt_rgb(stock, &mut psbt, invoice, leg_params)?);
+        }
+        meta.push(self.extend_psbt_sats(
+            stock,
+            &mut psbt,
+            Sats::ZERO,
+            params.tx,
+            &params.frozen,
+        )?);
+        psbt.fallback_locktime = params.tx.lock_time;
+        apply_timelocks(&mut psbt, &params, basket.expiry())?;
+        psbt.complete_construction();
+        Ok((psbt, meta))
+    }
+
+    /// Extends an existing modifiable PSBT, which may already contain inputs,
+    /// outputs and RGB state transitions of other parties, with the inputs,
+    /// outputs and state transitions required to pay the provided invoice.
+    ///
+    /// Unlike [`WalletProvider::construct_psbt_rgb`], the method never
+    /// reorders the existing outputs, since they may be already referenced by
+    /// the state transitions of other parties. The PSBT is left modifiable,
+    /// such that more parties can add their data to it.
+    #[allow(clippy::result_large_err)]
+    fn extend_psbt_rgb<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &Stock<S, H, P>,
+        psbt: &mut Psbt,
+        invoice: &RgbInvoice,
+        params: TransferParams,
+    ) -> Result<PsbtMeta, CompositionError> {
+        if !psbt.are_inputs_modifiable() || !psbt.are_outputs_modifiable() {
+            return Err(CompositionError::Unmodifiable);
+        }
+        let invoice = &*negotiate_amount(invoice, params.amount)?;
+        let contract_id = invoice.contract.ok_or(CompositionError::NoContract)?;
+        check_networks(self.network(), stock, invoice, contract_id)?;
+        let method = self.descriptor().seal_close_method();
+
+        let filter = ContractOutpointsFilter {
+            contract_id,
+            stock,
+            wallet: self,
+            frozen: &params.frozen,
+            imported: &params.imported,
+            _key_phantom: PhantomData,
+            _layer2_phantom: PhantomData,
+        };
+        let prev_outputs = select_rgb_state(stock, invoice, filter, params.coin_selection)?;
+
+        for spec in self.descriptor().xpubs() {
+            psbt.xpubs.insert(*spec.xpub(), spec.origin().clone());
+        }
+
+        let mut input_value = Sats::ZERO;
+        for output in &prev_outputs {
+            let outpoint = wallet_outpoint(output)?;
+            input_value +=
+                construct_coin_input(self, psbt, outpoint, &params.imported, params.tx.seq_no);
+        }
+
+        let witness_sats = beneficiary_sats(invoice, params.min_amount)?;
+        let output_value = match invoice.beneficiary.into_inner() {
+            Beneficiary::WitnessVout(_) => witness_sats,
+            Beneficiary::BlindedSeal(_) => Sats::ZERO,
+        };
+        let fee = params.tx.fee;
+        let remaining_value = input_value
+            .checked_sub(output_value)
+            .and_then(|value| value.checked_sub(fee))
+            .ok_or(ConstructionError::NoFundsForFee {
+                input_value,
+                output_value,
+                fee,
+            })?;
+
+        // Change is added before the beneficiary output, such that it may
+        // host tapret commitment, which must be put into the first taproot
+        // output
+        let (change_vout, change_terminal) = if remaining_value
+            > self.descriptor().class().dust_limit()
+        {
+            let keychain = self.descriptor().keychain_layout().for_method(method);
+            let index = self.next_derivation_index(keychain, params.tx.change_shift);
+            let terminal = Terminal::new(keychain, index);
+            let output = psbt.construct_change_expect(self.descriptor(), terminal, remaining_value);
+            (Some(output.vout()), Some(terminal))
+        } else {
+            (None, None)
+        };
+        if !psbt.outputs().any(psbt::Output::is_tapret_host) {
+            if let Some(output) = psbt.outputs_mut().find(|o| o.script.is_p2tr()) {
+                if Some(output.vout()) == change_vout {
+                    output.set_tapret_host().expect("just created");
+                }
+            }
+        }
+
+        let beneficiary_vout = match invoice.beneficiary.into_inner() {
+            Beneficiary::WitnessVout(pay2vout) => {
+                let script = pay2vout.address.script_pubkey();
+                Some(psbt.construct_output_expect(script, output_value).vout())
+            }
+            Beneficiary::BlindedSeal(_) => None,
+        };
+
+        let mut batch = compose_transfer(
+            stock,
+            invoice,
+            &prev_outputs,
+            method,
+            beneficiary_vout,
+            |_, _, _| change_vout,
+        )?;
+        if let Some(split) = SplitSeals::from_invoice(invoice)? {
+            split.apply(&mut batch, invoice)?;
+        }
+
+        let methods = batch.close_method_set();
+        if methods.has_tapret_first() && !psbt.outputs().any(psbt::Output::is_tapret_host) {
+            return Err(CompositionError::TapretRequired);
+        }
+        if methods.has_opret_first() && !psbt.outputs().any(psbt::Output::is_opret_host) {
+            let output = psbt.construct_output_expect(ScriptPubkey::op_return(&[]), Sats::ZERO);
+            output.set_opret_host().expect("just created");
+        }
+
+        mark_output_roles(psbt, beneficiary_vout, change_vout);
+        psbt.rgb_embed(batch)?;
+        Ok(PsbtMeta {
+            change_vout,
+            change_terminal,
+        })
+    }
+
+    /// Extends an existing modifiable PSBT with wallet inputs which do not hold
+    /// any RGB state, such that they cover the provided amount and the
+    /// transaction fee, adding change output if required.
+    ///
+    /// Like [`WalletProvider::extend_psbt_rgb`], the method never reorders the
+    /// existing outputs. The `frozen` outpoints are never spent.
+    #[allow(clippy::result_large_err)]
+    fn extend_psbt_sats<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &Stock<S, H, P>,
+        psbt: &mut Psbt,
+        amount: Sats,
+        params: TxParams,
+        frozen: &BTreeSet<Outpoint>,
+    ) -> Result<PsbtMeta, CompositionError> {
+        if !psbt.are_inputs_modifiable() || !psbt.are_outputs_modifiable() {
+            return Err(CompositionError::Unmodifiable);
+        }
+
+        let coins = bitcoin_coins(self, stock, frozen);
+
+        for spec in self.descriptor().xpubs() {
+            psbt.xpubs.insert(*spec.xpub(), spec.origin().clone());
+        }
+
+        let fee = params.fee;
+        let required_value = amount
+            .checked_add(fee)
+            .ok_or(ConstructionError::Overflow(amount))?;
+        let mut input_value = Sats::ZERO;
+        for utxo in coins {
+            if input_value >= required_value {
+                break;
+            }
+            input_value += utxo.value;
+            psbt.construct_input_expect(
+                utxo.to_prevout(),
+                self.descriptor(),
+                utxo.terminal,
+                params.seq_no,
+            );
+        }
+        let remaining_value =
+            input_value
+                .checked_sub(required_value)
+                .ok_or(ConstructionError::NoFundsForFee {
+                    input_value,
+                    output_value: amount,
+                    fee,
+                })?;
+
+        let (change_vout, change_terminal) = if remaining_value
+            > self.descriptor().class().dust_limit()
+        {
+            let index = self.next_derivation_index(params.change_keychain, params.change_shift);
+            let terminal = Terminal::new(params.change_keychain, index);
+            let output = psbt.construct_change_expect(self.descriptor(), terminal, remaining_value);
+            (Some(output.vout()), Some(terminal))
+        } else {
+            (None, None)
+        };
+
+        Ok(PsbtMeta {
+            change_vout,
+            change_terminal,
+        })
+    }
+
+    /// Contributes to a payjoin PSBT paying the `invoice` the smallest wallet
+    /// coin which doesn't hold any RGB state. The value of the coin is added to
+    /// the beneficiary output or, if the invoice uses a blinded seal, is sent
+    /// to a new output on the internal keychain. The existing inputs and
+    /// outputs are never removed or reordered, such that the seals defined by
+    /// the payer remain valid. The `frozen` outpoints are never spent.
+    ///
+    /// Returns the contributed outpoint, or `None` if the wallet has no coins
+    /// which can be contributed.
+    #[allow(clippy::result_large_err)]
+    fn extend_psbt_payjoin<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &Stock<S, H, P>,
+        psbt: &mut Psbt,
+        invoice: &RgbInvoice,
+        frozen: &BTreeSet<Outpoint>,
+    ) -> Result<Option<Outpoint>, CompositionError> {
+        if !psbt.are_inputs_modifiable() || !psbt.are_outputs_modifiable() {
+            return Err(CompositionError::Unmodifiable);
+        }
+        let Some(utxo) = bitcoin_coins(self, stock, frozen).pop() else {
+            return Ok(None);
+        };
+
+        for spec in self.descriptor().xpubs() {
+            psbt.xpubs.insert(*spec.xpub(), spec.origin().clone());
+        }
+        let seq_no = psbt
+            .inputs()
+            .find_map(|input| input.sequence_number)
+            .unwrap_or(SeqNo::ZERO);
+        psbt.construct_input_expect(utxo.to_prevout(), self.descriptor(), utxo.terminal, seq_no);
+
+        match invoice.beneficiary.into_inner() {
+            Beneficiary::WitnessVout(pay2vout) => {
+                let script = pay2vout.address.script_pubkey();
+                let output = psbt
+                    .outputs_mut()
+                    .find(|output| output.script == script)
+                    .ok_or(CompositionError::NoBeneficiaryOutput)?;
+                output.amount += utxo.value;
+            }
+            Beneficiary::BlindedSeal(_) => {
+                let keychain = self
+                    .descriptor()
+                    .keychain_layout()
+                    .keychain(RgbKeychain::Internal);
+                let index = self.next_derivation_index(keychain, true);
+                let script = self.descriptor().derive(keychain, index).to_script_pubkey();
+                psbt.construct_output_expect(script, utxo.value);
+            }
+        }
+        Ok(Some(utxo.outpoint))
+    }
+
+    /// Composes and embeds once again the state transitions paying the
+    /// `invoice` after another party has moved the outputs of the PSBT, as it
+    /// happens with payjoin proposals extended by the receiver.
+    ///
+    /// The transitions spend the state assigned to `prev_outpoints`; the
+    /// `beneficiary_vout` and `change_vouts` are the numbers of the
+    /// respective outputs in the modified PSBT. Fails if the RGB data are
+    /// already committed to the PSBT.
+    #[allow(clippy::result_large_err)]
+    fn realign_psbt_rgb<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &Stock<S, H, P>,
+        psbt: &mut Psbt,
+        invoice: &RgbInvoice,
+        prev_outpoints: impl IntoIterator<Item = Outpoint>,
+        beneficiary_vout: Option<Vout>,
+        change_vouts: &[Vout],
+    ) -> Result<(), CompositionError> {
+        let contract_id = invoice.contract.ok_or(CompositionError::NoContract)?;
+        check_networks(self.network(), stock, invoice, contract_id)?;
+        let method = self.descriptor().seal_close_method();
+        psbt.rgb_reset()?;
+
+        let prev_outputs = prev_outpoints
+            .into_iter()
+            .map(|outpoint| XChain::Bitcoin(ExplicitSeal::new(method, outpoint)))
+            .collect::<Vec<_>>();
+        let velocity_vouts = psbt
+            .outputs()
+            .filter(|output| Some(output.vout()) != beneficiary_vout)
+            .filter_map(|output| output.rgb_velocity_hint().map(|hint| (hint, output.vout())))
+            .collect::<BTreeMap<_, _>>();
+        let mut batch = stock
+            .compose(invoice, prev_outputs, method, beneficiary_vout, |_, _, hint| {
+                velocity_vouts
+                    .get(&hint)
+                    .copied()
+                    .or(change_vouts.first().copied())
+            })
+            .map_err(|e| e.to_string())?;
+        if let Some(split) = SplitSeals::from_invoice(invoice)? {
+            split.apply(&mut batch, invoice)?;
+        }
+
+        let methods = batch.close_method_set();
+        if methods.has_tapret_first()
+            && !psbt
+                .outputs()
+                .find(|output| output.script.is_p2tr())
+                .is_some_and(psbt::Output::is_tapret_host)
+        {
+            return Err(CompositionError::TapretRequired);
+        }
+        if methods.has_opret_first() && !psbt.outputs().any(psbt::Output::is_opret_host) {
+            return Err(CompositionError::OpretRequired);
+        }
+
+        mark_output_roles(psbt, beneficiary_vout, change_vouts.iter().copied());
+        psbt.rgb_embed(batch)?;
+        Ok(())
+    }
+
+    /// Constructs PSBT of a child transaction bumping the fee of the
+    /// unconfirmed `parent` transaction (paying `parent_fee`) with CPFP, such
+    /// that the package of both transactions reaches the `feerate` (in sats
+    /// per virtual byte).
+    ///
+    /// The child spends the wallet output `vout` of the parent and, if its
+    /// value doesn't cover the fee, wallet outputs which don't hold any RGB
+    /// state. The RGB state assigned to the spent parent output is moved to
+    /// the change output with blank transitions; if there is no such state,
+    /// the PSBT doesn't contain RGB data. The fee in `params` is ignored.
+    /// The `frozen` outpoints are never used to cover the fee.
+    #[allow(clippy::result_large_err, clippy::too_many_arguments)]
+    fn construct_psbt_bump<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &Stock<S, H, P>,
+        parent: &Tx,
+        parent_fee: Sats,
+        vout: Vout,
+        feerate: u64,
+        mut params: TxParams,
+        frozen: &BTreeSet<Outpoint>,
+    ) -> Result<(Psbt, PsbtMeta), CompositionError> {
+        let outpoint = Outpoint::new(parent.txid(), vout);
+        let txout = parent
+            .outputs
+            .get(vout.to_usize())
+            .ok_or(CompositionError::ForeignOutput(outpoint))?;
+        let terminal = self
+            .script_terminal(&txout.script_pubkey)
+            .ok_or(CompositionError::ForeignOutput(outpoint))?;
+        let method = self.descriptor().seal_close_method();
+
+        let mut psbt = Psbt::create(PsbtVer::V2);
+        psbt.fallback_locktime = params.lock_time;
+        for spec in self.descriptor().xpubs() {
+            psbt.xpubs.insert(*spec.xpub(), spec.origin().clone());
+        }
+        psbt.construct_input_expect(
+            Prevout::new(outpoint, txout.value),
+            self.descriptor(),
+            terminal,
+            params.seq_no,
+        );
+
+        // The change value is set once the fee is known
+        params.change_keychain = self.descriptor().keychain_layout().for_method(method);
+        let index = self.next_derivation_index(params.change_keychain, params.change_shift);
+        let change_terminal = Terminal::new(params.change_keychain, index);
+        let change_vout = psbt
+            .construct_change_expect(self.descriptor(), change_terminal, Sats::ZERO)
+            .vout();
+
+        let batch = compose_bump(stock, &outpoint_seals(outpoint), method, change_vout)?;
+        if let Some(batch) = &batch {
+            psbt.outputs_mut()
+                .find(|o| o.script.is_p2tr())
+                .map(|o| o.set_tapret_host().expect("just created"));
+            if batch.close_method_set().has_opret_first() {
+                let output = psbt.construct_output_expect(ScriptPubkey::op_return(&[]), Sats::ZERO);
+                output.set_opret_host().expect("just created");
+            }
+        }
+
+        let coins = bitcoin_coins(self, stock, frozen);
+        let (fee, _) = cover_fee(self, &mut psbt, coins, params.seq_no, |psbt| {
+            cpfp_fee(parent, parent_fee, estimate_vsize(psbt), feerate)
+        })?;
+        let change_value = psbt.input_sum() - fee;
+        if let Some(output) = psbt.outputs_mut().find(|o| o.vout() == change_vout) {
+            output.amount = change_value;
+        }
+
+        mark_output_roles(&mut psbt, None, [change_vout]);
+        psbt.complete_construction();
+        if let Some(batch) = batch {
+            psbt.rgb_embed(batch)?;
+        }
+        Ok((psbt, PsbtMeta {
+            change_vout: Some(change_vout),
+            change_terminal: Some(change_terminal),
+        }))
+    }
+
+    /// Constructs PSBT consolidating the RGB state fragmented across the
+    /// wallet outputs selected by the `scope` into a single change output;
+    /// see [`crate::ConsolidationScope`].
+    ///
+    /// The transaction pays fee at the `feerate` (in sats per virtual byte)
+    /// from the sats of the spent outputs and, if they are not sufficient,
+    /// from the wallet outputs which don't hold any RGB state. The `frozen`
+    /// outpoints are never spent. The fee in `params` is ignored.
+    #[allow(clippy::result_large_err)]
+    fn construct_psbt_consolidate<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &Stock<S, H, P>,
+        scope: &ConsolidationScope,
+        feerate: u64,
+        mut params: TxParams,
+        frozen: &BTreeSet<Outpoint>,
+    ) -> Result<(Psbt, PsbtMeta, ConsolidationReport), CompositionError> {
+        let inputs = match scope {
+            ConsolidationScope::Contract(contract_id) => self
+                .utxos()
+                .filter(|outpoint| !frozen.contains(outpoint))
+                .filter(|outpoint| {
+                    matches!(
+                        stock.contract_assignments_for(*contract_id, outpoint_seals(*outpoint)),
+                        Ok(list) if !list.is_empty()
+                    )
+                })
+                .collect::<BTreeSet<_>>(),
+            ConsolidationScope::Outpoints(outpoints) => {
+                for outpoint in outpoints {
+                    if self.utxo(*outpoint).is_none() {
+                        return Err(CompositionError::ForeignOutput(*outpoint));
+                    }
+                    if frozen.contains(outpoint) {
+                        return Err(CompositionError::FrozenOutput(*outpoint));
+                    }
+                }
+                outpoints.clone()
+            }
+        };
+        let prev_outputs = inputs
+            .iter()
+            .flat_map(|outpoint| outpoint_seals(*outpoint))
+            .collect::<BTreeSet<_>>();
+        let method = self.descriptor().seal_close_method();
+
+        let mut psbt = Psbt::create(PsbtVer::V2);
+        psbt.fallback_locktime = params.lock_time;
+        for spec in self.descriptor().xpubs() {
+            psbt.xpubs.insert(*spec.xpub(), spec.origin().clone());
+        }
+        for outpoint in &inputs {
+            let utxo = self.utxo(*outpoint).expect("checked above");
+            psbt.construct_input_expect(
+                utxo.to_prevout(),
+                self.descriptor(),
+                utxo.terminal,
+                params.seq_no,
+            );
+        }
+
+        // The change value is set once the fee is known
+        params.change_keychain = self.descriptor().keychain_layout().for_method(method);
+        let index = self.next_derivation_index(params.change_keychain, params.change_shift);
+        let change_terminal = Terminal::new(params.change_keychain, index);
+        let change_vout = psbt
+            .construct_change_expect(self.descriptor(), change_terminal, Sats::ZERO)
+            .vout();
+
+        let (batch, state) = compose_consolidation(stock, &prev_outputs, method, change_vout)?
+            .ok_or(CompositionError::NothingToConsolidate)?;
+        psbt.outputs_mut()
+            .find(|o| o.script.is_p2tr())
+            .map(|o| o.set_tapret_host().expect("just created"));
+        if batch.close_method_set().has_opret_first() {
+            let output = psbt.construct_output_expect(ScriptPubkey::op_return(&[]), Sats::ZERO);
+            output.set_opret_host().expect("just created");
+        }
+
+        let coins = bitcoin_coins(self, stock, frozen)
+            .into_iter()
+            .filter(|utxo| !inputs.contains(&utxo.outpoint));
+        let (fee, fee_inputs) = cover_fee(self, &mut psbt, coins, params.seq_no, |psbt| {
+            Sats::from_sats(feerate.saturating_mul(estimate_vsize(psbt) as u64))
+        })?;
+        let change = psbt.input_sum() - fee;
+        if let Some(output) = psbt.outputs_mut().find(|o| o.vout() == change_vout) {
+            output.amount = change;
+        }
+        let vsize = estimate_vsize(&psbt);
+
+        mark_output_roles(&mut psbt, None, [change_vout]);
+        psbt.complete_construction();
+        psbt.rgb_embed(batch)?;
+        let report = ConsolidationReport {
+            inputs,
+            fee_inputs,
+            state,
+            change_vout,
+            change,
+            fee,
+            vsize,
+        };
+        Ok((
+            psbt,
+            PsbtMeta {
+                change_vout: Some(change_vout),
+                change_terminal: Some(change_terminal),
+            },
+            report,
+        ))
+    }
+
+    /// Acts as an RGB updater (in terms of the PSBT v2 roles) of a PSBT
+    /// constructed by another wallet, which already spends the wallet outputs
+    /// holding the contract state and contains the outputs required by the
+    /// payment.
+    ///
+    /// All state of the contract assigned to the spent wallet outputs is
+    /// consumed: the invoice is paid from it (with the `amount` chosen by the
+    /// payer, if the invoice allows it) and the rest is assigned to the
+    /// `change_vout` output, which must belong to the wallet. The method sets
+    /// the DBC commitment hosts, embeds the state transitions and locks the
+    /// PSBT inputs and outputs, since their modification would invalidate the
+    /// seals. Once signed, the PSBT is completed with
+    /// [`WalletProvider::transfer`].
+    ///
+    /// Returns the wallet outputs which state is spent by the PSBT.
+    #[allow(clippy::result_large_err)]
+    fn update_psbt_rgb<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &Stock<S, H, P>,
+        psbt: &mut Psbt,
+        invoice: &RgbInvoice,
+        change_vout: Option<Vout>,
+        amount: Option<Amount>,
+    ) -> Result<BTreeSet<XOutputSeal>, CompositionError> {
+        if psbt.version != PsbtVer::V2 {
+            return Err(CompositionError::PsbtVersion);
+        }
+        let invoice = &*negotiate_amount(invoice, amount)?;
+        let contract_id = invoice.contract.ok_or(CompositionError::NoContract)?;
+        check_networks(self.network(), stock, invoice, contract_id)?;
+        let method = self.descriptor().seal_close_method();
+
+        let utxos = self.utxos().collect::<BTreeSet<_>>();
+        let spent = psbt
+            .inputs()
+            .map(|input| input.prevout().outpoint())
+            .filter(|outpoint| utxos.contains(outpoint))
+            .map(|outpoint| XOutpoint::from(XChain::Bitcoin(outpoint)))
+            .collect::<Vec<XOutpoint>>();
+        let prev_outputs = stock
+            .contract_assignments_for(contract_id, spent)
+            .map_err(|e| e.to_string())?
+            .into_keys()
+            .collect::<BTreeSet<XOutputSeal>>();
+        if prev_outputs.is_empty() {
+            return Err(CompositionError::InsufficientState);
+        }
+
+        let witness_sats = WitnessSats::from_invoice(invoice)?;
+        let beneficiary_vout = match invoice.beneficiary.into_inner() {
+            Beneficiary::WitnessVout(pay2vout) => {
+                let script = pay2vout.address.script_pubkey();
+                let output = psbt
+                    .outputs()
+                    .find(|output| output.script == script)
+                    .ok_or(CompositionError::NoBeneficiaryOutput)?;
+                if let Some(sats) = witness_sats {
+                    sats.check(output.amount)?;
+                }
+                Some(output.vout())
+            }
+            Beneficiary::BlindedSeal(_) => None,
+        };
+        if let Some(vout) = change_vout {
+            if psbt.output(vout.to_usize()).is_none() || Some(vout) == beneficiary_vout {
+                return Err(CompositionError::NoChange);
+            }
+        }
+
+        for spec in self.descriptor().xpubs() {
+            psbt.xpubs.insert(*spec.xpub(), spec.origin().clone());
+        }
+
+        let mut batch = compose_transfer(
+            stock,
+            invoice,
+            &prev_outputs,
+            method,
+            beneficiary_vout,
+            |_, _, _| change_vout,
+        )?;
+        if let Some(split) = SplitSeals::from_invoice(invoice)? {
+            split.apply(&mut batch, invoice)?;
+        }
+
+        // Tapret commitment tweaks the output key, thus it may be hosted only
+        // by the wallet change output
+        let methods = batch.close_method_set();
+        if methods.has_tapret_first() && !psbt.outputs().any(psbt::Output::is_tapret_host) {
+            change_vout
+                .and_then(|vout| psbt.output_mut(vout.to_usize()))
+                .and_then(|output| output.set_tapret_host().ok())
+                .ok_or(CompositionError::TapretRequired)?;
+        }
+        if methods.has_opret_first() && !psbt.outputs().any(psbt::Output::is_opret_host) {
+            let existing = psbt
+                .outputs_mut()
+                .find(|output| output.script.is_op_return())
+                .map(|output| output.set_opret_host().is_ok());
+            match existing {
+                Some(true) => {}
+                _ if psbt.are_outputs_modifiable() => {
+                    let output =
+                        psbt.construct_output_expect(ScriptPubkey::op_return(&[]), Sats::ZERO);
+                    output.set_opret_host().expect("just created");
+                }
+                _ => return Err(CompositionError::OpretRequired),
+            }
+        }
+
+        mark_output_roles(psbt, beneficiary_vout, change_vout);
+        psbt.complete_construction();
+        psbt.rgb_embed(batch)?;
+        Ok(prev_outputs)
+    }
+
+    /// Constructs PSBT anchoring the operation changing the contract supply.
+    ///
+    /// The PSBT spends all wallet outputs holding the state required by the
+    /// operation; the state which is not consumed by the operation is
+    /// assigned to the change output.
+    #[allow(clippy::result_large_err)]
+    fn construct_psbt_supply<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &Stock<S, H, P>,
+        operation: &SupplyOperation,
+        params: TxParams,
+    ) -> Result<(Psbt, PsbtMeta), CompositionError> {
+        let prev_outputs = operation.select_outputs(stock, self.utxos())?;
+        construct_psbt_operation(self, &prev_outputs, params, |method, change| {
+            operation.compose(stock, &prev_outputs, method, change)
+        })
+    }
+
+    /// Constructs PSBT anchoring the contract call.
+    ///
+    /// The PSBT spends all wallet outputs holding the allocations spent by the
+    /// call; other state assigned to these outputs is moved to the change
+    /// output.
+    #[cfg(feature = "serde")]
+    #[allow(clippy::result_large_err)]
+    fn construct_psbt_call<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &Stock<S, H, P>,
+        call: &ContractCall,
+        params: TxParams,
+    ) -> Result<(Psbt, PsbtMeta), CompositionError> {
+        let prev_outputs = call.select_outputs(stock, self.utxos())?;
+        construct_psbt_operation(self, &prev_outputs, params, |method, change| {
+            call.compose(stock, &prev_outputs, method, change)
+        })
+    }
+
+    /// Commits to the RGB data of the operation changing the contract supply
+    /// and adds the operation to the stock.
+    ///
+    /// Unlike transfers, supply operations assign the state only to the wallet
+    /// itself, thus no consignment is produced.
+    #[allow(clippy::result_large_err)]
+    fn complete_supply<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &mut Stock<S, H, P>,
+        psbt: &mut Psbt,
+    ) -> Result<(), CompletionError> {
+        fill_tap_outputs(psbt, self.descriptor());
+        let fascia = psbt.rgb_commit()?;
+        self.commit_fascia(stock, psbt, fascia)
+    }
+
     #[allow(clippy::result_large_err)]
     fn transfer<S: StashProvider, H: StateProvider, P: IndexProvider>(
         &mut self,
@@ -290,9 +2532,108 @@ where Self::Descr: DescriptorRgb<K>
         invoice: &RgbInvoice,
         psbt: &mut Psbt,
     ) -> Result<Transfer, CompletionError> {
-        let contract_id = invoice.contract.ok_or(CompletionError::NoContract)?;
+        fill_tap_outputs(psbt, self.descriptor());
+        let fascia = psbt.rgb_commit()?;
+        self.transfer_with_fascia(stock, invoice, psbt, fascia)
+    }
 
+    /// Commits to the RGB data of all basket legs in the PSBT and creates a
+    /// transfer consignment for each of them, in the order of the legs.
+    #[allow(clippy::result_large_err)]
+    fn transfer_basket<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &mut Stock<S, H, P>,
+        basket: &BasketInvoice,
+        psbt: &mut Psbt,
+    ) -> Result<Vec<Transfer>, CompletionError> {
+        fill_tap_outputs(psbt, self.descriptor());
         let fascia = psbt.rgb_commit()?;
+        self.transfer_legs_with_fascia(stock, basket.legs(), psbt, fascia)
+    }
+
+    /// Consumes fascia, which was produced by committing to the RGB data in
+    /// the PSBT, and creates transfer consignment for the invoice
+    /// beneficiary.
+    ///
+    /// Since the PSBT may be constructed by multiple parties, the tapret tweak
+    /// is registered with the wallet descriptor only if the tapret host output
+    /// belongs to the wallet.
+    #[allow(clippy::result_large_err)]
+    fn transfer_with_fascia<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &mut Stock<S, H, P>,
+        invoice: &RgbInvoice,
+        psbt: &mut Psbt,
+        fascia: Fascia,
+    ) -> Result<Transfer, CompletionError> {
+        let mut transfers =
+            self.transfer_legs_with_fascia(stock, slice::from_ref(invoice), psbt, fascia)?;
+        Ok(transfers.remove(0))
+    }
+
+    /// Consumes fascia, like [`WalletProvider::transfer_with_fascia`], creating
+    /// transfer consignments for multiple invoices paid by the same PSBT.
+    #[allow(clippy::result_large_err)]
+    fn transfer_legs_with_fascia<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &mut Stock<S, H, P>,
+        invoices: &[RgbInvoice],
+        psbt: &mut Psbt,
+        fascia: Fascia,
+    ) -> Result<Vec<Transfer>, CompletionError> {
+        let contract_ids = invoices
+            .iter()
+            .map(|invoice| invoice.contract.ok_or(CompletionError::NoContract))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let witness_txid = psbt.txid();
+        let mut beneficiaries = Vec::with_capacity(invoices.len());
+        for invoice in invoices {
+            beneficiaries.push(match invoice.beneficiary.into_inner() {
+                Beneficiary::WitnessVout(pay2vout) => {
+                    let s = pay2vout.address.script_pubkey();
+                    let vout = psbt
+                        .outputs()
+                        .position(|output| output.script == s)
+                        .ok_or(CompletionError::NoBeneficiaryOutput)?;
+                    let vout = Vout::from_u32(vout as u32);
+                    let seal = XChain::Bitcoin(ExplicitSeal::new(
+                        pay2vout.method,
+                        Outpoint::new(witness_txid, vout),
+                    ));
+                    (None, vec![seal])
+                }
+                Beneficiary::BlindedSeal(seal) => (Some(XChain::Bitcoin(seal)), vec![]),
+            });
+        }
+
+        self.commit_fascia(stock, psbt, fascia)?;
+        let mut transfers = Vec::with_capacity(contract_ids.len());
+        for (contract_id, (beneficiary1, beneficiary2)) in
+            contract_ids.into_iter().zip(beneficiaries)
+        {
+            let transfer = stock
+                .transfer(contract_id, beneficiary2, beneficiary1)
+                .map_err(|e| e.to_string())?;
+            transfers.push(transfer);
+        }
+
+        Ok(transfers)
+    }
+
+    /// Consumes fascia, which was produced by committing to the RGB data in
+    /// the PSBT, registering the witness transaction as tentative.
+    ///
+    /// Since the PSBT may be constructed by multiple parties, the tapret tweak
+    /// is registered with the wallet descriptor only if the tapret host output
+    /// belongs to the wallet.
+    #[allow(clippy::result_large_err)]
+    fn commit_fascia<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &mut self,
+        stock: &mut Stock<S, H, P>,
+        psbt: &mut Psbt,
+        fascia: Fascia,
+    ) -> Result<(), CompletionError> {
         if fascia.anchor.has_tapret() {
             let output = psbt
                 .dbc_output::<TapretProof>()
@@ -300,30 +2641,15 @@ where Self::Descr: DescriptorRgb<K>
             let terminal = output
                 .terminal_derivation()
                 .ok_or(CompletionError::InconclusiveDerivation)?;
-            let tapret_commitment = output.tapret_commitment()?;
-            self.with_descriptor_mut(|descr| {
-                descr.with_descriptor_mut(|d| d.add_tapret_tweak(terminal, tapret_commitment))
-            })?;
+            if output.tap_bip32_derivation == self.descriptor().xonly_keyset(terminal) {
+                let tapret_commitment = output.tapret_commitment()?;
+                self.with_descriptor_mut(|descr| {
+                    descr.with_descriptor_mut(|d| d.add_tapret_tweak(terminal, tapret_commitment))
+                })?;
+            }
         }
 
         let witness_txid = psbt.txid();
-        let (beneficiary1, beneficiary2) = match invoice.beneficiary.into_inner() {
-            Beneficiary::WitnessVout(pay2vout) => {
-                let s = pay2vout.address.script_pubkey();
-                let vout = psbt
-                    .outputs()
-                    .position(|output| output.script == s)
-                    .ok_or(CompletionError::NoBeneficiaryOutput)?;
-                let vout = Vout::from_u32(vout as u32);
-                let seal = XChain::Bitcoin(ExplicitSeal::new(
-                    pay2vout.method,
-                    Outpoint::new(witness_txid, vout),
-                ));
-                (None, vec![seal])
-            }
-            Beneficiary::BlindedSeal(seal) => (Some(XChain::Bitcoin(seal)), vec![]),
-        };
-
         struct FasciaResolver {
             witness_id: XWitnessId,
         }
@@ -348,11 +2674,7 @@ where Self::Descr: DescriptorRgb<K>
                 witness_id: XChain::Bitcoin(witness_txid),
             })
             .map_err(|e| e.to_string())?;
-        let transfer = stock
-            .transfer(contract_id, beneficiary2, beneficiary1)
-            .map_err(|e| e.to_string())?;
-
-        Ok(transfer)
+        Ok(())
     }
 }
 
@@ -0,0 +1,227 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+
+//! Payjoin (BIP-78 style pay-to-endpoint) transfers, where the receiver
+//! contributes its own input to the witness transaction, breaking the
+//! common-input-ownership heuristic of the chain analysis.
+//!
+//! The payer constructs [`PayjoinProposal`] with the RGB data embedded, but
+//! not committed, and sends it to the endpoint listed in the invoice. The
+//! receiver adds an input which doesn't hold any RGB state, receiving its
+//! value back to its own output, and returns the PSBT. The payer checks that
+//! the receiver hasn't taken anything from it, re-composes the state
+//! transitions if the receiver has moved the outputs, and commits to the RGB
+//! data.
+
+use std::str::FromStr;
+
+use bp::Vout;
+use psrgbt::{KeyMap, OutputRole, PropKey, ProprietaryKeyRgb, Psbt, RgbExt, RgbOutExt};
+
+use crate::invoice::RgbInvoice;
+use crate::PayjoinError;
+
+/// Invoice query parameter specifying the URL of the receiver payjoin
+/// endpoint.
+pub const INVOICE_QUERY_PAYJOIN: &str = "pj";
+
+/// Payjoin endpoint of an invoice beneficiary.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[display(inner)]
+pub struct PayjoinEndpoint(String);
+
+#[allow(clippy::result_large_err)]
+impl PayjoinEndpoint {
+    /// Constructs endpoint with the URL, which must use HTTPS.
+    pub fn new(url: impl Into<String>) -> Result<Self, PayjoinError> {
+        let url = url.into();
+        if !url.starts_with("https://") || url.contains(['?', '&', ' ']) {
+            return Err(PayjoinError::InvalidEndpoint(url));
+        }
+        Ok(Self(url))
+    }
+
+    pub fn url(&self) -> &str { &self.0 }
+
+    /// Reads the endpoint from the invoice query parameters. Returns `None` if
+    /// the invoice doesn't support payjoin.
+    pub fn from_invoice(invoice: &RgbInvoice) -> Result<Option<Self>, PayjoinError> {
+        invoice
+            .unknown_query
+            .get(INVOICE_QUERY_PAYJOIN)
+            .map(|url| Self::new(url.clone()))
+            .transpose()
+    }
+
+    /// Stores the endpoint in the invoice query parameters.
+    pub fn set_to_invoice(&self, invoice: &mut RgbInvoice) {
+        invoice
+            .unknown_query
+            .insert(INVOICE_QUERY_PAYJOIN.to_owned(), self.0.clone());
+    }
+}
+
+/// Original PSBT of a payjoin transfer, which pays the invoice and has to be
+/// extended with the inputs of the receiver.
+///
+/// The invoice is kept inside the PSBT using a global proprietary key, such
+/// that the receiver knows which of its invoices is paid.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PayjoinProposal {
+    psbt: Psbt,
+    invoice: RgbInvoice,
+}
+
+#[allow(clippy::result_large_err)]
+impl PayjoinProposal {
+    /// Constructs new payjoin proposal from a PSBT, which must already contain
+    /// the data paying the `invoice`.
+    pub fn new(mut psbt: Psbt, invoice: RgbInvoice) -> Self {
+        let _ = psbt.push_proprietary(PropKey::rgb_payjoin(), invoice.to_string().into_bytes());
+        PayjoinProposal { psbt, invoice }
+    }
+
+    /// Parses payjoin proposal out of a PSBT.
+    pub fn from_psbt(psbt: Psbt) -> Result<Self, PayjoinError> {
+        let data = psbt
+            .proprietary(&PropKey::rgb_payjoin())
+            .ok_or(PayjoinError::NoInvoice)?;
+        let s = String::from_utf8(data.to_vec()).map_err(|_| PayjoinError::NoInvoice)?;
+        let invoice = RgbInvoice::from_str(&s)?;
+        Ok(PayjoinProposal { psbt, invoice })
+    }
+
+    /// Invoice paid by the proposal.
+    pub fn invoice(&self) -> &RgbInvoice { &self.invoice }
+
+    pub fn psbt(&self) -> &Psbt { &self.psbt }
+
+    pub(crate) fn psbt_mut(&mut self) -> &mut Psbt { &mut self.psbt }
+
+    pub fn into_psbt(self) -> Psbt { self.psbt }
+
+    /// Checks that the proposal contains state transitions paying the invoice
+    /// and that they are not yet committed.
+    pub fn check_payment(&self) -> Result<(), PayjoinError> {
+        let contract_id = self.invoice.contract.ok_or(PayjoinError::NoInvoice)?;
+        let contracts = self
+            .psbt
+            .rgb_contract_ids()
+            .map_err(|_| PayjoinError::NoPayment(contract_id))?;
+        if !contracts.contains(&contract_id) {
+            return Err(PayjoinError::NoPayment(contract_id));
+        }
+        if self.psbt.proprietary(&PropKey::rgb_fascia()).is_some() {
+            return Err(PayjoinError::AlreadyCommitted);
+        }
+        Ok(())
+    }
+
+    /// Checks that the `payjoin` PSBT returned by the receiver keeps all inputs
+    /// and outputs of the proposal, doesn't reduce the fee, and pays the payer
+    /// outputs the same amounts as before.
+    ///
+    /// Returns numbers of the proposal outputs in the `payjoin` PSBT, indexed
+    /// by their original numbers.
+    pub fn match_contribution(&self, payjoin: &Psbt) -> Result<Vec<Vout>, PayjoinError> {
+        if payjoin.proprietary(&PropKey::rgb_fascia()).is_some() {
+            return Err(PayjoinError::AlreadyCommitted);
+        }
+        for input in self.psbt.inputs() {
+            let outpoint = input.previous_outpoint;
+            if !payjoin
+                .inputs()
+                .any(|input| input.previous_outpoint == outpoint)
+            {
+                return Err(PayjoinError::InputRemoved(outpoint));
+            }
+        }
+
+        let mut vouts = Vec::with_capacity(self.psbt.outputs().count());
+        for output in self.psbt.outputs() {
+            let vout = output.vout();
+            let Some(matched) = payjoin
+                .outputs()
+                .find(|o| o.script == output.script && !vouts.contains(&o.vout()))
+            else {
+                return Err(PayjoinError::OutputRemoved(vout.to_u32()));
+            };
+            // Only the value of the beneficiary output may be increased by the
+            // receiver
+            let beneficiary = matches!(output.rgb_role(), Some((OutputRole::Beneficiary, _)));
+            if matched.amount < output.amount || (!beneficiary && matched.amount != output.amount) {
+                return Err(PayjoinError::OutputChanged(vout.to_u32()));
+            }
+            vouts.push(matched.vout());
+        }
+
+        let fee = self.psbt.fee().unwrap_or_default();
+        let payjoin_fee = payjoin.fee().unwrap_or_default();
+        if payjoin_fee < fee {
+            return Err(PayjoinError::FeeReduced(fee, payjoin_fee));
+        }
+        Ok(vouts)
+    }
+}
+
+/// Transport delivering the original payjoin PSBT to the receiver endpoint
+/// and returning the PSBT extended by the receiver.
+pub trait PayjoinClient {
+    fn exchange(&self, endpoint: &PayjoinEndpoint, original: &Psbt) -> Result<Psbt, String>;
+}
+
+impl<F: Fn(&PayjoinEndpoint, &Psbt) -> Result<Psbt, String>> PayjoinClient for F {
+    fn exchange(&self, endpoint: &PayjoinEndpoint, original: &Psbt) -> Result<Psbt, String> {
+        self(endpoint, original)
+    }
+}
+
+/// Client posting the base64-encoded PSBT to the receiver endpoint over HTTPS,
+/// as defined by BIP-78.
+#[cfg(feature = "esplora_blocking")]
+pub struct HttpsPayjoin(ureq::Agent);
+
+#[cfg(feature = "esplora_blocking")]
+impl HttpsPayjoin {
+    /// Constructs the client, which connects via an optional SOCKS5 proxy.
+    pub fn new(timeout: std::time::Duration, socks5: Option<&str>) -> Result<Self, String> {
+        let mut agent = ureq::AgentBuilder::new().timeout(timeout);
+        if let Some(proxy) = socks5 {
+            let proxy = ureq::Proxy::new(format!("socks5://{proxy}")).map_err(|e| e.to_string())?;
+            agent = agent.proxy(proxy);
+        }
+        Ok(Self(agent.build()))
+    }
+}
+
+#[cfg(feature = "esplora_blocking")]
+impl PayjoinClient for HttpsPayjoin {
+    fn exchange(&self, endpoint: &PayjoinEndpoint, original: &Psbt) -> Result<Psbt, String> {
+        let resp = self
+            .0
+            .post(endpoint.url())
+            .query("v", "1")
+            .set("Content-Type", "text/plain")
+            .send_string(&original.to_base64())
+            .map_err(|e| e.to_string())?;
+        let body = resp.into_string().map_err(|e| e.to_string())?;
+        Psbt::from_base64(body.trim()).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,114 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+
+use bp::seals::txout::TxPtr;
+use bp::{Sats, Vout};
+use rgbstd::containers::Batch;
+use rgbstd::invoice::Amount;
+use rgbstd::{Assign, AssignmentType, ContractId, XChain, XOutputSeal};
+
+/// Estimated size of the witness transaction and anchor data added to the
+/// transfer consignment, in bytes.
+pub const PLAN_WITNESS_SIZE_ESTIMATE: usize = 1024;
+
+/// Output number used in place of the beneficiary output while planning.
+pub(crate) const PLAN_BENEFICIARY_VOUT: Vout = Vout::from_u32(0);
+/// Output number used in place of the change output while planning.
+pub(crate) const PLAN_CHANGE_VOUT: Vout = Vout::from_u32(1);
+
+/// Fungible state returned to the wallet as a change by the planned transfer.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct PlannedChange {
+    pub contract_id: ContractId,
+    pub assignment_type: AssignmentType,
+    pub amount: Amount,
+}
+
+/// Dry-run plan of a transfer, produced by selecting the state to spend and
+/// building the state transitions without constructing the transaction.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct TransferPlan {
+    /// Contract which state is transferred.
+    pub contract_id: ContractId,
+
+    /// Outputs holding the contract state which will be spent.
+    pub inputs: BTreeSet<XOutputSeal>,
+
+    /// Fungible state paid to the beneficiary.
+    pub amount: Option<Amount>,
+
+    /// Fungible state returned to the wallet as a change.
+    pub change: Vec<PlannedChange>,
+
+    /// Other contracts which state is assigned to the spent outputs and is
+    /// moved to the change by blank transitions.
+    pub blank_contracts: BTreeSet<ContractId>,
+
+    /// Amount of sats paid to the address-based beneficiary.
+    pub beneficiary_sats: Sats,
+
+    /// Fee for the witness transaction.
+    pub fee: Sats,
+
+    /// Estimated size of the transfer consignment, in bytes.
+    pub consignment_size: usize,
+}
+
+impl TransferPlan {
+    /// Extracts the change allocations from a batch composed with the
+    /// [`PLAN_CHANGE_VOUT`] used as the change output.
+    pub(crate) fn batch_change(batch: &Batch) -> Vec<PlannedChange> {
+        let mut change = vec![];
+        for info in batch.main.iter() {
+            let transition = &info.transition;
+            for (ty, assigns) in transition.assignments.iter() {
+                for assign in assigns.as_fungible() {
+                    let Assign::Revealed { seal, state, .. } = assign else {
+                        continue;
+                    };
+                    let XChain::Bitcoin(seal) = seal else {
+                        continue;
+                    };
+                    if seal.txid == TxPtr::WitnessTx && seal.vout == PLAN_CHANGE_VOUT {
+                        change.push(PlannedChange {
+                            contract_id: transition.contract_id,
+                            assignment_type: *ty,
+                            amount: Amount::from(*state),
+                        });
+                    }
+                }
+            }
+        }
+        change
+    }
+}
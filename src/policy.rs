@@ -0,0 +1,224 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use rgbstd::containers::ConsignmentExt;
+#[cfg(feature = "fs")]
+use rgbstd::containers::{ConsignmentId, FileContent, Transfer};
+use rgbstd::schema::SchemaId;
+use rgbstd::{ContractId, Identity};
+
+use crate::PolicyError;
+
+/// Rule of a [`TrustPolicy`], whitelisting consignments for automatic
+/// acceptance.
+///
+/// The string representation is the rule kind followed by a colon and the
+/// whitelisted value, e.g. `contract:<contract id>`, `schema:<schema id>` or
+/// `issuer:<identity>`.
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+pub enum PolicyRule {
+    #[display("contract:{0}")]
+    Contract(ContractId),
+
+    #[display("schema:{0}")]
+    Schema(SchemaId),
+
+    #[display("issuer:{0}")]
+    Issuer(Identity),
+}
+
+impl FromStr for PolicyRule {
+    type Err = PolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = s
+            .split_once(':')
+            .ok_or_else(|| PolicyError::InvalidRule(s.to_owned()))?;
+        let value = value.trim();
+        match kind.trim() {
+            "contract" => ContractId::from_str(value)
+                .map(PolicyRule::Contract)
+                .map_err(|_| PolicyError::InvalidRule(s.to_owned())),
+            "schema" => SchemaId::from_str(value)
+                .map(PolicyRule::Schema)
+                .map_err(|_| PolicyError::InvalidRule(s.to_owned())),
+            "issuer" => Identity::from_str(value)
+                .map(PolicyRule::Issuer)
+                .map_err(|_| PolicyError::InvalidRule(s.to_owned())),
+            _ => Err(PolicyError::InvalidRule(s.to_owned())),
+        }
+    }
+}
+
+/// Trust policy deciding which consignments are accepted automatically.
+///
+/// A consignment is trusted if its contract, schema or issuer is whitelisted.
+/// Empty policy doesn't restrict anything, such that wallets without a
+/// configured policy accept all valid consignments as before.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct TrustPolicy {
+    #[cfg_attr(feature = "serde", serde(default))]
+    contracts: BTreeSet<ContractId>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    schemata: BTreeSet<SchemaId>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    issuers: BTreeSet<Identity>,
+}
+
+impl TrustPolicy {
+    pub fn new() -> Self { Self::default() }
+
+    /// Loads policy from a YAML file, returning an empty policy if the file
+    /// doesn't exist.
+    #[cfg(feature = "fs")]
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self, PolicyError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = fs::File::open(path)?;
+        Ok(serde_yaml::from_reader(file)?)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn save_file(&self, path: impl AsRef<Path>) -> Result<(), PolicyError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = fs::File::create(path)?;
+        serde_yaml::to_writer(file, self)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contracts.is_empty() && self.schemata.is_empty() && self.issuers.is_empty()
+    }
+
+    /// Adds rule to the policy, returning whether it wasn't present before.
+    pub fn add(&mut self, rule: PolicyRule) -> bool {
+        match rule {
+            PolicyRule::Contract(id) => self.contracts.insert(id),
+            PolicyRule::Schema(id) => self.schemata.insert(id),
+            PolicyRule::Issuer(identity) => self.issuers.insert(identity),
+        }
+    }
+
+    /// Removes rule from the policy, returning whether it was present.
+    pub fn remove(&mut self, rule: &PolicyRule) -> bool {
+        match rule {
+            PolicyRule::Contract(id) => self.contracts.remove(id),
+            PolicyRule::Schema(id) => self.schemata.remove(id),
+            PolicyRule::Issuer(identity) => self.issuers.remove(identity),
+        }
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = PolicyRule> + '_ {
+        self.contracts
+            .iter()
+            .copied()
+            .map(PolicyRule::Contract)
+            .chain(self.schemata.iter().copied().map(PolicyRule::Schema))
+            .chain(self.issuers.iter().cloned().map(PolicyRule::Issuer))
+    }
+
+    /// Returns the rule whitelisting the consignment, if any.
+    pub fn matching_rule(&self, consignment: &impl ConsignmentExt) -> Option<PolicyRule> {
+        let contract_id = consignment.contract_id();
+        let schema_id = consignment.schema_id();
+        let issuer = &consignment.genesis().issuer;
+        if self.contracts.contains(&contract_id) {
+            Some(PolicyRule::Contract(contract_id))
+        } else if self.schemata.contains(&schema_id) {
+            Some(PolicyRule::Schema(schema_id))
+        } else if self.issuers.contains(issuer) {
+            Some(PolicyRule::Issuer(issuer.clone()))
+        } else {
+            None
+        }
+    }
+
+    /// Checks whether the consignment may be accepted automatically.
+    pub fn is_trusted(&self, consignment: &impl ConsignmentExt) -> bool {
+        self.is_empty() || self.matching_rule(consignment).is_some()
+    }
+}
+
+/// Directory keeping consignments which were not trusted by the
+/// [`TrustPolicy`] until they are reviewed manually.
+#[cfg(feature = "fs")]
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Quarantine {
+    dir: PathBuf,
+}
+
+#[cfg(feature = "fs")]
+impl Quarantine {
+    pub fn new(dir: impl Into<PathBuf>) -> Self { Self { dir: dir.into() } }
+
+    pub fn dir(&self) -> &Path { &self.dir }
+
+    fn path(&self, id: ConsignmentId) -> PathBuf { self.dir.join(format!("{id:-}.rgb")) }
+
+    /// Puts transfer into the quarantine, returning path to the stored file.
+    pub fn put(&self, transfer: &Transfer) -> Result<PathBuf, PolicyError> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(transfer.consignment_id());
+        transfer.save_file(&path)?;
+        Ok(path)
+    }
+
+    /// Lists files of the quarantined transfers.
+    pub fn list(&self) -> Result<Vec<PathBuf>, PolicyError> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut files = fs::read_dir(&self.dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        files.retain(|path| path.extension().is_some_and(|ext| ext == "rgb"));
+        files.sort();
+        Ok(files)
+    }
+
+    /// Removes transfer with the given consignment id from the quarantine, if
+    /// it is there.
+    pub fn release(&self, id: ConsignmentId) -> Result<bool, PolicyError> {
+        let path = self.path(id);
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(path)?;
+        Ok(true)
+    }
+}
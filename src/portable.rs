@@ -0,0 +1,152 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lossless representation of the serialized consignment data for the
+//! formats which are less expressive than YAML.
+//!
+//! Consignments use maps with non-string keys, unit values and enum tags,
+//! which can't be represented in JSON or TOML directly. The portable form
+//! replaces them with reserved single-entry maps:
+//! - `{"$null": true}` for a null (unit or absent) value;
+//! - `{"$u64": "<number>"}` for an integer not fitting into `i64`;
+//! - `{"$map": [[key, value], ...]}` for a map with non-string keys;
+//! - `{"$tag": "<tag>", "$value": value}` for a tagged (enum) value.
+//!
+//! Maps with string keys starting with `$` are always stored as `$map` lists,
+//! such that the reserved keys are never ambiguous.
+
+use serde_yaml::value::{Tag, TaggedValue};
+use serde_yaml::{Mapping, Number, Value};
+
+use crate::PortableValueError;
+
+const NULL: &str = "$null";
+const U64: &str = "$u64";
+const MAP: &str = "$map";
+const TAG: &str = "$tag";
+const TAG_VALUE: &str = "$value";
+
+/// Converts YAML value into the portable form, which can be serialized as
+/// JSON or TOML.
+pub fn to_portable(value: Value) -> Value {
+    match value {
+        Value::Null => marker(NULL, Value::Bool(true)),
+        Value::Number(num) if num.as_u64().is_some_and(|n| n > i64::MAX as u64) => {
+            marker(U64, Value::String(num.to_string()))
+        }
+        Value::Bool(_) | Value::Number(_) | Value::String(_) => value,
+        Value::Sequence(items) => Value::Sequence(items.into_iter().map(to_portable).collect()),
+        Value::Mapping(map)
+            if map
+                .keys()
+                .all(|key| key.as_str().is_some_and(|key| !key.starts_with('$'))) =>
+        {
+            Value::Mapping(
+                map.into_iter()
+                    .map(|(key, val)| (key, to_portable(val)))
+                    .collect(),
+            )
+        }
+        Value::Mapping(map) => {
+            let pairs = map
+                .into_iter()
+                .map(|(key, val)| Value::Sequence(vec![to_portable(key), to_portable(val)]))
+                .collect();
+            marker(MAP, Value::Sequence(pairs))
+        }
+        Value::Tagged(tagged) => {
+            let mut map = Mapping::new();
+            map.insert(Value::from(TAG), Value::String(tagged.tag.to_string()));
+            map.insert(Value::from(TAG_VALUE), to_portable(tagged.value));
+            Value::Mapping(map)
+        }
+    }
+}
+
+/// Restores YAML value from its portable form.
+pub fn from_portable(value: Value) -> Result<Value, PortableValueError> {
+    Ok(match value {
+        Value::Sequence(items) => Value::Sequence(
+            items
+                .into_iter()
+                .map(from_portable)
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Mapping(mut map) => {
+            if let Some(marker) = map.get(NULL) {
+                if map.len() != 1 || marker != &Value::Bool(true) {
+                    return Err(PortableValueError::InvalidMarker(NULL));
+                }
+                Value::Null
+            } else if let Some(num) = map.get(U64) {
+                let num = num
+                    .as_str()
+                    .filter(|_| map.len() == 1)
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .ok_or(PortableValueError::InvalidMarker(U64))?;
+                Value::Number(Number::from(num))
+            } else if let Some(pairs) = map.remove(MAP) {
+                let Value::Sequence(pairs) = pairs else {
+                    return Err(PortableValueError::InvalidMarker(MAP));
+                };
+                if !map.is_empty() {
+                    return Err(PortableValueError::InvalidMarker(MAP));
+                }
+                let mut map = Mapping::with_capacity(pairs.len());
+                for pair in pairs {
+                    let Value::Sequence(pair) = pair else {
+                        return Err(PortableValueError::InvalidMarker(MAP));
+                    };
+                    let [key, val] = <[Value; 2]>::try_from(pair)
+                        .map_err(|_| PortableValueError::InvalidMarker(MAP))?;
+                    map.insert(from_portable(key)?, from_portable(val)?);
+                }
+                Value::Mapping(map)
+            } else if let Some(tag) = map.remove(TAG) {
+                let (Value::String(tag), Some(val), true) =
+                    (tag, map.remove(TAG_VALUE), map.is_empty())
+                else {
+                    return Err(PortableValueError::InvalidMarker(TAG));
+                };
+                Value::Tagged(Box::new(TaggedValue {
+                    tag: Tag::new(tag),
+                    value: from_portable(val)?,
+                }))
+            } else {
+                if let Some(key) = map.keys().find(|key| !key.is_string()) {
+                    return Err(PortableValueError::NonStringKey(format!("{key:?}")));
+                }
+                Value::Mapping(
+                    map.into_iter()
+                        .map(|(key, val)| from_portable(val).map(|val| (key, val)))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+        }
+        value => value,
+    })
+}
+
+fn marker(key: &str, value: Value) -> Value {
+    let mut map = Mapping::with_capacity(1);
+    map.insert(Value::from(key), value);
+    Value::Mapping(map)
+}
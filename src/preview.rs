@@ -0,0 +1,214 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+
+use bpstd::{Address, Outpoint, Sats, ScriptPubkey, Txid, Vout};
+use rgbstd::interface::AllocatedState;
+use rgbstd::{AssignmentType, ContractId, Opout, SecretSeal, XChain};
+
+use crate::AmountFormatter;
+
+/// Destination of the state assigned by a transfer.
+#[derive(Clone, Eq, PartialEq, Debug, Display)]
+pub enum StateDestination {
+    /// Output of the witness transaction belonging to the wallet.
+    #[display("change output #{0}")]
+    Change(Vout),
+
+    /// Output of the witness transaction not belonging to the wallet.
+    #[display("output #{0}")]
+    Output(Vout),
+
+    /// Output of some other transaction.
+    #[display("{0}")]
+    Outpoint(Outpoint),
+
+    /// Blinded seal provided by the beneficiary.
+    #[display("blinded seal {0}")]
+    Blinded(XChain<SecretSeal>),
+}
+
+/// State assigned by a transfer.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct AssignmentPreview {
+    pub assignment_type: AssignmentType,
+    /// Assigned state, or `None` if the state is concealed.
+    pub state: Option<AllocatedState>,
+    pub destination: StateDestination,
+}
+
+/// State spent and assigned by a transfer under a specific contract.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ContractPreview {
+    pub contract_id: ContractId,
+    /// Spent state, or `None` for the state unknown to the wallet.
+    pub spent: BTreeMap<Opout, Option<AllocatedState>>,
+    pub assigned: Vec<AssignmentPreview>,
+    /// Formatter used to present the fungible amounts of the contract.
+    pub amounts: AmountFormatter,
+}
+
+impl ContractPreview {
+    /// Returns total amount of the spent fungible state of the given type.
+    pub fn amount_in(&self, ty: AssignmentType) -> u64 {
+        self.spent
+            .iter()
+            .filter(|(opout, _)| opout.ty == ty)
+            .filter_map(|(_, state)| match state {
+                Some(AllocatedState::Amount(amount)) => Some(amount.value()),
+                _ => None,
+            })
+            .sum()
+    }
+
+    /// Returns total amount of the fungible state of the given type assigned
+    /// to the destinations matching the `filter`.
+    pub fn amount_out(
+        &self,
+        ty: AssignmentType,
+        filter: impl Fn(&StateDestination) -> bool,
+    ) -> u64 {
+        self.assigned
+            .iter()
+            .filter(|a| a.assignment_type == ty && filter(&a.destination))
+            .filter_map(|a| match a.state {
+                Some(AllocatedState::Amount(amount)) => Some(amount.value()),
+                _ => None,
+            })
+            .sum()
+    }
+
+    /// Returns total amount of the fungible state of the given type returned
+    /// to the wallet as a change.
+    pub fn change(&self, ty: AssignmentType) -> u64 {
+        self.amount_out(ty, |d| matches!(d, StateDestination::Change(_)))
+    }
+}
+
+/// Bitcoin output of a transfer witness transaction.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TxOutPreview {
+    pub vout: Vout,
+    pub value: Sats,
+    pub script: ScriptPubkey,
+    pub address: Option<Address>,
+    /// Whether the output belongs to the wallet.
+    pub is_own: bool,
+}
+
+/// Human-readable description of the assets and bitcoins moved by a PSBT,
+/// which allows to audit the PSBT before signing it.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TransferPreview {
+    pub txid: Txid,
+    /// Transaction fee, or `None` if the PSBT lacks information about the
+    /// spent amounts.
+    pub fee: Option<Sats>,
+    pub outputs: Vec<TxOutPreview>,
+    pub contracts: Vec<ContractPreview>,
+}
+
+impl TransferPreview {
+    /// Switches presentation of the fungible amounts of all contracts to the
+    /// smallest units instead of adjusting them for the contract precision.
+    pub fn set_raw(&mut self, raw: bool) {
+        for contract in &mut self.contracts {
+            contract.amounts.set_raw(raw);
+        }
+    }
+}
+
+impl Display for TransferPreview {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Transaction {}", self.txid)?;
+        match self.fee {
+            Some(fee) => writeln!(f, "Fee: {fee} sats")?,
+            None => writeln!(f, "Fee: unknown")?,
+        }
+        writeln!(f, "Outputs:")?;
+        for output in &self.outputs {
+            let owner = if output.is_own { "wallet" } else { "external" };
+            write!(f, "  #{:<3} {:>12} sats  {owner:8}  ", output.vout, output.value)?;
+            match &output.address {
+                Some(address) => writeln!(f, "{address}")?,
+                None => writeln!(f, "{:x}", output.script)?,
+            }
+        }
+        for contract in &self.contracts {
+            writeln!(f, "Contract {}", contract.contract_id)?;
+            let types = contract
+                .spent
+                .keys()
+                .map(|opout| opout.ty)
+                .chain(contract.assigned.iter().map(|a| a.assignment_type))
+                .collect::<BTreeSet<_>>();
+            let amounts = &contract.amounts;
+            for ty in types {
+                let is_fungible = contract
+                    .spent
+                    .iter()
+                    .filter(|(opout, _)| opout.ty == ty)
+                    .map(|(_, state)| state)
+                    .chain(
+                        contract
+                            .assigned
+                            .iter()
+                            .filter(|a| a.assignment_type == ty)
+                            .map(|a| &a.state),
+                    )
+                    .any(|state| matches!(state, Some(AllocatedState::Amount(_))));
+                if is_fungible {
+                    let change = amounts.format(contract.change(ty));
+                    let sent = amounts.format(
+                        contract.amount_out(ty, |d| !matches!(d, StateDestination::Change(_))),
+                    );
+                    writeln!(
+                        f,
+                        "  assignment type {ty}: spent {}, sent {sent}, change {change}",
+                        amounts.format(contract.amount_in(ty))
+                    )?;
+                }
+            }
+            for (opout, state) in &contract.spent {
+                match state {
+                    Some(state) => {
+                        writeln!(f, "  spends {} from {opout}", amounts.format_state(state))?
+                    }
+                    None => writeln!(f, "  spends unknown state from {opout}")?,
+                }
+            }
+            for assignment in &contract.assigned {
+                match &assignment.state {
+                    Some(state) => write!(f, "  assigns {}", amounts.format_state(state))?,
+                    None => write!(f, "  assigns concealed state")?,
+                }
+                writeln!(
+                    f,
+                    " of type {} to {}",
+                    assignment.assignment_type, assignment.destination
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
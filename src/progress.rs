@@ -0,0 +1,132 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::{Cell, RefCell};
+
+use rgbstd::persistence::{IndexProvider, StashProvider, StateProvider, Stock, UpdateRes};
+
+use crate::validation::{ResolveWitness, WitnessResolverError};
+use crate::vm::{WitnessOrd, XWitnessTx};
+use crate::{SyncError, XWitnessId};
+
+/// Stage of the wallet synchronization reported by [`SyncProgress`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Display)]
+#[display(lowercase)]
+pub enum SyncStage {
+    /// Wallet transactions and UTXOs are retrieved from the indexer.
+    #[default]
+    Utxos,
+
+    /// Status of the witness transactions is refreshed.
+    Witnesses,
+
+    /// Synchronization is complete.
+    Done,
+}
+
+/// Progress of the wallet synchronization, reported at the start of each
+/// [`SyncStage`] and after each refreshed witness transaction.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct SyncProgress {
+    pub stage: SyncStage,
+    /// Number of wallet addresses having transaction history, known once the
+    /// UTXOs are retrieved.
+    pub addresses_used: usize,
+    /// Number of wallet UTXOs, known once the UTXOs are retrieved.
+    pub utxos_found: usize,
+    /// Number of witness transactions which status was refreshed.
+    pub witnesses_refreshed: usize,
+    /// Estimated number of witness transactions to refresh, which includes
+    /// the ones mined before the height the update starts from.
+    pub witnesses_total: usize,
+}
+
+impl SyncProgress {
+    /// Estimated number of witness transactions remaining to be refreshed.
+    pub fn witnesses_remaining(&self) -> usize {
+        self.witnesses_total
+            .saturating_sub(self.witnesses_refreshed)
+    }
+}
+
+/// Witness resolver reporting the progress after each witness status is
+/// resolved.
+struct ProgressResolver<'p, R: ResolveWitness> {
+    inner: R,
+    progress: Cell<SyncProgress>,
+    callback: RefCell<&'p mut dyn FnMut(&SyncProgress)>,
+}
+
+impl<R: ResolveWitness> ResolveWitness for ProgressResolver<'_, R> {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        self.inner.resolve_pub_witness(witness_id)
+    }
+
+    fn resolve_pub_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        let res = self.inner.resolve_pub_witness_ord(witness_id);
+        let mut progress = self.progress.get();
+        progress.witnesses_refreshed += 1;
+        self.progress.set(progress);
+        (self.callback.borrow_mut())(&progress);
+        res
+    }
+}
+
+/// Updates the status of the witness transactions mined after
+/// `after_height`, like [`Stock::update_witnesses`], reporting the progress
+/// after each refreshed witness.
+///
+/// The `progress` is updated with the witness counts and moved to the
+/// [`SyncStage::Witnesses`] stage; reaching [`SyncStage::Done`] is left to
+/// the caller.
+pub fn update_witnesses_with_progress<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &mut Stock<S, H, P>,
+    resolver: impl ResolveWitness,
+    after_height: u32,
+    progress: &mut SyncProgress,
+    callback: &mut dyn FnMut(&SyncProgress),
+) -> Result<UpdateRes, SyncError> {
+    progress.stage = SyncStage::Witnesses;
+    progress.witnesses_refreshed = 0;
+    progress.witnesses_total = stock
+        .as_stash_provider()
+        .witness_ids()
+        .map_err(|e| SyncError::Stock(e.to_string()))?
+        .count();
+    callback(progress);
+
+    let resolver = ProgressResolver {
+        inner: resolver,
+        progress: Cell::new(*progress),
+        callback: RefCell::new(callback),
+    };
+    let res = stock
+        .update_witnesses(&resolver, after_height)
+        .map_err(|e| SyncError::Stock(e.to_string()))?;
+    *progress = resolver.progress.get();
+    Ok(res)
+}
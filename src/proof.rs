@@ -0,0 +1,235 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proofs that a contract allocation exists at a given transaction output.
+//!
+//! A proof is a transfer consignment reduced to the history of the allocations
+//! at a single output: it contains the genesis, the operations leading to the
+//! allocations and the anchors of their bundles, but neither the interfaces,
+//! the supplements nor the signatures. Only the seals of the proven
+//! allocations are revealed, such that the other outputs of the owner are not
+//! disclosed to the verifier.
+//!
+//! The proof shows that the state was assigned to the output; it doesn't show
+//! that the output is not spent yet, which must be checked by the verifier
+//! against the blockchain.
+
+use std::collections::BTreeSet;
+
+use amplify::confinement::LargeOrdSet;
+use bp::seals::txout::TxPtr;
+use bp::Outpoint;
+use rgbstd::containers::{ConsignmentExt, Transfer};
+use rgbstd::invoice::Amount;
+use rgbstd::persistence::{ContractStateRead, IndexProvider, StashProvider, StateProvider, Stock};
+use rgbstd::{ContractId, Opout, XChain, XOutputSeal, XWitnessId};
+
+use crate::resolvers::AnyResolver;
+use crate::AllocationProofError;
+
+/// Allocation shown to exist by an [`AllocationProof`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ProvenAllocation {
+    pub opout: Opout,
+    /// Witness transaction of the operation assigning the state; absent for
+    /// the allocations made by the genesis.
+    pub witness: Option<XWitnessId>,
+    /// Fungible amount, if the state is fungible.
+    pub amount: Option<Amount>,
+}
+
+impl ProvenAllocation {
+    fn new(opout: Opout, witness: Option<XWitnessId>, amount: Option<Amount>) -> Self {
+        Self {
+            opout,
+            witness,
+            amount,
+        }
+    }
+}
+
+/// Proof of the allocations of a contract at a single transaction output.
+#[derive(Clone, Debug)]
+pub struct AllocationProof(Transfer);
+
+impl From<Transfer> for AllocationProof {
+    fn from(transfer: Transfer) -> Self { Self(transfer) }
+}
+
+impl AllocationProof {
+    /// Builds proof of all the allocations of the contract at the output
+    /// known to the stock.
+    pub fn build<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        stock: &Stock<S, H, P>,
+        contract_id: ContractId,
+        outpoint: Outpoint,
+    ) -> Result<Self, AllocationProofError> {
+        let state = stock
+            .contract_state(contract_id)
+            .map_err(|_| AllocationProofError::UnknownContract(contract_id))?;
+
+        let at_outpoint = |seal: &XOutputSeal| match seal {
+            XChain::Bitcoin(seal) => seal.to_outpoint() == outpoint,
+            _ => false,
+        };
+        let mut seals = Vec::new();
+        seals.extend(state.rights_all().map(|a| a.seal).filter(at_outpoint));
+        seals.extend(state.fungible_all().map(|a| a.seal).filter(at_outpoint));
+        seals.extend(state.data_all().map(|a| a.seal).filter(at_outpoint));
+        seals.extend(state.attach_all().map(|a| a.seal).filter(at_outpoint));
+        if seals.is_empty() {
+            return Err(AllocationProofError::NoAllocation {
+                contract_id,
+                outpoint,
+            });
+        }
+
+        let mut transfer = stock
+            .transfer(contract_id, seals, None)
+            .map_err(|e| AllocationProofError::Stock(e.to_string()))?;
+        transfer.ifaces = none!();
+        transfer.supplements = none!();
+        transfer.signatures = none!();
+
+        // Reveal the seals of the proven allocations and nothing else, such that
+        // the verifier can find them at the output.
+        let stash = stock.as_stash_provider();
+        let mut bundles = LargeOrdSet::with_capacity(transfer.bundles.len());
+        for mut witness_bundle in transfer.bundles {
+            let witness_id = witness_bundle.witness_id();
+            let secrets = witness_bundle
+                .anchored_bundles
+                .bundles()
+                .flat_map(|bundle| {
+                    bundle
+                        .known_transitions
+                        .values()
+                        .flat_map(|t| t.assignments.values())
+                        .flat_map(|a| a.to_confidential_seals())
+                        .map(move |secret| (bundle.bundle_id(), secret))
+                })
+                .collect::<BTreeSet<_>>();
+            for (bundle_id, secret) in secrets {
+                let seal = stash
+                    .seal_secret(secret)
+                    .map_err(|e| AllocationProofError::Stock(e.to_string()))?;
+                let Some(XChain::Bitcoin(graph_seal)) = seal else {
+                    continue;
+                };
+                let txid = match graph_seal.txid {
+                    TxPtr::Txid(txid) => txid,
+                    TxPtr::WitnessTx => *witness_id.as_reduced_unsafe(),
+                };
+                if Outpoint::new(txid, graph_seal.vout) == outpoint {
+                    witness_bundle.reveal_seal(bundle_id, XChain::Bitcoin(graph_seal));
+                }
+            }
+            bundles.push(witness_bundle).ok();
+        }
+        transfer.bundles = bundles;
+
+        Ok(Self(transfer))
+    }
+
+    pub fn contract_id(&self) -> ContractId { self.0.contract_id() }
+
+    /// Returns the consignment carrying the proof, which can be saved and
+    /// transferred like any other consignment.
+    pub fn consignment(&self) -> &Transfer { &self.0 }
+
+    pub fn into_consignment(self) -> Transfer { self.0 }
+
+    /// Verifies the proof against the contract genesis and the anchors of the
+    /// witness transactions, returning allocations of the contract at the
+    /// output.
+    ///
+    /// The check doesn't involve the stock of the verifier: the proof is
+    /// validated and accepted into a temporary in-memory stock. The verifier
+    /// must still check that the output is not spent.
+    pub fn verify(
+        &self,
+        contract_id: ContractId,
+        outpoint: Outpoint,
+        resolver: &mut AnyResolver,
+        is_testnet: bool,
+    ) -> Result<Vec<ProvenAllocation>, AllocationProofError> {
+        let found = self.contract_id();
+        if found != contract_id {
+            return Err(AllocationProofError::WrongContract {
+                expected: contract_id,
+                found,
+            });
+        }
+
+        resolver.add_terminals(&self.0);
+        let valid = self
+            .0
+            .clone()
+            .validate(&*resolver, is_testnet)
+            .map_err(|(status, _)| AllocationProofError::Invalid(status))?;
+        let mut stock = Stock::in_memory();
+        stock
+            .accept_transfer(valid, &*resolver)
+            .map_err(|e| AllocationProofError::Accept(e.to_string()))?;
+        let state = stock
+            .contract_state(contract_id)
+            .map_err(|e| AllocationProofError::Accept(e.to_string()))?;
+
+        let at_outpoint = |seal: &XOutputSeal| match seal {
+            XChain::Bitcoin(seal) => seal.to_outpoint() == outpoint,
+            _ => false,
+        };
+        let mut allocations = Vec::new();
+        allocations.extend(
+            state
+                .rights_all()
+                .filter(|a| at_outpoint(&a.seal))
+                .map(|a| ProvenAllocation::new(a.opout, a.witness, None)),
+        );
+        allocations.extend(state.fungible_all().filter(|a| at_outpoint(&a.seal)).map(|a| {
+            ProvenAllocation::new(a.opout, a.witness, Some(Amount::from(a.state.value.as_u64())))
+        }));
+        allocations.extend(
+            state
+                .data_all()
+                .filter(|a| at_outpoint(&a.seal))
+                .map(|a| ProvenAllocation::new(a.opout, a.witness, None)),
+        );
+        allocations.extend(
+            state
+                .attach_all()
+                .filter(|a| at_outpoint(&a.seal))
+                .map(|a| ProvenAllocation::new(a.opout, a.witness, None)),
+        );
+        if allocations.is_empty() {
+            return Err(AllocationProofError::NoAllocation {
+                contract_id,
+                outpoint,
+            });
+        }
+        Ok(allocations)
+    }
+}
@@ -0,0 +1,339 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2023 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2023 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+
+//! Queries over the state of contracts with a large number of allocations,
+//! filtering them by the seal outpoint, amount and status of the witness
+//! transaction, and splitting the result into pages.
+//!
+//! All queries are lazy: the allocations are filtered while iterating over
+//! the contract state, such that a page of the state never requires the whole
+//! allocation set to be collected in memory.
+
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use rgbstd::interface::{
+    AssignmentsFilter, AttachAllocation, ContractError, ContractIface, DataAllocation,
+    FungibleAllocation, RightsAllocation,
+};
+use rgbstd::persistence::ContractStateRead;
+use rgbstd::vm::WitnessOrd;
+use strict_types::encoding::FieldName;
+
+use crate::invoice::Amount;
+use crate::{AmountRange, Opout, XOutpoint, XOutputSeal, XWitnessId};
+
+/// Status of the witness transaction of an allocation.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display(lowercase)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum WitnessStatus {
+    /// Allocation is defined by the contract genesis and has no witness.
+    Genesis,
+
+    /// Witness transaction is mined.
+    Mined,
+
+    /// Witness transaction is not mined yet.
+    Tentative,
+
+    /// Witness transaction was excluded from the state after a re-org or an
+    /// RBF replacement.
+    Archived,
+}
+
+impl WitnessStatus {
+    /// Detects status of the `witness` of an allocation from the contract
+    /// state. Returns `None` if the witness is not known to the state.
+    pub fn with(witness: Option<XWitnessId>, state: &impl ContractStateRead) -> Option<Self> {
+        let Some(witness) = witness else {
+            return Some(WitnessStatus::Genesis);
+        };
+        Some(match state.witness_ord(witness)? {
+            WitnessOrd::Mined(_) => WitnessStatus::Mined,
+            WitnessOrd::Tentative => WitnessStatus::Tentative,
+            WitnessOrd::Archived => WitnessStatus::Archived,
+        })
+    }
+}
+
+/// Returns confirmation depth of the `witness` of an allocation at the
+/// `tip_height`, using the witness positions from the contract state: one for
+/// the witness mined in the tip block and zero for the witness which is not
+/// mined. Allocations defined by the genesis have no witness and get
+/// `u32::MAX`. Returns `None` if the witness is not known to the state.
+pub fn confirmations(
+    witness: Option<XWitnessId>,
+    state: &impl ContractStateRead,
+    tip_height: u32,
+) -> Option<u32> {
+    let Some(witness) = witness else {
+        return Some(u32::MAX);
+    };
+    Some(match state.witness_ord(witness)? {
+        WitnessOrd::Mined(pos) => (tip_height + 1).saturating_sub(pos.height().get()),
+        WitnessOrd::Tentative | WitnessOrd::Archived => 0,
+    })
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(
+    "unknown witness status '{0}'; valid statuses are `genesis`, `mined`, `tentative` and \
+     `archived`."
+)]
+pub struct WitnessStatusParseError(pub String);
+
+impl FromStr for WitnessStatus {
+    type Err = WitnessStatusParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "genesis" => Ok(WitnessStatus::Genesis),
+            "mined" => Ok(WitnessStatus::Mined),
+            "tentative" => Ok(WitnessStatus::Tentative),
+            "archived" => Ok(WitnessStatus::Archived),
+            _ => Err(WitnessStatusParseError(s.to_owned())),
+        }
+    }
+}
+
+/// Reason an allocation is flagged by [`crate::RgbWallet::check_witnesses`].
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display(lowercase)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum StaleReason {
+    /// Witness transaction is archived by the stock, since it was replaced
+    /// (for instance, by an RBF bump) or evicted from the mempool. The
+    /// allocation is not a part of the balance.
+    Archived,
+
+    /// Witness transaction is not known to the indexer anymore, while the
+    /// stock still counts it as tentative or mined: it was replaced or evicted
+    /// after the last witness update.
+    Evicted,
+
+    /// Witness transaction is counted as mined by the stock, while the
+    /// indexer sees it in the mempool only, after a re-org.
+    Unmined,
+}
+
+/// Allocation which witness transaction was replaced, evicted or re-orged out.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct StaleAllocation {
+    pub opout: Opout,
+    pub seal: XOutputSeal,
+    pub witness: XWitnessId,
+    /// Amount of the fungible state; `None` for other state types.
+    pub amount: Option<Amount>,
+    pub reason: StaleReason,
+}
+
+/// Query selecting a page of the contract allocations.
+///
+/// Empty sets of `outpoints` and `witness` statuses don't restrict the
+/// allocations. The range of `amounts` applies only to the fungible state.
+/// Confirmations are counted at the `tip_height`, which must be provided
+/// together with a non-zero `min_confirmations`.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct StateQuery {
+    /// Outpoints of the seals to which the state must be assigned.
+    pub outpoints: BTreeSet<XOutpoint>,
+    /// Range of the fungible amounts.
+    pub amounts: AmountRange,
+    /// Statuses of the witness transactions.
+    pub witness: BTreeSet<WitnessStatus>,
+    /// Minimal confirmation depth of the witness transactions; zero doesn't
+    /// restrict the allocations.
+    pub min_confirmations: u32,
+    /// Height of the blockchain tip at which the confirmations are counted.
+    pub tip_height: u32,
+    /// Number of the matching allocations to skip.
+    pub offset: usize,
+    /// Maximal number of the allocations to return.
+    pub limit: Option<usize>,
+}
+
+impl StateQuery {
+    pub fn new() -> Self { Self::default() }
+
+    /// Constructs query returning a single page of all allocations.
+    pub fn page(offset: usize, limit: usize) -> Self {
+        StateQuery {
+            offset,
+            limit: Some(limit),
+            ..default!()
+        }
+    }
+
+    /// Restricts the query to the allocations having at least
+    /// `min_confirmations` at the `tip_height`.
+    pub fn with_min_confirmations(mut self, min_confirmations: u32, tip_height: u32) -> Self {
+        self.min_confirmations = min_confirmations;
+        self.tip_height = tip_height;
+        self
+    }
+
+    /// Checks whether the allocation with the given seal outpoint and witness
+    /// status matches the query.
+    pub fn includes(&self, outpoint: XOutpoint, status: Option<WitnessStatus>) -> bool {
+        (self.outpoints.is_empty() || self.outpoints.contains(&outpoint))
+            && (self.witness.is_empty() || status.is_some_and(|s| self.witness.contains(&s)))
+    }
+
+    /// Checks whether the confirmation depth of the witness matches the query.
+    pub fn includes_depth(&self, confirmations: Option<u32>) -> bool {
+        self.min_confirmations == 0 || confirmations.is_some_and(|c| c >= self.min_confirmations)
+    }
+
+    /// Checks whether the fungible amount matches the query.
+    pub fn includes_amount(&self, amount: Amount) -> bool { self.amounts.contains(amount) }
+
+    /// Selects the page from the allocations which have matched the query.
+    pub fn paginate<T>(&self, iter: impl Iterator<Item = T>) -> impl Iterator<Item = T> {
+        iter.skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+    }
+}
+
+/// Filter combining [`StateQuery`] with another assignments filter.
+#[derive(Copy, Clone)]
+pub struct QueryFilter<'a, F: AssignmentsFilter, S: ContractStateRead> {
+    pub query: &'a StateQuery,
+    pub state: &'a S,
+    pub inner: F,
+}
+
+impl<'a, F: AssignmentsFilter, S: ContractStateRead> AssignmentsFilter for QueryFilter<'a, F, S> {
+    fn should_include(&self, outpoint: impl Into<XOutpoint>, witness: Option<XWitnessId>) -> bool {
+        let outpoint = outpoint.into();
+        self.query
+            .includes(outpoint, WitnessStatus::with(witness, self.state))
+            && self.query.includes_depth(confirmations(witness, self.state, self.query.tip_height))
+            && self.inner.should_include(outpoint, witness)
+    }
+}
+
+/// Extension for [`ContractIface`] returning pages of the contract state
+/// matching a [`StateQuery`].
+pub trait ContractStateQuery {
+    /// Returns confirmation depth of the witness of an allocation from the
+    /// query results at the `tip_height`; see [`confirmations`].
+    fn confirmations(&self, witness: Option<XWitnessId>, tip_height: u32) -> Option<u32>;
+
+    fn query_rights<'c>(
+        &'c self,
+        name: impl Into<FieldName>,
+        filter: impl AssignmentsFilter + 'c,
+        query: &'c StateQuery,
+    ) -> Result<impl Iterator<Item = RightsAllocation> + 'c, ContractError>;
+
+    fn query_fungible<'c>(
+        &'c self,
+        name: impl Into<FieldName>,
+        filter: impl AssignmentsFilter + 'c,
+        query: &'c StateQuery,
+    ) -> Result<impl Iterator<Item = FungibleAllocation> + 'c, ContractError>;
+
+    fn query_data<'c>(
+        &'c self,
+        name: impl Into<FieldName>,
+        filter: impl AssignmentsFilter + 'c,
+        query: &'c StateQuery,
+    ) -> Result<impl Iterator<Item = DataAllocation> + 'c, ContractError>;
+
+    fn query_attachments<'c>(
+        &'c self,
+        name: impl Into<FieldName>,
+        filter: impl AssignmentsFilter + 'c,
+        query: &'c StateQuery,
+    ) -> Result<impl Iterator<Item = AttachAllocation> + 'c, ContractError>;
+}
+
+impl<S: ContractStateRead> ContractStateQuery for ContractIface<S> {
+    fn confirmations(&self, witness: Option<XWitnessId>, tip_height: u32) -> Option<u32> {
+        confirmations(witness, &self.state, tip_height)
+    }
+    fn query_rights<'c>(
+        &'c self,
+        name: impl Into<FieldName>,
+        filter: impl AssignmentsFilter + 'c,
+        query: &'c StateQuery,
+    ) -> Result<impl Iterator<Item = RightsAllocation> + 'c, ContractError> {
+        let filter = QueryFilter {
+            query,
+            state: &self.state,
+            inner: filter,
+        };
+        Ok(query.paginate(self.rights(name, filter)?))
+    }
+
+    fn query_fungible<'c>(
+        &'c self,
+        name: impl Into<FieldName>,
+        filter: impl AssignmentsFilter + 'c,
+        query: &'c StateQuery,
+    ) -> Result<impl Iterator<Item = FungibleAllocation> + 'c, ContractError> {
+        let filter = QueryFilter {
+            query,
+            state: &self.state,
+            inner: filter,
+        };
+        let iter = self
+            .fungible(name, filter)?
+            .filter(|allocation| query.includes_amount(allocation.state));
+        Ok(query.paginate(iter))
+    }
+
+    fn query_data<'c>(
+        &'c self,
+        name: impl Into<FieldName>,
+        filter: impl AssignmentsFilter + 'c,
+        query: &'c StateQuery,
+    ) -> Result<impl Iterator<Item = DataAllocation> + 'c, ContractError> {
+        let filter = QueryFilter {
+            query,
+            state: &self.state,
+            inner: filter,
+        };
+        Ok(query.paginate(self.data(name, filter)?))
+    }
+
+    fn query_attachments<'c>(
+        &'c self,
+        name: impl Into<FieldName>,
+        filter: impl AssignmentsFilter + 'c,
+        query: &'c StateQuery,
+    ) -> Result<impl Iterator<Item = AttachAllocation> + 'c, ContractError> {
+        let filter = QueryFilter {
+            query,
+            state: &self.state,
+            inner: filter,
+        };
+        Ok(query.paginate(self.attachments(name, filter)?))
+    }
+}
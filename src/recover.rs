@@ -0,0 +1,147 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rgbstd::containers::{Contract, Kit, Transfer, UniversalFile};
+use rgbstd::persistence::{IndexProvider, StashProvider, StateProvider, Stock};
+use rgbstd::validation::ResolveWitness;
+
+use crate::{reveal_known_seals, AcceptError, RecoveryError};
+
+/// Report on the stock recovery performed by [`StockRecovery::recover_from_dir`].
+#[derive(Debug, Default)]
+pub struct RecoveryReport {
+    /// Number of imported kits.
+    pub kits: usize,
+    /// Number of imported contracts.
+    pub contracts: usize,
+    /// Number of accepted transfer consignments.
+    pub transfers: usize,
+    /// Files which were not recovered, with the reason of the failure.
+    pub failed: Vec<(PathBuf, RecoveryError)>,
+}
+
+impl RecoveryReport {
+    /// Total number of files which were successfully recovered.
+    pub fn recovered(&self) -> usize { self.kits + self.contracts + self.transfers }
+}
+
+/// Reconstruction of the stock from the backups of kits, contracts and
+/// transfer consignments.
+pub trait StockRecovery {
+    /// Ingests all kits, contracts and transfer consignments found in the
+    /// directory, re-validating them against the resolver and replaying them
+    /// into the stock in the dependency order: kits first, then contracts,
+    /// and finally transfers ordered by the length of their history.
+    ///
+    /// Files which can't be loaded, are invalid or can't be added to the stock
+    /// don't abort the recovery and are listed in the returned report.
+    ///
+    /// NB: the stock recovers only seals known to it or revealed by the
+    /// consignments; state assigned to blinded seals of the invoices issued
+    /// by the wallet becomes known once the stock learns their secrets.
+    #[allow(clippy::result_large_err)]
+    fn recover_from_dir(
+        &mut self,
+        dir: impl AsRef<Path>,
+        resolver: &impl ResolveWitness,
+        is_testnet: bool,
+    ) -> Result<RecoveryReport, RecoveryError>;
+}
+
+impl<S: StashProvider, H: StateProvider, P: IndexProvider> StockRecovery for Stock<S, H, P> {
+    fn recover_from_dir(
+        &mut self,
+        dir: impl AsRef<Path>,
+        resolver: &impl ResolveWitness,
+        is_testnet: bool,
+    ) -> Result<RecoveryReport, RecoveryError> {
+        let mut paths = vec![];
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                paths.push(entry.path());
+            }
+        }
+        paths.sort();
+
+        let mut report = RecoveryReport::default();
+        let mut kits = Vec::<(PathBuf, Kit)>::new();
+        let mut contracts = Vec::<(PathBuf, Contract)>::new();
+        let mut transfers = Vec::<(PathBuf, Transfer)>::new();
+        for path in paths {
+            match UniversalFile::load_file(&path) {
+                Ok(UniversalFile::Kit(kit)) => kits.push((path, kit)),
+                Ok(UniversalFile::Contract(contract)) => contracts.push((path, contract)),
+                Ok(UniversalFile::Transfer(transfer)) => transfers.push((path, transfer)),
+                Err(err) => report.failed.push((path, RecoveryError::Load(err))),
+            }
+        }
+        // Each transfer contains the whole history of the contract since its
+        // genesis, thus the transfers with shorter history precede the ones
+        // which may extend it.
+        transfers.sort_by_key(|(_, transfer)| transfer.bundles.len());
+
+        for (path, kit) in kits {
+            let res = kit
+                .validate()
+                .map_err(|(status, _)| AcceptError::Invalid(status))
+                .and_then(|kit| {
+                    self.import_kit(kit)
+                        .map_err(|e| AcceptError::Stock(e.to_string()))
+                });
+            match res {
+                Ok(_) => report.kits += 1,
+                Err(err) => report.failed.push((path, err.into())),
+            }
+        }
+        for (path, contract) in contracts {
+            let res = contract
+                .validate(resolver, is_testnet)
+                .map_err(|(status, _)| AcceptError::Invalid(status))
+                .and_then(|contract| {
+                    self.import_contract(contract, resolver)
+                        .map_err(|e| AcceptError::Stock(e.to_string()))
+                });
+            match res {
+                Ok(_) => report.contracts += 1,
+                Err(err) => report.failed.push((path, err.into())),
+            }
+        }
+        for (path, transfer) in transfers {
+            let res = reveal_known_seals(self, transfer).and_then(|transfer| {
+                let valid = transfer
+                    .validate(resolver, is_testnet)
+                    .map_err(|(status, _)| AcceptError::Invalid(status))?;
+                self.accept_transfer(valid, resolver)
+                    .map_err(|e| AcceptError::Stock(e.to_string()))
+            });
+            match res {
+                Ok(_) => report.transfers += 1,
+                Err(err) => report.failed.push((path, err.into())),
+            }
+        }
+
+        Ok(report)
+    }
+}
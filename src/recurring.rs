@@ -0,0 +1,149 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Templates of recurring payments.
+//!
+//! Services paying the same parties on a regular basis (like payroll) don't
+//! need to request an invoice for each of the payments: everything except the
+//! beneficiary seal stays the same across the payments, and the beneficiary
+//! may be derived from the descriptor shared by the payee once. A
+//! [`PaymentTemplate`] keeps these invoice-independent parameters and
+//! instantiates invoices for each of the payments, identified by their
+//! sequential number.
+
+use std::ops::Range;
+
+use bpstd::{AddressNetwork, DeriveScripts, Idx, Network, NormalIndex};
+use rgbstd::invoice::{Amount, Beneficiary, Pay2Vout, RgbInvoice, RgbInvoiceBuilder, XChainNet};
+use rgbstd::ContractId;
+use strict_types::encoding::{FieldName, TypeName};
+
+use crate::{CompositionError, DescriptorRgb, RgbDescr, RgbKeychain};
+
+/// Beneficiary of the payments made with a [`PaymentTemplate`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum TemplateBeneficiary {
+    /// All payments are made to the same address.
+    Address(Pay2Vout),
+
+    /// Each payment is made to a new address derived from the payee wallet
+    /// descriptor. The address is taken from the RGB keychain of the payee
+    /// wallet, which it uses for address-based invoices, at the index equal to
+    /// `start` plus the number of the payment.
+    Derived {
+        descriptor: RgbDescr,
+        start: NormalIndex,
+    },
+}
+
+impl TemplateBeneficiary {
+    /// Returns the beneficiary of the payment with the given number.
+    pub fn instantiate(&self, network: Network, no: u32) -> Result<Pay2Vout, CompositionError> {
+        match self {
+            TemplateBeneficiary::Address(pay2vout) => Ok(*pay2vout),
+            TemplateBeneficiary::Derived { descriptor, start } => {
+                let index = start
+                    .checked_add(no)
+                    .ok_or(CompositionError::TemplateExhausted(no))?;
+                let method = descriptor.seal_close_method();
+                let keychain = descriptor.keychain_layout().keychain(RgbKeychain::Rgb);
+                let address = descriptor
+                    .derive_address(AddressNetwork::from(network), keychain, index)
+                    .expect("RGB descriptors always produce standard scripts");
+                Ok(Pay2Vout {
+                    address: address.payload,
+                    method,
+                })
+            }
+        }
+    }
+}
+
+/// Parameters of a recurring payment, which don't change from one payment to
+/// another.
+///
+/// Invoices for the individual payments are created with
+/// [`PaymentTemplate::instantiate`]; they can be paid one by one, or all at
+/// once with `RgbWallet::pay_batch`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PaymentTemplate {
+    pub network: Network,
+    pub contract_id: ContractId,
+    pub iface: Option<TypeName>,
+    pub assignment: Option<FieldName>,
+    pub amount: Amount,
+    pub beneficiary: TemplateBeneficiary,
+}
+
+impl PaymentTemplate {
+    pub fn new(
+        network: Network,
+        contract_id: ContractId,
+        amount: impl Into<Amount>,
+        beneficiary: TemplateBeneficiary,
+    ) -> Self {
+        Self {
+            network,
+            contract_id,
+            iface: None,
+            assignment: None,
+            amount: amount.into(),
+            beneficiary,
+        }
+    }
+
+    /// Sets the interface under which the contract state is paid.
+    pub fn set_interface(mut self, name: impl Into<TypeName>) -> Self {
+        self.iface = Some(name.into());
+        self
+    }
+
+    /// Sets the name of the assignment type which state is paid.
+    pub fn set_assignment(mut self, name: impl Into<FieldName>) -> Self {
+        self.assignment = Some(name.into());
+        self
+    }
+
+    /// Creates invoice for the payment with the given number.
+    ///
+    /// # Errors
+    ///
+    /// If the beneficiary derivation index for the payment is out of range.
+    pub fn instantiate(&self, no: u32) -> Result<RgbInvoice, CompositionError> {
+        let beneficiary = Beneficiary::WitnessVout(self.beneficiary.instantiate(self.network, no)?);
+        let mut builder = RgbInvoiceBuilder::new(XChainNet::bitcoin(self.network, beneficiary))
+            .set_contract(self.contract_id)
+            .set_amount_raw(self.amount);
+        if let Some(iface) = &self.iface {
+            builder = builder.set_interface(iface.clone());
+        }
+        if let Some(assignment) = &self.assignment {
+            builder = builder.set_assignment(assignment.clone());
+        }
+        Ok(builder.finish())
+    }
+
+    /// Creates invoices for the payments within the range of numbers.
+    pub fn instantiate_range(&self, nos: Range<u32>) -> Result<Vec<RgbInvoice>, CompositionError> {
+        nos.map(|no| self.instantiate(no)).collect()
+    }
+}
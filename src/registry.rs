@@ -0,0 +1,243 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+
+use amplify::confinement::{Confined, U16};
+use rgbstd::containers::{Consignment, ConsignmentExt, ValidContract, ValidTransfer};
+use rgbstd::interface::IfaceImpl;
+use rgbstd::persistence::{IndexProvider, StashProvider, StateProvider, Stock};
+use rgbstd::stl::AssetSpec;
+use rgbstd::validation::{ResolveWitness, Status};
+use rgbstd::{ContractId, Genesis};
+use strict_types::encoding::StrictDeserialize;
+use strict_types::FieldName;
+
+use crate::RegistryError;
+
+/// Name of the global state holding asset specification in RGB20, RGB21 and
+/// RGB25 interfaces.
+pub const SPEC_GLOBAL: &str = "spec";
+
+/// Extracts asset specification from the contract genesis using the global
+/// state type provided by any of the interface implementations.
+pub fn asset_spec<'a>(
+    genesis: &Genesis,
+    iimpls: impl IntoIterator<Item = &'a IfaceImpl>,
+) -> Option<AssetSpec> {
+    let name = FieldName::from(SPEC_GLOBAL);
+    iimpls
+        .into_iter()
+        .filter_map(|iimpl| iimpl.global_type(&name))
+        .filter_map(|ty| genesis.globals.get(&ty))
+        .filter_map(|values| values.iter().next())
+        .find_map(|data| {
+            let data = Confined::<Vec<u8>, 0, U16>::try_from(data.to_vec()).ok()?;
+            AssetSpec::from_strict_serialized(data).ok()
+        })
+}
+
+/// Collision of a contract ticker or name with a contract already known to
+/// the stock.
+#[derive(Clone, Eq, PartialEq, Debug, Display)]
+pub enum AssetCollision {
+    #[display("ticker '{ticker}' is already used by contract {known}")]
+    Ticker { ticker: String, known: ContractId },
+
+    #[display("name '{name}' is already used by contract {known}")]
+    Name { name: String, known: ContractId },
+}
+
+/// Index of known contracts by their asset tickers and names, used to detect
+/// contracts imitating other assets.
+///
+/// Both tickers and names are compared case-insensitively.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct AssetRegistry {
+    tickers: BTreeMap<String, BTreeSet<ContractId>>,
+    names: BTreeMap<String, BTreeSet<ContractId>>,
+}
+
+impl AssetRegistry {
+    pub fn new() -> Self { Self::default() }
+
+    /// Indexes all contracts known to the stock.
+    pub fn with_stock<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        stock: &Stock<S, H, P>,
+    ) -> Result<Self, RegistryError> {
+        let mut registry = Self::new();
+        for info in stock
+            .contracts()
+            .map_err(|err| RegistryError::Stock(err.to_string()))?
+        {
+            let genesis = stock
+                .as_stash_provider()
+                .genesis(info.id)
+                .map_err(|err| RegistryError::Stock(err.to_string()))?;
+            let schema = stock
+                .schema(info.schema_id)
+                .map_err(|err| RegistryError::Stock(err.to_string()))?;
+            if let Some(spec) = asset_spec(genesis, schema.iimpls.values()) {
+                registry.register(info.id, &spec);
+            }
+        }
+        Ok(registry)
+    }
+
+    pub fn register(&mut self, contract_id: ContractId, spec: &AssetSpec) {
+        if !spec.ticker.is_empty() {
+            self.tickers
+                .entry(normalize(spec.ticker.as_str()))
+                .or_default()
+                .insert(contract_id);
+        }
+        self.names
+            .entry(normalize(spec.name.as_str()))
+            .or_default()
+            .insert(contract_id);
+    }
+
+    pub fn contracts_by_ticker(&self, ticker: &str) -> impl Iterator<Item = ContractId> + '_ {
+        self.tickers
+            .get(&normalize(ticker))
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+
+    pub fn contracts_by_name(&self, name: &str) -> impl Iterator<Item = ContractId> + '_ {
+        self.names
+            .get(&normalize(name))
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+
+    /// Lists collisions of the asset specification with other known
+    /// contracts.
+    pub fn collisions(&self, contract_id: ContractId, spec: &AssetSpec) -> Vec<AssetCollision> {
+        let mut collisions = vec![];
+        if !spec.ticker.is_empty() {
+            collisions.extend(
+                self.contracts_by_ticker(spec.ticker.as_str())
+                    .filter(|known| *known != contract_id)
+                    .map(|known| AssetCollision::Ticker {
+                        ticker: spec.ticker.to_string(),
+                        known,
+                    }),
+            );
+        }
+        collisions.extend(
+            self.contracts_by_name(spec.name.as_str())
+                .filter(|known| *known != contract_id)
+                .map(|known| AssetCollision::Name {
+                    name: spec.name.to_string(),
+                    known,
+                }),
+        );
+        collisions
+    }
+
+    /// Lists collisions of the contract from the consignment with other known
+    /// contracts.
+    pub fn check<const TRANSFER: bool>(
+        &self,
+        consignment: &Consignment<TRANSFER>,
+    ) -> Vec<AssetCollision> {
+        asset_spec(&consignment.genesis, consignment.ifaces.values())
+            .map(|spec| self.collisions(consignment.contract_id(), &spec))
+            .unwrap_or_default()
+    }
+}
+
+fn normalize(s: &str) -> String { s.trim().to_lowercase() }
+
+/// Collisions found when importing a contract; displayed as a list.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct AssetCollisions(pub Vec<AssetCollision>);
+
+impl Display for AssetCollisions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for collision in &self.0 {
+            writeln!(f, "- {collision}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Stock extension checking contracts for ticker and name collisions with the
+/// already known contracts on import.
+pub trait AssetRegistryStock {
+    /// Imports contract, returning collisions of its ticker and name as
+    /// warnings. Unless `allow_duplicates` is set, the contract with
+    /// collisions is not imported and an error is returned.
+    fn import_contract_checked<R: ResolveWitness>(
+        &mut self,
+        contract: ValidContract,
+        resolver: R,
+        allow_duplicates: bool,
+    ) -> Result<(Status, Vec<AssetCollision>), RegistryError>;
+
+    /// Accepts transfer, returning collisions of the contract ticker and name
+    /// as warnings. Unless `allow_duplicates` is set, the transfer with
+    /// collisions is not accepted and an error is returned.
+    fn accept_transfer_checked<R: ResolveWitness>(
+        &mut self,
+        transfer: ValidTransfer,
+        resolver: R,
+        allow_duplicates: bool,
+    ) -> Result<(Status, Vec<AssetCollision>), RegistryError>;
+}
+
+impl<S: StashProvider, H: StateProvider, P: IndexProvider> AssetRegistryStock for Stock<S, H, P> {
+    fn import_contract_checked<R: ResolveWitness>(
+        &mut self,
+        contract: ValidContract,
+        resolver: R,
+        allow_duplicates: bool,
+    ) -> Result<(Status, Vec<AssetCollision>), RegistryError> {
+        let collisions = AssetRegistry::with_stock(self)?.check(&contract);
+        if !allow_duplicates && !collisions.is_empty() {
+            return Err(RegistryError::Collision(AssetCollisions(collisions)));
+        }
+        let status = self
+            .import_contract(contract, resolver)
+            .map_err(|err| RegistryError::Stock(err.to_string()))?;
+        Ok((status, collisions))
+    }
+
+    fn accept_transfer_checked<R: ResolveWitness>(
+        &mut self,
+        transfer: ValidTransfer,
+        resolver: R,
+        allow_duplicates: bool,
+    ) -> Result<(Status, Vec<AssetCollision>), RegistryError> {
+        let collisions = AssetRegistry::with_stock(self)?.check(&transfer);
+        if !allow_duplicates && !collisions.is_empty() {
+            return Err(RegistryError::Collision(AssetCollisions(collisions)));
+        }
+        let status = self
+            .accept_transfer(transfer, resolver)
+            .map_err(|err| RegistryError::Stock(err.to_string()))?;
+        Ok((status, collisions))
+    }
+}
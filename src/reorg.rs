@@ -0,0 +1,120 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+
+use bp::BlockHash;
+use bpstd::Txid;
+
+use crate::resolvers::AnyResolver;
+use crate::vm::WitnessOrd;
+use crate::ReorgError;
+
+/// Tracks hashes of the blocks mining RGB witness transactions, allowing to
+/// detect blockchain re-orgs affecting the contract state.
+///
+/// Witness ordering in the stock only keeps block heights, thus without the
+/// tracker a re-org replacing a block with a witness transaction goes
+/// unnoticed until the next full witness update.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ReorgTracker {
+    blocks: BTreeMap<u32, BlockHash>,
+    witnesses: BTreeMap<Txid, u32>,
+}
+
+impl ReorgTracker {
+    pub fn new() -> Self { Self::default() }
+
+    /// Returns height of the block mining the witness transaction, if the
+    /// transaction is tracked.
+    pub fn witness_height(&self, txid: Txid) -> Option<u32> { self.witnesses.get(&txid).copied() }
+
+    /// Returns hash of the tracked block at the given height.
+    pub fn block_hash(&self, height: u32) -> Option<BlockHash> { self.blocks.get(&height).copied() }
+
+    /// Returns witness transactions mined at the given height or above.
+    pub fn witnesses_from(&self, height: u32) -> impl Iterator<Item = Txid> + '_ {
+        self.witnesses
+            .iter()
+            .filter(move |(_, h)| **h >= height)
+            .map(|(txid, _)| *txid)
+    }
+
+    /// Records the ordering of a witness transaction, resolving the hash of
+    /// the block mining it. Witnesses which are not mined are not tracked.
+    pub fn record(
+        &mut self,
+        txid: Txid,
+        ord: WitnessOrd,
+        resolver: &AnyResolver,
+    ) -> Result<(), ReorgError> {
+        let WitnessOrd::Mined(pos) = ord else {
+            self.witnesses.remove(&txid);
+            return Ok(());
+        };
+        let height = pos.height().get();
+        if let Entry::Vacant(entry) = self.blocks.entry(height) {
+            let block_hash = resolver
+                .resolve_block_hash(height)
+                .map_err(|e| ReorgError::BlockResolver(height, e))?;
+            entry.insert(block_hash);
+        }
+        self.witnesses.insert(txid, height);
+        Ok(())
+    }
+
+    /// Detects re-org by checking tracked blocks against the main chain with
+    /// the tip at `new_tip` height.
+    ///
+    /// Returns the lowest height of the tracked blocks which are not part of
+    /// the main chain anymore, or `None` if the tracked blocks are unaffected.
+    pub fn detect(&self, new_tip: u32, resolver: &AnyResolver) -> Result<Option<u32>, ReorgError> {
+        for (height, block_hash) in &self.blocks {
+            if *height > new_tip {
+                return Ok(Some(*height));
+            }
+            let main_hash = resolver
+                .resolve_block_hash(*height)
+                .map_err(|e| ReorgError::BlockResolver(*height, e))?;
+            if main_hash != *block_hash {
+                return Ok(Some(*height));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Removes all tracked blocks at the given height and above, returning
+    /// witness transactions which were mined in them.
+    pub fn rollback(&mut self, height: u32) -> Vec<Txid> {
+        self.blocks.retain(|h, _| *h < height);
+        let txids = self.witnesses_from(height).collect::<Vec<_>>();
+        for txid in &txids {
+            self.witnesses.remove(txid);
+        }
+        txids
+    }
+}
@@ -0,0 +1,557 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Structured report on the consignment validation.
+//!
+//! The validation status produced by the consensus validator is a flat list of
+//! failures and warnings. The report groups them by the transition bundles and
+//! operations of the consignment they refer to, such that it is possible to
+//! find out which part of the contract history was rejected and why.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+use rgbstd::containers::{Consignment, ConsignmentExt};
+use rgbstd::validation::{Failure, Info, ResolveWitness, Status, Validity, Warning};
+use rgbstd::{BundleId, ContractId, OpFullType, OpId, Operation, XWitnessId};
+
+/// Overall result of the validation.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(lowercase)]
+pub enum ReportValidity {
+    Valid,
+    Warnings,
+    Invalid,
+}
+
+impl From<Validity> for ReportValidity {
+    fn from(validity: Validity) -> Self {
+        match validity {
+            Validity::Valid => ReportValidity::Valid,
+            Validity::Warnings => ReportValidity::Warnings,
+            Validity::Invalid => ReportValidity::Invalid,
+        }
+    }
+}
+
+/// Severity of a validation issue.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(lowercase)]
+pub enum IssueSeverity {
+    Failure,
+    Warning,
+    Info,
+}
+
+/// Kind of a validation issue.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(lowercase)]
+pub enum IssueKind {
+    /// Validation runs on a network different from the contract one.
+    Network,
+    /// Schema is invalid or the operation violates it.
+    Schema,
+    /// Contract history is incomplete or inconsistent.
+    Consistency,
+    /// Witness transaction is absent or can't be resolved.
+    #[display("missing-witness")]
+    MissingWitness,
+    /// Bundle is not properly committed to or anchored in the witness.
+    Commitment,
+    /// Single-use seals are not closed or don't match their definitions.
+    #[display("seal-mismatch")]
+    SealMismatch,
+    /// State doesn't match the types required by the schema.
+    State,
+    /// Validation script of the operation has failed.
+    Script,
+    /// Issue reported by services on top of the consensus validation.
+    Custom,
+}
+
+/// Details of a failed validation script.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ScriptFailure {
+    /// Error code returned by the script, if any.
+    pub code: Option<u8>,
+    /// Error message returned by the script, if any.
+    pub message: Option<String>,
+    /// Entry point of the script in the AluVM library, in `offset @ library`
+    /// form, as defined by the schema for the operation type.
+    pub entry_point: Option<String>,
+}
+
+/// Single issue found during the validation.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ValidationIssue {
+    pub severity: IssueSeverity,
+    pub kind: IssueKind,
+    /// Witness the issue is related to, if any.
+    pub witness_id: Option<XWitnessId>,
+    /// Details of the script failure, for the issues of [`IssueKind::Script`]
+    /// kind.
+    pub script: Option<ScriptFailure>,
+    /// Human-readable description of the issue.
+    pub message: String,
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.severity, self.kind, self.message)?;
+        if let Some(entry_point) = self.script.as_ref().and_then(|s| s.entry_point.as_ref()) {
+            write!(f, " [script entry point {entry_point}]")?;
+        }
+        Ok(())
+    }
+}
+
+/// Validation results of a single contract operation.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct OperationReport {
+    pub opid: OpId,
+    /// Type of the operation, or `None` if the operation is absent from the
+    /// consignment.
+    pub op_type: Option<OpFullType>,
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl OperationReport {
+    /// Detects whether the operation has no validation failures.
+    pub fn is_valid(&self) -> bool { !has_failures(&self.issues) }
+}
+
+/// Validation results of a transition bundle and its operations.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct BundleReport {
+    pub bundle_id: BundleId,
+    /// Witness anchoring the bundle, or `None` if the bundle is absent from
+    /// the consignment.
+    pub witness_id: Option<XWitnessId>,
+    /// Issues related to the bundle as a whole, like its anchoring.
+    pub issues: Vec<ValidationIssue>,
+    pub operations: Vec<OperationReport>,
+}
+
+impl BundleReport {
+    /// Detects whether neither the bundle nor its operations have validation
+    /// failures.
+    pub fn is_valid(&self) -> bool {
+        !has_failures(&self.issues) && self.operations.iter().all(OperationReport::is_valid)
+    }
+}
+
+/// Full validation report on a consignment, with the results of each bundle
+/// and operation.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ValidationReport {
+    pub contract_id: ContractId,
+    pub validity: ReportValidity,
+    /// Issues not related to a specific bundle or operation, like the schema
+    /// or network mismatches.
+    pub issues: Vec<ValidationIssue>,
+    /// Operations which are not part of any bundle: genesis and state
+    /// extensions.
+    pub operations: Vec<OperationReport>,
+    pub bundles: Vec<BundleReport>,
+}
+
+enum Location {
+    General,
+    Bundle(BundleId),
+    Operation(OpId),
+}
+
+impl ValidationReport {
+    /// Validates the consignment and reports the results.
+    pub fn validate<const TRANSFER: bool>(
+        consignment: Consignment<TRANSFER>,
+        resolver: &impl ResolveWitness,
+        testnet: bool,
+    ) -> Self {
+        let (consignment, status) = match consignment.validate(resolver, testnet) {
+            Ok(valid) => valid.split(),
+            Err((status, consignment)) => (consignment, status),
+        };
+        Self::with(&consignment, &status)
+    }
+
+    /// Constructs the report from the validation `status` of the
+    /// `consignment`.
+    pub fn with<const TRANSFER: bool>(
+        consignment: &Consignment<TRANSFER>,
+        status: &Status,
+    ) -> Self {
+        let mut report = ValidationReport {
+            contract_id: consignment.contract_id(),
+            validity: status.validity().into(),
+            issues: vec![],
+            operations: vec![OperationReport {
+                opid: consignment.genesis.id(),
+                op_type: Some(OpFullType::Genesis),
+                issues: vec![],
+            }],
+            bundles: vec![],
+        };
+        report.operations.extend(
+            consignment
+                .extensions
+                .iter()
+                .map(|extension| OperationReport {
+                    opid: extension.id(),
+                    op_type: Some(extension.full_type()),
+                    issues: vec![],
+                }),
+        );
+        for witness_bundle in &consignment.bundles {
+            let witness_id = witness_bundle.witness_id();
+            for bundle in witness_bundle.anchored_bundles.bundles() {
+                let mut operations = bundle
+                    .known_transitions
+                    .iter()
+                    .map(|(opid, transition)| OperationReport {
+                        opid: *opid,
+                        op_type: Some(transition.full_type()),
+                        issues: vec![],
+                    })
+                    .collect::<Vec<_>>();
+                for opid in bundle.input_map.values() {
+                    if !operations.iter().any(|op| op.opid == *opid) {
+                        operations.push(OperationReport {
+                            opid: *opid,
+                            op_type: None,
+                            issues: vec![],
+                        });
+                    }
+                }
+                report.bundles.push(BundleReport {
+                    bundle_id: bundle.bundle_id(),
+                    witness_id: Some(witness_id),
+                    issues: vec![],
+                    operations,
+                });
+            }
+        }
+
+        let entry_points = consignment
+            .schema
+            .transitions
+            .iter()
+            .filter_map(|(ty, schema)| {
+                Some((OpFullType::StateTransition(*ty), schema.validator?.to_string()))
+            })
+            .chain(
+                consignment
+                    .schema
+                    .extensions
+                    .iter()
+                    .filter_map(|(ty, schema)| {
+                        Some((OpFullType::StateExtension(*ty), schema.validator?.to_string()))
+                    }),
+            )
+            .chain(
+                consignment
+                    .schema
+                    .genesis
+                    .validator
+                    .map(|site| (OpFullType::Genesis, site.to_string())),
+            )
+            .collect::<BTreeMap<_, _>>();
+
+        for failure in &status.failures {
+            let (location, mut issue) = failure_issue(failure);
+            if let Some(script) = &mut issue.script {
+                if let Location::Operation(opid) = location {
+                    script.entry_point = report
+                        .operation(opid)
+                        .and_then(|op| op.op_type)
+                        .and_then(|ty| entry_points.get(&ty).cloned());
+                }
+            }
+            report.push(location, issue);
+        }
+        for warning in &status.warnings {
+            let (location, issue) = warning_issue(warning);
+            report.push(location, issue);
+        }
+        for info in &status.info {
+            let Info::Custom(message) = info;
+            report.push(Location::General, issue(IssueSeverity::Info, IssueKind::Custom, message));
+        }
+        report
+    }
+
+    /// Returns the report of the operation, if it is known.
+    pub fn operation(&self, opid: OpId) -> Option<&OperationReport> {
+        self.operations
+            .iter()
+            .chain(self.bundles.iter().flat_map(|bundle| &bundle.operations))
+            .find(|op| op.opid == opid)
+    }
+
+    /// Iterates over all issues of the report.
+    pub fn all_issues(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues
+            .iter()
+            .chain(self.operations.iter().flat_map(|op| &op.issues))
+            .chain(self.bundles.iter().flat_map(|bundle| {
+                bundle
+                    .issues
+                    .iter()
+                    .chain(bundle.operations.iter().flat_map(|op| &op.issues))
+            }))
+    }
+
+    fn push(&mut self, location: Location, issue: ValidationIssue) {
+        match location {
+            Location::General => self.issues.push(issue),
+            Location::Bundle(bundle_id) => self.bundle_mut(bundle_id).issues.push(issue),
+            Location::Operation(opid) => {
+                let op = self
+                    .operations
+                    .iter_mut()
+                    .chain(
+                        self.bundles
+                            .iter_mut()
+                            .flat_map(|bundle| &mut bundle.operations),
+                    )
+                    .find(|op| op.opid == opid);
+                match op {
+                    Some(op) => op.issues.push(issue),
+                    None => self.operations.push(OperationReport {
+                        opid,
+                        op_type: None,
+                        issues: vec![issue],
+                    }),
+                }
+            }
+        }
+    }
+
+    fn bundle_mut(&mut self, bundle_id: BundleId) -> &mut BundleReport {
+        let pos = match self
+            .bundles
+            .iter()
+            .position(|bundle| bundle.bundle_id == bundle_id)
+        {
+            Some(pos) => pos,
+            None => {
+                self.bundles.push(BundleReport {
+                    bundle_id,
+                    witness_id: None,
+                    issues: vec![],
+                    operations: vec![],
+                });
+                self.bundles.len() - 1
+            }
+        };
+        &mut self.bundles[pos]
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Consignment for contract {} is {}", self.contract_id, self.validity)?;
+        for issue in &self.issues {
+            writeln!(f, "- {issue}")?;
+        }
+        let fmt_op = |f: &mut Formatter<'_>, op: &OperationReport, indent: &str| {
+            let status = if op.is_valid() { "ok" } else { "FAILED" };
+            match op.op_type {
+                Some(ty) => writeln!(f, "{indent}{ty} {}: {status}", op.opid)?,
+                None => writeln!(f, "{indent}absent operation {}: {status}", op.opid)?,
+            }
+            for issue in &op.issues {
+                writeln!(f, "{indent}  - {issue}")?;
+            }
+            Ok(())
+        };
+        for op in &self.operations {
+            fmt_op(f, op, "")?;
+        }
+        for bundle in &self.bundles {
+            let status = if bundle.is_valid() { "ok" } else { "FAILED" };
+            match bundle.witness_id {
+                Some(witness_id) => {
+                    writeln!(f, "bundle {} (witness {witness_id}): {status}", bundle.bundle_id)?
+                }
+                None => writeln!(f, "absent bundle {}: {status}", bundle.bundle_id)?,
+            }
+            for issue in &bundle.issues {
+                writeln!(f, "  - {issue}")?;
+            }
+            for op in &bundle.operations {
+                fmt_op(f, op, "  ")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn has_failures(issues: &[ValidationIssue]) -> bool {
+    issues
+        .iter()
+        .any(|issue| issue.severity == IssueSeverity::Failure)
+}
+
+fn issue(severity: IssueSeverity, kind: IssueKind, message: impl ToString) -> ValidationIssue {
+    ValidationIssue {
+        severity,
+        kind,
+        witness_id: None,
+        script: None,
+        message: message.to_string(),
+    }
+}
+
+fn failure_issue(failure: &Failure) -> (Location, ValidationIssue) {
+    use IssueKind::*;
+    use Location::{Bundle, General, Operation};
+
+    let (location, kind, witness_id) = match failure {
+        Failure::NetworkMismatch(_) => (General, Network, None),
+        Failure::SchemaMismatch { .. }
+        | Failure::SchemaBlankTransitionRedefined
+        | Failure::SchemaGlobalSemIdUnknown(..)
+        | Failure::SchemaOwnedSemIdUnknown(..)
+        | Failure::SchemaMetaSemIdUnknown(..)
+        | Failure::SchemaOpEmptyInputs(_)
+        | Failure::SchemaOpMetaTypeUnknown(..)
+        | Failure::SchemaOpGlobalTypeUnknown(..)
+        | Failure::SchemaOpAssignmentTypeUnknown(..)
+        | Failure::SchemaOpValencyTypeUnknown(..)
+        | Failure::AssetTagNoState(_)
+        | Failure::FungibleStateNoTag(_) => (General, Schema, None),
+        Failure::SchemaUnknownExtensionType(opid, _)
+        | Failure::SchemaUnknownTransitionType(opid, _)
+        | Failure::SchemaUnknownMetaType(opid, _)
+        | Failure::SchemaUnknownGlobalStateType(opid, _)
+        | Failure::SchemaUnknownAssignmentType(opid, _)
+        | Failure::SchemaUnknownValencyType(opid, _)
+        | Failure::SchemaGlobalStateOccurrences(opid, ..)
+        | Failure::SchemaGlobalStateLimit(opid, ..)
+        | Failure::SchemaNoMetadata(opid, _)
+        | Failure::SchemaInvalidMetadata(opid, _)
+        | Failure::SchemaInvalidGlobalValue(opid, ..)
+        | Failure::SchemaInvalidOwnedValue(opid, ..)
+        | Failure::SchemaInputOccurrences(opid, ..)
+        | Failure::SchemaAssignmentOccurrences(opid, ..) => (Operation(*opid), Schema, None),
+        Failure::CyclicGraph(opid)
+        | Failure::OperationAbsent(opid)
+        | Failure::ContractMismatch(opid, _)
+        | Failure::NoPrevState { opid, .. }
+        | Failure::NoPrevOut(opid, _)
+        | Failure::ValencyNoParent { opid, .. }
+        | Failure::NoPrevValency { opid, .. } => (Operation(*opid), Consistency, None),
+        Failure::BundleAbsent(bundle_id) => (Bundle(*bundle_id), Consistency, None),
+        Failure::WitnessIdAbsent(bundle_id) => (Bundle(*bundle_id), MissingWitness, None),
+        Failure::WitnessUnresolved(bundle_id, witness_id, _)
+        | Failure::SealNoPubWitness(bundle_id, witness_id, _) => {
+            (Bundle(*bundle_id), MissingWitness, Some(*witness_id))
+        }
+        Failure::AnchorAbsent(bundle_id) | Failure::AnchorMethodMismatch(bundle_id) => {
+            (Bundle(*bundle_id), Commitment, None)
+        }
+        Failure::MpcInvalid(bundle_id, witness_id, _) => {
+            (Bundle(*bundle_id), Commitment, Some(*witness_id))
+        }
+        Failure::BundleExtraTransition(_, opid) => (Operation(*opid), Commitment, None),
+        Failure::BundleInvalidInput(_, opid, witness_id)
+        | Failure::BundleInvalidCommitment(_, _, witness_id, opid) => {
+            (Operation(*opid), Commitment, Some(*witness_id))
+        }
+        Failure::ConfidentialSeal(opout) => (Operation(opout.op), SealMismatch, None),
+        Failure::SealsUnvalidated(opid) => (Operation(*opid), SealMismatch, None),
+        Failure::SealWitnessLayer1Mismatch { .. } | Failure::SealLayerMismatch(..) => {
+            (General, SealMismatch, None)
+        }
+        Failure::SealInvalidMethod(bundle_id, _) => (Bundle(*bundle_id), SealMismatch, None),
+        Failure::SealsInvalid(bundle_id, witness_id, _) => {
+            (Bundle(*bundle_id), SealMismatch, Some(*witness_id))
+        }
+        Failure::StateTypeMismatch { opid, .. }
+        | Failure::MediaTypeMismatch { opid, .. }
+        | Failure::FungibleTypeMismatch { opid, .. }
+        | Failure::BulletproofsInvalid(opid, ..)
+        | Failure::ContractStateFilled(opid) => (Operation(*opid), State, None),
+        Failure::ScriptFailure(opid, _, _) => (Operation(*opid), Script, None),
+        Failure::Custom(_) => (General, Custom, None),
+    };
+    let mut issue = issue(IssueSeverity::Failure, kind, failure);
+    issue.witness_id = witness_id;
+    if let Failure::ScriptFailure(_, code, message) = failure {
+        issue.script = Some(ScriptFailure {
+            code: *code,
+            message: message.clone(),
+            entry_point: None,
+        });
+    }
+    (location, issue)
+}
+
+fn warning_issue(warning: &Warning) -> (Location, ValidationIssue) {
+    let (location, kind) = match warning {
+        Warning::UncheckableConfidentialState(opid, _) => {
+            (Location::Operation(*opid), IssueKind::State)
+        }
+        Warning::Custom(_) => (Location::General, IssueKind::Custom),
+    };
+    (location, issue(IssueSeverity::Warning, kind, warning))
+}
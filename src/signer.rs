@@ -0,0 +1,174 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(feature = "hot")]
+use amplify::Wrapper;
+use bpstd::Psbt;
+#[cfg(feature = "hot")]
+use bpstd::{
+    secp256k1::{ecdsa, schnorr as bip340},
+    InternalKeypair, InternalPk, KeyOrigin, LegacyPk, Sighash, Sign, TapLeafHash, TapMerklePath,
+    TapNodeHash, TapSighash, XOnlyPk, Xpriv, XprivAccount,
+};
+
+use crate::SignerError;
+
+/// Signer of witness transactions.
+///
+/// Implementations may keep keys in memory, use hardware wallets or pass the
+/// PSBT to an external service. A signer must leave inputs it doesn't have
+/// keys for untouched, such that PSBT can be signed by multiple signers.
+pub trait Signer {
+    /// Signs PSBT inputs which can be signed by the signer, returning number
+    /// of the produced signatures.
+    fn sign_psbt(&self, psbt: &mut Psbt) -> Result<usize, SignerError>;
+}
+
+/// Software signer keeping extended private keys in memory.
+///
+/// Intended for tests and simple setups; the keys are not protected in any
+/// way.
+#[cfg(feature = "hot")]
+#[derive(Debug)]
+pub struct SoftwareSigner {
+    accounts: Vec<XprivAccount>,
+}
+
+#[cfg(feature = "hot")]
+impl SoftwareSigner {
+    /// Constructs signer from BIP39 mnemonic phrase and optional passphrase.
+    ///
+    /// The signer uses the master key derived from the mnemonic, thus it is
+    /// able to sign with keys of any derivation path.
+    pub fn from_mnemonic(
+        phrase: &str,
+        passphrase: &str,
+        testnet: bool,
+    ) -> Result<Self, SignerError> {
+        let mnemonic = bip39::Mnemonic::parse(phrase)?;
+        let seed = mnemonic.to_seed(passphrase);
+        Ok(Self::from_xpriv(Xpriv::new_master(testnet, &seed)))
+    }
+
+    /// Constructs signer from a master extended private key.
+    pub fn from_xpriv(xpriv: Xpriv) -> Self {
+        Self {
+            accounts: vec![XprivAccount::new_master(xpriv)],
+        }
+    }
+
+    /// Constructs signer from an extended private key of an account, which
+    /// is able to sign only with keys derived from that account.
+    pub fn from_account(account: XprivAccount) -> Self {
+        Self {
+            accounts: vec![account],
+        }
+    }
+
+    /// Adds one more account to the signer.
+    pub fn add_account(&mut self, account: XprivAccount) { self.accounts.push(account); }
+
+    fn derive_subkey(&self, origin: Option<&KeyOrigin>) -> Option<Xpriv> {
+        let origin = origin?;
+        self.accounts
+            .iter()
+            .find(|account| account.origin().is_subset_of(origin))
+            .map(|account| {
+                account
+                    .xpriv()
+                    .derive_priv(&origin.derivation()[account.origin().derivation().len()..])
+            })
+    }
+}
+
+#[cfg(feature = "hot")]
+impl Signer for SoftwareSigner {
+    fn sign_psbt(&self, psbt: &mut Psbt) -> Result<usize, SignerError> { Ok(psbt.sign(self)?) }
+}
+
+#[cfg(feature = "hot")]
+impl psrgbt::Signer for SoftwareSigner {
+    type Sign<'s> = &'s SoftwareSigner;
+
+    fn approve(&self, _psbt: &Psbt) -> Result<Self::Sign<'_>, psrgbt::Rejected> { Ok(self) }
+}
+
+#[cfg(feature = "hot")]
+impl Sign for &SoftwareSigner {
+    fn sign_ecdsa(
+        &self,
+        message: Sighash,
+        pk: LegacyPk,
+        origin: Option<&KeyOrigin>,
+    ) -> Option<ecdsa::Signature> {
+        let sk = self.derive_subkey(origin)?;
+        if sk.to_compr_pk().to_inner() != pk.pubkey {
+            return None;
+        }
+        Some(sk.to_private_ecdsa().sign_ecdsa(message.into()))
+    }
+
+    fn sign_bip340_key_only(
+        &self,
+        message: TapSighash,
+        pk: InternalPk,
+        origin: Option<&KeyOrigin>,
+        merkle_root: Option<TapNodeHash>,
+    ) -> Option<bip340::Signature> {
+        let xpriv = self.derive_subkey(origin)?;
+        if xpriv.to_xonly_pk() != pk.to_xonly_pk() {
+            return None;
+        }
+        let output_pair = InternalKeypair::from(xpriv.to_keypair_bip340())
+            .to_output_keypair(merkle_root)
+            .0;
+        if output_pair.x_only_public_key().0.serialize()
+            != pk.to_output_pk(merkle_root).0.to_byte_array()
+        {
+            return None;
+        }
+        Some(output_pair.sign_schnorr(message.as_ref()))
+    }
+
+    fn sign_bip340_script_path(
+        &self,
+        message: TapSighash,
+        pk: XOnlyPk,
+        origin: Option<&KeyOrigin>,
+    ) -> Option<bip340::Signature> {
+        let sk = self.derive_subkey(origin)?;
+        if sk.to_xonly_pk() != pk {
+            return None;
+        }
+        Some(sk.to_keypair_bip340().sign_schnorr(message.as_ref()))
+    }
+
+    fn should_sign_script_path(
+        &self,
+        _index: usize,
+        _merkle_path: &TapMerklePath,
+        _leaf: TapLeafHash,
+    ) -> bool {
+        true
+    }
+
+    fn should_sign_key_path(&self, _index: usize) -> bool { true }
+}
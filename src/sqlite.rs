@@ -0,0 +1,226 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use amplify::confinement::{Confined, U32 as U32MAX};
+use nonasync::persistence::{PersistenceError, PersistenceProvider};
+use rgbstd::persistence::{MemIndex, MemStash, MemState};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use strict_types::encoding::{StrictDeserialize, StrictSerialize};
+
+use crate::SqliteStoreError;
+
+/// Database schema migrations, where the migration at index `n` upgrades the
+/// schema from version `n` to version `n + 1`.
+const MIGRATIONS: &[&str] = &["CREATE TABLE stock (
+        component TEXT PRIMARY KEY NOT NULL,
+        data BLOB NOT NULL,
+        revision INTEGER NOT NULL
+    );"];
+
+/// Most recent version of the stock database schema.
+pub const SQLITE_SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Stock component persisted as a separate record in the database.
+trait StockComponent: StrictSerialize + StrictDeserialize {
+    const NAME: &'static str;
+}
+
+impl StockComponent for MemStash {
+    const NAME: &'static str = "stash";
+}
+
+impl StockComponent for MemState {
+    const NAME: &'static str = "state";
+}
+
+impl StockComponent for MemIndex {
+    const NAME: &'static str = "index";
+}
+
+/// SQLite-backed persistence for the stash, state and index providers of the
+/// stock.
+///
+/// The store doesn't implement `StashProvider`, `StateProvider` and
+/// `IndexProvider` over database tables: the stock keeps working with the
+/// in-memory providers, and the store is a [`PersistenceProvider`] for them.
+/// Thus the whole stock is still loaded into memory, and each save rewrites
+/// the complete data of the provider.
+///
+/// Each provider is kept as a single record, which is replaced atomically,
+/// such that a crash during the write never leaves the stock corrupted. The
+/// database runs in WAL mode, allowing any number of readers (including ones
+/// opened with [`SqliteStore::open_read_only`] from other processes) to work
+/// concurrently with a single writer.
+///
+/// To keep the providers consistent with each other, save the stock within
+/// [`SqliteStore::transaction`]; in this case either all three providers are
+/// written or none of them.
+#[derive(Clone, Debug)]
+pub struct SqliteStore {
+    connection: Arc<Mutex<Connection>>,
+    read_only: bool,
+}
+
+impl SqliteStore {
+    /// Opens stock database at the given path, creating it if it doesn't
+    /// exist, and upgrades its schema to the most recent version.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SqliteStoreError> {
+        let mut connection = Connection::open(path)?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "FULL")?;
+        Self::migrate(&mut connection)?;
+        Ok(Self::with(connection, false))
+    }
+
+    /// Opens existing stock database for reading only, which may be done while
+    /// the database is used by a writer.
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<Self, SqliteStoreError> {
+        let connection = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        let version = Self::schema_version(&connection)?;
+        if version > SQLITE_SCHEMA_VERSION {
+            return Err(SqliteStoreError::UnsupportedSchema(version, SQLITE_SCHEMA_VERSION));
+        }
+        if version < SQLITE_SCHEMA_VERSION {
+            return Err(SqliteStoreError::OutdatedSchema(version, SQLITE_SCHEMA_VERSION));
+        }
+        Ok(Self::with(connection, true))
+    }
+
+    /// Creates in-memory database, mostly useful for testing.
+    pub fn in_memory() -> Result<Self, SqliteStoreError> {
+        let mut connection = Connection::open_in_memory()?;
+        Self::migrate(&mut connection)?;
+        Ok(Self::with(connection, false))
+    }
+
+    fn with(connection: Connection, read_only: bool) -> Self {
+        Self {
+            connection: Arc::new(Mutex::new(connection)),
+            read_only,
+        }
+    }
+
+    fn schema_version(connection: &Connection) -> Result<u32, SqliteStoreError> {
+        Ok(connection.pragma_query_value(None, "user_version", |row| row.get(0))?)
+    }
+
+    fn migrate(connection: &mut Connection) -> Result<(), SqliteStoreError> {
+        let tx = connection.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        let version = Self::schema_version(&tx)?;
+        if version > SQLITE_SCHEMA_VERSION {
+            return Err(SqliteStoreError::UnsupportedSchema(version, SQLITE_SCHEMA_VERSION));
+        }
+        for migration in &MIGRATIONS[version as usize..] {
+            tx.execute_batch(migration)?;
+        }
+        tx.pragma_update(None, "user_version", SQLITE_SCHEMA_VERSION)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Checks whether the database is opened in read-only mode.
+    pub fn is_read_only(&self) -> bool { self.read_only }
+
+    /// Checks whether the database already contains stock data.
+    pub fn is_initialized(&self) -> Result<bool, SqliteStoreError> {
+        let count: u32 = self
+            .lock()
+            .query_row("SELECT COUNT(*) FROM stock", [], |row| row.get(0))?;
+        Ok(count > 0)
+    }
+
+    /// Runs `f` within a single database transaction, such that all writes
+    /// made by it are either committed together or, if `f` fails, discarded.
+    ///
+    /// The transaction is shared by all clones of the store, thus the store
+    /// must not be used from other threads while `f` is running.
+    pub fn transaction<T, E>(&self, f: impl FnOnce() -> Result<T, E>) -> Result<T, E>
+    where E: From<SqliteStoreError> {
+        if self.read_only {
+            return Err(SqliteStoreError::ReadOnly.into());
+        }
+        self.lock()
+            .execute_batch("BEGIN IMMEDIATE")
+            .map_err(SqliteStoreError::from)?;
+        let res = f();
+        let end = if res.is_ok() { "COMMIT" } else { "ROLLBACK" };
+        self.lock()
+            .execute_batch(end)
+            .map_err(SqliteStoreError::from)?;
+        res
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .expect("stock database connection mutex is poisoned")
+    }
+
+    fn load_component<T: StockComponent>(&self) -> Result<T, SqliteStoreError> {
+        let data: Option<Vec<u8>> = self
+            .lock()
+            .query_row("SELECT data FROM stock WHERE component = ?1", [T::NAME], |row| row.get(0))
+            .optional()?;
+        let data = data.ok_or(SqliteStoreError::Missing(T::NAME))?;
+        let data =
+            Confined::<Vec<u8>, 0, U32MAX>::try_from(data).expect("SQLite blobs can't exceed 4GB");
+        T::from_strict_serialized(data).map_err(|e| SqliteStoreError::Decode(T::NAME, e))
+    }
+
+    fn store_component<T: StockComponent>(&self, object: &T) -> Result<(), SqliteStoreError> {
+        if self.read_only {
+            return Err(SqliteStoreError::ReadOnly);
+        }
+        let data = object
+            .to_strict_serialized::<U32MAX>()
+            .map_err(|e| SqliteStoreError::Encode(T::NAME, e))?;
+        // A single statement is atomic on its own, and within
+        // `Self::transaction` it becomes a part of the outer transaction.
+        self.lock().execute(
+            "INSERT INTO stock (component, data, revision) VALUES (?1, ?2, 1)
+            ON CONFLICT (component) DO UPDATE
+            SET data = excluded.data, revision = revision + 1",
+            params![T::NAME, data.as_slice()],
+        )?;
+        Ok(())
+    }
+}
+
+impl<T: StockComponent> PersistenceProvider<T> for SqliteStore {
+    fn load(&self) -> Result<T, PersistenceError> {
+        self.load_component().map_err(PersistenceError::with)
+    }
+
+    fn store(&self, object: &T) -> Result<(), PersistenceError> {
+        self.store_component(object).map_err(PersistenceError::with)
+    }
+}
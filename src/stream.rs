@@ -0,0 +1,256 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::BufReader;
+use std::io::{self, Read, Write};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use amplify::confinement::{LargeOrdSet, U32 as FILE_MAX_LEN};
+use rgbstd::containers::{Consignment, Contract, FileContent, LoadError, Transfer, WitnessBundle};
+use strict_types::encoding::{
+    DecodeError, StreamReader, StreamWriter, StrictDecode, StrictDumb, StrictEncode, StrictReader,
+    StrictWriter,
+};
+
+const RGB_PREFIX: [u8; 4] = *b"RGB\x00";
+
+const fn magic<const TRANSFER: bool>() -> [u8; 3] {
+    if TRANSFER {
+        Transfer::MAGIC
+    } else {
+        Contract::MAGIC
+    }
+}
+
+/// Reader of a consignment file, decoding witness bundles one by one, such
+/// that the memory used doesn't depend on the number of bundles in the
+/// consignment.
+///
+/// The data preceding the bundles (version, terminals, genesis and
+/// extensions) are decoded on construction and available via
+/// [`ConsignmentReader::header`]; the data following the bundles are decoded
+/// by [`ConsignmentReader::finish`].
+pub struct ConsignmentReader<const TRANSFER: bool, R: Read> {
+    reader: StrictReader<StreamReader<R>>,
+    consignment: Consignment<TRANSFER>,
+    remaining: u32,
+    last: Option<WitnessBundle>,
+}
+
+pub type TransferReader<R> = ConsignmentReader<true, R>;
+pub type ContractReader<R> = ConsignmentReader<false, R>;
+
+impl<const TRANSFER: bool, R: Read> ConsignmentReader<TRANSFER, R> {
+    /// Checks file magic bytes and reads consignment data up to the witness
+    /// bundles.
+    pub fn new(mut data: R) -> Result<Self, LoadError> {
+        let mut rgb = [0u8; 4];
+        let mut magic = [0u8; 3];
+        data.read_exact(&mut rgb)?;
+        data.read_exact(&mut magic)?;
+        if rgb != RGB_PREFIX || magic != self::magic::<TRANSFER>() {
+            return Err(LoadError::InvalidMagic);
+        }
+
+        let mut reader = StrictReader::with(StreamReader::new::<FILE_MAX_LEN>(data));
+        let mut consignment = Consignment::<TRANSFER>::strict_dumb();
+        consignment.version = StrictDecode::strict_decode(&mut reader)?;
+        consignment.transfer = StrictDecode::strict_decode(&mut reader)?;
+        consignment.terminals = StrictDecode::strict_decode(&mut reader)?;
+        consignment.genesis = StrictDecode::strict_decode(&mut reader)?;
+        consignment.extensions = StrictDecode::strict_decode(&mut reader)?;
+        let remaining = u32::strict_decode(&mut reader)?;
+
+        Ok(Self {
+            reader,
+            consignment,
+            remaining,
+            last: None,
+        })
+    }
+
+    /// Returns consignment with the data read so far. Its witness bundles are
+    /// always empty.
+    pub fn header(&self) -> &Consignment<TRANSFER> { &self.consignment }
+
+    /// Returns number of witness bundles which are not read yet.
+    pub fn remaining_bundles(&self) -> u32 { self.remaining }
+
+    /// Reads next witness bundle, returning `None` once all bundles are read.
+    pub fn next_bundle(&mut self) -> Result<Option<WitnessBundle>, LoadError> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        let bundle = WitnessBundle::strict_decode(&mut self.reader)?;
+        match &self.last {
+            Some(last) if last > &bundle => return Err(DecodeError::BrokenSetOrder.into()),
+            Some(last) if last == &bundle => return Err(DecodeError::RepeatedSetValue.into()),
+            _ => {}
+        }
+        self.remaining -= 1;
+        self.last = Some(bundle.clone());
+        Ok(Some(bundle))
+    }
+
+    /// Reads the rest of the consignment, skipping witness bundles which were
+    /// not read yet.
+    ///
+    /// The returned consignment doesn't contain witness bundles.
+    pub fn finish(mut self) -> Result<Consignment<TRANSFER>, LoadError> {
+        while self.next_bundle()?.is_some() {}
+
+        let reader = &mut self.reader;
+        let mut consignment = self.consignment;
+        consignment.schema = StrictDecode::strict_decode(reader)?;
+        consignment.ifaces = StrictDecode::strict_decode(reader)?;
+        consignment.supplements = StrictDecode::strict_decode(reader)?;
+        consignment.types = StrictDecode::strict_decode(reader)?;
+        consignment.scripts = StrictDecode::strict_decode(reader)?;
+        consignment.attachments = StrictDecode::strict_decode(reader)?;
+        consignment.signatures = StrictDecode::strict_decode(reader)?;
+        Ok(consignment)
+    }
+
+    /// Reads the rest of the consignment, keeping all witness bundles in it.
+    ///
+    /// Bundles are decoded one by one, checking their order as they are read.
+    pub fn read_all(mut self) -> Result<Consignment<TRANSFER>, LoadError> {
+        let mut bundles = BTreeSet::new();
+        while let Some(bundle) = self.next_bundle()? {
+            bundles.insert(bundle);
+        }
+        let mut consignment = self.finish()?;
+        consignment.bundles = LargeOrdSet::from_checked(bundles);
+        Ok(consignment)
+    }
+}
+
+#[cfg(feature = "fs")]
+impl<const TRANSFER: bool> ConsignmentReader<TRANSFER, BufReader<File>> {
+    /// Opens consignment file, reading consignment data up to the witness
+    /// bundles.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let file = File::open(path)?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<const TRANSFER: bool, R: Read> Iterator for ConsignmentReader<TRANSFER, R> {
+    type Item = Result<WitnessBundle, LoadError>;
+
+    fn next(&mut self) -> Option<Self::Item> { self.next_bundle().transpose() }
+}
+
+/// Writer of a consignment file, encoding witness bundles one by one, such
+/// that they don't need to be kept in memory all together.
+///
+/// Produces the same data as [`FileContent::save`] for a consignment with the
+/// same bundles.
+pub struct ConsignmentWriter<const TRANSFER: bool, W: Write> {
+    writer: Option<StrictWriter<StreamWriter<W>>>,
+    consignment: Consignment<TRANSFER>,
+    remaining: u32,
+    last: Option<WitnessBundle>,
+}
+
+pub type TransferWriter<W> = ConsignmentWriter<true, W>;
+pub type ContractWriter<W> = ConsignmentWriter<false, W>;
+
+impl<const TRANSFER: bool, W: Write> ConsignmentWriter<TRANSFER, W> {
+    /// Writes consignment data up to the witness bundles.
+    ///
+    /// Witness bundles present in the `consignment` are ignored; instead,
+    /// exactly `bundle_count` bundles must be provided with
+    /// [`ConsignmentWriter::write_bundle`] before calling
+    /// [`ConsignmentWriter::finish`].
+    pub fn new(
+        mut writer: W,
+        consignment: Consignment<TRANSFER>,
+        bundle_count: u32,
+    ) -> io::Result<Self> {
+        writer.write_all(&RGB_PREFIX)?;
+        writer.write_all(&magic::<TRANSFER>())?;
+
+        let mut writer = StrictWriter::with(StreamWriter::new::<FILE_MAX_LEN>(writer));
+        writer = consignment.version.strict_encode(writer)?;
+        writer = consignment.transfer.strict_encode(writer)?;
+        writer = consignment.terminals.strict_encode(writer)?;
+        writer = consignment.genesis.strict_encode(writer)?;
+        writer = consignment.extensions.strict_encode(writer)?;
+        writer = bundle_count.strict_encode(writer)?;
+
+        Ok(Self {
+            writer: Some(writer),
+            consignment,
+            remaining: bundle_count,
+            last: None,
+        })
+    }
+
+    /// Writes next witness bundle. Bundles must be provided in ascending
+    /// order, without repetitions.
+    pub fn write_bundle(&mut self, bundle: &WitnessBundle) -> io::Result<()> {
+        if self.remaining == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "more witness bundles than declared are written into the consignment",
+            ));
+        }
+        if matches!(&self.last, Some(last) if last >= bundle) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "witness bundles must be written into the consignment in ascending order",
+            ));
+        }
+        let writer = self.writer.take().expect("writer is always present");
+        self.writer = Some(bundle.strict_encode(writer)?);
+        self.remaining -= 1;
+        self.last = Some(bundle.clone());
+        Ok(())
+    }
+
+    /// Writes the rest of the consignment and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.remaining > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} declared witness bundles are not written", self.remaining),
+            ));
+        }
+        let consignment = &self.consignment;
+        let mut writer = self.writer.take().expect("writer is always present");
+        writer = consignment.schema.strict_encode(writer)?;
+        writer = consignment.ifaces.strict_encode(writer)?;
+        writer = consignment.supplements.strict_encode(writer)?;
+        writer = consignment.types.strict_encode(writer)?;
+        writer = consignment.scripts.strict_encode(writer)?;
+        writer = consignment.attachments.strict_encode(writer)?;
+        writer = consignment.signatures.strict_encode(writer)?;
+        let mut writer = writer.unbox().unconfine();
+        writer.flush()?;
+        Ok(writer)
+    }
+}
@@ -0,0 +1,339 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Operations changing the supply of a contract: secondary issuance and burn.
+//!
+//! Contracts supporting these operations must implement an interface which
+//! provides state transitions named [`OPERATION_ISSUE`] and [`OPERATION_BURN`].
+//! The transitions are composed from the state owned by the wallet and are
+//! anchored in a witness transaction in the same way as normal transfers.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use amplify::confinement::Confined;
+use bp::seals::txout::CloseMethod;
+use bp::{Outpoint, Vout};
+use rgbstd::containers::{Batch, BuilderSeal, TransitionDichotomy, TransitionInfo};
+use rgbstd::interface::{IfaceImpl, TransitionIface};
+use rgbstd::invoice::Amount;
+use rgbstd::persistence::{IndexProvider, PersistedState, StashProvider, StateProvider, Stock};
+use rgbstd::{
+    AssetTag, AssignmentType, BlindingFactor, ContractId, GraphSeal, Opout, XChain, XOutputSeal,
+};
+use strict_types::{FieldName, TypeName};
+
+use crate::CompositionError;
+
+/// Name of the interface operation performing secondary issuance.
+pub const OPERATION_ISSUE: &str = "issue";
+/// Name of the interface operation burning the contract state.
+pub const OPERATION_BURN: &str = "burn";
+/// Name of the global state keeping the amount issued by the operation.
+pub const GLOBAL_ISSUED_SUPPLY: &str = "issuedSupply";
+/// Name of the global state keeping the amount burned by the operation.
+pub const GLOBAL_BURNED_SUPPLY: &str = "burnedSupply";
+
+/// Operation changing the supply of a contract.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum SupplyOperation {
+    /// Secondary issuance of the amount, spending the issuance rights owned by
+    /// the wallet. The issued amount is assigned to the provided outpoint or,
+    /// if no outpoint is given, to the change output of the witness
+    /// transaction.
+    Issue {
+        contract_id: ContractId,
+        amount: Amount,
+        seal: Option<Outpoint>,
+    },
+
+    /// Burn of the listed allocations owned by the wallet.
+    Burn {
+        contract_id: ContractId,
+        allocations: BTreeSet<Opout>,
+    },
+}
+
+impl SupplyOperation {
+    pub fn issue(contract_id: ContractId, amount: Amount, seal: Option<Outpoint>) -> Self {
+        SupplyOperation::Issue {
+            contract_id,
+            amount,
+            seal,
+        }
+    }
+
+    pub fn burn(contract_id: ContractId, allocations: impl IntoIterator<Item = Opout>) -> Self {
+        SupplyOperation::Burn {
+            contract_id,
+            allocations: allocations.into_iter().collect(),
+        }
+    }
+
+    pub fn contract_id(&self) -> ContractId {
+        match self {
+            SupplyOperation::Issue { contract_id, .. }
+            | SupplyOperation::Burn { contract_id, .. } => *contract_id,
+        }
+    }
+
+    /// Name of the interface operation performing the supply change.
+    pub fn operation_name(&self) -> &'static str {
+        match self {
+            SupplyOperation::Issue { .. } => OPERATION_ISSUE,
+            SupplyOperation::Burn { .. } => OPERATION_BURN,
+        }
+    }
+
+    /// Finds the contract interface providing the operation, returning the
+    /// interface name together with its implementation by the contract schema
+    /// and the operation definition.
+    fn interface<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+    ) -> Result<(TypeName, IfaceImpl, TransitionIface), CompositionError> {
+        let contract_id = self.contract_id();
+        let name = FieldName::from(self.operation_name());
+        let info = stock
+            .contract_info(contract_id)
+            .map_err(|e| e.to_string())?;
+        let schema = stock.schema(info.schema_id).map_err(|e| e.to_string())?;
+        for (iface_name, iimpl) in &schema.iimpls {
+            if iimpl.transition_type(&name).is_none() {
+                continue;
+            }
+            let iface = stock.iface(iface_name.clone()).map_err(|e| e.to_string())?;
+            if let Some(transition) = iface.transitions.get(&name) {
+                return Ok((iface_name.clone(), iimpl.clone(), transition.clone()));
+            }
+        }
+        Err(CompositionError::OperationUnsupported(contract_id, name.to_string()))
+    }
+
+    /// Selects outputs owned by the wallet which must be spent by the
+    /// operation.
+    ///
+    /// For the secondary issuance these are all outputs holding the state
+    /// required by the operation (like inflation allowance); for the burn these
+    /// are outputs holding the burned allocations and the other state required
+    /// by the operation (like burn rights).
+    pub fn select_outputs<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+        owned: impl IntoIterator<Item = Outpoint>,
+    ) -> Result<BTreeSet<XOutputSeal>, CompositionError> {
+        let (_, iimpl, transition) = self.interface(stock)?;
+        let mut input_types = transition
+            .inputs
+            .keys()
+            .filter_map(|name| iimpl.assignments_type(name))
+            .collect::<BTreeSet<_>>();
+
+        let state = stock
+            .contract_assignments_for(self.contract_id(), owned.into_iter().map(XChain::Bitcoin))
+            .map_err(|e| e.to_string())?;
+
+        let mut outputs = BTreeSet::new();
+        if let SupplyOperation::Burn { allocations, .. } = self {
+            for opout in allocations {
+                let seal = state
+                    .iter()
+                    .find(|(_, assigns)| assigns.contains_key(opout))
+                    .map(|(seal, _)| *seal)
+                    .ok_or(CompositionError::UnknownAllocation(*opout))?;
+                outputs.insert(seal);
+                input_types.remove(&opout.ty);
+            }
+        }
+        let required = state
+            .iter()
+            .filter(|(_, assigns)| assigns.keys().any(|opout| input_types.contains(&opout.ty)))
+            .map(|(seal, _)| *seal)
+            .collect::<BTreeSet<_>>();
+        if !input_types.is_empty() && required.is_empty() {
+            return Err(CompositionError::InsufficientState);
+        }
+        outputs.extend(required);
+        Ok(outputs)
+    }
+
+    /// Composes a batch of state transitions performing the operation, which
+    /// spends all contract state assigned to `prev_outputs`.
+    ///
+    /// The state which is not consumed by the operation, as well as the state
+    /// of other contracts assigned to the same outputs, is re-assigned to the
+    /// `change` output of the witness transaction.
+    pub fn compose<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+        prev_outputs: &BTreeSet<XOutputSeal>,
+        method: CloseMethod,
+        change: Option<Vout>,
+    ) -> Result<Batch, CompositionError> {
+        let contract_id = self.contract_id();
+        let operation = self.operation_name();
+        let (iface, iimpl, transition) = self.interface(stock)?;
+        let input_types = transition
+            .inputs
+            .keys()
+            .filter_map(|name| iimpl.assignments_type(name))
+            .collect::<BTreeSet<_>>();
+        let change_seal = || -> Result<BuilderSeal<GraphSeal>, CompositionError> {
+            let vout = change.ok_or(CompositionError::NoChange)?;
+            Ok(BuilderSeal::Revealed(XChain::Bitcoin(GraphSeal::new_random_vout(method, vout))))
+        };
+        let preserve = |opout: Opout| -> Result<(), CompositionError> {
+            match iimpl.assignment_name(opout.ty) {
+                Some(name) if transition.assignments.contains_key(name) => Ok(()),
+                _ => Err(CompositionError::UnpreservedState(opout, operation.to_owned())),
+            }
+        };
+
+        let mut builder = stock
+            .transition_builder(contract_id, iface, Some(operation))
+            .map_err(|e| e.to_string())?;
+        let state = stock
+            .contract_assignments_for(contract_id, prev_outputs.iter().copied())
+            .map_err(|e| e.to_string())?;
+        let mut fungible = BTreeMap::<AssignmentType, (Amount, AssetTag, Opout)>::new();
+        let mut burned = Amount::ZERO;
+        for (opout, state) in state.into_values().flatten() {
+            builder = builder.add_input(opout, state.clone())?;
+            let is_burned = matches!(self, SupplyOperation::Burn { allocations, .. } if allocations.contains(&opout));
+            match state {
+                PersistedState::Amount(value, _, _) if is_burned => burned += value,
+                _ if is_burned => {}
+                PersistedState::Amount(value, _, tag) => {
+                    fungible
+                        .entry(opout.ty)
+                        .or_insert((Amount::ZERO, tag, opout))
+                        .0 += value
+                }
+                state => {
+                    preserve(opout)?;
+                    builder = builder.add_owned_state_raw(opout.ty, change_seal()?, state)?;
+                }
+            }
+        }
+
+        match self {
+            SupplyOperation::Issue { amount, seal, .. } => {
+                // The issued amount is taken out of the fungible state required by the
+                // operation, like inflation allowance
+                for ty in &input_types {
+                    if let Some((sum, _, _)) = fungible.get_mut(ty) {
+                        if *sum < *amount {
+                            return Err(CompositionError::InsufficientState);
+                        }
+                        *sum -= *amount;
+                    }
+                }
+                let seal = match seal {
+                    Some(outpoint) => BuilderSeal::Revealed(XChain::Bitcoin(
+                        GraphSeal::new_random(method, outpoint.txid, outpoint.vout),
+                    )),
+                    None => change_seal()?,
+                };
+                builder = builder.add_fungible_default_state(seal, amount.value())?;
+                if transition
+                    .globals
+                    .contains_key(&FieldName::from(GLOBAL_ISSUED_SUPPLY))
+                {
+                    builder = builder.add_global_state(GLOBAL_ISSUED_SUPPLY, *amount)?;
+                }
+            }
+            SupplyOperation::Burn { .. } => {
+                if transition
+                    .globals
+                    .contains_key(&FieldName::from(GLOBAL_BURNED_SUPPLY))
+                {
+                    builder = builder.add_global_state(GLOBAL_BURNED_SUPPLY, burned)?;
+                }
+            }
+        }
+
+        for (ty, (sum, tag, opout)) in fungible {
+            if sum == Amount::ZERO {
+                continue;
+            }
+            preserve(opout)?;
+            let state = PersistedState::Amount(sum, BlindingFactor::random(), tag);
+            builder = builder.add_owned_state_raw(ty, change_seal()?, state)?;
+        }
+
+        let outputs = prev_outputs.iter().copied().collect::<Vec<_>>();
+        let main = TransitionInfo::new(builder.complete_transition()?, outputs)
+            .map_err(|e| e.to_string())?;
+
+        let blanks = blank_transitions(stock, Some(contract_id), prev_outputs, change_seal)?;
+        Ok(Batch {
+            main: TransitionDichotomy::single(main),
+            blanks: Confined::try_from(blanks).map_err(|e| e.to_string())?,
+        })
+    }
+}
+
+/// Composes blank transitions moving the state of all contracts (other than
+/// `except`, if provided) which is assigned to the spent `prev_outputs` to the
+/// change seal.
+pub(crate) fn blank_transitions<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &Stock<S, H, P>,
+    except: Option<ContractId>,
+    prev_outputs: &BTreeSet<XOutputSeal>,
+    change_seal: impl Fn() -> Result<BuilderSeal<GraphSeal>, CompositionError>,
+) -> Result<Vec<TransitionDichotomy>, CompositionError> {
+    // Other contracts assigning state to the spent outputs get blank
+    // transitions moving their state to the change output
+    let contracts = stock
+        .contracts_assigning(prev_outputs.iter().copied())
+        .map_err(|e| e.to_string())?
+        .filter(|id| Some(*id) != except)
+        .collect::<BTreeSet<_>>();
+    let mut blanks = Vec::with_capacity(contracts.len());
+    for id in contracts {
+        let info = stock.contract_info(id).map_err(|e| e.to_string())?;
+        let schema = stock.schema(info.schema_id).map_err(|e| e.to_string())?;
+        let Some(iface) = schema.iimpls.keys().next() else {
+            continue;
+        };
+        let mut builder = stock
+            .blank_builder(id, iface.clone())
+            .map_err(|e| e.to_string())?;
+        let mut outputs = Vec::new();
+        for (output, assigns) in stock
+            .contract_assignments_for(id, prev_outputs.iter().copied())
+            .map_err(|e| e.to_string())?
+        {
+            outputs.push(output);
+            for (opout, state) in assigns {
+                builder = builder
+                    .add_input(opout, state.clone())?
+                    .add_owned_state_raw(opout.ty, change_seal()?, state)?;
+            }
+        }
+        if !builder.has_inputs() {
+            continue;
+        }
+        let info = TransitionInfo::new(builder.complete_transition()?, outputs)
+            .map_err(|e| e.to_string())?;
+        blanks.push(TransitionDichotomy::single(info));
+    }
+    Ok(blanks)
+}
@@ -0,0 +1,502 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+use amplify::confinement::Confined;
+use bp::seals::txout::TxPtr;
+use bp::{Sats, Vout};
+use bpstd::seals::SecretSeal;
+use bpstd::{psbt, Derive};
+use psrgbt::{
+    KeyMap, PropKey, ProprietaryKeyRgb, Psbt, PsbtConstructor, PsbtMeta, RgbExt, RgbPsbt,
+    PSBT_IN_RGB_CONSUMED_BY, PSBT_RGB_PREFIX,
+};
+use rgbstd::containers::Fascia;
+use rgbstd::invoice::{Beneficiary, InvoiceState};
+use rgbstd::{AssignmentType, ContractId, GraphSeal, TypedAssigns, Vin, XChain};
+
+use crate::invoice::RgbInvoice;
+use crate::SwapError;
+
+/// Proposal for an atomic swap of RGB assets under two different contracts,
+/// which are exchanged within a single bitcoin transaction.
+///
+/// The proposal is created by the party which pays `offer` invoice, issued by
+/// the counterparty, in exchange for the counterparty paying the `request`
+/// invoice. Both invoices are kept inside the proposal PSBT using global
+/// proprietary keys. The counterparty accepts the proposal by adding its own
+/// inputs, outputs and state transitions to the PSBT and committing to the
+/// RGB data of both parties in a single anchor; after that the proposing
+/// party finalizes the swap by consuming the same anchor.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SwapProposal {
+    psbt: Psbt,
+    offer: RgbInvoice,
+    request: RgbInvoice,
+}
+
+#[allow(clippy::result_large_err)]
+impl SwapProposal {
+    /// Constructs new swap proposal from a PSBT, which must already contain
+    /// the data paying the `offer` invoice.
+    ///
+    /// Invoices already kept in the PSBT are replaced with the provided ones.
+    pub fn new(mut psbt: Psbt, offer: RgbInvoice, request: RgbInvoice) -> Self {
+        set_proprietary(&mut psbt, PropKey::rgb_swap_offer(), offer.to_string().into_bytes());
+        set_proprietary(&mut psbt, PropKey::rgb_swap_request(), request.to_string().into_bytes());
+        SwapProposal {
+            psbt,
+            offer,
+            request,
+        }
+    }
+
+    /// Parses swap proposal out of a PSBT.
+    pub fn from_psbt(psbt: Psbt) -> Result<Self, SwapError> {
+        let offer = parse_invoice(&psbt, PropKey::rgb_swap_offer(), "offer")?;
+        let request = parse_invoice(&psbt, PropKey::rgb_swap_request(), "request")?;
+        Ok(SwapProposal {
+            psbt,
+            offer,
+            request,
+        })
+    }
+
+    /// Invoice paid by the party proposing the swap.
+    pub fn offer(&self) -> &RgbInvoice { &self.offer }
+
+    /// Invoice which has to be paid by the party accepting the swap.
+    pub fn request(&self) -> &RgbInvoice { &self.request }
+
+    pub fn psbt(&self) -> &Psbt { &self.psbt }
+
+    pub(crate) fn psbt_mut(&mut self) -> &mut Psbt { &mut self.psbt }
+
+    pub fn into_psbt(self) -> Psbt { self.psbt }
+
+    /// Detects whether the proposal was accepted by the counterparty, i.e. RGB
+    /// data of both parties were committed to the transaction.
+    pub fn is_accepted(&self) -> bool { self.psbt.rgb_extract().is_ok() }
+
+    /// Checks that the proposal contains state transitions for both legs of
+    /// the swap.
+    pub fn check_legs(&self) -> Result<(), SwapError> {
+        let contracts = self
+            .psbt
+            .rgb_contract_ids()
+            .map_err(|_| SwapError::InvalidRgbData)?;
+        self.check_contracts(&contracts)
+    }
+
+    fn check_contracts(&self, contracts: &BTreeSet<ContractId>) -> Result<(), SwapError> {
+        for invoice in [&self.offer, &self.request] {
+            let contract_id = invoice.contract.ok_or(SwapError::NoContract)?;
+            if !contracts.contains(&contract_id) {
+                return Err(SwapError::MissingLeg(contract_id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Extracts fascia produced by the counterparty which has accepted the
+    /// swap, ensuring that both legs of the swap are committed in the same
+    /// anchor of the swap transaction.
+    pub fn fascia(&self) -> Result<Fascia, SwapError> {
+        let fascia = committed_fascia(&self.psbt)?;
+        self.check_contracts(&fascia.bundles.keys().copied().collect())?;
+        Ok(fascia)
+    }
+
+    /// Checks that the state transitions of the counterparty pay the
+    /// `request` invoice, i.e. assign at least the requested amount of the
+    /// requested contract state to the invoice beneficiary.
+    ///
+    /// If the `assignment_type` is not known (for instance since the contract
+    /// is not yet known to the stock) state of any type is accounted.
+    pub(crate) fn check_request(
+        &self,
+        fascia: &Fascia,
+        assignment_type: Option<AssignmentType>,
+    ) -> Result<(), SwapError> {
+        let contract_id = self.request.contract.ok_or(SwapError::NoContract)?;
+        let dichotomy = fascia
+            .bundles
+            .get(&contract_id)
+            .ok_or(SwapError::MissingLeg(contract_id))?;
+
+        let beneficiary_vout = match self.request.beneficiary.into_inner() {
+            Beneficiary::WitnessVout(pay2vout) => {
+                let script = pay2vout.address.script_pubkey();
+                let vout = self
+                    .psbt
+                    .outputs()
+                    .find(|output| output.script == script)
+                    .map(psbt::Output::vout)
+                    .ok_or(SwapError::RequestMismatch)?;
+                Some(vout)
+            }
+            Beneficiary::BlindedSeal(_) => None,
+        };
+        let pays = |secret: XChain<SecretSeal>, seal: Option<XChain<GraphSeal>>| match self
+            .request
+            .beneficiary
+            .into_inner()
+        {
+            Beneficiary::BlindedSeal(beneficiary) => *secret.as_reduced_unsafe() == beneficiary,
+            Beneficiary::WitnessVout(_) => seal.is_some_and(|seal| {
+                let seal = seal.as_reduced_unsafe();
+                seal.txid == TxPtr::WitnessTx && Some(seal.vout) == beneficiary_vout
+            }),
+        };
+
+        let mut assigned = false;
+        let mut amount = 0u64;
+        let transitions = dichotomy
+            .iter()
+            .flat_map(|bundle| bundle.known_transitions.values());
+        for transition in transitions {
+            for (ty, assigns) in transition.assignments.iter() {
+                if assignment_type.is_some_and(|t| t != *ty) {
+                    continue;
+                }
+                match assigns {
+                    TypedAssigns::Declarative(list) => {
+                        assigned |= list
+                            .iter()
+                            .any(|a| pays(a.to_confidential_seal(), a.revealed_seal()));
+                    }
+                    TypedAssigns::Fungible(list) => {
+                        for a in list {
+                            if !pays(a.to_confidential_seal(), a.revealed_seal()) {
+                                continue;
+                            }
+                            assigned = true;
+                            if let Some(state) = a.as_revealed_state() {
+                                amount = amount.saturating_add(state.value.as_u64());
+                            }
+                        }
+                    }
+                    TypedAssigns::Structured(list) => {
+                        assigned |= list
+                            .iter()
+                            .any(|a| pays(a.to_confidential_seal(), a.revealed_seal()));
+                    }
+                    TypedAssigns::Attachment(list) => {
+                        assigned |= list
+                            .iter()
+                            .any(|a| pays(a.to_confidential_seal(), a.revealed_seal()));
+                    }
+                }
+            }
+        }
+
+        let paid = match &self.request.owned_state {
+            InvoiceState::Amount(requested) => assigned && amount >= requested.value(),
+            _ => assigned,
+        };
+        if !paid {
+            return Err(SwapError::RequestMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Party of an asset-for-sats swap.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display(lowercase)]
+pub enum SwapParty {
+    /// Party selling RGB assets for sats.
+    Seller,
+
+    /// Party buying RGB assets for sats.
+    Buyer,
+}
+
+/// Part of a swap transaction contributed by one of the parties.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct SwapLeg {
+    /// Indexes of the transaction inputs spent by the party.
+    pub inputs: BTreeSet<usize>,
+
+    /// Output receiving sats (for the seller) or RGB state (for the buyer). It
+    /// is `None` for the buyer if the RGB state is assigned using a blinded
+    /// seal.
+    pub receive_vout: Option<Vout>,
+}
+
+/// Extension of [`PsbtMeta`] describing both legs of an asset-for-sats swap.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SwapMeta {
+    /// Change information for the part of PSBT constructed by the wallet.
+    pub psbt: PsbtMeta,
+
+    /// Part of the transaction contributed by the seller.
+    pub seller: SwapLeg,
+
+    /// Part of the transaction contributed by the buyer.
+    pub buyer: SwapLeg,
+}
+
+/// Proposal to sell RGB assets for sats, where both the asset and the sats
+/// are exchanged within a single bitcoin transaction.
+///
+/// The proposal is constructed by the seller, who pays the buyer invoice and
+/// adds an output receiving the price. The buyer funds the proposal with
+/// inputs which do not hold any RGB state and commits to the RGB data. The
+/// seller finalizes the sale by creating transfer consignment for the buyer.
+///
+/// To keep the exchange fair, the seller signs the transaction only after
+/// finalization, and the buyer signs it last, once the consignment received
+/// from the seller is validated; see [`SaleProposal::next_signer`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct SaleProposal {
+    psbt: Psbt,
+    invoice: RgbInvoice,
+    price: Sats,
+    price_vout: Vout,
+}
+
+#[allow(clippy::result_large_err)]
+impl SaleProposal {
+    /// Constructs new sale proposal from a PSBT, which must already contain
+    /// the data paying the buyer `invoice` and the output at `price_vout`
+    /// receiving the price.
+    ///
+    /// Invoice and price already kept in the PSBT are replaced with the
+    /// provided ones.
+    pub fn new(mut psbt: Psbt, invoice: RgbInvoice, price_vout: Vout, price: Sats) -> Self {
+        set_proprietary(&mut psbt, PropKey::rgb_swap_offer(), invoice.to_string().into_bytes());
+        let mut data = price_vout.to_u32().to_le_bytes().to_vec();
+        data.extend(price.0.to_le_bytes());
+        set_proprietary(&mut psbt, PropKey::rgb_swap_price(), data);
+        SaleProposal {
+            psbt,
+            invoice,
+            price,
+            price_vout,
+        }
+    }
+
+    /// Parses sale proposal out of a PSBT.
+    pub fn from_psbt(psbt: Psbt) -> Result<Self, SwapError> {
+        let invoice = parse_invoice(&psbt, PropKey::rgb_swap_offer(), "offer")?;
+        let data = psbt
+            .proprietary(&PropKey::rgb_swap_price())
+            .ok_or(SwapError::NoPrice)?;
+        if data.len() != 12 {
+            return Err(SwapError::NoPrice);
+        }
+        let mut vout = [0u8; 4];
+        let mut price = [0u8; 8];
+        vout.copy_from_slice(&data[..4]);
+        price.copy_from_slice(&data[4..]);
+        Ok(SaleProposal {
+            psbt,
+            invoice,
+            price: Sats::from_sats(u64::from_le_bytes(price)),
+            price_vout: Vout::from_u32(u32::from_le_bytes(vout)),
+        })
+    }
+
+    /// Buyer invoice paid by the seller.
+    pub fn invoice(&self) -> &RgbInvoice { &self.invoice }
+
+    /// Amount of sats paid by the buyer.
+    pub fn price(&self) -> Sats { self.price }
+
+    /// Output receiving the price.
+    pub fn price_vout(&self) -> Vout { self.price_vout }
+
+    pub fn psbt(&self) -> &Psbt { &self.psbt }
+
+    pub(crate) fn psbt_mut(&mut self) -> &mut Psbt { &mut self.psbt }
+
+    pub fn into_psbt(self) -> Psbt { self.psbt }
+
+    /// Detects whether the proposal was funded by the buyer, which has
+    /// committed to the RGB data.
+    pub fn is_funded(&self) -> bool { self.psbt.rgb_extract().is_ok() }
+
+    /// Describes the parts of the transaction contributed by the seller and
+    /// the buyer.
+    ///
+    /// The seller inputs are the ones spending RGB state; all other inputs are
+    /// the buyer ones.
+    pub fn legs(&self) -> (SwapLeg, SwapLeg) {
+        let (seller_inputs, buyer_inputs) = self
+            .psbt
+            .inputs()
+            .map(|input| {
+                let is_rgb = input.proprietary.keys().any(|key| {
+                    key.identifier == PSBT_RGB_PREFIX && key.subtype == PSBT_IN_RGB_CONSUMED_BY
+                });
+                (input.index(), is_rgb)
+            })
+            .partition::<Vec<_>, _>(|(_, is_rgb)| *is_rgb);
+        let receive_vout = match self.invoice.beneficiary.into_inner() {
+            Beneficiary::WitnessVout(pay2vout) => {
+                let script = pay2vout.address.script_pubkey();
+                self.psbt
+                    .outputs()
+                    .find(|output| output.script == script)
+                    .map(psbt::Output::vout)
+            }
+            Beneficiary::BlindedSeal(_) => None,
+        };
+        let seller = SwapLeg {
+            inputs: seller_inputs.into_iter().map(|(index, _)| index).collect(),
+            receive_vout: Some(self.price_vout),
+        };
+        let buyer = SwapLeg {
+            inputs: buyer_inputs.into_iter().map(|(index, _)| index).collect(),
+            receive_vout,
+        };
+        (seller, buyer)
+    }
+
+    pub(crate) fn meta(&self, psbt: PsbtMeta) -> SwapMeta {
+        let (seller, buyer) = self.legs();
+        SwapMeta {
+            psbt,
+            seller,
+            buyer,
+        }
+    }
+
+    /// Returns which of the parties has to sign the transaction next, or
+    /// `None` if the proposal is not yet funded or all inputs are already
+    /// signed.
+    pub fn next_signer(&self) -> Option<SwapParty> {
+        if !self.is_funded() {
+            return None;
+        }
+        let (seller, buyer) = self.legs();
+        let is_signed = |index: &usize| {
+            self.psbt.input(*index).is_some_and(|input| {
+                input.is_finalized()
+                    || input.tap_key_sig.is_some()
+                    || !input.tap_script_sig.is_empty()
+                    || !input.partial_sigs.is_empty()
+            })
+        };
+        if !seller.inputs.iter().all(is_signed) {
+            Some(SwapParty::Seller)
+        } else if !buyer.inputs.iter().all(is_signed) {
+            Some(SwapParty::Buyer)
+        } else {
+            None
+        }
+    }
+
+    /// Extracts fascia produced by the buyer which has funded the proposal,
+    /// ensuring that it matches the transaction.
+    pub fn fascia(&self) -> Result<Fascia, SwapError> {
+        let fascia = committed_fascia(&self.psbt)?;
+        let contract_id = self.invoice.contract.ok_or(SwapError::NoContract)?;
+        if !fascia.bundles.contains_key(&contract_id) {
+            return Err(SwapError::MissingLeg(contract_id));
+        }
+        Ok(fascia)
+    }
+
+    /// Checks that the transaction pays the price to an output controlled by
+    /// the seller wallet.
+    pub(crate) fn check_price(&self, wallet: &impl PsbtConstructor) -> Result<(), SwapError> {
+        let output = self
+            .psbt
+            .output(self.price_vout.to_usize())
+            .ok_or(SwapError::PriceMismatch)?;
+        let terminal = output
+            .terminal_derivation()
+            .ok_or(SwapError::PriceMismatch)?;
+        let script = wallet
+            .descriptor()
+            .derive(terminal.keychain, terminal.index)
+            .to_script_pubkey();
+        if output.script != script || output.amount != self.price {
+            return Err(SwapError::PriceMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Puts the value under the global proprietary key, replacing the existing
+/// one.
+fn set_proprietary(psbt: &mut Psbt, key: PropKey, value: Vec<u8>) {
+    psbt.remove_proprietary(&key);
+    psbt.push_proprietary(key, value)
+        .expect("the key is removed right before");
+}
+
+#[allow(clippy::result_large_err)]
+fn parse_invoice(psbt: &Psbt, key: PropKey, name: &'static str) -> Result<RgbInvoice, SwapError> {
+    let data = psbt.proprietary(&key).ok_or(SwapError::NoInvoice(name))?;
+    let s = String::from_utf8(data.to_vec()).map_err(|_| SwapError::NoInvoice(name))?;
+    Ok(RgbInvoice::from_str(&s)?)
+}
+
+#[allow(clippy::result_large_err)]
+fn committed_fascia(psbt: &Psbt) -> Result<Fascia, SwapError> {
+    let fascia = psbt.rgb_extract()?;
+    if fascia.witness_id() != XChain::Bitcoin(psbt.txid()) {
+        return Err(SwapError::WitnessMismatch);
+    }
+    Ok(fascia)
+}
+
+/// Leaves only the part of fascia which is related to the state spent by the
+/// transaction inputs belonging to the wallet, since the state transitions of
+/// the counterparty may be unknown to our stash.
+#[allow(clippy::result_large_err)]
+pub(crate) fn own_fascia(
+    psbt: &Psbt,
+    fascia: Fascia,
+    wallet: &impl PsbtConstructor,
+) -> Result<Fascia, SwapError> {
+    let vins = psbt
+        .inputs()
+        .filter(|input| wallet.utxo(input.prevout().outpoint()).is_some())
+        .map(|input| Vin::from_u32(input.index() as u32))
+        .collect::<BTreeSet<_>>();
+    let Fascia {
+        witness,
+        anchor,
+        bundles,
+    } = fascia;
+    let bundles = bundles
+        .into_iter()
+        .filter(|(_, dichotomy)| {
+            dichotomy.iter().any(|bundle| {
+                (&bundle.input_map)
+                    .into_iter()
+                    .any(|(vin, _)| vins.contains(vin))
+            })
+        })
+        .collect::<BTreeMap<_, _>>();
+    let bundles = Confined::try_from(bundles).map_err(|_| SwapError::NoOwnLeg)?;
+    Ok(Fascia {
+        witness,
+        anchor,
+        bundles,
+    })
+}
@@ -0,0 +1,376 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+
+use amplify::Wrapper;
+use rgbstd::containers::{BuilderSeal, ValidContract};
+use rgbstd::interface::ContractBuilder;
+use rgbstd::invoice::{Allocation, Amount, OwnedFraction, Precision, TokenIndex};
+use rgbstd::persistence::{IndexProvider, StashProvider, StateProvider, Stock};
+use rgbstd::schema::{Schema, SchemaId};
+use rgbstd::stl::{AssetSpec, ContractTerms, Details, Name, RicardianContract};
+use rgbstd::{GenesisSeal, Identity, OutputSeal, XChain};
+use strict_types::encoding::{StrictSerialize, TypeName};
+use strict_types::{FieldName, StrictVal};
+
+use crate::{IssueError, IssueProblem};
+
+/// Name of the interface used by [`Rgb20Issuance`].
+pub const RGB20_IFACE: &str = "RGB20Fixed";
+/// Name of the interface used by [`Rgb21Issuance`].
+pub const RGB21_IFACE: &str = "RGB21Unique";
+/// Name of the interface used by [`Rgb25Issuance`].
+pub const RGB25_IFACE: &str = "RGB25Base";
+
+/// Returns id of the first schema known to the stock which implements the
+/// interface with the provided name.
+pub fn iface_schema<S: StashProvider, H: StateProvider, P: IndexProvider>(
+    stock: &Stock<S, H, P>,
+    iface: &str,
+) -> Option<SchemaId> {
+    stock.schemata().ok()?.find_map(|info| {
+        info.implements
+            .iter()
+            .any(|iimpl| iimpl.iface_name.as_str() == iface)
+            .then_some(info.id)
+    })
+}
+
+/// Contract builder used by the issuance templates, which collects all
+/// problems with the contract state instead of stopping on the first one.
+pub struct TemplateBuilder<'schema> {
+    schema: &'schema Schema,
+    builder: ContractBuilder,
+    problems: Vec<IssueProblem>,
+}
+
+impl<'schema> TemplateBuilder<'schema> {
+    pub fn new(schema: &'schema Schema, builder: ContractBuilder) -> Self {
+        Self {
+            schema,
+            builder,
+            problems: vec![],
+        }
+    }
+
+    pub fn problem(&mut self, problem: IssueProblem) { self.problems.push(problem); }
+
+    /// Adds global state from a value of a known rust type.
+    pub fn add_global(&mut self, name: &'static str, value: impl StrictSerialize) {
+        self.add(name, |builder| builder.add_global_state(name, value));
+    }
+
+    /// Adds global state from a strict value, which is checked against the
+    /// type defined for the global state by the contract schema.
+    pub fn add_global_val(&mut self, name: &'static str, value: StrictVal) {
+        let field = FieldName::from(name);
+        let Some(sem_id) = self
+            .builder
+            .global_type(&field)
+            .and_then(|ty| self.schema.global_types.get(&ty))
+            .map(|global| global.sem_id)
+        else {
+            self.problem(IssueProblem::UnknownGlobal(name.to_owned(), s!("not checked")));
+            return;
+        };
+        let types = self.builder.type_system();
+        #[allow(deprecated)]
+        let data = types
+            .typify(value, sem_id)
+            .map_err(|e| e.to_string())
+            .and_then(|typed| {
+                types
+                    .strict_serialize_type::<{ u16::MAX as usize }>(&typed)
+                    .map_err(|e| e.to_string())
+            });
+        match data {
+            Ok(data) => self.add(name, |builder| builder.add_global_state(name, data)),
+            Err(details) => {
+                let expected = match types.get(sem_id) {
+                    Some(ty) => format!("{sem_id} ({ty})"),
+                    None => sem_id.to_string(),
+                };
+                self.problem(IssueProblem::GlobalTypeMismatch {
+                    name: name.to_owned(),
+                    expected,
+                    details,
+                })
+            }
+        }
+    }
+
+    /// Assigns fungible state to a seal.
+    pub fn add_fungible(&mut self, name: &'static str, seal: OutputSeal, amount: u64) {
+        let seal = genesis_seal(seal);
+        self.add(name, |builder| builder.add_fungible_state(name, seal, amount));
+    }
+
+    /// Assigns structured state to a seal.
+    pub fn add_data(&mut self, name: &'static str, seal: OutputSeal, data: impl StrictSerialize) {
+        let seal = genesis_seal(seal);
+        self.add(name, |builder| builder.add_data(name, seal, data));
+    }
+
+    /// Returns the contract builder, or all the problems found in the
+    /// contract state.
+    pub fn finish(self) -> Result<ContractBuilder, IssueError> {
+        if !self.problems.is_empty() {
+            return Err(IssueError::from(self.problems));
+        }
+        Ok(self.builder)
+    }
+
+    fn add(
+        &mut self,
+        name: &str,
+        f: impl FnOnce(ContractBuilder) -> Result<ContractBuilder, rgbstd::interface::BuilderError>,
+    ) {
+        match f(self.builder.clone()) {
+            Ok(builder) => self.builder = builder,
+            Err(e) => self.problem(IssueProblem::Builder(name.to_owned(), e.to_string())),
+        }
+    }
+}
+
+fn genesis_seal(seal: OutputSeal) -> BuilderSeal<GenesisSeal> {
+    let seal = GenesisSeal::new_random(seal.method, seal.txid, seal.vout);
+    BuilderSeal::Revealed(XChain::Bitcoin(seal))
+}
+
+/// Contract issuance under one of the standard interfaces, which doesn't
+/// require knowledge of the interface and schema details.
+pub trait IssuanceTemplate {
+    /// Name of the interface under which the contract is issued.
+    const IFACE: &'static str;
+
+    /// Adds contract state to the builder.
+    fn populate(&self, builder: &mut TemplateBuilder);
+
+    /// Issues the contract using the provided schema, or the first schema
+    /// implementing the template interface known to the stock.
+    fn issue<S: StashProvider, H: StateProvider, P: IndexProvider>(
+        &self,
+        stock: &Stock<S, H, P>,
+        issuer: Identity,
+        schema_id: Option<SchemaId>,
+    ) -> Result<ValidContract, IssueError> {
+        let schema_id = match schema_id {
+            Some(id) => id,
+            None => iface_schema(stock, Self::IFACE)
+                .ok_or_else(|| IssueProblem::NoSchema(Self::IFACE.to_owned()))?,
+        };
+        let schema_ifaces = stock
+            .schema(schema_id)
+            .map_err(|_| IssueProblem::UnknownSchema(schema_id))?;
+        if !schema_ifaces
+            .iimpls
+            .keys()
+            .any(|name| name.as_str() == Self::IFACE)
+        {
+            return Err(IssueProblem::NoImplementation(Self::IFACE.to_owned(), schema_id).into());
+        }
+        let builder = stock
+            .contract_builder(issuer, schema_id, TypeName::from(Self::IFACE))
+            .map_err(|e| IssueProblem::Builder(s!("genesis"), e.to_string()))?;
+
+        let mut builder = TemplateBuilder::new(&schema_ifaces.schema, builder);
+        self.populate(&mut builder);
+        builder
+            .finish()?
+            .issue_contract()
+            .map_err(|e| IssueProblem::Builder(s!("genesis"), e.to_string()).into())
+    }
+}
+
+/// Fungible asset issued under RGB20 interface.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Rgb20Issuance {
+    pub ticker: String,
+    pub name: String,
+    pub details: Option<String>,
+    pub precision: Precision,
+    /// Text of the ricardian contract, which may be empty.
+    pub terms: String,
+    /// Initial allocations of the asset; the issued supply is equal to their
+    /// sum.
+    pub allocations: Vec<(OutputSeal, u64)>,
+}
+
+impl Rgb20Issuance {
+    pub fn new(ticker: impl ToString, name: impl ToString, precision: Precision) -> Self {
+        Self {
+            ticker: ticker.to_string(),
+            name: name.to_string(),
+            precision,
+            ..default!()
+        }
+    }
+
+    pub fn allocate(&mut self, seal: OutputSeal, amount: u64) -> &mut Self {
+        self.allocations.push((seal, amount));
+        self
+    }
+}
+
+impl IssuanceTemplate for Rgb20Issuance {
+    const IFACE: &'static str = RGB20_IFACE;
+
+    fn populate(&self, builder: &mut TemplateBuilder) {
+        match AssetSpec::with(&self.ticker, &self.name, self.precision, self.details.as_deref()) {
+            Ok(spec) => builder.add_global("spec", spec),
+            Err(e) => builder.problem(IssueProblem::InvalidField(s!("asset spec"), e.to_string())),
+        }
+        add_terms(builder, &self.terms);
+        add_allocations(builder, &self.allocations);
+    }
+}
+
+/// Collectible asset issued under RGB25 interface.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Rgb25Issuance {
+    pub name: String,
+    pub details: Option<String>,
+    pub precision: Precision,
+    /// Text of the ricardian contract, which may be empty.
+    pub terms: String,
+    /// Initial allocations of the asset; the issued supply is equal to their
+    /// sum.
+    pub allocations: Vec<(OutputSeal, u64)>,
+}
+
+impl Rgb25Issuance {
+    pub fn new(name: impl ToString, precision: Precision) -> Self {
+        Self {
+            name: name.to_string(),
+            precision,
+            ..default!()
+        }
+    }
+
+    pub fn allocate(&mut self, seal: OutputSeal, amount: u64) -> &mut Self {
+        self.allocations.push((seal, amount));
+        self
+    }
+}
+
+impl IssuanceTemplate for Rgb25Issuance {
+    const IFACE: &'static str = RGB25_IFACE;
+
+    fn populate(&self, builder: &mut TemplateBuilder) {
+        match Name::try_from(self.name.clone()) {
+            Ok(name) => builder.add_global("name", name),
+            Err(e) => builder.problem(IssueProblem::InvalidField(s!("name"), e.to_string())),
+        }
+        if let Some(details) = &self.details {
+            match Details::from_str(details) {
+                Ok(details) => builder.add_global("details", details),
+                Err(e) => builder.problem(IssueProblem::InvalidField(s!("details"), e.to_string())),
+            }
+        }
+        builder.add_global("precision", self.precision);
+        add_terms(builder, &self.terms);
+        add_allocations(builder, &self.allocations);
+    }
+}
+
+/// Unique digital asset issued under RGB21 interface, consisting of a single
+/// token owned by a single seal.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Rgb21Issuance {
+    pub ticker: String,
+    pub name: String,
+    pub details: Option<String>,
+    /// Text of the ricardian contract, which may be empty.
+    pub terms: String,
+    pub token_index: TokenIndex,
+    /// Number of fractions the token is split into.
+    pub fractions: OwnedFraction,
+    pub owner: OutputSeal,
+}
+
+impl Rgb21Issuance {
+    pub fn new(ticker: impl ToString, name: impl ToString, owner: OutputSeal) -> Self {
+        Self {
+            ticker: ticker.to_string(),
+            name: name.to_string(),
+            details: None,
+            terms: none!(),
+            token_index: TokenIndex::from(0),
+            fractions: OwnedFraction::from(1),
+            owner,
+        }
+    }
+}
+
+impl IssuanceTemplate for Rgb21Issuance {
+    const IFACE: &'static str = RGB21_IFACE;
+
+    fn populate(&self, builder: &mut TemplateBuilder) {
+        let spec = AssetSpec::with(
+            &self.ticker,
+            &self.name,
+            Precision::Indivisible,
+            self.details.as_deref(),
+        );
+        match spec {
+            Ok(spec) => builder.add_global("spec", spec),
+            Err(e) => builder.problem(IssueProblem::InvalidField(s!("asset spec"), e.to_string())),
+        }
+        add_terms(builder, &self.terms);
+        let token = StrictVal::struc([
+            ("index", StrictVal::num(self.token_index.to_inner())),
+            ("ticker", StrictVal::none()),
+            ("name", StrictVal::none()),
+            ("details", StrictVal::none()),
+            ("preview", StrictVal::none()),
+            ("media", StrictVal::none()),
+            ("attachments", StrictVal::Map(vec![])),
+            ("reserves", StrictVal::none()),
+        ]);
+        builder.add_global_val("tokens", token);
+        builder.add_data(
+            "assetOwner",
+            self.owner,
+            Allocation::with(self.token_index, self.fractions),
+        );
+    }
+}
+
+fn add_terms(builder: &mut TemplateBuilder, terms: &str) {
+    match RicardianContract::from_str(terms) {
+        Ok(text) => builder.add_global("terms", ContractTerms { text, media: None }),
+        Err(e) => builder.problem(IssueProblem::InvalidField(s!("terms"), e.to_string())),
+    }
+}
+
+fn add_allocations(builder: &mut TemplateBuilder, allocations: &[(OutputSeal, u64)]) {
+    let supply = allocations
+        .iter()
+        .try_fold(0u64, |sum, (_, amount)| sum.checked_add(*amount));
+    match supply {
+        Some(supply) => builder.add_global("issuedSupply", Amount::from(supply)),
+        None => builder.problem(IssueProblem::SupplyOverflow),
+    }
+    for (seal, amount) in allocations {
+        builder.add_fungible("assetOwner", *seal, *amount);
+    }
+}
@@ -0,0 +1,326 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2023 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2023 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Delivery of transfer consignments to the transport endpoints listed in the
+//! invoice, which are tried in the order of their priorities.
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+use rgbstd::containers::{FileContent, Transfer};
+
+use crate::invoice::{RgbInvoice, RgbInvoiceBuilder, RgbTransport};
+use crate::{DeliveryError, InvoiceApiError, ValidatedInvoiceBuilder};
+
+/// Invoice query parameter specifying delivery metadata of the transport
+/// endpoints, in the order of the invoice transports.
+pub const INVOICE_QUERY_PRIORITIES: &str = "priorities";
+
+/// Delivery metadata of a transport endpoint.
+///
+/// The string representation is the priority, optionally followed by the
+/// timeout in seconds and the `strict` flag, separated by colons, like
+/// `1`, `2:30` or `3:10:strict`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TransportMeta {
+    /// Priority of the endpoint; endpoints with lower values are tried first.
+    pub priority: u8,
+    /// Time in seconds after which the delivery to the endpoint is considered
+    /// failed. If not given, the payer uses its own default timeout.
+    pub timeout: Option<u32>,
+    /// Whether the payer may fail over to the next endpoint once the delivery
+    /// to this one fails.
+    pub failover: bool,
+}
+
+impl TransportMeta {
+    pub fn with(priority: u8) -> Self {
+        TransportMeta {
+            priority,
+            timeout: None,
+            failover: true,
+        }
+    }
+
+    /// Timeout of the delivery to the endpoint, falling back to the `default`
+    /// one.
+    pub fn timeout_or(&self, default: Duration) -> Duration {
+        self.timeout
+            .map(|secs| Duration::from_secs(secs as u64))
+            .unwrap_or(default)
+    }
+}
+
+impl Display for TransportMeta {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.priority)?;
+        if let Some(timeout) = self.timeout {
+            write!(f, ":{timeout}")?;
+        }
+        if !self.failover {
+            f.write_str(":strict")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TransportMeta {
+    type Err = DeliveryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || DeliveryError::InvalidMeta(s.to_owned());
+        let mut parts = s.split(':');
+        let priority = parts
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| err())?;
+        let mut meta = TransportMeta::with(priority);
+        for part in parts {
+            match part {
+                "strict" if meta.failover => meta.failover = false,
+                _ if meta.timeout.is_none() && meta.failover => {
+                    meta.timeout = Some(part.parse().map_err(|_| err())?)
+                }
+                _ => return Err(err()),
+            }
+        }
+        Ok(meta)
+    }
+}
+
+/// Delivery metadata of all transport endpoints of an invoice, in the order of
+/// [`RgbInvoice::transports`].
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct TransportPriorities(Vec<TransportMeta>);
+
+impl TransportPriorities {
+    pub fn new(meta: impl IntoIterator<Item = TransportMeta>) -> Self {
+        Self(meta.into_iter().collect())
+    }
+
+    pub fn meta(&self) -> &[TransportMeta] { &self.0 }
+
+    /// Reads the metadata from the invoice query parameters. Returns `None` if
+    /// the invoice doesn't specify it, in which case the transports are tried
+    /// in the order they are listed.
+    pub fn from_invoice(invoice: &RgbInvoice) -> Result<Option<Self>, DeliveryError> {
+        let Some(value) = invoice.unknown_query.get(INVOICE_QUERY_PRIORITIES) else {
+            return Ok(None);
+        };
+        let meta = value
+            .split(',')
+            .map(TransportMeta::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        if meta.len() != invoice.transports.len() {
+            return Err(DeliveryError::MetaMismatch(meta.len(), invoice.transports.len()));
+        }
+        Ok(Some(Self(meta)))
+    }
+
+    /// Stores the metadata in the invoice query parameters.
+    pub fn set_to_invoice(&self, invoice: &mut RgbInvoice) {
+        let value = self
+            .0
+            .iter()
+            .map(TransportMeta::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        invoice
+            .unknown_query
+            .insert(INVOICE_QUERY_PRIORITIES.to_owned(), value);
+    }
+}
+
+/// Returns transport endpoints of the invoice together with their delivery
+/// metadata, in the order in which they have to be tried.
+///
+/// Endpoints with the same priority keep the order in which they are listed in
+/// the invoice. Invoices without the metadata are delivered to their
+/// endpoints in the listed order, failing over to the next endpoint.
+pub fn delivery_order(
+    invoice: &RgbInvoice,
+) -> Result<Vec<(RgbTransport, TransportMeta)>, DeliveryError> {
+    let meta = match TransportPriorities::from_invoice(invoice)? {
+        Some(priorities) => priorities.0,
+        None => (0..invoice.transports.len())
+            .map(|no| TransportMeta::with(no.min(u8::MAX as usize) as u8))
+            .collect(),
+    };
+    let mut order = invoice
+        .transports
+        .iter()
+        .cloned()
+        .zip(meta)
+        .filter(|(transport, _)| *transport != RgbTransport::UnspecifiedMeans)
+        .collect::<Vec<_>>();
+    order.sort_by_key(|(_, meta)| meta.priority);
+    Ok(order)
+}
+
+/// Extension of [`RgbInvoiceBuilder`] adding transport endpoints together with
+/// their delivery metadata.
+pub trait TransportInvoiceBuilder {
+    /// Adds transport endpoint with the given delivery metadata.
+    fn add_transport_with(self, transport: RgbTransport, meta: TransportMeta)
+        -> PrioritizedBuilder;
+}
+
+impl TransportInvoiceBuilder for RgbInvoiceBuilder {
+    fn add_transport_with(
+        self,
+        transport: RgbTransport,
+        meta: TransportMeta,
+    ) -> PrioritizedBuilder {
+        PrioritizedBuilder::from(self).add_transport_with(transport, meta)
+    }
+}
+
+/// Invoice builder with transport endpoints having delivery metadata.
+///
+/// Endpoints added to the [`RgbInvoiceBuilder`] before, without the metadata,
+/// are tried after all prioritized endpoints.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PrioritizedBuilder {
+    builder: RgbInvoiceBuilder,
+    transports: Vec<(RgbTransport, TransportMeta)>,
+}
+
+impl From<RgbInvoiceBuilder> for PrioritizedBuilder {
+    fn from(builder: RgbInvoiceBuilder) -> Self {
+        PrioritizedBuilder {
+            builder,
+            transports: vec![],
+        }
+    }
+}
+
+impl PrioritizedBuilder {
+    /// Adds transport endpoint with the given delivery metadata.
+    pub fn add_transport_with(mut self, transport: RgbTransport, meta: TransportMeta) -> Self {
+        self.builder = self.builder.add_transport_raw(transport.clone());
+        self.transports.push((transport, meta));
+        self
+    }
+
+    /// Completes the invoice, storing the delivery metadata of its transports.
+    pub fn finish(self) -> RgbInvoice {
+        let mut invoice = self.builder.finish();
+        let mut transports = self.transports;
+        let meta = invoice.transports.iter().map(|transport| {
+            match transports.iter().position(|(t, _)| t == transport) {
+                Some(pos) => transports.remove(pos).1,
+                None => TransportMeta::with(u8::MAX),
+            }
+        });
+        TransportPriorities::new(meta).set_to_invoice(&mut invoice);
+        invoice
+    }
+}
+
+impl ValidatedInvoiceBuilder for PrioritizedBuilder {
+    fn finish_validated(
+        self,
+        iface: &rgbstd::interface::Iface,
+    ) -> Result<RgbInvoice, InvoiceApiError> {
+        let invoice = self.finish();
+        crate::InvoiceValidation::validate_against(&invoice, iface)?;
+        Ok(invoice)
+    }
+}
+
+/// Client posting transfer consignments to a transport endpoint.
+pub trait ConsignmentPoster {
+    fn post(&self, transport: &RgbTransport, data: &[u8], timeout: Duration) -> Result<(), String>;
+}
+
+impl<F: Fn(&RgbTransport, &[u8], Duration) -> Result<(), String>> ConsignmentPoster for F {
+    fn post(&self, transport: &RgbTransport, data: &[u8], timeout: Duration) -> Result<(), String> {
+        self(transport, data, timeout)
+    }
+}
+
+/// Client posting consignments to REST HTTP endpoints.
+#[cfg(feature = "esplora_blocking")]
+pub struct HttpsPoster(ureq::Agent);
+
+#[cfg(feature = "esplora_blocking")]
+impl HttpsPoster {
+    /// Constructs the client, which connects via an optional SOCKS5 proxy.
+    pub fn new(socks5: Option<&str>) -> Result<Self, String> {
+        let mut agent = ureq::AgentBuilder::new();
+        if let Some(proxy) = socks5 {
+            let proxy = ureq::Proxy::new(format!("socks5://{proxy}")).map_err(|e| e.to_string())?;
+            agent = agent.proxy(proxy);
+        }
+        Ok(Self(agent.build()))
+    }
+}
+
+#[cfg(feature = "esplora_blocking")]
+impl ConsignmentPoster for HttpsPoster {
+    fn post(&self, transport: &RgbTransport, data: &[u8], timeout: Duration) -> Result<(), String> {
+        let RgbTransport::RestHttp { tls, host } = transport else {
+            return Err(format!("transport {transport} is not supported"));
+        };
+        let scheme = if *tls { "https" } else { "http" };
+        self.0
+            .post(&format!("{scheme}://{host}"))
+            .timeout(timeout)
+            .set("Content-Type", "application/octet-stream")
+            .send_bytes(data)
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// Delivers the transfer consignment paying the invoice to its transport
+/// endpoints, trying them in the order of [`delivery_order`] until one of them
+/// accepts the consignment. Endpoints without their own timeout use the
+/// `default_timeout`.
+///
+/// Returns the endpoint which has accepted the consignment.
+pub fn deliver_consignment(
+    invoice: &RgbInvoice,
+    transfer: &Transfer,
+    poster: &impl ConsignmentPoster,
+    default_timeout: Duration,
+) -> Result<RgbTransport, DeliveryError> {
+    let order = delivery_order(invoice)?;
+    if order.is_empty() {
+        return Err(DeliveryError::NoTransports);
+    }
+    let mut data = Vec::new();
+    transfer.save(&mut data)?;
+
+    let mut failures = Vec::with_capacity(order.len());
+    for (transport, meta) in order {
+        match poster.post(&transport, &data, meta.timeout_or(default_timeout)) {
+            Ok(()) => return Ok(transport),
+            Err(err) => failures.push(format!("{transport}: {err}")),
+        }
+        if !meta.failover {
+            break;
+        }
+    }
+    Err(DeliveryError::Failed(failures.join("; ")))
+}
@@ -0,0 +1,280 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Velocity classes requested for the outputs receiving RGB state.
+//!
+//! Velocity hints tell how often the state is expected to be spent, such that
+//! the state of different velocity is kept at different outputs and spending
+//! a frequently moved asset doesn't move the rarely spent ones. The contract
+//! supplements provide default hints; the beneficiary may request a velocity
+//! class for the received state with an invoice, and the wallet keeps its
+//! velocity preferences, which are honored when the wallet allocates the
+//! change of the same state in later payments.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::str::FromStr;
+
+use amplify::Wrapper;
+use rgbstd::containers::VelocityHint;
+use rgbstd::invoice::RgbInvoice;
+use rgbstd::{AssignmentType, ContractId};
+use strict_types::FieldName;
+
+use crate::{CompositionError, VelocityError};
+
+/// Invoice query parameter specifying the velocity class which the beneficiary
+/// requests for the output receiving the state.
+pub const INVOICE_QUERY_VELOCITY: &str = "velocity";
+
+/// Returns name of the velocity class used in invoices and preference files.
+pub fn velocity_name(hint: VelocityHint) -> &'static str {
+    match hint {
+        VelocityHint::Unspecified => "unspecified",
+        VelocityHint::Seldom => "seldom",
+        VelocityHint::Episodic => "episodic",
+        VelocityHint::Regular => "regular",
+        VelocityHint::Frequent => "frequent",
+        VelocityHint::HighFrequency => "high-frequency",
+    }
+}
+
+/// Parses name of the velocity class, as produced by [`velocity_name`].
+pub fn parse_velocity(name: &str) -> Option<VelocityHint> {
+    Some(match name {
+        "unspecified" => VelocityHint::Unspecified,
+        "seldom" => VelocityHint::Seldom,
+        "episodic" => VelocityHint::Episodic,
+        "regular" => VelocityHint::Regular,
+        "frequent" => VelocityHint::Frequent,
+        "high-frequency" => VelocityHint::HighFrequency,
+        _ => return None,
+    })
+}
+
+/// Velocity class requested by the invoice for the output receiving the state.
+///
+/// The payer marks the beneficiary witness output with the hint; for blinded
+/// seals the output is chosen by the beneficiary, which keeps the requested
+/// class in its own [`VelocityPrefs`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct RequestedVelocity(VelocityHint);
+
+impl RequestedVelocity {
+    pub fn new(hint: VelocityHint) -> Self { Self(hint) }
+
+    pub fn hint(&self) -> VelocityHint { self.0 }
+
+    /// Reads the requested velocity class from the invoice query parameters.
+    /// Returns `None` if the invoice doesn't request a specific class.
+    pub fn from_invoice(invoice: &RgbInvoice) -> Result<Option<Self>, CompositionError> {
+        let Some(value) = invoice.unknown_query.get(INVOICE_QUERY_VELOCITY) else {
+            return Ok(None);
+        };
+        parse_velocity(value)
+            .map(|hint| Some(Self(hint)))
+            .ok_or_else(|| CompositionError::InvalidVelocity(value.clone()))
+    }
+
+    /// Stores the velocity class in the invoice query parameters.
+    pub fn set_to_invoice(&self, invoice: &mut RgbInvoice) {
+        invoice
+            .unknown_query
+            .insert(INVOICE_QUERY_VELOCITY.to_owned(), velocity_name(self.0).to_owned());
+    }
+}
+
+/// Velocity preferences of the wallet for the assignment types of the
+/// contracts, overriding the hints from the contract supplements.
+///
+/// Preferences requested with the invoices for the contracts which are not
+/// known to the stock yet are kept by the name of the assignment, and are
+/// resolved into the assignment types with [`VelocityPrefs::resolve`] once
+/// the contracts are known.
+///
+/// Serialized as a map from `CONTRACT_ID/ASSIGNMENT_TYPE` or
+/// `CONTRACT_ID/ASSIGNMENT_NAME` string to the name of the velocity class,
+/// where the assignment type is a decimal number.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(
+        crate = "serde_crate",
+        try_from = "BTreeMap<String, String>",
+        into = "BTreeMap<String, String>"
+    )
+)]
+pub struct VelocityPrefs {
+    types: BTreeMap<(ContractId, AssignmentType), VelocityHint>,
+    names: BTreeMap<(ContractId, FieldName), VelocityHint>,
+}
+
+impl TryFrom<BTreeMap<String, String>> for VelocityPrefs {
+    type Error = VelocityError;
+
+    fn try_from(map: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        let mut prefs = VelocityPrefs::new();
+        for (key, hint) in map {
+            let (id, assignment) = key
+                .rsplit_once('/')
+                .ok_or_else(|| VelocityError::InvalidKey(key.clone()))?;
+            let contract_id =
+                ContractId::from_str(id).map_err(|_| VelocityError::InvalidKey(key.clone()))?;
+            let hint = parse_velocity(&hint).ok_or(VelocityError::InvalidHint(hint))?;
+            if let Ok(assignment_type) = u16::from_str(assignment) {
+                prefs.set(contract_id, AssignmentType::with(assignment_type), hint);
+            } else {
+                let name = FieldName::try_from(assignment.to_owned())
+                    .map_err(|_| VelocityError::InvalidKey(key.clone()))?;
+                prefs.set_named(contract_id, name, hint);
+            }
+        }
+        Ok(prefs)
+    }
+}
+
+impl From<VelocityPrefs> for BTreeMap<String, String> {
+    fn from(prefs: VelocityPrefs) -> Self {
+        let types = prefs
+            .types
+            .into_iter()
+            .map(|((contract_id, assignment_type), hint)| {
+                (format!("{contract_id}/{}", assignment_type.to_inner()), hint)
+            });
+        let names = prefs
+            .names
+            .into_iter()
+            .map(|((contract_id, name), hint)| (format!("{contract_id}/{name}"), hint));
+        types
+            .chain(names)
+            .map(|(key, hint)| (key, velocity_name(hint).to_owned()))
+            .collect()
+    }
+}
+
+impl VelocityPrefs {
+    pub fn new() -> Self { Self::default() }
+
+    /// Loads velocity preferences from a YAML file, returning no preferences
+    /// if the file doesn't exist.
+    #[cfg(feature = "fs")]
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self, VelocityError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = fs::File::open(path)?;
+        Ok(serde_yaml::from_reader(file)?)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn save_file(&self, path: impl AsRef<Path>) -> Result<(), VelocityError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = fs::File::create(path)?;
+        serde_yaml::to_writer(file, self)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool { self.types.is_empty() && self.names.is_empty() }
+
+    pub fn len(&self) -> usize { self.types.len() + self.names.len() }
+
+    /// Sets velocity class for the assignment type of the contract, returning
+    /// the previously set one, if any.
+    pub fn set(
+        &mut self,
+        contract_id: ContractId,
+        assignment_type: AssignmentType,
+        hint: VelocityHint,
+    ) -> Option<VelocityHint> {
+        self.types.insert((contract_id, assignment_type), hint)
+    }
+
+    /// Removes velocity preference for the assignment type of the contract,
+    /// returning it.
+    pub fn remove(
+        &mut self,
+        contract_id: ContractId,
+        assignment_type: AssignmentType,
+    ) -> Option<VelocityHint> {
+        self.types.remove(&(contract_id, assignment_type))
+    }
+
+    pub fn get(
+        &self,
+        contract_id: ContractId,
+        assignment_type: AssignmentType,
+    ) -> Option<VelocityHint> {
+        self.types.get(&(contract_id, assignment_type)).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ContractId, AssignmentType, VelocityHint)> + '_ {
+        self.types
+            .iter()
+            .map(|((contract_id, assignment_type), hint)| (*contract_id, *assignment_type, *hint))
+    }
+
+    /// Sets velocity class for the named assignment of the contract, which
+    /// type is not known yet, returning the previously set one, if any.
+    pub fn set_named(
+        &mut self,
+        contract_id: ContractId,
+        name: FieldName,
+        hint: VelocityHint,
+    ) -> Option<VelocityHint> {
+        self.names.insert((contract_id, name), hint)
+    }
+
+    /// Iterates over the preferences for the named assignments, which types
+    /// were not resolved yet.
+    pub fn iter_named(&self) -> impl Iterator<Item = (ContractId, &FieldName, VelocityHint)> + '_ {
+        self.names
+            .iter()
+            .map(|((contract_id, name), hint)| (*contract_id, name, *hint))
+    }
+
+    /// Resolves the named assignments into the assignment types with the
+    /// `resolver`, which returns `None` for the contracts which are still
+    /// unknown. Preferences already set for the resolved assignment types are
+    /// kept. Returns whether any of the named assignments was resolved.
+    pub fn resolve(
+        &mut self,
+        resolver: impl Fn(ContractId, &FieldName) -> Option<AssignmentType>,
+    ) -> bool {
+        let before = self.names.len();
+        let types = &mut self.types;
+        self.names.retain(|(contract_id, name), hint| {
+            let Some(assignment_type) = resolver(*contract_id, name) else {
+                return true;
+            };
+            types.entry((*contract_id, assignment_type)).or_insert(*hint);
+            false
+        });
+        self.names.len() != before
+    }
+}
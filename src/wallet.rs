@@ -19,33 +19,149 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
 use std::marker::PhantomData;
+use std::mem;
 #[cfg(feature = "fs")]
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
 
-use bpstd::XpubDerivable;
+use amplify::Wrapper;
+use bp::seals::txout::{CloseMethod, TxPtr, TxoSeal};
+use bpstd::{
+    Address, Derive, Descriptor, Idx, IdxBase, NormalIndex, Outpoint, Sats, ScriptPubkey,
+    Terminal, Tx, Txid, Vout, XpubDerivable,
+};
 #[cfg(feature = "fs")]
 use bpwallet::fs::FsTextStore;
+use bpwallet::{Counterparty, Indexer, Layer2, NoLayer2, Wallet};
+#[cfg(feature = "fs")]
+use commit_verify::Conceal;
 #[cfg(feature = "fs")]
-use bpwallet::Wallet;
-use bpwallet::{Layer2, NoLayer2};
-#[cfg(not(target_arch = "wasm32"))]
-use nonasync::persistence::PersistenceProvider;
-use psrgbt::{Psbt, PsbtMeta};
-use rgbstd::containers::Transfer;
-use rgbstd::interface::{ContractOp, IfaceRef};
+use nonasync::persistence::{PersistenceProvider, Persisting};
+use psrgbt::{OutputRole, Psbt, PsbtMeta, PsbtVer, RgbExt, RgbInExt, RgbOutExt, RgbPsbt, TxParams};
+use rgbstd::containers::{
+    ConsignmentExt, Fascia, PubWitness, SealWitness, Transfer, VelocityHint,
+};
+use rgbstd::interface::{AllocatedState, AssignmentsFilter, ContractOp, IfaceRef};
 #[cfg(feature = "fs")]
 use rgbstd::persistence::fs::FsBinStore;
 use rgbstd::persistence::{
-    ContractIfaceError, IndexProvider, MemIndex, MemStash, MemState, StashProvider, StateProvider,
-    Stock, StockError,
+    ContractIfaceError, ContractStateRead, IndexProvider, MemIndex, MemStash, MemState,
+    StashProvider, StashReadProvider, StateProvider, Stock, StockError, UpdateRes,
 };
 
+#[cfg(feature = "serde")]
+use super::ContractCall;
 use super::{
-    CompletionError, CompositionError, ContractId, DescriptorRgb, PayError, TransferParams,
-    WalletError, WalletProvider,
+    AcceptError, AddressReservations, AmountFormatter, AssignmentPreview, BasketInvoice,
+    CompletionError, CompositionError, ConsolidationReport, ConsolidationScope, ContractId,
+    ContractPreview, DescriptorRgb, FrozenOutpoints, HistoryExporter, ImportedUtxo, ImportedUtxos,
+    InvoiceExpiry, InvoiceStatusError, NetworkGuard, OwnershipError, OwnershipProof, PayError,
+    PayjoinClient, PayjoinEndpoint, PayjoinError, PayjoinProposal, PreviewError, RedeemInfo,
+    ReorgError, ReorgTracker, RequestedVelocity, RgbKeychain, SaleProposal, Signer,
+    StaleAllocation, StaleReason, StateDestination, SupplyOperation, SwapError, SwapMeta,
+    SwapProposal, SyncError, SyncProgress, SyncStage, TapTweakAlreadyAssigned, TapretTweaks,
+    TransferParams, TransferPlan, TransferPreview, TransferRecord, TxOutPreview, VelocityPrefs,
+    WalletEvent, WalletProvider, WitnessCheckError,
 };
-use crate::invoice::RgbInvoice;
+#[cfg(feature = "fs")]
+use super::{ArchiveError, SealExpiry, StockArchive, StockCompaction, StockLock, WalletError};
+use crate::bump::{resolve_bitcoin_tx, witness_fee};
+use crate::descriptor::fill_tap_outputs;
+use crate::events::{Observers, StateSnapshot};
+use crate::invoice::{Amount, Beneficiary, RgbInvoice};
+use crate::ownership::{bip322_psbt, invoice_id};
+use crate::pay::{contract_assignment_type, invoice_assignment_name, invoice_assignment_type};
+use crate::progress::update_witnesses_with_progress;
+use crate::resolvers::AnyResolver;
+use crate::swap::own_fascia;
+use crate::validation::{self, ResolveWitness, WitnessResolverError};
+use crate::vm::WitnessOrd;
+use crate::{
+    Assign, AssignmentType, ExposedState, GraphSeal, Layer1, Opout, TypedAssigns, XChain, XOutpoint,
+    XOutputSeal,
+};
+
+/// Number of unused tapret keychain derivation indexes scanned beyond the
+/// last used one during the tapret tweak recovery.
+pub const TAPRET_RECOVERY_GAP: u16 = 20;
+
+/// Name of the wallet provided on [`RgbWallet`] construction.
+pub const DEFAULT_WALLET_NAME: &str = "default";
+
+/// Hook receiving tapret tweaks newly added to the wallet descriptor, which
+/// allows incremental backups of the tweaks.
+pub type TweaksBackupHook = Box<dyn FnMut(&TapretTweaks) + Send>;
+
+/// Payment status of an invoice issued by the wallet.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display(lowercase)]
+pub enum InvoiceStatus {
+    /// No state was assigned to the invoice beneficiary yet.
+    Unpaid,
+
+    /// A transaction paying to the witness-out beneficiary of the invoice is
+    /// seen by the indexer (in mempool or mined), but the consignment
+    /// assigning state to it is not accepted yet.
+    #[display("payment seen, waiting for consignment")]
+    PaymentSeen,
+
+    /// State is assigned to the invoice beneficiary by a witness transaction
+    /// which is not mined yet.
+    #[display("pending confirmation")]
+    PendingConfirmation,
+
+    /// State is assigned to the invoice beneficiary by a mined witness
+    /// transaction.
+    Paid,
+}
+
+/// Default number of confirmations after which a mined allocation is counted
+/// as a part of the confirmed balance.
+pub const BALANCE_MIN_CONFIRMATIONS: u32 = 6;
+
+/// Fungible balance of a contract owned by the wallet, split into buckets by
+/// the status of the witness transactions assigning the state.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct BalanceReport {
+    /// State assigned in genesis or by witness transactions having at least
+    /// the required number of confirmations.
+    pub confirmed: Amount,
+
+    /// State assigned by witness transactions of the wallet itself (like
+    /// change of an outgoing transfer) which are not mined yet.
+    pub tentative: Amount,
+
+    /// State received by witness transactions of other parties which are not
+    /// mined yet.
+    pub unconfirmed_incoming: Amount,
+
+    /// State assigned by mined witness transactions which don't have the
+    /// required number of confirmations yet.
+    pub immature: Amount,
+}
+
+impl BalanceReport {
+    /// Returns balance assigned by genesis and mined witness transactions,
+    /// including the immature ones.
+    pub fn mined(&self) -> Amount { self.confirmed + self.immature }
+
+    /// Returns balance assigned by witness transactions which are not mined
+    /// yet and thus may still be replaced or evicted from the mempool.
+    pub fn pending(&self) -> Amount { self.tentative + self.unconfirmed_incoming }
+
+    /// Returns total balance across all buckets, mixing the mined state with
+    /// the pending one. Prefer [`Self::mined`] and [`Self::pending`] for the
+    /// display of the balance.
+    pub fn total(&self) -> Amount { self.mined() + self.pending() }
+}
 
 #[derive(Getters)]
 pub struct RgbWallet<
@@ -59,10 +175,33 @@ pub struct RgbWallet<
 {
     stock: Stock<S, H, P>,
     wallet: W,
+    /// Name of the active wallet.
+    wallet_name: String,
+    /// Other wallets sharing the same stock.
+    #[getter(skip)]
+    wallets: BTreeMap<String, W>,
+    reorg_tracker: ReorgTracker,
+    /// Outpoints excluded from the payments.
+    frozen: FrozenOutpoints,
+    /// Outputs not derived from the wallet descriptor, which the payments may
+    /// spend.
+    imported: ImportedUtxos,
+    /// Velocity classes used for the change of the contract state, including
+    /// the ones requested by the invoices issued by the wallet.
+    velocity_prefs: VelocityPrefs,
+    #[getter(skip)]
+    tweaks_backup: Option<TweaksBackupHook>,
+    #[getter(skip)]
+    observers: Observers,
     #[getter(skip)]
     _key_phantom: PhantomData<K>,
     #[getter(skip)]
     _layer2_phantom: PhantomData<L2>,
+    // Must be the last field, such that the lock is released after the stock
+    // is dropped.
+    #[cfg(feature = "fs")]
+    #[getter(skip)]
+    stock_lock: Option<StockLock>,
 }
 
 #[cfg(feature = "fs")]
@@ -94,11 +233,96 @@ impl<K, D: DescriptorRgb<K>, S: StashProvider, H: StateProvider, P: IndexProvide
         let wallet = Wallet::load(provider, autosave).map_err(WalletError::WalletPersist)?;
         Ok(Self {
             wallet,
+            wallet_name: DEFAULT_WALLET_NAME.to_owned(),
+            wallets: empty!(),
             stock,
+            reorg_tracker: none!(),
+            frozen: none!(),
+            imported: none!(),
+            velocity_prefs: none!(),
+            tweaks_backup: None,
+            observers: none!(),
             _key_phantom: PhantomData,
             _layer2_phantom: PhantomData,
+            #[cfg(feature = "fs")]
+            stock_lock: None,
         })
     }
+
+    /// Loads wallet like [`Self::load`], holding an exclusive lock on the
+    /// stock directory while the wallet exists, such that other processes
+    /// can't modify the same stock concurrently.
+    ///
+    /// If the stock is already locked, either blocks until the lock is
+    /// released (if `wait` is set) or fails with [`WalletError::StockLocked`].
+    #[allow(clippy::result_large_err)]
+    pub fn load_locked(
+        stock_path: PathBuf,
+        wallet_path: PathBuf,
+        autosave: bool,
+        wait: bool,
+    ) -> Result<Self, WalletError>
+    where
+        D: serde::Serialize + for<'de> serde::Deserialize<'de>,
+        L2::Descr: serde::Serialize + for<'de> serde::Deserialize<'de>,
+        L2::Data: serde::Serialize + for<'de> serde::Deserialize<'de>,
+        L2::Cache: serde::Serialize + for<'de> serde::Deserialize<'de>,
+        FsBinStore: PersistenceProvider<S>,
+        FsBinStore: PersistenceProvider<H>,
+        FsBinStore: PersistenceProvider<P>,
+        FsTextStore: PersistenceProvider<L2>,
+    {
+        let lock = StockLock::acquire(&stock_path, wait)?;
+        let mut wallet = Self::load(stock_path, wallet_path, autosave)?;
+        wallet.stock_lock = Some(lock);
+        Ok(wallet)
+    }
+
+    /// Keeps the stock lock while the wallet exists.
+    pub(crate) fn set_stock_lock(&mut self, lock: StockLock) { self.stock_lock = Some(lock); }
+}
+
+impl<K, D: DescriptorRgb<K>, S: StashProvider, H: StateProvider, P: IndexProvider, L2: Layer2>
+    RgbWallet<Wallet<K, D, L2>, K, S, H, P, L2>
+{
+    /// Synchronizes wallet UTXOs with the indexer and updates the status of
+    /// the witness transactions mined after `after_height`, reporting the
+    /// progress to the callback.
+    ///
+    /// The UTXOs are retrieved by the indexer at once, thus only the start of
+    /// the UTXO stage is reported; witness transactions are reported one by
+    /// one.
+    pub fn sync<I: Indexer>(
+        &mut self,
+        indexer: &I,
+        resolver: &AnyResolver,
+        after_height: u32,
+        mut callback: impl FnMut(&SyncProgress),
+    ) -> Result<UpdateRes, SyncError>
+    where
+        I::Error: Display,
+    {
+        let mut progress = SyncProgress::default();
+        callback(&progress);
+        if let Some(errors) = self.wallet.update(indexer).err {
+            let errors = errors.iter().map(I::Error::to_string).collect::<Vec<_>>();
+            return Err(SyncError::Indexer(errors.join("; ")));
+        }
+        progress.addresses_used = self.wallet.address_balance().count();
+        progress.utxos_found = self.wallet.utxos().count();
+
+        let res = update_witnesses_with_progress(
+            &mut self.stock,
+            resolver,
+            after_height,
+            &mut progress,
+            &mut callback,
+        )?;
+        progress.stage = SyncStage::Done;
+        callback(&progress);
+        self.check_changes();
+        Ok(res)
+    }
 }
 
 impl<
@@ -115,8 +339,18 @@ where W::Descr: DescriptorRgb<K>
         Self {
             stock,
             wallet,
+            wallet_name: DEFAULT_WALLET_NAME.to_owned(),
+            wallets: empty!(),
+            reorg_tracker: none!(),
+            frozen: none!(),
+            imported: none!(),
+            velocity_prefs: none!(),
+            tweaks_backup: None,
+            observers: none!(),
             _key_phantom: PhantomData,
             _layer2_phantom: PhantomData,
+            #[cfg(feature = "fs")]
+            stock_lock: None,
         }
     }
 
@@ -124,6 +358,616 @@ where W::Descr: DescriptorRgb<K>
 
     pub fn wallet_mut(&mut self) -> &mut W { &mut self.wallet }
 
+    /// Attaches other wallet to the same stock, such that it can be selected
+    /// with [`Self::select_wallet`] without duplicating the contract data.
+    ///
+    /// Returns the wallet previously attached under the same name; if the
+    /// name is the one of the active wallet, the active wallet is replaced.
+    pub fn attach_wallet(&mut self, name: impl Into<String>, wallet: W) -> Option<W> {
+        let name = name.into();
+        if name == self.wallet_name {
+            let prev = mem::replace(&mut self.wallet, wallet);
+            self.observers.reset();
+            self.check_changes();
+            return Some(prev);
+        }
+        self.wallets.insert(name, wallet)
+    }
+
+    /// Renames the active wallet.
+    pub(crate) fn set_wallet_name(&mut self, name: &str) { self.wallet_name = name.to_owned(); }
+
+    /// Detaches wallet which is not active, returning it.
+    pub fn detach_wallet(&mut self, name: &str) -> Option<W> { self.wallets.remove(name) }
+
+    /// Returns names of all wallets sharing the stock, including the active
+    /// one.
+    pub fn wallet_names(&self) -> impl Iterator<Item = &str> {
+        let mut names = self.wallets.keys().map(String::as_str).collect::<Vec<_>>();
+        names.push(&self.wallet_name);
+        names.sort_unstable();
+        names.into_iter()
+    }
+
+    /// Makes one of the attached wallets active, such that the state, history
+    /// and payments are processed using its outputs.
+    ///
+    /// Returns `false` if there is no wallet with the given name, leaving the
+    /// active wallet unchanged.
+    pub fn select_wallet(&mut self, name: &str) -> bool {
+        if name == self.wallet_name {
+            return true;
+        }
+        let Some(wallet) = self.wallets.remove(name) else {
+            return false;
+        };
+        let prev = mem::replace(&mut self.wallet, wallet);
+        let prev_name = mem::replace(&mut self.wallet_name, name.to_owned());
+        self.wallets.insert(prev_name, prev);
+        // Events are reported for the changes within the same wallet
+        self.observers.reset();
+        self.check_changes();
+        true
+    }
+
+    /// Replaces re-org tracker, for instance with the one restored from a
+    /// persistent storage.
+    pub fn set_reorg_tracker(&mut self, tracker: ReorgTracker) { self.reorg_tracker = tracker; }
+
+    /// Replaces the set of frozen outpoints, for instance with the one loaded
+    /// from a file.
+    pub fn set_frozen(&mut self, frozen: FrozenOutpoints) { self.frozen = frozen; }
+
+    /// Freezes the outpoint, such that neither its bitcoins nor the RGB state
+    /// it holds are spent by the payments. Returns the reason the outpoint was
+    /// frozen for before, if it was already frozen.
+    pub fn freeze(&mut self, outpoint: Outpoint, reason: impl Into<String>) -> Option<String> {
+        self.frozen.freeze(outpoint, reason)
+    }
+
+    /// Unfreezes the outpoint, returning the reason it was frozen for.
+    pub fn unfreeze(&mut self, outpoint: Outpoint) -> Option<String> {
+        self.frozen.unfreeze(outpoint)
+    }
+
+    /// Replaces the set of imported outputs, for instance with the one loaded
+    /// from a file.
+    pub fn set_imported(&mut self, imported: ImportedUtxos) { self.imported = imported; }
+
+    /// Imports output which is not derived from the wallet descriptor, such
+    /// that the payments may spend it together with the RGB state assigned to
+    /// it. The PSBT inputs spending the output are marked as externally
+    /// controlled and must be signed by the output owner.
+    ///
+    /// Returns the previously imported output with the same outpoint, if any.
+    pub fn import_utxo(
+        &mut self,
+        outpoint: Outpoint,
+        value: Sats,
+        script: ScriptPubkey,
+        redeem_info: RedeemInfo,
+    ) -> Option<ImportedUtxo> {
+        self.imported
+            .import(ImportedUtxo::new(outpoint, value, script, redeem_info))
+    }
+
+    /// Removes the imported output, returning it.
+    pub fn remove_imported(&mut self, outpoint: Outpoint) -> Option<ImportedUtxo> {
+        self.imported.remove(outpoint)
+    }
+
+    /// Replaces velocity preferences, for instance with the ones loaded from
+    /// a file.
+    pub fn set_velocity_prefs(&mut self, prefs: VelocityPrefs) {
+        self.velocity_prefs = prefs;
+        self.resolve_velocity_prefs();
+    }
+
+    /// Sets velocity class used for the change of the assignment type of the
+    /// contract, returning the previously set one, if any.
+    pub fn set_velocity_pref(
+        &mut self,
+        contract_id: ContractId,
+        assignment_type: AssignmentType,
+        hint: VelocityHint,
+    ) -> Option<VelocityHint> {
+        self.velocity_prefs.set(contract_id, assignment_type, hint)
+    }
+
+    /// Keeps velocity class requested by the invoice issued by the wallet, such
+    /// that the received state is kept at the outputs of the same class when
+    /// the wallet spends it later. Returns the requested class, if any.
+    ///
+    /// If the contract is not known to the stock yet, the class is kept for
+    /// the name of the assignment and is applied once the contract is
+    /// accepted.
+    pub fn keep_requested_velocity(
+        &mut self,
+        invoice: &RgbInvoice,
+    ) -> Result<Option<VelocityHint>, CompositionError> {
+        let Some(velocity) = RequestedVelocity::from_invoice(invoice)? else {
+            return Ok(None);
+        };
+        let contract_id = invoice.contract.ok_or(CompositionError::NoContract)?;
+        if self.stock.contract_info(contract_id).is_ok() {
+            let assignment_type = invoice_assignment_type(&self.stock, invoice)?;
+            self.velocity_prefs
+                .set(contract_id, assignment_type, velocity.hint());
+        } else {
+            let name = invoice_assignment_name(&self.stock, invoice)?;
+            self.velocity_prefs
+                .set_named(contract_id, name, velocity.hint());
+        }
+        Ok(Some(velocity.hint()))
+    }
+
+    /// Resolves velocity preferences kept for the named assignments of the
+    /// contracts which became known to the stock. Returns whether any of them
+    /// was resolved.
+    pub fn resolve_velocity_prefs(&mut self) -> bool {
+        let stock = &self.stock;
+        self.velocity_prefs
+            .resolve(|contract_id, name| contract_assignment_type(stock, contract_id, name))
+    }
+
+    /// Returns the witness transaction which has paid the invoice, if the
+    /// invoice was paid by the wallet and the transaction is mined or is not
+    /// known to be dropped.
+    pub fn invoice_payment(&self, invoice: &RgbInvoice) -> Option<Txid> {
+        crate::invoice_payment(&self.stock, invoice)
+    }
+
+    fn with_outpoints(&self, mut params: TransferParams) -> TransferParams {
+        params.frozen.extend(self.frozen.outpoints());
+        for utxo in self.imported.iter() {
+            params.imported.import(utxo.clone());
+        }
+        // Preferences given with the transfer parameters take precedence
+        for (contract_id, assignment_type, hint) in self.velocity_prefs.iter() {
+            params
+                .velocity_hints
+                .entry((contract_id, assignment_type))
+                .or_insert(hint);
+        }
+        params
+    }
+
+    /// Removes imported outputs spent by the PSBT.
+    fn spend_imported(&mut self, psbt: &Psbt) {
+        for input in psbt.inputs() {
+            self.imported.remove(input.previous_outpoint);
+        }
+    }
+
+    /// Starts tracking blocks mining witness transactions known to the stock,
+    /// which were not tracked yet.
+    pub fn track_witnesses(&mut self, resolver: &AnyResolver) -> Result<(), ReorgError> {
+        let txids = self
+            .stock
+            .as_stash_provider()
+            .witness_ids()
+            .map_err(|e| e.to_string())?
+            .filter_map(|witness_id| match witness_id {
+                XChain::Bitcoin(txid) => Some(txid),
+                XChain::Liquid(_) | XChain::Other(_) => None,
+            })
+            .filter(|txid| self.reorg_tracker.witness_height(*txid).is_none())
+            .collect::<Vec<_>>();
+        for txid in txids {
+            self.track_witness(txid, resolver)?;
+        }
+        Ok(())
+    }
+
+    fn track_witness(&mut self, txid: Txid, resolver: &AnyResolver) -> Result<(), ReorgError> {
+        let ord = resolver
+            .resolve_pub_witness_ord(XChain::Bitcoin(txid))
+            .map_err(|e| ReorgError::WitnessResolver(txid, e.to_string()))?;
+        self.reorg_tracker.record(txid, ord, resolver)?;
+        if let Some(height) = self.reorg_tracker.witness_height(txid) {
+            self.observers
+                .notify(WalletEvent::WitnessMined(txid, height));
+        }
+        Ok(())
+    }
+
+    /// Updates the status of the witness transactions mined after
+    /// `after_height` using the provided resolver.
+    pub fn update_witnesses(
+        &mut self,
+        resolver: &AnyResolver,
+        after_height: u32,
+    ) -> Result<UpdateRes, SyncError> {
+        let res = self
+            .stock
+            .update_witnesses(resolver, after_height)
+            .map_err(|e| SyncError::Stock(e.to_string()))?;
+        self.check_changes();
+        Ok(res)
+    }
+
+    /// Handles blockchain re-org which might have happened while the chain
+    /// tip has moved from `old_tip` to `new_tip` height.
+    ///
+    /// Witnesses mined in blocks which are not part of the main chain anymore
+    /// are re-resolved, such that they are demoted to tentative or archived
+    /// ones, and the contract state is updated accordingly.
+    ///
+    /// Returns `None` if the tracked witnesses are not affected by the re-org.
+    pub fn handle_reorg(
+        &mut self,
+        old_tip: u32,
+        new_tip: u32,
+        resolver: &AnyResolver,
+    ) -> Result<Option<UpdateRes>, ReorgError> {
+        let mut fork = self.reorg_tracker.detect(new_tip, resolver)?;
+        if new_tip < old_tip {
+            fork = Some(fork.map_or(new_tip + 1, |height| height.min(new_tip + 1)));
+        }
+        let Some(fork) = fork else {
+            return Ok(None);
+        };
+        let res = self
+            .stock
+            .update_witnesses(resolver, fork)
+            .map_err(|e| e.to_string())?;
+        for txid in self.reorg_tracker.rollback(fork) {
+            self.track_witness(txid, resolver)?;
+        }
+        self.check_changes();
+        Ok(Some(res))
+    }
+
+    /// Checks whether an invoice issued by the wallet was paid, i.e. whether
+    /// its beneficiary has received state from any of the consignments
+    /// accepted by the stock.
+    ///
+    /// Witness transactions are checked with the resolver, such that payments
+    /// which are not mined yet are reported as pending confirmation. For
+    /// witness-out beneficiaries the resolver is also used to match witness
+    /// outputs against the invoice address; if no consignment has paid such
+    /// invoice yet, the resolver is subscribed to the invoice address and
+    /// transactions paying to it are reported as
+    /// [`InvoiceStatus::PaymentSeen`].
+    pub fn check_invoice_status(
+        &self,
+        invoice: &RgbInvoice,
+        resolver: &AnyResolver,
+    ) -> Result<InvoiceStatus, InvoiceStatusError> {
+        let stash = self.stock.as_stash_provider();
+        let beneficiary = match invoice.beneficiary.into_inner() {
+            Beneficiary::BlindedSeal(secret) => {
+                let seal = stash
+                    .seal_secret(XChain::Bitcoin(secret))
+                    .map_err(|e| e.to_string())?
+                    .ok_or(InvoiceStatusError::UnknownSeal)?;
+                let outpoint = seal
+                    .map_ref(|seal| seal.outpoint())
+                    .transpose()
+                    .ok_or(InvoiceStatusError::UnknownSeal)?;
+                Ok(XOutpoint::from(outpoint))
+            }
+            Beneficiary::WitnessVout(pay2vout) => Err(pay2vout.address.script_pubkey()),
+        };
+
+        let contract_ids = match invoice.contract {
+            Some(contract_id) => vec![contract_id],
+            None => self
+                .stock
+                .contracts()
+                .map_err(|e| e.to_string())?
+                .map(|info| info.id)
+                .collect(),
+        };
+
+        let mut status = InvoiceStatus::Unpaid;
+        for contract_id in contract_ids {
+            let state = self
+                .stock
+                .contract_state(contract_id)
+                .map_err(|e| e.to_string())?;
+            let assignments = state
+                .rights_all()
+                .map(|a| (a.seal, a.witness))
+                .chain(state.fungible_all().map(|a| (a.seal, a.witness)))
+                .chain(state.data_all().map(|a| (a.seal, a.witness)))
+                .chain(state.attach_all().map(|a| (a.seal, a.witness)))
+                .collect::<Vec<_>>();
+            for (seal, witness) in assignments {
+                let matches = match (&beneficiary, witness) {
+                    (Ok(outpoint), _) => seal.to_outpoint() == *outpoint,
+                    // Witness-out beneficiaries can't be assigned in genesis
+                    (Err(_), None) => false,
+                    (Err(script), Some(witness_id)) => {
+                        let XChain::Bitcoin(seal) = seal else {
+                            continue;
+                        };
+                        match resolver.resolve_pub_witness(witness_id) {
+                            Ok(XChain::Bitcoin(tx)) => tx
+                                .outputs
+                                .get(seal.vout.into_usize())
+                                .is_some_and(|out| out.script_pubkey == *script),
+                            Ok(_) | Err(WitnessResolverError::Unknown(_)) => false,
+                            Err(e) => {
+                                return Err(InvoiceStatusError::WitnessResolver(
+                                    witness_id,
+                                    e.to_string(),
+                                ));
+                            }
+                        }
+                    }
+                };
+                if !matches {
+                    continue;
+                }
+                let seal_status = match witness {
+                    None => InvoiceStatus::Paid,
+                    Some(witness_id) => match resolver.resolve_pub_witness_ord(witness_id) {
+                        Ok(WitnessOrd::Mined(_)) => InvoiceStatus::Paid,
+                        Ok(WitnessOrd::Tentative) => InvoiceStatus::PendingConfirmation,
+                        Ok(WitnessOrd::Archived) | Err(WitnessResolverError::Unknown(_)) => {
+                            InvoiceStatus::Unpaid
+                        }
+                        Err(e) => {
+                            return Err(InvoiceStatusError::WitnessResolver(
+                                witness_id,
+                                e.to_string(),
+                            ));
+                        }
+                    },
+                };
+                status = status.max(seal_status);
+                if status == InvoiceStatus::Paid {
+                    return Ok(status);
+                }
+            }
+        }
+
+        if let (InvoiceStatus::Unpaid, Err(script)) = (status, &beneficiary) {
+            resolver
+                .subscribe_script(script)
+                .map_err(InvoiceStatusError::ScriptResolver)?;
+            let txids = resolver
+                .resolve_script_txids(script)
+                .map_err(InvoiceStatusError::ScriptResolver)?;
+            if !txids.is_empty() {
+                status = InvoiceStatus::PaymentSeen;
+            }
+        }
+        Ok(status)
+    }
+
+    /// Produces proof that the invoice beneficiary belongs to the wallet,
+    /// signing the invoice id with the key controlling the beneficiary
+    /// output.
+    ///
+    /// The proof for a blinded seal reveals the seal, and thus the wallet
+    /// UTXO, to the payer. The proof is not added to the invoice; use
+    /// [`OwnershipProof::set_to_invoice`] for that.
+    pub fn prove_ownership(
+        &mut self,
+        invoice: &RgbInvoice,
+        signer: &impl Signer,
+    ) -> Result<OwnershipProof, OwnershipError> {
+        let (seal, terminal) = match invoice.beneficiary.into_inner() {
+            Beneficiary::BlindedSeal(secret) => {
+                let layer1 = invoice.chain_network().layer1();
+                if layer1 != Layer1::Bitcoin {
+                    return Err(OwnershipError::UnsupportedLayer1(layer1));
+                }
+                let Some(XChain::Bitcoin(seal)) = self
+                    .stock
+                    .as_stash_provider()
+                    .seal_secret(XChain::Bitcoin(secret))
+                    .map_err(|e| OwnershipError::Stash(e.to_string()))?
+                else {
+                    return Err(OwnershipError::UnknownBeneficiary);
+                };
+                let TxPtr::Txid(txid) = seal.txid else {
+                    return Err(OwnershipError::UnknownBeneficiary);
+                };
+                let utxo = self
+                    .wallet
+                    .utxo(Outpoint::new(txid, seal.vout))
+                    .ok_or(OwnershipError::UnknownBeneficiary)?;
+                (Some(seal), utxo.terminal)
+            }
+            Beneficiary::WitnessVout(pay2vout) => {
+                let script = pay2vout.address.script_pubkey();
+                let terminal = self
+                    .wallet
+                    .script_terminal(&script)
+                    .ok_or(OwnershipError::UnknownBeneficiary)?;
+                (None, terminal)
+            }
+        };
+
+        let mut psbt = bip322_psbt(self.wallet.descriptor(), terminal, &invoice_id(invoice));
+        signer.sign_psbt(&mut psbt)?;
+        psbt.finalize(self.wallet.descriptor());
+        OwnershipProof::with_psbt(seal, &psbt)
+    }
+
+    /// Describes RGB state and bitcoins moved by the PSBT, allowing to audit it
+    /// before signing.
+    #[allow(clippy::result_large_err)]
+    pub fn describe_psbt(&self, psbt: &Psbt) -> Result<TransferPreview, PreviewError> {
+        let bundles = psbt.rgb_bundles()?;
+        if bundles.is_empty() {
+            return Err(PreviewError::NoRgbData);
+        }
+
+        let descriptor = self.wallet.descriptor();
+        let network = self.wallet.network();
+        let outputs = psbt
+            .outputs()
+            .map(|output| TxOutPreview {
+                vout: output.vout(),
+                value: output.value(),
+                script: output.script.clone(),
+                address: Address::with(&output.script, network).ok(),
+                is_own: output.terminal_derivation().is_some_and(|terminal| {
+                    (!output.tap_bip32_derivation.is_empty()
+                        && output.tap_bip32_derivation == descriptor.xonly_keyset(terminal))
+                        || (!output.bip32_derivation.is_empty()
+                            && output.bip32_derivation == descriptor.legacy_keyset(terminal))
+                }),
+            })
+            .collect::<Vec<_>>();
+        let is_own = |vout: Vout| {
+            outputs
+                .iter()
+                .any(|output| output.vout == vout && output.is_own)
+        };
+
+        let mut contracts = vec![];
+        for (contract_id, bundles) in bundles {
+            let state = self
+                .stock
+                .contract_state(contract_id)
+                .map_err(|e| e.to_string())?;
+            let mut known = BTreeMap::<Opout, AllocatedState>::new();
+            known.extend(state.rights_all().map(|a| (a.opout, AllocatedState::Void)));
+            known.extend(state.fungible_all().map(|a| (a.opout, a.state.into())));
+            known.extend(state.data_all().map(|a| (a.opout, a.state.clone().into())));
+            known.extend(
+                state
+                    .attach_all()
+                    .map(|a| (a.opout, a.state.clone().into())),
+            );
+
+            let mut spent = BTreeMap::new();
+            let mut assigned = vec![];
+            for bundle in bundles.iter() {
+                for transition in bundle.known_transitions.values() {
+                    for input in &transition.inputs {
+                        spent.insert(input.prev_out, known.get(&input.prev_out).cloned());
+                    }
+                    for (ty, assigns) in transition.assignments.iter() {
+                        let ty = *ty;
+                        match assigns {
+                            TypedAssigns::Declarative(a) => {
+                                assigned.extend(a.iter().map(|a| assignment_preview(ty, a, is_own)))
+                            }
+                            TypedAssigns::Fungible(a) => {
+                                assigned.extend(a.iter().map(|a| assignment_preview(ty, a, is_own)))
+                            }
+                            TypedAssigns::Structured(a) => {
+                                assigned.extend(a.iter().map(|a| assignment_preview(ty, a, is_own)))
+                            }
+                            TypedAssigns::Attachment(a) => {
+                                assigned.extend(a.iter().map(|a| assignment_preview(ty, a, is_own)))
+                            }
+                        }
+                    }
+                }
+            }
+            let amounts =
+                AmountFormatter::with_stock(&self.stock, contract_id).map_err(|e| e.to_string())?;
+            contracts.push(ContractPreview {
+                contract_id,
+                spent,
+                assigned,
+                amounts,
+            });
+        }
+
+        Ok(TransferPreview {
+            txid: psbt.txid(),
+            fee: psbt.fee(),
+            outputs,
+            contracts,
+        })
+    }
+
+    /// Returns the next unused address on the RGB keychain, which may be used
+    /// in address-based invoices.
+    ///
+    /// If `mark_used` is set, the address is marked as used, such that it is
+    /// not returned by subsequent calls even if it hasn't received any funds
+    /// yet. This information is persisted with the wallet data.
+    pub fn next_rgb_address(&mut self, mark_used: bool) -> (Terminal, Address) {
+        let keychain = self
+            .wallet
+            .descriptor()
+            .keychain_layout()
+            .keychain(RgbKeychain::Rgb);
+        let index = self.wallet.next_derivation_index(keychain, mark_used);
+        let terminal = Terminal::new(keychain, index);
+        (terminal, self.terminal_address(terminal))
+    }
+
+    fn terminal_address(&self, terminal: Terminal) -> Address {
+        let script = self
+            .wallet
+            .descriptor()
+            .derive(terminal.keychain, terminal.index)
+            .to_script_pubkey();
+        Address::with(&script, self.wallet.network())
+            .expect("RGB descriptors always produce addresses")
+    }
+
+    /// Returns address for a witness-out invoice expiring at `expiry`, reusing
+    /// an address released by the `reservations` if there is any. Unless the
+    /// invoice never expires, the address is reserved until the expiry.
+    pub fn reserve_rgb_address(
+        &mut self,
+        reservations: &mut AddressReservations,
+        expiry: InvoiceExpiry,
+    ) -> (Terminal, Address) {
+        let (terminal, address) = match reservations.take_released() {
+            Some(terminal) => (terminal, self.terminal_address(terminal)),
+            None => self.next_rgb_address(true),
+        };
+        if !expiry.is_never() {
+            reservations.reserve(terminal, expiry);
+        }
+        (terminal, address)
+    }
+
+    /// Releases the addresses of the witness-out invoices which have expired
+    /// unpaid at the UNIX timestamp `now` and the chain tip height
+    /// `tip_height`, such that new invoices may use them. Addresses which have
+    /// received any transaction known to the resolver stay used.
+    ///
+    /// Returns the released addresses.
+    pub fn release_expired(
+        &self,
+        reservations: &mut AddressReservations,
+        resolver: &AnyResolver,
+        now: i64,
+        tip_height: u32,
+    ) -> Result<Vec<Terminal>, InvoiceStatusError> {
+        let expired = reservations.expired(now, tip_height).collect::<Vec<_>>();
+        let mut released = Vec::with_capacity(expired.len());
+        for terminal in expired {
+            let script = self
+                .wallet
+                .descriptor()
+                .derive(terminal.keychain, terminal.index)
+                .to_script_pubkey();
+            let txids = resolver
+                .resolve_script_txids(&script)
+                .map_err(InvoiceStatusError::ScriptResolver)?;
+            if txids.is_empty() {
+                reservations.release(terminal);
+                released.push(terminal);
+            } else {
+                reservations.remove(terminal);
+            }
+        }
+        Ok(released)
+    }
+
+    /// Marks address with the given terminal derivation, and all preceding
+    /// addresses of the same keychain, as used, such that they are not
+    /// returned by [`Self::next_rgb_address`] anymore.
+    pub fn mark_used(&mut self, terminal: Terminal) {
+        while self.wallet.next_derivation_index(terminal.keychain, false) <= terminal.index {
+            self.wallet.next_derivation_index(terminal.keychain, true);
+        }
+    }
+
     pub fn history(
         &self,
         contract_id: ContractId,
@@ -134,13 +978,397 @@ where W::Descr: DescriptorRgb<K>
         Ok(contract.history(wallet.filter_outpoints(), wallet.filter_witnesses()))
     }
 
+    /// Computes fungible balance of the contract owned by the wallet, using
+    /// the witness status known to the stock and the current chain tip height.
+    ///
+    /// Mined allocations are counted as confirmed once they have at least
+    /// `min_confirmations` confirmations. Allocations assigned by archived
+    /// witness transactions are not counted.
+    pub fn balance(
+        &self,
+        contract_id: ContractId,
+        tip_height: u32,
+        min_confirmations: u32,
+    ) -> Result<BalanceReport, StockError<S, H, P>> {
+        let state = self.stock.contract_state(contract_id)?;
+        let filter_unspent = self.wallet.filter_unspent();
+        let filter_witnesses = self.wallet.filter_witnesses();
+        let mut report = BalanceReport::default();
+        for allocation in state.fungible_all() {
+            if !filter_unspent.should_include(allocation.seal, allocation.witness) {
+                continue;
+            }
+            let bucket = match allocation.witness {
+                None => &mut report.confirmed,
+                Some(witness_id) => match state.witness_ord(witness_id) {
+                    Some(WitnessOrd::Mined(pos)) => {
+                        let confirmations = (tip_height + 1).saturating_sub(pos.height().get());
+                        if confirmations >= min_confirmations {
+                            &mut report.confirmed
+                        } else {
+                            &mut report.immature
+                        }
+                    }
+                    Some(WitnessOrd::Tentative) => {
+                        if filter_witnesses.should_include(allocation.seal, Some(witness_id)) {
+                            &mut report.tentative
+                        } else {
+                            &mut report.unconfirmed_incoming
+                        }
+                    }
+                    Some(WitnessOrd::Archived) | None => continue,
+                },
+            };
+            *bucket += Amount::from(allocation.state);
+        }
+        Ok(report)
+    }
+
+    /// Checks the witness status of the contract allocations known to the
+    /// stock against the resolver, flagging the allocations which witness
+    /// transactions were replaced, evicted from the mempool or re-orged out.
+    ///
+    /// The check covers all allocations of the contract known to the stock,
+    /// including the ones assigned to other parties by the wallet transfers.
+    /// Allocations flagged as [`StaleReason::Evicted`] or
+    /// [`StaleReason::Unmined`] are counted by [`Self::balance`] in a wrong
+    /// bucket until the next witness update.
+    pub fn check_witnesses(
+        &self,
+        contract_id: ContractId,
+        resolver: &impl ResolveWitness,
+    ) -> Result<Vec<StaleAllocation>, WitnessCheckError> {
+        let state = self
+            .stock
+            .contract_state(contract_id)
+            .map_err(|e| e.to_string())?;
+        let allocations = state
+            .rights_all()
+            .map(|a| (a.opout, a.seal, a.witness, None))
+            .chain(
+                state
+                    .fungible_all()
+                    .map(|a| (a.opout, a.seal, a.witness, Some(Amount::from(a.state)))),
+            )
+            .chain(state.data_all().map(|a| (a.opout, a.seal, a.witness, None)))
+            .chain(
+                state
+                    .attach_all()
+                    .map(|a| (a.opout, a.seal, a.witness, None)),
+            )
+            .collect::<Vec<_>>();
+
+        let mut resolved = BTreeMap::new();
+        let mut stale = vec![];
+        for (opout, seal, witness, amount) in allocations {
+            let Some(witness) = witness else {
+                continue;
+            };
+            let reason = match state.witness_ord(witness) {
+                Some(WitnessOrd::Archived) => Some(StaleReason::Archived),
+                None => continue,
+                Some(ord) => {
+                    let current = match resolved.get(&witness) {
+                        Some(current) => *current,
+                        None => {
+                            let current = match resolver.resolve_pub_witness_ord(witness) {
+                                Ok(current) => current,
+                                Err(WitnessResolverError::Unknown(_)) => WitnessOrd::Archived,
+                                Err(e) => {
+                                    return Err(WitnessCheckError::WitnessResolver(
+                                        witness,
+                                        e.to_string(),
+                                    ));
+                                }
+                            };
+                            resolved.insert(witness, current);
+                            current
+                        }
+                    };
+                    match (ord, current) {
+                        (_, WitnessOrd::Archived) => Some(StaleReason::Evicted),
+                        (WitnessOrd::Mined(_), WitnessOrd::Tentative) => Some(StaleReason::Unmined),
+                        _ => None,
+                    }
+                }
+            };
+            if let Some(reason) = reason {
+                stale.push(StaleAllocation {
+                    opout,
+                    seal,
+                    witness,
+                    amount,
+                    reason,
+                });
+            }
+        }
+        Ok(stale)
+    }
+
+    /// Constructs formatter of the fungible amounts of the contract, using its
+    /// precision and ticker.
+    pub fn amount_formatter(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<AmountFormatter, StockError<S, H, P>> {
+        AmountFormatter::with_stock(&self.stock, contract_id)
+    }
+
+    /// Prepares exporter of the contract operation history, using the
+    /// contract precision for the fungible amounts.
+    pub fn history_exporter(
+        &self,
+        contract_id: ContractId,
+        iface: impl Into<IfaceRef>,
+    ) -> Result<HistoryExporter, StockError<S, H, P, ContractIfaceError>> {
+        let contract = self.stock.contract_iface(contract_id, iface.into())?;
+        let wallet = &self.wallet;
+        let ops = contract.history(wallet.filter_outpoints(), wallet.filter_witnesses());
+        Ok(HistoryExporter::with_contract(ops, &contract))
+    }
+
+    /// Returns history of the contract where the operations committed to by
+    /// the same witness transaction are grouped into a single transfer.
+    pub fn transfer_history(
+        &self,
+        contract_id: ContractId,
+        iface: impl Into<IfaceRef>,
+    ) -> Result<Vec<TransferRecord>, StockError<S, H, P, ContractIfaceError>> {
+        Ok(TransferRecord::group(self.history(contract_id, iface)?))
+    }
+
     #[allow(clippy::result_large_err)]
     pub fn pay(
         &mut self,
         invoice: &RgbInvoice,
         params: TransferParams,
     ) -> Result<(Psbt, PsbtMeta, Transfer), PayError> {
-        self.wallet.pay(&mut self.stock, invoice, params)
+        let params = self.with_outpoints(params);
+        let tweaks = self.tapret_tweaks();
+        let res = self.wallet.pay(&mut self.stock, invoice, params);
+        if let Ok((psbt, ..)) = &res {
+            self.spend_imported(psbt);
+        }
+        self.backup_tweaks(&tweaks);
+        self.check_changes();
+        res
+    }
+
+    /// Pays all legs of the basket invoice within a single transaction,
+    /// returning transfer consignments for each of the legs.
+    #[allow(clippy::result_large_err)]
+    pub fn pay_basket(
+        &mut self,
+        basket: &BasketInvoice,
+        params: TransferParams,
+    ) -> Result<(Psbt, Vec<PsbtMeta>, Vec<Transfer>), PayError> {
+        let params = self.with_outpoints(params);
+        let tweaks = self.tapret_tweaks();
+        let res = self.wallet.pay_basket(&mut self.stock, basket, params);
+        if let Ok((psbt, ..)) = &res {
+            self.spend_imported(psbt);
+        }
+        self.backup_tweaks(&tweaks);
+        self.check_changes();
+        res
+    }
+
+    /// Pays each of the invoices with a separate transaction, returning PSBTs
+    /// and transfer consignments for each of them. The transactions spend
+    /// different outputs and may be published all at once.
+    ///
+    /// Useful for recurring payments, which invoices are produced by
+    /// [`crate::PaymentTemplate::instantiate`].
+    #[allow(clippy::result_large_err)]
+    pub fn pay_batch(
+        &mut self,
+        invoices: &[RgbInvoice],
+        params: TransferParams,
+    ) -> Result<Vec<(Psbt, PsbtMeta, Transfer)>, PayError> {
+        let params = self.with_outpoints(params);
+        let tweaks = self.tapret_tweaks();
+        let res = self.wallet.pay_batch(&mut self.stock, invoices, params);
+        for (psbt, ..) in res.iter().flatten() {
+            self.spend_imported(psbt);
+        }
+        self.backup_tweaks(&tweaks);
+        self.check_changes();
+        res
+    }
+
+    /// Issues additional amount of the contract asset, spending the issuance
+    /// rights owned by the wallet. The issued amount is assigned to `seal` or,
+    /// if no seal is provided, to the change output of the witness
+    /// transaction.
+    ///
+    /// Returns PSBT which must be signed and published to complete the
+    /// issuance.
+    #[allow(clippy::result_large_err)]
+    pub fn issue_more(
+        &mut self,
+        contract_id: ContractId,
+        amount: Amount,
+        seal: Option<Outpoint>,
+        params: TxParams,
+    ) -> Result<(Psbt, PsbtMeta), PayError> {
+        self.supply(&SupplyOperation::issue(contract_id, amount, seal), params)
+    }
+
+    /// Burns contract allocations owned by the wallet.
+    ///
+    /// Returns PSBT which must be signed and published to complete the burn.
+    #[allow(clippy::result_large_err)]
+    pub fn burn(
+        &mut self,
+        contract_id: ContractId,
+        allocations: impl IntoIterator<Item = Opout>,
+        params: TxParams,
+    ) -> Result<(Psbt, PsbtMeta), PayError> {
+        self.supply(&SupplyOperation::burn(contract_id, allocations), params)
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn supply(
+        &mut self,
+        operation: &SupplyOperation,
+        params: TxParams,
+    ) -> Result<(Psbt, PsbtMeta), PayError> {
+        let (psbt, meta) = self
+            .wallet
+            .construct_psbt_supply(&self.stock, operation, params)?;
+        self.complete_operation(psbt, meta)
+    }
+
+    /// Calls a contract state transition composed from its YAML definition.
+    ///
+    /// Returns PSBT which must be signed and published to complete the call.
+    #[cfg(feature = "serde")]
+    #[allow(clippy::result_large_err)]
+    pub fn call(
+        &mut self,
+        call: &ContractCall,
+        params: TxParams,
+    ) -> Result<(Psbt, PsbtMeta), PayError> {
+        let (psbt, meta) = self.wallet.construct_psbt_call(&self.stock, call, params)?;
+        self.complete_operation(psbt, meta)
+    }
+
+    /// Bumps the fee of the incoming witness transaction with CPFP, such that
+    /// the package of the transaction and its child spending the wallet output
+    /// `outpoint` reaches the `feerate` (in sats per virtual byte).
+    ///
+    /// The parent transaction is taken from the stock or, if the stock knows
+    /// only its id, from the resolver, which is also used to compute the
+    /// parent fee. The RGB state assigned to the spent output is moved to the
+    /// change output of the child.
+    ///
+    /// Returns the parent transaction together with PSBT of the child, which
+    /// must be signed and broadcasted together with the parent as a package
+    /// using [`AnyResolver::broadcast_package`].
+    #[allow(clippy::result_large_err)]
+    pub fn bump_incoming(
+        &mut self,
+        outpoint: Outpoint,
+        feerate: u64,
+        resolver: &impl ResolveWitness,
+        params: TxParams,
+    ) -> Result<(Psbt, PsbtMeta, Tx), PayError> {
+        let witness_id = XChain::Bitcoin(outpoint.txid);
+        let parent = match self.stock.as_stash_provider().witness(witness_id) {
+            Ok(SealWitness {
+                public: XChain::Bitcoin(PubWitness::Tx(tx)),
+                ..
+            }) => tx.clone(),
+            _ => resolve_bitcoin_tx(resolver, outpoint.txid).map_err(CompositionError::from)?,
+        };
+        let parent_fee = witness_fee(&parent, resolver).map_err(CompositionError::from)?;
+        let (psbt, meta) = self.wallet.construct_psbt_bump(
+            &self.stock,
+            &parent,
+            parent_fee,
+            outpoint.vout,
+            feerate,
+            params,
+            &self.frozen.outpoints(),
+        )?;
+        if psbt.rgb_contract_ids().unwrap_or_default().is_empty() {
+            return Ok((psbt, meta, parent));
+        }
+        let (psbt, meta) = self.complete_operation(psbt, meta)?;
+        Ok((psbt, meta, parent))
+    }
+
+    /// Consolidates the RGB state fragmented across the wallet outputs
+    /// selected by the `scope` into a single new change output, paying the
+    /// fee at the `feerate` (in sats per virtual byte). Frozen outputs are
+    /// never spent.
+    ///
+    /// Returns PSBT which must be signed and published to complete the
+    /// consolidation, together with its report.
+    #[allow(clippy::result_large_err)]
+    pub fn consolidate(
+        &mut self,
+        scope: &ConsolidationScope,
+        feerate: u64,
+        params: TxParams,
+    ) -> Result<(Psbt, PsbtMeta, ConsolidationReport), PayError> {
+        let (psbt, meta, report) = self.wallet.construct_psbt_consolidate(
+            &self.stock,
+            scope,
+            feerate,
+            params,
+            &self.frozen.outpoints(),
+        )?;
+        let (psbt, meta) = self.complete_operation(psbt, meta)?;
+        Ok((psbt, meta, report))
+    }
+
+    /// Reports what [`Self::consolidate`] would do without modifying the
+    /// stock or advancing the wallet derivation indexes.
+    #[allow(clippy::result_large_err)]
+    pub fn plan_consolidation(
+        &mut self,
+        scope: &ConsolidationScope,
+        feerate: u64,
+        mut params: TxParams,
+    ) -> Result<ConsolidationReport, CompositionError> {
+        params.change_shift = false;
+        let (_, _, report) = self.wallet.construct_psbt_consolidate(
+            &self.stock,
+            scope,
+            feerate,
+            params,
+            &self.frozen.outpoints(),
+        )?;
+        Ok(report)
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn complete_operation(
+        &mut self,
+        mut psbt: Psbt,
+        meta: PsbtMeta,
+    ) -> Result<(Psbt, PsbtMeta), PayError> {
+        let tweaks = self.tapret_tweaks();
+        let res = self.wallet.complete_supply(&mut self.stock, &mut psbt);
+        self.backup_tweaks(&tweaks);
+        self.check_changes();
+        match res {
+            Ok(()) => Ok((psbt, meta)),
+            Err(e) => Err(PayError::Completion(e, psbt)),
+        }
+    }
+
+    /// Plans the transfer paying the invoice without constructing the
+    /// transaction or modifying the stock.
+    #[allow(clippy::result_large_err)]
+    pub fn plan_transfer(
+        &self,
+        invoice: &RgbInvoice,
+        params: TransferParams,
+    ) -> Result<TransferPlan, CompositionError> {
+        self.wallet
+            .plan_transfer(&self.stock, invoice, self.with_outpoints(params))
     }
 
     #[allow(clippy::result_large_err)]
@@ -149,15 +1377,730 @@ where W::Descr: DescriptorRgb<K>
         invoice: &RgbInvoice,
         params: TransferParams,
     ) -> Result<(Psbt, PsbtMeta), CompositionError> {
+        let params = self.with_outpoints(params);
         self.wallet.construct_psbt_rgb(&self.stock, invoice, params)
     }
 
+    /// Embeds the state transitions paying the invoice into a PSBT v2
+    /// constructed by another wallet; see [`WalletProvider::update_psbt_rgb`].
+    #[allow(clippy::result_large_err)]
+    pub fn update_psbt(
+        &mut self,
+        psbt: &mut Psbt,
+        invoice: &RgbInvoice,
+        change_vout: Option<Vout>,
+        amount: Option<Amount>,
+    ) -> Result<BTreeSet<XOutputSeal>, CompositionError> {
+        self.wallet
+            .update_psbt_rgb(&self.stock, psbt, invoice, change_vout, amount)
+    }
+
     #[allow(clippy::result_large_err)]
     pub fn transfer(
         &mut self,
         invoice: &RgbInvoice,
         psbt: &mut Psbt,
     ) -> Result<Transfer, CompletionError> {
-        self.wallet.transfer(&mut self.stock, invoice, psbt)
+        let tweaks = self.tapret_tweaks();
+        let res = self.wallet.transfer(&mut self.stock, invoice, psbt);
+        self.backup_tweaks(&tweaks);
+        self.check_changes();
+        res
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn transfer_with_fascia(
+        &mut self,
+        invoice: &RgbInvoice,
+        psbt: &mut Psbt,
+        fascia: Fascia,
+    ) -> Result<Transfer, CompletionError> {
+        let tweaks = self.tapret_tweaks();
+        let res = self
+            .wallet
+            .transfer_with_fascia(&mut self.stock, invoice, psbt, fascia);
+        self.backup_tweaks(&tweaks);
+        self.check_changes();
+        res
+    }
+
+    /// Validates transfer consignment and accepts it into the stock.
+    ///
+    /// Consignments for contracts issued on a network other than the wallet
+    /// one are rejected with [`AcceptError::NetworkMismatch`].
+    #[allow(clippy::result_large_err)]
+    pub fn accept_transfer(
+        &mut self,
+        transfer: Transfer,
+        resolver: &AnyResolver,
+        is_testnet: bool,
+    ) -> Result<validation::Status, AcceptError> {
+        let contract_id = transfer.contract_id();
+        NetworkGuard::new(self.wallet.network())
+            .check_genesis(&transfer.genesis)
+            .finish()?;
+        let seals = self.transfer_seal_terminals(&transfer);
+        let valid = transfer
+            .validate(resolver, is_testnet)
+            .map_err(|(status, _)| status)?;
+        let status = self
+            .stock
+            .accept_transfer(valid, resolver)
+            .map_err(|e| AcceptError::Stock(e.to_string()))?;
+        self.observers
+            .notify(WalletEvent::TransferAccepted(contract_id));
+        self.resolve_velocity_prefs();
+        self.detect_keychain_layout(seals);
+        self.check_changes();
+        Ok(status)
+    }
+
+    /// Finds derivation terminals of the wallet outputs which are used as
+    /// seals by the transfer, together with the seal close methods.
+    fn transfer_seal_terminals(&mut self, transfer: &Transfer) -> Vec<(CloseMethod, Terminal)> {
+        let mut seals = Vec::new();
+        // Seals on layers 1 other than bitcoin can't be defined over the wallet
+        // outputs and are skipped
+        for secret in transfer.terminals.values() {
+            let Ok(Some(XChain::Bitcoin(seal))) =
+                self.stock.as_stash_provider().seal_secret(*secret)
+            else {
+                continue;
+            };
+            let TxPtr::Txid(txid) = seal.txid else {
+                continue;
+            };
+            if let Some(utxo) = self.wallet.utxo(Outpoint::new(txid, seal.vout)) {
+                seals.push((seal.method, utxo.terminal));
+            }
+        }
+        for bw in &transfer.bundles {
+            let XChain::Bitcoin(pub_witness) = &bw.pub_witness else {
+                continue;
+            };
+            let Some(tx) = pub_witness.tx() else {
+                continue;
+            };
+            for assigns in bw.known_transitions().flat_map(|t| t.assignments.values()) {
+                for no in 0..=u16::MAX {
+                    let seal = match assigns.revealed_seal_at(no) {
+                        Ok(Some(XChain::Bitcoin(seal))) => seal,
+                        Ok(_) => continue,
+                        Err(_) => break,
+                    };
+                    let script = match seal.txid {
+                        TxPtr::WitnessTx => tx
+                            .outputs
+                            .get(seal.vout.to_usize())
+                            .map(|txout| txout.script_pubkey.clone()),
+                        TxPtr::Txid(txid) => {
+                            self.wallet
+                                .utxo(Outpoint::new(txid, seal.vout))
+                                .map(|utxo| {
+                                    self.wallet
+                                        .descriptor()
+                                        .derive(utxo.terminal.keychain, utxo.terminal.index)
+                                        .to_script_pubkey()
+                                })
+                        }
+                    };
+                    if let Some(terminal) =
+                        script.and_then(|script| self.wallet.script_terminal(&script))
+                    {
+                        seals.push((seal.method, terminal));
+                    }
+                }
+            }
+        }
+        seals
+    }
+
+    /// Adopts keychains hosting RGB seals of the wallet into the wallet
+    /// keychain layout, if they are not yet used for the RGB seals.
+    ///
+    /// Wallets migrated from other RGB implementations may receive state on
+    /// keychains different from the ones of the wallet layout; without the
+    /// detection such outputs would be spent as bitcoin-only coins. Seals on
+    /// the external and internal keychains are never adopted.
+    fn detect_keychain_layout(&mut self, seals: Vec<(CloseMethod, Terminal)>) {
+        let initial = self.wallet.descriptor().keychain_layout();
+        let mut layout = initial;
+        for (method, terminal) in seals {
+            let keychain = terminal.keychain;
+            if layout.contains_rgb(keychain)
+                || keychain == layout.keychain(RgbKeychain::External)
+                || keychain == layout.keychain(RgbKeychain::Internal)
+            {
+                continue;
+            }
+            match RgbKeychain::for_method(method) {
+                RgbKeychain::Tapret => layout.tapret = keychain.into_inner(),
+                _ => layout.rgb = keychain.into_inner(),
+            }
+        }
+        if layout == initial {
+            return;
+        }
+        // Descriptors with a fixed keychain layout can't track state on other
+        // keychains, which is left undetected then.
+        if self
+            .wallet
+            .with_descriptor_mut(|descr| {
+                descr.with_descriptor_mut(|d| d.set_keychain_layout(layout))
+            })
+            .is_err()
+        {
+            return;
+        }
+        self.observers
+            .notify(WalletEvent::KeychainLayoutDetected(layout));
+    }
+
+    /// Registers callback which is called on each change of the wallet or
+    /// contract state.
+    ///
+    /// Changes made by the wallet methods are reported automatically; changes
+    /// made outside of the wallet - like syncing the wallet with the indexer
+    /// or modifying the stock via [`Self::stock_mut`] - are reported by
+    /// [`Self::check_changes`].
+    pub fn subscribe(&mut self, hook: impl FnMut(&WalletEvent) + Send + 'static) {
+        self.observers.subscribe(Box::new(hook));
+        self.check_changes();
+    }
+
+    /// Subscribes to the wallet events like [`Self::subscribe`], delivering
+    /// them via a channel.
+    pub fn subscribe_channel(&mut self) -> mpsc::Receiver<WalletEvent> {
+        let receiver = self.observers.subscribe_channel();
+        self.check_changes();
+        receiver
+    }
+
+    /// Compares the wallet and contract state with the one known from the
+    /// previous check, notifying the subscribers about the changes.
+    pub fn check_changes(&mut self) {
+        if self.observers.is_empty() {
+            return;
+        }
+        let utxos = self.wallet.utxos().collect::<BTreeSet<_>>();
+        let outpoints = utxos
+            .iter()
+            .copied()
+            .map(XChain::Bitcoin)
+            .collect::<Vec<_>>();
+        let mut allocations = BTreeSet::new();
+        let contract_ids = self
+            .stock
+            .contracts()
+            .map(|list| list.map(|info| info.id).collect::<Vec<_>>())
+            .unwrap_or_default();
+        for contract_id in contract_ids {
+            let Ok(assignments) = self
+                .stock
+                .contract_assignments_for(contract_id, outpoints.iter().copied())
+            else {
+                continue;
+            };
+            allocations.extend(assignments.into_values().flat_map(|map| map.into_keys()));
+        }
+        self.observers.update(StateSnapshot { utxos, allocations });
+    }
+
+    /// Sets a hook which is called each time new tapret tweaks are added to
+    /// the wallet descriptor by a transfer, providing the added tweaks.
+    pub fn set_tweaks_backup(&mut self, hook: impl FnMut(&TapretTweaks) + Send + 'static) {
+        self.tweaks_backup = Some(Box::new(hook));
+    }
+
+    fn backup_tweaks(&mut self, known: &TapretTweaks) {
+        let Some(hook) = &mut self.tweaks_backup else {
+            return;
+        };
+        let added = self.wallet.descriptor().tapret_tweaks().difference(known);
+        if !added.is_empty() {
+            hook(&added);
+        }
+    }
+
+    /// Returns all tapret tweaks known to the wallet descriptor.
+    pub fn tapret_tweaks(&self) -> TapretTweaks { self.wallet.descriptor().tapret_tweaks() }
+
+    /// Imports tapret tweaks from a backup, skipping the ones already known to
+    /// the wallet descriptor.
+    ///
+    /// Returns the tweaks which were added to the descriptor.
+    pub fn import_tapret_tweaks(
+        &mut self,
+        tweaks: &TapretTweaks,
+    ) -> Result<TapretTweaks, TapTweakAlreadyAssigned> {
+        let known = self.tapret_tweaks();
+        if let Some((terminal, _)) = tweaks
+            .iter()
+            .find(|(terminal, tweak)| known.get(terminal).is_some_and(|t| t != *tweak))
+        {
+            return Err(TapTweakAlreadyAssigned(*terminal));
+        }
+        let added = tweaks.difference(&known);
+        for (terminal, tweak) in added.iter() {
+            self.wallet.with_descriptor_mut(|descr| {
+                descr.with_descriptor_mut(|d| d.add_tapret_tweak(*terminal, tweak.clone()))
+            })?;
+        }
+        Ok(added)
+    }
+
+    /// Recovers tapret tweaks missing from the wallet descriptor using the
+    /// tapret commitments from the consignments accepted by the stock.
+    ///
+    /// For each witness transaction of the wallet the tapret keychain is
+    /// scanned up to [`TAPRET_RECOVERY_GAP`] indexes past the last used one,
+    /// looking for an output matching the tweaked key.
+    ///
+    /// Returns the tweaks which were added to the descriptor.
+    pub fn recover_tapret_tweaks(
+        &mut self,
+    ) -> Result<TapretTweaks, <S as StashReadProvider>::Error> {
+        let taprets = self
+            .stock
+            .as_stash_provider()
+            .taprets()?
+            .collect::<Vec<_>>();
+        let outputs = self
+            .wallet
+            .history()
+            .map(|row| {
+                let scripts = row
+                    .counterparties
+                    .into_iter()
+                    .filter_map(|(party, _)| match party {
+                        Counterparty::Miner => None,
+                        Counterparty::Address(addr) => Some(addr.script_pubkey()),
+                        Counterparty::Unknown(script) => Some(script),
+                    })
+                    .collect::<Vec<_>>();
+                (XChain::Bitcoin(row.txid), scripts)
+            })
+            .collect::<Vec<_>>();
+
+        let keychain = self
+            .wallet
+            .descriptor()
+            .keychain_layout()
+            .keychain(RgbKeychain::Tapret);
+        let end = self
+            .wallet
+            .next_derivation_index(keychain, false)
+            .saturating_add(TAPRET_RECOVERY_GAP);
+        let known = self.tapret_tweaks();
+        let mut recovered = TapretTweaks::default();
+        for (witness_id, tweak) in taprets {
+            let Some((_, scripts)) = outputs.iter().find(|(id, _)| *id == witness_id) else {
+                continue;
+            };
+            for index in 0..end.index() {
+                let index = NormalIndex::try_from_index(index).expect("index below normal one");
+                let terminal = Terminal::new(keychain, index);
+                if known.contains_key(&terminal) || recovered.contains_key(&terminal) {
+                    continue;
+                }
+                let Some(script) = self.wallet.descriptor().tapret_script(terminal, &tweak) else {
+                    break;
+                };
+                if scripts.contains(&script) {
+                    recovered.insert(terminal, tweak);
+                    break;
+                }
+            }
+        }
+
+        for (terminal, tweak) in recovered.iter() {
+            self.wallet
+                .with_descriptor_mut(|descr| {
+                    descr.with_descriptor_mut(|d| d.add_tapret_tweak(*terminal, tweak.clone()))
+                })
+                .expect("tweak is checked to be absent");
+        }
+        Ok(recovered)
+    }
+
+    /// Proposes an atomic swap by constructing a PSBT paying the `offer`
+    /// invoice, issued by the counterparty, in exchange for the counterparty
+    /// paying the `request` invoice.
+    #[allow(clippy::result_large_err)]
+    pub fn propose_swap(
+        &mut self,
+        offer: RgbInvoice,
+        request: RgbInvoice,
+        params: TransferParams,
+    ) -> Result<(SwapProposal, PsbtMeta), SwapError> {
+        let params = self.with_outpoints(params);
+        let mut psbt = Psbt::create(PsbtVer::V2);
+        let meta = self
+            .wallet
+            .extend_psbt_rgb(&self.stock, &mut psbt, &offer, params)?;
+        Ok((SwapProposal::new(psbt, offer, request), meta))
+    }
+
+    /// Accepts an atomic swap proposal by paying its `request` invoice within
+    /// the same PSBT and committing to the RGB data of both swap legs.
+    ///
+    /// Returns transfer consignment for the party which has proposed the swap.
+    #[allow(clippy::result_large_err)]
+    pub fn accept_swap(
+        &mut self,
+        proposal: &mut SwapProposal,
+        params: TransferParams,
+    ) -> Result<(PsbtMeta, Transfer), SwapError> {
+        if proposal.is_accepted() {
+            return Err(SwapError::AlreadyAccepted);
+        }
+        let request = proposal.request().clone();
+        let params = self.with_outpoints(params);
+        let meta =
+            self.wallet
+                .extend_psbt_rgb(&self.stock, proposal.psbt_mut(), &request, params)?;
+        proposal.check_legs()?;
+
+        let psbt = proposal.psbt_mut();
+        psbt.complete_construction();
+        fill_tap_outputs(psbt, self.wallet.descriptor());
+        let fascia = psbt.rgb_commit()?;
+
+        let fascia = own_fascia(proposal.psbt(), fascia, &self.wallet)?;
+        let transfer = self.transfer_with_fascia(&request, proposal.psbt_mut(), fascia)?;
+        Ok((meta, transfer))
+    }
+
+    /// Finalizes an atomic swap accepted by the counterparty, ensuring that
+    /// both swap legs are committed in the same anchor and that the state
+    /// transitions of the counterparty pay the `request` invoice.
+    ///
+    /// Returns transfer consignment for the party which has accepted the swap.
+    #[allow(clippy::result_large_err)]
+    pub fn finalize_swap(&mut self, proposal: &mut SwapProposal) -> Result<Transfer, SwapError> {
+        let fascia = proposal.fascia()?;
+        let assignment_type = invoice_assignment_type(&self.stock, proposal.request()).ok();
+        proposal.check_request(&fascia, assignment_type)?;
+        let fascia = own_fascia(proposal.psbt(), fascia, &self.wallet)?;
+        let offer = proposal.offer().clone();
+        let transfer = self.transfer_with_fascia(&offer, proposal.psbt_mut(), fascia)?;
+        Ok(transfer)
+    }
+
+    /// Proposes to sell RGB assets for sats by constructing a PSBT paying the
+    /// buyer `invoice` and receiving the `price` to a new wallet address.
+    #[allow(clippy::result_large_err)]
+    pub fn propose_sale(
+        &mut self,
+        invoice: RgbInvoice,
+        price: Sats,
+        params: TransferParams,
+    ) -> Result<(SaleProposal, SwapMeta), SwapError> {
+        let params = self.with_outpoints(params);
+        let mut psbt = Psbt::create(PsbtVer::V2);
+        let meta = self
+            .wallet
+            .extend_psbt_rgb(&self.stock, &mut psbt, &invoice, params)?;
+        let keychain = self
+            .wallet
+            .descriptor()
+            .keychain_layout()
+            .keychain(RgbKeychain::External);
+        let index = self.wallet.next_derivation_index(keychain, true);
+        let terminal = Terminal::new(keychain, index);
+        let price_vout = psbt
+            .construct_change_expect(self.wallet.descriptor(), terminal, price)
+            .vout();
+        let proposal = SaleProposal::new(psbt, invoice, price_vout, price);
+        let meta = proposal.meta(meta);
+        Ok((proposal, meta))
+    }
+
+    /// Funds sale proposal with the wallet inputs which do not hold any RGB
+    /// state, paying the price, and commits to the RGB data of the seller.
+    ///
+    /// The buyer must sign the transaction only after validating the transfer
+    /// consignment received from the seller.
+    #[allow(clippy::result_large_err)]
+    pub fn fund_purchase(
+        &mut self,
+        proposal: &mut SaleProposal,
+        params: TxParams,
+    ) -> Result<SwapMeta, SwapError> {
+        if proposal.is_funded() {
+            return Err(SwapError::AlreadyAccepted);
+        }
+        let price = proposal.price();
+        let frozen = self.frozen.outpoints();
+        let meta = self.wallet.extend_psbt_sats(
+            &self.stock,
+            proposal.psbt_mut(),
+            price,
+            params,
+            &frozen,
+        )?;
+        let psbt = proposal.psbt_mut();
+        psbt.complete_construction();
+        fill_tap_outputs(psbt, self.wallet.descriptor());
+        psbt.rgb_commit()?;
+        Ok(proposal.meta(meta))
+    }
+
+    /// Finalizes sale proposal funded by the buyer, checking that the price is
+    /// paid to the wallet, and creates transfer consignment for the buyer.
+    #[allow(clippy::result_large_err)]
+    pub fn finalize_sale(&mut self, proposal: &mut SaleProposal) -> Result<Transfer, SwapError> {
+        proposal.check_price(&self.wallet)?;
+        let fascia = proposal.fascia()?;
+        let fascia = own_fascia(proposal.psbt(), fascia, &self.wallet)?;
+        let invoice = proposal.invoice().clone();
+        let transfer = self.transfer_with_fascia(&invoice, proposal.psbt_mut(), fascia)?;
+        Ok(transfer)
+    }
+
+    /// Proposes to pay the `invoice` with a payjoin transfer, constructing a
+    /// PSBT which the receiver may extend with its own inputs. The RGB data are
+    /// embedded into the PSBT, but are not committed to.
+    ///
+    /// The invoice must specify the receiver payjoin endpoint.
+    #[allow(clippy::result_large_err)]
+    pub fn propose_payjoin(
+        &mut self,
+        invoice: RgbInvoice,
+        params: TransferParams,
+    ) -> Result<(PayjoinProposal, PsbtMeta), PayjoinError> {
+        if PayjoinEndpoint::from_invoice(&invoice)?.is_none() {
+            return Err(PayjoinError::NoEndpoint);
+        }
+        let params = self.with_outpoints(params);
+        let mut psbt = Psbt::create(PsbtVer::V2);
+        let meta = self
+            .wallet
+            .extend_psbt_rgb(&self.stock, &mut psbt, &invoice, params)?;
+        Ok((PayjoinProposal::new(psbt, invoice), meta))
+    }
+
+    /// Contributes to a payjoin proposal paying an invoice issued by the wallet
+    /// with a wallet coin which doesn't hold any RGB state, receiving its value
+    /// back. Returns the contributed outpoint.
+    ///
+    /// The contributed input must be signed only after the payer commits to
+    /// the RGB data and the transfer consignment received from the payer is
+    /// validated.
+    #[allow(clippy::result_large_err)]
+    pub fn contribute_payjoin(
+        &mut self,
+        proposal: &mut PayjoinProposal,
+    ) -> Result<Outpoint, PayjoinError> {
+        proposal.check_payment()?;
+        let invoice = proposal.invoice().clone();
+        let frozen = self.frozen.outpoints();
+        self.wallet
+            .extend_psbt_payjoin(&self.stock, proposal.psbt_mut(), &invoice, &frozen)?
+            .ok_or(PayjoinError::NoCoins)
+    }
+
+    /// Finalizes the payjoin transfer, checking the `payjoin` PSBT returned by
+    /// the receiver for the `proposal`, and commits to the RGB data.
+    ///
+    /// If the receiver has moved the outputs of the proposal, the state
+    /// transitions are composed once again, such that their seals point to the
+    /// new output numbers. Returns the PSBT, which has to be signed by both
+    /// payer and receiver, together with the transfer consignment.
+    #[allow(clippy::result_large_err)]
+    pub fn finalize_payjoin(
+        &mut self,
+        proposal: &PayjoinProposal,
+        mut payjoin: Psbt,
+    ) -> Result<(Psbt, Transfer), PayjoinError> {
+        let vouts = proposal.match_contribution(&payjoin)?;
+        for input in payjoin.inputs() {
+            let outpoint = input.previous_outpoint;
+            if self.wallet.utxo(outpoint).is_some()
+                && !proposal
+                    .psbt()
+                    .inputs()
+                    .any(|input| input.previous_outpoint == outpoint)
+            {
+                return Err(PayjoinError::OwnInputAdded(outpoint));
+            }
+        }
+
+        let invoice = proposal.invoice();
+        let contract_id = invoice.contract.ok_or(PayjoinError::NoInvoice)?;
+        let moved = vouts
+            .iter()
+            .enumerate()
+            .any(|(vout, moved)| moved.to_usize() != vout);
+        if moved || payjoin.rgb_verify_layout().is_err() {
+            let mut beneficiary_vout = None;
+            let mut change_vouts = vec![];
+            for output in proposal.psbt().outputs() {
+                let vout = vouts[output.vout().to_usize()];
+                let target = payjoin.output_mut(vout.to_usize()).expect("matched output");
+                if output.is_tapret_host() {
+                    target.set_tapret_host().expect("matched by script");
+                }
+                if output.is_opret_host() {
+                    target.set_opret_host().expect("matched by script");
+                }
+                match output.rgb_role() {
+                    Some((OutputRole::Beneficiary, _)) => beneficiary_vout = Some(vout),
+                    Some((OutputRole::Change, _)) => change_vouts.push(vout),
+                    _ => {}
+                }
+            }
+            let prev_outpoints = proposal
+                .psbt()
+                .inputs()
+                .filter(|input| matches!(input.rgb_consumer(contract_id), Ok(Some(_))))
+                .map(|input| input.previous_outpoint)
+                .collect::<Vec<_>>();
+            self.wallet.realign_psbt_rgb(
+                &self.stock,
+                &mut payjoin,
+                invoice,
+                prev_outpoints,
+                beneficiary_vout,
+                &change_vouts,
+            )?;
+        }
+
+        payjoin.complete_construction();
+        let transfer = self.transfer(invoice, &mut payjoin)?;
+        Ok((payjoin, transfer))
+    }
+
+    /// Pays the `invoice` with a payjoin transfer, exchanging the proposal with
+    /// the receiver endpoint specified in the invoice using the `client`.
+    ///
+    /// Returns the PSBT, which has to be signed by both payer and receiver,
+    /// together with the transfer consignment.
+    #[allow(clippy::result_large_err)]
+    pub fn pay_payjoin(
+        &mut self,
+        invoice: RgbInvoice,
+        params: TransferParams,
+        client: &impl PayjoinClient,
+    ) -> Result<(Psbt, Transfer), PayjoinError> {
+        let endpoint = PayjoinEndpoint::from_invoice(&invoice)?.ok_or(PayjoinError::NoEndpoint)?;
+        let (proposal, _) = self.propose_payjoin(invoice, params)?;
+        let payjoin = client
+            .exchange(&endpoint, proposal.psbt())
+            .map_err(|err| PayjoinError::Exchange(endpoint.to_string(), err))?;
+        self.finalize_payjoin(&proposal, payjoin)
+    }
+}
+
+#[cfg(feature = "fs")]
+impl<K, W: WalletProvider<K, L2>, H: StateProvider, P: IndexProvider, L2: Layer2>
+    RgbWallet<W, K, MemStash, H, P, L2>
+where W::Descr: DescriptorRgb<K>
+{
+    /// Compacts the stock, moving the history which is not required for
+    /// consigning the state owned by the wallet into the archive file.
+    ///
+    /// If the archive file already exists, the newly archived history is
+    /// added to it. The wallet must be synced before the compaction, since
+    /// the history of the state assigned to the outputs which are not yet
+    /// known to the wallet gets archived.
+    ///
+    /// Returns number of the archived transition bundles.
+    #[allow(clippy::result_large_err)]
+    pub fn compact(&mut self, archive_path: impl AsRef<Path>) -> Result<usize, ArchiveError> {
+        let archive_path = archive_path.as_ref();
+        let utxos = self.wallet.utxos().map(XChain::Bitcoin).collect::<Vec<_>>();
+        let compacted = self.stock.compact(utxos)?;
+        let count = compacted.bundle_count();
+        if compacted.is_empty() {
+            return Ok(count);
+        }
+        let mut archive = if archive_path.exists() {
+            StockArchive::load_file(archive_path)?
+        } else {
+            StockArchive::new()
+        };
+        archive.merge(compacted)?;
+        archive.save_file(archive_path)?;
+        self.stock.as_stash_provider_mut().mark_dirty();
+        Ok(count)
+    }
+
+    /// Restores the history from the archive file into the stock, returning
+    /// number of the restored transition bundles.
+    #[allow(clippy::result_large_err)]
+    pub fn restore_archive(
+        &mut self,
+        archive_path: impl AsRef<Path>,
+    ) -> Result<usize, ArchiveError> {
+        let archive = StockArchive::load_file(archive_path)?;
+        self.stock.restore_archive(&archive)
+    }
+
+    /// Prunes the secret seals which can't receive any state anymore: the
+    /// seals of the invoices which have expired at `now` according to the
+    /// `expiry` metadata, and the seals defined on the wallet outputs which
+    /// are already spent. Seals used by any operation known to the stash are
+    /// never pruned. The wallet must be synced before the garbage collection,
+    /// since otherwise the seals on spent outputs can't be detected.
+    ///
+    /// Returns the pruned seals, which are also removed from the `expiry`
+    /// metadata.
+    #[allow(clippy::result_large_err)]
+    pub fn gc(
+        &mut self,
+        expiry: &mut SealExpiry,
+        now: i64,
+    ) -> Result<BTreeSet<XChain<GraphSeal>>, ArchiveError> {
+        let txos = self.wallet.txos().collect::<BTreeSet<_>>();
+        let utxos = self.wallet.utxos().collect::<BTreeSet<_>>();
+        let pruned = self.stock.prune_secret_seals(|seal| {
+            let XChain::Bitcoin(seal) = seal else {
+                return false;
+            };
+            if expiry.is_expired(&seal.conceal(), now) {
+                return true;
+            }
+            let TxPtr::Txid(txid) = seal.txid else {
+                return false;
+            };
+            let outpoint = Outpoint::new(txid, seal.vout);
+            txos.contains(&outpoint) && !utxos.contains(&outpoint)
+        })?;
+        for seal in &pruned {
+            if let XChain::Bitcoin(seal) = seal {
+                expiry.remove(&seal.conceal());
+            }
+        }
+        Ok(pruned)
+    }
+}
+
+fn assignment_preview<State: ExposedState>(
+    ty: AssignmentType,
+    assign: &Assign<State, GraphSeal>,
+    is_own: impl Fn(Vout) -> bool,
+) -> AssignmentPreview
+where
+    AllocatedState: From<State>,
+{
+    let destination = match assign.revealed_seal() {
+        Some(seal) => {
+            let seal = seal.as_reduced_unsafe();
+            match seal.txid {
+                TxPtr::WitnessTx if is_own(seal.vout) => StateDestination::Change(seal.vout),
+                TxPtr::WitnessTx => StateDestination::Output(seal.vout),
+                TxPtr::Txid(txid) => StateDestination::Outpoint(Outpoint::new(txid, seal.vout)),
+            }
+        }
+        None => StateDestination::Blinded(assign.to_confidential_seal()),
+    };
+    AssignmentPreview {
+        assignment_type: ty,
+        state: assign
+            .as_revealed_state()
+            .cloned()
+            .map(AllocatedState::from),
+        destination,
     }
 }
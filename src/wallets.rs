@@ -0,0 +1,365 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Management of the wallets kept in the data directory, each of which is a
+//! sub-directory named after the wallet.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bp::seals::txout::CloseMethod;
+use bpstd::{Network, Sats, XpubDerivable};
+use bpwallet::fs::FsTextStore;
+use bpwallet::Wallet;
+use psrgbt::PsbtConstructor;
+
+#[cfg(feature = "encryption")]
+use crate::{is_encrypted, EncryptedStore, Passphrase};
+use crate::{DescriptorRgb, KeychainLayout, RgbDescr, WalletDirError};
+
+/// Name of the file inside the wallet directory holding the descriptor.
+pub const WALLET_DESCRIPTOR_FILE: &str = "descriptor.toml";
+
+/// Name of the file with tapret tweaks written on wallet export.
+pub const WALLET_TWEAKS_FILE: &str = "tapret.tweaks";
+
+/// Files of the wallet directory keeping the wallet data in plaintext.
+#[cfg(feature = "encryption")]
+const PLAINTEXT_FILES: [&str; 4] =
+    [WALLET_DESCRIPTOR_FILE, "data.toml", "cache.yaml", "layer2.yaml"];
+
+/// Files of the wallet directory keeping the encrypted wallet data.
+#[cfg(feature = "encryption")]
+const ENCRYPTED_FILES: [&str; 3] = [crate::ENCRYPTED_DESCRIPTOR_FILE, "data.enc", "cache.enc"];
+
+/// Networks which data is nested into a directory named after the network.
+const NETWORKS: [Network; 5] =
+    [Network::Mainnet, Network::Testnet3, Network::Testnet4, Network::Signet, Network::Regtest];
+
+/// Name of the stash file, used to detect the stock in the data directory.
+const STASH_FILE: &str = "stash.dat";
+
+/// Summary of a wallet kept in the data directory.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct WalletInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub descriptor: String,
+    pub network: Network,
+    pub close_method: CloseMethod,
+    pub keychain_layout: KeychainLayout,
+    pub balance: Sats,
+    pub utxos: usize,
+    pub transactions: usize,
+    pub tapret_tweaks: usize,
+}
+
+/// Data directory holding the wallets.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct WalletDir {
+    base: PathBuf,
+}
+
+impl WalletDir {
+    pub fn new(base: impl Into<PathBuf>) -> Self { Self { base: base.into() } }
+
+    pub fn base(&self) -> &Path { &self.base }
+
+    /// Returns path of the wallet directory, without checking the wallet
+    /// existence.
+    pub fn path(&self, name: &str) -> PathBuf { self.base.join(name) }
+
+    /// Checks whether the directory with the given name contains a wallet,
+    /// either a plaintext or an encrypted one.
+    pub fn exists(&self, name: &str) -> bool {
+        let path = self.path(name);
+        #[cfg(feature = "encryption")]
+        if is_encrypted(&path) {
+            return true;
+        }
+        path.join(WALLET_DESCRIPTOR_FILE).is_file()
+    }
+
+    /// Lists names of the wallets in the data directory, skipping other
+    /// directories, like the ones keeping the stock data.
+    pub fn list(&self) -> Result<Vec<String>, WalletDirError> {
+        let mut names = vec![];
+        for entry in fs::read_dir(&self.base)? {
+            let entry = entry?;
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if entry.file_type()?.is_dir() && self.exists(&name) {
+                names.push(name);
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Loads the wallet and provides its summary.
+    pub fn info(&self, name: &str) -> Result<WalletInfo, WalletDirError> {
+        let wallet = self.load(name)?;
+        Ok(WalletInfo {
+            name: name.to_owned(),
+            path: self.path(name),
+            descriptor: wallet.descriptor().to_string(),
+            network: wallet.network(),
+            close_method: wallet.seal_close_method(),
+            keychain_layout: wallet.keychain_layout(),
+            balance: wallet.balance(),
+            utxos: wallet.utxos().count(),
+            transactions: wallet.transactions().len(),
+            tapret_tweaks: wallet.descriptor().tapret_tweaks().len(),
+        })
+    }
+
+    /// Renames the wallet directory.
+    pub fn rename(&self, from: &str, to: &str) -> Result<(), WalletDirError> {
+        self.check_exists(from)?;
+        check_name(to)?;
+        let target = self.path(to);
+        if target.exists() {
+            return Err(WalletDirError::AlreadyExists(to.to_owned()));
+        }
+        fs::rename(self.path(from), target)?;
+        Ok(())
+    }
+
+    /// Writes the wallet descriptor and tapret tweaks into the directory,
+    /// such that the wallet can be re-created from them.
+    ///
+    /// Returns paths of the written files.
+    pub fn export(
+        &self,
+        name: &str,
+        dir: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>, WalletDirError> {
+        let wallet = self.load(name)?;
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let descriptor = dir.join(WALLET_DESCRIPTOR_FILE);
+        fs::copy(self.path(name).join(WALLET_DESCRIPTOR_FILE), &descriptor)?;
+        let mut files = vec![descriptor];
+        let tweaks = wallet.descriptor().tapret_tweaks();
+        if !tweaks.is_empty() {
+            let path = dir.join(WALLET_TWEAKS_FILE);
+            fs::write(&path, tweaks.to_string())?;
+            files.push(path);
+        }
+        Ok(files)
+    }
+
+    /// Deletes the wallet directory with all the wallet data.
+    pub fn delete(&self, name: &str) -> Result<(), WalletDirError> {
+        self.check_exists(name)?;
+        fs::remove_dir_all(self.path(name))?;
+        Ok(())
+    }
+
+    /// Encrypts the wallet data with the passphrase, removing the plaintext
+    /// wallet files.
+    ///
+    /// Only the files of the wallet directory are encrypted. The stock and
+    /// the files of the data directory shared by all wallets (labels, frozen
+    /// and imported outpoints, velocity preferences and the resolver cache)
+    /// are kept in plaintext.
+    ///
+    /// If the encrypted files can't be written, the ones written so far are
+    /// removed and the wallet is left in plaintext. If some of the plaintext
+    /// files can't be removed afterwards, repeating the call removes them.
+    #[cfg(feature = "encryption")]
+    pub fn encrypt(
+        &self,
+        name: &str,
+        passphrase: impl Into<Passphrase>,
+    ) -> Result<(), WalletDirError> {
+        let path = self.path(name);
+        if is_encrypted(&path) {
+            if PLAINTEXT_FILES.iter().any(|file| path.join(file).exists()) {
+                return remove_files(&path, &PLAINTEXT_FILES);
+            }
+            return Err(WalletDirError::AlreadyEncrypted(name.to_owned()));
+        }
+        let mut wallet = self.load(name)?;
+        let store = EncryptedStore::new(path.clone(), passphrase)?;
+        if let Err(e) = wallet
+            .make_persistent(store, true)
+            .and_then(|_| wallet.store())
+        {
+            let _ = remove_files(&path, &ENCRYPTED_FILES);
+            return Err(WalletDirError::Store(name.to_owned(), e.to_string()));
+        }
+        remove_files(&path, &PLAINTEXT_FILES)
+    }
+
+    /// Decrypts the wallet data, keeping it in plaintext files afterwards.
+    #[cfg(feature = "encryption")]
+    pub fn decrypt(
+        &self,
+        name: &str,
+        passphrase: impl Into<Passphrase>,
+    ) -> Result<(), WalletDirError> {
+        check_name(name)?;
+        let path = self.path(name);
+        if !is_encrypted(&path) {
+            return match self.exists(name) {
+                true => Err(WalletDirError::NotEncrypted(name.to_owned())),
+                false => Err(WalletDirError::NotFound(name.to_owned())),
+            };
+        }
+        let store = EncryptedStore::unlock(path.clone(), passphrase)?;
+        let mut wallet = Wallet::<XpubDerivable, RgbDescr>::load(store, false)
+            .map_err(|e| WalletDirError::Load(name.to_owned(), e.to_string()))?;
+        // Plaintext files written before a failure must not be left next to
+        // the encrypted ones
+        let provider = FsTextStore::new(path.clone())?;
+        if let Err(e) = wallet
+            .make_persistent(provider, true)
+            .and_then(|_| wallet.store())
+        {
+            let _ = remove_files(&path, &PLAINTEXT_FILES);
+            return Err(WalletDirError::Store(name.to_owned(), e.to_string()));
+        }
+        remove_files(&path, &ENCRYPTED_FILES)
+    }
+
+    fn load(&self, name: &str) -> Result<Wallet<XpubDerivable, RgbDescr>, WalletDirError> {
+        self.check_exists(name)?;
+        #[cfg(feature = "encryption")]
+        if is_encrypted(self.path(name)) {
+            return Err(WalletDirError::Locked(name.to_owned()));
+        }
+        let provider = FsTextStore::new(self.path(name))?;
+        Wallet::load(provider, false)
+            .map_err(|e| WalletDirError::Load(name.to_owned(), e.to_string()))
+    }
+
+    fn check_exists(&self, name: &str) -> Result<(), WalletDirError> {
+        check_name(name)?;
+        if !self.exists(name) {
+            return Err(WalletDirError::NotFound(name.to_owned()));
+        }
+        Ok(())
+    }
+}
+
+/// Detects the stock or wallets kept directly in the data directory, as it was
+/// done before the data got nested into directories named after the network.
+pub fn has_flat_layout(data_dir: impl AsRef<Path>) -> bool {
+    let data_dir = data_dir.as_ref();
+    data_dir.join(STASH_FILE).is_file()
+        || WalletDir::new(data_dir)
+            .list()
+            .is_ok_and(|wallets| !wallets.is_empty())
+}
+
+/// Moves the data kept directly in the data directory into the directory
+/// named after the `network`, which is used by the nested data layout.
+///
+/// All the wallets must use the `network`. Files listed in `shared`, like the
+/// configuration, are copied instead of being moved, such that they keep
+/// applying to the data directories of the other networks; they are skipped if
+/// the network directory already has them. Nothing is moved if any of the
+/// other entries already exists in the network directory.
+///
+/// Returns the paths of the migrated entries in the network directory.
+pub fn migrate_flat_layout(
+    data_dir: impl AsRef<Path>,
+    network: Network,
+    shared: &[&str],
+) -> Result<Vec<PathBuf>, WalletDirError> {
+    let data_dir = data_dir.as_ref();
+    let wallets = WalletDir::new(data_dir);
+    for name in wallets.list()? {
+        let found = wallets.load(&name)?.network();
+        if found != network {
+            return Err(WalletDirError::WrongNetwork {
+                name,
+                expected: network,
+                found,
+            });
+        }
+    }
+
+    let target = data_dir.join(network.to_string());
+    let mut entries = vec![];
+    for entry in fs::read_dir(data_dir)? {
+        let name = entry?.file_name();
+        if NETWORKS
+            .iter()
+            .any(|network| name == network.to_string().as_str())
+        {
+            continue;
+        }
+        let is_shared = shared.iter().any(|shared| name == *shared);
+        let path = target.join(&name);
+        match path.exists() {
+            // Network directory already has its own copy of the shared file
+            true if is_shared => continue,
+            true => return Err(WalletDirError::Collision(path.display().to_string())),
+            false => entries.push((name, is_shared)),
+        }
+    }
+    entries.sort();
+
+    fs::create_dir_all(&target)?;
+    let mut migrated = Vec::with_capacity(entries.len());
+    for (name, is_shared) in entries {
+        let path = target.join(&name);
+        if is_shared {
+            fs::copy(data_dir.join(&name), &path)?;
+        } else {
+            fs::rename(data_dir.join(&name), &path)?;
+        }
+        migrated.push(path);
+    }
+    Ok(migrated)
+}
+
+pub(crate) fn check_name(name: &str) -> Result<(), WalletDirError> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || name.contains(|c: char| c == '/' || c == '\\' || c.is_control())
+    {
+        return Err(WalletDirError::InvalidName(name.to_owned()));
+    }
+    Ok(())
+}
+
+/// Removes the files from the directory, together with the temporary files
+/// left by interrupted writes. Tries to remove all the files even if some of
+/// them can't be removed, returning the first error.
+#[cfg(feature = "encryption")]
+fn remove_files(dir: &Path, files: &[&str]) -> Result<(), WalletDirError> {
+    let mut res = Ok(());
+    for file in files {
+        for path in [dir.join(file), dir.join(format!("{file}.tmp"))] {
+            match fs::remove_file(path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound && res.is_ok() => {
+                    res = Err(err.into())
+                }
+                _ => {}
+            }
+        }
+    }
+    res
+}
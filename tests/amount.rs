@@ -0,0 +1,71 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Presentation of the fungible amounts adjusted for the contract precision.
+
+mod common;
+
+use common::{Party, NETWORK};
+use rgb::resolvers::MockChain;
+use rgb::{AmountFormatter, Precision};
+
+#[test]
+fn contract_precision() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue_with_precision(outpoint, 123_456, Precision::Centi);
+
+    let mut amounts = alice.wallet.amount_formatter(contract_id).unwrap();
+    assert_eq!(amounts.precision(), Precision::Centi);
+    assert_eq!(amounts.ticker(), Some("TEST"));
+    let balance = alice.balance(contract_id);
+    assert_eq!(amounts.format(balance.total().value()), "1234.56 TEST");
+    assert_eq!(amounts.format(100), "1 TEST");
+    assert_eq!(amounts.format(5), "0.05 TEST");
+
+    amounts.set_raw(true);
+    assert_eq!(amounts.format(balance.total().value()), "123456");
+}
+
+#[test]
+fn indivisible() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    let amounts = alice.wallet.amount_formatter(contract_id).unwrap();
+    assert_eq!(amounts.precision(), Precision::Indivisible);
+    assert_eq!(amounts.format(1_000), "1000 TEST");
+}
+
+#[test]
+fn raw() {
+    let amounts = AmountFormatter::raw();
+    assert!(amounts.is_raw());
+    assert_eq!(amounts.ticker(), None);
+    assert_eq!(amounts.format(123_456), "123456");
+
+    let amounts = AmountFormatter::new(Precision::Milli, Some(String::new()));
+    assert_eq!(amounts.ticker(), None);
+    assert_eq!(amounts.format(123_456), "123.456");
+}
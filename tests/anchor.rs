@@ -0,0 +1,100 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of the anchors of a transfer against the witness transaction,
+//! without validation of the consignment.
+
+mod common;
+
+use bpstd::{Tx, Txid};
+use common::{Party, NETWORK};
+use rgb::containers::Transfer;
+use rgb::resolvers::MockChain;
+use rgb::validation::EAnchor;
+use rgb::{verify_anchor, AnchorError, BundleId, ContractId};
+
+/// Returns the witness transaction of the transfer with the anchor and the id
+/// of the bundle it commits to.
+fn witness(transfer: &Transfer, txid: Txid) -> (Tx, EAnchor, BundleId) {
+    let witness_bundle = transfer
+        .bundles
+        .iter()
+        .find(|bundle| bundle.pub_witness.as_reduced_unsafe().txid() == txid)
+        .expect("transfer witness");
+    let tx = witness_bundle
+        .pub_witness
+        .as_reduced_unsafe()
+        .tx()
+        .expect("transfer contains witness transaction")
+        .clone();
+    let (anchor, bundle) = witness_bundle
+        .anchored_bundles()
+        .next()
+        .expect("witness bundle");
+    (tx, anchor, bundle.bundle_id())
+}
+
+/// Returns id of a contract which the multi-protocol commitment tree of the
+/// anchor places at a position other than the one proven by the anchor.
+fn misplaced_contract(anchor: &EAnchor) -> ContractId {
+    let proof = &anchor.mpc_proof;
+    let width = proof.factored_width();
+    assert!(width > 1, "single-leaf commitment tree");
+    let mut id = [0u8; 32];
+    id[..4].copy_from_slice(&((proof.pos() + 1) % width).to_le_bytes());
+    ContractId::from(id)
+}
+
+#[test]
+fn anchor_verification() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    let invoice = bob.invoice(contract_id, 100, false);
+    let (txid, transfer) = alice.pay(&invoice);
+    let (tx, anchor, bundle_id) = witness(&transfer, txid);
+    assert_eq!(tx.txid(), txid);
+    assert!(verify_anchor(&tx, &anchor, contract_id, bundle_id).is_ok());
+
+    // The anchor doesn't commit to other contracts or bundles
+    let other_id = misplaced_contract(&anchor);
+    let err = verify_anchor(&tx, &anchor, other_id, bundle_id).unwrap_err();
+    assert!(
+        matches!(err, AnchorError::Mpc { contract_id, .. } if contract_id == other_id),
+        "{err}"
+    );
+
+    chain.mine(1);
+    alice.sync();
+    let invoice = bob.invoice(contract_id, 100, false);
+    let (other_txid, other_transfer) = alice.pay(&invoice);
+    let (other_tx, other_anchor, other_bundle_id) = witness(&other_transfer, other_txid);
+    let err = verify_anchor(&tx, &anchor, contract_id, other_bundle_id).unwrap_err();
+    assert!(matches!(err, AnchorError::Dbc { txid: id, .. } if id == txid), "{err}");
+    assert!(verify_anchor(&other_tx, &other_anchor, contract_id, other_bundle_id).is_ok());
+
+    // Other transactions don't contain the commitment
+    let err = verify_anchor(&other_tx, &anchor, contract_id, bundle_id).unwrap_err();
+    assert!(matches!(err, AnchorError::Dbc { txid, .. } if txid == other_txid), "{err}");
+}
@@ -0,0 +1,119 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compaction of the stock, moving the spent history into an archive, and its
+//! restoration.
+
+mod common;
+
+use std::collections::BTreeSet;
+use std::fs;
+
+use common::{amount, temp_dir, Party, NETWORK};
+use rgb::resolvers::MockChain;
+use rgb::{ContractId, StockArchive, RGB20_IFACE};
+
+/// Contracts, history, balance and transition bundles of the party.
+fn snapshot(party: &Party, contract_id: ContractId) -> impl Eq + std::fmt::Debug {
+    let stock = party.wallet.stock();
+    let contracts = stock
+        .contracts()
+        .unwrap()
+        .map(|info| info.id)
+        .collect::<BTreeSet<_>>();
+    let history = party.wallet.history(contract_id, RGB20_IFACE).unwrap();
+    (contracts, history, party.balance(contract_id))
+}
+
+fn bundles(party: &Party) -> BTreeSet<rgb::BundleId> {
+    party
+        .wallet
+        .stock()
+        .as_stash_provider()
+        .debug_bundles()
+        .keys()
+        .copied()
+        .collect()
+}
+
+#[test]
+fn archive_compact_restore() {
+    let dir = temp_dir("archive");
+    let path = dir.join("stock.archive");
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+
+    // Bob receives the asset and sends all of it back, so he doesn't own any
+    // state and his whole history may be archived
+    let invoice = bob.invoice(contract_id, 400, true);
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    bob.accept(transfer);
+    bob.sync();
+    let invoice = alice.invoice(contract_id, 400, false);
+    let (_, transfer) = bob.pay(&invoice);
+    chain.mine(1);
+    alice.accept(transfer);
+    alice.sync();
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).confirmed, amount(0));
+
+    let before = snapshot(&bob, contract_id);
+    let bundles_before = bundles(&bob);
+    assert_eq!(bundles_before.len(), 2);
+    let count = bob.wallet.compact(&path).unwrap();
+    assert_eq!(count, 2);
+    assert!(bundles(&bob).is_empty());
+    assert_eq!(snapshot(&bob, contract_id), before);
+
+    let archive = StockArchive::load_file(&path).unwrap();
+    assert_eq!(archive.bundle_count(), 2);
+    assert_eq!(archive.witness_count(), 2);
+
+    // Nothing else can be archived, and the archive file is left unchanged
+    assert_eq!(bob.wallet.compact(&path).unwrap(), 0);
+    assert_eq!(StockArchive::load_file(&path).unwrap().bundle_count(), 2);
+
+    assert_eq!(bob.wallet.restore_archive(&path).unwrap(), 2);
+    assert_eq!(bundles(&bob), bundles_before);
+    assert_eq!(snapshot(&bob, contract_id), before);
+
+    // Alice keeps the history of the state she owns, so she is still able to
+    // pay with it after the compaction
+    let before = snapshot(&alice, contract_id);
+    alice.wallet.compact(dir.join("alice.archive")).unwrap();
+    assert_eq!(snapshot(&alice, contract_id), before);
+    let invoice = bob.invoice(contract_id, 900, true);
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    bob.accept(transfer);
+    bob.sync();
+    alice.sync();
+    assert_eq!(alice.balance(contract_id).confirmed, amount(100));
+    assert_eq!(bob.balance(contract_id).confirmed, amount(900));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
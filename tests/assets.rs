@@ -0,0 +1,51 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed metadata of the contracts following the asset standards.
+
+use rgb::containers::{ConsignmentExt, Contract, FileContent};
+use rgb::persistence::Stock;
+use rgb::resolvers::ContractIssueResolver;
+use rgb::{Amount, AssetInfoExt, Precision};
+
+#[test]
+fn rgb20_info() {
+    let mut stock = Stock::in_memory();
+    let contract = Contract::load_file("examples/rgb20-demo.rgb")
+        .unwrap()
+        .validate(&ContractIssueResolver, true)
+        .unwrap();
+    let contract_id = contract.contract_id();
+    stock
+        .import_contract(contract, &ContractIssueResolver)
+        .unwrap();
+    let info = stock.contract_info(contract_id).unwrap();
+
+    let asset = info.as_rgb20(&stock).unwrap().expect("RGB20 contract");
+    assert_eq!(asset.contract_id, contract_id);
+    assert_eq!(asset.ticker, "DBG");
+    assert_eq!(asset.name, "Debug asset");
+    assert_eq!(asset.details.as_deref(), Some("Pay attention: the asset has no value"));
+    assert_eq!(asset.precision, Precision::Centi);
+    assert_eq!(asset.supply, Amount::from(100_000_000u64));
+
+    assert_eq!(info.as_rgb21(&stock).unwrap(), None);
+}
@@ -0,0 +1,87 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rotation of the stock file backups and fallback to them on a damaged file.
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use common::temp_dir;
+use nonasync::persistence::PersistenceProvider;
+use rgb::containers::{Contract, FileContent};
+use rgb::persistence::{MemStash, Stock};
+use rgb::resolvers::ContractIssueResolver;
+use rgb::{BackupStore, BackupStoreError};
+
+/// Creates stock persisted with the store and imports a contract into it,
+/// such that the stash is saved twice.
+fn stock_with_contract(store: &BackupStore) {
+    let mut stock = Stock::in_memory();
+    stock.make_persistent(store.clone(), true).unwrap();
+    let contract = Contract::load_file("examples/rgb20-demo.rgb")
+        .unwrap()
+        .validate(&ContractIssueResolver, true)
+        .unwrap();
+    stock
+        .import_contract(contract, &ContractIssueResolver)
+        .unwrap();
+}
+
+fn damage(path: PathBuf) {
+    let mut data = fs::read(&path).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 0xFF;
+    fs::write(path, data).unwrap();
+}
+
+#[test]
+fn fallback_to_backup() {
+    let dir = temp_dir("fallback");
+    let store = BackupStore::new(dir.clone(), 2).unwrap();
+    stock_with_contract(&store);
+    assert!(dir.join("stash.dat.sha256").exists());
+    assert!(dir.join("stash.dat.1").exists());
+
+    let stash: MemStash = store.load().unwrap();
+    assert_eq!(stash.debug_geneses().len(), 1);
+
+    damage(dir.join("stash.dat"));
+    let stash: MemStash = store.load().unwrap();
+    assert_eq!(stash.debug_geneses().len(), 0);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn no_backups() {
+    let dir = temp_dir("none");
+    let store = BackupStore::new(dir.clone(), 0).unwrap();
+    stock_with_contract(&store);
+    assert!(!dir.join("stash.dat.1").exists());
+
+    damage(dir.join("stash.dat"));
+    let err = PersistenceProvider::<MemStash>::load(&store).unwrap_err();
+    assert!(matches!(err.0.downcast_ref(), Some(BackupStoreError::Checksum(_))));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,175 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Caching of the resolved witnesses and their final mining positions.
+
+mod common;
+
+use std::fs;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use bp::{BlockHash, Tx};
+use bpstd::{Network, Sats, ScriptPubkey, Txid};
+use common::{temp_dir, NETWORK};
+use rgb::resolvers::{CachingResolver, MockChain, RgbResolver, DEFAULT_REORG_DEPTH};
+use rgb::vm::WitnessOrd;
+
+/// Resolver counting witness requests which reach the chain.
+#[derive(Clone)]
+struct Counting {
+    chain: MockChain,
+    requests: Arc<AtomicU32>,
+}
+
+impl Counting {
+    fn new(chain: &MockChain) -> Self {
+        Self {
+            chain: chain.clone(),
+            requests: Arc::default(),
+        }
+    }
+
+    fn requests(&self) -> u32 { self.requests.load(Ordering::SeqCst) }
+}
+
+impl RgbResolver for Counting {
+    fn check(&self, network: Network, expected_block_hash: String) -> Result<(), String> {
+        self.chain.check(network, expected_block_hash)
+    }
+    fn resolve_pub_witness(&self, txid: Txid) -> Result<Option<Tx>, String> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        self.chain.resolve_pub_witness(txid)
+    }
+    fn resolve_pub_witness_ord(&self, txid: Txid) -> Result<WitnessOrd, String> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        self.chain.resolve_pub_witness_ord(txid)
+    }
+    fn resolve_block_hash(&self, height: u32) -> Result<BlockHash, String> {
+        self.chain.resolve_block_hash(height)
+    }
+    fn resolve_tip_height(&self) -> Result<u32, String> { self.chain.resolve_tip_height() }
+}
+
+fn fund(chain: &MockChain) -> Txid {
+    chain
+        .fund(&ScriptPubkey::op_return(&[0x01]), Sats::from_sats(10_000u64))
+        .txid
+}
+
+#[test]
+fn cache_hit() {
+    let dir = temp_dir("cache-hit");
+    let path = dir.join("resolver.cache");
+    let chain = MockChain::new(NETWORK);
+    let txid = fund(&chain);
+    chain.mine(DEFAULT_REORG_DEPTH + 1);
+
+    let inner = Counting::new(&chain);
+    let resolver = CachingResolver::load(inner.clone(), &path).unwrap();
+    let tx = resolver.resolve_pub_witness(txid).unwrap().unwrap();
+    let ord = resolver.resolve_pub_witness_ord(txid).unwrap();
+    assert!(matches!(ord, WitnessOrd::Mined(_)));
+    assert_eq!(inner.requests(), 2);
+
+    assert_eq!(resolver.resolve_pub_witness(txid).unwrap(), Some(tx.clone()));
+    assert_eq!(resolver.resolve_pub_witness_ord(txid).unwrap(), ord);
+    assert_eq!(inner.requests(), 2);
+
+    // Cached data survive the resolver
+    let inner = Counting::new(&chain);
+    let resolver = CachingResolver::load(inner.clone(), &path).unwrap();
+    assert_eq!(resolver.resolve_pub_witness(txid).unwrap(), Some(tx));
+    assert_eq!(resolver.resolve_pub_witness_ord(txid).unwrap(), ord);
+    assert_eq!(inner.requests(), 0);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cache_miss() {
+    let dir = temp_dir("cache-miss");
+    let path = dir.join("resolver.cache");
+    let chain = MockChain::new(NETWORK);
+    let inner = Counting::new(&chain);
+    let resolver = CachingResolver::load(inner.clone(), &path).unwrap();
+
+    // Unknown transactions are not cached and are requested each time
+    let unknown = Txid::from([0x42; 32]);
+    assert_eq!(resolver.resolve_pub_witness(unknown).unwrap(), None);
+    assert_eq!(resolver.resolve_pub_witness(unknown).unwrap(), None);
+    assert_eq!(inner.requests(), 2);
+
+    // Positions within the re-org distance from the tip fall through to the
+    // chain until they become final
+    let txid = fund(&chain);
+    assert_eq!(resolver.resolve_pub_witness_ord(txid).unwrap(), WitnessOrd::Tentative);
+    chain.mine(DEFAULT_REORG_DEPTH);
+    resolver.resolve_tip_height().unwrap();
+    let ord = resolver.resolve_pub_witness_ord(txid).unwrap();
+    assert!(matches!(ord, WitnessOrd::Mined(_)));
+    assert_eq!(resolver.resolve_pub_witness_ord(txid).unwrap(), ord);
+    assert_eq!(inner.requests(), 5);
+
+    chain.mine(1);
+    resolver.resolve_tip_height().unwrap();
+    assert_eq!(resolver.resolve_pub_witness_ord(txid).unwrap(), ord);
+    assert_eq!(resolver.resolve_pub_witness_ord(txid).unwrap(), ord);
+    assert_eq!(inner.requests(), 6);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cache_invalidation() {
+    let dir = temp_dir("cache-invalidation");
+    let path = dir.join("resolver.cache");
+    let chain = MockChain::new(NETWORK);
+    let txid = fund(&chain);
+    chain.mine(2);
+
+    // Position which is not final yet is dropped by the re-org
+    let inner = Counting::new(&chain);
+    let resolver = CachingResolver::load(inner.clone(), &path).unwrap();
+    assert!(matches!(resolver.resolve_pub_witness_ord(txid).unwrap(), WitnessOrd::Mined(_)));
+    chain.reorg(2);
+    resolver.resolve_tip_height().unwrap();
+    assert_eq!(resolver.resolve_pub_witness_ord(txid).unwrap(), WitnessOrd::Tentative);
+
+    chain.mine(DEFAULT_REORG_DEPTH + 1);
+    resolver.resolve_tip_height().unwrap();
+    resolver.resolve_pub_witness(txid).unwrap().unwrap();
+    resolver.resolve_pub_witness_ord(txid).unwrap();
+    assert!(path.exists());
+    let requests = inner.requests();
+
+    // Cleared cache requests everything from the chain once again
+    resolver.clear().unwrap();
+    assert!(!path.exists());
+    resolver.resolve_pub_witness(txid).unwrap().unwrap();
+    resolver.resolve_pub_witness_ord(txid).unwrap();
+    assert_eq!(inner.requests(), requests + 2);
+
+    fs::write(&path, "unknown\tentry\n").unwrap();
+    assert!(CachingResolver::load(Counting::new(&chain), &path).is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,77 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checkpoints of the witnesses resolved while accepting a consignment,
+//! allowing to resume an interrupted acceptance.
+
+mod common;
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use common::{Party, NETWORK};
+use rgb::resolvers::{CheckpointResolver, MockChain, RgbResolver};
+use rgb::vm::WitnessOrd;
+
+fn checkpoint(name: &str) -> PathBuf {
+    let path = std::env::temp_dir()
+        .join(format!("rgb-checkpoint-{name}-{}", std::process::id()))
+        .join("test.checkpoint");
+    fs::remove_file(&path).ok();
+    path
+}
+
+#[test]
+fn resume() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(10_000);
+    let path = checkpoint("resume");
+
+    let resolver = CheckpointResolver::load(chain.clone(), &path).unwrap();
+    assert_eq!(resolver.restored(), 0);
+    let tx = resolver.resolve_pub_witness(outpoint.txid).unwrap().unwrap();
+    let ord = resolver.resolve_pub_witness_ord(outpoint.txid).unwrap();
+    assert!(matches!(ord, WitnessOrd::Mined(_)));
+    assert_eq!(resolver.resolved(), 1);
+
+    // Acceptance is interrupted in the middle of writing the next entry
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    write!(file, "tx\t{}\t0200", outpoint.txid).unwrap();
+    drop(file);
+
+    // Restored witnesses are not requested again, so the resolver doesn't have
+    // to know them
+    let empty = MockChain::new(NETWORK);
+    let resolver = CheckpointResolver::load(empty, &path).unwrap();
+    assert_eq!(resolver.restored(), 1);
+    assert_eq!(resolver.resolve_pub_witness(outpoint.txid).unwrap(), Some(tx));
+    assert_eq!(resolver.resolve_pub_witness_ord(outpoint.txid).unwrap(), ord);
+}
+
+#[test]
+fn corrupted() {
+    let path = checkpoint("corrupted");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, "unknown\tentry\n").unwrap();
+    assert!(CheckpointResolver::load(MockChain::new(NETWORK), &path).is_err());
+}
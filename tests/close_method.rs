@@ -0,0 +1,125 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Close method of the seals created by a transfer, overriding the method of
+//! the wallet descriptor.
+
+mod common;
+
+use bpstd::seals::txout::CloseMethod;
+use bpstd::Sats;
+use common::{Party, FEE, NETWORK, SATS};
+use rgb::resolvers::MockChain;
+use rgb::{CompositionError, KeychainLayout, PayError, TransferParams};
+
+fn params(method: Option<CloseMethod>) -> TransferParams {
+    let mut params = TransferParams::with(Sats::from_sats(FEE), Sats::from_sats(SATS));
+    params.close_method = method;
+    params
+}
+
+#[test]
+fn opret_on_tapret_wallet() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+    let invoice = bob.invoice(contract_id, 400, true);
+
+    let (_, meta) = alice.wallet.construct_psbt(&invoice, params(None)).unwrap();
+    assert_eq!(
+        meta.change_terminal.unwrap().keychain,
+        KeychainLayout::STANDARD.for_method(CloseMethod::TapretFirst)
+    );
+
+    // The change is sent to the keychain of the opret seals
+    let (_, meta) = alice
+        .wallet
+        .construct_psbt(&invoice, params(Some(CloseMethod::OpretFirst)))
+        .unwrap();
+    assert_eq!(
+        meta.change_terminal.unwrap().keychain,
+        KeychainLayout::STANDARD.for_method(CloseMethod::OpretFirst)
+    );
+}
+
+#[test]
+fn tapret_on_wpkh_wallet() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new_wpkh(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+    let invoice = bob.invoice(contract_id, 400, true);
+
+    let err = alice
+        .wallet
+        .pay(&invoice, params(Some(CloseMethod::TapretFirst)))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        PayError::Composition(CompositionError::CloseMethodUnsupported(CloseMethod::TapretFirst))
+    ));
+    assert!(alice
+        .wallet
+        .plan_transfer(&invoice, params(Some(CloseMethod::TapretFirst)))
+        .is_err());
+    assert!(alice
+        .wallet
+        .pay(&invoice, params(Some(CloseMethod::OpretFirst)))
+        .is_ok());
+}
+
+#[test]
+fn opret_change_spent() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    alice.fund(100_000);
+    bob.fund(10_000);
+
+    // Tapret seals are spent by a transfer creating opret seals
+    let invoice = bob.invoice(contract_id, 400, true);
+    let (_, transfer) = alice.pay_with(&invoice, params(Some(CloseMethod::OpretFirst)));
+    chain.mine(1);
+    bob.accept(transfer);
+    alice.sync();
+    bob.sync();
+
+    // The opret change is spent by a transfer creating tapret seals
+    let invoice = bob.invoice(contract_id, 600, true);
+    let (_, transfer) = alice.pay_with(&invoice, params(None));
+    chain.mine(1);
+    bob.accept(transfer);
+    alice.sync();
+    bob.sync();
+
+    assert_eq!(alice.balance(contract_id).confirmed, common::amount(0));
+    assert_eq!(bob.balance(contract_id).confirmed, common::amount(1_000));
+}
@@ -0,0 +1,276 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Wallets of the parties participating in the offline scenarios, which use
+//! [`MockChain`] in place of the blockchain indexer.
+
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bpstd::{
+    h, HardenedIndex, Network, Outpoint, Sats, TapTree, Txid, Wpkh, XprivAccount, XpubDerivable,
+};
+use bpwallet::Wallet;
+use psrgbt::PsbtConstructor;
+use rgb::containers::{ConsignmentExt, FileContent, Transfer};
+use rgb::invoice::{Beneficiary, Pay2Vout, RgbInvoice, RgbInvoiceBuilder, XChainNet};
+use rgb::persistence::Stock;
+use rgb::resolvers::{AnyResolver, ContractIssueResolver, MockChain};
+use rgb::{
+    reveal_known_seals, Amount, BalanceReport, ContractId, DescriptorRgb, GraphSeal, Identity,
+    IssuanceTemplate, KeychainLayout, OutputSeal, Precision, RgbDescr, RgbKeychain, RgbWallet,
+    Signer, SoftwareSigner, TapretKey, TransferParams, XChain,
+};
+
+pub const NETWORK: Network = Network::Regtest;
+pub const FEE: u64 = 500;
+pub const SATS: u64 = 2000;
+
+/// Contract providing RGB20 schema and interfaces to the stock of each party.
+const SCHEMA_SOURCE: &str = "examples/rgb20-demo.rgb";
+
+/// Transfer parameters with the default fee and the sats of the new outputs.
+pub fn params() -> TransferParams {
+    TransferParams::with(Sats::from_sats(FEE), Sats::from_sats(SATS))
+}
+
+/// Returns an empty directory for the test data, which is removed if it was
+/// left by a previous run.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rgb-test-{name}-{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).expect("writable temp directory");
+    dir
+}
+
+pub struct Party {
+    pub wallet: RgbWallet<Wallet<XpubDerivable, RgbDescr>>,
+    pub signer: SoftwareSigner,
+    pub chain: MockChain,
+}
+
+impl Party {
+    /// Creates party with a tapret key-only wallet derived from the seed.
+    pub fn new(chain: &MockChain, seed: u8) -> Self {
+        let account = XprivAccount::with_seed(true, &[seed; 32]).derive(h![86, 1, 0]);
+        let xpub = XpubDerivable::from_str(&format!("{}/<0;1;9;10>/*", account.to_xpub_account()))
+            .expect("valid xpub descriptor");
+        Self::with_descriptor(chain, account, RgbDescr::from(TapretKey::from(xpub)))
+    }
+
+    /// Creates party with a tapret key-only wallet derived from the seed,
+    /// which uses a non-standard keychain layout.
+    pub fn with_layout(chain: &MockChain, seed: u8, layout: KeychainLayout) -> Self {
+        let account = XprivAccount::with_seed(true, &[seed; 32]).derive(h![86, 1, 0]);
+        let xpub = XpubDerivable::from_str(&format!("{}/<0;1;9;10>/*", account.to_xpub_account()))
+            .expect("valid xpub descriptor");
+        let descr = RgbDescr::from(TapretKey::from(xpub)).with_keychain_layout(layout);
+        Self::with_descriptor(chain, account, descr)
+    }
+
+    /// Creates party with a tapret wallet derived from the seed, which outputs
+    /// have the script tree next to the key.
+    pub fn with_tap_tree(chain: &MockChain, seed: u8, tap_tree: TapTree) -> Self {
+        let account = XprivAccount::with_seed(true, &[seed; 32]).derive(h![86, 1, 0]);
+        let xpub = XpubDerivable::from_str(&format!("{}/<0;1;9;10>/*", account.to_xpub_account()))
+            .expect("valid xpub descriptor");
+        let descr = TapretKey::from(xpub)
+            .with_tap_tree(tap_tree)
+            .expect("script tree hosting tapret commitments");
+        Self::with_descriptor(chain, account, RgbDescr::from(descr))
+    }
+
+    /// Creates party with a P2WPKH wallet derived from the seed, which uses
+    /// opret commitments.
+    pub fn new_wpkh(chain: &MockChain, seed: u8) -> Self {
+        let account = XprivAccount::with_seed(true, &[seed; 32]).derive(h![84, 1, 0]);
+        let xpub = XpubDerivable::from_str(&format!("{}/<0;1;9;10>/*", account.to_xpub_account()))
+            .expect("valid xpub descriptor");
+        Self::with_descriptor(chain, account, RgbDescr::from(Wpkh::from(xpub)))
+    }
+
+    fn with_descriptor(chain: &MockChain, account: XprivAccount, descr: RgbDescr) -> Self {
+        let network = chain.network();
+        AnyResolver::mock(chain)
+            .check(network)
+            .expect("mock chain network");
+
+        let mut stock = Stock::in_memory();
+        let contract = rgb::containers::Contract::load_file(SCHEMA_SOURCE)
+            .expect("demo contract")
+            .validate(&ContractIssueResolver, true)
+            .expect("valid demo contract");
+        stock
+            .import_contract(contract, &ContractIssueResolver)
+            .expect("demo contract import");
+
+        Party {
+            wallet: RgbWallet::new(stock, Wallet::new_layer1(descr, network)),
+            signer: SoftwareSigner::from_account(account),
+            chain: chain.clone(),
+        }
+    }
+
+    /// Funds the next address of the RGB keychain with the given amount and
+    /// mines the funding transaction.
+    pub fn fund(&mut self, sats: u64) -> Outpoint {
+        let (_, address) = self.wallet.next_rgb_address(true);
+        let outpoint = self
+            .chain
+            .fund(&address.script_pubkey(), Sats::from_sats(sats));
+        self.chain.mine(1);
+        self.sync();
+        outpoint
+    }
+
+    /// Funds the next address of the external keychain, which outputs are not
+    /// used for RGB seals, and mines the funding transaction.
+    pub fn fund_sats(&mut self, sats: u64) -> Outpoint {
+        let keychain = self
+            .wallet
+            .wallet()
+            .descriptor()
+            .keychain_layout()
+            .keychain(RgbKeychain::External);
+        let address = self.wallet.wallet_mut().next_address(keychain, true);
+        let outpoint = self
+            .chain
+            .fund(&address.script_pubkey(), Sats::from_sats(sats));
+        self.chain.mine(1);
+        self.sync();
+        outpoint
+    }
+
+    /// Updates wallet UTXOs and the status of the witness transactions.
+    pub fn sync(&mut self) {
+        let res = self.wallet.wallet_mut().update(&self.chain);
+        assert!(res.err.is_none(), "wallet sync failed: {:?}", res.err);
+        let res = self
+            .wallet
+            .stock_mut()
+            .update_witnesses(AnyResolver::mock(&self.chain), 0)
+            .expect("witness update");
+        assert!(res.failed.is_empty(), "witness update failed: {:?}", res.failed);
+    }
+
+    /// Issues RGB20 asset allocating the whole supply to the outpoint.
+    pub fn issue(&mut self, outpoint: Outpoint, supply: u64) -> ContractId {
+        self.issue_with_precision(outpoint, supply, Precision::Indivisible)
+    }
+
+    /// Issues RGB20 asset with the given precision, allocating the whole
+    /// supply (in the smallest units) to the outpoint.
+    pub fn issue_with_precision(
+        &mut self,
+        outpoint: Outpoint,
+        supply: u64,
+        precision: Precision,
+    ) -> ContractId {
+        let method = self.wallet.wallet().seal_close_method();
+        let mut issuance = rgb::Rgb20Issuance::new("TEST", "Test asset", precision);
+        issuance.allocate(OutputSeal::new(method, outpoint), supply);
+        let contract = issuance
+            .issue(self.wallet.stock(), Identity::default(), None)
+            .expect("valid issuance");
+        let contract_id = contract.contract_id();
+        self.wallet
+            .stock_mut()
+            .import_contract(contract, &ContractIssueResolver)
+            .expect("contract import");
+        contract_id
+    }
+
+    /// Creates invoice for the amount, paying either to a blinded seal
+    /// defined over an existing wallet UTXO, or to a new wallet output of the
+    /// witness transaction.
+    pub fn invoice(&mut self, contract_id: ContractId, amount: u64, blinded: bool) -> RgbInvoice {
+        let method = self.wallet.wallet().seal_close_method();
+        let layout = self.wallet.wallet().descriptor().keychain_layout();
+        let beneficiary = if blinded {
+            let outpoint = self
+                .wallet
+                .wallet()
+                .coinselect(Sats::ZERO, |utxo| layout.contains_rgb(utxo.terminal.keychain))
+                .next()
+                .expect("funded wallet");
+            let seal = XChain::Bitcoin(GraphSeal::new_random(method, outpoint.txid, outpoint.vout));
+            self.wallet
+                .stock_mut()
+                .store_secret_seal(seal)
+                .expect("stock is writable");
+            Beneficiary::BlindedSeal(*seal.to_secret_seal().as_reduced_unsafe())
+        } else {
+            let (_, address) = self.wallet.next_rgb_address(true);
+            Beneficiary::WitnessVout(Pay2Vout {
+                address: address.payload,
+                method,
+            })
+        };
+        RgbInvoiceBuilder::new(XChainNet::bitcoin(self.chain.network(), beneficiary))
+            .set_contract(contract_id)
+            .set_interface(rgb::RGB20_IFACE)
+            .set_amount_raw(amount)
+            .finish()
+    }
+
+    /// Pays the invoice, signing and broadcasting the witness transaction.
+    pub fn pay(&mut self, invoice: &RgbInvoice) -> (Txid, Transfer) {
+        self.pay_with(invoice, params())
+    }
+
+    /// Pays the invoice with the transfer parameters, signing and
+    /// broadcasting the witness transaction.
+    pub fn pay_with(&mut self, invoice: &RgbInvoice, params: TransferParams) -> (Txid, Transfer) {
+        let (mut psbt, _, transfer) = self.wallet.pay(invoice, params).expect("payment");
+        let signed = self.signer.sign_psbt(&mut psbt).expect("signing");
+        assert!(signed > 0, "no inputs were signed");
+        psbt.finalize(self.wallet.wallet().descriptor());
+        let tx = psbt.extract().expect("finalized transaction");
+        self.chain
+            .broadcast(&tx)
+            .expect("valid witness transaction");
+        (tx.txid(), transfer)
+    }
+
+    /// Validates and accepts the transfer consignment.
+    pub fn accept(&mut self, transfer: Transfer) {
+        let mut resolver = AnyResolver::mock(&self.chain);
+        resolver.add_terminals(&transfer);
+        let transfer = reveal_known_seals(self.wallet.stock(), transfer).expect("stock access");
+        let status = self
+            .wallet
+            .accept_transfer(transfer, &resolver, true)
+            .expect("valid transfer");
+        assert!(status.failures.is_empty(), "{status}");
+    }
+
+    /// Returns fungible balance of the contract, counting allocations with at
+    /// least one confirmation as the confirmed ones.
+    pub fn balance(&self, contract_id: ContractId) -> BalanceReport {
+        self.wallet
+            .balance(contract_id, self.chain.tip_height(), 1)
+            .expect("known contract")
+    }
+}
+
+pub fn amount(value: u64) -> Amount { Amount::from(value) }
@@ -0,0 +1,210 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Round-trip and rejection of malformed data in the compact binary invoice
+//! encoding.
+
+use std::str::FromStr;
+
+use bpstd::seals::txout::CloseMethod;
+use bpstd::{AddressPayload, OutputPk, PubkeyHash, ScriptHash, WPubkeyHash, WScriptHash};
+use rgb::invoice::{
+    Allocation, Beneficiary, ChainNet, InvoiceState, Pay2Vout, RgbInvoice, RgbInvoiceBuilder,
+    RgbTransport, XChainNet,
+};
+use rgb::{
+    AttachId, CompactInvoice, CompactInvoiceError, ContractId, SecretSeal, COMPACT_INVOICE_VERSION,
+};
+
+const CHAIN_NETS: [ChainNet; 6] = [
+    ChainNet::BitcoinMainnet,
+    ChainNet::BitcoinTestnet,
+    ChainNet::BitcoinSignet,
+    ChainNet::BitcoinRegtest,
+    ChainNet::LiquidMainnet,
+    ChainNet::LiquidTestnet,
+];
+
+fn output_pk() -> OutputPk {
+    OutputPk::from_str("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap()
+}
+
+fn beneficiaries() -> Vec<Beneficiary> {
+    let addresses = [
+        AddressPayload::Pkh(PubkeyHash::from([0x01; 20])),
+        AddressPayload::Sh(ScriptHash::from([0x02; 20])),
+        AddressPayload::Wpkh(WPubkeyHash::from([0x03; 20])),
+        AddressPayload::Wsh(WScriptHash::from([0x04; 32])),
+        AddressPayload::Tr(output_pk()),
+    ];
+    let mut beneficiaries = vec![Beneficiary::BlindedSeal(SecretSeal::from([0x11; 32]))];
+    for method in [CloseMethod::OpretFirst, CloseMethod::TapretFirst] {
+        beneficiaries.extend(addresses.iter().map(|address| {
+            Beneficiary::WitnessVout(Pay2Vout {
+                method,
+                address: *address,
+            })
+        }));
+    }
+    beneficiaries
+}
+
+fn states() -> [InvoiceState; 4] {
+    [
+        InvoiceState::Void,
+        InvoiceState::Amount(100_000_000_000u64.into()),
+        InvoiceState::Data(rgb::invoice::NonFungible::RGB21(Allocation::with(7, 1))),
+        InvoiceState::Attach(AttachId::from([0x22; 32])),
+    ]
+}
+
+fn transports() -> Vec<RgbTransport> {
+    vec![
+        RgbTransport::JsonRpc {
+            tls: true,
+            host: "rpc.example.com:3000".to_owned(),
+        },
+        RgbTransport::RestHttp {
+            tls: false,
+            host: "127.0.0.1:8080".to_owned(),
+        },
+        RgbTransport::WebSockets {
+            tls: true,
+            host: "ws.example.com".to_owned(),
+        },
+        RgbTransport::Storm {},
+        RgbTransport::UnspecifiedMeans,
+    ]
+}
+
+fn invoice(chain_net: ChainNet, beneficiary: Beneficiary) -> RgbInvoice {
+    RgbInvoiceBuilder::new(XChainNet::with(chain_net, beneficiary))
+        .set_contract(ContractId::from([0x33; 32]))
+        .set_interface("RGB20Fixed")
+        .set_operation("transfer")
+        .set_assignment("assetOwner")
+        .set_expiry_timestamp(1_700_000_000)
+        .finish()
+}
+
+fn assert_round_trip(invoice: &RgbInvoice) {
+    let data = invoice.to_compact_bytes().unwrap();
+    assert_eq!(data[0], COMPACT_INVOICE_VERSION);
+    assert_eq!(&RgbInvoice::from_compact_bytes(&data).unwrap(), invoice);
+}
+
+#[test]
+fn compact_beneficiaries() {
+    for chain_net in CHAIN_NETS {
+        for beneficiary in beneficiaries() {
+            assert_round_trip(&invoice(chain_net, beneficiary));
+        }
+    }
+}
+
+#[test]
+fn compact_states() {
+    for state in states() {
+        let mut invoice = invoice(ChainNet::BitcoinMainnet, beneficiaries()[0]);
+        invoice.owned_state = state;
+        assert_round_trip(&invoice);
+    }
+}
+
+#[test]
+fn compact_transports() {
+    let mut invoice = invoice(ChainNet::BitcoinTestnet, beneficiaries()[1]);
+    for transport in transports() {
+        invoice.transports = vec![transport];
+        assert_round_trip(&invoice);
+    }
+    invoice.transports = transports();
+    assert_round_trip(&invoice);
+    invoice.transports = vec![];
+    assert_round_trip(&invoice);
+}
+
+#[test]
+fn compact_minimal() {
+    let mut invoice =
+        RgbInvoiceBuilder::new(XChainNet::with(ChainNet::BitcoinRegtest, beneficiaries()[0]))
+            .finish();
+    assert_round_trip(&invoice);
+    invoice
+        .unknown_query
+        .insert("sig".to_owned(), "eaa37b8f6a2c".to_owned());
+    invoice
+        .unknown_query
+        .insert("note".to_owned(), "".to_owned());
+    assert_round_trip(&invoice);
+}
+
+#[test]
+fn compact_trailing_bytes() {
+    let invoice = invoice(ChainNet::BitcoinMainnet, beneficiaries()[2]);
+    let mut data = invoice.to_compact_bytes().unwrap();
+    data.extend([0x00, 0x01]);
+    let err = RgbInvoice::from_compact_bytes(&data).unwrap_err();
+    assert!(matches!(err, CompactInvoiceError::ExtraData(2)));
+}
+
+#[test]
+fn compact_truncated() {
+    let invoice = invoice(ChainNet::BitcoinMainnet, beneficiaries()[0]);
+    let data = invoice.to_compact_bytes().unwrap();
+    let err = RgbInvoice::from_compact_bytes(&data[..data.len() - 1]).unwrap_err();
+    assert!(matches!(err, CompactInvoiceError::Decode(_)));
+}
+
+#[test]
+fn compact_unknown_version() {
+    let invoice = invoice(ChainNet::BitcoinMainnet, beneficiaries()[0]);
+    let mut data = invoice.to_compact_bytes().unwrap();
+    for version in [0, COMPACT_INVOICE_VERSION + 1, 0xFF] {
+        data[0] = version;
+        let err = RgbInvoice::from_compact_bytes(&data).unwrap_err();
+        assert!(matches!(err, CompactInvoiceError::UnsupportedVersion(v) if v == version));
+    }
+}
+
+#[test]
+fn compact_unknown_tags() {
+    let invoice = invoice(ChainNet::BitcoinMainnet, beneficiaries()[0]);
+    let data = invoice.to_compact_bytes().unwrap();
+
+    let mut wrong = data.clone();
+    wrong[1] = CHAIN_NETS.len() as u8;
+    let err = RgbInvoice::from_compact_bytes(&wrong).unwrap_err();
+    assert!(matches!(err, CompactInvoiceError::InvalidTag("network", 6)));
+
+    let mut wrong = data;
+    wrong[2] = 2;
+    let err = RgbInvoice::from_compact_bytes(&wrong).unwrap_err();
+    assert!(matches!(err, CompactInvoiceError::InvalidTag("beneficiary", 2)));
+}
+
+#[cfg(feature = "qr")]
+#[test]
+fn compact_qr() {
+    let mut invoice = invoice(ChainNet::BitcoinMainnet, beneficiaries()[10]);
+    invoice.transports = transports();
+    assert!(!invoice.to_qr_string().unwrap().is_empty());
+}
@@ -0,0 +1,113 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Consolidation of the RGB state fragmented across many wallet outputs.
+
+mod common;
+
+use amplify::bset;
+use bpstd::{Outpoint, Sats};
+use common::{amount, Party, NETWORK};
+use psrgbt::{PsbtConstructor, TxParams};
+use rgb::resolvers::MockChain;
+use rgb::{CompositionError, ConsolidationScope, PayError, Signer};
+
+const FEERATE: u64 = 2;
+
+fn params() -> TxParams { TxParams::with(Sats::ZERO) }
+
+#[test]
+fn consolidate_contract() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    for _ in 0..3 {
+        let invoice = bob.invoice(contract_id, 100, false);
+        let (_, transfer) = alice.pay(&invoice);
+        chain.mine(1);
+        alice.sync();
+        bob.accept(transfer);
+        bob.sync();
+    }
+    assert_eq!(bob.balance(contract_id).confirmed, amount(300));
+
+    let scope = ConsolidationScope::Contract(contract_id);
+    let plan = bob
+        .wallet
+        .plan_consolidation(&scope, FEERATE, params())
+        .unwrap();
+    assert_eq!(plan.inputs.len(), 3);
+    assert_eq!(plan.allocations_before(), 3);
+    assert_eq!(plan.allocations_after(), 1);
+    assert_eq!(plan.state[0].amount, Some(amount(300)));
+    assert_eq!(plan.fee.sats(), FEERATE * plan.vsize as u64);
+
+    let (mut psbt, meta, report) = bob.wallet.consolidate(&scope, FEERATE, params()).unwrap();
+    assert_eq!(report.inputs, plan.inputs);
+    assert_eq!(meta.change_vout, Some(report.change_vout));
+    bob.signer.sign_psbt(&mut psbt).expect("signing");
+    psbt.finalize(bob.wallet.wallet().descriptor());
+    let tx = psbt.extract().expect("finalized transaction");
+    chain.broadcast(&tx).expect("valid witness transaction");
+    chain.mine(1);
+    bob.sync();
+
+    // All the state now belongs to the single change output
+    assert_eq!(bob.balance(contract_id).confirmed, amount(300));
+    let change = Outpoint::new(tx.txid(), report.change_vout);
+    let plan = bob
+        .wallet
+        .plan_consolidation(&scope, FEERATE, params())
+        .unwrap();
+    assert_eq!(plan.inputs, bset![change]);
+    assert_eq!(plan.allocations_before(), 1);
+}
+
+#[test]
+fn consolidate_errors() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+
+    let outpoint = alice.fund(100_000);
+    alice.issue(outpoint, 1_000);
+    let coin = alice.fund_sats(10_000);
+
+    let scope = ConsolidationScope::Outpoints(bset![coin]);
+    let err = alice
+        .wallet
+        .plan_consolidation(&scope, FEERATE, params())
+        .unwrap_err();
+    assert!(matches!(err, CompositionError::NothingToConsolidate), "{err}");
+
+    alice.wallet.freeze(outpoint, "");
+    let scope = ConsolidationScope::Outpoints(bset![outpoint]);
+    let err = alice
+        .wallet
+        .consolidate(&scope, FEERATE, params())
+        .unwrap_err();
+    assert!(
+        matches!(err, PayError::Composition(CompositionError::FrozenOutput(o)) if o == outpoint),
+        "{err}"
+    );
+}
@@ -0,0 +1,108 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Selection of the outputs spent by a transfer, avoiding the outputs holding
+//! the state of unrelated contracts.
+
+mod common;
+
+use amplify::bset;
+use bpstd::{Outpoint, Sats};
+use common::{Party, FEE, NETWORK, SATS};
+use rgb::resolvers::MockChain;
+use rgb::{blank_contracts, CoinSelection, TransferParams};
+
+fn params(selection: CoinSelection) -> TransferParams {
+    let mut params = TransferParams::with(Sats::from_sats(FEE), Sats::from_sats(SATS));
+    params.coin_selection = selection;
+    params
+}
+
+#[test]
+fn prefer_isolated_outputs() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let mut carol = Party::new(&chain, 3);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(100_000);
+    for value in [400, 300] {
+        let invoice = bob.invoice(contract_id, value, false);
+        let (_, transfer) = alice.pay(&invoice);
+        chain.mine(1);
+        alice.sync();
+        bob.accept(transfer);
+        bob.sync();
+    }
+
+    // Another asset is issued to the output holding the larger allocation
+    let invoice = carol.invoice(contract_id, 200, false);
+    let plan = bob
+        .wallet
+        .plan_transfer(&invoice, params(CoinSelection::Largest))
+        .unwrap();
+    assert_eq!(plan.inputs.len(), 1);
+    let seal = plan.inputs.first().unwrap().as_reduced_unsafe();
+    let contaminated = Outpoint::new(seal.txid, seal.vout);
+    let other_id = bob.issue(contaminated, 500);
+
+    let plan = bob
+        .wallet
+        .plan_transfer(&invoice, params(CoinSelection::Largest))
+        .unwrap();
+    assert_eq!(plan.blank_contracts, bset![other_id]);
+
+    let plan = bob
+        .wallet
+        .plan_transfer(&invoice, params(CoinSelection::Isolated))
+        .unwrap();
+    assert!(plan.blank_contracts.is_empty());
+    assert_eq!(plan.inputs.len(), 1);
+    let seal = plan.inputs.first().unwrap().as_reduced_unsafe();
+    assert_ne!(Outpoint::new(seal.txid, seal.vout), contaminated);
+
+    let (psbt, ..) = bob
+        .wallet
+        .pay(&invoice, params(CoinSelection::Isolated))
+        .unwrap();
+    assert!(psbt.inputs().all(|input| input.previous_outpoint != contaminated));
+    assert!(blank_contracts(&psbt, invoice.contract).is_empty());
+}
+
+#[test]
+fn unavoidable_contamination() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let other_id = alice.issue(outpoint, 500);
+
+    let invoice = bob.invoice(contract_id, 100, false);
+    let (psbt, ..) = alice
+        .wallet
+        .pay(&invoice, params(CoinSelection::Isolated))
+        .unwrap();
+    assert_eq!(blank_contracts(&psbt, invoice.contract), bset![other_id]);
+}
@@ -0,0 +1,97 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Coordination of the cosigners of an RGB transfer passing PSBT copies
+//! around.
+
+mod common;
+
+use bpstd::Sats;
+use common::{Party, FEE, NETWORK, SATS};
+use psrgbt::{
+    CosignError, KeyMap, PropKey, ProprietaryKeyRgb, Psbt, PsbtLocation, RgbCosign, RgbSignRequest,
+};
+use rgb::resolvers::MockChain;
+use rgb::{Signer, TransferParams};
+
+fn transfer_psbt() -> (Party, Psbt) {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+
+    let invoice = bob.invoice(contract_id, 100, true);
+    let (psbt, _, _) = alice
+        .wallet
+        .pay(&invoice, TransferParams::with(Sats::from_sats(FEE), Sats::from_sats(SATS)))
+        .expect("payment");
+    (alice, psbt)
+}
+
+#[test]
+fn cosign_combine() {
+    let (alice, mut psbt) = transfer_psbt();
+    let cosigners = psbt.rgb_cosigners();
+    assert_eq!(cosigners.len(), 1);
+    let status = cosigners.values().next().unwrap();
+    assert!(!status.inputs.is_empty());
+    assert!(status.signed.is_empty());
+    assert!(!status.is_complete());
+
+    let mut copy = psbt.clone();
+    assert!(alice.signer.sign_psbt(&mut copy).unwrap() > 0);
+    let count = psbt.rgb_cosign_combine([&copy]).unwrap();
+    assert_eq!(count, psbt.inputs().count());
+    assert!(psbt
+        .rgb_cosigners()
+        .values()
+        .all(|status| status.is_complete()));
+
+    // Repeated merge doesn't add anything
+    assert_eq!(psbt.rgb_cosign_combine([&copy]).unwrap(), 0);
+}
+
+#[test]
+fn cosign_stripped() {
+    let (alice, mut psbt) = transfer_psbt();
+    let mut copy = psbt.rgb_sign_request().unwrap();
+    alice.signer.sign_psbt(&mut copy).unwrap();
+
+    let err = psbt.rgb_cosign_combine([&psbt.clone(), &copy]).unwrap_err();
+    assert_eq!(err, CosignError::RgbStripped(2, PsbtLocation::Global));
+    assert!(psbt
+        .rgb_cosigners()
+        .values()
+        .all(|status| status.signed.is_empty()));
+}
+
+#[test]
+fn cosign_altered() {
+    let (alice, mut psbt) = transfer_psbt();
+    let mut copy = psbt.clone();
+    alice.signer.sign_psbt(&mut copy).unwrap();
+    let _ = copy.push_proprietary(PropKey::rgb_summary(), b"tampered".to_vec());
+
+    let err = psbt.rgb_cosign_combine([&copy]).unwrap_err();
+    assert_eq!(err, CosignError::RgbAltered(1, PsbtLocation::Global));
+}
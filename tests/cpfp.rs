@@ -0,0 +1,120 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fee bumping of the incoming witness transaction, which feerate is too low
+//! for the mempool, with a child transaction of the beneficiary (CPFP).
+
+mod common;
+
+use std::slice;
+
+use bpstd::{Outpoint, Sats, Vout};
+use common::{amount, Party, FEE, NETWORK, SATS};
+use psrgbt::{PsbtConstructor, TxParams};
+use rgb::invoice::Beneficiary;
+use rgb::resolvers::{AnyResolver, MockChain};
+use rgb::{CompositionError, PayError, Signer, TransferParams};
+
+const MIN_FEERATE: u64 = 10;
+
+#[test]
+fn bump_incoming() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    // The beneficiary output doesn't cover the fee of the child
+    bob.fund_sats(10_000);
+    chain.set_min_feerate(MIN_FEERATE);
+
+    let invoice = bob.invoice(contract_id, 400, false);
+    let (mut psbt, _, transfer) = alice
+        .wallet
+        .pay(&invoice, TransferParams::with(Sats::from_sats(FEE), Sats::from_sats(SATS)))
+        .expect("payment");
+    alice.signer.sign_psbt(&mut psbt).expect("signing");
+    psbt.finalize(alice.wallet.wallet().descriptor());
+    let parent = psbt.extract().expect("finalized transaction");
+    assert!(chain.broadcast(&parent).is_err());
+
+    let mut resolver = AnyResolver::mock(&chain);
+    resolver.add_terminals(&transfer);
+    bob.accept(transfer);
+
+    let Beneficiary::WitnessVout(pay2vout) = invoice.beneficiary.into_inner() else {
+        unreachable!()
+    };
+    let script = pay2vout.address.script_pubkey();
+    let vout = parent
+        .outputs()
+        .position(|txout| txout.script_pubkey == script)
+        .expect("beneficiary output");
+    let foreign = (0..parent.outputs.len())
+        .find(|no| *no != vout)
+        .expect("payer change output");
+
+    let err = bob
+        .wallet
+        .bump_incoming(
+            Outpoint::new(parent.txid(), Vout::from_u32(foreign as u32)),
+            MIN_FEERATE,
+            &resolver,
+            TxParams::with(Sats::ZERO),
+        )
+        .unwrap_err();
+    assert!(matches!(err, PayError::Composition(CompositionError::ForeignOutput(_))));
+
+    let (mut psbt, meta, bumped) = bob
+        .wallet
+        .bump_incoming(
+            Outpoint::new(parent.txid(), Vout::from_u32(vout as u32)),
+            MIN_FEERATE,
+            &resolver,
+            TxParams::with(Sats::ZERO),
+        )
+        .expect("CPFP child");
+    assert_eq!(bumped.txid(), parent.txid());
+    assert!(meta.change_vout.is_some());
+    // The child spends the beneficiary output and an additional wallet UTXO
+    assert_eq!(psbt.inputs().count(), 2);
+    bob.signer.sign_psbt(&mut psbt).expect("signing");
+    psbt.finalize(bob.wallet.wallet().descriptor());
+    let child = psbt.extract().expect("finalized transaction");
+
+    let package = [parent.clone(), child.clone()];
+    assert!(chain.broadcast(&child).is_err());
+    assert!(resolver
+        .broadcast_package(slice::from_ref(&parent))
+        .is_err());
+    resolver
+        .broadcast_package(&package)
+        .expect("package reaching the feerate");
+    assert_eq!(chain.confirmations(parent.txid()), Some(0));
+    assert_eq!(chain.confirmations(child.txid()), Some(0));
+
+    chain.mine(1);
+    alice.sync();
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).confirmed, amount(400));
+    assert_eq!(alice.balance(contract_id).confirmed, amount(600));
+}
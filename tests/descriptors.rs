@@ -0,0 +1,154 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export and import of the wallet descriptors in the standard output
+//! descriptor format used by other bitcoin wallets.
+
+mod common;
+
+use amplify::hex::ToHex;
+use bpstd::{Derive, Txid};
+use common::{Party, NETWORK};
+use psrgbt::PsbtConstructor;
+use rgb::containers::Transfer;
+use rgb::resolvers::MockChain;
+use rgb::{descriptor_checksum, DescriptorImportError, DescriptorRgb, KeychainLayout, RgbDescr};
+
+fn witness_outputs(transfer: &Transfer, txid: Txid) -> Vec<String> {
+    let witness = transfer
+        .bundles
+        .iter()
+        .find(|bundle| bundle.pub_witness.as_reduced_unsafe().txid() == txid)
+        .expect("transfer witness");
+    let tx = witness
+        .pub_witness
+        .as_reduced_unsafe()
+        .tx()
+        .expect("transfer contains witness transaction");
+    tx.outputs
+        .iter()
+        .map(|out| out.script_pubkey.as_slice().to_hex())
+        .collect()
+}
+
+#[test]
+fn checksum() {
+    assert_eq!(descriptor_checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+    assert_eq!(
+        descriptor_checksum("addr(mkmZxiEcEd8ZqjQWVZuC6so5dFMKEFpN2j)").unwrap(),
+        "02wpgw69"
+    );
+    assert_eq!(descriptor_checksum("raw(dead€)"), None);
+}
+
+#[test]
+fn export_import() {
+    let chain = MockChain::new(NETWORK);
+    for party in [Party::new(&chain, 1), Party::new_wpkh(&chain, 2)] {
+        let descr = party.wallet.wallet().descriptor();
+        let layout = descr.keychain_layout();
+
+        let keychains = descr.keychain_descriptors();
+        assert_eq!(keychains.len(), layout.keychains().len());
+        let imported =
+            RgbDescr::from_std_descriptors(keychains.iter().map(|(_, d)| d.as_str()), layout)
+                .unwrap();
+        assert_eq!(&imported, descr);
+
+        let multipath = descr.multipath_descriptor();
+        assert!(multipath.contains("/<0;1>/*)#"), "{multipath}");
+        let imported = RgbDescr::from_std_descriptors([multipath.as_str()], layout).unwrap();
+        assert_eq!(&imported, descr);
+
+        let core = descr.core_descriptors(0);
+        assert_eq!(core.len(), keychains.len());
+        assert_eq!(core.iter().filter(|d| d.active).count(), 2);
+        assert_eq!(core.iter().filter(|d| d.internal).count(), 1);
+    }
+}
+
+#[test]
+fn tapret_outputs() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    let invoice = bob.invoice(contract_id, 100, false);
+    let (txid, transfer) = alice.pay(&invoice);
+    let outputs = witness_outputs(&transfer, txid);
+
+    let descr = alice.wallet.wallet().descriptor();
+    let tapret = descr.tapret_descriptors();
+    assert_eq!(tapret.len(), descr.tapret_tweaks().len());
+    assert!(!tapret.is_empty());
+    for (terminal, desc) in &tapret {
+        let spk = descr
+            .derive(terminal.keychain, terminal.index)
+            .to_script_pubkey();
+        let key = spk.as_slice()[2..].to_hex();
+        assert!(desc.starts_with(&format!("rawtr({key})#")), "{desc}");
+        assert!(outputs.contains(&spk.as_slice().to_hex()));
+    }
+
+    let core = descr.core_descriptors(0);
+    assert_eq!(core.len(), descr.keychain_descriptors().len() + tapret.len());
+    assert!(core
+        .iter()
+        .filter(|d| d.desc.starts_with("rawtr("))
+        .all(|d| !d.active));
+}
+
+#[test]
+fn import_errors() {
+    let chain = MockChain::new(NETWORK);
+    let alice = Party::new(&chain, 1);
+    let bob = Party::new_wpkh(&chain, 2);
+    let layout = KeychainLayout::STANDARD;
+    let tr = alice.wallet.wallet().descriptor().multipath_descriptor();
+    let wpkh = bob.wallet.wallet().descriptor().multipath_descriptor();
+
+    assert_eq!(
+        RgbDescr::from_std_descriptors([], layout).unwrap_err(),
+        DescriptorImportError::Empty
+    );
+    assert!(matches!(
+        RgbDescr::from_std_descriptors([tr.as_str(), wpkh.as_str()], layout).unwrap_err(),
+        DescriptorImportError::Mismatch
+    ));
+
+    let (body, checksum) = tr.rsplit_once('#').unwrap();
+    let corrupted = format!("{body}#{}", checksum.chars().rev().collect::<String>());
+    assert!(matches!(
+        RgbDescr::from_std_descriptors([corrupted.as_str()], layout).unwrap_err(),
+        DescriptorImportError::Checksum { .. }
+    ));
+    // Descriptors without checksum are accepted
+    assert!(RgbDescr::from_std_descriptors([body], layout).is_ok());
+
+    let (body, _) = wpkh.rsplit_once('#').unwrap();
+    let nested = format!("sh({body})");
+    assert!(matches!(
+        RgbDescr::from_std_descriptors([nested.as_str()], layout).unwrap_err(),
+        DescriptorImportError::Unsupported(_)
+    ));
+}
@@ -0,0 +1,96 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Comparison of consignments for the same contract.
+
+mod common;
+
+use common::{Party, NETWORK};
+use rgb::containers::ConsignmentExt;
+use rgb::resolvers::MockChain;
+use rgb::{ConsignmentDiff, ContractMismatch, XChain, XWitnessId};
+
+#[test]
+fn diff_transfers() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+
+    let invoice = bob.invoice(contract_id, 100, true);
+    let (_, first) = alice.pay(&invoice);
+    chain.mine(1);
+    alice.sync();
+
+    let invoice = bob.invoice(contract_id, 200, true);
+    let (txid, second) = alice.pay(&invoice);
+    let witness_id: XWitnessId = XChain::Bitcoin(txid);
+
+    let diff = ConsignmentDiff::new(&first, &first).unwrap();
+    assert!(diff.is_empty());
+    assert_eq!(diff.contract_id, contract_id);
+
+    let diff = ConsignmentDiff::new(&first, &second).unwrap();
+    assert!(!diff.is_empty());
+    assert!(diff.removed_bundles.is_empty());
+    assert!(diff.rewitnessed_bundles.is_empty());
+    assert_eq!(diff.added_bundles.len(), 1);
+    assert_eq!(diff.added_bundles.values().next(), Some(&witness_id));
+    assert_eq!(diff.added_witnesses.len(), 1);
+    assert!(diff.removed_witnesses.is_empty());
+
+    // the terminal moves from the first bundle to the new one
+    let added = diff.added_bundles.keys().next().unwrap();
+    let terminal = &diff.terminals[added];
+    assert_eq!(terminal.first, None);
+    assert_eq!(terminal.second, second.terminals.get(added).copied());
+    let moved = first.terminals.keys().next().unwrap();
+    assert_eq!(diff.terminals[moved].second, None);
+
+    let reversed = ConsignmentDiff::new(&second, &first).unwrap();
+    assert_eq!(reversed.removed_bundles, diff.added_bundles);
+    assert_eq!(reversed.removed_witnesses, diff.added_witnesses);
+
+    let text = diff.to_string();
+    assert!(text.contains(&format!("+ witness {witness_id}")));
+}
+
+#[test]
+fn different_contracts() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(100_000);
+    let first_id = alice.issue(outpoint, 1_000);
+    let second_id = alice.issue(outpoint, 2_000);
+
+    let stock = alice.wallet.stock();
+    let first = stock.export_contract(first_id).unwrap();
+    let second = stock.export_contract(second_id).unwrap();
+    assert_eq!(first.contract_id(), first_id);
+
+    assert_eq!(
+        ConsignmentDiff::new(&first, &second).unwrap_err(),
+        ContractMismatch(first_id, second_id)
+    );
+}
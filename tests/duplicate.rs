@@ -0,0 +1,101 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Refusal of repeated payments of the invoices which were already paid.
+
+mod common;
+
+use common::{params, Party, NETWORK};
+use rgb::resolvers::MockChain;
+use rgb::{CompositionError, PayError};
+
+#[test]
+fn duplicate_refused() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    alice.fund(100_000);
+    bob.fund(100_000);
+
+    let invoice = bob.invoice(contract_id, 100, true);
+    let (txid, _) = alice.pay(&invoice);
+    assert_eq!(alice.wallet.invoice_payment(&invoice), Some(txid));
+    chain.mine(1);
+    alice.sync();
+
+    let err = alice.wallet.pay(&invoice, params()).unwrap_err();
+    assert!(matches!(
+        err,
+        PayError::Composition(CompositionError::DuplicatePayment(_, paid)) if paid == txid
+    ));
+
+    let mut params = params();
+    params.allow_duplicate = true;
+    let (psbt, ..) = alice.wallet.pay(&invoice, params).unwrap();
+    assert_eq!(alice.wallet.invoice_payment(&invoice), Some(txid));
+    assert_ne!(psbt.txid(), txid);
+}
+
+#[test]
+fn witness_invoices_not_tracked() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    alice.fund(100_000);
+
+    let invoice = bob.invoice(contract_id, 100, false);
+    alice.pay(&invoice);
+    assert_eq!(alice.wallet.invoice_payment(&invoice), None);
+    chain.mine(1);
+    alice.sync();
+    alice.wallet.pay(&invoice, params()).unwrap();
+}
+
+#[test]
+fn unpublished_payment_released() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    alice.fund(100_000);
+    bob.fund(100_000);
+
+    // The payment is constructed but never signed and published
+    let invoice = bob.invoice(contract_id, 100, true);
+    let (psbt, ..) = alice.wallet.pay(&invoice, params()).unwrap();
+    assert_eq!(alice.wallet.invoice_payment(&invoice), Some(psbt.txid()));
+    let err = alice.wallet.pay(&invoice, params()).unwrap_err();
+    assert!(matches!(err, PayError::Composition(CompositionError::DuplicatePayment(..))));
+
+    // The witness transaction unknown to the chain is archived by sync, after
+    // which the invoice is not considered paid anymore
+    alice.sync();
+    assert_eq!(alice.wallet.invoice_payment(&invoice), None);
+    alice.wallet.construct_psbt(&invoice, params()).unwrap();
+}
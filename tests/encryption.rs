@@ -0,0 +1,121 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Encryption of the wallet data at rest.
+
+mod common;
+
+use std::str::FromStr;
+
+use bpstd::XpubDerivable;
+use common::{temp_dir, NETWORK};
+use rgb::{
+    is_encrypted, DescriptorSpec, EncryptionError, KeychainLayout, WalletDirError, WalletError,
+    WalletFactory, ENCRYPTED_DESCRIPTOR_FILE, WALLET_DESCRIPTOR_FILE,
+};
+
+const XPUB: &str = "[643a7adc/86h/1h/0h]tpubDCNiWHaiSkgnQjuhsg9kjwaUzaxQjUcmhagvYzqQ3TYJTgFGJstVaqnu4yhtFktBhCVFmBNLQ5sN53qKzZbMksm3XEyGJsEhQPfVZdWmTE2/<0;1;9;10>/*";
+
+fn spec() -> DescriptorSpec {
+    DescriptorSpec::TapretKeyOnly(XpubDerivable::from_str(XPUB).unwrap())
+}
+
+#[test]
+fn encrypted_wallet() {
+    let data_dir = temp_dir("create");
+    let factory = WalletFactory::new(&data_dir, NETWORK).with_passphrase("secret");
+    drop(
+        factory
+            .create("alice", &spec(), KeychainLayout::STANDARD)
+            .unwrap(),
+    );
+
+    let path = factory.base_dir().join("alice");
+    assert!(is_encrypted(&path));
+    assert!(!path.join(WALLET_DESCRIPTOR_FILE).exists());
+    let data = std::fs::read(path.join(ENCRYPTED_DESCRIPTOR_FILE)).unwrap();
+    assert!(!String::from_utf8_lossy(&data).contains("643a7adc"));
+    assert!(factory.wallets().exists("alice"));
+
+    let wallet = factory.open("alice").unwrap();
+    assert_eq!(wallet.wallet().network(), NETWORK);
+    drop(wallet);
+
+    let locked = WalletFactory::new(&data_dir, NETWORK);
+    assert!(matches!(locked.open("alice"), Err(WalletError::WalletLocked(_))));
+    assert!(matches!(locked.wallets().info("alice"), Err(WalletDirError::Locked(_))));
+
+    let wrong = WalletFactory::new(&data_dir, NETWORK).with_passphrase("wrong");
+    assert!(matches!(
+        wrong.open("alice"),
+        Err(WalletError::Encryption(EncryptionError::WrongPassphrase(_)))
+    ));
+}
+
+#[test]
+fn encrypt_and_decrypt() {
+    let data_dir = temp_dir("convert");
+    let factory = WalletFactory::new(&data_dir, NETWORK);
+    drop(
+        factory
+            .create("bob", &spec(), KeychainLayout::STANDARD)
+            .unwrap(),
+    );
+    let wallets = factory.wallets();
+    let path = factory.base_dir().join("bob");
+
+    wallets.encrypt("bob", "secret").unwrap();
+    assert!(is_encrypted(&path));
+    assert!(!path.join(WALLET_DESCRIPTOR_FILE).exists());
+    assert!(matches!(wallets.encrypt("bob", "secret"), Err(WalletDirError::AlreadyEncrypted(_))));
+    assert!(matches!(
+        wallets.decrypt("bob", "wrong"),
+        Err(WalletDirError::Encryption(EncryptionError::WrongPassphrase(_)))
+    ));
+
+    wallets.decrypt("bob", "secret").unwrap();
+    assert!(!is_encrypted(&path));
+    assert_eq!(wallets.info("bob").unwrap().network, NETWORK);
+    assert!(matches!(wallets.decrypt("bob", "secret"), Err(WalletDirError::NotEncrypted(_))));
+}
+
+#[test]
+fn plaintext_leftovers_removed() {
+    let data_dir = temp_dir("leftovers");
+    let factory = WalletFactory::new(&data_dir, NETWORK);
+    drop(
+        factory
+            .create("carol", &spec(), KeychainLayout::STANDARD)
+            .unwrap(),
+    );
+    let wallets = factory.wallets();
+    let path = factory.base_dir().join("carol");
+    let descriptor = std::fs::read(path.join(WALLET_DESCRIPTOR_FILE)).unwrap();
+
+    // Plaintext descriptor which failed to be removed on encryption
+    wallets.encrypt("carol", "secret").unwrap();
+    std::fs::write(path.join(WALLET_DESCRIPTOR_FILE), descriptor).unwrap();
+
+    wallets.encrypt("carol", "secret").unwrap();
+    assert!(is_encrypted(&path));
+    assert!(!path.join(WALLET_DESCRIPTOR_FILE).exists());
+    assert!(matches!(wallets.encrypt("carol", "secret"), Err(WalletDirError::AlreadyEncrypted(_))));
+}
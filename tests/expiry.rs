@@ -0,0 +1,134 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Expiry of invoices by time and block height, and release of the addresses
+//! reserved by the witness-out invoices which have expired unpaid.
+
+mod common;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bpstd::Sats;
+use common::{params, Party, NETWORK, SATS};
+use rgb::resolvers::{AnyResolver, MockChain};
+use rgb::{AddressReservations, CompositionError, InvoiceExpiry, INVOICE_QUERY_EXPIRY_HEIGHT};
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time after UNIX epoch")
+        .as_secs() as i64
+}
+
+#[test]
+fn expired_invoice_refused() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    // The expiry timestamp is in the future, since invoices expired by the
+    // wall clock are refused by the stock regardless of the transfer params
+    let timestamp = unix_now() + 3600;
+    let mut invoice = bob.invoice(contract_id, 100, false);
+    let expiry = InvoiceExpiry::new(Some(timestamp), Some(chain.tip_height() + 2));
+    expiry.set_to_invoice(&mut invoice);
+    assert_eq!(InvoiceExpiry::from_invoice(&invoice).unwrap(), expiry);
+
+    let mut checked = params();
+    checked.now = Some(timestamp - 1);
+    checked.tip_height = Some(chain.tip_height());
+    alice
+        .wallet
+        .construct_psbt(&invoice, checked.clone())
+        .unwrap();
+
+    chain.mine(2);
+    checked.tip_height = Some(chain.tip_height());
+    let err = alice
+        .wallet
+        .construct_psbt(&invoice, checked.clone())
+        .unwrap_err();
+    assert!(matches!(err, CompositionError::InvoiceExpired));
+
+    checked.tip_height = None;
+    checked.now = Some(timestamp);
+    let err = alice.wallet.construct_psbt(&invoice, checked).unwrap_err();
+    assert!(matches!(err, CompositionError::InvoiceExpired));
+
+    // Expiry is not checked unless the current time and height are given
+    alice.wallet.construct_psbt(&invoice, params()).unwrap();
+
+    invoice
+        .unknown_query
+        .insert(INVOICE_QUERY_EXPIRY_HEIGHT.to_owned(), "soon".to_owned());
+    let err = alice.wallet.construct_psbt(&invoice, params()).unwrap_err();
+    assert!(matches!(err, CompositionError::InvalidExpiryHeight(_)));
+}
+
+#[test]
+fn expired_address_released() {
+    let chain = MockChain::new(NETWORK);
+    let mut bob = Party::new(&chain, 2);
+    let resolver = AnyResolver::mock(&chain);
+
+    let mut reservations = AddressReservations::new();
+    let expiry = InvoiceExpiry::new(None, Some(chain.tip_height() + 1));
+    let (paid, address) = bob.wallet.reserve_rgb_address(&mut reservations, expiry);
+    let (unpaid, _) = bob.wallet.reserve_rgb_address(&mut reservations, expiry);
+    assert_ne!(paid, unpaid);
+    chain.fund(&address.script_pubkey(), Sats::from_sats(SATS));
+
+    let released = bob
+        .wallet
+        .release_expired(&mut reservations, &resolver, 0, chain.tip_height())
+        .unwrap();
+    assert!(released.is_empty());
+    assert_eq!(reservations.expiry(unpaid), Some(expiry));
+
+    chain.mine(1);
+    let released = bob
+        .wallet
+        .release_expired(&mut reservations, &resolver, 0, chain.tip_height())
+        .unwrap();
+    assert_eq!(released, vec![unpaid]);
+    assert_eq!(reservations.expiry(paid), None);
+    assert_eq!(reservations.released().collect::<Vec<_>>(), vec![unpaid]);
+
+    let path = std::env::temp_dir().join(format!("rgb-reservations-{}.yaml", std::process::id()));
+    reservations.save_file(&path).unwrap();
+    assert_eq!(AddressReservations::load_file(&path).unwrap(), reservations);
+    std::fs::remove_file(&path).unwrap();
+
+    // The released address is reused by the next invoice, which doesn't
+    // reserve it since it never expires
+    let (terminal, _) = bob
+        .wallet
+        .reserve_rgb_address(&mut reservations, InvoiceExpiry::default());
+    assert_eq!(terminal, unpaid);
+    assert!(reservations.is_empty());
+    let (terminal, _) = bob
+        .wallet
+        .reserve_rgb_address(&mut reservations, InvoiceExpiry::default());
+    assert!(terminal != paid && terminal != unpaid);
+}
@@ -0,0 +1,92 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export of the contract history graph for the explorers.
+
+mod common;
+
+use common::{amount, Party, NETWORK};
+use rgb::resolvers::MockChain;
+use rgb::vm::WitnessOrd;
+use rgb::{ContractGraph, ExploreError, XChain};
+
+#[test]
+fn contract_graph() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    let invoice = bob.invoice(contract_id, 100, false);
+    let (txid, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    bob.accept(transfer);
+    bob.sync();
+
+    let graph = ContractGraph::build(bob.wallet.stock(), contract_id).unwrap();
+    assert_eq!(graph.contract_id, contract_id);
+    assert_eq!(graph.operations.len(), 2);
+    let genesis = &graph.operations[0];
+    assert!(genesis.witness_id.is_none());
+    let transition = &graph.operations[1];
+
+    let witness = graph
+        .witnesses
+        .iter()
+        .find(|w| w.witness_id.as_reduced_unsafe() == &txid)
+        .expect("witness");
+    assert_eq!(witness.height, Some(chain.tip_height()));
+    assert!(matches!(witness.status, Some(WitnessOrd::Mined(_))));
+    assert_eq!(transition.witness_id, Some(witness.witness_id));
+    assert_eq!(transition.bundle_id.as_ref(), witness.bundles.first());
+
+    // Issued state flows into the transition, which allocates the payment
+    let issued = graph.allocations_by(genesis.opid).collect::<Vec<_>>();
+    assert_eq!(issued.len(), 1);
+    assert_eq!(issued[0].spent_by, Some(transition.opid));
+    let paid = graph
+        .allocations_by(transition.opid)
+        .find(|a| a.amount == Some(amount(100)))
+        .expect("payment allocation");
+    assert_eq!(paid.seal.map(|seal| seal.map(|seal| seal.txid)), Some(XChain::Bitcoin(txid)));
+    assert!(paid.spent_by.is_none());
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph"));
+    assert!(dot.contains(&format!("\"{}\" -> \"{}\"", issued[0].opout, transition.opid)));
+    assert!(dot.contains(&format!("\"{}\" -> \"{}\"", transition.opid, paid.opout)));
+    assert!(dot.contains(&format!("mined at {}", chain.tip_height())));
+}
+
+#[test]
+fn unknown_contract() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    assert_eq!(
+        ContractGraph::build(bob.wallet.stock(), contract_id).unwrap_err(),
+        ExploreError::UnknownContract(contract_id)
+    );
+}
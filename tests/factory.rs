@@ -0,0 +1,77 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Creation and opening of the wallets in the data directory.
+
+mod common;
+
+use std::str::FromStr;
+
+use bpstd::{Network, XpubDerivable};
+use common::{temp_dir, NETWORK};
+use rgb::{
+    DescriptorSpec, KeychainLayout, WalletDirError, WalletError, WalletFactory,
+    WALLET_DESCRIPTOR_FILE,
+};
+
+fn spec() -> DescriptorSpec {
+    let xpub = XpubDerivable::from_str(
+        "[643a7adc/86h/1h/0h]tpubDCNiWHaiSkgnQjuhsg9kjwaUzaxQjUcmhagvYzqQ3TYJTgFGJstVaqnu4yhtFktBhCVFmBNLQ5sN53qKzZbMksm3XEyGJsEhQPfVZdWmTE2/<0;1;9;10>/*",
+    )
+    .unwrap();
+    DescriptorSpec::TapretKeyOnly(xpub)
+}
+
+#[test]
+fn create_and_open() {
+    let data_dir = temp_dir("create");
+    let factory = WalletFactory::new(&data_dir, NETWORK);
+    assert_eq!(factory.base_dir(), data_dir.join(NETWORK.to_string()));
+
+    let wallet = factory
+        .create("alice", &spec(), KeychainLayout::STANDARD)
+        .unwrap();
+    assert_eq!(wallet.wallet().network(), NETWORK);
+    assert!(factory
+        .base_dir()
+        .join("alice")
+        .join(WALLET_DESCRIPTOR_FILE)
+        .is_file());
+    assert!(matches!(
+        factory.create("alice", &spec(), KeychainLayout::STANDARD),
+        Err(WalletError::WalletDir(WalletDirError::AlreadyExists(_)))
+    ));
+    drop(wallet);
+
+    let wallet = factory.open("alice").unwrap();
+    assert_eq!(wallet.wallet_names().collect::<Vec<_>>(), vec!["alice"]);
+    assert_eq!(factory.wallets().list().unwrap(), vec!["alice".to_owned()]);
+    drop(wallet);
+
+    assert!(matches!(
+        factory.open("bob"),
+        Err(WalletError::WalletDir(WalletDirError::NotFound(_)))
+    ));
+
+    // Wallet is kept in the data directory of other network
+    let factory = WalletFactory::in_dir(factory.base_dir(), Network::Mainnet);
+    assert!(matches!(factory.open("alice"), Err(WalletError::NetworkMismatch(_))));
+}
@@ -0,0 +1,87 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Witness transactions paying fee at the requested feerate, with the size of
+//! the commitment outputs accounted for.
+
+mod common;
+
+use bpstd::{Sats, Weight};
+use common::{Party, NETWORK, SATS};
+use psrgbt::PsbtConstructor;
+use rgb::resolvers::MockChain;
+use rgb::{effective_feerate, estimate_vsize, Signer, TransferParams};
+
+const FEERATE: u64 = 10;
+
+/// Pays the invoice at the [`FEERATE`] and checks the feerate of the signed
+/// witness transaction against the estimation.
+fn check_feerate(mut alice: Party, mut bob: Party, opret: bool) {
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let invoice = bob.invoice(contract_id, 100, false);
+    let params = TransferParams::with_feerate(FEERATE, Sats::from_sats(SATS));
+    let (mut psbt, _, _) = alice.wallet.pay(&invoice, params).expect("payment");
+    assert_eq!(psbt.outputs().any(|output| output.script.is_op_return()), opret);
+
+    let fee = psbt.fee().expect("fee").sats();
+    let vsize = estimate_vsize(&psbt) as u64;
+    assert_eq!(fee, FEERATE * vsize);
+    assert_eq!(effective_feerate(&psbt), Some(FEERATE as f64));
+
+    alice.signer.sign_psbt(&mut psbt).expect("signing");
+    psbt.finalize(alice.wallet.wallet().descriptor());
+    let tx = psbt.extract().expect("finalized transaction");
+    let actual = tx.vbytes().to_u32() as u64;
+    // The estimation never underpays and is off by less than a vbyte per input
+    assert!(vsize >= actual, "estimated {vsize} vbytes, actual {actual}");
+    assert!(vsize - actual <= tx.inputs().count() as u64, "estimated {vsize}, actual {actual}");
+
+    alice.chain.set_min_feerate(FEERATE);
+    alice
+        .chain
+        .broadcast(&tx)
+        .expect("transaction reaching the feerate");
+}
+
+#[test]
+fn feerate_tapret() {
+    let chain = MockChain::new(NETWORK);
+    check_feerate(Party::new(&chain, 1), Party::new(&chain, 2), false);
+}
+
+#[test]
+fn feerate_opret() {
+    let chain = MockChain::new(NETWORK);
+    check_feerate(Party::new_wpkh(&chain, 1), Party::new_wpkh(&chain, 2), true);
+}
+
+#[test]
+fn feerate_no_funds() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let invoice = bob.invoice(contract_id, 100, false);
+    let params = TransferParams::with_feerate(1_000, Sats::from_sats(SATS));
+    assert!(alice.wallet.pay(&invoice, params).is_err());
+}
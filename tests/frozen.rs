@@ -0,0 +1,102 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Frozen outpoints, which are excluded from payments.
+
+mod common;
+
+use common::{params, Party, NETWORK};
+use rgb::resolvers::MockChain;
+use rgb::{BasketInvoice, CompositionError, FrozenOutpoints};
+
+#[test]
+fn frozen_file() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let first = alice.fund(10_000);
+    let second = alice.fund(20_000);
+
+    let mut frozen = FrozenOutpoints::new();
+    assert_eq!(frozen.freeze(first, "swap collateral"), None);
+    assert_eq!(frozen.freeze(second, ""), None);
+    assert_eq!(frozen.freeze(second, "compliance hold"), Some(String::new()));
+    assert!(frozen.is_frozen(first));
+    assert_eq!(frozen.reason(second), Some("compliance hold"));
+
+    let path = std::env::temp_dir().join(format!("rgb-frozen-{}.yaml", std::process::id()));
+    frozen.save_file(&path).unwrap();
+    let loaded = FrozenOutpoints::load_file(&path).unwrap();
+    assert_eq!(loaded, frozen);
+    std::fs::remove_file(&path).unwrap();
+    assert!(FrozenOutpoints::load_file(&path).unwrap().is_empty());
+
+    assert_eq!(frozen.unfreeze(first), Some("swap collateral".to_owned()));
+    assert_eq!(frozen.unfreeze(first), None);
+    assert_eq!(frozen.len(), 1);
+}
+
+#[test]
+fn frozen_state() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let invoice = bob.invoice(contract_id, 400, false);
+
+    alice.wallet.freeze(outpoint, "compliance hold");
+    assert_eq!(alice.wallet.frozen().reason(outpoint), Some("compliance hold"));
+    assert!(alice.wallet.plan_transfer(&invoice, params()).is_err());
+    assert!(alice.wallet.construct_psbt(&invoice, params()).is_err());
+
+    // Outpoints frozen with the transfer parameters are respected as well
+    alice.wallet.unfreeze(outpoint);
+    let mut frozen = params();
+    frozen.frozen.insert(outpoint);
+    assert!(alice.wallet.construct_psbt(&invoice, frozen).is_err());
+
+    let (psbt, _) = alice.wallet.construct_psbt(&invoice, params()).unwrap();
+    assert!(psbt
+        .inputs()
+        .any(|input| input.previous_outpoint == outpoint));
+}
+
+#[test]
+fn frozen_coins() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let coin = alice.fund_sats(50_000);
+    let basket = BasketInvoice::new([bob.invoice(contract_id, 400, false)]).unwrap();
+
+    // The basket fee is paid from the coins which don't hold RGB state
+    alice.wallet.freeze(coin, "");
+    let err = alice.wallet.pay_basket(&basket, params()).unwrap_err();
+    assert!(matches!(err, rgb::PayError::Composition(CompositionError::Construction(_))), "{err}");
+
+    alice.wallet.unfreeze(coin);
+    let (psbt, _, _) = alice.wallet.pay_basket(&basket, params()).unwrap();
+    assert!(psbt.inputs().any(|input| input.previous_outpoint == coin));
+}
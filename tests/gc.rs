@@ -0,0 +1,124 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Garbage collection of the blinded seals which were created for invoices
+//! but can't receive any state anymore.
+
+mod common;
+
+use common::{amount, Party, NETWORK};
+use rgb::invoice::{Beneficiary, RgbInvoice};
+use rgb::persistence::StashReadProvider;
+use rgb::resolvers::MockChain;
+use rgb::{SealExpiry, SecretSeal, XChain};
+
+fn secret(invoice: &RgbInvoice) -> SecretSeal {
+    match invoice.beneficiary.into_inner() {
+        Beneficiary::BlindedSeal(secret) => secret,
+        Beneficiary::WitnessVout(_) => panic!("invoice is not blinded"),
+    }
+}
+
+fn is_stored(party: &Party, invoice: &RgbInvoice) -> bool {
+    party
+        .wallet
+        .stock()
+        .as_stash_provider()
+        .seal_secret(XChain::Bitcoin(secret(invoice)))
+        .expect("stash access")
+        .is_some()
+}
+
+#[test]
+fn gc_expired() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+
+    let expiring = bob.invoice(contract_id, 100, true);
+    let permanent = bob.invoice(contract_id, 200, true);
+    let mut expiry = SealExpiry::new();
+    expiry.insert(secret(&expiring), 1_000);
+
+    let pruned = bob.wallet.gc(&mut expiry, 999).unwrap();
+    assert!(pruned.is_empty());
+    assert!(is_stored(&bob, &expiring));
+
+    let pruned = bob.wallet.gc(&mut expiry, 1_000).unwrap();
+    assert_eq!(pruned.len(), 1);
+    assert!(!is_stored(&bob, &expiring));
+    assert!(is_stored(&bob, &permanent));
+    assert!(expiry.is_empty());
+}
+
+#[test]
+fn gc_retains_used() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+
+    let invoice = bob.invoice(contract_id, 400, true);
+    let (_, consignment) = alice.pay(&invoice);
+    bob.accept(consignment);
+    chain.mine(1);
+    bob.sync();
+
+    // The seal has received the state, thus it is never pruned, even if the
+    // invoice has expired
+    let mut expiry = SealExpiry::new();
+    expiry.insert(secret(&invoice), 0);
+    let pruned = bob.wallet.gc(&mut expiry, 1_000).unwrap();
+    assert!(pruned.is_empty());
+    assert!(is_stored(&bob, &invoice));
+    assert_eq!(bob.balance(contract_id).confirmed, amount(400));
+}
+
+#[test]
+fn gc_spent() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    // The seal is defined over the only wallet UTXO, which gets spent by the
+    // payment to bob
+    let unpaid = alice.invoice(contract_id, 100, true);
+    let mut expiry = SealExpiry::new();
+    assert!(alice.wallet.gc(&mut expiry, 0).unwrap().is_empty());
+
+    let invoice = bob.invoice(contract_id, 400, false);
+    let (_, consignment) = alice.pay(&invoice);
+    bob.accept(consignment);
+    chain.mine(1);
+    alice.sync();
+
+    let pruned = alice.wallet.gc(&mut expiry, 0).unwrap();
+    assert_eq!(pruned.len(), 1);
+    assert!(!is_stored(&alice, &unpaid));
+    assert_eq!(alice.balance(contract_id).confirmed, amount(600));
+}
@@ -0,0 +1,149 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signing of the contract genesis with the issuer key and verification of the
+//! issuer identity by the receiver of the consignment.
+
+mod common;
+
+use std::str::FromStr;
+
+use bpstd::secp256k1::{Keypair, SECP256K1};
+use bpstd::Outpoint;
+use common::{amount, Party, NETWORK};
+use rgb::containers::{ConsignmentExt, ContentId, Transfer};
+use rgb::resolvers::{ContractIssueResolver, MockChain};
+use rgb::{
+    sign_issuer, Bip340Verifier, ContractId, ContractInfoExt, DescriptorRgb, Identity,
+    IdentityError, IssuanceTemplate, IssuerSigStock, IssuerStatus, OutputSeal, Precision,
+};
+
+fn keypair(byte: u8) -> Keypair { Keypair::from_seckey_slice(SECP256K1, &[byte; 32]).unwrap() }
+
+fn identity(keypair: &Keypair) -> Identity {
+    Identity::from_str(&format!("ssi:acme bip340:{}", keypair.x_only_public_key().0)).unwrap()
+}
+
+/// Issues RGB20 asset on behalf of the issuer with the given identity.
+fn issue(party: &mut Party, outpoint: Outpoint, issuer: Identity) -> ContractId {
+    let method = party.wallet.wallet().seal_close_method();
+    let mut issuance = rgb::Rgb20Issuance::new("ACME", "Acme asset", Precision::Indivisible);
+    issuance.allocate(OutputSeal::new(method, outpoint), 1_000);
+    let contract = issuance.issue(party.wallet.stock(), issuer, None).unwrap();
+    let contract_id = contract.contract_id();
+    party
+        .wallet
+        .stock_mut()
+        .import_contract(contract, &ContractIssueResolver)
+        .unwrap();
+    contract_id
+}
+
+fn issuer_status(party: &Party, contract_id: ContractId) -> IssuerStatus {
+    let stock = party.wallet.stock();
+    stock
+        .contracts()
+        .unwrap()
+        .find(|info| info.id == contract_id)
+        .expect("known contract")
+        .issuer_verified(stock, &Bip340Verifier)
+        .unwrap()
+}
+
+/// Issues signed contract and pays part of it to Bob.
+fn signed_transfer(
+    chain: &MockChain,
+    alice: &mut Party,
+    bob: &mut Party,
+) -> (ContractId, Transfer) {
+    let issuer = keypair(0x42);
+    let outpoint = alice.fund(100_000);
+    let contract_id = issue(alice, outpoint, identity(&issuer));
+    assert_eq!(issuer_status(alice, contract_id), IssuerStatus::Unsigned);
+    alice
+        .wallet
+        .stock_mut()
+        .sign_issuer(contract_id, &issuer)
+        .unwrap();
+    assert_eq!(issuer_status(alice, contract_id), IssuerStatus::Verified);
+
+    bob.fund(10_000);
+    let invoice = bob.invoice(contract_id, 100, true);
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    assert!(transfer
+        .signatures
+        .contains_key(&ContentId::Genesis(contract_id)));
+    (contract_id, transfer)
+}
+
+#[test]
+fn identity_verified() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let (contract_id, transfer) = signed_transfer(&chain, &mut alice, &mut bob);
+    bob.accept(transfer);
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).confirmed, amount(100));
+    assert_eq!(issuer_status(&bob, contract_id), IssuerStatus::Verified);
+}
+
+#[test]
+fn identity_tampered() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let (contract_id, mut transfer) = signed_transfer(&chain, &mut alice, &mut bob);
+    // Signature is replaced with the one made by some other key over the same
+    // genesis
+    let spoofer = keypair(0x66);
+    let sig = sign_issuer(&identity(&spoofer), contract_id, &spoofer).unwrap();
+    let sigs = transfer
+        .signatures
+        .get_mut(&ContentId::Genesis(contract_id))
+        .unwrap();
+    let issuer = sigs.keys().next().unwrap().clone();
+    sigs.insert(issuer, sig).unwrap();
+
+    bob.accept(transfer);
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).confirmed, amount(100));
+    assert_eq!(issuer_status(&bob, contract_id), IssuerStatus::Invalid);
+}
+
+#[test]
+fn identity_key_mismatch() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(100_000);
+    let contract_id = issue(&mut alice, outpoint, identity(&keypair(0x42)));
+
+    let err = alice
+        .wallet
+        .stock_mut()
+        .sign_issuer(contract_id, &keypair(0x66))
+        .unwrap_err();
+    assert!(matches!(err, IdentityError::KeyMismatch));
+    assert_eq!(issuer_status(&alice, contract_id), IssuerStatus::Unsigned);
+}
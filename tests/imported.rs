@@ -0,0 +1,102 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Payments spending outputs which are not derived from the wallet descriptor.
+
+mod common;
+
+use bpstd::{Sats, SigScript, Witness, WitnessScript};
+use common::{Party, FEE, NETWORK, SATS};
+use psrgbt::{PsbtConstructor, RgbInExt};
+use rgb::resolvers::MockChain;
+use rgb::{ImportedUtxo, ImportedUtxos, RedeemInfo, Signer, TransferParams};
+
+/// Witness script which anyone can spend, standing for the script controlled
+/// by a third party.
+fn anyone_can_spend() -> WitnessScript { WitnessScript::from_unsafe(vec![0x51]) }
+
+#[test]
+fn imported_spend() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new_wpkh(&chain, 1);
+    let mut bob = Party::new_wpkh(&chain, 2);
+
+    let witness_script = anyone_can_spend();
+    let script = witness_script.to_script_pubkey();
+    let value = Sats::from_sats(100_000_u64);
+    let outpoint = chain.fund(&script, value);
+    chain.mine(1);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    let redeem_info = RedeemInfo {
+        redeem_script: None,
+        witness_script: Some(witness_script.clone()),
+    };
+    assert!(alice
+        .wallet
+        .import_utxo(outpoint, value, script, redeem_info)
+        .is_none());
+
+    let invoice = bob.invoice(contract_id, 100, false);
+    let params = TransferParams::with(Sats::from_sats(FEE), Sats::from_sats(SATS));
+    let (mut psbt, _, transfer) = alice.wallet.pay(&invoice, params).expect("payment");
+    assert!(alice.wallet.imported().is_empty());
+
+    let input = psbt
+        .inputs_mut()
+        .find(|input| input.previous_outpoint == outpoint)
+        .expect("imported output is spent");
+    assert!(input.is_rgb_external());
+    assert_eq!(input.witness_script, Some(witness_script.clone()));
+    assert!(input.bip32_derivation.is_empty());
+    input.final_script_sig = Some(SigScript::new());
+    input.final_witness = Some(Witness::from_consensus_stack([witness_script.to_vec()]));
+
+    alice.signer.sign_psbt(&mut psbt).expect("signing");
+    psbt.finalize(alice.wallet.wallet().descriptor());
+    let tx = psbt.extract().expect("finalized transaction");
+    chain.broadcast(&tx).expect("valid witness transaction");
+    chain.mine(1);
+
+    bob.accept(transfer);
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).confirmed.value(), 100);
+}
+
+#[test]
+fn imported_file() {
+    let chain = MockChain::new(NETWORK);
+    let witness_script = anyone_can_spend();
+    let script = witness_script.to_script_pubkey();
+    let outpoint = chain.fund(&script, Sats::from_sats(10_000_u64));
+
+    let mut imported = ImportedUtxos::new();
+    imported.import(ImportedUtxo::new(outpoint, Sats::from_sats(10_000_u64), script, RedeemInfo {
+        redeem_script: None,
+        witness_script: Some(witness_script),
+    }));
+    let path = std::env::temp_dir().join(format!("rgb-imported-{}.yaml", std::process::id()));
+    imported.save_file(&path).unwrap();
+    let loaded = ImportedUtxos::load_file(&path).unwrap();
+    assert_eq!(loaded, imported);
+    std::fs::remove_file(&path).unwrap();
+    assert!(ImportedUtxos::load_file(&path).unwrap().is_empty());
+}
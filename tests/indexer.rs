@@ -0,0 +1,142 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Authentication and retrying of the requests to the hosted esplora
+//! instances.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use rgb::resolvers::{AnyResolver, ConnectionOpts, ResolverConfig};
+
+const REGTEST_GENESIS: &str = "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206";
+
+/// Serves a single request with the regtest genesis block hash, returning the
+/// received request headers.
+fn serve_genesis() -> (String, thread::JoinHandle<Vec<String>>) { serve_flaky(0) }
+
+/// Fails the first `failures` requests with HTTP 503 error and serves the next
+/// one with the regtest genesis block hash, returning the headers of the last
+/// received request.
+fn serve_flaky(failures: usize) -> (String, thread::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        let mut headers = vec![];
+        for no in 0..=failures {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            headers.clear();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end().to_owned();
+                if line.is_empty() {
+                    break;
+                }
+                headers.push(line.to_lowercase());
+            }
+            let (status, body) = if no < failures {
+                ("503 Service Unavailable", "")
+            } else {
+                ("200 OK", REGTEST_GENESIS)
+            };
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        }
+        headers
+    });
+    (url, handle)
+}
+
+fn retry_opts(retries: u8) -> ConnectionOpts {
+    ConnectionOpts {
+        resolver: ResolverConfig {
+            retries,
+            backoff: Duration::from_millis(10),
+            ..ResolverConfig::default()
+        },
+        ..ConnectionOpts::default()
+    }
+}
+
+#[test]
+fn esplora_headers() {
+    let (url, server) = serve_genesis();
+    let opts = ConnectionOpts {
+        headers: [("X-Api-Key".to_owned(), "secret-key".to_owned())].into(),
+        bearer_token: Some("secret-token".to_owned()),
+        ..ConnectionOpts::default()
+    };
+    let resolver = AnyResolver::esplora_blocking_with(&url, &opts).unwrap();
+    assert_eq!(resolver.resolve_block_hash(0).unwrap().to_string(), REGTEST_GENESIS);
+
+    let headers = server.join().unwrap();
+    assert!(headers[0].starts_with("get /block-height/0 "), "{headers:?}");
+    assert!(headers.contains(&"x-api-key: secret-key".to_owned()), "{headers:?}");
+    assert!(headers.contains(&"authorization: bearer secret-token".to_owned()), "{headers:?}");
+}
+
+#[test]
+fn esplora_no_headers() {
+    let (url, server) = serve_genesis();
+    let resolver = AnyResolver::esplora_blocking_with(&url, &ConnectionOpts::default()).unwrap();
+    resolver.resolve_block_hash(0).unwrap();
+
+    let headers = server.join().unwrap();
+    assert!(
+        !headers
+            .iter()
+            .any(|header| header.starts_with("authorization:")),
+        "{headers:?}"
+    );
+}
+
+#[test]
+fn esplora_retries() {
+    let (url, server) = serve_flaky(2);
+    let resolver = AnyResolver::esplora_blocking_with(&url, &retry_opts(2)).unwrap();
+    assert_eq!(resolver.resolve_block_hash(0).unwrap().to_string(), REGTEST_GENESIS);
+
+    let headers = server.join().unwrap();
+    assert!(headers[0].starts_with("get /block-height/0 "), "{headers:?}");
+}
+
+#[test]
+fn esplora_retries_exhausted() {
+    let (url, _server) = serve_flaky(2);
+    let resolver = AnyResolver::esplora_blocking_with(&url, &retry_opts(1)).unwrap();
+    let err = resolver.resolve_block_hash(0).unwrap_err();
+    assert!(err.contains("failed after 2 attempts"), "{err}");
+}
+
+#[test]
+fn esplora_no_retries() {
+    let (url, _server) = serve_flaky(1);
+    let resolver = AnyResolver::esplora_blocking_with(&url, &retry_opts(0)).unwrap();
+    assert!(resolver.resolve_block_hash(0).is_err());
+}
@@ -0,0 +1,95 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export of the stock with the auxiliary data into a single file and its
+//! import into another stock backend.
+
+mod common;
+
+use std::fs;
+
+use common::temp_dir;
+use rgb::containers::{Contract, FileContent};
+use rgb::persistence::Stock;
+use rgb::resolvers::ContractIssueResolver;
+use rgb::{BackupStore, StockExport, StockExportError, TapretTweaks, STOCK_EXPORT_MAGIC};
+
+fn export_with_contract() -> StockExport {
+    let mut stock = Stock::in_memory();
+    let contract = Contract::load_file("examples/rgb20-demo.rgb")
+        .unwrap()
+        .validate(&ContractIssueResolver, true)
+        .unwrap();
+    stock
+        .import_contract(contract, &ContractIssueResolver)
+        .unwrap();
+    let mut export = StockExport::with_stock(&stock).unwrap();
+    export.set_tapret_tweaks("alice", &TapretTweaks::default());
+    export.add_file("paid.yaml", b"{}\n".to_vec());
+    export
+}
+
+#[test]
+fn round_trip() {
+    let dir = temp_dir("round-trip");
+    let path = dir.join("stock.rgbx");
+    let export = export_with_contract();
+    export.save_file(&path).unwrap();
+
+    let imported = StockExport::load_file(&path).unwrap();
+    assert_eq!(imported, export);
+    assert_eq!(imported.files().collect::<Vec<_>>(), vec![("paid.yaml", b"{}\n".as_slice())]);
+    assert_eq!(imported.tapret_tweaks().unwrap().keys().collect::<Vec<_>>(), vec!["alice"]);
+
+    // The imported stock is persisted by the file backend
+    let mut stock = imported.stock().unwrap();
+    assert_eq!(stock.as_stash_provider().debug_geneses().len(), 1);
+    stock
+        .make_persistent(BackupStore::new(dir.join("stock"), 0).unwrap(), true)
+        .unwrap();
+    assert!(dir.join("stock").join("stash.dat").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn damaged_export() {
+    let mut data = export_with_contract().to_bytes();
+
+    let mut version = data.clone();
+    version[STOCK_EXPORT_MAGIC.len()] = 0xFF;
+    assert!(matches!(
+        StockExport::from_bytes(&version),
+        Err(StockExportError::UnsupportedVersion(_))
+    ));
+
+    assert!(matches!(
+        StockExport::from_bytes(&data[..data.len() - 1]),
+        Err(StockExportError::Truncated)
+    ));
+
+    // Last byte is a part of the checksum of the last entry
+    let last = data.len() - 1;
+    data[last] ^= 0xFF;
+    assert!(matches!(StockExport::from_bytes(&data), Err(StockExportError::Checksum(_))));
+
+    assert!(matches!(StockExport::from_bytes(b"RGBWENC1"), Err(StockExportError::NotExport)));
+}
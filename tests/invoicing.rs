@@ -0,0 +1,112 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validation of invoices against the contract API.
+
+mod common;
+
+use common::{Party, NETWORK};
+use rgb::invoice::{Allocation, InvoiceState, NonFungible};
+use rgb::resolvers::MockChain;
+use rgb::{ContractId, InvoiceApiError, InvoiceValidation, ValidatedInvoiceBuilder, RGB20_IFACE};
+use strict_types::FieldName;
+
+#[test]
+fn invoice_valid() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let mut invoice = alice.invoice(contract_id, 100, true);
+    let stock = alice.wallet.stock();
+    let iface = stock.iface(RGB20_IFACE).unwrap();
+
+    invoice.validate_in(stock).unwrap();
+    invoice.validate_against(iface).unwrap();
+
+    // Invoices without amount let the payer choose it
+    invoice.owned_state = InvoiceState::Void;
+    invoice.validate_in(stock).unwrap();
+
+    invoice.iface = None;
+    invoice.validate_in(stock).unwrap();
+}
+
+#[test]
+fn invoice_invalid() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let invoice = alice.invoice(contract_id, 100, true);
+    let stock = alice.wallet.stock();
+
+    let mut wrong = invoice.clone();
+    wrong.operation = Some(FieldName::from("mint"));
+    assert!(matches!(wrong.validate_in(stock), Err(InvoiceApiError::UnknownOperation(..))));
+
+    let mut wrong = invoice.clone();
+    wrong.assignment = Some(FieldName::from("owner"));
+    assert!(matches!(wrong.validate_in(stock), Err(InvoiceApiError::UnknownAssignment { .. })));
+
+    let mut wrong = invoice.clone();
+    wrong.owned_state = InvoiceState::Data(NonFungible::RGB21(Allocation::with(1, 1)));
+    assert!(matches!(wrong.validate_in(stock), Err(InvoiceApiError::StateMismatch { .. })));
+
+    let mut wrong = invoice.clone();
+    wrong.contract = Some(ContractId::from([0xAB; 32]));
+    assert!(matches!(wrong.validate_in(stock), Err(InvoiceApiError::UnknownContract(_))));
+
+    let mut wrong = invoice.clone();
+    wrong.iface = Some("RGB21".into());
+    assert!(matches!(wrong.validate_in(stock), Err(InvoiceApiError::UnknownIface(_))));
+}
+
+#[test]
+fn invoice_builder() {
+    use rgb::invoice::{Beneficiary, RgbInvoiceBuilder, XChainNet};
+    use rgb::SecretSeal;
+
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let iface = alice.wallet.stock().iface(RGB20_IFACE).unwrap();
+
+    let beneficiary = Beneficiary::BlindedSeal(SecretSeal::from([0x11; 32]));
+    let builder = RgbInvoiceBuilder::new(XChainNet::bitcoin(NETWORK, beneficiary))
+        .set_contract(contract_id)
+        .set_interface(RGB20_IFACE);
+    builder
+        .clone()
+        .set_amount_raw(10u64)
+        .finish_validated(iface)
+        .unwrap();
+    let err = builder
+        .set_allocation_raw(Allocation::with(1, 1))
+        .finish_validated(iface)
+        .unwrap_err();
+    assert_eq!(err, InvoiceApiError::StateMismatch {
+        assignment: "assetOwner".to_owned(),
+        expected: "an amount",
+        found: "a token allocation",
+    });
+}
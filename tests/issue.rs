@@ -0,0 +1,45 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Issuance of a fungible asset to a UTXO funded on the mock chain.
+
+mod common;
+
+use common::{amount, Party, NETWORK};
+use rgb::resolvers::MockChain;
+
+#[test]
+fn issue() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(100_000);
+    assert_eq!(chain.confirmations(outpoint.txid), Some(1));
+    assert!(alice
+        .wallet
+        .wallet()
+        .utxos()
+        .any(|utxo| utxo.outpoint == outpoint));
+
+    let contract_id = alice.issue(outpoint, 1_000);
+    let balance = alice.balance(contract_id);
+    assert_eq!(balance.confirmed, amount(1_000));
+    assert_eq!(balance.total(), amount(1_000));
+}
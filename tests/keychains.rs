@@ -0,0 +1,169 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Wallets with non-standard keychain layouts, like the ones migrated from
+//! other RGB implementations.
+
+mod common;
+
+use std::str::FromStr;
+
+use bpstd::{Address, Derive, XpubDerivable};
+use bpwallet::fs::FsTextStore;
+use bpwallet::Wallet;
+use common::{Party, NETWORK};
+use psrgbt::PsbtConstructor;
+use rgb::invoice::{Beneficiary, Pay2Vout, RgbInvoiceBuilder, XChainNet};
+use rgb::resolvers::MockChain;
+use rgb::{DescriptorError, DescriptorRgb, KeychainLayout, RgbDescr, TapretKey, WalletEvent};
+
+const MIGRATED: KeychainLayout = KeychainLayout {
+    external: 0,
+    internal: 1,
+    rgb: 5,
+    tapret: 6,
+};
+
+#[test]
+fn layout_parse() {
+    assert_eq!(KeychainLayout::STANDARD.to_string(), "<0;1;9;10>");
+    assert_eq!(KeychainLayout::from_str("<0;1;9;10>").unwrap(), KeychainLayout::STANDARD);
+    assert_eq!(KeychainLayout::from_str("0,1,5,6").unwrap(), MIGRATED);
+    assert_eq!(KeychainLayout::from_str(&MIGRATED.to_string()).unwrap(), MIGRATED);
+    assert!(KeychainLayout::from_str("<0;1;9>").is_err());
+    assert!(KeychainLayout::from_str("<0;1;9;256>").is_err());
+}
+
+#[test]
+fn layout_change() {
+    let xpub = XpubDerivable::from_str(
+        "[643a7adc/86h/1h/0h]tpubDCNiWHaiSkgnQjuhsg9kjwaUzaxQjUcmhagvYzqQ3TYJTgFGJstVaqnu4yhtFktBhCVFmBNLQ5sN53qKzZbMksm3XEyGJsEhQPfVZdWmTE2/<0;1;9;10>/*",
+    )
+    .unwrap();
+
+    // Bare tapret key descriptor is fixed to the standard layout
+    let mut tapret = TapretKey::from(xpub.clone());
+    tapret
+        .set_keychain_layout(KeychainLayout::STANDARD)
+        .unwrap();
+    assert_eq!(
+        tapret.set_keychain_layout(MIGRATED),
+        Err(DescriptorError::FixedKeychainLayout(MIGRATED))
+    );
+    assert_eq!(tapret.keychain_layout(), KeychainLayout::STANDARD);
+
+    let mut descr = RgbDescr::<XpubDerivable>::from(tapret);
+    descr.set_keychain_layout(MIGRATED).unwrap();
+    assert_eq!(descr.keychain_layout(), MIGRATED);
+}
+
+#[test]
+fn layout_persistence() {
+    let xpub = XpubDerivable::from_str(
+        "[643a7adc/86h/1h/0h]tpubDCNiWHaiSkgnQjuhsg9kjwaUzaxQjUcmhagvYzqQ3TYJTgFGJstVaqnu4yhtFktBhCVFmBNLQ5sN53qKzZbMksm3XEyGJsEhQPfVZdWmTE2/<0;1;9;10>/*",
+    )
+    .unwrap();
+    let dir = std::env::temp_dir().join(format!("rgb-keychains-{}", std::process::id()));
+    for layout in [KeychainLayout::STANDARD, MIGRATED] {
+        let descr = RgbDescr::from(TapretKey::from(xpub.clone())).with_keychain_layout(layout);
+        let mut wallet = Wallet::<XpubDerivable, RgbDescr>::new_layer1(descr, NETWORK);
+        wallet
+            .make_persistent(FsTextStore::new(dir.clone()).unwrap(), true)
+            .unwrap();
+        wallet.store().unwrap();
+
+        // Wallets with the standard layout keep the descriptor format unchanged
+        let text = std::fs::read_to_string(dir.join("descriptor.toml")).unwrap();
+        assert_eq!(text.contains("keychains"), !layout.is_standard());
+
+        let wallet =
+            Wallet::<XpubDerivable, RgbDescr>::load(FsTextStore::new(dir.clone()).unwrap(), false)
+                .unwrap();
+        assert_eq!(wallet.descriptor().keychain_layout(), layout);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
+
+#[test]
+fn migrated_wallet() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::with_layout(&chain, 2, MIGRATED);
+
+    // Bob receives on the migrated layout keychains
+    let outpoint = bob.fund(10_000);
+    let terminal = bob.wallet.wallet().utxo(outpoint).unwrap().terminal;
+    assert_eq!(terminal.keychain, MIGRATED.keychain(rgb::RgbKeychain::Rgb));
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    // Invoice of the previous RGB implementation used by Bob, which put tapret
+    // seals on the keychain 9
+    let script = bob
+        .wallet
+        .wallet()
+        .descriptor()
+        .derive(9, 0u16)
+        .to_script_pubkey();
+    let address = Address::with(&script, NETWORK).unwrap();
+    let invoice = RgbInvoiceBuilder::new(XChainNet::bitcoin(
+        NETWORK,
+        Beneficiary::WitnessVout(Pay2Vout {
+            address: address.payload,
+            method: bob.wallet.wallet().seal_close_method(),
+        }),
+    ))
+    .set_contract(contract_id)
+    .set_interface(rgb::RGB20_IFACE)
+    .set_amount_raw(400u64)
+    .finish();
+
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    alice.sync();
+    bob.sync();
+    let events = bob.wallet.subscribe_channel();
+    bob.accept(transfer);
+
+    let layout = bob.wallet.wallet().descriptor().keychain_layout();
+    assert_eq!(layout, KeychainLayout {
+        tapret: 9,
+        ..MIGRATED
+    });
+    assert!(events
+        .try_iter()
+        .any(|event| event == WalletEvent::KeychainLayoutDetected(layout)));
+    // The received allocation is not treated as a bitcoin-only coin anymore
+    assert!(layout.contains_rgb(9u8));
+    assert!(bob
+        .wallet
+        .wallet()
+        .utxos()
+        .any(|utxo| utxo.terminal.keychain == 9u8.into()));
+
+    // Wallets with the standard layout are not changed by accepting transfers
+    let mut carol = Party::new(&chain, 3);
+    let invoice = carol.invoice(contract_id, 100, false);
+    let (_, transfer) = alice.pay(&invoice);
+    carol.accept(transfer);
+    assert_eq!(carol.wallet.wallet().descriptor().keychain_layout(), KeychainLayout::STANDARD);
+}
@@ -0,0 +1,150 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fetching issuer kits from registries.
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use commit_verify::{Digest, Sha256};
+use common::{Party, NETWORK};
+use rgb::containers::FileContent;
+use rgb::persistence::Stock;
+use rgb::resolvers::MockChain;
+use rgb::{
+    fetch_schema_kit, update_kits, KitEntry, KitIndex, KitRegistry, KitRegistryError,
+    KIT_INDEX_FILE,
+};
+
+const REGISTRY: &str = "https://kits.example.com/";
+
+fn sha256(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Files served by the mock registry, together with the hash of the index.
+fn registry_files() -> (BTreeMap<String, Vec<u8>>, String, rgb::schema::SchemaId) {
+    let chain = MockChain::new(NETWORK);
+    let party = Party::new(&chain, 1);
+    let stock = party.wallet.stock();
+    let schema_id = stock.schemata().unwrap().next().unwrap().id;
+    let mut kit = vec![];
+    stock
+        .export_schema(schema_id)
+        .unwrap()
+        .save(&mut kit)
+        .unwrap();
+
+    let index = KitIndex {
+        kits: vec![KitEntry {
+            schema_id,
+            file: "rgb20.kit".to_owned(),
+            sha256: sha256(&kit),
+        }],
+    };
+    let index = serde_yaml::to_string(&index).unwrap().into_bytes();
+    let pin = sha256(&index);
+    let files = BTreeMap::from([
+        (format!("https://kits.example.com/{KIT_INDEX_FILE}"), index),
+        ("https://kits.example.com/rgb20.kit".to_owned(), kit),
+    ]);
+    (files, pin, schema_id)
+}
+
+fn fetcher(files: &BTreeMap<String, Vec<u8>>) -> impl Fn(&str) -> Result<Vec<u8>, String> + '_ {
+    |url| files.get(url).cloned().ok_or_else(|| "404".to_owned())
+}
+
+#[test]
+fn kits_update() {
+    let (files, pin, schema_id) = registry_files();
+    let registries = [KitRegistry::new(REGISTRY).unwrap().with_pin(pin)];
+
+    let mut stock = Stock::in_memory();
+    assert!(stock.schema(schema_id).is_err());
+    let imported = update_kits(&mut stock, &registries, &fetcher(&files)).unwrap();
+    assert_eq!(imported.len(), 1);
+    assert!(stock.schema(schema_id).is_ok());
+
+    let imported = update_kits(&mut stock, &registries, &fetcher(&files)).unwrap();
+    assert!(imported.is_empty());
+}
+
+#[test]
+fn kits_fetch_schema() {
+    let (files, _, schema_id) = registry_files();
+    let registries = [KitRegistry::new(REGISTRY).unwrap()];
+
+    let mut stock = Stock::in_memory();
+    let kit_id = fetch_schema_kit(&mut stock, &registries, &fetcher(&files), schema_id).unwrap();
+    assert!(kit_id.is_some());
+    assert!(stock.schema(schema_id).is_ok());
+    let kit_id = fetch_schema_kit(&mut stock, &registries, &fetcher(&files), schema_id).unwrap();
+    assert_eq!(kit_id, None);
+}
+
+#[test]
+fn kits_pinning() {
+    let (mut files, pin, schema_id) = registry_files();
+
+    assert!(matches!(
+        KitRegistry::new("http://kits.example.com"),
+        Err(KitRegistryError::InsecureUrl(_))
+    ));
+
+    let registries = [KitRegistry::new(REGISTRY)
+        .unwrap()
+        .with_pin(sha256(b"other index"))];
+    let mut stock = Stock::in_memory();
+    assert!(matches!(
+        update_kits(&mut stock, &registries, &fetcher(&files)),
+        Err(KitRegistryError::HashMismatch { .. })
+    ));
+
+    files
+        .get_mut("https://kits.example.com/rgb20.kit")
+        .unwrap()
+        .push(0);
+    let registries = [KitRegistry::new(REGISTRY).unwrap().with_pin(pin)];
+    assert!(matches!(
+        fetch_schema_kit(&mut stock, &registries, &fetcher(&files), schema_id),
+        Err(KitRegistryError::HashMismatch { .. })
+    ));
+    assert!(stock.schema(schema_id).is_err());
+}
+
+#[test]
+#[cfg(feature = "kit_registry")]
+fn kits_https_fetcher_insecure() {
+    use std::time::Duration;
+
+    use rgb::{HttpsFetcher, KitFetcher};
+
+    let fetcher = HttpsFetcher::new(Duration::from_secs(1), None).unwrap();
+    let err = fetcher
+        .fetch(&format!("http://kits.example.com/{KIT_INDEX_FILE}"))
+        .unwrap_err();
+    assert!(err.contains("HTTPS"), "{err}");
+}
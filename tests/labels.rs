@@ -0,0 +1,83 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! User-assigned labels persisted next to the stock.
+
+mod common;
+
+use common::{Party, NETWORK};
+use rgb::resolvers::MockChain;
+use rgb::{AssignmentType, LabelError, LabelTarget, Operation, Opout, WalletLabels};
+
+#[test]
+fn labels_persist() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let invoice = bob.invoice(contract_id, 400, false);
+    let (txid, transfer) = alice.pay(&invoice);
+    let opout = Opout::new(transfer.genesis.id(), AssignmentType::with(4000), 0);
+
+    let mut labels = WalletLabels::new();
+    labels.set(LabelTarget::Contract(contract_id), "USDT");
+    labels.set(LabelTarget::Witness(txid), "payment to Bob");
+    labels.set(LabelTarget::Allocation(opout), "issued");
+    assert_eq!(labels.set(LabelTarget::Contract(contract_id), "Tether"), Some("USDT".to_owned()));
+
+    let dir = std::env::temp_dir().join(format!("rgb-labels-{}", std::process::id()));
+    let path = dir.join("labels.yaml");
+    labels.save_file(&path).unwrap();
+    let loaded = WalletLabels::load_file(&path).unwrap();
+    std::fs::remove_dir_all(&dir).ok();
+
+    assert_eq!(loaded, labels);
+    assert_eq!(loaded.len(), 3);
+    assert_eq!(loaded.contract(contract_id), Some("Tether"));
+    assert_eq!(loaded.witness(txid), Some("payment to Bob"));
+    assert_eq!(loaded.allocation(opout), Some("issued"));
+
+    let missing = WalletLabels::load_file(dir.join("labels.yaml")).unwrap();
+    assert!(missing.is_empty());
+}
+
+#[test]
+fn label_targets() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let invoice = bob.invoice(contract_id, 400, false);
+    let (_, transfer) = alice.pay(&invoice);
+
+    for target in [
+        LabelTarget::Contract(contract_id),
+        LabelTarget::Witness(outpoint.txid),
+        LabelTarget::Allocation(Opout::new(transfer.genesis.id(), AssignmentType::with(4000), 1)),
+    ] {
+        assert_eq!(target.to_string().parse::<LabelTarget>().unwrap(), target);
+    }
+    assert!(matches!("tx:abc".parse::<LabelTarget>(), Err(LabelError::InvalidTarget(_))));
+    assert!(matches!("witness:abc".parse::<LabelTarget>(), Err(LabelError::InvalidTarget(_))));
+}
@@ -0,0 +1,262 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transfers of the RGB state over a channel with off-chain commitment
+//! transactions tracked by [`OffchainRegistry`].
+
+mod common;
+
+use bp::{Tx, TxOut, VarIntArray};
+use bpstd::{Outpoint, Sats};
+use common::{amount, params, Party, NETWORK};
+use psrgbt::{PsbtConstructor, RgbPsbt};
+use rgb::containers::{Fascia, Transfer};
+use rgb::invoice::{Beneficiary, RgbInvoice};
+use rgb::persistence::ContractStateRead;
+use rgb::resolvers::{AnyResolver, MockChain};
+use rgb::vm::WitnessOrd;
+use rgb::{
+    reanchor_fascia, Amount, ContractId, Layer2Error, OffchainRegistry, OffchainStock, OutputSeal,
+    Signer, XChain, XOutputSeal,
+};
+
+fn commitment_tx(fascia: &Fascia) -> Tx {
+    let XChain::Bitcoin(witness) = &fascia.witness else {
+        panic!("channel state is anchored to bitcoin");
+    };
+    witness.tx().expect("full commitment transaction").clone()
+}
+
+/// Returns the same commitment transaction paying less to the change output.
+fn with_less_change(tx: &Tx, sats: u64) -> Tx {
+    let mut reduced = false;
+    let outputs = tx
+        .outputs()
+        .map(|output| {
+            if reduced || output.script_pubkey.is_op_return() || output.value.sats() <= sats {
+                return output.clone();
+            }
+            reduced = true;
+            TxOut {
+                value: Sats::from_sats(output.value.sats() - sats),
+                script_pubkey: output.script_pubkey.clone(),
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut tx = tx.clone();
+    tx.outputs = VarIntArray::from_checked(outputs);
+    tx
+}
+
+/// Returns the output of the commitment transaction paying to the invoice.
+fn channel_output(tx: &Tx, invoice: &RgbInvoice) -> XOutputSeal {
+    let Beneficiary::WitnessVout(pay2vout) = invoice.beneficiary.into_inner() else {
+        panic!("channel state is assigned to the commitment outputs");
+    };
+    let script_pubkey = pay2vout.address.script_pubkey();
+    let vout = tx
+        .outputs()
+        .position(|output| output.script_pubkey == script_pubkey)
+        .expect("commitment pays to the invoice");
+    XChain::Bitcoin(OutputSeal::new(pay2vout.method, Outpoint::new(tx.txid(), vout as u32)))
+}
+
+/// Constructs the transfer of the channel state assigned to the output of
+/// the commitment transaction.
+fn channel_transfer(party: &Party, contract_id: ContractId, output: XOutputSeal) -> Transfer {
+    party
+        .wallet
+        .stock()
+        .transfer(contract_id, [output], None)
+        .expect("channel transfer")
+}
+
+/// Accepts the transfer of the channel state, resolving the commitment
+/// transactions with the registry.
+fn accept_offchain(party: &mut Party, registry: &OffchainRegistry, transfer: Transfer) {
+    let resolver = registry.resolver(AnyResolver::mock(&party.chain));
+    let valid = transfer.validate(&resolver, true).expect("valid transfer");
+    party
+        .wallet
+        .stock_mut()
+        .accept_transfer(valid, &resolver)
+        .expect("stock access");
+}
+
+/// Returns the state assigned to the output, unless the commitment
+/// transaction having the output is revoked.
+fn channel_state(party: &Party, contract_id: ContractId, output: XOutputSeal) -> Amount {
+    let state = party.wallet.stock().contract_state(contract_id).unwrap();
+    state
+        .fungible_all()
+        .filter(|allocation| allocation.seal == output)
+        .map(|allocation| Amount::from(allocation.state))
+        .sum()
+}
+
+#[test]
+fn layer2_channel_transfer() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new_wpkh(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let funding = alice.fund(10_000);
+    let contract_id = alice.issue(funding, 1_000);
+
+    let mut registry = OffchainRegistry::new();
+    registry.open(funding).unwrap();
+    assert!(matches!(registry.open(funding), Err(Layer2Error::ChannelExists(_))));
+
+    let invoice = bob.invoice(contract_id, 400, false);
+    let (mut psbt, _) = alice.wallet.construct_psbt(&invoice, params()).unwrap();
+    let fascia = psbt.rgb_commit().unwrap();
+    let latest = commitment_tx(&fascia);
+
+    // The first channel update pays less to the change and gets revoked by
+    // the second one, which is used for closing the channel
+    let first = with_less_change(&latest, 100);
+    assert_ne!(first.txid(), latest.txid());
+    let first_fascia = reanchor_fascia(&fascia, first.clone()).unwrap();
+    let revoked = alice
+        .wallet
+        .stock_mut()
+        .register_offchain_witness(&mut registry, funding, first_fascia)
+        .unwrap();
+    assert_eq!(revoked, None);
+    assert!(registry.is_offchain(first.txid()));
+    assert_eq!(registry.witness_ord(first.txid()), Some(WitnessOrd::Tentative));
+
+    let first_output = channel_output(&first, &invoice);
+    accept_offchain(&mut bob, &registry, channel_transfer(&alice, contract_id, first_output));
+    assert_eq!(channel_state(&bob, contract_id, first_output), amount(400));
+
+    // Re-anchoring must keep the commitment outputs
+    let mut tampered = latest.clone();
+    tampered.outputs = VarIntArray::from_checked(
+        latest
+            .outputs()
+            .filter(|output| !output.script_pubkey.is_op_return())
+            .cloned()
+            .collect(),
+    );
+    assert!(matches!(reanchor_fascia(&fascia, tampered), Err(Layer2Error::CommitmentMismatch(_))));
+
+    let revoked = alice
+        .wallet
+        .stock_mut()
+        .register_offchain_witness(&mut registry, funding, fascia)
+        .unwrap();
+    assert_eq!(revoked, Some(first.txid()));
+    assert_eq!(registry.witness_ord(first.txid()), Some(WitnessOrd::Archived));
+    assert_eq!(registry.witness_ord(latest.txid()), Some(WitnessOrd::Tentative));
+    assert_eq!(
+        registry
+            .channel(funding)
+            .unwrap()
+            .revoked()
+            .collect::<Vec<_>>(),
+        vec![first.txid()]
+    );
+    assert_eq!(channel_state(&alice, contract_id, first_output), amount(0));
+
+    // The state of the revoked commitment is gone for the receiver as well
+    bob.wallet
+        .stock_mut()
+        .update_witnesses(registry.resolver(AnyResolver::mock(&chain)), 0)
+        .unwrap();
+    assert_eq!(channel_state(&bob, contract_id, first_output), amount(0));
+
+    let latest_output = channel_output(&latest, &invoice);
+    accept_offchain(&mut bob, &registry, channel_transfer(&alice, contract_id, latest_output));
+    assert_eq!(channel_state(&bob, contract_id, latest_output), amount(400));
+
+    // Closing the channel with the latest commitment leaves its ordering to
+    // the blockchain
+    alice.signer.sign_psbt(&mut psbt).unwrap();
+    psbt.finalize(alice.wallet.wallet().descriptor());
+    let tx = psbt.extract().unwrap();
+    assert_eq!(tx.txid(), latest.txid());
+    chain.broadcast(&tx).unwrap();
+    chain.mine(1);
+    alice
+        .wallet
+        .stock_mut()
+        .evict_channel(&mut registry, funding, Some(latest.txid()))
+        .unwrap();
+    assert!(registry.channel(funding).unwrap().is_closed());
+    assert_eq!(registry.witness_ord(latest.txid()), None);
+    assert_eq!(registry.witness_ord(first.txid()), Some(WitnessOrd::Archived));
+
+    alice.sync();
+    bob.sync();
+    assert_eq!(alice.balance(contract_id).confirmed, amount(600));
+    assert_eq!(bob.balance(contract_id).confirmed, amount(400));
+
+    registry.forget(funding).unwrap();
+    assert!(!registry.is_offchain(latest.txid()));
+    assert!(matches!(registry.forget(funding), Err(Layer2Error::UnknownChannel(_))));
+}
+
+#[test]
+fn layer2_evict_uncooperative() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new_wpkh(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let funding = alice.fund(10_000);
+    let contract_id = alice.issue(funding, 1_000);
+
+    let mut registry = OffchainRegistry::new();
+    registry.open(funding).unwrap();
+    let invoice = bob.invoice(contract_id, 400, false);
+    let (mut psbt, _) = alice.wallet.construct_psbt(&invoice, params()).unwrap();
+    let fascia = psbt.rgb_commit().unwrap();
+    let commitment = commitment_tx(&fascia);
+    alice
+        .wallet
+        .stock_mut()
+        .register_offchain_witness(&mut registry, funding, fascia.clone())
+        .unwrap();
+    let output = channel_output(&commitment, &invoice);
+    accept_offchain(&mut bob, &registry, channel_transfer(&alice, contract_id, output));
+    assert_eq!(channel_state(&bob, contract_id, output), amount(400));
+
+    // Channel closed by a transaction not carrying the channel state
+    // revokes the latest commitment
+    alice
+        .wallet
+        .stock_mut()
+        .evict_channel(&mut registry, funding, None)
+        .unwrap();
+    assert_eq!(registry.witness_ord(commitment.txid()), Some(WitnessOrd::Archived));
+    assert_eq!(channel_state(&alice, contract_id, output), amount(0));
+    assert!(matches!(
+        alice
+            .wallet
+            .stock_mut()
+            .register_offchain_witness(&mut registry, funding, fascia),
+        Err(Layer2Error::ChannelClosed(_))
+    ));
+
+    bob.wallet
+        .stock_mut()
+        .update_witnesses(registry.resolver(AnyResolver::mock(&chain)), 0)
+        .unwrap();
+    assert_eq!(channel_state(&bob, contract_id, output), amount(0));
+}
@@ -0,0 +1,179 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+
+//! Contracts anchored to Liquid: issuance, invoices and resolution of the
+//! witness transactions.
+
+mod common;
+
+use std::str::FromStr;
+
+use bpstd::{Network, Sats, ScriptPubkey};
+use common::{Party, NETWORK};
+use rgb::containers::ConsignmentExt;
+use rgb::interface::FilterIncludeAll;
+use rgb::invoice::{Beneficiary, ChainNet, Pay2Vout, RgbInvoice, RgbInvoiceBuilder, XChainNet};
+use rgb::resolvers::{AnyResolver, ContractIssueResolver, MockChain};
+use rgb::validation::ResolveWitness;
+use rgb::vm::WitnessOrd;
+use rgb::{
+    chain_net, iface_schema, AltLayer1, ContractDefinition, DescriptorRgb, GraphSeal, Identity,
+    IssueProblem, Layer1, NetworkComponent, NetworkGuard, OwnershipError, XChain, RGB20_IFACE,
+};
+use strict_types::TypeName;
+
+const DEFINITION: &str = "
+interface: RGB20Fixed
+layer1: liquid
+globals:
+  spec:
+    ticker: LQD
+    name: Liquid asset
+    details: ~
+    precision: 0
+  terms:
+    text: ''
+    media: ~
+  issuedSupply: 1000
+assignments:
+  assetOwner:
+    seal: tapret1st:b449f7eaa3f98c145b27ad0eeb7b5679ceb567faef7a52479bc995792b65f804:1
+    amount: 1000
+";
+
+#[test]
+fn issue_on_liquid() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+
+    let definition = ContractDefinition::new(serde_yaml::from_str(DEFINITION).unwrap());
+    assert_eq!(definition.layer1().unwrap(), Layer1::Liquid);
+    let schema_id = iface_schema(alice.wallet.stock(), RGB20_IFACE).unwrap();
+    let contract = definition
+        .builder(alice.wallet.stock(), Identity::default(), schema_id)
+        .unwrap()
+        .issue_contract()
+        .unwrap();
+    assert!(contract.genesis.alt_layers1.contains(&AltLayer1::Liquid));
+
+    let contract_id = contract.contract_id();
+    alice
+        .wallet
+        .stock_mut()
+        .import_contract(contract, &ContractIssueResolver)
+        .unwrap();
+    let iface = alice
+        .wallet
+        .stock()
+        .contract_iface(contract_id, TypeName::from(RGB20_IFACE))
+        .unwrap();
+    let allocations = iface
+        .fungible("assetOwner", FilterIncludeAll)
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(allocations.len(), 1);
+    assert_eq!(allocations[0].seal.layer1(), Layer1::Liquid);
+}
+
+#[test]
+fn unsupported_layer1() {
+    let yaml = serde_yaml::from_str("{interface: RGB20Fixed, layer1: abraxas}").unwrap();
+    let err = ContractDefinition::new(yaml).layer1().unwrap_err();
+    assert_eq!(err.problems(), &[IssueProblem::UnsupportedLayer1("abraxas".to_owned())]);
+}
+
+#[test]
+fn liquid_invoice() {
+    let chain = MockChain::new(NETWORK);
+    let mut bob = Party::new(&chain, 2);
+
+    assert_eq!(chain_net(Layer1::Liquid, Network::Mainnet), ChainNet::LiquidMainnet);
+    assert_eq!(chain_net(Layer1::Liquid, Network::Signet), ChainNet::LiquidTestnet);
+
+    let (_, address) = bob.wallet.next_rgb_address(true);
+    let beneficiary = Beneficiary::WitnessVout(Pay2Vout {
+        address: address.payload,
+        method: bob.wallet.wallet().seal_close_method(),
+    });
+    let invoice =
+        RgbInvoiceBuilder::new(XChainNet::with(chain_net(Layer1::Liquid, NETWORK), beneficiary))
+            .set_interface(RGB20_IFACE)
+            .set_amount_raw(100u64)
+            .finish();
+    let invoice = RgbInvoice::from_str(&invoice.to_string()).unwrap();
+    assert_eq!(invoice.chain_network(), ChainNet::LiquidTestnet);
+
+    NetworkGuard::new(NETWORK)
+        .check_invoice(&invoice)
+        .finish()
+        .unwrap();
+    let guard = NetworkGuard::new(Network::Mainnet).check_invoice(&invoice);
+    assert_eq!(guard.mismatches(), &[NetworkComponent::Invoice(ChainNet::LiquidTestnet)]);
+}
+
+#[test]
+fn liquid_witness_resolution() {
+    let bitcoin = MockChain::new(NETWORK);
+    let liquid = MockChain::liquid(NETWORK);
+    let outpoint = liquid.fund(&ScriptPubkey::op_return(&[]), Sats::from(1000u64));
+    liquid.mine(1);
+    let witness_id = XChain::Liquid(outpoint.txid);
+
+    let resolver = AnyResolver::mock(&bitcoin);
+    assert!(resolver.resolve_pub_witness_ord(witness_id).is_err());
+    assert!(resolver.check_liquid(NETWORK).is_err());
+
+    let resolver = resolver.with_liquid(AnyResolver::mock(&liquid));
+    resolver.check_liquid(NETWORK).unwrap();
+    assert!(resolver.check_liquid(Network::Mainnet).is_err());
+    let WitnessOrd::Mined(pos) = resolver.resolve_pub_witness_ord(witness_id).unwrap() else {
+        panic!("witness must be mined");
+    };
+    assert_eq!(pos.layer1(), Layer1::Liquid);
+    assert_eq!(pos.height().get(), 1);
+    let tx = resolver.resolve_pub_witness(witness_id).unwrap();
+    assert_eq!(tx.witness_id(), witness_id);
+
+    let testnet = AnyResolver::mock(&MockChain::new(Network::Testnet3))
+        .with_liquid(AnyResolver::mock(&MockChain::liquid(Network::Testnet3)));
+    testnet.check_liquid(Network::Testnet3).unwrap();
+}
+
+#[test]
+fn liquid_ownership_unsupported() {
+    let chain = MockChain::new(NETWORK);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = bob.fund(10_000);
+
+    let method = bob.wallet.wallet().seal_close_method();
+    let seal = XChain::Liquid(GraphSeal::new_random(method, outpoint.txid, outpoint.vout));
+    bob.wallet.stock_mut().store_secret_seal(seal).unwrap();
+    let beneficiary = Beneficiary::BlindedSeal(*seal.to_secret_seal().as_reduced_unsafe());
+    let invoice =
+        RgbInvoiceBuilder::new(XChainNet::with(chain_net(Layer1::Liquid, NETWORK), beneficiary))
+            .set_interface(RGB20_IFACE)
+            .set_amount_raw(100u64)
+            .finish();
+    let err = bob
+        .wallet
+        .prove_ownership(&invoice, &bob.signer)
+        .unwrap_err();
+    assert!(matches!(err, OwnershipError::UnsupportedLayer1(Layer1::Liquid)), "{err}");
+}
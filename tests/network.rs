@@ -0,0 +1,156 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Guarding wallet operations against components for different networks.
+
+mod common;
+
+use bpstd::{Network, Sats};
+use common::{amount, Party, FEE, NETWORK, SATS};
+use rgb::invoice::{RgbInvoice, XChainNet};
+use rgb::persistence::StashReadProvider;
+use rgb::resolvers::{indexer_url, AnyResolver, MockChain};
+use rgb::{
+    CompositionError, NetworkComponent, NetworkGuard, NetworkMismatch, PayError, TransferParams,
+};
+
+fn mainnet_invoice(invoice: &RgbInvoice) -> RgbInvoice {
+    let mut invoice = invoice.clone();
+    invoice.beneficiary = XChainNet::bitcoin(Network::Mainnet, invoice.beneficiary.into_inner());
+    invoice
+}
+
+#[test]
+fn guard_reports_all_components() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let invoice = bob.invoice(contract_id, 100, false);
+    let genesis = alice
+        .wallet
+        .stock()
+        .as_stash_provider()
+        .genesis(contract_id)
+        .unwrap()
+        .clone();
+
+    let resolver = AnyResolver::mock(&chain);
+    NetworkGuard::new(NETWORK)
+        .check_descriptor(alice.wallet.wallet().network())
+        .check_invoice(&invoice)
+        .check_genesis(&genesis)
+        .check_resolver(&resolver)
+        .unwrap()
+        .finish()
+        .unwrap();
+
+    let mainnet = AnyResolver::mock(&MockChain::new(Network::Mainnet));
+    let err = NetworkGuard::new(NETWORK)
+        .check_descriptor(Network::Testnet4)
+        .check_invoice(&mainnet_invoice(&invoice))
+        .check_genesis(&genesis)
+        .check_resolver(&mainnet)
+        .unwrap()
+        .finish()
+        .unwrap_err();
+    assert_eq!(err.expected, NETWORK);
+    assert_eq!(err.components.len(), 3);
+    assert!(matches!(err.components[0], NetworkComponent::Descriptor(Network::Testnet4)));
+    assert!(matches!(err.components[1], NetworkComponent::Invoice(_)));
+    assert!(matches!(err.components[2], NetworkComponent::Resolver(_)));
+    let msg = err.to_string();
+    assert!(msg.contains("wallet descriptor for testnet4"), "{msg}");
+    assert!(msg.contains("resolver for bitcoin"), "{msg}");
+
+    // Regtest contracts are issued for the testnet
+    let err = NetworkGuard::new(Network::Mainnet)
+        .check_genesis(&genesis)
+        .finish()
+        .unwrap_err();
+    assert_eq!(err.components, vec![NetworkComponent::Contract(contract_id, true)]);
+}
+
+#[test]
+fn pay_foreign_invoice() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let invoice = mainnet_invoice(&bob.invoice(contract_id, 100, false));
+
+    let err = alice
+        .wallet
+        .pay(&invoice, TransferParams::with(Sats::from_sats(FEE), Sats::from_sats(SATS)))
+        .unwrap_err();
+    let PayError::Composition(CompositionError::NetworkMismatch(NetworkMismatch {
+        expected,
+        components,
+    })) = err
+    else {
+        panic!("unexpected error {err}");
+    };
+    assert_eq!(expected, NETWORK);
+    assert_eq!(components, vec![NetworkComponent::Invoice(invoice.chain_network())]);
+}
+
+fn transfer_on(network: Network) {
+    let chain = MockChain::new(network);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new_wpkh(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+
+    for blinded in [false, true] {
+        let invoice = bob.invoice(contract_id, 100, blinded);
+        assert_eq!(invoice.chain_network(), XChainNet::bitcoin(network, ()).chain_network());
+        let invoice = invoice.to_string().parse::<RgbInvoice>().unwrap();
+        let (_, transfer) = alice.pay(&invoice);
+        chain.mine(1);
+        alice.sync();
+        bob.accept(transfer);
+        bob.sync();
+    }
+    assert_eq!(bob.balance(contract_id).confirmed, amount(200));
+    assert_eq!(alice.balance(contract_id).confirmed, amount(800));
+}
+
+#[test]
+fn signet_transfer() { transfer_on(Network::Signet) }
+
+#[test]
+fn testnet4_transfer() { transfer_on(Network::Testnet4) }
+
+#[test]
+fn testnet3_transfer() { transfer_on(Network::Testnet3) }
+
+#[test]
+fn indexer_url_network() {
+    let template = "https://mempool.space/{network}/api";
+    assert_eq!(indexer_url(template, Network::Mainnet), "https://mempool.space/api");
+    assert_eq!(indexer_url(template, Network::Testnet3), "https://mempool.space/testnet/api");
+    assert_eq!(indexer_url(template, Network::Testnet4), "https://mempool.space/testnet4/api");
+    assert_eq!(indexer_url(template, Network::Signet), "https://mempool.space/signet/api");
+    assert_eq!(indexer_url("http://localhost:3002", Network::Signet), "http://localhost:3002");
+}
@@ -0,0 +1,87 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contracts issued offline with the verification of their genesis seals
+//! deferred until the chain access is available.
+
+mod common;
+
+use bpstd::{Outpoint, Txid, Vout};
+use common::{Party, NETWORK};
+use rgb::persistence::StashReadProvider;
+use rgb::resolvers::{AnyResolver, MockChain};
+use rgb::{ContractId, DeferredValidation, Genesis};
+
+fn genesis(party: &Party, contract_id: ContractId) -> Genesis {
+    party
+        .wallet
+        .stock()
+        .as_stash_provider()
+        .genesis(contract_id)
+        .expect("known contract")
+        .clone()
+}
+
+#[test]
+fn reconcile() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let funded = alice.fund(10_000);
+    let unknown = Outpoint::new(Txid::from([0xAB; 32]), Vout::from_u32(0));
+    let missing = Outpoint::new(funded.txid, Vout::from_u32(100));
+
+    let verified_id = alice.issue(funded, 1_000);
+    let pending_id = alice.issue(unknown, 1_000);
+    let invalid_id = alice.issue(missing, 1_000);
+
+    let mut deferred = DeferredValidation::new();
+    for id in [verified_id, pending_id, invalid_id] {
+        assert_eq!(deferred.defer(&genesis(&alice, id)), 1);
+    }
+    assert_eq!(deferred.len(), 3);
+    assert!(deferred.outpoints(pending_id).unwrap().contains(&unknown));
+
+    let report = deferred.reconcile(&AnyResolver::mock(&chain)).unwrap();
+    assert_eq!(report.verified.into_iter().collect::<Vec<_>>(), vec![verified_id]);
+    assert_eq!(report.pending[&pending_id].iter().collect::<Vec<_>>(), vec![&unknown]);
+    assert_eq!(report.invalid[&invalid_id].iter().collect::<Vec<_>>(), vec![&missing]);
+    assert!(!deferred.contains(verified_id));
+    assert!(deferred.contains(pending_id));
+    assert!(deferred.contains(invalid_id));
+}
+
+#[test]
+fn deferred_file() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    let mut deferred = DeferredValidation::new();
+    deferred.defer(&genesis(&alice, contract_id));
+
+    let dir = std::env::temp_dir().join(format!("rgb-offline-{}", std::process::id()));
+    let path = dir.join("deferred.yaml");
+    assert!(DeferredValidation::load_file(&path).unwrap().is_empty());
+    deferred.save_file(&path).unwrap();
+    assert_eq!(DeferredValidation::load_file(&path).unwrap(), deferred);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
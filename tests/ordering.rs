@@ -0,0 +1,128 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Ordering of the witness transaction outputs, hiding the position of the
+//! output hosting the tapret commitment.
+
+mod common;
+
+use std::str::FromStr;
+
+use bpstd::Sats;
+use common::{amount, Party, FEE, NETWORK, SATS};
+use psrgbt::{OutputOrdering, Psbt, PsbtConstructor, RgbOutExt, RgbOutputOrdering, RgbPsbt};
+use rgb::resolvers::MockChain;
+use rgb::{Signer, TransferParams};
+
+fn params(ordering: OutputOrdering) -> TransferParams {
+    let mut params = TransferParams::with(Sats::from_sats(FEE), Sats::from_sats(SATS));
+    params.ordering = ordering;
+    params
+}
+
+#[test]
+fn ordering_parse() {
+    for ordering in [OutputOrdering::HostFirst, OutputOrdering::Bip69, OutputOrdering::Shuffle(42)]
+    {
+        assert_eq!(OutputOrdering::from_str(&ordering.to_string()), Ok(ordering));
+    }
+    assert!(matches!(OutputOrdering::from_str("shuffle"), Ok(OutputOrdering::Shuffle(_))));
+    assert!(OutputOrdering::from_str("shuffle:seed").is_err());
+    assert!(OutputOrdering::from_str("random").is_err());
+}
+
+#[test]
+fn ordering_bip69() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new_wpkh(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    let invoice = bob.invoice(contract_id, 100, false);
+    let (psbt, _) = alice
+        .wallet
+        .construct_psbt(&invoice, params(OutputOrdering::Bip69))
+        .unwrap();
+    assert_eq!(psbt.rgb_output_ordering(), Some(OutputOrdering::Bip69));
+    let keys = psbt
+        .outputs()
+        .map(|output| (output.amount, output.script.to_vec()))
+        .collect::<Vec<_>>();
+    let mut sorted = keys.clone();
+    sorted.sort();
+    assert_eq!(keys, sorted);
+    // Tapret host is the only taproot output, thus it is ordered as any other
+    // one; outputs were assigned their roles after the reordering
+    assert!(psbt
+        .outputs()
+        .find(|output| output.script.is_p2tr())
+        .is_some_and(|output| output.is_tapret_host()));
+    for output in psbt.outputs() {
+        if let Some((_, vout)) = output.rgb_role() {
+            assert_eq!(vout, output.vout());
+        }
+    }
+}
+
+#[test]
+fn ordering_shuffle() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new_wpkh(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    // The same seed produces the same ordering; the change script differs
+    // since each PSBT derives a new change address
+    let invoice = bob.invoice(contract_id, 100, false);
+    let (first, _) = alice
+        .wallet
+        .construct_psbt(&invoice, params(OutputOrdering::Shuffle(7)))
+        .unwrap();
+    let (second, _) = alice
+        .wallet
+        .construct_psbt(&invoice, params(OutputOrdering::Shuffle(7)))
+        .unwrap();
+    assert_eq!(first.rgb_output_ordering(), Some(OutputOrdering::Shuffle(7)));
+    let amounts = |psbt: &Psbt| {
+        psbt.outputs()
+            .map(|output| output.amount)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(amounts(&first), amounts(&second));
+
+    // Seals reference the outputs at their new positions
+    let (mut psbt, _, transfer) = alice
+        .wallet
+        .pay(&invoice, params(OutputOrdering::Shuffle(7)))
+        .unwrap();
+    assert!(psbt.rgb_verify_layout().is_ok());
+    alice.signer.sign_psbt(&mut psbt).unwrap();
+    psbt.finalize(alice.wallet.wallet().descriptor());
+    chain.broadcast(&psbt.extract().unwrap()).unwrap();
+    bob.accept(transfer);
+    chain.mine(1);
+    alice.sync();
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).confirmed, amount(100));
+    assert_eq!(alice.balance(contract_id).confirmed, amount(900));
+}
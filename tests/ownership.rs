@@ -0,0 +1,109 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proofs that the invoice beneficiary belongs to the wallet which has issued
+//! the invoice, checked by the payer before paying.
+
+mod common;
+
+use common::{Party, NETWORK};
+use rgb::resolvers::{AnyResolver, MockChain};
+use rgb::{verify_ownership, OwnershipError, OwnershipProof, INVOICE_QUERY_PROOF};
+
+fn prove(party: &mut Party, blinded: bool) {
+    let chain = party.chain.clone();
+    let mut issuer = Party::new(&chain, 9);
+    let outpoint = issuer.fund(10_000);
+    let contract_id = issuer.issue(outpoint, 1_000);
+    party.fund(10_000);
+
+    let mut invoice = party.invoice(contract_id, 400, blinded);
+    let resolver = AnyResolver::mock(&chain);
+    let err = verify_ownership(&invoice, &resolver).unwrap_err();
+    assert!(matches!(err, OwnershipError::NoProof));
+
+    // Other wallets can't prove the ownership
+    let err = issuer
+        .wallet
+        .prove_ownership(&invoice, &issuer.signer)
+        .unwrap_err();
+    assert!(matches!(err, OwnershipError::UnknownBeneficiary));
+
+    let proof = party
+        .wallet
+        .prove_ownership(&invoice, &party.signer)
+        .unwrap();
+    assert_eq!(proof.seal().is_some(), blinded);
+    proof.set_to_invoice(&mut invoice);
+    let invoice = invoice.to_string().parse().unwrap();
+    assert_eq!(OwnershipProof::from_invoice(&invoice).unwrap(), Some(proof));
+    verify_ownership(&invoice, &resolver).unwrap();
+
+    // The proof commits to all invoice parameters
+    let mut tampered = invoice.clone();
+    tampered.expiry = Some(1);
+    let err = verify_ownership(&tampered, &resolver).unwrap_err();
+    assert!(matches!(err, OwnershipError::InvalidSignature));
+
+    let mut tampered = invoice.clone();
+    tampered
+        .unknown_query
+        .insert(INVOICE_QUERY_PROOF.to_owned(), "00".to_owned());
+    let err = verify_ownership(&tampered, &resolver).unwrap_err();
+    assert!(matches!(err, OwnershipError::InvalidEncoding(_)));
+}
+
+#[test]
+fn prove_witness_tapret() { prove(&mut Party::new(&MockChain::new(NETWORK), 1), false) }
+
+#[test]
+fn prove_blinded_tapret() { prove(&mut Party::new(&MockChain::new(NETWORK), 1), true) }
+
+#[test]
+fn prove_witness_wpkh() { prove(&mut Party::new_wpkh(&MockChain::new(NETWORK), 1), false) }
+
+#[test]
+fn prove_blinded_wpkh() { prove(&mut Party::new_wpkh(&MockChain::new(NETWORK), 1), true) }
+
+#[test]
+fn prove_tweaked_utxo() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    // The change of the payment is a tapret host, thus the invoice seal
+    // defined over it requires the tweaked key for the proof
+    let invoice = bob.invoice(contract_id, 400, false);
+    let (_, consignment) = alice.pay(&invoice);
+    bob.accept(consignment);
+    chain.mine(1);
+    alice.sync();
+
+    let mut invoice = alice.invoice(contract_id, 100, true);
+    let proof = alice
+        .wallet
+        .prove_ownership(&invoice, &alice.signer)
+        .unwrap();
+    proof.set_to_invoice(&mut invoice);
+    verify_ownership(&invoice, &AnyResolver::mock(&chain)).unwrap();
+}
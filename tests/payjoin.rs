@@ -0,0 +1,186 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Payjoin transfers, where the receiver contributes its own input to the
+//! witness transaction.
+
+mod common;
+
+use std::cell::RefCell;
+use std::cmp::Reverse;
+
+use bpstd::{Outpoint, Psbt, Sats};
+use common::{amount, params, Party, NETWORK};
+use psrgbt::{PsbtConstructor, RgbPsbt};
+use rgb::invoice::RgbInvoice;
+use rgb::resolvers::MockChain;
+use rgb::{PayjoinEndpoint, PayjoinError, PayjoinProposal, Signer};
+
+const ENDPOINT: &str = "https://payjoin.example.com/pj";
+
+fn payjoin_invoice(party: &mut Party, contract_id: rgb::ContractId, blinded: bool) -> RgbInvoice {
+    let mut invoice = party.invoice(contract_id, 100, blinded);
+    PayjoinEndpoint::new(ENDPOINT)
+        .unwrap()
+        .set_to_invoice(&mut invoice);
+    invoice
+}
+
+/// Runs the payjoin transfer, where the receiver contributes its input and
+/// then passes the PSBT through `modify`. Returns the contributed outpoint.
+fn pay_payjoin(
+    alice: &mut Party,
+    bob: &mut Party,
+    invoice: RgbInvoice,
+    modify: impl Fn(&mut Psbt),
+) -> Outpoint {
+    let contributed = RefCell::new(None);
+    let receiver = RefCell::new(&mut *bob);
+    let client = |endpoint: &PayjoinEndpoint, original: &Psbt| {
+        assert_eq!(endpoint.url(), ENDPOINT);
+        let mut proposal =
+            PayjoinProposal::from_psbt(original.clone()).map_err(|e| e.to_string())?;
+        let outpoint = receiver
+            .borrow_mut()
+            .wallet
+            .contribute_payjoin(&mut proposal)
+            .map_err(|e| e.to_string())?;
+        *contributed.borrow_mut() = Some(outpoint);
+        let mut psbt = proposal.into_psbt();
+        modify(&mut psbt);
+        Ok(psbt)
+    };
+    let (mut psbt, transfer) = alice
+        .wallet
+        .pay_payjoin(invoice, params(), &client)
+        .unwrap();
+    assert!(psbt.rgb_verify_layout().is_ok());
+    let contributed = contributed.into_inner().expect("receiver contribution");
+
+    alice.signer.sign_psbt(&mut psbt).unwrap();
+    psbt.finalize(alice.wallet.wallet().descriptor());
+    bob.accept(transfer);
+    bob.signer.sign_psbt(&mut psbt).unwrap();
+    psbt.finalize(bob.wallet.wallet().descriptor());
+    let tx = psbt.extract().unwrap();
+    assert!(tx
+        .inputs
+        .iter()
+        .any(|input| input.prev_output == contributed));
+    alice.chain.broadcast(&tx).unwrap();
+    alice.chain.mine(1);
+    alice.sync();
+    bob.sync();
+    contributed
+}
+
+#[test]
+fn payjoin_endpoint() {
+    assert!(PayjoinEndpoint::new(ENDPOINT).is_ok());
+    assert!(matches!(
+        PayjoinEndpoint::new("http://payjoin.example.com"),
+        Err(PayjoinError::InvalidEndpoint(_))
+    ));
+
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new_wpkh(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    let invoice = bob.invoice(contract_id, 100, false);
+    assert!(PayjoinEndpoint::from_invoice(&invoice).unwrap().is_none());
+    assert!(matches!(
+        alice.wallet.propose_payjoin(invoice, params()),
+        Err(PayjoinError::NoEndpoint)
+    ));
+
+    let invoice = payjoin_invoice(&mut bob, contract_id, false);
+    let invoice: RgbInvoice = invoice.to_string().parse().unwrap();
+    assert_eq!(
+        PayjoinEndpoint::from_invoice(&invoice).unwrap(),
+        Some(PayjoinEndpoint::new(ENDPOINT).unwrap())
+    );
+}
+
+#[test]
+fn payjoin_witness() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new_wpkh(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund_sats(5_000);
+    let smallest = bob.fund_sats(3_000);
+
+    let invoice = payjoin_invoice(&mut bob, contract_id, false);
+    let contributed = pay_payjoin(&mut alice, &mut bob, invoice, |_| {});
+    // The smallest coin is contributed
+    assert_eq!(contributed, smallest);
+
+    assert_eq!(bob.balance(contract_id).confirmed, amount(100));
+    assert_eq!(alice.balance(contract_id).confirmed, amount(900));
+}
+
+#[test]
+fn payjoin_blinded_reordered() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new_wpkh(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(1_000);
+    bob.fund_sats(5_000);
+
+    // The receiver moves the outputs, such that the payer has to compose the
+    // state transitions once again
+    let invoice = payjoin_invoice(&mut bob, contract_id, true);
+    pay_payjoin(&mut alice, &mut bob, invoice, |psbt| {
+        psbt.sort_outputs_by(|output| Reverse(output.vout()))
+            .unwrap();
+    });
+
+    assert_eq!(bob.balance(contract_id).confirmed, amount(100));
+    assert_eq!(alice.balance(contract_id).confirmed, amount(900));
+}
+
+#[test]
+fn payjoin_output_changed() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new_wpkh(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund_sats(5_000);
+
+    let invoice = payjoin_invoice(&mut bob, contract_id, false);
+    let (proposal, meta) = alice.wallet.propose_payjoin(invoice, params()).unwrap();
+    let change_vout = meta.change_vout.unwrap();
+
+    let mut contributed = PayjoinProposal::from_psbt(proposal.psbt().clone()).unwrap();
+    bob.wallet.contribute_payjoin(&mut contributed).unwrap();
+    let mut payjoin = contributed.into_psbt();
+    payjoin.output_mut(change_vout.to_usize()).unwrap().amount -= Sats::from_sats(100_u64);
+    assert!(matches!(
+        alice.wallet.finalize_payjoin(&proposal, payjoin),
+        Err(PayjoinError::OutputChanged(vout)) if vout == change_vout.to_u32()
+    ));
+}
@@ -0,0 +1,169 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Trust policy deciding on automatic acceptance of transfers depending on
+//! their issuer, and quarantine of the untrusted ones.
+
+mod common;
+
+use std::fs;
+use std::str::FromStr;
+
+use bpstd::Outpoint;
+use common::{amount, temp_dir, Party, NETWORK};
+use rgb::containers::{ConsignmentExt, FileContent, Transfer};
+use rgb::resolvers::{ContractIssueResolver, MockChain};
+use rgb::{
+    ContractId, DescriptorRgb, Identity, IssuanceTemplate, OutputSeal, PolicyRule, Precision,
+    Quarantine, TrustPolicy,
+};
+
+const ISSUER: &str = "ssi:acme";
+
+/// Issues RGB20 asset on behalf of the issuer with the given identity.
+fn issue(party: &mut Party, outpoint: Outpoint, issuer: Identity) -> ContractId {
+    let method = party.wallet.wallet().seal_close_method();
+    let mut issuance = rgb::Rgb20Issuance::new("ACME", "Acme asset", Precision::Indivisible);
+    issuance.allocate(OutputSeal::new(method, outpoint), 1_000);
+    let contract = issuance.issue(party.wallet.stock(), issuer, None).unwrap();
+    let contract_id = contract.contract_id();
+    party
+        .wallet
+        .stock_mut()
+        .import_contract(contract, &ContractIssueResolver)
+        .unwrap();
+    contract_id
+}
+
+/// Alice issues asset on behalf of the issuer and pays part of it to Bob.
+fn transfer(chain: &MockChain, bob: &mut Party, issuer: Identity) -> (ContractId, Transfer) {
+    let mut alice = Party::new(chain, 1);
+    let outpoint = alice.fund(100_000);
+    let contract_id = issue(&mut alice, outpoint, issuer);
+    bob.fund(10_000);
+    let invoice = bob.invoice(contract_id, 100, true);
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    (contract_id, transfer)
+}
+
+fn policy() -> TrustPolicy {
+    let mut policy = TrustPolicy::new();
+    assert!(policy.add(PolicyRule::from_str(&format!("issuer:{ISSUER}")).unwrap()));
+    assert!(!policy.add(PolicyRule::Issuer(Identity::from_str(ISSUER).unwrap())));
+    policy
+}
+
+#[test]
+fn policy_trusted_issuer() {
+    let chain = MockChain::new(NETWORK);
+    let mut bob = Party::new(&chain, 2);
+    let issuer = Identity::from_str(ISSUER).unwrap();
+    let (contract_id, transfer) = transfer(&chain, &mut bob, issuer.clone());
+
+    let policy = policy();
+    assert!(policy.is_trusted(&transfer));
+    assert_eq!(policy.matching_rule(&transfer), Some(PolicyRule::Issuer(issuer)));
+
+    bob.accept(transfer);
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).confirmed, amount(100));
+}
+
+#[test]
+fn policy_untrusted_issuer() {
+    let chain = MockChain::new(NETWORK);
+    let mut bob = Party::new(&chain, 2);
+    let issuer = Identity::from_str("ssi:spoofer").unwrap();
+    let (contract_id, transfer) = transfer(&chain, &mut bob, issuer.clone());
+
+    let mut policy = policy();
+    assert!(!policy.is_trusted(&transfer));
+    assert_eq!(policy.matching_rule(&transfer), None);
+
+    // Untrusted transfer waits in the quarantine until it is reviewed
+    let dir = temp_dir("policy-quarantine");
+    let quarantine = Quarantine::new(dir.join("quarantine"));
+    assert!(quarantine.list().unwrap().is_empty());
+    let path = quarantine.put(&transfer).unwrap();
+    assert_eq!(quarantine.list().unwrap(), vec![path.clone()]);
+    assert!(bob
+        .wallet
+        .stock()
+        .contracts()
+        .unwrap()
+        .all(|info| info.id != contract_id));
+
+    // After the review the issuer becomes trusted and the transfer is accepted
+    let transfer = Transfer::load_file(&path).unwrap();
+    assert!(policy.add(PolicyRule::Issuer(issuer.clone())));
+    assert_eq!(policy.matching_rule(&transfer), Some(PolicyRule::Issuer(issuer)));
+    let id = transfer.consignment_id();
+    bob.accept(transfer);
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).confirmed, amount(100));
+    assert!(quarantine.release(id).unwrap());
+    assert!(!quarantine.release(id).unwrap());
+    assert!(quarantine.list().unwrap().is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn policy_unknown_issuer() {
+    let chain = MockChain::new(NETWORK);
+    let mut bob = Party::new(&chain, 2);
+    let (contract_id, transfer) = transfer(&chain, &mut bob, Identity::default());
+
+    // Wallet without the policy accepts anything
+    assert!(TrustPolicy::new().is_trusted(&transfer));
+
+    let mut policy = policy();
+    assert!(!policy.is_trusted(&transfer));
+
+    // The contract itself may be trusted regardless of its issuer
+    let rule = PolicyRule::from_str(&format!("contract:{contract_id}")).unwrap();
+    assert!(policy.add(rule.clone()));
+    assert_eq!(policy.matching_rule(&transfer), Some(rule.clone()));
+    assert!(policy.remove(&rule));
+    assert!(!policy.is_trusted(&transfer));
+
+    assert!(PolicyRule::from_str("issuer").is_err());
+    assert!(PolicyRule::from_str("owner:ssi:acme").is_err());
+    assert!(PolicyRule::from_str("contract:ssi:acme").is_err());
+}
+
+#[test]
+fn policy_file() {
+    let dir = temp_dir("policy-file");
+    let path = dir.join("policy.yaml");
+    assert!(TrustPolicy::load_file(&path).unwrap().is_empty());
+
+    let policy = policy();
+    policy.save_file(&path).unwrap();
+    let loaded = TrustPolicy::load_file(&path).unwrap();
+    assert_eq!(loaded, policy);
+    assert_eq!(loaded.rules().collect::<Vec<_>>(), vec![PolicyRule::Issuer(
+        Identity::from_str(ISSUER).unwrap()
+    )]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
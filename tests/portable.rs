@@ -0,0 +1,85 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lossless portable form of the consignment data.
+
+mod common;
+
+use common::{Party, NETWORK};
+use rgb::containers::Transfer;
+use rgb::resolvers::MockChain;
+use rgb::{from_portable, to_portable, PortableValueError};
+use serde_yaml::{Mapping, Value};
+
+fn assert_portable(value: &Value) {
+    match value {
+        Value::Null => panic!("null value in the portable form"),
+        Value::Number(num) => assert!(num.as_u64().map_or(true, |n| n <= i64::MAX as u64)),
+        Value::Sequence(items) => items.iter().for_each(assert_portable),
+        Value::Mapping(map) => {
+            for (key, val) in map {
+                assert!(key.is_string(), "non-string key {key:?}");
+                assert_portable(val);
+            }
+        }
+        Value::Tagged(_) => panic!("tagged value in the portable form"),
+        Value::Bool(_) | Value::String(_) => {}
+    }
+}
+
+#[test]
+fn transfer_round_trip() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+    let invoice = bob.invoice(contract_id, 100, true);
+    let (_, transfer) = alice.pay(&invoice);
+
+    let value = serde_yaml::to_value(&transfer).unwrap();
+    let portable = to_portable(value.clone());
+    assert_portable(&portable);
+
+    let restored = from_portable(portable).unwrap();
+    assert_eq!(restored, value);
+    let reconstructed: Transfer = serde_yaml::from_value(restored).unwrap();
+    assert_eq!(reconstructed.consignment_id(), transfer.consignment_id());
+}
+
+#[test]
+fn markers() {
+    let mut map = Mapping::new();
+    map.insert(Value::from(1u8), Value::Null);
+    map.insert(Value::from("$key"), Value::from(u64::MAX));
+    let value = Value::Sequence(vec![Value::Mapping(map), Value::Null]);
+    let portable = to_portable(value.clone());
+    assert_portable(&portable);
+    assert_eq!(from_portable(portable).unwrap(), value);
+
+    let invalid: Value = serde_yaml::from_str("{$u64: abc}").unwrap();
+    assert_eq!(from_portable(invalid), Err(PortableValueError::InvalidMarker("$u64")));
+    let invalid: Value = serde_yaml::from_str("{$map: [[1]]}").unwrap();
+    assert_eq!(from_portable(invalid), Err(PortableValueError::InvalidMarker("$map")));
+    let invalid: Value = serde_yaml::from_str("{1: 2}").unwrap();
+    assert!(matches!(from_portable(invalid), Err(PortableValueError::NonStringKey(_))));
+}
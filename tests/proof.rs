@@ -0,0 +1,106 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proofs of a single allocation verified by a third party without the full
+//! contract history.
+
+mod common;
+
+use common::{amount, Party, NETWORK};
+use rgb::resolvers::{AnyResolver, MockChain};
+use rgb::{AllocationProof, AllocationProofError, ContractGraph, XChain};
+
+#[test]
+fn allocation_proof() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+
+    let invoice = bob.invoice(contract_id, 100, true);
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    bob.accept(transfer);
+    let unrelated = bob.fund(10_000);
+
+    let graph = ContractGraph::build(bob.wallet.stock(), contract_id).unwrap();
+    let paid = graph
+        .allocations
+        .iter()
+        .find(|a| a.amount == Some(amount(100)))
+        .and_then(|a| a.seal)
+        .expect("payment allocation");
+    let XChain::Bitcoin(paid) = paid else {
+        panic!("payment allocation on bitcoin")
+    };
+
+    let proof = AllocationProof::build(bob.wallet.stock(), contract_id, paid).unwrap();
+    assert_eq!(proof.contract_id(), contract_id);
+    assert!(proof.consignment().ifaces.is_empty());
+
+    let mut resolver = AnyResolver::mock(&chain);
+    let allocations = proof
+        .verify(contract_id, paid, &mut resolver, NETWORK.is_testnet())
+        .unwrap();
+    assert_eq!(allocations.len(), 1);
+    assert_eq!(allocations[0].amount, Some(amount(100)));
+    assert!(allocations[0].witness.is_some());
+
+    // The proof says nothing about the outputs without the contract state
+    assert!(matches!(
+        proof.verify(contract_id, unrelated, &mut resolver, NETWORK.is_testnet()),
+        Err(AllocationProofError::NoAllocation { .. })
+    ));
+}
+
+#[test]
+fn wrong_contract() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let other_outpoint = alice.fund(10_000);
+    let other = alice.issue(other_outpoint, 500);
+
+    let proof = AllocationProof::build(alice.wallet.stock(), contract_id, outpoint).unwrap();
+    let mut resolver = AnyResolver::mock(&chain);
+    assert!(matches!(
+        proof.verify(other, outpoint, &mut resolver, NETWORK.is_testnet()),
+        Err(AllocationProofError::WrongContract { expected, found })
+            if expected == other && found == contract_id
+    ));
+}
+
+#[test]
+fn no_allocation() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let empty = alice.fund(10_000);
+
+    assert!(matches!(
+        AllocationProof::build(alice.wallet.stock(), contract_id, empty),
+        Err(AllocationProofError::NoAllocation { .. })
+    ));
+}
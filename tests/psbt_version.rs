@@ -0,0 +1,115 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Versioning of the RGB data layout in PSBTs and upgrade of the PSBTs
+//! produced by the older versions.
+
+mod common;
+
+use bpstd::Sats;
+use common::{Party, FEE, NETWORK, SATS};
+use psrgbt::{
+    CommitError, ExtractError, PropKey, ProprietaryKeyRgb, Psbt, RgbExt, RgbPsbt, RgbPsbtError,
+    UnsupportedRgbPsbtVersion, PSBT_RGB_PREFIX, RGB_PSBT_VERSION, RGB_PSBT_VERSION_LEGACY,
+};
+use rgb::resolvers::MockChain;
+use rgb::TransferParams;
+
+fn transfer_psbt() -> Psbt {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+
+    let invoice = bob.invoice(contract_id, 100, true);
+    let (psbt, _, _) = alice
+        .wallet
+        .pay(&invoice, TransferParams::with(Sats::from_sats(FEE), Sats::from_sats(SATS)))
+        .expect("payment");
+    psbt
+}
+
+#[test]
+fn version_embedded() {
+    let psbt = transfer_psbt();
+    assert_eq!(
+        psbt.proprietary
+            .get(&PropKey::rgb_version())
+            .map(|data| data.to_vec()),
+        Some(vec![RGB_PSBT_VERSION])
+    );
+    assert_eq!(psbt.rgb_version(), Ok(RGB_PSBT_VERSION));
+    assert!(psbt.rgb_extract().is_ok());
+}
+
+#[test]
+fn version_legacy_upgrade() {
+    let mut psbt = transfer_psbt();
+    let fascia = psbt.rgb_extract().unwrap();
+    psbt.proprietary.shift_remove(&PropKey::rgb_version());
+    assert!(psbt.has_rgb_data());
+    assert_eq!(psbt.rgb_version(), Ok(RGB_PSBT_VERSION_LEGACY));
+    assert_eq!(psbt.rgb_extract().unwrap(), fascia);
+
+    assert_eq!(psbt.rgb_upgrade(), Ok(RGB_PSBT_VERSION_LEGACY));
+    assert_eq!(psbt.rgb_version(), Ok(RGB_PSBT_VERSION));
+    assert_eq!(psbt.rgb_extract().unwrap(), fascia);
+    // Upgrade of an up-to-date PSBT doesn't change anything
+    assert_eq!(psbt.rgb_upgrade(), Ok(RGB_PSBT_VERSION));
+
+    // PSBTs without RGB data are left intact
+    let mut plain = psbt.clone();
+    plain
+        .proprietary
+        .retain(|key, _| key.identifier != PSBT_RGB_PREFIX);
+    for input in plain.inputs_mut() {
+        input
+            .proprietary
+            .retain(|key, _| key.identifier != PSBT_RGB_PREFIX);
+    }
+    for output in plain.outputs_mut() {
+        output
+            .proprietary
+            .retain(|key, _| key.identifier != PSBT_RGB_PREFIX);
+    }
+    assert!(!plain.has_rgb_data());
+    assert_eq!(plain.rgb_upgrade(), Ok(RGB_PSBT_VERSION_LEGACY));
+    assert!(!plain.has_rgb_data());
+}
+
+#[test]
+fn version_unsupported() {
+    let mut psbt = transfer_psbt();
+    let newer = RGB_PSBT_VERSION + 1;
+    psbt.proprietary
+        .insert(PropKey::rgb_version(), vec![newer].into());
+    let err = UnsupportedRgbPsbtVersion::Newer(newer);
+    assert_eq!(psbt.rgb_version(), Err(err));
+    assert_eq!(psbt.rgb_upgrade(), Err(err));
+    assert_eq!(psbt.rgb_extract(), Err(ExtractError::UnsupportedVersion(err)));
+    assert_eq!(psbt.rgb_commit(), Err(CommitError::Rgb(RgbPsbtError::UnsupportedVersion(err))));
+
+    psbt.proprietary
+        .insert(PropKey::rgb_version(), vec![RGB_PSBT_VERSION, 0].into());
+    assert_eq!(psbt.rgb_version(), Err(UnsupportedRgbPsbtVersion::Malformed));
+}
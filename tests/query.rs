@@ -0,0 +1,178 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pagination and filtering of the contract state.
+
+mod common;
+
+use std::str::FromStr;
+
+use bpstd::{Outpoint, Txid};
+use common::{amount, Party, NETWORK};
+use rgb::containers::ConsignmentExt;
+use rgb::interface::FilterIncludeAll;
+use rgb::resolvers::{ContractIssueResolver, MockChain};
+use rgb::{
+    AmountRange, ContractId, ContractStateQuery, DescriptorRgb, Identity, IssuanceTemplate,
+    OutputSeal, Precision, StateQuery, WitnessStatus, XChain, XOutpoint, RGB20_IFACE,
+};
+use strict_types::TypeName;
+
+/// Issues contract allocating amounts from 1 to `count` to separate outpoints.
+fn issue_many(party: &mut Party, count: u8) -> (ContractId, Vec<Outpoint>) {
+    let method = party.wallet.wallet().seal_close_method();
+    let mut issuance = rgb::Rgb20Issuance::new("MANY", "Many allocations", Precision::Indivisible);
+    let outpoints = (1..=count)
+        .map(|no| Outpoint::new(Txid::from([no; 32]), 0))
+        .collect::<Vec<_>>();
+    for (no, outpoint) in outpoints.iter().enumerate() {
+        issuance.allocate(OutputSeal::new(method, *outpoint), no as u64 + 1);
+    }
+    let contract = issuance
+        .issue(party.wallet.stock(), Identity::default(), None)
+        .unwrap();
+    let contract_id = contract.contract_id();
+    party
+        .wallet
+        .stock_mut()
+        .import_contract(contract, &ContractIssueResolver)
+        .unwrap();
+    (contract_id, outpoints)
+}
+
+fn amounts(party: &Party, contract_id: ContractId, query: &StateQuery) -> Vec<u64> {
+    let contract = party
+        .wallet
+        .stock()
+        .contract_iface(contract_id, TypeName::from(RGB20_IFACE))
+        .unwrap();
+    let mut amounts = contract
+        .query_fungible("assetOwner", FilterIncludeAll, query)
+        .unwrap()
+        .map(|allocation| allocation.state.value())
+        .collect::<Vec<_>>();
+    amounts.sort();
+    amounts
+}
+
+#[test]
+fn query_pages() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let (contract_id, outpoints) = issue_many(&mut alice, 10);
+
+    assert_eq!(amounts(&alice, contract_id, &StateQuery::new()), (1..=10).collect::<Vec<_>>());
+
+    // Pages don't overlap and cover all the allocations
+    let mut paged = vec![];
+    for page in 0..4 {
+        let found = amounts(&alice, contract_id, &StateQuery::page(page * 3, 3));
+        assert!(found.len() <= 3);
+        paged.extend(found);
+    }
+    paged.sort();
+    assert_eq!(paged, (1..=10).collect::<Vec<_>>());
+    assert!(amounts(&alice, contract_id, &StateQuery::page(10, 3)).is_empty());
+
+    let mut query = StateQuery::new();
+    query.amounts = AmountRange {
+        min: Some(amount(3)),
+        max: Some(amount(5)),
+    };
+    assert_eq!(amounts(&alice, contract_id, &query), vec![3, 4, 5]);
+    query.limit = Some(2);
+    assert_eq!(amounts(&alice, contract_id, &query).len(), 2);
+
+    let mut query = StateQuery::new();
+    query.outpoints = [outpoints[0], outpoints[9]]
+        .into_iter()
+        .map(|outpoint| XOutpoint::from(XChain::Bitcoin(outpoint)))
+        .collect();
+    assert_eq!(amounts(&alice, contract_id, &query), vec![1, 10]);
+}
+
+#[test]
+fn query_witness_status() {
+    for status in [
+        WitnessStatus::Genesis,
+        WitnessStatus::Mined,
+        WitnessStatus::Tentative,
+        WitnessStatus::Archived,
+    ] {
+        assert_eq!(WitnessStatus::from_str(&status.to_string()), Ok(status));
+    }
+    assert!(WitnessStatus::from_str("confirmed").is_err());
+
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let invoice = bob.invoice(contract_id, 100, false);
+    alice.pay(&invoice);
+
+    let query = |status| {
+        let mut query = StateQuery::new();
+        query.witness.insert(status);
+        query
+    };
+    assert_eq!(amounts(&alice, contract_id, &query(WitnessStatus::Genesis)), vec![1_000]);
+    assert_eq!(amounts(&alice, contract_id, &query(WitnessStatus::Tentative)), vec![100, 900]);
+    assert!(amounts(&alice, contract_id, &query(WitnessStatus::Mined)).is_empty());
+
+    chain.mine(1);
+    alice.sync();
+    assert_eq!(amounts(&alice, contract_id, &query(WitnessStatus::Mined)), vec![100, 900]);
+    assert!(amounts(&alice, contract_id, &query(WitnessStatus::Tentative)).is_empty());
+}
+
+#[test]
+fn query_confirmations() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let invoice = bob.invoice(contract_id, 100, false);
+    let (txid, _) = alice.pay(&invoice);
+
+    let query = |min| StateQuery::new().with_min_confirmations(min, chain.tip_height());
+    assert_eq!(amounts(&alice, contract_id, &query(0)), vec![100, 900, 1_000]);
+    assert_eq!(amounts(&alice, contract_id, &query(1)), vec![1_000]);
+
+    chain.mine(1);
+    alice.sync();
+    assert_eq!(amounts(&alice, contract_id, &query(1)), vec![100, 900, 1_000]);
+    assert_eq!(amounts(&alice, contract_id, &query(2)), vec![1_000]);
+
+    chain.mine(1);
+    alice.sync();
+    assert_eq!(amounts(&alice, contract_id, &query(2)), vec![100, 900, 1_000]);
+
+    let contract = alice
+        .wallet
+        .stock()
+        .contract_iface(contract_id, TypeName::from(RGB20_IFACE))
+        .unwrap();
+    let witness = XChain::Bitcoin(txid);
+    assert_eq!(contract.confirmations(Some(witness), chain.tip_height()), Some(2));
+    assert_eq!(contract.confirmations(None, chain.tip_height()), Some(u32::MAX));
+}
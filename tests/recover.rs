@@ -0,0 +1,69 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reconstruction of the stock from the backups of the received consignments.
+
+mod common;
+
+use std::fs;
+
+use common::{amount, Party, NETWORK};
+use rgb::containers::FileContent;
+use rgb::resolvers::{AnyResolver, MockChain};
+use rgb::StockRecovery;
+
+#[test]
+fn recover_from_dir() {
+    let dir = std::env::temp_dir().join(format!("rgb-recover-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let invoice = bob.invoice(contract_id, 400, false);
+    let (_, consignment) = alice.pay(&invoice);
+    consignment.save_file(dir.join("payment.rgb")).unwrap();
+    fs::write(dir.join("notes.txt"), "not a consignment").unwrap();
+    bob.accept(consignment);
+    chain.mine(1);
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).confirmed, amount(400));
+
+    // Bob has lost his stock and recovers it from the backups
+    let mut bob = Party::new(&chain, 2);
+    let report = bob
+        .wallet
+        .stock_mut()
+        .recover_from_dir(&dir, &AnyResolver::mock(&chain), true)
+        .unwrap();
+    assert_eq!(report.transfers, 1);
+    assert_eq!(report.recovered(), 1);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, dir.join("notes.txt"));
+
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).confirmed, amount(400));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
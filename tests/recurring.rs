@@ -0,0 +1,183 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recurring payments instantiated from a payment template and paid in a
+//! batch of transactions.
+
+mod common;
+
+use bpstd::{Idx, NormalIndex, Sats};
+use common::{amount, params, Party, FEE, NETWORK};
+use psrgbt::{ConstructionError, PsbtConstructor};
+use rgb::resolvers::MockChain;
+use rgb::{
+    CompositionError, DescriptorRgb, PayError, PaymentTemplate, Signer, TemplateBeneficiary,
+    TransferParams, RGB20_IFACE,
+};
+
+/// Provides the payer with the state of a new contract split into `count`
+/// allocations of 100 each, every one holding enough bitcoins to pay for the
+/// transfer.
+fn payer(chain: &MockChain, count: usize) -> (Party, rgb::ContractId) {
+    let mut alice = Party::new(chain, 1);
+    let mut bob = Party::new(chain, 2);
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    for _ in 0..count {
+        let invoice = bob.invoice(contract_id, 100, false);
+        let (mut psbt, _, transfer) = alice
+            .wallet
+            .pay(&invoice, TransferParams::with(Sats::from_sats(FEE), Sats::from_sats(10_000u64)))
+            .expect("payment");
+        alice.signer.sign_psbt(&mut psbt).expect("signing");
+        psbt.finalize(alice.wallet.wallet().descriptor());
+        let tx = psbt.extract().expect("finalized transaction");
+        chain.broadcast(&tx).expect("valid witness transaction");
+        chain.mine(1);
+        alice.sync();
+        bob.accept(transfer);
+        bob.sync();
+    }
+    (bob, contract_id)
+}
+
+#[test]
+fn template_instances() {
+    let chain = MockChain::new(NETWORK);
+    let mut carol = Party::new(&chain, 3);
+    let (terminal, address) = carol.wallet.next_rgb_address(false);
+    let descriptor = carol.wallet.wallet().descriptor().clone();
+    let method = descriptor.seal_close_method();
+    let beneficiary = TemplateBeneficiary::Derived {
+        descriptor,
+        start: terminal.index,
+    };
+    let outpoint = carol.fund(10_000);
+    let contract_id = carol.issue(outpoint, 1_000);
+    let template =
+        PaymentTemplate::new(NETWORK, contract_id, 100u64, beneficiary).set_interface(RGB20_IFACE);
+
+    let invoices = template.instantiate_range(0..3).unwrap();
+    assert_eq!(invoices.len(), 3);
+    assert_eq!(invoices[0], template.instantiate(0).unwrap());
+    assert_ne!(invoices[0].beneficiary, invoices[1].beneficiary);
+    for invoice in &invoices {
+        assert_eq!(invoice.contract, Some(contract_id));
+        assert_eq!(invoice.iface.as_ref().map(|iface| iface.as_str()), Some(RGB20_IFACE));
+        assert_eq!(invoice.owned_state, rgb::invoice::InvoiceState::Amount(amount(100)));
+    }
+    let rgb::invoice::Beneficiary::WitnessVout(pay2vout) = invoices[0].beneficiary.into_inner()
+    else {
+        panic!("template must pay to witness output");
+    };
+    assert_eq!(pay2vout.address, address.payload);
+    assert_eq!(pay2vout.method, method);
+
+    let fixed =
+        PaymentTemplate::new(NETWORK, contract_id, 100u64, TemplateBeneficiary::Address(pay2vout));
+    assert_eq!(
+        fixed.instantiate(0).unwrap().beneficiary,
+        fixed.instantiate(1).unwrap().beneficiary
+    );
+
+    let exhausted =
+        PaymentTemplate::new(NETWORK, contract_id, 100u64, TemplateBeneficiary::Derived {
+            descriptor: carol.wallet.wallet().descriptor().clone(),
+            start: NormalIndex::MAX,
+        });
+    assert!(exhausted.instantiate(0).is_ok());
+    assert!(matches!(exhausted.instantiate(1), Err(CompositionError::TemplateExhausted(1))));
+}
+
+#[test]
+fn pay_batch() {
+    let chain = MockChain::new(NETWORK);
+    let (mut bob, contract_id) = payer(&chain, 3);
+    let mut carol = Party::new(&chain, 3);
+    let (terminal, _) = carol.wallet.next_rgb_address(false);
+    let template =
+        PaymentTemplate::new(NETWORK, contract_id, 100u64, TemplateBeneficiary::Derived {
+            descriptor: carol.wallet.wallet().descriptor().clone(),
+            start: terminal.index,
+        })
+        .set_interface(RGB20_IFACE);
+
+    let invoices = template.instantiate_range(0..2).unwrap();
+    let payments = bob.wallet.pay_batch(&invoices, params()).unwrap();
+    assert_eq!(payments.len(), 2);
+    let mut transfers = vec![];
+    let mut spent = vec![];
+    for (mut psbt, _, transfer) in payments {
+        spent.extend(psbt.inputs().map(|input| input.previous_outpoint));
+        bob.signer.sign_psbt(&mut psbt).expect("signing");
+        psbt.finalize(bob.wallet.wallet().descriptor());
+        let tx = psbt.extract().expect("finalized transaction");
+        chain.broadcast(&tx).expect("valid witness transaction");
+        transfers.push(transfer);
+    }
+    // Transactions of the batch never spend the same outputs
+    let count = spent.len();
+    spent.sort();
+    spent.dedup();
+    assert_eq!(spent.len(), count);
+
+    chain.mine(1);
+    bob.sync();
+    carol.sync();
+    for transfer in transfers {
+        carol.accept(transfer);
+    }
+    carol.sync();
+    assert_eq!(carol.balance(contract_id).confirmed, amount(200));
+    assert_eq!(bob.balance(contract_id).confirmed, amount(100));
+}
+
+#[test]
+fn pay_batch_insufficient() {
+    let chain = MockChain::new(NETWORK);
+    let (mut bob, contract_id) = payer(&chain, 1);
+    let carol = Party::new(&chain, 3);
+    let template =
+        PaymentTemplate::new(NETWORK, contract_id, 100u64, TemplateBeneficiary::Derived {
+            descriptor: carol.wallet.wallet().descriptor().clone(),
+            start: NormalIndex::ZERO,
+        })
+        .set_interface(RGB20_IFACE);
+
+    let invoices = template.instantiate_range(0..2).unwrap();
+    let err = bob.wallet.pay_batch(&invoices, params()).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            PayError::Composition(CompositionError::Construction(ConstructionError::NoInputs))
+        ),
+        "{err}"
+    );
+    // Nothing was paid
+    assert_eq!(bob.balance(contract_id).confirmed, amount(100));
+    assert_eq!(
+        bob.wallet
+            .pay_batch(&invoices[..1], params())
+            .unwrap()
+            .len(),
+        1
+    );
+}
@@ -0,0 +1,218 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detection of contracts imitating tickers and names of the known assets on
+//! contract import and transfer acceptance.
+
+mod common;
+
+use bpstd::{Outpoint, Txid};
+use common::{amount, Party, NETWORK};
+use rgb::containers::{ConsignmentExt, ValidContract};
+use rgb::resolvers::{AnyResolver, ContractIssueResolver, MockChain};
+use rgb::{
+    reveal_known_seals, AssetCollision, AssetRegistry, AssetRegistryStock, DescriptorRgb, Identity,
+    IssuanceTemplate, OutputSeal, Precision, RegistryError,
+};
+
+/// Creates RGB20 contract with the given ticker and name, allocating the
+/// supply to the outpoint.
+fn contract(party: &Party, ticker: &str, name: &str, outpoint: Outpoint) -> ValidContract {
+    let method = party.wallet.wallet().seal_close_method();
+    let mut issuance = rgb::Rgb20Issuance::new(ticker, name, Precision::Indivisible);
+    issuance.allocate(OutputSeal::new(method, outpoint), 1_000);
+    issuance
+        .issue(party.wallet.stock(), Identity::default(), None)
+        .unwrap()
+}
+
+fn dummy_outpoint(no: u8) -> Outpoint { Outpoint::new(Txid::from([no; 32]), 0) }
+
+fn contracts(party: &Party) -> usize { party.wallet.stock().contracts().unwrap().count() }
+
+#[test]
+fn registry_ticker_collision() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let known = contract(&alice, "ACME", "Acme asset", dummy_outpoint(1));
+    let known_id = known.contract_id();
+    let (_, collisions) = alice
+        .wallet
+        .stock_mut()
+        .import_contract_checked(known, ContractIssueResolver, false)
+        .unwrap();
+    assert!(collisions.is_empty());
+    let count = contracts(&alice);
+
+    // Tickers are compared case-insensitively
+    let imitation = contract(&alice, "acme", "Other asset", dummy_outpoint(2));
+    let imitation_id = imitation.contract_id();
+    let err = alice
+        .wallet
+        .stock_mut()
+        .import_contract_checked(imitation.clone(), ContractIssueResolver, false)
+        .unwrap_err();
+    let RegistryError::Collision(collisions) = err else {
+        panic!("unexpected error {err}");
+    };
+    assert_eq!(collisions.0, vec![AssetCollision::Ticker {
+        ticker: "acme".to_owned(),
+        known: known_id,
+    }]);
+    assert_eq!(contracts(&alice), count);
+
+    let (_, collisions) = alice
+        .wallet
+        .stock_mut()
+        .import_contract_checked(imitation, ContractIssueResolver, true)
+        .unwrap();
+    assert_eq!(collisions.len(), 1);
+    assert_eq!(contracts(&alice), count + 1);
+
+    let registry = AssetRegistry::with_stock(alice.wallet.stock()).unwrap();
+    let mut by_ticker = registry.contracts_by_ticker(" Acme ").collect::<Vec<_>>();
+    by_ticker.sort();
+    let mut expected = vec![known_id, imitation_id];
+    expected.sort();
+    assert_eq!(by_ticker, expected);
+}
+
+#[test]
+fn registry_name_collision() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let known = contract(&alice, "ACME", "Acme asset", dummy_outpoint(1));
+    let known_id = known.contract_id();
+    alice
+        .wallet
+        .stock_mut()
+        .import_contract_checked(known, ContractIssueResolver, false)
+        .unwrap();
+
+    let imitation = contract(&alice, "ACM", "ACME Asset", dummy_outpoint(2));
+    let err = alice
+        .wallet
+        .stock_mut()
+        .import_contract_checked(imitation, ContractIssueResolver, false)
+        .unwrap_err();
+    let RegistryError::Collision(collisions) = err else {
+        panic!("unexpected error {err}");
+    };
+    assert_eq!(collisions.0, vec![AssetCollision::Name {
+        name: "ACME Asset".to_owned(),
+        known: known_id,
+    }]);
+}
+
+#[test]
+fn registry_reimport() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let known = contract(&alice, "ACME", "Acme asset", dummy_outpoint(1));
+    alice
+        .wallet
+        .stock_mut()
+        .import_contract_checked(known.clone(), ContractIssueResolver, false)
+        .unwrap();
+    let count = contracts(&alice);
+
+    // Contract doesn't collide with itself, so the second import is a no-op
+    let (_, collisions) = alice
+        .wallet
+        .stock_mut()
+        .import_contract_checked(known, ContractIssueResolver, false)
+        .unwrap();
+    assert!(collisions.is_empty());
+    assert_eq!(contracts(&alice), count);
+}
+
+#[test]
+fn registry_transfer() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    // Bob knows asset with the same ticker as the one he is paid with
+    let known = contract(&bob, "ACME", "Acme asset", dummy_outpoint(1));
+    let known_id = known.contract_id();
+    bob.wallet
+        .stock_mut()
+        .import_contract_checked(known, ContractIssueResolver, false)
+        .unwrap();
+
+    let outpoint = alice.fund(100_000);
+    let imitation = contract(&alice, "ACME", "Acme coin", outpoint);
+    let contract_id = imitation.contract_id();
+    alice
+        .wallet
+        .stock_mut()
+        .import_contract(imitation, &ContractIssueResolver)
+        .unwrap();
+    bob.fund(10_000);
+    let invoice = bob.invoice(contract_id, 100, true);
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+
+    let mut resolver = AnyResolver::mock(&chain);
+    resolver.add_terminals(&transfer);
+    let transfer = reveal_known_seals(bob.wallet.stock(), transfer).unwrap();
+    let valid = transfer.clone().validate(&resolver, true).unwrap();
+    let err = bob
+        .wallet
+        .stock_mut()
+        .accept_transfer_checked(valid, &resolver, false)
+        .unwrap_err();
+    let RegistryError::Collision(collisions) = err else {
+        panic!("unexpected error {err}");
+    };
+    assert_eq!(collisions.0, vec![AssetCollision::Ticker {
+        ticker: "ACME".to_owned(),
+        known: known_id,
+    }]);
+    assert!(bob
+        .wallet
+        .stock()
+        .contracts()
+        .unwrap()
+        .all(|info| info.id != contract_id));
+
+    let count = contracts(&bob);
+    let valid = transfer.clone().validate(&resolver, true).unwrap();
+    let (_, collisions) = bob
+        .wallet
+        .stock_mut()
+        .accept_transfer_checked(valid, &resolver, true)
+        .unwrap();
+    assert_eq!(collisions.len(), 1);
+    assert_eq!(contracts(&bob), count + 1);
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).confirmed, amount(100));
+
+    // Accepting the same transfer once more changes nothing
+    let valid = transfer.validate(&resolver, true).unwrap();
+    bob.wallet
+        .stock_mut()
+        .accept_transfer_checked(valid, &resolver, true)
+        .unwrap();
+    bob.sync();
+    assert_eq!(contracts(&bob), count + 1);
+    assert_eq!(bob.balance(contract_id).confirmed, amount(100));
+}
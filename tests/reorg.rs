@@ -0,0 +1,72 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Handling of a re-org disconnecting the block with the witness transaction,
+//! which is then evicted from the mempool.
+
+mod common;
+
+use common::{amount, Party, NETWORK};
+use rgb::resolvers::{AnyResolver, MockChain};
+
+#[test]
+fn reorg() {
+    let chain = MockChain::new(NETWORK);
+    let resolver = AnyResolver::mock(&chain);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+
+    let invoice = bob.invoice(contract_id, 400, true);
+    let (txid, consignment) = alice.pay(&invoice);
+    bob.accept(consignment);
+    chain.mine(1);
+    bob.sync();
+    bob.wallet.track_witnesses(&resolver).unwrap();
+    assert_eq!(bob.wallet.reorg_tracker().witness_height(txid), Some(chain.tip_height()));
+    assert_eq!(bob.balance(contract_id).confirmed, amount(400));
+
+    // Block with the witness transaction is disconnected
+    let old_tip = chain.tip_height();
+    assert_eq!(chain.reorg(1), vec![txid]);
+    let new_tip = chain.tip_height();
+    assert_eq!(chain.confirmations(txid), Some(0));
+    assert!(bob
+        .wallet
+        .handle_reorg(old_tip, new_tip, &resolver)
+        .unwrap()
+        .is_some());
+    let balance = bob.balance(contract_id);
+    assert_eq!(balance.confirmed, amount(0));
+    assert_eq!(balance.unconfirmed_incoming, amount(400));
+
+    // Once the witness is evicted from the mempool the transfer is void and
+    // the state returns to the payer
+    assert_eq!(chain.drop_tx(txid), vec![txid]);
+    chain.mine(1);
+    alice.sync();
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).total(), amount(0));
+    assert_eq!(alice.balance(contract_id).confirmed, amount(1_000));
+}
@@ -0,0 +1,87 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Structured consignment validation reports.
+
+mod common;
+
+use common::{Party, NETWORK};
+use rgb::resolvers::{AnyResolver, MockChain};
+use rgb::{IssueKind, IssueSeverity, OpFullType, ReportValidity, ValidationReport, XChain};
+
+#[test]
+fn report_valid() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let invoice = bob.invoice(contract_id, 100, false);
+    let (txid, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+
+    let mut resolver = AnyResolver::mock(&chain);
+    resolver.add_terminals(&transfer);
+    let report = ValidationReport::validate(transfer.clone(), &resolver, true);
+    assert_eq!(report.validity, ReportValidity::Valid, "{report}");
+    assert_eq!(report.contract_id, contract_id);
+    assert_eq!(report.all_issues().count(), 0);
+    assert_eq!(report.operations.len(), 1);
+    assert_eq!(report.operations[0].op_type, Some(OpFullType::Genesis));
+    assert_eq!(report.bundles.len(), 1);
+    let bundle = &report.bundles[0];
+    assert_eq!(bundle.witness_id, Some(XChain::Bitcoin(txid)));
+    assert!(bundle.is_valid());
+    assert_eq!(bundle.operations.len(), 1);
+    assert!(matches!(bundle.operations[0].op_type, Some(OpFullType::StateTransition(_))));
+    assert!(report.operation(bundle.operations[0].opid).is_some());
+
+    let yaml = serde_yaml::to_string(&report).unwrap();
+    let restored: ValidationReport = serde_yaml::from_str(&yaml).unwrap();
+    assert_eq!(restored, report);
+}
+
+#[test]
+fn report_failures() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let invoice = bob.invoice(contract_id, 100, false);
+    let (txid, transfer) = alice.pay(&invoice);
+
+    // The resolver doesn't know the witness transaction
+    let other = MockChain::new(NETWORK);
+    let report = ValidationReport::validate(transfer, &AnyResolver::mock(&other), true);
+    assert_eq!(report.validity, ReportValidity::Invalid);
+    let bundle = &report.bundles[0];
+    assert!(!bundle.is_valid());
+    assert!(bundle.issues.iter().any(|issue| {
+        issue.severity == IssueSeverity::Failure
+            && issue.kind == IssueKind::MissingWitness
+            && issue.witness_id == Some(XChain::Bitcoin(txid))
+    }));
+    assert!(report.to_string().contains("FAILED"));
+    assert_eq!(report.contract_id, contract_id);
+}
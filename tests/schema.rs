@@ -0,0 +1,87 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Description of the schemata known to the stock.
+
+mod common;
+
+use common::{Party, NETWORK};
+use rgb::persistence::StashReadProvider;
+use rgb::resolvers::MockChain;
+use rgb::SchemaDescription;
+
+#[test]
+fn describe_schema() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    let stock = alice.wallet.stock();
+    let schema_id = stock
+        .as_stash_provider()
+        .genesis(contract_id)
+        .expect("known contract")
+        .schema_id;
+    let description = SchemaDescription::with_stock(stock, schema_id).unwrap();
+    assert_eq!(description.schema_id, schema_id);
+    assert_eq!(description.name, "NonInflatableAsset");
+
+    assert_eq!(description.interfaces.len(), 1);
+    let iface = &description.interfaces[0];
+    assert_eq!(iface.name, "RGB20Fixed");
+    assert_eq!(iface.default_operation.as_deref(), Some("transfer"));
+
+    let spec = &description.global_types[0];
+    assert_eq!(spec.name.as_deref(), Some("spec"));
+    assert_eq!(spec.type_name.as_deref(), Some("RGBContract.AssetSpec"));
+    assert_eq!(spec.max_items, 1);
+
+    assert_eq!(description.owned_types.len(), 1);
+    let owner = &description.owned_types[0];
+    assert_eq!(owner.name.as_deref(), Some("assetOwner"));
+    assert_eq!(owner.kind, "fungible");
+
+    let genesis = &description.genesis;
+    assert_eq!(genesis.globals.len(), 3);
+    assert_eq!(genesis.assignments[0].min, 1);
+    assert_eq!(genesis.assignments[0].max, None);
+
+    let transfer = description
+        .transitions
+        .iter()
+        .find(|op| op.name.as_deref() == Some("transfer"))
+        .expect("transfer transition");
+    assert_eq!(transfer.default_assignment.as_deref(), Some("assetOwner"));
+    assert_eq!(transfer.inputs[0].name.as_deref(), Some("assetOwner"));
+
+    // all the state types are defined
+    for name in ["RGBContract.AssetSpec", "RGBContract.ContractTerms", "RGBContract.Amount"] {
+        assert!(description
+            .types
+            .iter()
+            .any(|ty| ty.name.as_deref() == Some(name)));
+    }
+
+    let text = description.to_string();
+    assert!(text.contains("#2000 spec: RGBContract.AssetSpec"));
+    assert!(text.contains("default assignment: assetOwner"));
+}
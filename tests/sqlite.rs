@@ -0,0 +1,187 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Schema migrations, transactions and read-only access of the SQLite stock
+//! database.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::temp_dir;
+use nonasync::persistence::PersistenceProvider;
+use rgb::containers::{Contract, FileContent, ValidContract};
+use rgb::persistence::{MemStash, Stock};
+use rgb::resolvers::ContractIssueResolver;
+use rgb::{SqliteStore, SqliteStoreError, SQLITE_SCHEMA_VERSION};
+use rusqlite::Connection;
+
+#[derive(Debug)]
+enum TxError {
+    Store(SqliteStoreError),
+    Aborted,
+}
+
+impl From<SqliteStoreError> for TxError {
+    fn from(err: SqliteStoreError) -> Self { TxError::Store(err) }
+}
+
+fn contract() -> ValidContract {
+    Contract::load_file("examples/rgb20-demo.rgb")
+        .unwrap()
+        .validate(&ContractIssueResolver, true)
+        .unwrap()
+}
+
+fn schema_version(path: &Path) -> u32 {
+    Connection::open(path)
+        .unwrap()
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .unwrap()
+}
+
+fn set_schema_version(path: &Path, version: u32) {
+    Connection::open(path)
+        .unwrap()
+        .pragma_update(None, "user_version", version)
+        .unwrap();
+}
+
+fn geneses(store: &SqliteStore) -> usize {
+    let stash: MemStash = store.load().unwrap();
+    stash.debug_geneses().len()
+}
+
+#[test]
+fn sqlite_migration() {
+    let dir = temp_dir("sqlite-migration");
+    let path = dir.join("stock.db");
+    set_schema_version(&path, 0);
+
+    // Database with an outdated schema can't be read before being upgraded
+    let err = SqliteStore::open_read_only(&path).unwrap_err();
+    assert!(matches!(err, SqliteStoreError::OutdatedSchema(0, SQLITE_SCHEMA_VERSION)));
+
+    let store = SqliteStore::open(&path).unwrap();
+    assert_eq!(schema_version(&path), SQLITE_SCHEMA_VERSION);
+    assert!(!store.is_initialized().unwrap());
+    Stock::in_memory()
+        .make_persistent(store.clone(), true)
+        .unwrap();
+    assert!(store.is_initialized().unwrap());
+    drop(store);
+
+    // Opening an up-to-date database keeps its data
+    let store = SqliteStore::open(&path).unwrap();
+    assert_eq!(schema_version(&path), SQLITE_SCHEMA_VERSION);
+    assert!(store.is_initialized().unwrap());
+    drop(store);
+
+    set_schema_version(&path, SQLITE_SCHEMA_VERSION + 1);
+    let err = SqliteStore::open(&path).unwrap_err();
+    assert!(matches!(
+        err,
+        SqliteStoreError::UnsupportedSchema(version, SQLITE_SCHEMA_VERSION)
+            if version == SQLITE_SCHEMA_VERSION + 1
+    ));
+    let err = SqliteStore::open_read_only(&path).unwrap_err();
+    assert!(matches!(err, SqliteStoreError::UnsupportedSchema(..)));
+    assert_eq!(schema_version(&path), SQLITE_SCHEMA_VERSION + 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sqlite_transaction_rollback() {
+    let dir = temp_dir("sqlite-rollback");
+    let store = SqliteStore::open(dir.join("stock.db")).unwrap();
+    let mut stock = Stock::in_memory();
+    stock.make_persistent(store.clone(), true).unwrap();
+
+    let err = store
+        .transaction(|| {
+            stock
+                .import_contract(contract(), &ContractIssueResolver)
+                .unwrap();
+            // The write is visible within the transaction
+            assert_eq!(geneses(&store), 1);
+            Err::<(), _>(TxError::Aborted)
+        })
+        .unwrap_err();
+    assert!(matches!(err, TxError::Aborted));
+    assert_eq!(geneses(&store), 0);
+
+    let mut stock = Stock::in_memory();
+    store
+        .transaction(|| {
+            stock
+                .make_persistent(store.clone(), true)
+                .map_err(|_| TxError::Aborted)?;
+            stock
+                .import_contract(contract(), &ContractIssueResolver)
+                .unwrap();
+            Ok::<_, TxError>(())
+        })
+        .unwrap();
+    assert_eq!(geneses(&store), 1);
+    drop(store);
+
+    let store = SqliteStore::open(dir.join("stock.db")).unwrap();
+    assert_eq!(geneses(&store), 1);
+    drop(store);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sqlite_read_only() {
+    let dir = temp_dir("sqlite-read-only");
+    let path = dir.join("stock.db");
+    assert!(matches!(SqliteStore::open_read_only(&path), Err(SqliteStoreError::Sqlite(_))));
+
+    let writer = SqliteStore::open(&path).unwrap();
+    let mut stock = Stock::in_memory();
+    stock.make_persistent(writer.clone(), true).unwrap();
+
+    let reader = SqliteStore::open_read_only(&path).unwrap();
+    assert!(reader.is_read_only());
+    assert!(!writer.is_read_only());
+    assert_eq!(geneses(&reader), 0);
+
+    // Writer works while the database is opened by the reader, which sees the
+    // committed changes
+    stock
+        .import_contract(contract(), &ContractIssueResolver)
+        .unwrap();
+    assert_eq!(geneses(&reader), 1);
+    let stock: Stock = Stock::load(reader.clone(), false).unwrap();
+    assert_eq!(stock.contracts().unwrap().count(), 1);
+
+    let stash: MemStash = reader.load().unwrap();
+    let err = reader.store(&stash).unwrap_err();
+    assert!(matches!(err.0.downcast_ref(), Some(SqliteStoreError::ReadOnly)));
+    let err = reader.transaction(|| Ok::<_, TxError>(())).unwrap_err();
+    assert!(matches!(err, TxError::Store(SqliteStoreError::ReadOnly)));
+    assert_eq!(geneses(&writer), 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
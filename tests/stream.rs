@@ -0,0 +1,114 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Streaming of consignments one witness bundle at a time.
+
+mod common;
+
+use common::{Party, NETWORK};
+use rgb::containers::{FileContent, LoadError, Transfer};
+use rgb::resolvers::MockChain;
+use rgb::{ContractReader, TransferReader, TransferWriter};
+
+/// Transfer of the contract with two witness bundles in its history.
+fn transfer() -> Transfer {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+
+    let invoice = bob.invoice(contract_id, 100, true);
+    alice.pay(&invoice);
+    chain.mine(1);
+    alice.sync();
+
+    let invoice = bob.invoice(contract_id, 200, true);
+    let (_, transfer) = alice.pay(&invoice);
+    assert_eq!(transfer.bundles.len(), 2);
+    transfer
+}
+
+fn saved(transfer: &Transfer) -> Vec<u8> {
+    let mut data = vec![];
+    transfer.save(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn stream_reader_reads_saved() {
+    let transfer = transfer();
+    let data = saved(&transfer);
+
+    let mut reader = TransferReader::new(data.as_slice()).unwrap();
+    assert_eq!(reader.remaining_bundles(), 2);
+    assert!(reader.header().bundles.is_empty());
+    assert_eq!(reader.header().genesis, transfer.genesis);
+    assert_eq!(reader.header().terminals, transfer.terminals);
+    let bundles = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(bundles, transfer.bundles.iter().cloned().collect::<Vec<_>>());
+    let header = reader.finish().unwrap();
+    assert!(header.bundles.is_empty());
+    assert_eq!(header.schema, transfer.schema);
+    assert_eq!(header.signatures, transfer.signatures);
+
+    let read = TransferReader::new(data.as_slice())
+        .unwrap()
+        .read_all()
+        .unwrap();
+    assert_eq!(read, transfer);
+    assert_eq!(saved(&read), data);
+}
+
+#[test]
+fn stream_writer_loads() {
+    let transfer = transfer();
+    let mut writer =
+        TransferWriter::new(vec![], transfer.clone(), transfer.bundles.len() as u32).unwrap();
+    for bundle in &transfer.bundles {
+        writer.write_bundle(bundle).unwrap();
+    }
+    let data = writer.finish().unwrap();
+
+    assert_eq!(data, saved(&transfer));
+    assert_eq!(Transfer::load(data.as_slice()).unwrap(), transfer);
+}
+
+#[test]
+fn stream_writer_bundle_count() {
+    let transfer = transfer();
+    let mut bundles = transfer.bundles.iter();
+
+    let mut writer = TransferWriter::new(vec![], transfer.clone(), 1).unwrap();
+    writer.write_bundle(bundles.next().unwrap()).unwrap();
+    assert!(writer.write_bundle(bundles.next().unwrap()).is_err());
+
+    let writer = TransferWriter::new(vec![], transfer.clone(), 2).unwrap();
+    assert!(writer.finish().is_err());
+}
+
+#[test]
+fn stream_reader_magic() {
+    let data = saved(&transfer());
+    assert!(matches!(ContractReader::new(data.as_slice()), Err(LoadError::InvalidMagic)));
+}
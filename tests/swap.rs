@@ -0,0 +1,126 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Atomic swaps of assets under two different contracts.
+
+mod common;
+
+use common::{amount, params, Party, NETWORK};
+use psrgbt::PsbtConstructor;
+use rgb::invoice::{InvoiceState, RgbInvoice};
+use rgb::resolvers::MockChain;
+use rgb::{ContractId, Signer, SwapError, SwapProposal};
+
+struct Swap {
+    alice: Party,
+    bob: Party,
+    asset_a: ContractId,
+    asset_b: ContractId,
+    offer: RgbInvoice,
+    request: RgbInvoice,
+}
+
+/// Alice offers 100 of her asset A in exchange for 200 of Bob asset B.
+fn swap() -> Swap {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let asset_a = alice.issue(outpoint, 1000);
+    let outpoint = bob.fund(10_000);
+    let asset_b = bob.issue(outpoint, 1000);
+    alice.fund(10_000);
+    bob.fund(10_000);
+
+    let offer = bob.invoice(asset_a, 100, true);
+    let request = alice.invoice(asset_b, 200, true);
+    Swap {
+        alice,
+        bob,
+        asset_a,
+        asset_b,
+        offer,
+        request,
+    }
+}
+
+#[test]
+fn swap_request_paid() {
+    let Swap {
+        mut alice,
+        mut bob,
+        asset_a,
+        asset_b,
+        offer,
+        request,
+    } = swap();
+
+    let (mut proposal, _) = alice.wallet.propose_swap(offer, request, params()).unwrap();
+    let (_, alice_transfer) = bob.wallet.accept_swap(&mut proposal, params()).unwrap();
+    let bob_transfer = alice.wallet.finalize_swap(&mut proposal).unwrap();
+
+    let mut psbt = proposal.into_psbt();
+    assert!(alice.signer.sign_psbt(&mut psbt).unwrap() > 0);
+    assert!(bob.signer.sign_psbt(&mut psbt).unwrap() > 0);
+    psbt.finalize(alice.wallet.wallet().descriptor());
+    psbt.finalize(bob.wallet.wallet().descriptor());
+    let tx = psbt.extract().expect("finalized transaction");
+    alice.chain.broadcast(&tx).unwrap();
+    alice.chain.mine(1);
+
+    alice.accept(alice_transfer);
+    bob.accept(bob_transfer);
+    alice.sync();
+    bob.sync();
+    assert_eq!(alice.balance(asset_a).confirmed, amount(900));
+    assert_eq!(alice.balance(asset_b).confirmed, amount(200));
+    assert_eq!(bob.balance(asset_a).confirmed, amount(100));
+    assert_eq!(bob.balance(asset_b).confirmed, amount(800));
+}
+
+#[test]
+fn swap_request_underpaid() {
+    let Swap {
+        mut alice,
+        mut bob,
+        offer,
+        request,
+        ..
+    } = swap();
+
+    let (proposal, _) = alice
+        .wallet
+        .propose_swap(offer.clone(), request.clone(), params())
+        .unwrap();
+
+    // Bob replaces the request in the proposal with a smaller amount and pays
+    // it
+    let mut underpaid = request.clone();
+    underpaid.owned_state = InvoiceState::Amount(amount(50));
+    let mut accepted = SwapProposal::new(proposal.into_psbt(), offer.clone(), underpaid);
+    bob.wallet.accept_swap(&mut accepted, params()).unwrap();
+    let accepted = SwapProposal::from_psbt(accepted.into_psbt()).unwrap();
+    assert_eq!(accepted.request().owned_state, InvoiceState::Amount(amount(50)));
+
+    // Alice checks the payment against the invoices she has proposed
+    let mut accepted = SwapProposal::new(accepted.into_psbt(), offer, request);
+    assert!(matches!(alice.wallet.finalize_swap(&mut accepted), Err(SwapError::RequestMismatch)));
+}
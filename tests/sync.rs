@@ -0,0 +1,73 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Progress reporting during the wallet synchronization.
+
+mod common;
+
+use common::{Party, NETWORK};
+use rgb::resolvers::{AnyResolver, MockChain};
+use rgb::{SyncProgress, SyncStage};
+
+#[test]
+fn sync_progress() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let invoice = bob.invoice(contract_id, 400, false);
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    bob.accept(transfer);
+
+    let mut reports = Vec::<SyncProgress>::new();
+    let resolver = AnyResolver::mock(&chain);
+    let res = bob
+        .wallet
+        .sync(&chain, &resolver, 0, |progress| reports.push(*progress))
+        .unwrap();
+    assert!(res.failed.is_empty());
+
+    let first = reports.first().unwrap();
+    assert_eq!(first.stage, SyncStage::Utxos);
+    assert_eq!(first.utxos_found, 0);
+
+    let witnesses = reports
+        .iter()
+        .filter(|progress| progress.stage == SyncStage::Witnesses)
+        .collect::<Vec<_>>();
+    assert_eq!(witnesses[0].witnesses_refreshed, 0);
+    assert!(witnesses[0].witnesses_total > 0);
+    for (no, progress) in witnesses.iter().enumerate() {
+        assert_eq!(progress.witnesses_refreshed, no);
+        assert_eq!(progress.witnesses_remaining(), progress.witnesses_total - no);
+        assert!(progress.utxos_found > 0);
+        assert!(progress.addresses_used > 0);
+    }
+
+    let last = reports.last().unwrap();
+    assert_eq!(last.stage, SyncStage::Done);
+    assert_eq!(last.witnesses_refreshed, witnesses.len() - 1);
+    assert_eq!(last.witnesses_remaining(), 0);
+    assert_eq!(reports.len(), witnesses.len() + 2);
+}
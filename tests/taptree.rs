@@ -0,0 +1,346 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tapret commitments in taproot outputs having script trees.
+
+mod common;
+
+use std::str::FromStr;
+
+use amplify::num::u7;
+use bp::dbc::tapret::{TapretCommitment, TapretNodePartner, TapretPathProof};
+use bpstd::psbt::Output;
+use bpstd::{
+    Derive, DerivedScript, InternalPk, IntoTapHash, LeafInfo, ScriptPubkey, TapBranchHash,
+    TapNodeHash, TapScript, TapTree, Terminal, XpubDerivable,
+};
+use commit_verify::{mpc, CommitVerify};
+use common::{amount, Party, NETWORK};
+use psrgbt::{
+    check_tapret_tree, tap_tree_root, tapret_path_proof, tapret_tree, TapTreeError, TapretOutExt,
+};
+use rgb::resolvers::MockChain;
+use rgb::{DescriptorRgb, RgbDescr, RgbKeychain, TapretKey};
+
+const XPUB: &str = "[643a7adc/86h/1h/0h]tpubDCNiWHaiSkgnQjuhsg9kjwaUzaxQjUcmhagvYzqQ3TYJTgFGJstVaqnu4yhtFktBhCVFmBNLQ5sN53qKzZbMksm3XEyGJsEhQPfVZdWmTE2/<0;1;9;10>/*";
+
+fn leaf(op: u8) -> TapScript { TapScript::from_unsafe(vec![op]) }
+
+fn leaf_hash(script: &TapScript) -> TapNodeHash { script.tap_leaf_hash().into_tap_hash() }
+
+fn branch(a: TapNodeHash, b: TapNodeHash) -> TapNodeHash {
+    TapBranchHash::with_nodes(a, b).into_tap_hash()
+}
+
+fn commitment(byte: u8) -> TapretCommitment {
+    TapretCommitment::with(mpc::Commitment::from([byte; 32]), 0)
+}
+
+fn internal_pk() -> InternalPk {
+    InternalPk::from_str("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+        .unwrap()
+}
+
+fn single_leaf_tree() -> TapTree { TapTree::with_single_leaf(leaf(0x51)) }
+
+/// Tree with the leaf at the depth 1 and two leaves at the depth 2.
+fn multi_leaf_tree() -> TapTree {
+    TapTree::from_leaves([
+        LeafInfo::tap_script(u7::ONE, leaf(0x51)),
+        LeafInfo::tap_script(u7::with(2), leaf(0x52)),
+        LeafInfo::tap_script(u7::with(2), leaf(0x53)),
+    ])
+    .unwrap()
+}
+
+/// Checks that the path proof leads from the commitment leaf to the root of
+/// the tree hosting the commitment, returning the partner node.
+fn verify_path(
+    proof: &TapretPathProof,
+    commitment: &TapretCommitment,
+    root: TapNodeHash,
+) -> TapretNodePartner {
+    let commitment_hash = leaf_hash(&TapScript::commit(commitment));
+    let partner = proof.partner_node().clone().expect("tree with scripts");
+    assert_eq!(proof.nonce(), commitment.nonce);
+    assert!(partner.check_no_commitment());
+    assert!(partner.check_ordering(commitment_hash));
+    assert_eq!(branch(commitment_hash, partner.tap_node_hash()), root);
+    partner
+}
+
+#[test]
+fn taptree_single_leaf() {
+    let tree = single_leaf_tree();
+    assert_eq!(tap_tree_root(&tree).unwrap(), leaf_hash(&leaf(0x51)));
+    check_tapret_tree(&tree).unwrap();
+
+    let commitment = commitment(1);
+    let commitment_hash = leaf_hash(&TapScript::commit(&commitment));
+    let tapret = tapret_tree(None, &commitment).unwrap();
+    assert_eq!(tapret.len(), 1);
+    assert_eq!(tap_tree_root(&tapret).unwrap(), commitment_hash);
+
+    let tapret = tapret_tree(Some(&tree), &commitment).unwrap();
+    assert_eq!(tapret.len(), 2);
+    assert!(tapret.iter().all(|leaf| leaf.depth == u7::ONE));
+    let root = tap_tree_root(&tapret).unwrap();
+    assert_eq!(root, branch(leaf_hash(&leaf(0x51)), commitment_hash));
+
+    let proof = tapret_path_proof(&tree, &commitment).unwrap();
+    verify_path(&proof, &commitment, root);
+}
+
+#[test]
+fn taptree_multi_leaf() {
+    let tree = multi_leaf_tree();
+    let root =
+        branch(leaf_hash(&leaf(0x51)), branch(leaf_hash(&leaf(0x52)), leaf_hash(&leaf(0x53))));
+    assert_eq!(tap_tree_root(&tree).unwrap(), root);
+    check_tapret_tree(&tree).unwrap();
+
+    let commitment = commitment(1);
+    let tapret = tapret_tree(Some(&tree), &commitment).unwrap();
+    assert_eq!(tapret.len(), 4);
+    assert_eq!(
+        tap_tree_root(&tapret).unwrap(),
+        branch(root, leaf_hash(&TapScript::commit(&commitment)))
+    );
+}
+
+#[test]
+fn taptree_path_proof() {
+    let tree = multi_leaf_tree();
+    let (mut left, mut right) = (false, false);
+    for byte in 0..16 {
+        let commitment = commitment(byte);
+        let root = tap_tree_root(&tapret_tree(Some(&tree), &commitment).unwrap()).unwrap();
+        let proof = tapret_path_proof(&tree, &commitment).unwrap();
+        match verify_path(&proof, &commitment, root) {
+            TapretNodePartner::LeftNode(node) => {
+                assert_eq!(node, tap_tree_root(&tree).unwrap());
+                left = true;
+            }
+            TapretNodePartner::RightBranch(branch) => {
+                assert_eq!(branch.node_hash(), tap_tree_root(&tree).unwrap());
+                right = true;
+            }
+            TapretNodePartner::RightLeaf(_) => panic!("multi-leaf tree proven as a leaf"),
+        }
+    }
+    assert!(left && right, "both partner node placements must be covered");
+}
+
+#[test]
+fn taptree_alternative_commitment() {
+    let tree = TapTree::with_single_leaf(TapScript::commit(&commitment(1)));
+    assert_eq!(check_tapret_tree(&tree), Err(TapTreeError::AlternativeCommitment));
+    let xpub = XpubDerivable::from_str(XPUB).unwrap();
+    assert_eq!(
+        TapretKey::from(xpub).with_tap_tree(tree).unwrap_err(),
+        TapTreeError::AlternativeCommitment
+    );
+}
+
+#[test]
+fn taptree_commit_output() {
+    let tree = multi_leaf_tree();
+    let mut output = Output::new(0);
+    output.script = ScriptPubkey::p2tr(internal_pk(), Some(tap_tree_root(&tree).unwrap()));
+    output.tap_internal_key = Some(internal_pk());
+    output.tap_tree = Some(tree.clone());
+    output.set_tapret_host().unwrap();
+
+    let mpc = mpc::Commitment::from([3u8; 32]);
+    let proof = output.tapret_commit_tree(mpc).unwrap();
+    let commitment = TapretCommitment::with(mpc, 0);
+    let tapret = tapret_tree(Some(&tree), &commitment).unwrap();
+    let root = tap_tree_root(&tapret).unwrap();
+    assert_eq!(output.script, ScriptPubkey::p2tr(internal_pk(), Some(root)));
+    assert_eq!(output.tap_tree, Some(tapret));
+    assert_eq!(output.tapret_commitment().unwrap(), commitment);
+    assert_eq!(proof.internal_pk, internal_pk());
+    verify_path(&proof.path_proof, &commitment, root);
+    assert!(output.tapret_commit_tree(mpc).is_err());
+}
+
+#[test]
+fn taptree_commit_output_key_only() {
+    let mut output = Output::new(0);
+    output.script = ScriptPubkey::p2tr_key_only(internal_pk());
+    output.tap_internal_key = Some(internal_pk());
+    output.set_tapret_host().unwrap();
+
+    let mpc = mpc::Commitment::from([3u8; 32]);
+    let proof = output.tapret_commit_tree(mpc).unwrap();
+    let commitment = TapretCommitment::with(mpc, proof.path_proof.nonce());
+    let root = leaf_hash(&TapScript::commit(&commitment));
+    assert_eq!(output.script, ScriptPubkey::p2tr(internal_pk(), Some(root)));
+    assert_eq!(proof.path_proof.partner_node(), &None);
+}
+
+#[test]
+fn taptree_descriptor() {
+    let xpub = XpubDerivable::from_str(XPUB).unwrap();
+    let terminal = Terminal::new(RgbKeychain::Tapret, 0u16.into());
+    let DerivedScript::TaprootKeyOnly(internal_pk) =
+        TapretKey::from(xpub.clone()).derive(terminal.keychain, terminal.index)
+    else {
+        panic!("key-only descriptor derives key-only outputs");
+    };
+
+    let descr = TapretKey::from(xpub.clone())
+        .with_tap_tree(single_leaf_tree())
+        .unwrap();
+    assert_eq!(
+        descr.derive(terminal.keychain, terminal.index),
+        DerivedScript::TaprootScript(internal_pk, single_leaf_tree())
+    );
+    assert_eq!(descr.tap_output(terminal), None);
+
+    let tree = multi_leaf_tree();
+    let mut descr = TapretKey::from(xpub).with_tap_tree(tree.clone()).unwrap();
+    let root = tap_tree_root(&tree).unwrap();
+    assert_eq!(
+        descr
+            .derive(terminal.keychain, terminal.index)
+            .to_script_pubkey(),
+        ScriptPubkey::p2tr(internal_pk, Some(root))
+    );
+    let output = descr.tap_output(terminal).unwrap();
+    assert_eq!(output.internal_pk, internal_pk);
+    assert_eq!(output.merkle_root, root);
+    assert_eq!(output.tap_tree, Some(tree.clone()));
+
+    let commitment = commitment(1);
+    descr
+        .add_tapret_tweak(terminal, commitment.clone())
+        .unwrap();
+    let tweak = &descr.tweaks[&terminal];
+    assert_eq!(
+        &tweak.partner,
+        tapret_path_proof(&tree, &commitment)
+            .unwrap()
+            .partner_node()
+    );
+    let root = tap_tree_root(&tapret_tree(Some(&tree), &commitment).unwrap()).unwrap();
+    assert_eq!(tweak.merkle_root(), root);
+    assert_eq!(
+        descr
+            .derive(terminal.keychain, terminal.index)
+            .to_script_pubkey(),
+        ScriptPubkey::p2tr(internal_pk, Some(root))
+    );
+    let output = descr.tap_output(terminal).unwrap();
+    assert_eq!(output.merkle_root, root);
+    assert_eq!(output.tap_tree, None);
+    assert_eq!(descr.tapret_tweaks().get(&terminal), Some(&commitment));
+
+    // Tweaked outputs keep the tree they were tweaked with
+    let descr = descr.with_tap_tree(single_leaf_tree()).unwrap();
+    assert_eq!(
+        descr
+            .derive(terminal.keychain, terminal.index)
+            .to_script_pubkey(),
+        ScriptPubkey::p2tr(internal_pk, Some(root))
+    );
+}
+
+#[test]
+fn taptree_descriptor_serde() {
+    let xpub = XpubDerivable::from_str(XPUB).unwrap();
+    let terminal = Terminal::new(RgbKeychain::Tapret, 0u16.into());
+
+    let mut descr = TapretKey::from(xpub.clone());
+    descr.add_tapret_tweak(terminal, commitment(1)).unwrap();
+    let yaml = serde_yaml::to_string(&descr).unwrap();
+    assert!(!yaml.contains("tapTree") && !yaml.contains("partner"), "{yaml}");
+    assert_eq!(serde_yaml::from_str::<TapretKey>(&yaml).unwrap(), descr);
+
+    let mut descr = TapretKey::from(xpub)
+        .with_tap_tree(multi_leaf_tree())
+        .unwrap();
+    descr.add_tapret_tweak(terminal, commitment(1)).unwrap();
+    let yaml = serde_yaml::to_string(&descr).unwrap();
+    assert!(yaml.contains("tapTree") && yaml.contains("partner"), "{yaml}");
+    assert_eq!(serde_yaml::from_str::<TapretKey>(&yaml).unwrap(), descr);
+}
+
+#[test]
+fn taptree_transfer() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::with_tap_tree(&chain, 1, multi_leaf_tree());
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+
+    // Spends the output with the script tree and commits into the change
+    let invoice = bob.invoice(contract_id, 100, true);
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    bob.accept(transfer);
+    bob.sync();
+    alice.sync();
+    let tweaks = alice.wallet.tapret_tweaks();
+    assert_eq!(tweaks.len(), 1);
+    assert_eq!(alice.balance(contract_id).confirmed, amount(900));
+    assert_eq!(bob.balance(contract_id).confirmed, amount(100));
+
+    // Spends the tweaked change, which has both the commitment and the scripts
+    let invoice = bob.invoice(contract_id, 200, true);
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    bob.accept(transfer);
+    bob.sync();
+    alice.sync();
+    assert_eq!(alice.balance(contract_id).confirmed, amount(700));
+    assert_eq!(bob.balance(contract_id).confirmed, amount(300));
+
+    // Tweaks lost with the descriptor are recovered with their partner nodes
+    let tweaks = alice.wallet.tapret_tweaks();
+    assert_eq!(tweaks.len(), 2);
+    alice
+        .wallet
+        .wallet_mut()
+        .descriptor_mut(|descr| {
+            descr.with_descriptor_mut(|d| {
+                if let RgbDescr::TapretKey(d, _) = d {
+                    d.tweaks.clear();
+                }
+                Ok::<_, ()>(())
+            })
+        })
+        .unwrap();
+    alice.sync();
+    assert_eq!(alice.balance(contract_id).confirmed, amount(0));
+    // The second witness spends the first tweaked output, so it is seen by the
+    // wallet only once the first tweak is recovered
+    let recovered = alice.wallet.recover_tapret_tweaks().unwrap();
+    assert_eq!(recovered.len(), 1);
+    alice.sync();
+    let recovered = alice.wallet.recover_tapret_tweaks().unwrap();
+    assert_eq!(recovered.len(), 1);
+    assert_eq!(alice.wallet.tapret_tweaks(), tweaks);
+    alice.sync();
+    assert_eq!(alice.balance(contract_id).confirmed, amount(700));
+}
@@ -0,0 +1,74 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Separation of the mined and pending balance, and flagging of allocations
+//! which witness transaction was evicted before the witness update.
+
+mod common;
+
+use common::{amount, Party, NETWORK};
+use rgb::resolvers::{AnyResolver, MockChain};
+use rgb::StaleReason;
+
+#[test]
+fn tentative() {
+    let chain = MockChain::new(NETWORK);
+    let resolver = AnyResolver::mock(&chain);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+
+    let invoice = bob.invoice(contract_id, 400, true);
+    let (txid, consignment) = alice.pay(&invoice);
+    bob.accept(consignment);
+    let balance = bob.balance(contract_id);
+    assert_eq!(balance.mined(), amount(0));
+    assert_eq!(balance.pending(), amount(400));
+    assert!(bob
+        .wallet
+        .check_witnesses(contract_id, &resolver)
+        .unwrap()
+        .is_empty());
+
+    // Witness is evicted from the mempool, while the stock still counts it as
+    // a tentative one
+    assert_eq!(chain.drop_tx(txid), vec![txid]);
+    let stale = bob.wallet.check_witnesses(contract_id, &resolver).unwrap();
+    assert!(stale
+        .iter()
+        .all(|a| a.witness.as_reduced_unsafe() == &txid && a.reason == StaleReason::Evicted));
+    assert!(stale.iter().any(|a| a.amount == Some(amount(400))));
+
+    // Witness update archives the witness, removing the state from the balance
+    bob.sync();
+    let balance = bob.balance(contract_id);
+    assert_eq!(balance.mined(), amount(0));
+    assert_eq!(balance.pending(), amount(0));
+    assert!(bob
+        .wallet
+        .check_witnesses(contract_id, &resolver)
+        .unwrap()
+        .iter()
+        .all(|a| a.reason == StaleReason::Archived));
+}
@@ -0,0 +1,126 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transfers of a fungible asset between two wallets, paying either to a
+//! blinded UTXO of the beneficiary or to an output of the witness transaction.
+
+mod common;
+
+use bpstd::Sats;
+use common::{amount, Party, FEE, NETWORK, SATS};
+use rgb::invoice::Beneficiary;
+use rgb::resolvers::MockChain;
+use rgb::{CompositionError, PayError, TransferParams, WitnessSats, INVOICE_QUERY_SATS};
+
+fn transfer(blinded: bool) {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    if blinded {
+        bob.fund(10_000);
+    }
+
+    let invoice = bob.invoice(contract_id, 400, blinded);
+    let (txid, consignment) = alice.pay(&invoice);
+    assert_eq!(chain.confirmations(txid), Some(0));
+    bob.accept(consignment);
+
+    bob.sync();
+    let balance = bob.balance(contract_id);
+    assert_eq!(balance.unconfirmed_incoming, amount(400));
+    assert_eq!(balance.confirmed, amount(0));
+
+    chain.mine(1);
+    alice.sync();
+    bob.sync();
+    assert_eq!(chain.confirmations(txid), Some(1));
+    assert_eq!(bob.balance(contract_id).confirmed, amount(400));
+    assert_eq!(alice.balance(contract_id).confirmed, amount(600));
+
+    // The received state can be spent further; the payment doesn't use
+    // additional bitcoin inputs, thus a witness output of the beneficiary
+    // would not be covered by the received bitcoins
+    let invoice = alice.invoice(contract_id, 100, true);
+    let (txid, consignment) = bob.pay(&invoice);
+    alice.accept(consignment);
+    chain.mine(1);
+    alice.sync();
+    bob.sync();
+    assert_eq!(chain.confirmations(txid), Some(1));
+    assert_eq!(bob.balance(contract_id).confirmed, amount(300));
+    assert_eq!(alice.balance(contract_id).confirmed, amount(700));
+}
+
+#[test]
+fn transfer_blinded() { transfer(true) }
+
+#[test]
+fn transfer_witness() { transfer(false) }
+
+#[test]
+fn transfer_witness_sats() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+    let params = TransferParams::with(Sats::from_sats(FEE), Sats::from_sats(SATS));
+
+    // Blinded seals can't require sats for the witness output
+    let mut invoice = bob.invoice(contract_id, 400, true);
+    WitnessSats::new(Sats::from_sats(5_000u64)).set_to_invoice(&mut invoice);
+    let err = alice.wallet.pay(&invoice, params.clone()).unwrap_err();
+    assert!(matches!(err, PayError::Composition(CompositionError::WitnessSatsUnsupported)));
+
+    let mut invoice = bob.invoice(contract_id, 400, false);
+    invoice
+        .unknown_query
+        .insert(INVOICE_QUERY_SATS.to_owned(), "lots".to_owned());
+    let err = alice.wallet.pay(&invoice, params.clone()).unwrap_err();
+    assert!(matches!(err, PayError::Composition(CompositionError::InvalidWitnessSats(_))));
+
+    WitnessSats::new(Sats::from_sats(5_000u64)).set_to_invoice(&mut invoice);
+    assert_eq!(
+        WitnessSats::from_invoice(&invoice).unwrap(),
+        Some(WitnessSats::new(Sats::from_sats(5_000u64)))
+    );
+    let plan = alice
+        .wallet
+        .plan_transfer(&invoice, params.clone())
+        .unwrap();
+    assert_eq!(plan.beneficiary_sats, Sats::from_sats(5_000u64));
+
+    let (psbt, _, _) = alice.wallet.pay(&invoice, params).unwrap();
+    let Beneficiary::WitnessVout(pay2vout) = invoice.beneficiary.into_inner() else {
+        unreachable!()
+    };
+    let script = pay2vout.address.script_pubkey();
+    let output = psbt
+        .outputs()
+        .find(|output| output.script == script)
+        .unwrap();
+    assert_eq!(output.amount, Sats::from_sats(5_000u64));
+}
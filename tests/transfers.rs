@@ -0,0 +1,68 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contract history grouped into transfers.
+
+mod common;
+
+use common::{amount, Party, NETWORK};
+use rgb::resolvers::MockChain;
+use rgb::{TransferRole, RGB20_IFACE};
+
+#[test]
+fn grouped_history() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    let invoice = bob.invoice(contract_id, 400, false);
+    let (txid, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    bob.accept(transfer);
+    alice.sync();
+    bob.sync();
+
+    let history = alice
+        .wallet
+        .transfer_history(contract_id, RGB20_IFACE)
+        .unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].role, TransferRole::Issuance);
+    assert!(history[0].witness.is_none());
+    assert_eq!(history[0].received, amount(1_000));
+    let payment = &history[1];
+    assert_eq!(payment.role, TransferRole::Payment);
+    assert_eq!(payment.witness.map(|id| *id.as_reduced_unsafe()), Some(txid));
+    assert!(!payment.counterparty.is_empty());
+    assert!(payment.net() < 0);
+
+    let history = bob
+        .wallet
+        .transfer_history(contract_id, RGB20_IFACE)
+        .unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].role, TransferRole::Receipt);
+    assert_eq!(history[0].received, amount(400));
+    assert_eq!(history[0].sent, amount(0));
+    assert!(history[0].counterparty.is_empty());
+}
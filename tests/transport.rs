@@ -0,0 +1,204 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Delivery of consignments to invoice transports in the order of their
+//! priorities, failing over to the next transport.
+
+mod common;
+
+use std::cell::RefCell;
+use std::str::FromStr;
+use std::time::Duration;
+
+use common::{amount, Party, NETWORK};
+use rgb::containers::{FileContent, Transfer};
+use rgb::invoice::{RgbInvoice, RgbInvoiceBuilder, RgbTransport};
+use rgb::resolvers::MockChain;
+use rgb::{
+    deliver_consignment, delivery_order, DeliveryError, TransportInvoiceBuilder, TransportMeta,
+    TransportPriorities,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+fn rest(host: &str) -> RgbTransport {
+    RgbTransport::RestHttp {
+        tls: true,
+        host: host.to_owned(),
+    }
+}
+
+fn meta(priority: u8, timeout: Option<u32>, failover: bool) -> TransportMeta {
+    TransportMeta {
+        priority,
+        timeout,
+        failover,
+    }
+}
+
+/// Sets transports with their metadata to the invoice.
+fn set_transports(invoice: &mut RgbInvoice, transports: &[(&str, TransportMeta)]) {
+    invoice.transports = transports.iter().map(|(host, _)| rest(host)).collect();
+    TransportPriorities::new(transports.iter().map(|(_, meta)| *meta)).set_to_invoice(invoice);
+}
+
+#[test]
+fn transport_meta_format() {
+    for (s, expected) in [
+        ("1", meta(1, None, true)),
+        ("2:30", meta(2, Some(30), true)),
+        ("3:10:strict", meta(3, Some(10), false)),
+        ("4:strict", meta(4, None, false)),
+    ] {
+        let parsed = TransportMeta::from_str(s).unwrap();
+        assert_eq!(parsed, expected);
+        assert_eq!(parsed.to_string(), s);
+    }
+    for s in ["", "x", "256", "1:x", "1:strict:10", "1:10:20", "1:strict:strict"] {
+        assert!(matches!(TransportMeta::from_str(s), Err(DeliveryError::InvalidMeta(_))), "{s}");
+    }
+}
+
+#[test]
+fn transport_priorities() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let mut bob = Party::new(&chain, 2);
+    let beneficiary = bob.invoice(contract_id, 100, false).beneficiary;
+
+    let invoice = RgbInvoiceBuilder::new(beneficiary)
+        .set_contract(contract_id)
+        .set_interface(rgb::RGB20_IFACE)
+        .set_amount_raw(100_u64)
+        .add_transport_raw(rest("fallback.example.com"))
+        .add_transport_with(rest("slow.example.com"), meta(2, Some(120), true))
+        .add_transport_with(rest("fast.example.com"), meta(1, Some(5), true))
+        .add_transport_with(rest("last.example.com"), meta(2, None, false))
+        .finish();
+    let invoice = RgbInvoice::from_str(&invoice.to_string()).unwrap();
+
+    let order = delivery_order(&invoice).unwrap();
+    assert_eq!(order, vec![
+        (rest("fast.example.com"), meta(1, Some(5), true)),
+        (rest("slow.example.com"), meta(2, Some(120), true)),
+        (rest("last.example.com"), meta(2, None, false)),
+        (rest("fallback.example.com"), meta(u8::MAX, None, true)),
+    ]);
+    assert_eq!(order[0].1.timeout_or(TIMEOUT), Duration::from_secs(5));
+    assert_eq!(order[2].1.timeout_or(TIMEOUT), TIMEOUT);
+
+    // Invoices without metadata are delivered in the order of their transports
+    let mut plain = invoice.clone();
+    plain.unknown_query.clear();
+    let order = delivery_order(&plain).unwrap();
+    assert_eq!(
+        order
+            .into_iter()
+            .map(|(transport, _)| transport)
+            .collect::<Vec<_>>(),
+        invoice.transports
+    );
+
+    let mut mismatch = invoice.clone();
+    mismatch.transports.pop();
+    assert!(matches!(delivery_order(&mismatch), Err(DeliveryError::MetaMismatch(4, 3))));
+}
+
+#[test]
+fn transport_failover() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    let mut invoice = bob.invoice(contract_id, 100, false);
+    set_transports(&mut invoice, &[
+        ("backup.example.com", meta(1, None, true)),
+        ("down.example.com", meta(0, Some(5), true)),
+        ("never.example.com", meta(2, None, true)),
+    ]);
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+
+    let attempts = RefCell::new(vec![]);
+    let delivered = RefCell::new(None);
+    let poster = |transport: &RgbTransport, data: &[u8], timeout: Duration| {
+        attempts.borrow_mut().push((transport.clone(), timeout));
+        if *transport == rest("down.example.com") {
+            return Err("connection refused".to_owned());
+        }
+        *delivered.borrow_mut() = Some(data.to_vec());
+        Ok(())
+    };
+    let transport = deliver_consignment(&invoice, &transfer, &poster, TIMEOUT).unwrap();
+    assert_eq!(transport, rest("backup.example.com"));
+    assert_eq!(attempts.into_inner(), vec![
+        (rest("down.example.com"), Duration::from_secs(5)),
+        (rest("backup.example.com"), TIMEOUT),
+    ]);
+
+    let data = delivered.into_inner().expect("delivered consignment");
+    let transfer = Transfer::load(data.as_slice()).expect("valid consignment");
+    bob.accept(transfer);
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).confirmed, amount(100));
+}
+
+#[test]
+fn transport_strict() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    let mut invoice = bob.invoice(contract_id, 100, false);
+    let (_, transfer) = alice.pay(&invoice);
+    let poster = |_: &RgbTransport, _: &[u8], _: Duration| -> Result<(), String> {
+        Err("connection refused".to_owned())
+    };
+    assert!(matches!(
+        deliver_consignment(&invoice, &transfer, &poster, TIMEOUT),
+        Err(DeliveryError::NoTransports)
+    ));
+
+    set_transports(&mut invoice, &[
+        ("first.example.com", meta(0, None, true)),
+        ("strict.example.com", meta(1, None, false)),
+        ("never.example.com", meta(2, None, true)),
+    ]);
+    let attempts = RefCell::new(0usize);
+    let poster = |_: &RgbTransport, _: &[u8], _: Duration| -> Result<(), String> {
+        *attempts.borrow_mut() += 1;
+        Err("connection refused".to_owned())
+    };
+    let err = deliver_consignment(&invoice, &transfer, &poster, TIMEOUT).unwrap_err();
+    assert_eq!(attempts.into_inner(), 2);
+    let DeliveryError::Failed(details) = err else {
+        panic!("unexpected error {err}");
+    };
+    assert!(details.contains("first.example.com"));
+    assert!(details.contains("strict.example.com"));
+    assert!(!details.contains("never.example.com"));
+}
@@ -0,0 +1,144 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Backup, import and recovery of the tapret tweaks of a key-only wallet.
+
+mod common;
+
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use common::{amount, Party, NETWORK};
+use rgb::resolvers::MockChain;
+use rgb::{ContractId, RgbDescr, TapretTweaks};
+
+/// Pays 300 of 1000 issued by Alice to Bob, leaving Alice with the change
+/// on the tweaked output.
+fn setup(chain: &MockChain) -> (Party, ContractId) {
+    let mut alice = Party::new(chain, 1);
+    let mut bob = Party::new(chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+
+    let invoice = bob.invoice(contract_id, 300, true);
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    bob.sync();
+    bob.accept(transfer);
+    alice.sync();
+    assert_eq!(alice.balance(contract_id).confirmed, amount(700));
+    (alice, contract_id)
+}
+
+/// Deletes all tweaks from the wallet descriptor, making the change lost.
+fn delete_tweaks(party: &mut Party, contract_id: ContractId) {
+    party
+        .wallet
+        .wallet_mut()
+        .descriptor_mut(|descr| {
+            descr.with_descriptor_mut(|d| {
+                if let RgbDescr::TapretKey(d, _) = d {
+                    d.tweaks.clear();
+                }
+                Ok::<_, ()>(())
+            })
+        })
+        .unwrap();
+    assert!(party.wallet.tapret_tweaks().is_empty());
+    party.sync();
+    assert_eq!(party.balance(contract_id).confirmed, amount(0));
+}
+
+#[test]
+fn tweaks_recover() {
+    let chain = MockChain::new(NETWORK);
+    let (mut alice, contract_id) = setup(&chain);
+    let tweaks = alice.wallet.tapret_tweaks();
+    assert_eq!(tweaks.len(), 1);
+
+    delete_tweaks(&mut alice, contract_id);
+    let recovered = alice.wallet.recover_tapret_tweaks().unwrap();
+    assert_eq!(recovered, tweaks);
+    assert_eq!(alice.wallet.tapret_tweaks(), tweaks);
+    alice.sync();
+    assert_eq!(alice.balance(contract_id).confirmed, amount(700));
+
+    // Known tweaks are not recovered twice
+    assert!(alice.wallet.recover_tapret_tweaks().unwrap().is_empty());
+}
+
+#[test]
+fn tweaks_backup_import() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+    let outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(10_000);
+
+    let backup = Arc::new(Mutex::new(String::new()));
+    let hook = backup.clone();
+    alice
+        .wallet
+        .set_tweaks_backup(move |tweaks| hook.lock().unwrap().push_str(&tweaks.to_string()));
+
+    let invoice = bob.invoice(contract_id, 300, true);
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    bob.sync();
+    bob.accept(transfer);
+    alice.sync();
+
+    // Each transfer backs up only the tweaks it has added
+    let tweaks = alice.wallet.tapret_tweaks();
+    assert_eq!(tweaks.len(), 1);
+    let exported = backup.lock().unwrap().clone();
+    assert_eq!(exported, tweaks.to_string());
+    assert_eq!(TapretTweaks::from_str(&exported).unwrap(), tweaks);
+
+    delete_tweaks(&mut alice, contract_id);
+    let added = alice
+        .wallet
+        .import_tapret_tweaks(&TapretTweaks::from_str(&exported).unwrap())
+        .unwrap();
+    assert_eq!(added, tweaks);
+    alice.sync();
+    assert_eq!(alice.balance(contract_id).confirmed, amount(700));
+
+    // Repeated import is a no-op, while a different tweak for the same
+    // terminal is rejected
+    assert!(alice
+        .wallet
+        .import_tapret_tweaks(&tweaks)
+        .unwrap()
+        .is_empty());
+    let conflicting = tweaks
+        .iter()
+        .map(|(terminal, tweak)| {
+            let mut tweak = tweak.clone();
+            tweak.nonce = tweak.nonce.wrapping_add(1);
+            (*terminal, tweak)
+        })
+        .collect::<TapretTweaks>();
+    assert!(alice.wallet.import_tapret_tweaks(&conflicting).is_err());
+    assert_eq!(alice.wallet.tapret_tweaks(), tweaks);
+}
@@ -0,0 +1,126 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Payment embedded into a PSBT v2 constructed by another wallet, which funds
+//! the transaction together with the payer.
+
+mod common;
+
+use bpstd::psbt::PsbtVer;
+use bpstd::{Psbt, Sats, SeqNo, Terminal};
+use common::{amount, Party, NETWORK};
+use psrgbt::{PsbtConstructor, RgbPsbt};
+use rgb::resolvers::MockChain;
+use rgb::{CompositionError, RgbKeychain, Signer, WitnessSats};
+
+#[test]
+fn collaborative_funding() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let rgb_outpoint = alice.fund(10_000);
+    let contract_id = alice.issue(rgb_outpoint, 1_000);
+    let btc_outpoint = bob.fund(100_000);
+    let invoice = bob.invoice(contract_id, 400, false);
+
+    // Bob constructs the transaction spending the RGB output of Alice and
+    // his own coins, which pay the fee
+    let mut psbt = Psbt::create(PsbtVer::V2);
+    let rgb_utxo = alice.wallet.wallet().utxo(rgb_outpoint).unwrap();
+    psbt.construct_input_expect(
+        rgb_utxo.to_prevout(),
+        alice.wallet.wallet().descriptor(),
+        rgb_utxo.terminal,
+        SeqNo::ZERO,
+    );
+    let btc_utxo = bob.wallet.wallet().utxo(btc_outpoint).unwrap();
+    psbt.construct_input_expect(
+        btc_utxo.to_prevout(),
+        bob.wallet.wallet().descriptor(),
+        btc_utxo.terminal,
+        SeqNo::ZERO,
+    );
+    let keychain = RgbKeychain::Tapret;
+    let index = alice
+        .wallet
+        .wallet_mut()
+        .next_derivation_index(keychain, true);
+    let change_vout = psbt
+        .construct_change_expect(
+            alice.wallet.wallet().descriptor(),
+            Terminal::new(keychain, index),
+            Sats::from_sats(10_000u64),
+        )
+        .vout();
+    let address = match invoice.beneficiary.into_inner() {
+        rgb::invoice::Beneficiary::WitnessVout(pay2vout) => pay2vout.address,
+        _ => unreachable!(),
+    };
+    psbt.construct_output_expect(address.script_pubkey(), Sats::from_sats(99_500u64));
+
+    // PSBT v0 can't be updated
+    let mut psbt_v0 = psbt.clone();
+    psbt_v0.version = PsbtVer::V0;
+    let err = alice
+        .wallet
+        .update_psbt(&mut psbt_v0, &invoice, Some(change_vout), None)
+        .unwrap_err();
+    assert!(matches!(err, CompositionError::PsbtVersion));
+
+    // The beneficiary output must pay the amount of sats required by the
+    // invoice
+    let mut required = invoice.clone();
+    WitnessSats::new(Sats::from_sats(50_000u64)).set_to_invoice(&mut required);
+    let err = alice
+        .wallet
+        .update_psbt(&mut psbt.clone(), &required, Some(change_vout), None)
+        .unwrap_err();
+    assert!(matches!(err, CompositionError::WitnessSatsMismatch(required, paid)
+        if required == Sats::from_sats(50_000u64) && paid == Sats::from_sats(99_500u64)));
+
+    let spent = alice
+        .wallet
+        .update_psbt(&mut psbt, &invoice, Some(change_vout), None)
+        .unwrap();
+    assert_eq!(spent.len(), 1);
+    assert!(!psbt.are_inputs_modifiable());
+    assert!(psbt
+        .output(change_vout.to_usize())
+        .unwrap()
+        .is_tapret_host());
+
+    let consignment = alice.wallet.transfer(&invoice, &mut psbt).unwrap();
+    assert!(psbt.rgb_extract().is_ok());
+    assert_eq!(alice.signer.sign_psbt(&mut psbt).unwrap(), 1);
+    assert_eq!(bob.signer.sign_psbt(&mut psbt).unwrap(), 1);
+    psbt.finalize(alice.wallet.wallet().descriptor());
+    psbt.finalize(bob.wallet.wallet().descriptor());
+    let tx = psbt.extract().unwrap();
+    chain.broadcast(&tx).unwrap();
+
+    bob.accept(consignment);
+    chain.mine(1);
+    alice.sync();
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).confirmed, amount(400));
+    assert_eq!(alice.balance(contract_id).confirmed, amount(600));
+}
@@ -0,0 +1,264 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Velocity classes requested by invoices for the received state and kept by
+//! the wallet for the change of that state.
+
+mod common;
+
+use std::collections::BTreeSet;
+
+use common::{amount, params, Party, NETWORK};
+use psrgbt::RgbOutExt;
+use rgb::containers::VelocityHint;
+use rgb::invoice::Beneficiary;
+use rgb::persistence::ContractStateRead;
+use rgb::resolvers::MockChain;
+use rgb::{
+    CompositionError, ContractId, PayError, Precision, RequestedVelocity, VelocityPrefs, XChain,
+    INVOICE_QUERY_VELOCITY,
+};
+
+/// Keeps velocity class for the change of the contract state.
+fn prefer_velocity(party: &mut Party, contract_id: ContractId, hint: VelocityHint) {
+    let mut invoice = party.invoice(contract_id, 1, true);
+    RequestedVelocity::new(hint).set_to_invoice(&mut invoice);
+    party.wallet.keep_requested_velocity(&invoice).unwrap();
+}
+
+#[test]
+fn velocity_prefs_file() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    let mut invoice = alice.invoice(contract_id, 100, true);
+    RequestedVelocity::new(VelocityHint::Seldom).set_to_invoice(&mut invoice);
+    assert_eq!(alice.wallet.keep_requested_velocity(&invoice).unwrap(), Some(VelocityHint::Seldom));
+    let prefs = alice.wallet.velocity_prefs().clone();
+    assert_eq!(prefs.len(), 1);
+    let (id, ty, hint) = prefs.iter().next().unwrap();
+    assert_eq!((id, hint), (contract_id, VelocityHint::Seldom));
+
+    let path = std::env::temp_dir().join(format!("rgb-velocity-{}.yaml", std::process::id()));
+    prefs.save_file(&path).unwrap();
+    let mut loaded = VelocityPrefs::load_file(&path).unwrap();
+    assert_eq!(loaded, prefs);
+    std::fs::remove_file(&path).unwrap();
+    assert!(VelocityPrefs::load_file(&path).unwrap().is_empty());
+
+    assert_eq!(loaded.remove(contract_id, ty), Some(VelocityHint::Seldom));
+    assert_eq!(loaded.get(contract_id, ty), None);
+}
+
+#[test]
+fn velocity_beneficiary_output() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+
+    let mut invoice = bob.invoice(contract_id, 400, false);
+    invoice
+        .unknown_query
+        .insert(INVOICE_QUERY_VELOCITY.to_owned(), "sometimes".to_owned());
+    let err = alice.wallet.pay(&invoice, params()).unwrap_err();
+    assert!(matches!(err, PayError::Composition(CompositionError::InvalidVelocity(_))));
+
+    RequestedVelocity::new(VelocityHint::Frequent).set_to_invoice(&mut invoice);
+    assert_eq!(
+        RequestedVelocity::from_invoice(&invoice).unwrap(),
+        Some(RequestedVelocity::new(VelocityHint::Frequent))
+    );
+    let (psbt, meta, _) = alice.wallet.pay(&invoice, params()).unwrap();
+    let Beneficiary::WitnessVout(pay2vout) = invoice.beneficiary.into_inner() else {
+        unreachable!()
+    };
+    let script = pay2vout.address.script_pubkey();
+    let output = psbt
+        .outputs()
+        .find(|output| output.script == script)
+        .unwrap();
+    assert_eq!(output.rgb_velocity_hint(), Some(VelocityHint::Frequent));
+    // The change of the payer doesn't take the class requested by the payee
+    let change = psbt.output(meta.change_vout.unwrap().to_usize()).unwrap();
+    assert_ne!(change.rgb_velocity_hint(), Some(VelocityHint::Frequent));
+}
+
+#[test]
+fn velocity_honored_by_receiver() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    bob.fund(100_000);
+
+    let mut invoice = bob.invoice(contract_id, 400, true);
+    RequestedVelocity::new(VelocityHint::HighFrequency).set_to_invoice(&mut invoice);
+    bob.wallet.keep_requested_velocity(&invoice).unwrap();
+    // Bob doesn't know the contract yet, so the class is kept for the name of
+    // the assignment until the contract is accepted
+    assert_eq!(bob.wallet.velocity_prefs().iter().count(), 0);
+    assert_eq!(bob.wallet.velocity_prefs().iter_named().count(), 1);
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    alice.sync();
+    bob.accept(transfer);
+    bob.sync();
+    assert_eq!(bob.wallet.velocity_prefs().iter_named().count(), 0);
+
+    // The change of the received state goes to the output of the requested
+    // class, unless the transfer parameters say otherwise
+    let invoice = alice.invoice(contract_id, 100, true);
+    let (psbt, meta) = bob.wallet.construct_psbt(&invoice, params()).unwrap();
+    let change = psbt.output(meta.change_vout.unwrap().to_usize()).unwrap();
+    assert_eq!(change.rgb_velocity_hint(), Some(VelocityHint::HighFrequency));
+
+    let (_, ty, _) = bob.wallet.velocity_prefs().iter().next().unwrap();
+    let mut params = params();
+    params.set_velocity_hint(contract_id, ty, VelocityHint::Seldom);
+    let (psbt, meta) = bob.wallet.construct_psbt(&invoice, params).unwrap();
+    let change = psbt.output(meta.change_vout.unwrap().to_usize()).unwrap();
+    assert_eq!(change.rgb_velocity_hint(), Some(VelocityHint::Seldom));
+}
+
+#[test]
+fn velocity_change_mixed() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    // Both contracts share the output, so paying one of them moves the other
+    let outpoint = alice.fund(100_000);
+    let frequent_id = alice.issue(outpoint, 1_000);
+    let seldom_id = alice.issue_with_precision(outpoint, 500, Precision::CentiMicro);
+    prefer_velocity(&mut alice, frequent_id, VelocityHint::Frequent);
+    prefer_velocity(&mut alice, seldom_id, VelocityHint::Seldom);
+    bob.fund(100_000);
+
+    let invoice = bob.invoice(frequent_id, 400, true);
+    let (psbt, meta) = alice.wallet.construct_psbt(&invoice, params()).unwrap();
+    // The main change output keeps the sats and the most frequently spent
+    // state, while the other class gets a dust output of its own
+    let main = psbt.output(meta.change_vout.unwrap().to_usize()).unwrap();
+    assert_eq!(main.rgb_velocity_hint(), Some(VelocityHint::Frequent));
+    let seldom = psbt
+        .outputs()
+        .find(|output| output.rgb_velocity_hint() == Some(VelocityHint::Seldom))
+        .unwrap();
+    assert_ne!(seldom.vout(), main.vout());
+    assert!(seldom.amount < main.amount);
+    assert_eq!(
+        psbt.outputs()
+            .filter(|output| output.rgb_velocity_hint().is_some())
+            .count(),
+        2
+    );
+
+    // Without the change of the paid contract its class doesn't get an output
+    let full = bob.invoice(frequent_id, 1_000, true);
+    let (psbt, meta) = alice.wallet.construct_psbt(&full, params()).unwrap();
+    let hinted = psbt
+        .outputs()
+        .filter_map(|output| output.rgb_velocity_hint().map(|hint| (output.vout(), hint)))
+        .collect::<Vec<_>>();
+    assert_eq!(hinted, vec![(meta.change_vout.unwrap(), VelocityHint::Seldom)]);
+
+    let (txid, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    alice.sync();
+    bob.accept(transfer);
+    bob.sync();
+    assert_eq!(bob.balance(frequent_id).confirmed, amount(400));
+    assert_eq!(alice.balance(frequent_id).confirmed, amount(600));
+    assert_eq!(alice.balance(seldom_id).confirmed, amount(500));
+
+    let vouts = |contract_id| {
+        let state = alice.wallet.stock().contract_state(contract_id).unwrap();
+        state
+            .fungible_all()
+            .filter_map(|allocation| match allocation.seal {
+                XChain::Bitcoin(seal) if seal.txid == txid => Some(seal.vout),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>()
+    };
+    let frequent = vouts(frequent_id);
+    let seldom = vouts(seldom_id);
+    assert_eq!(seldom.len(), 1);
+    assert!(frequent.is_disjoint(&seldom));
+}
+
+#[test]
+fn velocity_change_single() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    prefer_velocity(&mut alice, contract_id, VelocityHint::Seldom);
+    bob.fund(100_000);
+
+    // A single velocity class doesn't need any extra outputs
+    let invoice = bob.invoice(contract_id, 400, true);
+    let (psbt, meta) = alice.wallet.construct_psbt(&invoice, params()).unwrap();
+    let change_vout = meta.change_vout.unwrap();
+    let hinted = psbt
+        .outputs()
+        .filter_map(|output| output.rgb_velocity_hint().map(|hint| (output.vout(), hint)))
+        .collect::<Vec<_>>();
+    assert_eq!(hinted, vec![(change_vout, VelocityHint::Seldom)]);
+    assert_eq!(psbt.outputs().count(), 1);
+}
+
+#[test]
+fn velocity_change_zero() {
+    let chain = MockChain::new(NETWORK);
+    let mut alice = Party::new(&chain, 1);
+    let mut bob = Party::new(&chain, 2);
+
+    let outpoint = alice.fund(100_000);
+    let contract_id = alice.issue(outpoint, 1_000);
+    prefer_velocity(&mut alice, contract_id, VelocityHint::Seldom);
+    bob.fund(100_000);
+
+    // Paying the whole state leaves no change to allocate, so no output gets
+    // the velocity class
+    let invoice = bob.invoice(contract_id, 1_000, true);
+    let (psbt, meta) = alice.wallet.construct_psbt(&invoice, params()).unwrap();
+    assert!(meta.change_vout.is_some());
+    assert!(psbt
+        .outputs()
+        .all(|output| output.rgb_velocity_hint().is_none()));
+
+    let (_, transfer) = alice.pay(&invoice);
+    chain.mine(1);
+    alice.sync();
+    bob.accept(transfer);
+    bob.sync();
+    assert_eq!(bob.balance(contract_id).confirmed, amount(1_000));
+    assert_eq!(alice.balance(contract_id).total(), amount(0));
+}
@@ -0,0 +1,156 @@
+// RGB wallet library for smart contracts on Bitcoin & Lightning network
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Management of the wallets kept in the data directory.
+
+mod common;
+
+use std::str::FromStr;
+
+use bpstd::{Network, Sats, XpubDerivable};
+use bpwallet::fs::FsTextStore;
+use bpwallet::Wallet;
+use common::{temp_dir, NETWORK};
+use rgb::{
+    has_flat_layout, migrate_flat_layout, KeychainLayout, RgbDescr, TapretKey, WalletDir,
+    WalletDirError, WALLET_DESCRIPTOR_FILE, WALLET_TWEAKS_FILE,
+};
+
+fn create_wallet(dir: &WalletDir, name: &str) {
+    let xpub = XpubDerivable::from_str(
+        "[643a7adc/86h/1h/0h]tpubDCNiWHaiSkgnQjuhsg9kjwaUzaxQjUcmhagvYzqQ3TYJTgFGJstVaqnu4yhtFktBhCVFmBNLQ5sN53qKzZbMksm3XEyGJsEhQPfVZdWmTE2/<0;1;9;10>/*",
+    )
+    .unwrap();
+    let descr = RgbDescr::from(TapretKey::from(xpub));
+    let mut wallet = Wallet::<XpubDerivable, RgbDescr>::new_layer1(descr, NETWORK);
+    wallet
+        .make_persistent(FsTextStore::new(dir.path(name)).unwrap(), true)
+        .unwrap();
+    wallet.store().unwrap();
+}
+
+#[test]
+fn list_and_info() {
+    let base = temp_dir("info");
+    let wallets = WalletDir::new(&base);
+    create_wallet(&wallets, "bob");
+    create_wallet(&wallets, "alice");
+    // Directories without wallet descriptor are not wallets
+    std::fs::create_dir_all(base.join("stock")).unwrap();
+
+    assert_eq!(wallets.list().unwrap(), vec!["alice".to_owned(), "bob".to_owned()]);
+
+    let info = wallets.info("alice").unwrap();
+    assert_eq!(info.name, "alice");
+    assert_eq!(info.path, base.join("alice"));
+    assert_eq!(info.network, NETWORK);
+    assert_eq!(info.keychain_layout, KeychainLayout::STANDARD);
+    assert_eq!(info.balance, Sats::ZERO);
+    assert_eq!(info.utxos, 0);
+    assert_eq!(info.transactions, 0);
+    assert_eq!(info.tapret_tweaks, 0);
+    assert!(info.descriptor.contains("643a7adc"));
+
+    assert!(matches!(wallets.info("carol"), Err(WalletDirError::NotFound(_))));
+    assert!(matches!(wallets.info("../alice"), Err(WalletDirError::InvalidName(_))));
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[test]
+fn rename() {
+    let base = temp_dir("rename");
+    let wallets = WalletDir::new(&base);
+    create_wallet(&wallets, "alice");
+    create_wallet(&wallets, "bob");
+
+    assert!(matches!(wallets.rename("alice", "bob"), Err(WalletDirError::AlreadyExists(_))));
+    assert!(matches!(wallets.rename("alice", ".hidden"), Err(WalletDirError::InvalidName(_))));
+    assert!(matches!(wallets.rename("carol", "dave"), Err(WalletDirError::NotFound(_))));
+
+    wallets.rename("alice", "carol").unwrap();
+    assert!(!wallets.exists("alice"));
+    assert_eq!(wallets.info("carol").unwrap().name, "carol");
+    assert_eq!(wallets.list().unwrap(), vec!["bob".to_owned(), "carol".to_owned()]);
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[test]
+fn export_and_delete() {
+    let base = temp_dir("delete");
+    let wallets = WalletDir::new(&base);
+    create_wallet(&wallets, "alice");
+
+    let backup = temp_dir("backup");
+    let files = wallets.export("alice", &backup).unwrap();
+    // Wallet without tapret commitments has no tweaks to export
+    assert_eq!(files, vec![backup.join(WALLET_DESCRIPTOR_FILE)]);
+    assert!(!backup.join(WALLET_TWEAKS_FILE).exists());
+    assert_eq!(
+        std::fs::read_to_string(&files[0]).unwrap(),
+        std::fs::read_to_string(base.join("alice").join(WALLET_DESCRIPTOR_FILE)).unwrap()
+    );
+
+    wallets.delete("alice").unwrap();
+    assert!(!base.join("alice").exists());
+    assert!(wallets.list().unwrap().is_empty());
+    assert!(matches!(wallets.delete("alice"), Err(WalletDirError::NotFound(_))));
+    std::fs::remove_dir_all(&base).ok();
+    std::fs::remove_dir_all(&backup).ok();
+}
+
+#[test]
+fn flat_layout_migration() {
+    let base = temp_dir("migrate");
+    let wallets = WalletDir::new(&base);
+    assert!(!has_flat_layout(&base));
+    create_wallet(&wallets, "alice");
+    std::fs::write(base.join("stash.dat"), b"stash").unwrap();
+    std::fs::write(base.join("rgb.toml"), "stockBackups = 2\n").unwrap();
+    // Data of other networks are left intact
+    std::fs::create_dir_all(base.join("signet")).unwrap();
+    assert!(has_flat_layout(&base));
+
+    assert!(matches!(
+        migrate_flat_layout(&base, Network::Signet, &["rgb.toml"]),
+        Err(WalletDirError::WrongNetwork { name, expected: Network::Signet, found: NETWORK })
+            if name == "alice"
+    ));
+
+    let network_dir = base.join(NETWORK.to_string());
+    let migrated = migrate_flat_layout(&base, NETWORK, &["rgb.toml"]).unwrap();
+    assert_eq!(migrated, vec![
+        network_dir.join("alice"),
+        network_dir.join("rgb.toml"),
+        network_dir.join("stash.dat")
+    ]);
+    assert!(!has_flat_layout(&base));
+    assert!(base.join("rgb.toml").is_file());
+    assert!(base.join("signet").is_dir());
+    assert_eq!(WalletDir::new(&network_dir).list().unwrap(), vec!["alice".to_owned()]);
+
+    // Entries existing in the network directory are never overwritten
+    std::fs::write(base.join("stash.dat"), b"stash").unwrap();
+    assert!(matches!(
+        migrate_flat_layout(&base, NETWORK, &["rgb.toml"]),
+        Err(WalletDirError::Collision(_))
+    ));
+    assert!(base.join("stash.dat").is_file());
+}